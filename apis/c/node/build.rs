@@ -0,0 +1,20 @@
+use std::path::Path;
+
+fn main() {
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR not set");
+    let out_dir = std::env::var("OUT_DIR").expect("OUT_DIR not set");
+    let config = cbindgen::Config::from_file(Path::new(&crate_dir).join("cbindgen.toml"))
+        .expect("failed to read cbindgen.toml");
+    // The build must not modify the source tree, so the checked-in header is
+    // updated separately through the `update_header` example.
+    cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("failed to generate node_api.h")
+        .write_to_file(Path::new(&out_dir).join("node_api.h"));
+
+    // only regenerate the header when the API changes
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+}
@@ -0,0 +1,15 @@
+//! Copies the `node_api.h` header that is generated on build into the crate
+//! directory, where it is used by the C and C++ examples and the CMake
+//! package.
+//!
+//! Run through `cargo run -p dora-node-api-c --example update_header` after
+//! changing the API.
+
+use std::path::Path;
+
+fn main() -> std::io::Result<()> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("node_api.h");
+    std::fs::write(&path, dora_node_api_c::HEADER_NODE_API)?;
+    println!("updated `{}`", path.display());
+    Ok(())
+}
@@ -0,0 +1,385 @@
+// C++17 wrapper around the dora node C API (`node_api.h`).
+//
+// Provides RAII types for nodes, events, and output samples, zero-copy access
+// to input data through `dora::span`, and exception-based error handling. For
+// code that is compiled without exceptions, use the `try_*` functions, which
+// return a `dora::Status` instead.
+//
+// ```c++
+// #include <dora/node.hpp>
+//
+// int main()
+// {
+//     auto node = dora::Node::from_env();
+//     while (auto event = node.next())
+//     {
+//         if (event->type() == dora::EventType::Input)
+//         {
+//             auto data = event->data();
+//             node.send_output("echo", data);
+//         }
+//     }
+// }
+// ```
+
+#pragma once
+
+#include <chrono>
+#include <cstddef>
+#include <cstdint>
+#include <optional>
+#include <stdexcept>
+#include <string>
+#include <string_view>
+#include <type_traits>
+#include <utility>
+
+#if __cplusplus >= 202002L && __has_include(<span>)
+#include <span>
+#endif
+
+extern "C"
+{
+#include "node_api.h"
+}
+
+namespace dora
+{
+
+#if defined(__cpp_lib_span)
+
+template <typename T>
+using span = std::span<T>;
+
+#else
+
+/// Minimal replacement for `std::span` on C++17 compilers.
+template <typename T>
+class span
+{
+public:
+    using element_type = T;
+    using value_type = std::remove_cv_t<T>;
+    using size_type = std::size_t;
+    using pointer = T *;
+    using iterator = T *;
+
+    constexpr span() noexcept = default;
+    constexpr span(T *data, size_type size) noexcept : data_(data), size_(size) {}
+
+    template <typename Container,
+              typename = std::enable_if_t<std::is_convertible_v<
+                  decltype(std::declval<Container &>().data()), T *>>>
+    constexpr span(Container &container) noexcept
+        : data_(container.data()), size_(container.size()) {}
+
+    template <typename U, typename = std::enable_if_t<std::is_convertible_v<U *, T *>>>
+    constexpr span(const span<U> &other) noexcept : data_(other.data()), size_(other.size()) {}
+
+    constexpr pointer data() const noexcept { return data_; }
+    constexpr size_type size() const noexcept { return size_; }
+    constexpr size_type size_bytes() const noexcept { return size_ * sizeof(T); }
+    constexpr bool empty() const noexcept { return size_ == 0; }
+    constexpr T &operator[](size_type index) const noexcept { return data_[index]; }
+    constexpr iterator begin() const noexcept { return data_; }
+    constexpr iterator end() const noexcept { return data_ + size_; }
+
+private:
+    T *data_ = nullptr;
+    size_type size_ = 0;
+};
+
+#endif
+
+enum class Status : int
+{
+    Ok = DoraStatus_Ok,
+    Error = DoraStatus_Error,
+    InvalidArgument = DoraStatus_InvalidArgument,
+    UnsupportedDataType = DoraStatus_UnsupportedDataType,
+};
+
+enum class EventType
+{
+    Stop = DoraEventType_Stop,
+    Input = DoraEventType_Input,
+    InputClosed = DoraEventType_InputClosed,
+    Error = DoraEventType_Error,
+    Unknown = DoraEventType_Unknown,
+};
+
+/// Returns the message of the last error that occurred on the current thread.
+inline std::string last_error()
+{
+    char *ptr = nullptr;
+    size_t len = 0;
+    dora_last_error(&ptr, &len);
+    return std::string(ptr == nullptr ? "" : ptr, len);
+}
+
+/// Exception thrown by the wrapper when a dora API call fails.
+class Error : public std::runtime_error
+{
+public:
+    Error(Status status, const std::string &message)
+        : std::runtime_error(message), status_(status) {}
+
+    Status status() const noexcept { return status_; }
+
+private:
+    Status status_;
+};
+
+namespace detail
+{
+    inline void check(DoraStatus status, const char *context)
+    {
+        if (status != DoraStatus_Ok)
+        {
+            throw Error(static_cast<Status>(status), std::string(context) + ": " + last_error());
+        }
+    }
+} // namespace detail
+
+/// An event received by a node.
+///
+/// All views returned by an event point directly into the event's memory, so
+/// they must not be used after the event is destroyed.
+class Event
+{
+public:
+    explicit Event(void *raw) noexcept : raw_(raw) {}
+    ~Event()
+    {
+        if (raw_ != nullptr)
+        {
+            free_dora_event(raw_);
+        }
+    }
+
+    Event(const Event &) = delete;
+    Event &operator=(const Event &) = delete;
+    Event(Event &&other) noexcept : raw_(std::exchange(other.raw_, nullptr)) {}
+    Event &operator=(Event &&other) noexcept
+    {
+        std::swap(raw_, other.raw_);
+        return *this;
+    }
+
+    EventType type() const noexcept
+    {
+        return static_cast<EventType>(read_dora_event_type(raw_));
+    }
+
+    /// The ID of an input or input closed event, empty otherwise.
+    std::string_view id() const noexcept
+    {
+        char *ptr = nullptr;
+        size_t len = 0;
+        read_dora_input_id(raw_, &ptr, &len);
+        return std::string_view(ptr, len);
+    }
+
+    /// The raw bytes of the input data, without copying.
+    ///
+    /// Throws if the input data is not a byte array or an array of primitive values.
+    span<const uint8_t> data() const
+    {
+        span<const uint8_t> bytes;
+        detail::check(try_data(bytes), "failed to read input data");
+        return bytes;
+    }
+
+    /// The input data as an array of `T` values, without copying.
+    ///
+    /// The caller is responsible for choosing a `T` that matches the input's data type.
+    template <typename T>
+    span<const T> data_as() const
+    {
+        static_assert(std::is_trivially_copyable_v<T>, "data_as requires a trivially copyable type");
+        auto bytes = data();
+        if (bytes.size() % sizeof(T) != 0)
+        {
+            throw Error(Status::InvalidArgument, "input data size is not a multiple of the requested element size");
+        }
+        return span<const T>(reinterpret_cast<const T *>(bytes.data()), bytes.size() / sizeof(T));
+    }
+
+    DoraStatus try_data(span<const uint8_t> &out) const noexcept
+    {
+        char *ptr = nullptr;
+        size_t len = 0;
+        auto status = read_dora_input_data(raw_, &ptr, &len);
+        out = span<const uint8_t>(reinterpret_cast<const uint8_t *>(ptr), len);
+        return status;
+    }
+
+    /// The timestamp of an input event, `0` otherwise.
+    uint64_t timestamp() const noexcept
+    {
+        return read_dora_input_timestamp(raw_);
+    }
+
+    /// The message of an error event, empty otherwise.
+    std::string_view error() const noexcept
+    {
+        char *ptr = nullptr;
+        size_t len = 0;
+        read_dora_event_error(raw_, &ptr, &len);
+        return std::string_view(ptr, len);
+    }
+
+private:
+    void *raw_;
+};
+
+/// An output buffer that can be filled in place and sent without copying.
+class OutputSample
+{
+public:
+    explicit OutputSample(void *raw) noexcept : raw_(raw) {}
+    ~OutputSample()
+    {
+        if (raw_ != nullptr)
+        {
+            free_dora_output_sample(raw_);
+        }
+    }
+
+    OutputSample(const OutputSample &) = delete;
+    OutputSample &operator=(const OutputSample &) = delete;
+    OutputSample(OutputSample &&other) noexcept : raw_(std::exchange(other.raw_, nullptr)) {}
+    OutputSample &operator=(OutputSample &&other) noexcept
+    {
+        std::swap(raw_, other.raw_);
+        return *this;
+    }
+
+    span<uint8_t> data() noexcept
+    {
+        char *ptr = nullptr;
+        size_t len = 0;
+        dora_output_sample_data(raw_, &ptr, &len);
+        return span<uint8_t>(reinterpret_cast<uint8_t *>(ptr), len);
+    }
+
+    /// Gives up ownership of the underlying C sample.
+    void *release() noexcept { return std::exchange(raw_, nullptr); }
+
+private:
+    void *raw_;
+};
+
+/// A dora node. Events received from a node must not outlive it.
+class Node
+{
+public:
+    /// Initializes the node from the environment variables set by dora.
+    static Node from_env()
+    {
+        return Node(init_dora_context_from_env());
+    }
+
+    /// Initializes the node with the given ID.
+    ///
+    /// When the node was not started by dora, it connects to the local daemon as
+    /// a dynamic node.
+    static Node with_id(const std::string &node_id)
+    {
+        return Node(dora_init_node(node_id.c_str()));
+    }
+
+    ~Node()
+    {
+        if (context_ != nullptr)
+        {
+            free_dora_context(context_);
+        }
+    }
+
+    Node(const Node &) = delete;
+    Node &operator=(const Node &) = delete;
+    Node(Node &&other) noexcept : context_(std::exchange(other.context_, nullptr)) {}
+    Node &operator=(Node &&other) noexcept
+    {
+        std::swap(context_, other.context_);
+        return *this;
+    }
+
+    /// Waits for the next event. Returns `std::nullopt` when the event stream is closed.
+    std::optional<Event> next()
+    {
+        return wrap_event(dora_next_event(context_));
+    }
+
+    /// Waits for the next event for at most the given duration.
+    ///
+    /// An event of type `EventType::Error` is returned when the timeout expires.
+    std::optional<Event> next(std::chrono::milliseconds timeout)
+    {
+        return wrap_event(dora_next_event_timeout(context_, static_cast<uint64_t>(timeout.count())));
+    }
+
+    /// Sends the given bytes as output. The output ID must be declared in the dataflow.
+    void send_output(std::string_view id, span<const uint8_t> data)
+    {
+        detail::check(try_send_output(id, data), "failed to send output");
+    }
+
+    DoraStatus try_send_output(std::string_view id, span<const uint8_t> data) noexcept
+    {
+        return static_cast<DoraStatus>(dora_send_output(
+            context_,
+            id.data(),
+            id.size(),
+            reinterpret_cast<const char *>(data.data()),
+            data.size()));
+    }
+
+    /// Allocates an output sample of `len` bytes, see `send_output(id, OutputSample)`.
+    OutputSample allocate_output(size_t len)
+    {
+        void *sample = dora_allocate_output(context_, len);
+        if (sample == nullptr)
+        {
+            throw Error(Status::Error, "failed to allocate output sample: " + last_error());
+        }
+        return OutputSample(sample);
+    }
+
+    /// Sends a sample allocated through `allocate_output`, without copying it.
+    void send_output(std::string_view id, OutputSample sample)
+    {
+        detail::check(try_send_output(id, std::move(sample)), "failed to send output sample");
+    }
+
+    DoraStatus try_send_output(std::string_view id, OutputSample sample) noexcept
+    {
+        return dora_send_output_sample(context_, id.data(), id.size(), sample.release());
+    }
+
+    /// Gives access to the underlying C context, e.g. for calling C API functions directly.
+    void *raw() const noexcept { return context_; }
+
+private:
+    explicit Node(void *context) : context_(context)
+    {
+        if (context_ == nullptr)
+        {
+            throw Error(Status::Error, "failed to init dora node: " + last_error());
+        }
+    }
+
+    static std::optional<Event> wrap_event(void *event)
+    {
+        if (event == nullptr)
+        {
+            return std::nullopt;
+        }
+        return Event(event);
+    }
+
+    void *context_;
+};
+
+} // namespace dora
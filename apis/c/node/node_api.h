@@ -1,22 +1,65 @@
+#ifndef DORA_NODE_API_H
+#define DORA_NODE_API_H
+
+/* Generated by cbindgen from `src/lib.rs`, do not edit by hand. */
+
 #include <stddef.h>
+#include <stdint.h>
+
+typedef enum DoraEventType {
+  DoraEventType_Stop,
+  DoraEventType_Input,
+  DoraEventType_InputClosed,
+  DoraEventType_Error,
+  DoraEventType_Unknown,
+} DoraEventType;
+
+typedef enum DoraStatus {
+  DoraStatus_Ok = 0,
+  DoraStatus_Error = -1,
+  DoraStatus_InvalidArgument = -2,
+  DoraStatus_UnsupportedDataType = -3,
+} DoraStatus;
+
+void dora_last_error(char **out_ptr, size_t *out_len);
+
+void *init_dora_context_from_env(void);
+
+void *dora_init_node(const char *node_id);
+
+void free_dora_context(void *context);
+
+void *dora_next_event(void *context);
+
+void *dora_next_event_timeout(void *context, uint64_t timeout_ms);
+
+enum DoraEventType read_dora_event_type(const void *event);
+
+void read_dora_input_id(const void *event, char **out_ptr, size_t *out_len);
+
+enum DoraStatus read_dora_input_data(const void *event, char **out_ptr, size_t *out_len);
+
+unsigned long long read_dora_input_timestamp(const void *event);
+
+void read_dora_event_error(const void *event, char **out_ptr, size_t *out_len);
+
+void free_dora_event(void *event);
+
+ptrdiff_t dora_send_output(void *context,
+                           const char *id_ptr,
+                           size_t id_len,
+                           const char *data_ptr,
+                           size_t data_len);
+
+void *dora_allocate_output(void *context, size_t len);
+
+void dora_output_sample_data(void *sample, char **out_ptr, size_t *out_len);
+
+enum DoraStatus dora_send_output_sample(void *context,
+                                        const char *id_ptr,
+                                        size_t id_len,
+                                        void *sample);
+
+void free_dora_output_sample(void *sample);
 
-void *init_dora_context_from_env();
-void free_dora_context(void *dora_context);
-
-void *dora_next_event(void *dora_context);
-void free_dora_event(void *dora_event);
-
-enum DoraEventType
-{
-    DoraEventType_Stop,
-    DoraEventType_Input,
-    DoraEventType_InputClosed,
-    DoraEventType_Error,
-    DoraEventType_Unknown,
-};
-enum DoraEventType read_dora_event_type(void *dora_event);
-
-void read_dora_input_id(void *dora_event, char **out_ptr, size_t *out_len);
-void read_dora_input_data(void *dora_event, char **out_ptr, size_t *out_len);
-unsigned long long read_dora_input_timestamp(void *dora_event);
-int dora_send_output(void *dora_context, char *id_ptr, size_t id_len, char *data_ptr, size_t data_len);
+#endif  /* DORA_NODE_API_H */
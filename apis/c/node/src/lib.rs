@@ -1,17 +1,86 @@
 #![deny(unsafe_op_in_unsafe_fn)]
 
-use arrow_array::UInt8Array;
-use dora_node_api::{arrow::array::AsArray, DoraNode, Event, EventStream};
-use eyre::Context;
-use std::{ffi::c_void, ptr, slice};
+use dora_node_api::{
+    arrow::datatypes::DataType,
+    dora_core::{
+        config::{DataId, NodeId},
+        metadata::ArrowTypeInfoExt,
+    },
+    DataSample, DoraNode, Event, EventStream,
+};
+use eyre::{bail, Context};
+use std::{
+    cell::RefCell,
+    ffi::{c_char, c_void, CStr},
+    ptr, slice,
+    time::Duration,
+};
 
-pub const HEADER_NODE_API: &str = include_str!("../node_api.h");
+/// The `node_api.h` header, as generated from this crate on build.
+pub const HEADER_NODE_API: &str = include_str!(concat!(env!("OUT_DIR"), "/node_api.h"));
 
 struct DoraContext {
     node: &'static mut DoraNode,
     events: EventStream,
 }
 
+thread_local! {
+    static LAST_ERROR: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Status codes returned by the dora C API.
+///
+/// All error codes are negative. More details about the last error can be read
+/// through [`dora_last_error`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok = 0,
+    Error = -1,
+    InvalidArgument = -2,
+    UnsupportedDataType = -3,
+}
+
+fn set_last_error(err: eyre::Report) {
+    tracing::error!("{err:?}");
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(format!("{err:?}")));
+}
+
+fn to_status(result: eyre::Result<()>, error_status: Status) -> Status {
+    match result {
+        Ok(()) => Status::Ok,
+        Err(err) => {
+            set_last_error(err);
+            error_status
+        }
+    }
+}
+
+/// Reads out the message of the last error that occurred on the current thread.
+///
+/// Writes the `out_ptr` and `out_len` with the start pointer and length of the
+/// UTF-8 encoded error message. Writes a null pointer and length `0` if no
+/// error occurred yet.
+///
+/// ## Safety
+///
+/// The returned `out_ptr` is only valid until the next dora API call on the
+/// same thread. It is not null-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn dora_last_error(out_ptr: *mut *mut c_char, out_len: *mut usize) {
+    LAST_ERROR.with(|last| {
+        let last = last.borrow();
+        let (ptr, len) = match last.as_deref() {
+            Some(message) => (message.as_ptr() as *mut c_char, message.len()),
+            None => (ptr::null_mut(), 0),
+        };
+        unsafe {
+            *out_ptr = ptr;
+            *out_len = len;
+        }
+    });
+}
+
 /// Initializes a dora context from the environment variables that were set by
 /// the dora-coordinator.
 ///
@@ -23,16 +92,50 @@ struct DoraContext {
 /// On error, a null pointer is returned.
 #[no_mangle]
 pub extern "C" fn init_dora_context_from_env() -> *mut c_void {
+    init_context(DoraNode::init_from_env)
+}
+
+/// Initializes a dora node.
+///
+/// If `node_id` is a null pointer, the node is initialized from the environment
+/// variables that were set by the dora-coordinator, like
+/// [`init_dora_context_from_env`]. Otherwise, `node_id` must be a null-terminated
+/// UTF-8 string. In this case, the node connects to the local daemon as a
+/// dynamic node with the given ID if it was not started by dora.
+///
+/// Returns a pointer to the dora context on success, which must be freed through
+/// [`free_dora_context`]. On error, a null pointer is returned and the error
+/// can be read through [`dora_last_error`].
+///
+/// ## Safety
+///
+/// The `node_id` argument must be either null or a valid pointer to a
+/// null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn dora_init_node(node_id: *const c_char) -> *mut c_void {
+    if node_id.is_null() {
+        return init_context(DoraNode::init_from_env);
+    }
+    let node_id = match unsafe { CStr::from_ptr(node_id) }.to_str() {
+        Ok(id) => NodeId::from(id.to_owned()),
+        Err(err) => {
+            set_last_error(eyre::Report::new(err).wrap_err("node ID is not valid UTF-8"));
+            return ptr::null_mut();
+        }
+    };
+    init_context(|| DoraNode::init_flexible(node_id))
+}
+
+fn init_context(init: impl FnOnce() -> eyre::Result<(DoraNode, EventStream)>) -> *mut c_void {
     let context = || {
-        let (node, events) = DoraNode::init_from_env()?;
+        let (node, events) = init()?;
         let node = Box::leak(Box::new(node));
         Result::<_, eyre::Report>::Ok(DoraContext { node, events })
     };
     let context = match context().context("failed to initialize node") {
         Ok(n) => n,
         Err(err) => {
-            let err: eyre::Error = err;
-            tracing::error!("{err:?}");
+            set_last_error(err);
             return ptr::null_mut();
         }
     };
@@ -44,9 +147,9 @@ pub extern "C" fn init_dora_context_from_env() -> *mut c_void {
 ///
 /// ## Safety
 ///
-/// Only pointers created through [`init_dora_context_from_env`] are allowed
-/// as arguments. Each context pointer must be freed exactly once. After
-/// freeing, the pointer must not be used anymore.
+/// Only pointers created through [`init_dora_context_from_env`] or
+/// [`dora_init_node`] are allowed as arguments. Each context pointer must be
+/// freed exactly once. After freeing, the pointer must not be used anymore.
 #[no_mangle]
 pub unsafe extern "C" fn free_dora_context(context: *mut c_void) {
     let context: Box<DoraContext> = unsafe { Box::from_raw(context.cast()) };
@@ -69,8 +172,8 @@ pub unsafe extern "C" fn free_dora_context(context: *mut c_void) {
 /// ## Safety
 ///
 /// The `context` argument must be a dora context created through
-/// [`init_dora_context_from_env`]. The context must be still valid, i.e., not
-/// freed yet.
+/// [`init_dora_context_from_env`] or [`dora_init_node`]. The context must be
+/// still valid, i.e., not freed yet.
 #[no_mangle]
 pub unsafe extern "C" fn dora_next_event(context: *mut c_void) -> *mut c_void {
     let context: &mut DoraContext = unsafe { &mut *context.cast() };
@@ -80,6 +183,30 @@ pub unsafe extern "C" fn dora_next_event(context: *mut c_void) -> *mut c_void {
     }
 }
 
+/// Waits for the next incoming event for the node, for at most `timeout_ms`
+/// milliseconds.
+///
+/// Behaves like [`dora_next_event`], except that an event of type
+/// `DoraEventType_Error` is returned when the timeout expires.
+///
+/// ## Safety
+///
+/// See [`dora_next_event`].
+#[no_mangle]
+pub unsafe extern "C" fn dora_next_event_timeout(
+    context: *mut c_void,
+    timeout_ms: u64,
+) -> *mut c_void {
+    let context: &mut DoraContext = unsafe { &mut *context.cast() };
+    match context
+        .events
+        .recv_timeout(Duration::from_millis(timeout_ms))
+    {
+        Some(event) => Box::into_raw(Box::new(event)).cast(),
+        None => ptr::null_mut(),
+    }
+}
+
 /// Reads out the type of the given event.
 ///
 /// ## Safety
@@ -88,7 +215,7 @@ pub unsafe extern "C" fn dora_next_event(context: *mut c_void) -> *mut c_void {
 /// [`dora_next_event`]. The event must be still valid, i.e., not
 /// freed yet.
 #[no_mangle]
-pub unsafe extern "C" fn read_dora_event_type(event: *const ()) -> EventType {
+pub unsafe extern "C" fn read_dora_event_type(event: *const c_void) -> EventType {
     let event: &Event = unsafe { &*event.cast() };
     match event {
         Event::Stop => EventType::Stop,
@@ -108,12 +235,13 @@ pub enum EventType {
     Unknown,
 }
 
-/// Reads out the ID of the given input event.
+/// Reads out the ID of the given input or input closed event.
 ///
 /// Writes the `out_ptr` and `out_len` with the start pointer and length of the
 /// ID string of the input. The ID is guaranteed to be valid UTF-8.
 ///
-/// Writes a null pointer and length `0` if the given event is not an input event.
+/// Writes a null pointer and length `0` if the given event is not an input or
+/// input closed event.
 ///
 /// ## Safety
 ///
@@ -127,15 +255,15 @@ pub enum EventType {
 /// is given by `out_len`.
 #[no_mangle]
 pub unsafe extern "C" fn read_dora_input_id(
-    event: *const (),
-    out_ptr: *mut *const u8,
+    event: *const c_void,
+    out_ptr: *mut *mut c_char,
     out_len: *mut usize,
 ) {
     let event: &Event = unsafe { &*event.cast() };
     match event {
-        Event::Input { id, .. } => {
+        Event::Input { id, .. } | Event::InputClosed { id } => {
             let id = id.as_str().as_bytes();
-            let ptr = id.as_ptr();
+            let ptr = id.as_ptr() as *mut c_char;
             let len = id.len();
             unsafe {
                 *out_ptr = ptr;
@@ -143,21 +271,26 @@ pub unsafe extern "C" fn read_dora_input_id(
             }
         }
         _ => unsafe {
-            *out_ptr = ptr::null();
+            *out_ptr = ptr::null_mut();
             *out_len = 0;
         },
     }
 }
 
-/// Reads out the data of the given input event.
+/// Reads out the data of the given input event without copying it.
 ///
-/// Writes the `out_ptr` and `out_len` with the start pointer and length of the
-/// input's data array. The data array is a raw byte array, whose format
-/// depends on the source operator/node.
+/// Writes the `out_ptr` and `out_len` with the start pointer and length in bytes
+/// of the input's data. Byte arrays are passed as-is. For other arrays of
+/// fixed-size primitive values (e.g. `float` or `int32_t`), the pointer points to
+/// the tightly packed values, so `out_len` is the number of values times the
+/// value size.
 ///
 /// Writes a null pointer and length `0` if the given event is not an input event
 /// or when an input event has no associated data.
 ///
+/// Returns `DoraStatus_UnsupportedDataType` if the input data is not a primitive
+/// array, e.g. a string or struct array.
+///
 /// ## Safety
 ///
 /// The `event` argument must be a dora event received through
@@ -167,35 +300,38 @@ pub unsafe extern "C" fn read_dora_input_id(
 /// memory.
 #[no_mangle]
 pub unsafe extern "C" fn read_dora_input_data(
-    event: *const (),
-    out_ptr: *mut *const u8,
+    event: *const c_void,
+    out_ptr: *mut *mut c_char,
     out_len: *mut usize,
-) {
+) -> Status {
     let event: &Event = unsafe { &*event.cast() };
-    match event {
-        Event::Input { data, metadata, .. } => match metadata.type_info.data_type {
-            dora_node_api::arrow::datatypes::DataType::UInt8 => {
-                let array: &UInt8Array = data.as_primitive();
-                let ptr = array.values().as_ptr();
-                unsafe {
-                    *out_ptr = ptr;
-                    *out_len = metadata.type_info.len;
-                }
-            }
-            dora_node_api::arrow::datatypes::DataType::Null => unsafe {
-                *out_ptr = ptr::null();
-                *out_len = 0;
-            },
-            _ => {
-                todo!("dora C++ Node does not yet support higher level type of arrow. Only UInt8. 
-                The ultimate solution should be based on arrow FFI interface. Feel free to contribute :)")
-            }
-        },
-        _ => unsafe {
-            *out_ptr = ptr::null();
-            *out_len = 0;
-        },
+    unsafe {
+        *out_ptr = ptr::null_mut();
+        *out_len = 0;
     }
+    let Event::Input { data, .. } = event else {
+        return Status::Ok;
+    };
+    let array = data.0.as_ref();
+    let result = match array.data_type() {
+        DataType::Null => Ok(()),
+        data_type if data_type.is_primitive() => {
+            let array_data = array.to_data();
+            let buffer = &array_data.buffers()[0];
+            let size = data_type.primitive_width().unwrap_or_default();
+            let offset = array_data.offset() * size;
+            unsafe {
+                *out_ptr = buffer.as_ptr().add(offset) as *mut c_char;
+                *out_len = array_data.len() * size;
+            }
+            Ok(())
+        }
+        other => Err(eyre::eyre!(
+            "input data of type `{other}` is not supported by the C API, only \
+            primitive arrays are supported"
+        )),
+    };
+    to_status(result, Status::UnsupportedDataType)
 }
 
 /// Reads out the timestamp of the given input event from metadata.
@@ -204,7 +340,7 @@ pub unsafe extern "C" fn read_dora_input_data(
 ///
 /// Return `0` if the given event is not an input event.
 #[no_mangle]
-pub unsafe extern "C" fn read_dora_input_timestamp(event: *const ()) -> core::ffi::c_ulonglong {
+pub unsafe extern "C" fn read_dora_input_timestamp(event: *const c_void) -> core::ffi::c_ulonglong {
     let event: &Event = unsafe { &*event.cast() };
     match event {
         Event::Input { metadata, .. } => metadata.timestamp().get_time().as_u64(),
@@ -212,6 +348,34 @@ pub unsafe extern "C" fn read_dora_input_timestamp(event: *const ()) -> core::ff
     }
 }
 
+/// Reads out the message of the given error event.
+///
+/// Writes the `out_ptr` and `out_len` with the start pointer and length of the
+/// UTF-8 encoded error message. Writes a null pointer and length `0` if the
+/// given event is not an error event.
+///
+/// ## Safety
+///
+/// The `event` argument must be a dora event received through
+/// [`dora_next_event`]. The returned `out_ptr` must not be used after
+/// freeing the `event`. It is not null-terminated.
+#[no_mangle]
+pub unsafe extern "C" fn read_dora_event_error(
+    event: *const c_void,
+    out_ptr: *mut *mut c_char,
+    out_len: *mut usize,
+) {
+    let event: &Event = unsafe { &*event.cast() };
+    let (ptr, len) = match event {
+        Event::Error(message) => (message.as_ptr() as *mut c_char, message.len()),
+        _ => (ptr::null_mut(), 0),
+    };
+    unsafe {
+        *out_ptr = ptr;
+        *out_len = len;
+    }
+}
+
 /// Frees the given dora event.
 ///
 /// ## Safety
@@ -235,6 +399,8 @@ pub unsafe extern "C" fn free_dora_event(event: *mut c_void) {
 /// The `data_ptr` and `data_len` fields must be the start pointer and length
 /// a byte array. The dora API sends this data as-is, without any processing.
 ///
+/// To avoid copying large outputs, use [`dora_allocate_output`] instead.
+///
 /// ## Safety
 ///
 /// - The `id_ptr` and `id_len` fields must be the start pointer and length of an
@@ -244,34 +410,138 @@ pub unsafe extern "C" fn free_dora_event(event: *mut c_void) {
 #[no_mangle]
 pub unsafe extern "C" fn dora_send_output(
     context: *mut c_void,
-    id_ptr: *const u8,
+    id_ptr: *const c_char,
     id_len: usize,
-    data_ptr: *const u8,
+    data_ptr: *const c_char,
     data_len: usize,
 ) -> isize {
-    match unsafe { try_send_output(context, id_ptr, id_len, data_ptr, data_len) } {
-        Ok(()) => 0,
-        Err(err) => {
-            tracing::error!("{err:?}");
-            -1
-        }
-    }
+    let result = unsafe { try_send_output(context, id_ptr, id_len, data_ptr, data_len) };
+    to_status(result, Status::Error) as isize
 }
 
 unsafe fn try_send_output(
     context: *mut c_void,
-    id_ptr: *const u8,
+    id_ptr: *const c_char,
     id_len: usize,
-    data_ptr: *const u8,
+    data_ptr: *const c_char,
     data_len: usize,
 ) -> eyre::Result<()> {
     let context: &mut DoraContext = unsafe { &mut *context.cast() };
-    let id = std::str::from_utf8(unsafe { slice::from_raw_parts(id_ptr, id_len) })?;
-    let output_id = id.to_owned().into();
-    let data = unsafe { slice::from_raw_parts(data_ptr, data_len) };
+    let output_id = unsafe { read_output_id(id_ptr, id_len) }?;
+    let data = unsafe { slice::from_raw_parts(data_ptr.cast::<u8>(), data_len) };
     context
         .node
         .send_output_raw(output_id, Default::default(), data.len(), |out| {
             out.copy_from_slice(data);
         })
 }
+
+unsafe fn read_output_id(id_ptr: *const c_char, id_len: usize) -> eyre::Result<DataId> {
+    if id_ptr.is_null() {
+        bail!("output ID must not be null");
+    }
+    let id = std::str::from_utf8(unsafe { slice::from_raw_parts(id_ptr.cast::<u8>(), id_len) })
+        .context("output ID is not valid UTF-8")?;
+    Ok(id.to_owned().into())
+}
+
+/// Allocates an output sample of `len` bytes that can be filled and sent without
+/// an additional copy.
+///
+/// Large samples are allocated in shared memory, so that receivers on the same
+/// machine can read them without copying. Use [`dora_output_sample_data`] to get
+/// a pointer to the sample's memory and [`dora_send_output_sample`] to send it.
+/// Samples that are not sent must be freed through [`free_dora_output_sample`].
+///
+/// Returns a null pointer on error.
+///
+/// ## Safety
+///
+/// The `context` argument must be a valid dora context.
+#[no_mangle]
+pub unsafe extern "C" fn dora_allocate_output(context: *mut c_void, len: usize) -> *mut c_void {
+    let context: &mut DoraContext = unsafe { &mut *context.cast() };
+    match context.node.allocate_data_sample(len) {
+        Ok(sample) => Box::into_raw(Box::new(sample)).cast(),
+        Err(err) => {
+            set_last_error(err.wrap_err("failed to allocate output sample"));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Reads out the writable memory of the given output sample.
+///
+/// ## Safety
+///
+/// The `sample` argument must be an output sample created through
+/// [`dora_allocate_output`] that was not sent or freed yet. The returned pointer
+/// must not be used after sending or freeing the sample.
+#[no_mangle]
+pub unsafe extern "C" fn dora_output_sample_data(
+    sample: *mut c_void,
+    out_ptr: *mut *mut c_char,
+    out_len: *mut usize,
+) {
+    let sample: &mut DataSample = unsafe { &mut *sample.cast() };
+    unsafe {
+        *out_ptr = sample.as_mut_ptr().cast();
+        *out_len = sample.len();
+    }
+}
+
+/// Sends the given output sample to subscribed dora nodes/operators.
+///
+/// The sample is consumed by this function, also on error, so it must not be
+/// used or freed afterwards.
+///
+/// ## Safety
+///
+/// - The `context` argument must be a valid dora context.
+/// - The `id_ptr` and `id_len` fields must be the start pointer and length of an
+///   UTF8-encoded string.
+/// - The `sample` argument must be an output sample created through
+///   [`dora_allocate_output`] that was not sent or freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn dora_send_output_sample(
+    context: *mut c_void,
+    id_ptr: *const c_char,
+    id_len: usize,
+    sample: *mut c_void,
+) -> Status {
+    let context: &mut DoraContext = unsafe { &mut *context.cast() };
+    let sample: Box<DataSample> = unsafe { Box::from_raw(sample.cast()) };
+    let output_id = match unsafe { read_output_id(id_ptr, id_len) } {
+        Ok(id) => id,
+        Err(err) => return to_status(Err(err), Status::InvalidArgument),
+    };
+    let type_info = ArrowTypeInfoExt::byte_array(sample.len());
+    let result =
+        context
+            .node
+            .send_output_sample(output_id, type_info, Default::default(), Some(*sample));
+    to_status(result, Status::Error)
+}
+
+/// Frees an output sample that was not sent.
+///
+/// ## Safety
+///
+/// The `sample` argument must be an output sample created through
+/// [`dora_allocate_output`] that was not sent or freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn free_dora_output_sample(sample: *mut c_void) {
+    let _: Box<DataSample> = unsafe { Box::from_raw(sample.cast()) };
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn checked_in_header_is_up_to_date() {
+        assert!(
+            super::HEADER_NODE_API == include_str!("../node_api.h"),
+            "`node_api.h` is outdated, update it through \
+            `cargo run -p dora-node-api-c --example update_header`"
+        );
+    }
+}
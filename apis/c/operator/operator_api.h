@@ -6,14 +6,23 @@ extern "C"
 #endif
 
 #include <stddef.h>
+#include <stdint.h>
 #include "operator_types.h"
 
 #ifdef _WIN32
 #define EXPORT __declspec(dllexport)
+#define DORA_WEAK __declspec(selectany)
 #else
 #define EXPORT __attribute__((visibility("default")))
+#define DORA_WEAK __attribute__((weak))
 #endif
 
+// Version of the operator ABI that this header belongs to. The runtime
+// refuses to load operators that were built against a different version.
+#define DORA_OPERATOR_API_VERSION 4
+
+    EXPORT DORA_WEAK uint32_t dora_operator_api_version = DORA_OPERATOR_API_VERSION;
+
     EXPORT DoraInitResult_t dora_init_operator(void);
 
     EXPORT DoraResult_t dora_drop_operator(void *operator_context);
@@ -23,6 +32,22 @@ extern "C"
         const SendOutput_t *send_output,
         void *operator_context);
 
+    // Optional lifecycle hooks, only called if the operator defines them.
+
+    EXPORT DoraResult_t dora_on_start(
+        const RawOperatorConfig_t *config,
+        const SendOutput_t *send_output,
+        void *operator_context);
+
+    EXPORT DoraResult_t dora_on_stop(
+        const SendOutput_t *send_output,
+        void *operator_context);
+
+    EXPORT DoraResult_t dora_on_error(
+        const Vec_uint8_t *error,
+        const SendOutput_t *send_output,
+        void *operator_context);
+
     static void __dora_type_assertions()
     {
         DoraInitOperator_t __dora_init_operator = {.init_operator = dora_init_operator};
@@ -31,6 +31,72 @@ typedef struct Vec_uint8 {
     size_t cap;
 } Vec_uint8_t;
 
+/** \brief
+ *  Sends the shared device buffer with the given handle to the operators of
+ *  the same runtime node that are connected to output `id`.
+ */
+typedef struct DeviceBufferOutput {
+    /** <No documentation available> */
+    Vec_uint8_t id;
+
+    /** <No documentation available> */
+    uint64_t handle;
+} DeviceBufferOutput_t;
+
+/** <No documentation available> */
+/** \remark Has the same ABI as `uint8_t` **/
+#ifdef DOXYGEN
+typedef
+#endif
+enum DeviceKind {
+    /** <No documentation available> */
+    DEVICE_KIND_CUDA = 0,
+    /** <No documentation available> */
+    DEVICE_KIND_OPEN_CL = 1,
+}
+#ifndef DOXYGEN
+; typedef uint8_t
+#endif
+DeviceKind_t;
+
+/** \brief
+ *  Borrowed view of a shared device buffer.
+ *
+ *  The `ptr` is null if the handle is unknown. The view is valid as long as
+ *  the caller holds a reference to the buffer, e.g. the input that carried
+ *  the handle.
+ */
+typedef struct DeviceBufferView {
+    /** <No documentation available> */
+    DeviceKind_t device;
+
+    /** <No documentation available> */
+    int32_t device_id;
+
+    /** <No documentation available> */
+    void * ptr;
+
+    /** <No documentation available> */
+    size_t len;
+} DeviceBufferView_t;
+
+/** \brief
+ *  Gets the device context that is shared by the operators of a runtime
+ *  node, or registers `context` if there is none yet.
+ */
+typedef struct DeviceContextRequest {
+    /** <No documentation available> */
+    DeviceKind_t device;
+
+    /** <No documentation available> */
+    int32_t device_id;
+
+    /** \brief
+     *  Context to register, may be null to only look up the existing one.
+     */
+    void * context;
+} DeviceContextRequest_t;
+
 /** <No documentation available> */
 typedef struct DoraResult {
     /** <No documentation available> */
@@ -58,6 +124,182 @@ typedef struct DoraInitOperator {
     DoraInitResult_t (*init_operator)(void);
 } DoraInitOperator_t;
 
+/** <No documentation available> */
+typedef struct Output Output_t;
+
+/** \brief
+ *  `Arc<dyn Send + Sync + Fn(A1) -> Ret>`
+ */
+typedef struct ArcDynFn1_DoraResult_Output {
+    /** <No documentation available> */
+    void * env_ptr;
+
+    /** <No documentation available> */
+    DoraResult_t (*call)(void *, Output_t);
+
+    /** <No documentation available> */
+    void (*release)(void *);
+
+    /** <No documentation available> */
+    void (*retain)(void *);
+} ArcDynFn1_DoraResult_Output_t;
+
+/** \brief
+ *  Requests a [`Timer`] event with the given token after `delay_ns` nanoseconds.
+ */
+typedef struct ScheduleTimer {
+    /** <No documentation available> */
+    uint64_t delay_ns;
+
+    /** <No documentation available> */
+    uint64_t token;
+} ScheduleTimer_t;
+
+/** \brief
+ *  `Arc<dyn Send + Sync + Fn(A1) -> Ret>`
+ */
+typedef struct ArcDynFn1_DoraResult_ScheduleTimer {
+    /** <No documentation available> */
+    void * env_ptr;
+
+    /** <No documentation available> */
+    DoraResult_t (*call)(void *, ScheduleTimer_t);
+
+    /** <No documentation available> */
+    void (*release)(void *);
+
+    /** <No documentation available> */
+    void (*retain)(void *);
+} ArcDynFn1_DoraResult_ScheduleTimer_t;
+
+/** \brief
+ *  Device memory that is shared between the operators of a runtime node.
+ *
+ *  The runtime counts the references to the buffer and calls `release` with
+ *  `ptr` and `release_context` once the last reference was dropped.
+ */
+typedef struct RawDeviceBuffer {
+    /** <No documentation available> */
+    DeviceKind_t device;
+
+    /** <No documentation available> */
+    int32_t device_id;
+
+    /** <No documentation available> */
+    void * ptr;
+
+    /** <No documentation available> */
+    size_t len;
+
+    /** <No documentation available> */
+    void (*release)(void *, void *);
+
+    /** <No documentation available> */
+    void * release_context;
+} RawDeviceBuffer_t;
+
+/** \brief
+ *  `Arc<dyn Send + Sync + Fn(A1) -> Ret>`
+ */
+typedef struct ArcDynFn1_uint64_RawDeviceBuffer {
+    /** <No documentation available> */
+    void * env_ptr;
+
+    /** <No documentation available> */
+    uint64_t (*call)(void *, RawDeviceBuffer_t);
+
+    /** <No documentation available> */
+    void (*release)(void *);
+
+    /** <No documentation available> */
+    void (*retain)(void *);
+} ArcDynFn1_uint64_RawDeviceBuffer_t;
+
+/** \brief
+ *  `Arc<dyn Send + Sync + Fn(A1) -> Ret>`
+ */
+typedef struct ArcDynFn1_DoraResult_DeviceBufferOutput {
+    /** <No documentation available> */
+    void * env_ptr;
+
+    /** <No documentation available> */
+    DoraResult_t (*call)(void *, DeviceBufferOutput_t);
+
+    /** <No documentation available> */
+    void (*release)(void *);
+
+    /** <No documentation available> */
+    void (*retain)(void *);
+} ArcDynFn1_DoraResult_DeviceBufferOutput_t;
+
+/** \brief
+ *  `Arc<dyn Send + Sync + Fn(A1) -> Ret>`
+ */
+typedef struct ArcDynFn1_DeviceBufferView_uint64 {
+    /** <No documentation available> */
+    void * env_ptr;
+
+    /** <No documentation available> */
+    DeviceBufferView_t (*call)(void *, uint64_t);
+
+    /** <No documentation available> */
+    void (*release)(void *);
+
+    /** <No documentation available> */
+    void (*retain)(void *);
+} ArcDynFn1_DeviceBufferView_uint64_t;
+
+/** \brief
+ *  `Arc<dyn Send + Sync + Fn(A1) -> Ret>`
+ */
+typedef struct ArcDynFn1_void_ptr_DeviceContextRequest {
+    /** <No documentation available> */
+    void * env_ptr;
+
+    /** <No documentation available> */
+    void * (*call)(void *, DeviceContextRequest_t);
+
+    /** <No documentation available> */
+    void (*release)(void *);
+
+    /** <No documentation available> */
+    void (*retain)(void *);
+} ArcDynFn1_void_ptr_DeviceContextRequest_t;
+
+/** <No documentation available> */
+typedef struct SendOutput {
+    /** <No documentation available> */
+    ArcDynFn1_DoraResult_Output_t send_output;
+
+    /** <No documentation available> */
+    ArcDynFn1_DoraResult_ScheduleTimer_t schedule_timer;
+
+    /** \brief
+     *  Registers a device buffer and returns its handle, or `0` on error.
+     */
+    ArcDynFn1_uint64_RawDeviceBuffer_t share_device_buffer;
+
+    /** <No documentation available> */
+    ArcDynFn1_DoraResult_DeviceBufferOutput_t send_device_buffer;
+
+    /** \brief
+     *  Looks up a device buffer by handle, see [`DeviceBufferView`].
+     */
+    ArcDynFn1_DeviceBufferView_uint64_t device_buffer;
+
+    /** <No documentation available> */
+    ArcDynFn1_void_ptr_DeviceContextRequest_t device_context;
+} SendOutput_t;
+
+/** \brief
+ *  Optional, called when handling an event failed, before the operator is
+ *  stopped.
+ */
+typedef struct DoraOnError {
+    /** <No documentation available> */
+    DoraResult_t (*on_error)(Vec_uint8_t const *, SendOutput_t const *, void *);
+} DoraOnError_t;
+
 /** <No documentation available> */
 /** \remark Has the same ABI as `uint8_t` **/
 #ifdef DOXYGEN
@@ -91,6 +333,12 @@ typedef struct Input Input_t;
 
 #include <stdbool.h>
 
+/** <No documentation available> */
+typedef struct Timer {
+    /** <No documentation available> */
+    uint64_t token;
+} Timer_t;
+
 /** <No documentation available> */
 typedef struct RawEvent {
     /** <No documentation available> */
@@ -104,39 +352,61 @@ typedef struct RawEvent {
 
     /** <No documentation available> */
     Vec_uint8_t error;
+
+    /** <No documentation available> */
+    Timer_t * timer;
 } RawEvent_t;
 
 /** <No documentation available> */
-typedef struct Output Output_t;
+typedef struct DoraOnEvent {
+    /** <No documentation available> */
+    OnEventResult_t (*on_event)(RawEvent_t *, SendOutput_t const *, void *);
+} DoraOnEvent_t;
 
 /** \brief
- *  `Arc<dyn Send + Sync + Fn(A1) -> Ret>`
+ *  Same as [`Vec<T>`][`rust::Vec`], but with guaranteed `#[repr(C)]` layout
  */
-typedef struct ArcDynFn1_DoraResult_Output {
+typedef struct Vec_Vec_uint8 {
     /** <No documentation available> */
-    void * env_ptr;
+    Vec_uint8_t * ptr;
 
     /** <No documentation available> */
-    DoraResult_t (*call)(void *, Output_t);
+    size_t len;
 
     /** <No documentation available> */
-    void (*release)(void *);
+    size_t cap;
+} Vec_Vec_uint8_t;
 
+/** \brief
+ *  Configuration of an operator as defined in the dataflow.
+ */
+typedef struct RawOperatorConfig {
     /** <No documentation available> */
-    void (*retain)(void *);
-} ArcDynFn1_DoraResult_Output_t;
+    Vec_uint8_t id;
 
-/** <No documentation available> */
-typedef struct SendOutput {
     /** <No documentation available> */
-    ArcDynFn1_DoraResult_Output_t send_output;
-} SendOutput_t;
+    Vec_Vec_uint8_t inputs;
 
-/** <No documentation available> */
-typedef struct DoraOnEvent {
     /** <No documentation available> */
-    OnEventResult_t (*on_event)(RawEvent_t *, SendOutput_t const *, void *);
-} DoraOnEvent_t;
+    Vec_Vec_uint8_t outputs;
+} RawOperatorConfig_t;
+
+/** \brief
+ *  Optional, called once before the first event.
+ */
+typedef struct DoraOnStart {
+    /** <No documentation available> */
+    DoraResult_t (*on_start)(RawOperatorConfig_t const *, SendOutput_t const *, void *);
+} DoraOnStart_t;
+
+/** \brief
+ *  Optional, called once after the last event, whatever the reason for the
+ *  stop was.
+ */
+typedef struct DoraOnStop {
+    /** <No documentation available> */
+    DoraResult_t (*on_stop)(SendOutput_t const *, void *);
+} DoraOnStop_t;
 
 /** <No documentation available> */
 typedef struct Metadata {
@@ -144,6 +414,20 @@ typedef struct Metadata {
     Vec_uint8_t open_telemetry_context;
 } Metadata_t;
 
+/** <No documentation available> */
+DeviceBufferView_t
+dora_device_buffer (
+    SendOutput_t const * send_output,
+    uint64_t handle);
+
+/** <No documentation available> */
+void *
+dora_device_context (
+    SendOutput_t const * send_output,
+    DeviceKind_t device,
+    int32_t device_id,
+    void * context);
+
 /** <No documentation available> */
 void
 dora_free_data (
@@ -164,6 +448,20 @@ char *
 dora_read_input_id (
     Input_t const * input);
 
+/** <No documentation available> */
+DoraResult_t
+dora_schedule_timer (
+    SendOutput_t const * send_output,
+    uint64_t delay_ns,
+    uint64_t token);
+
+/** <No documentation available> */
+DoraResult_t
+dora_send_device_buffer (
+    SendOutput_t const * send_output,
+    char const * id,
+    uint64_t handle);
+
 /** <No documentation available> */
 DoraResult_t
 dora_send_operator_output (
@@ -172,6 +470,12 @@ dora_send_operator_output (
     uint8_t const * data_ptr,
     size_t data_len);
 
+/** <No documentation available> */
+uint64_t
+dora_share_device_buffer (
+    SendOutput_t const * send_output,
+    RawDeviceBuffer_t buffer);
+
 
 #ifdef __cplusplus
 } /* extern \"C\" */
@@ -42,6 +42,17 @@ This method returns the parsed dataflow YAML file."""
     def dataflow_id(self) -> str:
         """Returns the dataflow id."""
 
+    def log(self, level: str, message: str, fields: dict=None) -> None:
+        """Send a structured log record to the daemon.
+
+The record is tagged with the dataflow and node id, stored in the node's
+log file, and shown by `dora logs` and attached `dora start` sessions.
+`level` is one of `"error"`, `"warn"`, `"info"`, `"debug"`, or `"trace"`.
+
+```python
+node.log("warn", "dropped frame", {"frame": "42"})
+```"""
+
     def merge_external_events(self, subscription: dora.Ros2Subscription) -> None:
         """Merge an external event stream with dora main loop.
 This currently only work with ROS2."""
@@ -64,9 +75,45 @@ match event["type"]:
 case "INPUT":
 match event["id"]:
 case "image":
+```
+
+The `value` of input events is a `pyarrow` array that points directly into
+the received (shared) memory. Use `dora.zero_copy.to_numpy` to view it as a
+NumPy array without copying.
+
+Input events also contain a `timestamp` entry with the send time of the
+message in nanoseconds since the Unix epoch, see `node.now()`.
+
+Events of type `BACKPRESSURE` signal that a receiver of the output `id`
+can't keep up. Their `level` entry is between `0.0` and `1.0`, higher
+values mean that more messages are dropped or delayed."""
+
+    def now(self) -> int:
+        """Returns the current time of the node's hybrid logical clock in
+nanoseconds since the Unix epoch.
+
+The clock is synchronized with the timestamps of all received messages,
+so it can be compared to the `timestamp` of input events, e.g. to drop
+stale inputs:
+
+```python
+age_ns = node.now() - event["timestamp"]
+if age_ns > 100_000_000:
+    continue
+```"""
+
+    def report_health(self, status: str, message: str=None, counters: dict=None, gauges: dict=None) -> None:
+        """Report the application-level health of this node.
+
+`status` is one of `"ok"`, `"degraded"`, or `"error"`. Counters are
+monotonically increasing integers, gauges are floats that can go up and down.
+Each report replaces the previous one.
+
+```python
+node.report_health("degraded", "frame rate below target", counters={"frames": 1024}, gauges={"fps": 12.5})
 ```"""
 
-    def send_output(self, output_id: str, data: pyarrow.Array, metadata: dict=None) -> None:
+    def send_output(self, output_id: str, data: pyarrow.Array, metadata: dict=None, acquisition_time: int=None) -> None:
         """`send_output` send data from the node.
 
 ```python
@@ -80,6 +127,46 @@ ex:
 
 ```python
 node.send_output("string", b"string", {"open_telemetry_context": "7632e76"})
+```
+
+The optional `acquisition_time` is the time at which the data was
+captured (e.g. a camera hardware timestamp) in nanoseconds since the
+Unix epoch. It is sent alongside the send timestamp and is available to
+receivers as `event["metadata"]["acquisition_time"]`."""
+
+    def send_outputs(self, outputs: list) -> None:
+        """Send multiple outputs as one unit.
+
+All outputs get the same timestamp and are delivered back-to-back, so
+receivers can match them by their `timestamp`. Each entry is a tuple of
+output ID, data, and optional metadata. The supported data types are
+the same as for `send_output`.
+
+```python
+node.send_outputs([("image", image), ("camera_info", camera_info, {"frame": "cam0"})])
+```"""
+
+    def stop_dataflow(self, reason: str=None) -> None:
+        """Request that the whole dataflow is stopped.
+
+The request is routed through the daemon to the coordinator, which then
+sends a stop event to all nodes of the dataflow.
+
+```python
+node.stop_dataflow("mission complete")
+```"""
+
+    def topology(self) -> dict:
+        """Returns the position of this node in the dataflow.
+
+The returned dict contains the resolved descriptor entry of the node
+(`node`), its `inputs` and `outputs`, and for each output the inputs
+of other nodes that receive it (`downstream`).
+
+```python
+topology = node.topology()
+for output, peers in topology["downstream"].items():
+    print(output, peers)
 ```"""
 
     def __iter__(self) -> typing.Any:
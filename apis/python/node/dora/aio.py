@@ -0,0 +1,116 @@
+"""asyncio support for dora nodes.
+
+The `Node` of this module wraps a `dora.Node` so that receiving events and
+sending outputs don't block the asyncio event loop. This makes it possible to
+serve websockets or HTTP requests from the same process while processing dora
+inputs:
+
+```python
+import asyncio
+from dora.aio import Node
+
+async def main():
+    node = Node()
+    async for event in node:
+        if event["type"] == "INPUT":
+            await node.send_output("output", event["value"], event["metadata"])
+
+asyncio.run(main())
+```
+
+All other methods of `dora.Node` (e.g. `log` or `dataflow_descriptor`) are
+available on the wrapper as well and are called synchronously.
+"""
+
+import asyncio
+from concurrent.futures import ThreadPoolExecutor
+
+from .dora import Node as _Node
+
+
+class Node:
+    """asyncio-compatible wrapper around `dora.Node`.
+
+    Blocking calls are run on background threads: one for receiving events
+    and one for sending outputs, so that sending is not delayed by a pending
+    receive.
+
+    :type node_id: str, optional
+    """
+
+    def __init__(self, node_id: str = None):
+        self._node = _Node(node_id)
+        self._recv_executor = ThreadPoolExecutor(
+            max_workers=1, thread_name_prefix="dora-recv"
+        )
+        self._send_executor = ThreadPoolExecutor(
+            max_workers=1, thread_name_prefix="dora-send"
+        )
+        self._pending_recv = None
+
+    @property
+    def node(self) -> _Node:
+        """The wrapped synchronous node."""
+        return self._node
+
+    async def recv(self, timeout: float = None) -> dict:
+        """Wait for the next event without blocking the event loop.
+
+        Returns `None` when the event stream is closed. If the returned
+        coroutine is cancelled, the pending receive is kept and its event is
+        returned by the next `recv` call, so that no event is lost. In this
+        case, the `timeout` of the next call is ignored.
+        """
+        if self._pending_recv is None:
+            loop = asyncio.get_running_loop()
+            self._pending_recv = loop.run_in_executor(
+                self._recv_executor, self._node.next, timeout
+            )
+        pending = self._pending_recv
+        try:
+            return await asyncio.shield(pending)
+        finally:
+            if pending.done():
+                self._pending_recv = None
+
+    def __aiter__(self):
+        return self
+
+    async def __anext__(self) -> dict:
+        event = await self.recv()
+        if event is None:
+            raise StopAsyncIteration
+        return event
+
+    async def send_output(
+        self, output_id: str, data, metadata: dict = None, acquisition_time: int = None
+    ) -> None:
+        """Send an output without blocking the event loop.
+
+        See `dora.Node.send_output` for the supported `data` types.
+        """
+        loop = asyncio.get_running_loop()
+        await loop.run_in_executor(
+            self._send_executor,
+            self._node.send_output,
+            output_id,
+            data,
+            metadata,
+            acquisition_time,
+        )
+
+    async def send_outputs(self, outputs: list) -> None:
+        """Send multiple outputs as one unit without blocking the event loop.
+
+        See `dora.Node.send_outputs`.
+        """
+        loop = asyncio.get_running_loop()
+        await loop.run_in_executor(self._send_executor, self._node.send_outputs, outputs)
+
+    def close(self) -> None:
+        """Stop the background threads once all pending calls are finished."""
+        self._recv_executor.shutdown(wait=False)
+        self._send_executor.shutdown(wait=False)
+
+    def __getattr__(self, name):
+        return getattr(self._node, name)
@@ -0,0 +1,205 @@
+"""Interactive access to a running dataflow, e.g. from a Jupyter notebook.
+
+A `Session` connects to a running dataflow as dynamic node, so the dataflow
+needs a node with `path: dynamic` whose inputs are the outputs that should be
+explored:
+
+```yaml
+- id: notebook
+  path: dynamic
+  inputs:
+    image: camera/image
+    speed: robot/speed
+  outputs:
+    - command
+```
+
+The session receives the inputs in the background, so that they can be
+consumed as async iterators or inspected as pandas DataFrames, and it can send
+outputs to inject data into the dataflow:
+
+```python
+from dora.notebook import Session
+
+session = Session("notebook")
+
+async for message in session.subscribe("image"):
+    print(message.metadata["width"], message.metadata["height"])
+    break
+
+session.frame("speed").plot(x="timestamp", y="value")
+
+session.send("command", [1.0, 0.0])
+```
+
+The values of received messages point directly into the shared memory of the
+sender. As the session keeps the latest messages of each input, a large
+`history` of large messages, e.g. images, keeps a lot of shared memory alive.
+"""
+
+import asyncio
+import collections
+import threading
+from typing import NamedTuple
+
+import pyarrow as pa
+
+from .dora import Node
+
+DEFAULT_HISTORY = 1000
+# interval in which the background thread checks whether the session was closed
+_RECV_TIMEOUT = 0.1
+
+
+class Message(NamedTuple):
+    """A received input message."""
+
+    input_id: str
+    """ID of the input of the session node."""
+    timestamp: int
+    """Send time of the message in nanoseconds since the Unix epoch."""
+    value: pa.Array
+    metadata: dict
+
+
+class Session:
+    """Connection to a running dataflow as dynamic node.
+
+    The session keeps the latest `history` messages of each input.
+
+    :type node_id: str, optional
+    :type history: int, optional
+    """
+
+    def __init__(self, node_id: str = None, history: int = DEFAULT_HISTORY):
+        self._node = Node(node_id)
+        self._history = collections.defaultdict(
+            lambda: collections.deque(maxlen=history)
+        )
+        # (event loop, queue) of the active subscriptions by input ID
+        self._subscriptions = collections.defaultdict(list)
+        self._lock = threading.Lock()
+        self._stopped = threading.Event()
+        self._thread = threading.Thread(
+            target=self._receive, name="dora-notebook", daemon=True
+        )
+        self._thread.start()
+
+    @property
+    def node(self) -> Node:
+        """The underlying node, e.g. to access `dataflow_descriptor()`."""
+        return self._node
+
+    @property
+    def inputs(self) -> list:
+        """IDs of the inputs that received messages so far."""
+        with self._lock:
+            return list(self._history)
+
+    @property
+    def stopped(self) -> bool:
+        """Whether the dataflow stopped or the session was closed."""
+        return self._stopped.is_set()
+
+    def _receive(self):
+        while not self._stopped.is_set():
+            event = self._node.next(timeout=_RECV_TIMEOUT)
+            if event is None or event["type"] == "STOP":
+                break
+            if event["type"] != "INPUT":
+                # e.g. timeouts, which are reported as errors
+                continue
+            message = Message(
+                event["id"], event["timestamp"], event["value"], dict(event["metadata"])
+            )
+            with self._lock:
+                self._history[message.input_id].append(message)
+                subscriptions = list(self._subscriptions[message.input_id])
+            for loop, queue in subscriptions:
+                loop.call_soon_threadsafe(queue.put_nowait, message)
+
+        self._stopped.set()
+        with self._lock:
+            subscriptions = [s for entries in self._subscriptions.values() for s in entries]
+        for loop, queue in subscriptions:
+            # signal the end of the stream
+            loop.call_soon_threadsafe(queue.put_nowait, None)
+
+    async def subscribe(self, input_id: str):
+        """Iterate over the messages of an input asynchronously.
+
+        The iteration starts with the next message that arrives and ends when
+        the dataflow stops.
+        """
+        loop = asyncio.get_running_loop()
+        queue = asyncio.Queue()
+        subscription = (loop, queue)
+        with self._lock:
+            if self._stopped.is_set():
+                return
+            self._subscriptions[input_id].append(subscription)
+        try:
+            while True:
+                message = await queue.get()
+                if message is None:
+                    return
+                yield message
+        finally:
+            with self._lock:
+                self._subscriptions[input_id].remove(subscription)
+
+    def messages(self, input_id: str) -> list:
+        """The kept messages of an input, oldest first."""
+        with self._lock:
+            return list(self._history.get(input_id, ()))
+
+    def latest(self, input_id: str) -> Message:
+        """The latest message of an input, or `None` if none arrived yet."""
+        with self._lock:
+            history = self._history.get(input_id)
+            return history[-1] if history else None
+
+    def frame(self, input_id: str):
+        """The kept messages of an input as pandas DataFrame.
+
+        The DataFrame has a `timestamp` column, a `value` column with the data
+        of each message (a single value if the message contains one element, a
+        list otherwise), and one column per metadata parameter.
+
+        :rtype: pandas.DataFrame
+        """
+        import pandas as pd
+
+        rows = []
+        for message in self.messages(input_id):
+            values = message.value.to_pylist()
+            row = {
+                "timestamp": message.timestamp,
+                "value": values[0] if len(values) == 1 else values,
+            }
+            row.update(message.metadata)
+            rows.append(row)
+        frame = pd.DataFrame(rows, columns=None if rows else ["timestamp", "value"])
+        frame["timestamp"] = pd.to_datetime(frame["timestamp"], unit="ns", utc=True)
+        return frame
+
+    def send(self, output_id: str, data, metadata: dict = None) -> None:
+        """Send an output of the session node into the dataflow.
+
+        `data` is a `pyarrow` array, `bytes`, or a list of values that is
+        converted to a `pyarrow` array.
+        """
+        if isinstance(data, (list, tuple)):
+            data = pa.array(data)
+        self._node.send_output(output_id, data, metadata)
+
+    def close(self) -> None:
+        """Stop receiving messages and end all subscriptions."""
+        self._stopped.set()
+        self._thread.join()
+
+    def __enter__(self):
+        return self
+
+    def __exit__(self, *_exc):
+        self.close()
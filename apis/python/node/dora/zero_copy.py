@@ -0,0 +1,53 @@
+from typing import TYPE_CHECKING
+
+import pyarrow as pa
+
+if TYPE_CHECKING:
+    import numpy as np
+
+
+def to_numpy(value: pa.Array, metadata: dict = None) -> "np.ndarray":
+    """Returns a read-only NumPy view of an input value, without copying it.
+
+    Large inputs are received in shared memory, which is mapped directly into
+    the node. The returned array points into this mapping and keeps it alive:
+    the sender can only reuse the memory after the array (and all views of it)
+    have been garbage collected. So avoid holding on to the array longer than
+    needed, e.g. by copying the parts you want to keep.
+
+    List arrays (e.g. a batch of images sent as a list of `uint8` lists) are
+    flattened into their values. If the `metadata` contains a `shape` entry,
+    the result is reshaped accordingly. If it contains a `dtype` entry (a NumPy
+    dtype string like `"<f4"`), the raw values are reinterpreted as this type.
+
+    Raises a `pyarrow.ArrowInvalid` error if the value can't be viewed without
+    copying, e.g. because it contains nulls or is not a primitive array.
+
+    Requires NumPy, e.g. through the `numpy` extra: `pip install dora-rs[numpy]`.
+
+    Example Use:
+    ```python
+    from dora.zero_copy import to_numpy
+
+    event = node.next()
+    frame = to_numpy(event["value"], event["metadata"])
+    ```
+    """
+    import numpy as np
+
+    array = value
+    while (
+        pa.types.is_list(array.type)
+        or pa.types.is_large_list(array.type)
+        or pa.types.is_fixed_size_list(array.type)
+    ):
+        array = array.flatten()
+
+    result = array.to_numpy(zero_copy_only=True)
+    if metadata is not None:
+        if "dtype" in metadata:
+            result = result.view(np.dtype(metadata["dtype"]))
+        if "shape" in metadata:
+            result = result.reshape(metadata["shape"])
+    result.flags.writeable = False
+    return result
@@ -481,10 +481,12 @@ def concatenated_path_to_type(
 
 
 def build_doc_comment(doc: str) -> Optional[ast.Expr]:
-    lines = [line.strip() for line in doc.split("\n")]
+    # `inspect.getdoc` already removed the common indentation, the remaining
+    # indentation belongs to code examples
+    lines = [line.rstrip() for line in doc.split("\n")]
     clean_lines = []
     for line in lines:
-        if line.startswith((":type", ":rtype")):
+        if line.lstrip().startswith((":type", ":rtype")):
             continue
         clean_lines.append(line)
     text = "\n".join(clean_lines).strip()
@@ -1,18 +1,22 @@
 #![allow(clippy::borrow_deref_ref)] // clippy warns about code generated by #[pymethods]
 
-use std::sync::Arc;
-use std::time::Duration;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
 
+use arrow::array::{ArrayRef, UInt8Array};
 use arrow::pyarrow::{FromPyArrow, ToPyArrow};
 use dora_node_api::dora_core::config::NodeId;
 use dora_node_api::merged::{MergeExternalSend, MergedEvent};
-use dora_node_api::{DataflowId, DoraNode, EventStream};
+use dora_node_api::{
+    DataflowId, DoraNode, EventStream, HealthStatus, LogLevel, MetadataParametersExt, NodeHealth,
+};
 use dora_operator_api_python::{pydict_to_metadata, DelayedCleanup, NodeCleanupHandle, PyEvent};
 use dora_ros2_bridge_python::Ros2Subscription;
 use eyre::Context;
 use futures::{Stream, StreamExt};
 use pyo3::prelude::*;
-use pyo3::types::{PyBytes, PyDict};
+use pyo3::types::{PyBytes, PyDict, PyTuple};
 use pyo3_special_method_derive::{Dict, Dir, Repr, Str};
 
 /// The custom node API lets you integrate `dora` into your application.
@@ -30,7 +34,9 @@ use pyo3_special_method_derive::{Dict, Dir, Repr, Str};
 #[pyclass]
 #[derive(Dir, Dict, Str, Repr)]
 pub struct Node {
-    events: Events,
+    // kept outside of the node's borrow so that outputs can be sent from other
+    // threads while `next` is waiting for an event
+    events: Arc<Mutex<Events>>,
     node: DelayedCleanup<DoraNode>,
 
     dataflow_id: DataflowId,
@@ -57,10 +63,10 @@ impl Node {
             _handles: Arc::new((node.handle(), events.handle())),
         };
         Ok(Node {
-            events: Events {
+            events: Arc::new(Mutex::new(Events {
                 inner: EventsInner::Dora(events),
                 cleanup_handle,
-            },
+            })),
             dataflow_id,
             node_id,
             node,
@@ -86,12 +92,30 @@ impl Node {
     ///                 case "image":
     /// ```
     ///
+    /// The `value` of input events is a `pyarrow` array that points directly into
+    /// the received (shared) memory. Use `dora.zero_copy.to_numpy` to view it as a
+    /// NumPy array without copying.
+    ///
+    /// Input events also contain a `timestamp` entry with the send time of the
+    /// message in nanoseconds since the Unix epoch, see `node.now()`.
+    ///
+    /// Events of type `BACKPRESSURE` signal that a receiver of the output `id`
+    /// can't keep up. Their `level` entry is between `0.0` and `1.0`, higher
+    /// values mean that more messages are dropped or delayed.
+    ///
     /// :type timeout: float, optional
     /// :rtype: dict
     #[pyo3(signature = (timeout=None))]
     #[allow(clippy::should_implement_trait)]
-    pub fn next(&mut self, py: Python, timeout: Option<f32>) -> PyResult<Option<Py<PyDict>>> {
-        let event = py.allow_threads(|| self.events.recv(timeout.map(Duration::from_secs_f32)));
+    pub fn next(slf: &Bound<'_, Self>, timeout: Option<f32>) -> PyResult<Option<Py<PyDict>>> {
+        let py = slf.py();
+        let events = slf.borrow().events.clone();
+        let event = py.allow_threads(|| {
+            events
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .recv(timeout.map(Duration::from_secs_f32))
+        });
         if let Some(event) = event {
             let dict = event
                 .to_py_dict(py)
@@ -115,8 +139,8 @@ impl Node {
     /// Default behaviour is to timeout after 2 seconds.
     ///
     /// :rtype: dict
-    pub fn __next__(&mut self, py: Python) -> PyResult<Option<Py<PyDict>>> {
-        self.next(py, None)
+    pub fn __next__(slf: &Bound<'_, Self>) -> PyResult<Option<Py<PyDict>>> {
+        Self::next(slf, None)
     }
 
     /// You can iterate over the event stream with a loop
@@ -149,19 +173,29 @@ impl Node {
     /// node.send_output("string", b"string", {"open_telemetry_context": "7632e76"})
     /// ```
     ///
+    /// The optional `acquisition_time` is the time at which the data was
+    /// captured (e.g. a camera hardware timestamp) in nanoseconds since the
+    /// Unix epoch. It is sent alongside the send timestamp and is available to
+    /// receivers as `event["metadata"]["acquisition_time"]`.
+    ///
     /// :type output_id: str
     /// :type data: pyarrow.Array
     /// :type metadata: dict, optional
+    /// :type acquisition_time: int, optional
     /// :rtype: None
-    #[pyo3(signature = (output_id, data, metadata=None))]
+    #[pyo3(signature = (output_id, data, metadata=None, acquisition_time=None))]
     pub fn send_output(
         &mut self,
         output_id: String,
         data: PyObject,
         metadata: Option<Bound<'_, PyDict>>,
+        acquisition_time: Option<u64>,
         py: Python,
     ) -> eyre::Result<()> {
-        let parameters = pydict_to_metadata(metadata)?;
+        let mut parameters = pydict_to_metadata(metadata)?;
+        if let Some(nanos) = acquisition_time {
+            parameters.set_acquisition_time(UNIX_EPOCH + Duration::from_nanos(nanos));
+        }
 
         if let Ok(py_bytes) = data.downcast_bound::<PyBytes>(py) {
             let data = py_bytes.as_bytes();
@@ -182,6 +216,147 @@ impl Node {
         Ok(())
     }
 
+    /// Send multiple outputs as one unit.
+    ///
+    /// All outputs get the same timestamp and each receiver gets its inputs of
+    /// the batch together, back-to-back. Each entry is a tuple of
+    /// output ID, data, and optional metadata. The supported data types are
+    /// the same as for `send_output`.
+    ///
+    /// ```python
+    /// node.send_outputs([("image", image), ("camera_info", camera_info, {"frame": "cam0"})])
+    /// ```
+    ///
+    /// :type outputs: list
+    /// :rtype: None
+    pub fn send_outputs(&mut self, outputs: Vec<Bound<'_, PyTuple>>) -> eyre::Result<()> {
+        let mut arrays = Vec::with_capacity(outputs.len());
+        for output in outputs {
+            let (output_id, data, metadata) = match output.len() {
+                2 => (output.get_item(0)?, output.get_item(1)?, None),
+                3 => (
+                    output.get_item(0)?,
+                    output.get_item(1)?,
+                    Some(output.get_item(2)?),
+                ),
+                _ => eyre::bail!("outputs must be `(output_id, data[, metadata])` tuples"),
+            };
+            let output_id: String = output_id.extract()?;
+            let metadata = match metadata {
+                Some(metadata) if !metadata.is_none() => {
+                    Some(metadata.downcast_into::<PyDict>().map_err(PyErr::from)?)
+                }
+                _ => None,
+            };
+            let parameters = pydict_to_metadata(metadata)?;
+            let array: ArrayRef = if let Ok(py_bytes) = data.downcast::<PyBytes>() {
+                Arc::new(UInt8Array::from(py_bytes.as_bytes().to_vec()))
+            } else if let Ok(arrow_array) = arrow::array::ArrayData::from_pyarrow_bound(&data) {
+                arrow::array::make_array(arrow_array)
+            } else {
+                eyre::bail!("invalid `data` type, must by `PyBytes` or arrow array")
+            };
+            arrays.push((output_id.into(), parameters, array));
+        }
+
+        self.node
+            .get_mut()
+            .send_outputs(arrays)
+            .wrap_err("failed to send outputs")
+    }
+
+    /// Request that the whole dataflow is stopped.
+    ///
+    /// The request is routed through the daemon to the coordinator, which then
+    /// sends a stop event to all nodes of the dataflow.
+    ///
+    /// ```python
+    /// node.stop_dataflow("mission complete")
+    /// ```
+    ///
+    /// :type reason: str, optional
+    /// :rtype: None
+    #[pyo3(signature = (reason=None))]
+    pub fn stop_dataflow(&mut self, reason: Option<String>) -> eyre::Result<()> {
+        self.node
+            .get_mut()
+            .stop_dataflow(reason.unwrap_or_default())
+            .wrap_err("failed to request dataflow stop")
+    }
+
+    /// Send a structured log record to the daemon.
+    ///
+    /// The record is tagged with the dataflow and node id, stored in the node's
+    /// log file, and shown by `dora logs` and attached `dora start` sessions.
+    /// `level` is one of `"error"`, `"warn"`, `"info"`, `"debug"`, or `"trace"`.
+    ///
+    /// ```python
+    /// node.log("warn", "dropped frame", {"frame": "42"})
+    /// ```
+    ///
+    /// :type level: str
+    /// :type message: str
+    /// :type fields: dict, optional
+    /// :rtype: None
+    #[pyo3(signature = (level, message, fields=None))]
+    pub fn log(
+        &mut self,
+        level: &str,
+        message: String,
+        fields: Option<BTreeMap<String, String>>,
+    ) -> eyre::Result<()> {
+        let level: LogLevel = level
+            .parse()
+            .map_err(|_| eyre::eyre!("invalid log level `{level}`"))?;
+        self.node
+            .get_mut()
+            .log(level, message, fields.unwrap_or_default())
+            .wrap_err("failed to send log record")
+    }
+
+    /// Report the application-level health of this node.
+    ///
+    /// `status` is one of `"ok"`, `"degraded"`, or `"error"`. Counters are
+    /// monotonically increasing integers, gauges are floats that can go up and down.
+    /// Each report replaces the previous one.
+    ///
+    /// ```python
+    /// node.report_health("degraded", "frame rate below target", counters={"frames": 1024}, gauges={"fps": 12.5})
+    /// ```
+    ///
+    /// :type status: str
+    /// :type message: str, optional
+    /// :type counters: dict, optional
+    /// :type gauges: dict, optional
+    /// :rtype: None
+    #[pyo3(signature = (status, message=None, counters=None, gauges=None))]
+    pub fn report_health(
+        &mut self,
+        status: &str,
+        message: Option<String>,
+        counters: Option<BTreeMap<String, u64>>,
+        gauges: Option<BTreeMap<String, f64>>,
+    ) -> eyre::Result<()> {
+        let status = match status {
+            "ok" => HealthStatus::Ok,
+            "degraded" => HealthStatus::Degraded,
+            "error" => HealthStatus::Error,
+            other => eyre::bail!(
+                "invalid health status `{other}`, expected `ok`, `degraded`, or `error`"
+            ),
+        };
+        let health = NodeHealth {
+            status,
+            message,
+            counters: counters.unwrap_or_default(),
+            gauges: gauges.unwrap_or_default(),
+        };
+        self.node
+            .get_mut()
+            .report_health(health)
+            .wrap_err("failed to report node health")
+    }
+
     /// Returns the full dataflow descriptor that this node is part of.
     ///
     /// This method returns the parsed dataflow YAML file.
@@ -194,6 +369,25 @@ impl Node {
         )
     }
 
+    /// Returns the current time of the node's hybrid logical clock in
+    /// nanoseconds since the Unix epoch.
+    ///
+    /// The clock is synchronized with the timestamps of all received messages,
+    /// so it can be compared to the `timestamp` of input events, e.g. to drop
+    /// stale inputs:
+    ///
+    /// ```python
+    /// age_ns = node.now() - event["timestamp"]
+    /// if age_ns > 100_000_000:
+    ///     continue
+    /// ```
+    ///
+    /// :rtype: int
+    pub fn now(&mut self) -> u64 {
+        let now = self.node.get_mut().now();
+        now.get_time().to_duration().as_nanos() as u64
+    }
+
     /// Returns the dataflow id.
     ///
     /// :rtype: str
@@ -201,6 +395,23 @@ impl Node {
         self.dataflow_id.to_string()
     }
 
+    /// Returns the position of this node in the dataflow.
+    ///
+    /// The returned dict contains the resolved descriptor entry of the node
+    /// (`node`), its `inputs` and `outputs`, and for each output the inputs
+    /// of other nodes that receive it (`downstream`).
+    ///
+    /// ```python
+    /// topology = node.topology()
+    /// for output, peers in topology["downstream"].items():
+    ///     print(output, peers)
+    /// ```
+    ///
+    /// :rtype: dict
+    pub fn topology(&mut self, py: Python) -> eyre::Result<PyObject> {
+        Ok(pythonize::pythonize(py, self.node.get_mut().topology()).map(|x| x.unbind())?)
+    }
+
     /// Merge an external event stream with dora main loop.
     /// This currently only work with ROS2.
     ///
@@ -227,13 +438,14 @@ impl Node {
             s.poll_next_unpin(cx)
         });
 
+        let mut events = self.events.lock().unwrap_or_else(|err| err.into_inner());
         // take out the event stream and temporarily replace it with a dummy
-        let events = std::mem::replace(
-            &mut self.events.inner,
+        let inner = std::mem::replace(
+            &mut events.inner,
             EventsInner::Merged(Box::new(futures::stream::empty())),
         );
         // update self.events with the merged stream
-        self.events.inner = EventsInner::Merged(events.merge_external_send(Box::pin(stream)));
+        events.inner = EventsInner::Merged(inner.merge_external_send(Box::pin(stream)));
 
         Ok(())
     }
@@ -298,8 +510,10 @@ impl Node {
 ///
 /// :rtype: None
 #[pyfunction]
-pub fn start_runtime() -> eyre::Result<()> {
-    dora_runtime::main().wrap_err("Dora Runtime raised an error.")
+pub fn start_runtime(py: Python<'_>) -> eyre::Result<()> {
+    // operators run on their own threads, so they need to acquire the GIL
+    py.allow_threads(dora_runtime::main)
+        .wrap_err("Dora Runtime raised an error.")
 }
 
 #[pymodule]
@@ -13,7 +13,7 @@ use futures::{Stream, StreamExt};
 use futures_concurrency::stream::Merge as _;
 use pyo3::{
     prelude::*,
-    types::{IntoPyDict, PyBool, PyDict, PyInt, PyList, PyString, PyTuple},
+    types::{IntoPyDict, PyBool, PyBytes, PyDict, PyInt, PyList, PyString, PyTuple},
 };
 
 /// Dora Event
@@ -97,6 +97,31 @@ impl PyEvent {
                 if let Some(metadata) = Self::metadata(event, py)? {
                     pydict.insert("metadata", metadata);
                 }
+                if let Event::Input { metadata, .. } = event {
+                    let timestamp = metadata.timestamp().get_time().to_duration().as_nanos();
+                    pydict.insert("timestamp", (timestamp as u64).to_object(py));
+                }
+                if let Event::Backpressure { level, .. } = event {
+                    pydict.insert("level", level.to_object(py));
+                }
+                if let Event::BlackboardChanged { value, .. } = event {
+                    let value = value.as_deref().map(|v| PyBytes::new_bound(py, v).unbind());
+                    pydict.insert("value", value.into_py(py));
+                }
+                if let Event::LeadershipChanged {
+                    leader, is_leader, ..
+                } = event
+                {
+                    pydict.insert("leader", leader.as_ref().map(|l| l.to_string()).into_py(py));
+                    pydict.insert("is_leader", is_leader.to_object(py));
+                }
+                if let Event::NodeOutput { stream, text, .. } = event {
+                    pydict.insert("stream", stream.to_object(py));
+                    pydict.insert("text", text.to_object(py));
+                }
+                if let Event::Timer { token } = event {
+                    pydict.insert("token", token.to_object(py));
+                }
                 if let Some(error) = Self::error(event) {
                     pydict.insert("error", error.to_object(py));
                 }
@@ -118,6 +143,11 @@ impl PyEvent {
             Event::Stop => "STOP",
             Event::Input { .. } => "INPUT",
             Event::InputClosed { .. } => "INPUT_CLOSED",
+            Event::Backpressure { .. } => "BACKPRESSURE",
+            Event::BlackboardChanged { .. } => "BLACKBOARD_CHANGED",
+            Event::LeadershipChanged { .. } => "LEADERSHIP_CHANGED",
+            Event::NodeOutput { .. } => "NODE_OUTPUT",
+            Event::Timer { .. } => "TIMER",
             Event::Error(_) => "ERROR",
             _other => "UNKNOWN",
         }
@@ -127,6 +157,10 @@ impl PyEvent {
         match event {
             Event::Input { id, .. } => Some(id),
             Event::InputClosed { id } => Some(id),
+            Event::Backpressure { output_id, .. } => Some(output_id),
+            Event::BlackboardChanged { key, .. } => Some(key),
+            Event::LeadershipChanged { group, .. } => Some(group),
+            Event::NodeOutput { node_id, .. } => Some(node_id.as_ref()),
             _ => None,
         }
     }
@@ -1,7 +1,7 @@
 use dora_core::{config::NodeId, uhlc::Timestamp};
 use dora_message::{
-    daemon_to_node::DaemonReply,
-    node_to_daemon::{DaemonRequest, NodeRegisterRequest, Timestamped},
+    daemon_to_node::{DaemonCommunication, DaemonReply},
+    node_to_daemon::{DaemonRequest, NodeRegisterRequest, NodeToken, Timestamped},
     DataflowId,
 };
 use eyre::{bail, eyre, Context};
@@ -13,10 +13,33 @@ use std::{
     time::Duration,
 };
 
+pub(crate) use reconnect::{ReconnectingChannel, Reconnector};
+
+mod reconnect;
 mod tcp;
 #[cfg(unix)]
 mod unix_domain;
 
+/// The different channels that a node opens to the daemon.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ChannelKind {
+    Control,
+    Events,
+    EventsClose,
+    Drop,
+}
+
+impl std::fmt::Display for ChannelKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChannelKind::Control => write!(f, "control channel"),
+            ChannelKind::Events => write!(f, "event stream"),
+            ChannelKind::EventsClose => write!(f, "event close channel"),
+            ChannelKind::Drop => write!(f, "drop stream"),
+        }
+    }
+}
+
 pub enum DaemonChannel {
     Shmem(ShmemClient<Timestamped<DaemonRequest>, DaemonReply>),
     Tcp(TcpStream),
@@ -25,6 +48,36 @@ pub enum DaemonChannel {
 }
 
 impl DaemonChannel {
+    /// Opens the given channel kind using the daemon communication config of the node.
+    pub(crate) fn connect(
+        daemon_communication: &DaemonCommunication,
+        kind: ChannelKind,
+    ) -> eyre::Result<Self> {
+        match daemon_communication {
+            DaemonCommunication::Shmem {
+                daemon_control_region_id,
+                daemon_drop_region_id,
+                daemon_events_region_id,
+                daemon_events_close_region_id,
+            } => {
+                let region_id = match kind {
+                    ChannelKind::Control => daemon_control_region_id,
+                    ChannelKind::Events => daemon_events_region_id,
+                    ChannelKind::EventsClose => daemon_events_close_region_id,
+                    ChannelKind::Drop => daemon_drop_region_id,
+                };
+                unsafe { Self::new_shmem(region_id) }
+                    .wrap_err_with(|| format!("failed to create shmem {kind}"))
+            }
+            DaemonCommunication::Tcp { socket_addr } => {
+                Self::new_tcp(*socket_addr).wrap_err_with(|| format!("failed to connect {kind}"))
+            }
+            #[cfg(unix)]
+            DaemonCommunication::UnixDomain { socket_file } => Self::new_unix_socket(socket_file)
+                .wrap_err_with(|| format!("failed to connect {kind}")),
+        }
+    }
+
     #[tracing::instrument(level = "trace")]
     pub fn new_tcp(socket_addr: SocketAddr) -> eyre::Result<Self> {
         let stream = TcpStream::connect(socket_addr).wrap_err("failed to open TCP connection")?;
@@ -56,10 +109,11 @@ impl DaemonChannel {
         &mut self,
         dataflow_id: DataflowId,
         node_id: NodeId,
+        token: NodeToken,
         timestamp: Timestamp,
     ) -> eyre::Result<()> {
         let msg = Timestamped {
-            inner: DaemonRequest::Register(NodeRegisterRequest::new(dataflow_id, node_id)),
+            inner: DaemonRequest::Register(NodeRegisterRequest::new(dataflow_id, node_id, token)),
             timestamp,
         };
         let reply = self
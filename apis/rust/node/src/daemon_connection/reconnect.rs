@@ -0,0 +1,317 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use dora_core::{config::NodeId, uhlc::HLC};
+use dora_message::{
+    daemon_to_node::{DaemonCommunication, DaemonReply},
+    node_to_daemon::{DaemonRequest, NodeToken, Timestamped},
+    DataflowId,
+};
+use eyre::{bail, Context};
+
+use super::{ChannelKind, DaemonChannel};
+
+/// How long a node keeps trying to reach a restarted daemon before giving up.
+const RECONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+const RECONNECT_RETRY_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Config for reconnecting the channels of a node after the daemon was
+/// restarted.
+///
+/// When a restarted daemon re-adopts the node, it sets up the listener of the
+/// node again at the same address and with the same token. So the channels
+/// reconnect by opening the original daemon communication again, retrying
+/// until [`RECONNECT_TIMEOUT`] expires.
+pub(crate) struct Reconnector {
+    dataflow_id: DataflowId,
+    node_id: NodeId,
+    communication: DaemonCommunication,
+    token: NodeToken,
+    clock: Arc<HLC>,
+    /// Set once a channel failed to reconnect, so that the other channels
+    /// don't wait for the daemon again.
+    gave_up: AtomicBool,
+}
+
+impl Reconnector {
+    pub(crate) fn new(
+        dataflow_id: DataflowId,
+        node_id: NodeId,
+        communication: DaemonCommunication,
+        token: NodeToken,
+        clock: Arc<HLC>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            dataflow_id,
+            node_id,
+            communication,
+            token,
+            clock,
+            gave_up: AtomicBool::new(false),
+        })
+    }
+}
+
+/// A [`DaemonChannel`] that can be re-established after a daemon restart.
+pub(crate) struct ReconnectingChannel {
+    channel: DaemonChannel,
+    kind: ChannelKind,
+    reconnector: Arc<Reconnector>,
+}
+
+impl ReconnectingChannel {
+    /// Opens the channel and registers the node on it.
+    pub(crate) fn connect(reconnector: Arc<Reconnector>, kind: ChannelKind) -> eyre::Result<Self> {
+        let channel = Self::open(&reconnector, kind)?;
+        Ok(Self {
+            channel,
+            kind,
+            reconnector,
+        })
+    }
+
+    fn open(reconnector: &Reconnector, kind: ChannelKind) -> eyre::Result<DaemonChannel> {
+        let mut channel = DaemonChannel::connect(&reconnector.communication, kind)?;
+        channel.register(
+            reconnector.dataflow_id,
+            reconnector.node_id.clone(),
+            reconnector.token.clone(),
+            reconnector.clock.new_timestamp(),
+        )?;
+        Ok(channel)
+    }
+
+    pub(crate) fn request(
+        &mut self,
+        request: &Timestamped<DaemonRequest>,
+    ) -> eyre::Result<DaemonReply> {
+        self.channel.request(request)
+    }
+
+    /// Sends the given request, reconnecting if the connection to the daemon
+    /// is broken.
+    ///
+    /// The request is sent again after reconnecting, unless it sends outputs.
+    /// The daemon might have forwarded these before the connection broke, so
+    /// resending them could deliver them twice. Instead, the error is returned.
+    pub(crate) fn request_with_reconnect(
+        &mut self,
+        request: &Timestamped<DaemonRequest>,
+    ) -> eyre::Result<DaemonReply> {
+        match self.channel.request(request) {
+            Ok(reply) => Ok(reply),
+            Err(err) => {
+                tracing::debug!("{} request failed: {err:?}", self.kind);
+                self.reconnect()?;
+                match &request.inner {
+                    DaemonRequest::SendMessage { .. } | DaemonRequest::SendMessages { .. } => {
+                        Err(err.wrap_err(
+                            "connection to dora-daemon broke while sending outputs, \
+                            they might not have been sent",
+                        ))
+                    }
+                    _ => self.channel.request(request),
+                }
+            }
+        }
+    }
+
+    /// Sends the given request, retrying once on a reopened channel if the
+    /// connection to the daemon is broken.
+    ///
+    /// Unlike [`Self::request_with_reconnect`], this never waits for the
+    /// daemon, so it is suitable for cleanup code.
+    pub(crate) fn request_without_waiting(
+        &mut self,
+        request: &Timestamped<DaemonRequest>,
+    ) -> eyre::Result<DaemonReply> {
+        match self.channel.request(request) {
+            Ok(reply) => Ok(reply),
+            Err(err) => {
+                tracing::debug!("{} request failed: {err:?}", self.kind);
+                self.channel = Self::open(&self.reconnector, self.kind)
+                    .wrap_err_with(|| format!("failed to reopen {}", self.kind))?;
+                self.channel.request(request)
+            }
+        }
+    }
+
+    /// Waits until the daemon is reachable again, then reopens the channel
+    /// and registers the node on it.
+    ///
+    /// Subscriptions are not restored, this is up to the caller.
+    pub(crate) fn reconnect(&mut self) -> eyre::Result<()> {
+        if self.reconnector.gave_up.load(Ordering::Relaxed) {
+            bail!("failed to reconnect to dora-daemon");
+        }
+
+        tracing::warn!(
+            "lost connection to dora-daemon, trying to reopen {} of node `{}`",
+            self.kind,
+            self.reconnector.node_id
+        );
+        let deadline = Instant::now() + RECONNECT_TIMEOUT;
+        loop {
+            match Self::open(&self.reconnector, self.kind) {
+                Ok(channel) => {
+                    tracing::info!(
+                        "node `{}` reopened {} to dora-daemon",
+                        self.reconnector.node_id,
+                        self.kind
+                    );
+                    self.channel = channel;
+                    return Ok(());
+                }
+                Err(err) if Instant::now() >= deadline => {
+                    self.reconnector.gave_up.store(true, Ordering::Relaxed);
+                    return Err(err.wrap_err(format!(
+                        "failed to reopen {} within {RECONNECT_TIMEOUT:?}",
+                        self.kind
+                    )));
+                }
+                Err(err) if self.reconnector.gave_up.load(Ordering::Relaxed) => {
+                    return Err(err.wrap_err("failed to reconnect to dora-daemon"));
+                }
+                Err(err) => {
+                    tracing::debug!("reconnect attempt failed: {err:?}");
+                    std::thread::sleep(RECONNECT_RETRY_INTERVAL);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::{SocketAddr, TcpListener, TcpStream},
+        sync::mpsc,
+        thread::JoinHandle,
+    };
+
+    use super::*;
+
+    /// Fake daemon that serves a single connection of a node and records the
+    /// received requests.
+    ///
+    /// The daemon closes the connection and its listener after answering
+    /// `requests` requests, like a crashing daemon.
+    fn fake_daemon(
+        listener: TcpListener,
+        requests: usize,
+        received: mpsc::Sender<DaemonRequest>,
+    ) -> JoinHandle<()> {
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            for _ in 0..requests {
+                let request = receive(&mut stream);
+                received.send(request).unwrap();
+                send(&mut stream, &DaemonReply::Result(Ok(())));
+            }
+        })
+    }
+
+    fn receive(stream: &mut TcpStream) -> DaemonRequest {
+        let mut len = [0; 8];
+        stream.read_exact(&mut len).unwrap();
+        let mut raw = vec![0; u64::from_le_bytes(len) as usize];
+        stream.read_exact(&mut raw).unwrap();
+        let request: Timestamped<DaemonRequest> = bincode::deserialize(&raw).unwrap();
+        request.inner
+    }
+
+    fn send(stream: &mut TcpStream, reply: &DaemonReply) {
+        let raw = bincode::serialize(reply).unwrap();
+        stream.write_all(&(raw.len() as u64).to_le_bytes()).unwrap();
+        stream.write_all(&raw).unwrap();
+    }
+
+    fn reconnector(socket_addr: SocketAddr, token: NodeToken) -> Arc<Reconnector> {
+        Reconnector::new(
+            DataflowId::nil(),
+            NodeId::from("node".to_owned()),
+            DaemonCommunication::Tcp { socket_addr },
+            token,
+            Arc::new(HLC::default()),
+        )
+    }
+
+    fn request(inner: DaemonRequest) -> Timestamped<DaemonRequest> {
+        Timestamped {
+            inner,
+            timestamp: HLC::default().new_timestamp(),
+        }
+    }
+
+    fn assert_register(request: DaemonRequest, token: &NodeToken) {
+        match request {
+            DaemonRequest::Register(register) => {
+                assert_eq!(register.node_id.as_ref(), "node");
+                assert!(register.token.matches(token));
+            }
+            other => panic!("expected register request, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn request_is_resent_to_restarted_daemon() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let token = NodeToken::generate();
+        let (received_tx, received) = mpsc::channel();
+
+        // the first daemon only answers the register request, then crashes
+        let daemon = fake_daemon(listener, 1, received_tx.clone());
+        let mut channel =
+            ReconnectingChannel::connect(reconnector(addr, token.clone()), ChannelKind::Control)
+                .unwrap();
+        assert_register(received.recv().unwrap(), &token);
+        daemon.join().unwrap();
+
+        // the restarted daemon sets up the listener of the node again
+        let restarted = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            let listener = TcpListener::bind(addr).unwrap();
+            fake_daemon(listener, 2, received_tx).join().unwrap();
+        });
+        let reply = channel
+            .request_with_reconnect(&request(DaemonRequest::OutputsDone))
+            .unwrap();
+        assert!(matches!(reply, DaemonReply::Result(Ok(()))));
+
+        // the node registers again with its original token
+        assert_register(received.recv().unwrap(), &token);
+        assert!(matches!(
+            received.recv().unwrap(),
+            DaemonRequest::OutputsDone
+        ));
+        restarted.join().unwrap();
+    }
+
+    #[test]
+    fn reconnect_fails_after_other_channel_gave_up() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let token = NodeToken::generate();
+        let (received_tx, received) = mpsc::channel();
+
+        let daemon = fake_daemon(listener, 1, received_tx);
+        let reconnector = reconnector(addr, token.clone());
+        let mut channel =
+            ReconnectingChannel::connect(reconnector.clone(), ChannelKind::Events).unwrap();
+        assert_register(received.recv().unwrap(), &token);
+        daemon.join().unwrap();
+
+        reconnector.gave_up.store(true, Ordering::Relaxed);
+        let started = Instant::now();
+        assert!(channel.reconnect().is_err());
+        // doesn't wait for the daemon again
+        assert!(started.elapsed() < RECONNECT_TIMEOUT);
+    }
+}
@@ -2,7 +2,7 @@ use std::{ptr::NonNull, sync::Arc};
 
 use aligned_vec::{AVec, ConstAlign};
 use dora_arrow_convert::{ArrowData, IntoArrow};
-use dora_core::config::{DataId, OperatorId};
+use dora_core::config::{DataId, NodeId, OperatorId};
 use dora_message::metadata::{ArrowTypeInfo, BufferOffset, Metadata};
 use eyre::{Context, Result};
 use shared_memory_extended::{Shmem, ShmemConf};
@@ -17,11 +17,64 @@ pub enum Event {
     Input {
         id: DataId,
         metadata: Metadata,
+        /// The received data.
+        ///
+        /// Large inputs are received in shared memory that is owned by the
+        /// sender. The memory stays mapped and is not reused by the sender as
+        /// long as the array or any clone or slice of it is alive. Once the
+        /// last reference is dropped, the input is released to the sender
+        /// automatically. So inputs can't be leaked by forgetting to release
+        /// them, and it's safe to keep them beyond the event handler, e.g. to
+        /// buffer the last few camera frames.
         data: ArrowData,
     },
     InputClosed {
         id: DataId,
     },
+    /// A receiver of the given output can't keep up with the sent messages.
+    ///
+    /// The `level` is between `0.0` and `1.0`, higher values mean that more
+    /// messages are dropped or delayed. Nodes can react to this event by
+    /// lowering their output rate.
+    Backpressure {
+        output_id: DataId,
+        level: f32,
+    },
+    /// A key of the dataflow blackboard that the node watches was set, or
+    /// removed if `value` is `None`.
+    ///
+    /// Only sent for keys that match a prefix passed to
+    /// `DoraNode::blackboard_watch`.
+    BlackboardChanged {
+        key: String,
+        value: Option<Vec<u8>>,
+    },
+    /// The leader of an election group that the node campaigns in changed.
+    ///
+    /// Sent once after `DoraNode::campaign` with the current leader, and then
+    /// on every change. `leader` is `None` if no candidate of the group is
+    /// running anymore.
+    LeadershipChanged {
+        group: String,
+        leader: Option<NodeId>,
+        is_leader: bool,
+    },
+    /// Lines that a node printed to stdout or stderr.
+    ///
+    /// Only sent for the nodes passed to `DoraNode::subscribe_node_output`.
+    NodeOutput {
+        node_id: NodeId,
+        /// Either `stdout` or `stderr`.
+        stream: String,
+        text: String,
+    },
+    /// A timer that an operator scheduled for itself expired.
+    ///
+    /// Only sent to operators, with the token that was passed when scheduling
+    /// the timer.
+    Timer {
+        token: u64,
+    },
     Error(String),
 }
 
@@ -55,10 +108,12 @@ impl RawData {
 
 pub struct SharedMemoryData {
     pub data: MappedInputData,
+    /// Signals the event stream thread on drop that the drop token of this
+    /// input can be reported to the daemon.
     pub _drop: flume::Sender<()>,
 }
 
-fn buffer_into_arrow_array(
+pub(crate) fn buffer_into_arrow_array(
     raw_buffer: &arrow::buffer::Buffer,
     type_info: &ArrowTypeInfo,
 ) -> eyre::Result<arrow::array::ArrayData> {
@@ -124,3 +179,38 @@ impl std::ops::Deref for MappedInputData {
 
 unsafe impl Send for MappedInputData {}
 unsafe impl Sync for MappedInputData {}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::Array;
+    use dora_core::metadata::ArrowTypeInfoExt;
+
+    use super::*;
+
+    #[test]
+    fn shared_memory_is_released_after_last_clone() {
+        let memory = ShmemConf::new().size(64).create().unwrap();
+        let mapped = unsafe { MappedInputData::map(memory.get_os_id(), 64) }.unwrap();
+        let (drop_tx, drop_rx) = flume::bounded::<()>(0);
+        let raw = RawData::SharedMemory(SharedMemoryData {
+            data: mapped,
+            _drop: drop_tx,
+        });
+
+        let data = ArrowData(arrow::array::make_array(
+            raw.into_arrow_array(&ArrowTypeInfo::byte_array(64))
+                .unwrap(),
+        ));
+        let clone = data.clone();
+        let slice = clone.slice(8, 8);
+        drop(data);
+        drop(clone);
+        assert!(
+            !drop_rx.is_disconnected(),
+            "released while a slice is alive"
+        );
+
+        drop(slice);
+        assert!(drop_rx.is_disconnected());
+    }
+}
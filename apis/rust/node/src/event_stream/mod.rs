@@ -1,15 +1,16 @@
 use std::{
     collections::{BTreeMap, HashMap, VecDeque},
+    pin::pin,
     sync::Arc,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use dora_message::{
-    daemon_to_node::{DaemonCommunication, DaemonReply, DataMessage, NodeEvent},
+    daemon_to_node::{DaemonReply, DataMessage, NodeEvent},
     id::DataId,
     node_to_daemon::{DaemonRequest, Timestamped},
-    DataflowId,
 };
+pub(crate) use event::buffer_into_arrow_array;
 pub use event::{Event, MappedInputData, RawData};
 use futures::{
     future::{select, Either},
@@ -22,9 +23,9 @@ use self::{
     event::SharedMemoryData,
     thread::{EventItem, EventStreamThreadHandle},
 };
-use crate::daemon_connection::DaemonChannel;
+use crate::daemon_connection::{ChannelKind, ReconnectingChannel, Reconnector};
 use dora_core::{
-    config::{Input, NodeId},
+    config::{Input, NodeId, QueuePolicy},
     uhlc,
 };
 use eyre::{eyre, Context};
@@ -33,68 +34,49 @@ mod event;
 pub mod merged;
 mod scheduler;
 mod thread;
+pub mod time_sync;
+
+/// Minimum interval between two backpressure reports of the same node.
+const BACKPRESSURE_REPORT_INTERVAL: Duration = Duration::from_millis(100);
 
 pub struct EventStream {
     node_id: NodeId,
     receiver: flume::r#async::RecvStream<'static, EventItem>,
     _thread_handle: EventStreamThreadHandle,
-    close_channel: DaemonChannel,
+    close_channel: ReconnectingChannel,
     clock: Arc<uhlc::HLC>,
     scheduler: Scheduler,
+    last_backpressure_report: Instant,
+    /// Remaining inputs of a batch, for events that bypass the scheduler.
+    pending_batch: VecDeque<EventItem>,
 }
 
 impl EventStream {
-    #[tracing::instrument(level = "trace", skip(clock))]
+    #[tracing::instrument(level = "trace", skip(reconnector, clock))]
     pub(crate) fn init(
-        dataflow_id: DataflowId,
         node_id: &NodeId,
-        daemon_communication: &DaemonCommunication,
+        reconnector: Arc<Reconnector>,
         input_config: BTreeMap<DataId, Input>,
         clock: Arc<uhlc::HLC>,
     ) -> eyre::Result<Self> {
-        let channel = match daemon_communication {
-            DaemonCommunication::Shmem {
-                daemon_events_region_id,
-                ..
-            } => unsafe { DaemonChannel::new_shmem(daemon_events_region_id) }.wrap_err_with(
-                || format!("failed to create shmem event stream for node `{node_id}`"),
-            )?,
-            DaemonCommunication::Tcp { socket_addr } => DaemonChannel::new_tcp(*socket_addr)
-                .wrap_err_with(|| format!("failed to connect event stream for node `{node_id}`"))?,
-            #[cfg(unix)]
-            DaemonCommunication::UnixDomain { socket_file } => {
-                DaemonChannel::new_unix_socket(socket_file).wrap_err_with(|| {
-                    format!("failed to connect event stream for node `{node_id}`")
-                })?
-            }
-        };
+        let mut channel = ReconnectingChannel::connect(reconnector.clone(), ChannelKind::Events)
+            .wrap_err_with(|| format!("failed to connect event stream for node `{node_id}`"))?;
+        thread::subscribe(&mut channel, &clock)?;
 
-        let close_channel = match daemon_communication {
-            DaemonCommunication::Shmem {
-                daemon_events_close_region_id,
-                ..
-            } => unsafe { DaemonChannel::new_shmem(daemon_events_close_region_id) }.wrap_err_with(
-                || format!("failed to create shmem event close channel for node `{node_id}`"),
-            )?,
-            DaemonCommunication::Tcp { socket_addr } => DaemonChannel::new_tcp(*socket_addr)
-                .wrap_err_with(|| {
-                    format!("failed to connect event close channel for node `{node_id}`")
-                })?,
-            #[cfg(unix)]
-            DaemonCommunication::UnixDomain { socket_file } => {
-                DaemonChannel::new_unix_socket(socket_file).wrap_err_with(|| {
-                    format!("failed to connect event close channel for node `{node_id}`")
-                })?
-            }
-        };
+        let close_channel = ReconnectingChannel::connect(reconnector, ChannelKind::EventsClose)
+            .wrap_err_with(|| {
+                format!("failed to connect event close channel for node `{node_id}`")
+            })?;
 
         let mut queue_size_limit: HashMap<DataId, (usize, VecDeque<EventItem>)> = input_config
             .iter()
             .map(|(input, config)| {
-                (
-                    input.clone(),
-                    (config.queue_size.unwrap_or(1), VecDeque::new()),
-                )
+                let queue_size = match config.queue_policy {
+                    QueuePolicy::DropOldest => config.queue_size.unwrap_or(1),
+                    QueuePolicy::KeepLatest => 1,
+                    QueuePolicy::Lossless => usize::MAX,
+                };
+                (input.clone(), (queue_size, VecDeque::new()))
             })
             .collect();
 
@@ -105,43 +87,6 @@ impl EventStream {
 
         let scheduler = Scheduler::new(queue_size_limit);
 
-        Self::init_on_channel(
-            dataflow_id,
-            node_id,
-            channel,
-            close_channel,
-            clock,
-            scheduler,
-        )
-    }
-
-    pub(crate) fn init_on_channel(
-        dataflow_id: DataflowId,
-        node_id: &NodeId,
-        mut channel: DaemonChannel,
-        mut close_channel: DaemonChannel,
-        clock: Arc<uhlc::HLC>,
-        scheduler: Scheduler,
-    ) -> eyre::Result<Self> {
-        channel.register(dataflow_id, node_id.clone(), clock.new_timestamp())?;
-        let reply = channel
-            .request(&Timestamped {
-                inner: DaemonRequest::Subscribe,
-                timestamp: clock.new_timestamp(),
-            })
-            .map_err(|e| eyre!(e))
-            .wrap_err("failed to create subscription with dora-daemon")?;
-
-        match reply {
-            DaemonReply::Result(Ok(())) => {}
-            DaemonReply::Result(Err(err)) => {
-                eyre::bail!("subscribe failed: {err}")
-            }
-            other => eyre::bail!("unexpected subscribe reply: {other:?}"),
-        }
-
-        close_channel.register(dataflow_id, node_id.clone(), clock.new_timestamp())?;
-
         let (tx, rx) = flume::bounded(100_000_000);
 
         let thread_handle = thread::init(node_id.clone(), tx, channel, clock.clone())?;
@@ -153,6 +98,8 @@ impl EventStream {
             close_channel,
             clock,
             scheduler,
+            last_backpressure_report: Instant::now(),
+            pending_batch: VecDeque::new(),
         })
     }
 
@@ -182,18 +129,53 @@ impl EventStream {
                 };
             }
         }
+        self.report_backpressure();
         let event = self.scheduler.next();
         event.map(Self::convert_event_item)
     }
 
     pub async fn recv_async_timeout(&mut self, dur: Duration) -> Option<Event> {
-        let next_event = match select(Delay::new(dur), self.receiver.next()).await {
-            Either::Left((_elapsed, _)) => {
-                Some(EventItem::TimeoutError(eyre!("Receiver timed out")))
+        {
+            // goes through the scheduler so that queue sizes are enforced and
+            // overflows are reported, as in `recv_async`
+            let next_event = pin!(self.recv_async());
+            if let Either::Right((event, _)) = select(Delay::new(dur), next_event).await {
+                return event;
             }
-            Either::Right((event, _)) => event,
-        };
-        next_event.map(Self::convert_event_item)
+        }
+        self.report_backpressure();
+        Some(Self::convert_event_item(EventItem::TimeoutError(eyre!(
+            "Receiver timed out"
+        ))))
+    }
+
+    /// Reports inputs whose queue overflowed to the daemon, which notifies
+    /// the producing nodes through [`Event::Backpressure`].
+    fn report_backpressure(&mut self) {
+        if self.last_backpressure_report.elapsed() < BACKPRESSURE_REPORT_INTERVAL {
+            return;
+        }
+        self.last_backpressure_report = Instant::now();
+        for (input_id, level, dropped) in self.scheduler.take_overflows() {
+            let request = Timestamped {
+                inner: DaemonRequest::ReportBackpressure {
+                    input_id: input_id.clone(),
+                    level,
+                    dropped,
+                },
+                timestamp: self.clock.new_timestamp(),
+            };
+            let result = self
+                .close_channel
+                .request(&request)
+                .and_then(|reply| match reply {
+                    DaemonReply::Empty => Ok(()),
+                    other => Err(eyre!("unexpected ReportBackpressure reply: {other:?}")),
+                });
+            if let Err(err) = result {
+                tracing::warn!("failed to report backpressure on input `{input_id}`: {err:?}");
+            }
+        }
     }
 
     fn convert_event_item(item: EventItem) -> Event {
@@ -202,6 +184,30 @@ impl EventStream {
                 NodeEvent::Stop => Event::Stop,
                 NodeEvent::Reload { operator_id } => Event::Reload { operator_id },
                 NodeEvent::InputClosed { id } => Event::InputClosed { id },
+                NodeEvent::Backpressure { output_id, level } => {
+                    Event::Backpressure { output_id, level }
+                }
+                NodeEvent::BlackboardChanged { key, value } => {
+                    Event::BlackboardChanged { key, value }
+                }
+                NodeEvent::LeadershipChanged {
+                    group,
+                    leader,
+                    is_leader,
+                } => Event::LeadershipChanged {
+                    group,
+                    leader,
+                    is_leader,
+                },
+                NodeEvent::NodeOutput {
+                    node_id,
+                    stream,
+                    text,
+                } => Event::NodeOutput {
+                    node_id,
+                    stream,
+                    text,
+                },
                 NodeEvent::Input { id, metadata, data } => {
                     let data = match data {
                         None => Ok(None),
@@ -241,7 +247,19 @@ impl EventStream {
                     tracing::error!("{err:?}");
                     Event::Error(err.wrap_err("internal error").to_string())
                 }
+                NodeEvent::InputBatch { .. } => {
+                    let err = eyre!(
+                        "received `InputBatch` event, which should be split by background task"
+                    );
+                    tracing::error!("{err:?}");
+                    Event::Error(err.wrap_err("internal error").to_string())
+                }
             },
+            EventItem::InputBatch(_) => {
+                let err = eyre!("received input batch, which should be split by scheduler");
+                tracing::error!("{err:?}");
+                Event::Error(err.wrap_err("internal error").to_string())
+            }
 
             EventItem::FatalError(err) => {
                 Event::Error(format!("fatal event stream error: {err:?}"))
@@ -260,9 +278,15 @@ impl Stream for EventStream {
         mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Option<Self::Item>> {
-        self.receiver
-            .poll_next_unpin(cx)
-            .map(|item| item.map(Self::convert_event_item))
+        loop {
+            if let Some(item) = self.pending_batch.pop_front() {
+                return std::task::Poll::Ready(Some(Self::convert_event_item(item)));
+            }
+            match std::task::ready!(self.receiver.poll_next_unpin(cx)) {
+                Some(EventItem::InputBatch(inputs)) => self.pending_batch.extend(inputs),
+                item => return std::task::Poll::Ready(item.map(Self::convert_event_item)),
+            }
+        }
     }
 }
 
@@ -275,7 +299,7 @@ impl Drop for EventStream {
         };
         let result = self
             .close_channel
-            .request(&request)
+            .request_without_waiting(&request)
             .map_err(|e| eyre!(e))
             .wrap_err("failed to signal event stream closure to dora-daemon")
             .and_then(|r| match r {
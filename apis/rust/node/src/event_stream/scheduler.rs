@@ -16,10 +16,21 @@ pub const NON_INPUT_EVENT: &str = "dora/non_input_event";
 // The Node will always alternate between the two inputs when each input is available
 // Avoiding one input to be overwhelmingly present.
 //
+// Input batches take a single slot in the queue of their first input. Once the
+// batch is due, all of its inputs are handed out back-to-back.
+//
 #[derive(Debug)]
 pub struct Scheduler {
     last_used: VecDeque<DataId>, // Tracks the last-used event ID
     event_queues: HashMap<DataId, (usize, VecDeque<EventItem>)>, // Tracks events per ID
+    overflows: HashMap<DataId, QueueOverflow>, // Tracks dropped events per ID
+    released: VecDeque<EventItem>, // Remaining inputs of a batch that is handed out
+}
+
+#[derive(Debug, Default)]
+struct QueueOverflow {
+    received: u32,
+    dropped: u32,
 }
 
 impl Scheduler {
@@ -34,6 +45,8 @@ impl Scheduler {
         Self {
             last_used: topic,
             event_queues,
+            overflows: HashMap::new(),
+            released: VecDeque::new(),
         }
     }
 
@@ -48,14 +61,21 @@ impl Scheduler {
                     },
                 ack_channel: _,
             } => id,
+            EventItem::InputBatch(inputs) => match inputs.first().and_then(input_id) {
+                Some(id) => id,
+                None => return,
+            },
             _ => &DataId::from(NON_INPUT_EVENT.to_string()),
         };
 
         // Enforce queue size limit
         if let Some((size, queue)) = self.event_queues.get_mut(event_id) {
+            let overflow = self.overflows.entry(event_id.clone()).or_default();
+            overflow.received += 1;
             // Remove the oldest event if at limit
             if &queue.len() >= size {
                 queue.pop_front();
+                overflow.dropped += 1;
             }
             queue.push_back(event);
         } else {
@@ -64,34 +84,294 @@ impl Scheduler {
     }
 
     pub fn next(&mut self) -> Option<EventItem> {
+        // Inputs of a batch are not interrupted by other events
+        if let Some(event) = self.released.pop_front() {
+            return Some(event);
+        }
+
         // Retrieve message from the non input event first that have priority over input message.
-        if let Some((_size, queue)) = self
-            .event_queues
-            .get_mut(&DataId::from(NON_INPUT_EVENT.to_string()))
-        {
-            if let Some(event) = queue.pop_front() {
-                return Some(event);
+        let non_input_id = DataId::from(NON_INPUT_EVENT.to_string());
+        if let Some((_size, queue)) = self.event_queues.get(&non_input_id) {
+            // close events must not overtake the inputs that were sent before
+            // them, so deliver the queued inputs of the closed input(s) first
+            let pending_input = match queue.front() {
+                Some(EventItem::NodeEvent {
+                    event: NodeEvent::InputClosed { id },
+                    ..
+                }) => self.queue_with_inputs_of(id),
+                Some(EventItem::NodeEvent {
+                    event: NodeEvent::AllInputsClosed,
+                    ..
+                }) => self
+                    .last_used
+                    .iter()
+                    .find(|id| self.has_queued_inputs(id))
+                    .cloned(),
+                _ => None,
+            };
+            match pending_input {
+                Some(id) => return self.next_input(&id),
+                None => {
+                    if let Some(event) = self
+                        .event_queues
+                        .get_mut(&non_input_id)
+                        .and_then(|(_size, queue)| queue.pop_front())
+                    {
+                        return Some(event);
+                    }
+                }
             }
         }
 
         // Process the ID with the oldest timestamp using BTreeMap Ordering
-        for (index, id) in self.last_used.clone().iter().enumerate() {
-            if let Some((_size, queue)) = self.event_queues.get_mut(id) {
-                if let Some(event) = queue.pop_front() {
-                    // Put last used at last
-                    self.last_used.remove(index);
-                    self.last_used.push_back(id.clone());
-                    return Some(event);
-                }
+        for id in self.last_used.clone() {
+            if let Some(event) = self.next_input(&id) {
+                return Some(event);
             }
         }
 
         None
     }
 
-    pub fn is_empty(&self) -> bool {
+    fn next_input(&mut self, id: &DataId) -> Option<EventItem> {
+        let (_size, queue) = self.event_queues.get_mut(id)?;
+        match queue.pop_front()? {
+            EventItem::InputBatch(inputs) => {
+                let mut inputs = VecDeque::from(inputs);
+                for input in &inputs {
+                    if let Some(id) = input_id(input) {
+                        self.mark_used(id);
+                    }
+                }
+                let first = inputs.pop_front();
+                self.released.extend(inputs);
+                first
+            }
+            event => {
+                self.mark_used(id);
+                Some(event)
+            }
+        }
+    }
+
+    // Put last used at last
+    fn mark_used(&mut self, id: &DataId) {
+        if let Some(index) = self.last_used.iter().position(|used| used == id) {
+            self.last_used.remove(index);
+            self.last_used.push_back(id.clone());
+        }
+    }
+
+    fn has_queued_inputs(&self, id: &DataId) -> bool {
+        self.event_queues
+            .get(id)
+            .is_some_and(|(_size, queue)| !queue.is_empty())
+    }
+
+    /// Returns the input whose queue holds queued inputs of the given input.
+    ///
+    /// This is a different input if the queued inputs are part of a batch.
+    fn queue_with_inputs_of(&self, id: &DataId) -> Option<DataId> {
+        if self.has_queued_inputs(id) {
+            return Some(id.clone());
+        }
         self.event_queues
             .iter()
-            .all(|(_id, (_size, queue))| queue.is_empty())
+            .find(|(_, (_size, queue))| {
+                queue.iter().any(|event| match event {
+                    EventItem::InputBatch(inputs) => {
+                        inputs.iter().any(|input| input_id(input) == Some(id))
+                    }
+                    _ => false,
+                })
+            })
+            .map(|(queue_id, _)| queue_id.clone())
+    }
+
+    /// Returns the inputs that dropped events since the last call, together
+    /// with the fraction and the number of dropped events.
+    pub fn take_overflows(&mut self) -> Vec<(DataId, f32, u32)> {
+        self.overflows
+            .drain()
+            .filter(|(id, overflow)| overflow.dropped > 0 && id.as_str() != NON_INPUT_EVENT)
+            .map(|(id, overflow)| {
+                let level = overflow.dropped as f32 / overflow.received as f32;
+                (id, level, overflow.dropped)
+            })
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.released.is_empty()
+            && self
+                .event_queues
+                .iter()
+                .all(|(_id, (_size, queue))| queue.is_empty())
+    }
+}
+
+fn input_id(event: &EventItem) -> Option<&DataId> {
+    match event {
+        EventItem::NodeEvent {
+            event: NodeEvent::Input { id, .. },
+            ..
+        } => Some(id),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dora_core::{metadata::ArrowTypeInfoExt, uhlc::HLC};
+    use dora_message::metadata::{ArrowTypeInfo, Metadata};
+
+    use super::*;
+
+    fn event(event: NodeEvent) -> EventItem {
+        EventItem::NodeEvent {
+            event,
+            ack_channel: flume::bounded(1).0,
+        }
+    }
+
+    fn input(id: &str) -> EventItem {
+        let clock = HLC::default();
+        event(NodeEvent::Input {
+            id: id.to_owned().into(),
+            metadata: Metadata::new(clock.new_timestamp(), ArrowTypeInfo::byte_array(0)),
+            data: None,
+        })
+    }
+
+    fn batch(ids: &[&str]) -> EventItem {
+        EventItem::InputBatch(ids.iter().map(|id| input(id)).collect())
+    }
+
+    fn scheduler(inputs: &[&str]) -> Scheduler {
+        let mut queues: HashMap<_, _> = inputs
+            .iter()
+            .map(|id| (DataId::from(id.to_string()), (10, VecDeque::new())))
+            .collect();
+        queues.insert(
+            DataId::from(NON_INPUT_EVENT.to_string()),
+            (1_000, VecDeque::new()),
+        );
+        Scheduler::new(queues)
+    }
+
+    fn next(scheduler: &mut Scheduler) -> String {
+        match scheduler.next() {
+            Some(EventItem::NodeEvent { event, .. }) => match event {
+                NodeEvent::Input { id, .. } => format!("input {id}"),
+                NodeEvent::InputClosed { id } => format!("closed {id}"),
+                NodeEvent::AllInputsClosed => "all closed".to_owned(),
+                NodeEvent::Stop => "stop".to_owned(),
+                other => format!("{other:?}"),
+            },
+            Some(other) => format!("{other:?}"),
+            None => "none".to_owned(),
+        }
+    }
+
+    #[test]
+    fn input_closed_after_queued_inputs() {
+        let mut scheduler = scheduler(&["a", "b"]);
+        scheduler.add_event(input("a"));
+        scheduler.add_event(input("a"));
+        scheduler.add_event(event(NodeEvent::InputClosed {
+            id: "a".to_owned().into(),
+        }));
+        scheduler.add_event(input("b"));
+
+        assert_eq!(next(&mut scheduler), "input a");
+        assert_eq!(next(&mut scheduler), "input a");
+        assert_eq!(next(&mut scheduler), "closed a");
+        assert_eq!(next(&mut scheduler), "input b");
+        assert_eq!(next(&mut scheduler), "none");
+    }
+
+    #[test]
+    fn all_inputs_closed_after_queued_inputs() {
+        let mut scheduler = scheduler(&["a", "b"]);
+        scheduler.add_event(input("a"));
+        scheduler.add_event(input("b"));
+        scheduler.add_event(event(NodeEvent::AllInputsClosed));
+
+        let mut inputs = vec![next(&mut scheduler), next(&mut scheduler)];
+        inputs.sort();
+        assert_eq!(inputs, ["input a", "input b"]);
+        assert_eq!(next(&mut scheduler), "all closed");
+        assert_eq!(next(&mut scheduler), "none");
+    }
+
+    #[test]
+    fn batch_inputs_are_released_together() {
+        let mut scheduler = scheduler(&["a", "b", "c"]);
+        scheduler.add_event(input("c"));
+        scheduler.add_event(batch(&["a", "b"]));
+        scheduler.add_event(input("c"));
+
+        let events: Vec<_> = (0..4).map(|_| next(&mut scheduler)).collect();
+        let a = events.iter().position(|e| e == "input a").unwrap();
+        assert_eq!(events[a + 1], "input b", "{events:?}");
+        assert_eq!(next(&mut scheduler), "none");
+    }
+
+    #[test]
+    fn batch_is_not_interrupted_by_other_events() {
+        let mut scheduler = scheduler(&["a", "b"]);
+        scheduler.add_event(batch(&["a", "b"]));
+
+        assert_eq!(next(&mut scheduler), "input a");
+        scheduler.add_event(event(NodeEvent::Stop));
+        assert_eq!(next(&mut scheduler), "input b");
+        assert_eq!(next(&mut scheduler), "stop");
+        assert_eq!(next(&mut scheduler), "none");
+    }
+
+    #[test]
+    fn batches_are_dropped_as_a_whole() {
+        let mut queues: HashMap<_, _> = [("a", 1), ("b", 10)]
+            .into_iter()
+            .map(|(id, size)| (DataId::from(id.to_owned()), (size, VecDeque::new())))
+            .collect();
+        queues.insert(
+            DataId::from(NON_INPUT_EVENT.to_string()),
+            (1_000, VecDeque::new()),
+        );
+        let mut scheduler = Scheduler::new(queues);
+        scheduler.add_event(batch(&["a", "b"]));
+        scheduler.add_event(batch(&["a", "b"]));
+
+        assert_eq!(next(&mut scheduler), "input a");
+        assert_eq!(next(&mut scheduler), "input b");
+        assert_eq!(next(&mut scheduler), "none");
+        let overflows = scheduler.take_overflows();
+        assert_eq!(overflows.len(), 1);
+        assert_eq!(overflows[0].0.as_str(), "a");
+    }
+
+    #[test]
+    fn input_closed_after_queued_batch() {
+        let mut scheduler = scheduler(&["a", "b"]);
+        scheduler.add_event(batch(&["a", "b"]));
+        scheduler.add_event(event(NodeEvent::InputClosed {
+            id: "b".to_owned().into(),
+        }));
+
+        assert_eq!(next(&mut scheduler), "input a");
+        assert_eq!(next(&mut scheduler), "input b");
+        assert_eq!(next(&mut scheduler), "closed b");
+        assert_eq!(next(&mut scheduler), "none");
+    }
+
+    #[test]
+    fn other_events_have_priority() {
+        let mut scheduler = scheduler(&["a"]);
+        scheduler.add_event(input("a"));
+        scheduler.add_event(event(NodeEvent::Stop));
+
+        assert_eq!(next(&mut scheduler), "stop");
+        assert_eq!(next(&mut scheduler), "input a");
     }
 }
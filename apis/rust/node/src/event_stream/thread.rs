@@ -13,12 +13,12 @@ use std::{
     time::{Duration, Instant},
 };
 
-use crate::daemon_connection::DaemonChannel;
+use crate::daemon_connection::ReconnectingChannel;
 
 pub fn init(
     node_id: NodeId,
     tx: flume::Sender<EventItem>,
-    channel: DaemonChannel,
+    channel: ReconnectingChannel,
     clock: Arc<uhlc::HLC>,
 ) -> eyre::Result<EventStreamThreadHandle> {
     let node_id_cloned = node_id.clone();
@@ -26,12 +26,34 @@ pub fn init(
     Ok(EventStreamThreadHandle::new(node_id, join_handle))
 }
 
+/// Subscribes the given channel to the node's events.
+pub fn subscribe(channel: &mut ReconnectingChannel, clock: &uhlc::HLC) -> eyre::Result<()> {
+    let reply = channel
+        .request(&Timestamped {
+            inner: DaemonRequest::Subscribe,
+            timestamp: clock.new_timestamp(),
+        })
+        .map_err(|e| eyre!(e))
+        .wrap_err("failed to create subscription with dora-daemon")?;
+
+    match reply {
+        DaemonReply::Result(Ok(())) => Ok(()),
+        DaemonReply::Result(Err(err)) => {
+            eyre::bail!("subscribe failed: {err}")
+        }
+        other => eyre::bail!("unexpected subscribe reply: {other:?}"),
+    }
+}
+
 #[derive(Debug)]
 pub enum EventItem {
     NodeEvent {
         event: NodeEvent,
         ack_channel: flume::Sender<()>,
     },
+    /// The inputs of a [`NodeEvent::InputBatch`], which are handed to the
+    /// node together.
+    InputBatch(Vec<EventItem>),
     FatalError(eyre::Report),
     TimeoutError(eyre::Report),
 }
@@ -88,7 +110,7 @@ impl Drop for EventStreamThreadHandle {
 fn event_stream_loop(
     node_id: NodeId,
     tx: flume::Sender<EventItem>,
-    mut channel: DaemonChannel,
+    mut channel: ReconnectingChannel,
     clock: Arc<uhlc::HLC>,
 ) {
     let mut tx = Some(tx);
@@ -123,6 +145,16 @@ fn event_stream_loop(
             Err(err) => {
                 let err = eyre!(err).wrap_err("failed to receive incoming event");
                 tracing::warn!("{err:?}");
+                // the daemon might have been restarted -> try to reconnect
+                if let Err(err) = channel
+                    .reconnect()
+                    .and_then(|()| subscribe(&mut channel, &clock))
+                {
+                    break 'outer Err(err);
+                }
+                // drop tokens of the previous daemon instance are no longer valid
+                drop_tokens.clear();
+                pending_drop_tokens.clear();
                 continue;
             }
         };
@@ -130,6 +162,30 @@ fn event_stream_loop(
             if let Err(err) = clock.update_with_timestamp(&timestamp) {
                 tracing::warn!("failed to update HLC: {err}");
             }
+            if let NodeEvent::InputBatch { inputs } = inner {
+                let Some(tx) = tx.as_ref() else {
+                    tracing::warn!("dropping input batch because event `tx` was already closed");
+                    continue;
+                };
+                let mut items = Vec::new();
+                let mut tokens = Vec::new();
+                for input in inputs {
+                    let (drop_tx, drop_rx) = flume::bounded(0);
+                    if let Some(token) = input.data.as_ref().and_then(|d| d.drop_token()) {
+                        tokens.push((token, drop_rx, Instant::now(), 1));
+                    }
+                    items.push(EventItem::NodeEvent {
+                        event: input.into(),
+                        ack_channel: drop_tx,
+                    });
+                }
+                if tx.send(EventItem::InputBatch(items)).is_err() {
+                    tracing::trace!("event channel was closed already, could not forward batch");
+                    break 'outer Ok(());
+                }
+                pending_drop_tokens.extend(tokens);
+                continue;
+            }
             let drop_token = match &inner {
                 NodeEvent::Input {
                     data: Some(data), ..
@@ -220,7 +276,7 @@ fn handle_pending_drop_tokens(
 }
 
 fn report_remaining_drop_tokens(
-    mut channel: DaemonChannel,
+    mut channel: ReconnectingChannel,
     mut drop_tokens: Vec<DropToken>,
     mut pending_drop_tokens: Vec<(DropToken, flume::Receiver<()>, Instant, u64)>,
     timestamp: Timestamp,
@@ -260,7 +316,7 @@ fn report_remaining_drop_tokens(
 
 fn report_drop_tokens(
     drop_tokens: &mut Vec<DropToken>,
-    channel: &mut DaemonChannel,
+    channel: &mut ReconnectingChannel,
     timestamp: Timestamp,
 ) -> Result<(), eyre::ErrReport> {
     if drop_tokens.is_empty() {
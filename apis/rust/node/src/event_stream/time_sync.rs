@@ -0,0 +1,250 @@
+//! Combine multiple inputs into tuples with approximately equal timestamps.
+//!
+//! This is useful for sensor fusion, e.g. when a node needs a camera image, a
+//! lidar scan, and an IMU reading that were all captured at roughly the same
+//! time. The alignment is based on the timestamps in the input [`Metadata`].
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use dora_node_api::{time_sync::TimeSync, DoraNode, Event};
+//!
+//! let (_node, mut events) = DoraNode::init_from_env().expect("Could not init node.");
+//!
+//! let inputs = ["image", "lidar"].map(|id| id.to_owned().into());
+//! let mut sync = TimeSync::new(inputs)
+//!     .with_slop(Duration::from_millis(20))
+//!     .with_queue_size(10);
+//!
+//! while let Some(event) = events.recv() {
+//!     if let Event::Input { id, metadata, data } = event {
+//!         if let Some(inputs) = sync.push(id, metadata, data) {
+//!             let image = &inputs["image"];
+//!             let lidar = &inputs["lidar"];
+//!             // ...
+//!         }
+//!     }
+//! }
+//! ```
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::Duration,
+};
+
+use dora_arrow_convert::ArrowData;
+use dora_message::{id::DataId, metadata::Metadata};
+
+/// Default maximum timestamp difference between the inputs of a synchronized tuple.
+pub const DEFAULT_SLOP: Duration = Duration::from_millis(50);
+/// Default number of buffered messages per input.
+pub const DEFAULT_QUEUE_SIZE: usize = 10;
+
+/// Buffers inputs and emits them as soon as one message of every input is
+/// available and all of them lie within the configured slop.
+///
+/// Messages of a single input are expected to arrive in timestamp order. Older
+/// messages that can no longer be part of a synchronized tuple are dropped, as
+/// are the oldest messages of an input whose queue is full.
+#[derive(Debug)]
+pub struct TimeSync {
+    queues: BTreeMap<DataId, VecDeque<SyncedInput>>,
+    slop: Duration,
+    queue_size: usize,
+}
+
+/// A single input of a synchronized tuple.
+#[derive(Debug)]
+pub struct SyncedInput {
+    pub metadata: Metadata,
+    pub data: ArrowData,
+}
+
+impl SyncedInput {
+    fn time(&self) -> Duration {
+        self.metadata.timestamp().get_time().to_duration()
+    }
+}
+
+impl TimeSync {
+    /// Creates a combiner for the given inputs, using [`DEFAULT_SLOP`] and
+    /// [`DEFAULT_QUEUE_SIZE`].
+    pub fn new(inputs: impl IntoIterator<Item = DataId>) -> Self {
+        Self {
+            queues: inputs.into_iter().map(|id| (id, VecDeque::new())).collect(),
+            slop: DEFAULT_SLOP,
+            queue_size: DEFAULT_QUEUE_SIZE,
+        }
+    }
+
+    /// Sets the maximum timestamp difference between the inputs of a tuple.
+    pub fn with_slop(mut self, slop: Duration) -> Self {
+        self.slop = slop;
+        self
+    }
+
+    /// Sets the maximum number of buffered messages per input.
+    pub fn with_queue_size(mut self, queue_size: usize) -> Self {
+        self.queue_size = queue_size.max(1);
+        self
+    }
+
+    /// Returns whether the given input is part of this combiner.
+    pub fn contains(&self, id: &DataId) -> bool {
+        self.queues.contains_key(id)
+    }
+
+    /// Adds a message and returns a synchronized tuple if one became available.
+    ///
+    /// Messages for inputs that are not part of this combiner are ignored.
+    pub fn push(
+        &mut self,
+        id: DataId,
+        metadata: Metadata,
+        data: ArrowData,
+    ) -> Option<BTreeMap<DataId, SyncedInput>> {
+        let queue = self.queues.get_mut(&id)?;
+        if queue.len() >= self.queue_size {
+            queue.pop_front();
+        }
+        queue.push_back(SyncedInput { metadata, data });
+
+        self.try_match()
+    }
+
+    /// Drops all buffered messages.
+    pub fn clear(&mut self) {
+        for queue in self.queues.values_mut() {
+            queue.clear();
+        }
+    }
+
+    fn try_match(&mut self) -> Option<BTreeMap<DataId, SyncedInput>> {
+        loop {
+            let mut oldest: Option<(&DataId, Duration)> = None;
+            let mut newest = Duration::ZERO;
+            for (id, queue) in &self.queues {
+                let time = queue.front()?.time();
+                if oldest.map(|(_, t)| time < t).unwrap_or(true) {
+                    oldest = Some((id, time));
+                }
+                newest = newest.max(time);
+            }
+            let (oldest_id, oldest_time) = oldest?;
+
+            if newest - oldest_time <= self.slop {
+                return Some(
+                    self.queues
+                        .iter_mut()
+                        .filter_map(|(id, queue)| Some((id.clone(), queue.pop_front()?)))
+                        .collect(),
+                );
+            }
+
+            // the oldest message is more than `slop` older than the first message of
+            // some other input, so it can never become part of a tuple
+            let oldest_id = oldest_id.clone();
+            if let Some(queue) = self.queues.get_mut(&oldest_id) {
+                queue.pop_front();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::array::NullArray;
+    use dora_core::{
+        metadata::ArrowTypeInfoExt,
+        uhlc::{Timestamp, HLC, NTP64},
+    };
+    use dora_message::metadata::ArrowTypeInfo;
+
+    use super::*;
+
+    fn push(sync: &mut TimeSync, id: &str, millis: u64) -> Option<BTreeMap<DataId, SyncedInput>> {
+        let clock = HLC::default();
+        let time = NTP64::from(Duration::from_millis(millis));
+        let metadata = Metadata::new(
+            Timestamp::new(time, *clock.get_id()),
+            ArrowTypeInfo::byte_array(0),
+        );
+        sync.push(
+            id.to_owned().into(),
+            metadata,
+            ArrowData(Arc::new(NullArray::new(0))),
+        )
+    }
+
+    fn millis(inputs: &BTreeMap<DataId, SyncedInput>, id: &str) -> u64 {
+        // NTP64 timestamps don't represent milliseconds exactly
+        let time = inputs[&DataId::from(id.to_owned())].time();
+        (time.as_secs_f64() * 1000.0).round() as u64
+    }
+
+    fn image_and_lidar() -> TimeSync {
+        TimeSync::new(["image", "lidar"].map(|id| id.to_owned().into()))
+            .with_slop(Duration::from_millis(20))
+    }
+
+    #[test]
+    fn waits_for_all_inputs() {
+        let mut sync = image_and_lidar();
+        assert!(push(&mut sync, "image", 100).is_none());
+        assert!(push(&mut sync, "image", 110).is_none());
+
+        let inputs = push(&mut sync, "lidar", 105).expect("no tuple");
+        assert_eq!(inputs.len(), 2);
+        assert_eq!(millis(&inputs, "image"), 100);
+        assert_eq!(millis(&inputs, "lidar"), 105);
+
+        // the second image stays buffered for the next tuple
+        let inputs = push(&mut sync, "lidar", 112).expect("no tuple");
+        assert_eq!(millis(&inputs, "image"), 110);
+    }
+
+    #[test]
+    fn drops_messages_outside_of_slop() {
+        let mut sync = image_and_lidar();
+        assert!(push(&mut sync, "image", 0).is_none());
+        assert!(push(&mut sync, "lidar", 100).is_none());
+
+        let inputs = push(&mut sync, "image", 95).expect("no tuple");
+        assert_eq!(millis(&inputs, "image"), 95);
+        assert_eq!(millis(&inputs, "lidar"), 100);
+    }
+
+    #[test]
+    fn drops_oldest_message_of_full_queue() {
+        let mut sync = TimeSync::new(["image", "lidar"].map(|id| id.to_owned().into()))
+            .with_slop(Duration::from_millis(50))
+            .with_queue_size(2);
+        for time in [0, 10, 20] {
+            assert!(push(&mut sync, "image", time).is_none());
+        }
+
+        let inputs = push(&mut sync, "lidar", 15).expect("no tuple");
+        assert_eq!(millis(&inputs, "image"), 10);
+    }
+
+    #[test]
+    fn ignores_unknown_inputs() {
+        let mut sync = image_and_lidar();
+        assert!(!sync.contains(&"imu".to_owned().into()));
+        assert!(push(&mut sync, "imu", 100).is_none());
+        assert!(push(&mut sync, "image", 100).is_none());
+        assert!(push(&mut sync, "imu", 100).is_none());
+    }
+
+    #[test]
+    fn clear_drops_buffered_messages() {
+        let mut sync = image_and_lidar();
+        assert!(push(&mut sync, "image", 100).is_none());
+        sync.clear();
+        assert!(push(&mut sync, "lidar", 100).is_none());
+
+        let inputs = push(&mut sync, "image", 101).expect("no tuple");
+        assert_eq!(millis(&inputs, "image"), 101);
+    }
+}
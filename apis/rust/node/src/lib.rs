@@ -17,12 +17,14 @@ pub use arrow;
 pub use dora_arrow_convert::*;
 pub use dora_core::{self, uhlc};
 pub use dora_message::{
-    metadata::{Metadata, MetadataParameters, Parameter},
+    common::{HealthStatus, LogLevel, NodeHealth, NodeToken},
+    metadata::{Metadata, MetadataParameters, MetadataParametersExt, Parameter},
+    node_to_daemon::DYNAMIC_NODE_TOKEN_ENV,
     DataflowId,
 };
-pub use event_stream::{merged, Event, EventStream, MappedInputData, RawData};
+pub use event_stream::{merged, time_sync, Event, EventStream, MappedInputData, RawData};
 pub use flume::Receiver;
-pub use node::{arrow_utils, DataSample, DoraNode, ZERO_COPY_THRESHOLD};
+pub use node::{arrow_utils, topology, CallbackNode, DataSample, DoraNode, ZERO_COPY_THRESHOLD};
 
 mod daemon_connection;
 mod event_stream;
@@ -0,0 +1,222 @@
+//! Callback-based alternative to the `match`-based event loop.
+//!
+//! Instead of matching on every [`Event`], handlers are registered per input ID,
+//! plus optional timer, input-closed, and stop handlers. [`CallbackNode::run`]
+//! then drives the event loop and dispatches each event to its handler.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use dora_node_api::CallbackNode;
+//!
+//! let mut node = CallbackNode::init_from_env().expect("Could not init node.");
+//!
+//! node.on_input("image", |node, metadata, data| {
+//!     let output = "processed".to_owned().into();
+//!     node.send_output(output, metadata.parameters, data.0)
+//! })
+//! .on_timer(Duration::from_secs(1), |_node| {
+//!     println!("still alive");
+//!     Ok(())
+//! })
+//! .on_stop(|_node| {
+//!     println!("stopping");
+//!     Ok(())
+//! });
+//!
+//! node.run().expect("node failed");
+//! ```
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    time::{Duration, Instant},
+};
+
+use dora_arrow_convert::ArrowData;
+use dora_core::config::DataId;
+use dora_message::metadata::Metadata;
+use eyre::Context;
+use futures::future::{select, Either};
+use futures_timer::Delay;
+use tracing::warn;
+
+use super::DoraNode;
+use crate::{Event, EventStream};
+
+type InputHandler<'a> = Box<dyn FnMut(&mut DoraNode, Metadata, ArrowData) -> eyre::Result<()> + 'a>;
+type InputClosedHandler<'a> = Box<dyn FnMut(&mut DoraNode, DataId) -> eyre::Result<()> + 'a>;
+type NodeHandler<'a> = Box<dyn FnMut(&mut DoraNode) -> eyre::Result<()> + 'a>;
+
+struct Timer<'a> {
+    interval: Duration,
+    next: Instant,
+    handler: NodeHandler<'a>,
+}
+
+/// A node that dispatches its events to registered handlers.
+pub struct CallbackNode<'a> {
+    node: DoraNode,
+    events: EventStream,
+    input_handlers: HashMap<DataId, InputHandler<'a>>,
+    input_closed_handler: Option<InputClosedHandler<'a>>,
+    stop_handler: Option<NodeHandler<'a>>,
+    timers: Vec<Timer<'a>>,
+    warned_unhandled_input: BTreeSet<DataId>,
+}
+
+impl<'a> CallbackNode<'a> {
+    /// Initiate a node from environment variables set by `dora-coordinator`.
+    ///
+    /// See [`DoraNode::init_from_env`].
+    pub fn init_from_env() -> eyre::Result<Self> {
+        let (node, events) = DoraNode::init_from_env()?;
+        Ok(Self::new(node, events))
+    }
+
+    /// Creates a callback node from an already initialized node and its event stream.
+    pub fn new(node: DoraNode, events: EventStream) -> Self {
+        Self {
+            node,
+            events,
+            input_handlers: HashMap::new(),
+            input_closed_handler: None,
+            stop_handler: None,
+            timers: Vec::new(),
+            warned_unhandled_input: BTreeSet::new(),
+        }
+    }
+
+    /// Registers the handler for the input with the given ID, replacing any
+    /// previously registered handler for it.
+    pub fn on_input(
+        &mut self,
+        input_id: &str,
+        handler: impl FnMut(&mut DoraNode, Metadata, ArrowData) -> eyre::Result<()> + 'a,
+    ) -> &mut Self {
+        self.input_handlers
+            .insert(DataId::from(input_id.to_owned()), Box::new(handler));
+        self
+    }
+
+    /// Registers a handler that is called when an input is closed.
+    pub fn on_input_closed(
+        &mut self,
+        handler: impl FnMut(&mut DoraNode, DataId) -> eyre::Result<()> + 'a,
+    ) -> &mut Self {
+        self.input_closed_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Registers a handler that is called periodically with the given interval.
+    ///
+    /// Unlike `dora/timer` inputs, these timers are local to the node and are not
+    /// declared in the dataflow descriptor.
+    pub fn on_timer(
+        &mut self,
+        interval: Duration,
+        handler: impl FnMut(&mut DoraNode) -> eyre::Result<()> + 'a,
+    ) -> &mut Self {
+        self.timers.push(Timer {
+            interval,
+            next: Instant::now() + interval,
+            handler: Box::new(handler),
+        });
+        self
+    }
+
+    /// Registers a handler that is called when the node is asked to stop.
+    pub fn on_stop(
+        &mut self,
+        handler: impl FnMut(&mut DoraNode) -> eyre::Result<()> + 'a,
+    ) -> &mut Self {
+        self.stop_handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Gives access to the underlying node, e.g. to send outputs before `run`.
+    pub fn node_mut(&mut self) -> &mut DoraNode {
+        &mut self.node
+    }
+
+    /// Runs the event loop until a stop event is received or the event stream is
+    /// closed.
+    ///
+    /// Returns an error as soon as one of the handlers fails.
+    pub fn run(mut self) -> eyre::Result<()> {
+        loop {
+            let next_timer = self.timers.iter().map(|t| t.next).min();
+            let event = match next_timer {
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    let next_event = match futures::executor::block_on(select(
+                        Delay::new(timeout),
+                        Box::pin(self.events.recv_async()),
+                    )) {
+                        Either::Left(((), _)) => None,
+                        Either::Right((event, _)) => Some(event),
+                    };
+                    match next_event {
+                        Some(event) => event,
+                        None => {
+                            self.run_due_timers()?;
+                            continue;
+                        }
+                    }
+                }
+                None => self.events.recv(),
+            };
+
+            let Some(event) = event else {
+                break;
+            };
+            match event {
+                Event::Input { id, metadata, data } => match self.input_handlers.get_mut(&id) {
+                    Some(handler) => handler(&mut self.node, metadata, data)
+                        .wrap_err_with(|| format!("handler for input `{id}` failed"))?,
+                    None => {
+                        if self.warned_unhandled_input.insert(id.clone()) {
+                            warn!("Ignoring input `{id}` because no handler is registered for it.");
+                        }
+                    }
+                },
+                Event::InputClosed { id } => {
+                    if let Some(handler) = &mut self.input_closed_handler {
+                        handler(&mut self.node, id.clone())
+                            .wrap_err_with(|| format!("input closed handler for `{id}` failed"))?;
+                    }
+                }
+                Event::Stop => {
+                    if let Some(handler) = &mut self.stop_handler {
+                        handler(&mut self.node).wrap_err("stop handler failed")?;
+                    }
+                    break;
+                }
+                Event::Reload { .. }
+                | Event::Backpressure { .. }
+                | Event::BlackboardChanged { .. }
+                | Event::LeadershipChanged { .. }
+                | Event::NodeOutput { .. } => {}
+                Event::Error(err) => warn!("received error event: {err}"),
+                other => warn!("Ignoring unexpected event: {other:?}"),
+            }
+        }
+        Ok(())
+    }
+
+    fn run_due_timers(&mut self) -> eyre::Result<()> {
+        let now = Instant::now();
+        for timer in &mut self.timers {
+            if timer.next <= now {
+                (timer.handler)(&mut self.node).wrap_err_with(|| {
+                    format!("timer handler with interval {:?} failed", timer.interval)
+                })?;
+                // skip missed ticks instead of trying to catch up
+                timer.next = if timer.next + timer.interval <= now {
+                    now + timer.interval
+                } else {
+                    timer.next + timer.interval
+                };
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,57 +1,31 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
 
-use crate::daemon_connection::DaemonChannel;
+use crate::daemon_connection::{ChannelKind, ReconnectingChannel, Reconnector};
 use dora_core::{
     config::{DataId, NodeId},
     uhlc::HLC,
 };
 use dora_message::{
-    daemon_to_node::{DaemonCommunication, DaemonReply},
+    daemon_to_node::DaemonReply,
     metadata::Metadata,
-    node_to_daemon::{DaemonRequest, DataMessage, Timestamped},
-    DataflowId,
+    node_to_daemon::{
+        DaemonRequest, DataMessage, LogLevel, NodeHealth, OutputMessage, Timestamped,
+    },
 };
 use eyre::{bail, eyre, Context};
 
 pub(crate) struct ControlChannel {
-    channel: DaemonChannel,
+    channel: ReconnectingChannel,
     clock: Arc<HLC>,
 }
 
 impl ControlChannel {
-    #[tracing::instrument(level = "trace", skip(clock))]
-    pub(crate) fn init(
-        dataflow_id: DataflowId,
-        node_id: &NodeId,
-        daemon_communication: &DaemonCommunication,
-        clock: Arc<HLC>,
-    ) -> eyre::Result<Self> {
-        let channel = match daemon_communication {
-            DaemonCommunication::Shmem {
-                daemon_control_region_id,
-                ..
-            } => unsafe { DaemonChannel::new_shmem(daemon_control_region_id) }
-                .wrap_err("failed to create shmem control channel")?,
-            DaemonCommunication::Tcp { socket_addr } => DaemonChannel::new_tcp(*socket_addr)
-                .wrap_err("failed to connect control channel")?,
-            #[cfg(unix)]
-            DaemonCommunication::UnixDomain { socket_file } => {
-                DaemonChannel::new_unix_socket(socket_file)
-                    .wrap_err("failed to connect control channel")?
-            }
-        };
-
-        Self::init_on_channel(dataflow_id, node_id, channel, clock)
-    }
-
-    #[tracing::instrument(skip(channel, clock), level = "trace")]
-    pub fn init_on_channel(
-        dataflow_id: DataflowId,
-        node_id: &NodeId,
-        mut channel: DaemonChannel,
-        clock: Arc<HLC>,
-    ) -> eyre::Result<Self> {
-        channel.register(dataflow_id, node_id.clone(), clock.new_timestamp())?;
+    #[tracing::instrument(level = "trace", skip(reconnector, clock))]
+    pub(crate) fn init(reconnector: Arc<Reconnector>, clock: Arc<HLC>) -> eyre::Result<Self> {
+        let channel = ReconnectingChannel::connect(reconnector, ChannelKind::Control)?;
 
         Ok(Self { channel, clock })
     }
@@ -59,7 +33,7 @@ impl ControlChannel {
     pub fn report_outputs_done(&mut self) -> eyre::Result<()> {
         let reply = self
             .channel
-            .request(&Timestamped {
+            .request_with_reconnect(&Timestamped {
                 inner: DaemonRequest::OutputsDone,
                 timestamp: self.clock.new_timestamp(),
             })
@@ -76,7 +50,7 @@ impl ControlChannel {
     pub fn report_closed_outputs(&mut self, outputs: Vec<DataId>) -> eyre::Result<()> {
         let reply = self
             .channel
-            .request(&Timestamped {
+            .request_with_reconnect(&Timestamped {
                 inner: DaemonRequest::CloseOutputs(outputs),
                 timestamp: self.clock.new_timestamp(),
             })
@@ -90,6 +64,202 @@ impl ControlChannel {
         Ok(())
     }
 
+    pub fn request_dataflow_stop(&mut self, reason: String) -> eyre::Result<()> {
+        let reply = self
+            .channel
+            .request_with_reconnect(&Timestamped {
+                inner: DaemonRequest::StopDataflow { reason },
+                timestamp: self.clock.new_timestamp(),
+            })
+            .wrap_err("failed to send StopDataflow request to dora-daemon")?;
+        match reply {
+            DaemonReply::Result(result) => result
+                .map_err(|e| eyre!(e))
+                .wrap_err("failed to receive StopDataflow reply from dora-daemon")?,
+            other => bail!("unexpected StopDataflow reply: {other:?}"),
+        }
+        Ok(())
+    }
+
+    pub fn report_health(&mut self, health: NodeHealth) -> eyre::Result<()> {
+        let reply = self
+            .channel
+            .request_with_reconnect(&Timestamped {
+                inner: DaemonRequest::ReportHealth { health },
+                timestamp: self.clock.new_timestamp(),
+            })
+            .wrap_err("failed to send ReportHealth request to dora-daemon")?;
+        match reply {
+            DaemonReply::Result(result) => result
+                .map_err(|e| eyre!(e))
+                .wrap_err("failed to receive ReportHealth reply from dora-daemon")?,
+            other => bail!("unexpected ReportHealth reply: {other:?}"),
+        }
+        Ok(())
+    }
+
+    pub fn save_state(&mut self, state: Vec<u8>) -> eyre::Result<()> {
+        let reply = self
+            .channel
+            .request_with_reconnect(&Timestamped {
+                inner: DaemonRequest::SaveState { state },
+                timestamp: self.clock.new_timestamp(),
+            })
+            .wrap_err("failed to send SaveState request to dora-daemon")?;
+        match reply {
+            DaemonReply::Result(result) => result
+                .map_err(|e| eyre!(e))
+                .wrap_err("failed to receive SaveState reply from dora-daemon")?,
+            other => bail!("unexpected SaveState reply: {other:?}"),
+        }
+        Ok(())
+    }
+
+    pub fn restored_state(&mut self) -> eyre::Result<Option<Vec<u8>>> {
+        let reply = self
+            .channel
+            .request_with_reconnect(&Timestamped {
+                inner: DaemonRequest::RestoredState,
+                timestamp: self.clock.new_timestamp(),
+            })
+            .wrap_err("failed to send RestoredState request to dora-daemon")?;
+        match reply {
+            DaemonReply::RestoredState(state) => Ok(state),
+            DaemonReply::Result(Err(err)) => {
+                Err(eyre!(err)).wrap_err("failed to receive RestoredState reply from dora-daemon")
+            }
+            other => bail!("unexpected RestoredState reply: {other:?}"),
+        }
+    }
+
+    pub fn blackboard_get(&mut self, key: String) -> eyre::Result<Option<Vec<u8>>> {
+        let reply = self
+            .channel
+            .request_with_reconnect(&Timestamped {
+                inner: DaemonRequest::BlackboardGet { key },
+                timestamp: self.clock.new_timestamp(),
+            })
+            .wrap_err("failed to send BlackboardGet request to dora-daemon")?;
+        match reply {
+            DaemonReply::BlackboardValue(value) => Ok(value),
+            DaemonReply::Result(Err(err)) => {
+                Err(eyre!(err)).wrap_err("failed to receive BlackboardGet reply from dora-daemon")
+            }
+            other => bail!("unexpected BlackboardGet reply: {other:?}"),
+        }
+    }
+
+    pub fn blackboard_set(&mut self, key: String, value: Option<Vec<u8>>) -> eyre::Result<()> {
+        let reply = self
+            .channel
+            .request_with_reconnect(&Timestamped {
+                inner: DaemonRequest::BlackboardSet { key, value },
+                timestamp: self.clock.new_timestamp(),
+            })
+            .wrap_err("failed to send BlackboardSet request to dora-daemon")?;
+        match reply {
+            DaemonReply::Result(result) => result
+                .map_err(|e| eyre!(e))
+                .wrap_err("failed to receive BlackboardSet reply from dora-daemon")?,
+            other => bail!("unexpected BlackboardSet reply: {other:?}"),
+        }
+        Ok(())
+    }
+
+    pub fn blackboard_watch(&mut self, prefix: String) -> eyre::Result<()> {
+        let reply = self
+            .channel
+            .request_with_reconnect(&Timestamped {
+                inner: DaemonRequest::BlackboardWatch { prefix },
+                timestamp: self.clock.new_timestamp(),
+            })
+            .wrap_err("failed to send BlackboardWatch request to dora-daemon")?;
+        match reply {
+            DaemonReply::Result(result) => result
+                .map_err(|e| eyre!(e))
+                .wrap_err("failed to receive BlackboardWatch reply from dora-daemon")?,
+            other => bail!("unexpected BlackboardWatch reply: {other:?}"),
+        }
+        Ok(())
+    }
+
+    pub fn campaign(&mut self, group: String) -> eyre::Result<()> {
+        let reply = self
+            .channel
+            .request_with_reconnect(&Timestamped {
+                inner: DaemonRequest::Campaign { group },
+                timestamp: self.clock.new_timestamp(),
+            })
+            .wrap_err("failed to send Campaign request to dora-daemon")?;
+        match reply {
+            DaemonReply::Result(result) => result
+                .map_err(|e| eyre!(e))
+                .wrap_err("failed to receive Campaign reply from dora-daemon")?,
+            other => bail!("unexpected Campaign reply: {other:?}"),
+        }
+        Ok(())
+    }
+
+    pub fn resign(&mut self, group: String) -> eyre::Result<()> {
+        let reply = self
+            .channel
+            .request_with_reconnect(&Timestamped {
+                inner: DaemonRequest::Resign { group },
+                timestamp: self.clock.new_timestamp(),
+            })
+            .wrap_err("failed to send Resign request to dora-daemon")?;
+        match reply {
+            DaemonReply::Result(result) => result
+                .map_err(|e| eyre!(e))
+                .wrap_err("failed to receive Resign reply from dora-daemon")?,
+            other => bail!("unexpected Resign reply: {other:?}"),
+        }
+        Ok(())
+    }
+
+    pub fn subscribe_node_output(&mut self, nodes: BTreeSet<NodeId>) -> eyre::Result<()> {
+        let reply = self
+            .channel
+            .request_with_reconnect(&Timestamped {
+                inner: DaemonRequest::SubscribeNodeOutput { nodes },
+                timestamp: self.clock.new_timestamp(),
+            })
+            .wrap_err("failed to send SubscribeNodeOutput request to dora-daemon")?;
+        match reply {
+            DaemonReply::Result(result) => result
+                .map_err(|e| eyre!(e))
+                .wrap_err("failed to receive SubscribeNodeOutput reply from dora-daemon")?,
+            other => bail!("unexpected SubscribeNodeOutput reply: {other:?}"),
+        }
+        Ok(())
+    }
+
+    pub fn send_log(
+        &mut self,
+        level: LogLevel,
+        target: Option<String>,
+        message: String,
+        fields: BTreeMap<String, String>,
+    ) -> eyre::Result<()> {
+        let request = DaemonRequest::Log {
+            level,
+            target,
+            message,
+            fields,
+        };
+        let reply = self
+            .channel
+            .request_with_reconnect(&Timestamped {
+                inner: request,
+                timestamp: self.clock.new_timestamp(),
+            })
+            .wrap_err("failed to send Log request to dora-daemon")?;
+        match reply {
+            DaemonReply::Empty => Ok(()),
+            other => bail!("unexpected Log reply: {other:?}"),
+        }
+    }
+
     pub fn send_message(
         &mut self,
         output_id: DataId,
@@ -103,7 +273,7 @@ impl ControlChannel {
         };
         let reply = self
             .channel
-            .request(&Timestamped {
+            .request_with_reconnect(&Timestamped {
                 inner: request,
                 timestamp: self.clock.new_timestamp(),
             })
@@ -113,4 +283,18 @@ impl ControlChannel {
             other => bail!("unexpected SendMessage reply: {other:?}"),
         }
     }
+
+    pub fn send_messages(&mut self, messages: Vec<OutputMessage>) -> eyre::Result<()> {
+        let reply = self
+            .channel
+            .request_with_reconnect(&Timestamped {
+                inner: DaemonRequest::SendMessages { messages },
+                timestamp: self.clock.new_timestamp(),
+            })
+            .wrap_err("failed to send SendMessages request to dora-daemon")?;
+        match reply {
+            DaemonReply::Empty => Ok(()),
+            other => bail!("unexpected SendMessages reply: {other:?}"),
+        }
+    }
 }
@@ -1,11 +1,10 @@
 use std::{sync::Arc, time::Duration};
 
-use crate::daemon_connection::DaemonChannel;
+use crate::daemon_connection::{ChannelKind, ReconnectingChannel, Reconnector};
 use dora_core::{config::NodeId, uhlc};
 use dora_message::{
-    daemon_to_node::{DaemonCommunication, DaemonReply, NodeDropEvent},
+    daemon_to_node::{DaemonReply, NodeDropEvent},
     node_to_daemon::{DaemonRequest, DropToken, Timestamped},
-    DataflowId,
 };
 use eyre::{eyre, Context};
 use flume::RecvTimeoutError;
@@ -16,63 +15,20 @@ pub struct DropStream {
 }
 
 impl DropStream {
-    #[tracing::instrument(level = "trace", skip(hlc))]
+    #[tracing::instrument(level = "trace", skip(reconnector, hlc))]
     pub(crate) fn init(
-        dataflow_id: DataflowId,
         node_id: &NodeId,
-        daemon_communication: &DaemonCommunication,
+        reconnector: Arc<Reconnector>,
         hlc: Arc<uhlc::HLC>,
     ) -> eyre::Result<Self> {
-        let channel = match daemon_communication {
-            DaemonCommunication::Shmem {
-                daemon_drop_region_id,
-                ..
-            } => {
-                unsafe { DaemonChannel::new_shmem(daemon_drop_region_id) }.wrap_err_with(|| {
-                    format!("failed to create shmem drop stream for node `{node_id}`")
-                })?
-            }
-            DaemonCommunication::Tcp { socket_addr } => DaemonChannel::new_tcp(*socket_addr)
-                .wrap_err_with(|| format!("failed to connect drop stream for node `{node_id}`"))?,
-            #[cfg(unix)]
-            DaemonCommunication::UnixDomain { socket_file } => {
-                DaemonChannel::new_unix_socket(socket_file).wrap_err_with(|| {
-                    format!("failed to connect drop stream for node `{node_id}`")
-                })?
-            }
-        };
-
-        Self::init_on_channel(dataflow_id, node_id, channel, hlc)
-    }
-
-    pub fn init_on_channel(
-        dataflow_id: DataflowId,
-        node_id: &NodeId,
-        mut channel: DaemonChannel,
-        clock: Arc<uhlc::HLC>,
-    ) -> eyre::Result<Self> {
-        channel.register(dataflow_id, node_id.clone(), clock.new_timestamp())?;
-
-        let reply = channel
-            .request(&Timestamped {
-                inner: DaemonRequest::SubscribeDrop,
-                timestamp: clock.new_timestamp(),
-            })
-            .map_err(|e| eyre!(e))
-            .wrap_err("failed to create subscription with dora-daemon")?;
-
-        match reply {
-            DaemonReply::Result(Ok(())) => {}
-            DaemonReply::Result(Err(err)) => {
-                eyre::bail!("drop subscribe failed: {err}")
-            }
-            other => eyre::bail!("unexpected drop subscribe reply: {other:?}"),
-        }
+        let mut channel = ReconnectingChannel::connect(reconnector, ChannelKind::Drop)
+            .wrap_err_with(|| format!("failed to connect drop stream for node `{node_id}`"))?;
+        subscribe(&mut channel, &hlc)?;
 
         let (tx, rx) = flume::bounded(0);
         let node_id_cloned = node_id.clone();
 
-        let handle = std::thread::spawn(|| drop_stream_loop(node_id_cloned, tx, channel, clock));
+        let handle = std::thread::spawn(|| drop_stream_loop(node_id_cloned, tx, channel, hlc));
 
         Ok(Self {
             receiver: rx,
@@ -81,6 +37,24 @@ impl DropStream {
     }
 }
 
+fn subscribe(channel: &mut ReconnectingChannel, clock: &uhlc::HLC) -> eyre::Result<()> {
+    let reply = channel
+        .request(&Timestamped {
+            inner: DaemonRequest::SubscribeDrop,
+            timestamp: clock.new_timestamp(),
+        })
+        .map_err(|e| eyre!(e))
+        .wrap_err("failed to create subscription with dora-daemon")?;
+
+    match reply {
+        DaemonReply::Result(Ok(())) => Ok(()),
+        DaemonReply::Result(Err(err)) => {
+            eyre::bail!("drop subscribe failed: {err}")
+        }
+        other => eyre::bail!("unexpected drop subscribe reply: {other:?}"),
+    }
+}
+
 impl std::ops::Deref for DropStream {
     type Target = flume::Receiver<DropToken>;
 
@@ -93,7 +67,7 @@ impl std::ops::Deref for DropStream {
 fn drop_stream_loop(
     node_id: NodeId,
     tx: flume::Sender<DropToken>,
-    mut channel: DaemonChannel,
+    mut channel: ReconnectingChannel,
     clock: Arc<uhlc::HLC>,
 ) {
     'outer: loop {
@@ -118,6 +92,14 @@ fn drop_stream_loop(
             Err(err) => {
                 let err = eyre!(err).wrap_err("failed to receive incoming drop event");
                 tracing::warn!("{err:?}");
+                // the daemon might have been restarted -> try to reconnect
+                let reconnected = channel
+                    .reconnect()
+                    .and_then(|()| subscribe(&mut channel, &clock));
+                if let Err(err) = reconnected {
+                    tracing::error!("drop stream of node `{node_id}` failed: {err:?}");
+                    break;
+                }
                 continue;
             }
         };
@@ -1,9 +1,15 @@
-use crate::{daemon_connection::DaemonChannel, EventStream};
+use crate::{
+    daemon_connection::{DaemonChannel, Reconnector},
+    event_stream::buffer_into_arrow_array,
+    EventStream,
+};
 
+pub use self::callback::CallbackNode;
 use self::{
     arrow_utils::{copy_array_into_sample, required_data_size},
     control_channel::ControlChannel,
     drop_stream::DropStream,
+    topology::NodeTopology,
 };
 use aligned_vec::{AVec, ConstAlign};
 use arrow::array::Array;
@@ -16,16 +22,21 @@ use dora_core::{
 };
 
 use dora_message::{
-    daemon_to_node::{DaemonReply, NodeConfig},
+    daemon_to_node::{DaemonReply, NodeConfig, NodeToken},
     metadata::{ArrowTypeInfo, Metadata, MetadataParameters},
-    node_to_daemon::{DaemonRequest, DataMessage, DropToken, Timestamped},
+    node_to_daemon::{
+        DaemonRequest, DataMessage, DropToken, LogLevel, NodeHealth, OutputMessage, Timestamped,
+        DYNAMIC_NODE_TOKEN_ENV,
+    },
     DataflowId,
 };
 use eyre::{bail, WrapErr};
 use shared_memory_extended::{Shmem, ShmemConf};
 use std::{
-    collections::{BTreeSet, HashMap, VecDeque},
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
+    net::SocketAddr,
     ops::{Deref, DerefMut},
+    ptr::NonNull,
     sync::Arc,
     time::Duration,
 };
@@ -35,8 +46,11 @@ use tracing::{info, warn};
 use dora_tracing::set_up_tracing;
 
 pub mod arrow_utils;
+mod callback;
 mod control_channel;
 mod drop_stream;
+mod numa;
+pub mod topology;
 
 pub const ZERO_COPY_THRESHOLD: usize = 4096;
 
@@ -47,11 +61,14 @@ pub struct DoraNode {
     control_channel: ControlChannel,
     clock: Arc<uhlc::HLC>,
 
-    sent_out_shared_memory: HashMap<DropToken, ShmemHandle>,
+    /// Shared memory regions might also be referenced by arrays returned from
+    /// [`Self::send_output_sample_shared`].
+    sent_out_shared_memory: HashMap<DropToken, Arc<ShmemHandle>>,
     drop_stream: DropStream,
     cache: VecDeque<ShmemHandle>,
 
     dataflow_descriptor: Descriptor,
+    topology: NodeTopology,
     warned_unknown_output: BTreeSet<DataId>,
 }
 
@@ -79,6 +96,10 @@ impl DoraNode {
 
     /// Initiate a node from a dataflow id and a node id.
     ///
+    /// The node authenticates at the daemon with the token in the
+    /// `DORA_DYNAMIC_NODE_TOKEN` environment variable, which must match the
+    /// token that the daemon was started with.
+    ///
     /// ```no_run
     /// use dora_node_api::DoraNode;
     /// use dora_node_api::dora_core::config::NodeId;
@@ -89,14 +110,28 @@ impl DoraNode {
     pub fn init_from_node_id(node_id: NodeId) -> eyre::Result<(Self, EventStream)> {
         // Make sure that the node is initialized outside of dora start.
         let daemon_address = (LOCALHOST, DORA_DAEMON_LOCAL_LISTEN_PORT_DEFAULT).into();
+        let token = NodeToken::from_env(DYNAMIC_NODE_TOKEN_ENV);
+        Self::init_dynamic(node_id, daemon_address, token)
+    }
 
+    /// Initiate a dynamic node through the local listener of the daemon at
+    /// the given address.
+    ///
+    /// Like [`Self::init_from_node_id`], but for daemons that don't listen on
+    /// the default port. The daemon only hands out the node config if the
+    /// given `token` matches its dynamic node token.
+    pub fn init_dynamic(
+        node_id: NodeId,
+        daemon_address: SocketAddr,
+        token: Option<NodeToken>,
+    ) -> eyre::Result<(Self, EventStream)> {
         let mut channel =
             DaemonChannel::new_tcp(daemon_address).context("Could not connect to the daemon")?;
         let clock = Arc::new(uhlc::HLC::default());
 
         let reply = channel
             .request(&Timestamped {
-                inner: DaemonRequest::NodeConfig { node_id },
+                inner: DaemonRequest::NodeConfig { node_id, token },
                 timestamp: clock.new_timestamp(),
             })
             .wrap_err("failed to request node config from daemon")?;
@@ -120,6 +155,13 @@ impl DoraNode {
         }
     }
 
+    /// Initiate a node from the given node config.
+    ///
+    /// If the connection to the daemon breaks, e.g. because the daemon was
+    /// restarted, the node waits up to 30 seconds for the daemon to re-adopt it
+    /// and then reconnects automatically. Outputs that were being sent when the
+    /// connection broke are not sent again, their `send_output` call returns an
+    /// error instead.
     #[tracing::instrument]
     pub fn init(node_config: NodeConfig) -> eyre::Result<(Self, EventStream)> {
         let NodeConfig {
@@ -127,26 +169,34 @@ impl DoraNode {
             node_id,
             run_config,
             daemon_communication,
+            token,
             dataflow_descriptor,
             dynamic: _,
         } = node_config;
         let clock = Arc::new(uhlc::HLC::default());
         let input_config = run_config.inputs.clone();
-
-        let event_stream = EventStream::init(
+        // the topology is informational, so the node still starts without it
+        let topology = NodeTopology::from_descriptor(&dataflow_descriptor, &node_id)
+            .unwrap_or_else(|err| {
+                warn!("failed to determine node topology: {err:?}");
+                NodeTopology::empty()
+            });
+
+        let reconnector = Reconnector::new(
             dataflow_id,
-            &node_id,
-            &daemon_communication,
-            input_config,
+            node_id.clone(),
+            daemon_communication,
+            token,
             clock.clone(),
-        )
-        .wrap_err("failed to init event stream")?;
-        let drop_stream =
-            DropStream::init(dataflow_id, &node_id, &daemon_communication, clock.clone())
-                .wrap_err("failed to init drop stream")?;
-        let control_channel =
-            ControlChannel::init(dataflow_id, &node_id, &daemon_communication, clock.clone())
-                .wrap_err("failed to init control channel")?;
+        );
+
+        let event_stream =
+            EventStream::init(&node_id, reconnector.clone(), input_config, clock.clone())
+                .wrap_err("failed to init event stream")?;
+        let drop_stream = DropStream::init(&node_id, reconnector.clone(), clock.clone())
+            .wrap_err("failed to init drop stream")?;
+        let control_channel = ControlChannel::init(reconnector, clock.clone())
+            .wrap_err("failed to init control channel")?;
 
         let node = Self {
             id: node_id,
@@ -158,6 +208,7 @@ impl DoraNode {
             drop_stream,
             cache: VecDeque::new(),
             dataflow_descriptor,
+            topology,
             warned_unknown_output: BTreeSet::new(),
         };
         Ok((node, event_stream))
@@ -219,6 +270,24 @@ impl DoraNode {
         self.send_output_sample(output_id, type_info, parameters, Some(sample))
     }
 
+    /// Send the given arrow array as output.
+    ///
+    /// The `parameters` are forwarded to the receivers as part of the input
+    /// metadata. To pass on the original sensor time of the data, set it
+    /// through [`MetadataParametersExt::set_acquisition_time`](crate::MetadataParametersExt::set_acquisition_time):
+    ///
+    /// ```no_run
+    /// use dora_node_api::{arrow::array::UInt8Array, DoraNode, MetadataParameters, MetadataParametersExt};
+    ///
+    /// let (mut node, mut events) = DoraNode::init_from_env().expect("Could not init node.");
+    ///
+    /// let frame = UInt8Array::from(vec![0u8; 640 * 480]);
+    /// let mut parameters = MetadataParameters::default();
+    /// parameters.set_acquisition_time(std::time::SystemTime::now());
+    ///
+    /// node.send_output("image".to_owned().into(), parameters, frame)
+    ///     .expect("Could not send output");
+    /// ```
     pub fn send_output(
         &mut self,
         output_id: DataId,
@@ -294,6 +363,48 @@ impl DoraNode {
             None => (None, None),
         };
 
+        self.send_finalized_sample(output_id, metadata, data, shmem)
+    }
+
+    /// Sends the given sample like [`send_output_sample`](Self::send_output_sample)
+    /// and returns it as arrow array, together with the metadata that was
+    /// sent.
+    ///
+    /// This is meant for passing the output on to receivers in the same
+    /// process, e.g. to other operators of a runtime node. The returned array
+    /// shares the memory of the sample instead of copying it. Shared memory
+    /// regions are not reused for other outputs while the array or any clone
+    /// of it is alive.
+    pub fn send_output_sample_shared(
+        &mut self,
+        output_id: DataId,
+        type_info: ArrowTypeInfo,
+        parameters: MetadataParameters,
+        sample: Option<DataSample>,
+    ) -> eyre::Result<(Metadata, arrow::array::ArrayData)> {
+        self.handle_finished_drop_tokens()?;
+
+        let (data, shmem, array) = match sample {
+            Some(sample) => sample.finalize_shared(&type_info)?,
+            None => (
+                None,
+                None,
+                arrow::array::ArrayData::new_empty(&type_info.data_type),
+            ),
+        };
+        let metadata = Metadata::from_parameters(self.clock.new_timestamp(), type_info, parameters);
+
+        self.send_finalized_sample(output_id, metadata.clone(), data, shmem)?;
+        Ok((metadata, array))
+    }
+
+    fn send_finalized_sample(
+        &mut self,
+        output_id: DataId,
+        metadata: Metadata,
+        data: Option<DataMessage>,
+        shmem: Option<(Arc<ShmemHandle>, DropToken)>,
+    ) -> eyre::Result<()> {
         self.control_channel
             .send_message(output_id.clone(), metadata, data)
             .wrap_err_with(|| format!("failed to send output {output_id}"))?;
@@ -306,6 +417,76 @@ impl DoraNode {
         Ok(())
     }
 
+    /// Sends multiple outputs as a single batch.
+    ///
+    /// All messages get the same timestamp and each receiver gets its inputs
+    /// of the batch as a unit: they are queued and dropped together and the
+    /// receiver gets one [`Event::Input`](crate::Event::Input) per input
+    /// back-to-back, in the order of the outputs, without other events in
+    /// between. A batch takes a single slot of the queue of its first input.
+    /// This is useful for outputs that belong together, such as an image and
+    /// the corresponding camera info.
+    ///
+    /// Outputs that are not declared in the dataflow are ignored, as in
+    /// [`send_output`](Self::send_output). Use an
+    /// [`ArrayRef`](arrow::array::ArrayRef) as data type to send arrays of
+    /// different types.
+    ///
+    /// ```no_run
+    /// use std::sync::Arc;
+    /// use dora_node_api::{arrow::array::{ArrayRef, Float32Array, UInt8Array}, DoraNode, MetadataParameters};
+    ///
+    /// let (mut node, mut events) = DoraNode::init_from_env().expect("Could not init node.");
+    ///
+    /// let image: ArrayRef = Arc::new(UInt8Array::from(vec![0u8; 640 * 480]));
+    /// let camera_info: ArrayRef = Arc::new(Float32Array::from(vec![500.0, 500.0, 320.0, 240.0]));
+    /// node.send_outputs([
+    ///     ("image".to_owned().into(), MetadataParameters::default(), image),
+    ///     ("camera_info".to_owned().into(), MetadataParameters::default(), camera_info),
+    /// ])
+    /// .expect("Could not send outputs");
+    /// ```
+    pub fn send_outputs<A: Array>(
+        &mut self,
+        outputs: impl IntoIterator<Item = (DataId, MetadataParameters, A)>,
+    ) -> eyre::Result<()> {
+        self.handle_finished_drop_tokens()?;
+
+        let timestamp = self.clock.new_timestamp();
+        let mut messages = Vec::new();
+        let mut shared_memory = Vec::new();
+        for (output_id, parameters, data) in outputs {
+            if !self.validate_output(&output_id) {
+                continue;
+            }
+            let arrow_array = data.to_data();
+            let mut sample = self.allocate_data_sample(required_data_size(&arrow_array))?;
+            let type_info = copy_array_into_sample(&mut sample, &arrow_array);
+
+            let (data, shmem) = sample.finalize();
+            messages.push(OutputMessage {
+                output_id,
+                metadata: Metadata::from_parameters(timestamp, type_info, parameters),
+                data,
+            });
+            shared_memory.extend(shmem);
+        }
+        if messages.is_empty() {
+            return Ok(());
+        }
+
+        self.control_channel
+            .send_messages(messages)
+            .wrap_err("failed to send outputs")?;
+
+        for (shared_memory, drop_token) in shared_memory {
+            self.sent_out_shared_memory
+                .insert(drop_token, shared_memory);
+        }
+
+        Ok(())
+    }
+
     pub fn close_outputs(&mut self, outputs: Vec<DataId>) -> eyre::Result<()> {
         for output_id in &outputs {
             if !self.node_config.outputs.remove(output_id) {
@@ -320,6 +501,282 @@ impl DoraNode {
         Ok(())
     }
 
+    /// Requests that the whole dataflow is stopped.
+    ///
+    /// The request is routed through the daemon to the coordinator, which then sends a
+    /// stop event to all nodes of the dataflow (including this one). The given `reason`
+    /// is forwarded to the dataflow logs.
+    ///
+    /// ```no_run
+    /// use dora_node_api::DoraNode;
+    ///
+    /// let (mut node, mut events) = DoraNode::init_from_env().expect("Could not init node.");
+    ///
+    /// node.stop_dataflow("mission complete").expect("Could not request dataflow stop");
+    /// ```
+    pub fn stop_dataflow(&mut self, reason: impl Into<String>) -> eyre::Result<()> {
+        self.control_channel
+            .request_dataflow_stop(reason.into())
+            .wrap_err("failed to request dataflow stop")
+    }
+
+    /// Sends a structured log record to the daemon.
+    ///
+    /// Unlike printing to stdout, the record keeps its level and fields. The daemon
+    /// tags it with the dataflow and node ID, stores it in the node's log file (see
+    /// `dora logs`), and forwards it to the coordinator. Records that exceed the
+    /// daemon's rate limit are dropped.
+    ///
+    /// ```no_run
+    /// use std::collections::BTreeMap;
+    /// use dora_node_api::{DoraNode, LogLevel};
+    ///
+    /// let (mut node, mut events) = DoraNode::init_from_env().expect("Could not init node.");
+    ///
+    /// let fields = BTreeMap::from([("frame".to_owned(), "42".to_owned())]);
+    /// node.log(LogLevel::Warn, "dropped frame", fields).expect("Could not send log");
+    /// ```
+    pub fn log(
+        &mut self,
+        level: LogLevel,
+        message: impl Into<String>,
+        fields: BTreeMap<String, String>,
+    ) -> eyre::Result<()> {
+        self.control_channel
+            .send_log(level, None, message.into(), fields)
+            .wrap_err("failed to send log record to daemon")
+    }
+
+    /// Reports the application-level health of this node to the daemon.
+    ///
+    /// Each report replaces the previous one. The reports of all nodes are aggregated
+    /// into the dataflow health summary, which is available through `dora health`.
+    ///
+    /// ```no_run
+    /// use dora_node_api::{DoraNode, HealthStatus, NodeHealth};
+    ///
+    /// let (mut node, mut events) = DoraNode::init_from_env().expect("Could not init node.");
+    ///
+    /// let mut health = NodeHealth {
+    ///     status: HealthStatus::Degraded,
+    ///     message: Some("camera frame rate below target".into()),
+    ///     ..Default::default()
+    /// };
+    /// health.counters.insert("frames".into(), 1024);
+    /// health.gauges.insert("fps".into(), 12.5);
+    /// node.report_health(health).expect("Could not report health");
+    /// ```
+    pub fn report_health(&mut self, health: NodeHealth) -> eyre::Result<()> {
+        self.control_channel
+            .report_health(health)
+            .wrap_err("failed to report node health")
+    }
+
+    /// Stores the given state of the node for dataflow checkpoints.
+    ///
+    /// The daemon keeps only the most recent state of each node. When a
+    /// checkpoint is created through `dora checkpoint`, the stored state is
+    /// written to the checkpoint together with the inputs that are queued for
+    /// the node. Nodes should store their state whenever it is consistent,
+    /// e.g. after handling an input.
+    ///
+    /// ```no_run
+    /// use dora_node_api::{DoraNode, Event};
+    ///
+    /// let (mut node, mut events) = DoraNode::init_from_env().expect("Could not init node.");
+    ///
+    /// let mut count: u64 = node
+    ///     .restored_state()
+    ///     .expect("Could not get restored state")
+    ///     .and_then(|state| state.try_into().ok())
+    ///     .map(u64::from_le_bytes)
+    ///     .unwrap_or_default();
+    /// while let Some(event) = events.recv() {
+    ///     if let Event::Input { .. } = event {
+    ///         count += 1;
+    ///         node.save_state(count.to_le_bytes().to_vec())
+    ///             .expect("Could not save state");
+    ///     }
+    /// }
+    /// ```
+    pub fn save_state(&mut self, state: Vec<u8>) -> eyre::Result<()> {
+        self.control_channel
+            .save_state(state)
+            .wrap_err("failed to save node state")
+    }
+
+    /// Returns the state that the node stored through
+    /// [`save_state`](Self::save_state) before the checkpoint that the dataflow
+    /// was restored from.
+    ///
+    /// Returns `None` if the dataflow was not restored from a checkpoint or if
+    /// the node didn't store a state.
+    pub fn restored_state(&mut self) -> eyre::Result<Option<Vec<u8>>> {
+        self.control_channel
+            .restored_state()
+            .wrap_err("failed to get restored node state")
+    }
+
+    /// Returns the value of the given key in the blackboard of the dataflow.
+    ///
+    /// The blackboard is a key-value store for small state that is shared by
+    /// all nodes of the dataflow, e.g. the current mission ID or calibration
+    /// values. Each daemon keeps a replica of it, so reads are answered
+    /// locally and see the writes of other machines after a short delay.
+    ///
+    /// ```no_run
+    /// use dora_node_api::DoraNode;
+    ///
+    /// let (mut node, _events) = DoraNode::init_from_env().expect("Could not init node.");
+    ///
+    /// node.blackboard_set("mission_id", b"survey-7".to_vec())
+    ///     .expect("Could not write to blackboard");
+    /// let mission_id = node
+    ///     .blackboard_get("mission_id")
+    ///     .expect("Could not read from blackboard");
+    /// assert_eq!(mission_id.as_deref(), Some(&b"survey-7"[..]));
+    /// ```
+    pub fn blackboard_get(&mut self, key: impl Into<String>) -> eyre::Result<Option<Vec<u8>>> {
+        self.control_channel
+            .blackboard_get(key.into())
+            .wrap_err("failed to read from blackboard")
+    }
+
+    /// Sets the value of the given key in the blackboard of the dataflow.
+    ///
+    /// Concurrent writes to the same key from different machines are ordered
+    /// by the coordinator, the last write wins on all machines.
+    pub fn blackboard_set(&mut self, key: impl Into<String>, value: Vec<u8>) -> eyre::Result<()> {
+        self.control_channel
+            .blackboard_set(key.into(), Some(value))
+            .wrap_err("failed to write to blackboard")
+    }
+
+    /// Removes the given key from the blackboard of the dataflow.
+    pub fn blackboard_remove(&mut self, key: impl Into<String>) -> eyre::Result<()> {
+        self.control_channel
+            .blackboard_set(key.into(), None)
+            .wrap_err("failed to remove key from blackboard")
+    }
+
+    /// Subscribes to changes of all blackboard keys that start with the given
+    /// prefix, e.g. `""` for all keys.
+    ///
+    /// Changes are delivered as [`Event::BlackboardChanged`] events, including
+    /// the changes made by this node.
+    ///
+    /// [`Event::BlackboardChanged`]: crate::Event::BlackboardChanged
+    pub fn blackboard_watch(&mut self, prefix: impl Into<String>) -> eyre::Result<()> {
+        self.control_channel
+            .blackboard_watch(prefix.into())
+            .wrap_err("failed to watch blackboard")
+    }
+
+    /// Becomes a candidate in the leader election of the given group.
+    ///
+    /// Redundant replicas of a node, e.g. two controllers on different
+    /// machines, campaign in the same group so that exactly one of them is
+    /// active at a time while the others stay on hot standby. The leader is
+    /// the candidate that campaigned first. When it exits, resigns, or its
+    /// machine loses the connection to the coordinator, the next candidate
+    /// takes over.
+    ///
+    /// The node is notified of the current leader and of every change through
+    /// [`Event::LeadershipChanged`] events. Standby replicas should keep
+    /// processing their inputs, but not act on them, so that they can take
+    /// over without delay.
+    ///
+    /// ```no_run
+    /// use dora_node_api::{DoraNode, Event};
+    ///
+    /// let (mut node, mut events) = DoraNode::init_from_env().expect("Could not init node.");
+    /// node.campaign("controller").expect("Could not join election");
+    ///
+    /// let mut active = false;
+    /// while let Some(event) = events.recv() {
+    ///     match event {
+    ///         Event::LeadershipChanged { is_leader, .. } => active = is_leader,
+    ///         Event::Input { .. } if active => {
+    ///             // send commands
+    ///         }
+    ///         _ => {}
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// [`Event::LeadershipChanged`]: crate::Event::LeadershipChanged
+    pub fn campaign(&mut self, group: impl Into<String>) -> eyre::Result<()> {
+        self.control_channel
+            .campaign(group.into())
+            .wrap_err("failed to join leader election")
+    }
+
+    /// Stops being a candidate in the leader election of the given group.
+    ///
+    /// If the node is the current leader, the next candidate takes over.
+    pub fn resign(&mut self, group: impl Into<String>) -> eyre::Result<()> {
+        self.control_channel
+            .resign(group.into())
+            .wrap_err("failed to leave leader election")
+    }
+
+    /// Subscribes to the stdout and stderr output of the given nodes, or of
+    /// all other nodes if `nodes` is empty.
+    ///
+    /// The output is delivered as [`Event::NodeOutput`] events, e.g. for
+    /// nodes that monitor or display the logs of the dataflow. Only the
+    /// output of nodes that run on the same machine is delivered. The
+    /// output of this node itself is never delivered to it, to avoid
+    /// feedback loops.
+    ///
+    /// The captured output is also written to the log files of the nodes,
+    /// which `dora logs` reads.
+    ///
+    /// [`Event::NodeOutput`]: crate::Event::NodeOutput
+    pub fn subscribe_node_output(
+        &mut self,
+        nodes: impl IntoIterator<Item = NodeId>,
+    ) -> eyre::Result<()> {
+        self.control_channel
+            .subscribe_node_output(nodes.into_iter().collect())
+            .wrap_err("failed to subscribe to node output")
+    }
+
+    /// Returns the current time of the node's hybrid logical clock (HLC).
+    ///
+    /// The clock is updated with the timestamps of all messages received from
+    /// the daemon, so the returned timestamp is consistent with the timestamps
+    /// of the input metadata.
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use dora_node_api::{DoraNode, Event};
+    ///
+    /// let (node, mut events) = DoraNode::init_from_env().expect("Could not init node.");
+    ///
+    /// while let Some(event) = events.recv() {
+    ///     if let Event::Input { metadata, .. } = event {
+    ///         if node.is_stale(&metadata, Duration::from_millis(100)) {
+    ///             continue; // skip outdated messages
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn now(&self) -> uhlc::Timestamp {
+        self.clock.new_timestamp()
+    }
+
+    /// Returns how long ago the message with the given metadata was sent,
+    /// according to [`now`](Self::now).
+    pub fn age(&self, metadata: &Metadata) -> Duration {
+        metadata.age_at(self.now())
+    }
+
+    /// Returns whether the message with the given metadata is older than `max_age`.
+    pub fn is_stale(&self, metadata: &Metadata, max_age: Duration) -> bool {
+        self.age(metadata) > max_age
+    }
+
     pub fn id(&self) -> &NodeId {
         &self.id
     }
@@ -364,13 +821,15 @@ impl DoraNode {
                 // we know that this index exists, so we can safely unwrap here
                 self.cache.remove(i).unwrap()
             }
-            None => ShmemHandle(Box::new(
-                ShmemConf::new()
+            None => {
+                let memory = ShmemConf::new()
                     .size(data_len)
                     .writable(true)
                     .create()
-                    .wrap_err("failed to allocate shared memory")?,
-            )),
+                    .wrap_err("failed to allocate shared memory")?;
+                numa::prefer_local_node(memory.as_ptr(), memory.len());
+                ShmemHandle(Box::new(memory))
+            }
         };
         assert!(memory.len() >= data_len);
 
@@ -381,7 +840,13 @@ impl DoraNode {
         loop {
             match self.drop_stream.try_recv() {
                 Ok(token) => match self.sent_out_shared_memory.remove(&token) {
-                    Some(region) => self.add_to_cache(region),
+                    Some(region) => {
+                        // regions that are still used in this process are freed
+                        // once their last reference is dropped
+                        if let Ok(region) = Arc::try_unwrap(region) {
+                            self.add_to_cache(region);
+                        }
+                    }
                     None => tracing::warn!("received unknown finished drop token `{token:?}`"),
                 },
                 Err(flume::TryRecvError::Empty) => break,
@@ -408,6 +873,26 @@ impl DoraNode {
     pub fn dataflow_descriptor(&self) -> &Descriptor {
         &self.dataflow_descriptor
     }
+
+    /// Returns the position of this node in the dataflow, i.e. its resolved
+    /// descriptor entry, its inputs and outputs, and its upstream and
+    /// downstream peers.
+    ///
+    /// ```no_run
+    /// use dora_node_api::DoraNode;
+    ///
+    /// let (node, _events) = DoraNode::init_from_env().expect("Could not init node.");
+    ///
+    /// for output in node.topology().outputs() {
+    ///     let receivers = node.topology().downstream(output).into_iter().flatten();
+    ///     for peer in receivers {
+    ///         println!("{output} -> {}/{}", peer.node_id, peer.input_id);
+    ///     }
+    /// }
+    /// ```
+    pub fn topology(&self) -> &NodeTopology {
+        &self.topology
+    }
 }
 
 impl Drop for DoraNode {
@@ -469,20 +954,66 @@ pub struct DataSample {
 }
 
 impl DataSample {
-    fn finalize(self) -> (Option<DataMessage>, Option<(ShmemHandle, DropToken)>) {
+    fn finalize(self) -> (Option<DataMessage>, Option<(Arc<ShmemHandle>, DropToken)>) {
         match self.inner {
             DataSampleInner::Shmem(shared_memory) => {
-                let drop_token = DropToken::generate();
-                let data = DataMessage::SharedMemory {
-                    shared_memory_id: shared_memory.get_os_id().to_owned(),
-                    len: self.len,
-                    drop_token,
-                };
+                let shared_memory = Arc::new(shared_memory);
+                let (data, drop_token) = shared_memory_message(&shared_memory, self.len);
                 (Some(data), Some((shared_memory, drop_token)))
             }
             DataSampleInner::Vec(buffer) => (Some(DataMessage::Vec(buffer)), None),
         }
     }
+
+    /// Like [`Self::finalize`], but additionally returns an arrow array that
+    /// shares the memory of the sample.
+    #[allow(clippy::type_complexity)]
+    fn finalize_shared(
+        self,
+        type_info: &ArrowTypeInfo,
+    ) -> eyre::Result<(
+        Option<DataMessage>,
+        Option<(Arc<ShmemHandle>, DropToken)>,
+        arrow::array::ArrayData,
+    )> {
+        let (data, shmem, buffer) = match self.inner {
+            DataSampleInner::Shmem(shared_memory) => {
+                let shared_memory = Arc::new(shared_memory);
+                let (data, drop_token) = shared_memory_message(&shared_memory, self.len);
+                let ptr = NonNull::new(shared_memory.as_ptr()).unwrap();
+                let buffer = unsafe {
+                    arrow::buffer::Buffer::from_custom_allocation(
+                        ptr,
+                        self.len,
+                        shared_memory.clone(),
+                    )
+                };
+                (Some(data), Some((shared_memory, drop_token)), buffer)
+            }
+            DataSampleInner::Vec(buffer) => {
+                // small samples are sent inline, so the message needs its own
+                // copy; the local receivers keep the original allocation
+                let data = DataMessage::Vec(AVec::from_slice(128, &buffer[..self.len]));
+                let ptr = NonNull::new(buffer.as_ptr() as *mut u8).unwrap();
+                let buffer = unsafe {
+                    arrow::buffer::Buffer::from_custom_allocation(ptr, self.len, Arc::new(buffer))
+                };
+                (Some(data), None, buffer)
+            }
+        };
+        let array = buffer_into_arrow_array(&buffer, type_info)?;
+        Ok((data, shmem, array))
+    }
+}
+
+fn shared_memory_message(shared_memory: &ShmemHandle, len: usize) -> (DataMessage, DropToken) {
+    let drop_token = DropToken::generate();
+    let data = DataMessage::SharedMemory {
+        shared_memory_id: shared_memory.get_os_id().to_owned(),
+        len,
+        drop_token,
+    };
+    (data, drop_token)
 }
 
 impl Deref for DataSample {
@@ -552,3 +1083,64 @@ impl DerefMut for ShmemHandle {
 
 unsafe impl Send for ShmemHandle {}
 unsafe impl Sync for ShmemHandle {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_memory_sample_is_shared_with_local_receivers() {
+        let memory = ShmemConf::new()
+            .size(ZERO_COPY_THRESHOLD)
+            .writable(true)
+            .create()
+            .unwrap();
+        let mut sample = DataSample {
+            inner: DataSampleInner::Shmem(ShmemHandle(Box::new(memory))),
+            len: ZERO_COPY_THRESHOLD,
+        };
+        sample.fill(42);
+        let ptr = sample.as_ptr();
+
+        let (data, shmem, array) = sample
+            .finalize_shared(&ArrowTypeInfo::byte_array(ZERO_COPY_THRESHOLD))
+            .unwrap();
+        let (region, _) = shmem.unwrap();
+        match data {
+            Some(DataMessage::SharedMemory {
+                shared_memory_id,
+                len,
+                ..
+            }) => {
+                assert_eq!(shared_memory_id, region.get_os_id());
+                assert_eq!(len, ZERO_COPY_THRESHOLD);
+            }
+            other => panic!("expected shared memory message, got {other:?}"),
+        }
+        assert_eq!(array.buffers()[0].as_ptr(), ptr);
+        assert!(array.buffers()[0].iter().all(|&b| b == 42));
+
+        // the region can't be reused while the array is alive
+        assert_eq!(Arc::strong_count(&region), 2);
+        drop(array);
+        assert!(Arc::try_unwrap(region).is_ok());
+    }
+
+    #[test]
+    fn vec_sample_is_shared_with_local_receivers() {
+        let mut sample = DataSample::from(AVec::__from_elem(128, 0, 16));
+        sample.copy_from_slice(&[7; 16]);
+        let ptr = sample.as_ptr();
+
+        let (data, shmem, array) = sample
+            .finalize_shared(&ArrowTypeInfo::byte_array(16))
+            .unwrap();
+        assert!(shmem.is_none());
+        match data {
+            Some(DataMessage::Vec(data)) => assert_eq!(&data[..], &[7; 16]),
+            other => panic!("expected vec message, got {other:?}"),
+        }
+        assert_eq!(array.buffers()[0].as_ptr(), ptr);
+        assert_eq!(array.buffers()[0].as_slice(), &[7; 16]);
+    }
+}
@@ -0,0 +1,79 @@
+//! Placement of shared memory on the NUMA node of the sending process.
+
+/// Binds the memory region to the NUMA node that the current thread runs on.
+///
+/// The pages of a shared memory region are allocated when they are first
+/// written. Without a binding, they might be allocated on another NUMA node
+/// if the process is migrated in the meantime, so that all accesses of the
+/// sender and of the receivers on its NUMA node cross the socket boundary.
+///
+/// This is a no-op on machines with a single NUMA node. Failures are ignored,
+/// as they only affect the performance.
+#[cfg(target_os = "linux")]
+pub fn prefer_local_node(region: *mut u8, len: usize) {
+    use std::sync::OnceLock;
+
+    const MPOL_PREFERRED: libc::c_ulong = 1;
+
+    static MULTIPLE_NODES: OnceLock<bool> = OnceLock::new();
+    let multiple_nodes = *MULTIPLE_NODES.get_or_init(|| {
+        std::fs::read_dir("/sys/devices/system/node")
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .filter(|e| {
+                        let name = e.file_name();
+                        let name = name.to_string_lossy();
+                        name.strip_prefix("node")
+                            .is_some_and(|n| n.chars().all(|c| c.is_ascii_digit()))
+                    })
+                    .count()
+                    > 1
+            })
+            .unwrap_or(false)
+    });
+    if !multiple_nodes || len == 0 {
+        return;
+    }
+
+    let mut cpu: libc::c_uint = 0;
+    let mut node: libc::c_uint = 0;
+    // SAFETY: `getcpu` writes to the given valid pointers only
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_getcpu,
+            &mut cpu as *mut libc::c_uint,
+            &mut node as *mut libc::c_uint,
+            std::ptr::null_mut::<libc::c_void>(),
+        )
+    };
+    if result != 0 {
+        return;
+    }
+
+    let bits = libc::c_ulong::BITS as usize;
+    let mut node_mask: Vec<libc::c_ulong> = vec![0; node as usize / bits + 1];
+    node_mask[node as usize / bits] |= 1 << (node as usize % bits);
+    // SAFETY: the region is a mapping of the given length and the node mask
+    // contains the given number of bits
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_mbind,
+            region,
+            len as libc::c_ulong,
+            MPOL_PREFERRED,
+            node_mask.as_ptr(),
+            (node_mask.len() * bits) as libc::c_ulong,
+            0 as libc::c_uint,
+        )
+    };
+    if result != 0 {
+        tracing::debug!(
+            "failed to bind shared memory to NUMA node {node}: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn prefer_local_node(_region: *mut u8, _len: usize) {}
@@ -0,0 +1,290 @@
+//! Read-only view of the dataflow graph around a node.
+//!
+//! Generic nodes such as recorders, bridges, or visualizers can use this to
+//! configure themselves from the dataflow descriptor instead of duplicating
+//! the input and output lists of the YAML file.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use dora_core::{
+    config::{DataId, Input, InputMapping, NodeId, UserInputMapping},
+    descriptor::{CoreNodeKind, Descriptor, DescriptorExt, ResolvedNode},
+};
+use eyre::{Context, ContextCompat};
+use serde::Serialize;
+
+/// The position of a node in its dataflow.
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeTopology {
+    node: Option<ResolvedNode>,
+    inputs: BTreeMap<DataId, Input>,
+    outputs: BTreeSet<DataId>,
+    downstream: BTreeMap<DataId, BTreeSet<InputPeer>>,
+}
+
+/// An input of another node that is connected to one of our outputs.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub struct InputPeer {
+    pub node_id: NodeId,
+    pub input_id: DataId,
+}
+
+impl NodeTopology {
+    pub(crate) fn from_descriptor(descriptor: &Descriptor, node_id: &NodeId) -> eyre::Result<Self> {
+        let nodes = descriptor
+            .resolve_aliases_and_set_defaults()
+            .wrap_err("failed to resolve dataflow descriptor")?;
+
+        let mut downstream: BTreeMap<DataId, BTreeSet<InputPeer>> = BTreeMap::new();
+        for node in &nodes {
+            for (input_id, input) in node_inputs(node) {
+                if let InputMapping::User(UserInputMapping { source, output }) = input.mapping {
+                    if &source == node_id {
+                        downstream.entry(output).or_default().insert(InputPeer {
+                            node_id: node.id.clone(),
+                            input_id,
+                        });
+                    }
+                }
+            }
+        }
+
+        let node = nodes
+            .into_iter()
+            .find(|n| &n.id == node_id)
+            .wrap_err_with(|| format!("node `{node_id}` not found in dataflow descriptor"))?;
+        let inputs = node_inputs(&node);
+        let outputs = node_outputs(&node);
+        for output in &outputs {
+            downstream.entry(output.clone()).or_default();
+        }
+
+        Ok(Self {
+            node: Some(node),
+            inputs,
+            outputs,
+            downstream,
+        })
+    }
+
+    /// Topology of a node whose dataflow descriptor couldn't be resolved.
+    pub(crate) fn empty() -> Self {
+        Self {
+            node: None,
+            inputs: BTreeMap::new(),
+            outputs: BTreeSet::new(),
+            downstream: BTreeMap::new(),
+        }
+    }
+
+    /// The resolved descriptor entry of this node.
+    ///
+    /// Returns `None` if the dataflow descriptor couldn't be resolved.
+    pub fn resolved_node(&self) -> Option<&ResolvedNode> {
+        self.node.as_ref()
+    }
+
+    /// The declared inputs of this node, including their mapping and queue size.
+    ///
+    /// Inputs of runtime nodes are prefixed with their operator ID, e.g. `op/input`.
+    pub fn inputs(&self) -> &BTreeMap<DataId, Input> {
+        &self.inputs
+    }
+
+    /// The declared outputs of this node.
+    ///
+    /// Outputs of runtime nodes are prefixed with their operator ID, e.g. `op/output`.
+    pub fn outputs(&self) -> &BTreeSet<DataId> {
+        &self.outputs
+    }
+
+    /// The nodes that this node receives inputs from, excluding `dora` inputs.
+    pub fn upstream_nodes(&self) -> BTreeSet<&NodeId> {
+        self.inputs
+            .values()
+            .filter_map(|input| match &input.mapping {
+                InputMapping::User(mapping) => Some(&mapping.source),
+                InputMapping::Timer { .. } | InputMapping::Status => None,
+            })
+            .collect()
+    }
+
+    /// The inputs of other nodes that are connected to the given output.
+    ///
+    /// Returns `None` if the output is not declared by this node.
+    pub fn downstream(&self, output_id: &DataId) -> Option<&BTreeSet<InputPeer>> {
+        self.downstream.get(output_id)
+    }
+
+    /// The nodes that receive at least one output of this node.
+    pub fn downstream_nodes(&self) -> BTreeSet<&NodeId> {
+        self.downstream
+            .values()
+            .flatten()
+            .map(|peer| &peer.node_id)
+            .collect()
+    }
+}
+
+fn node_inputs(node: &ResolvedNode) -> BTreeMap<DataId, Input> {
+    match &node.kind {
+        CoreNodeKind::Custom(n) => n.run_config.inputs.clone(),
+        CoreNodeKind::Runtime(n) => n
+            .operators
+            .iter()
+            .flat_map(|operator| {
+                operator.config.inputs.iter().map(|(input_id, input)| {
+                    (
+                        DataId::from(format!("{}/{input_id}", operator.id)),
+                        input.clone(),
+                    )
+                })
+            })
+            .collect(),
+    }
+}
+
+fn node_outputs(node: &ResolvedNode) -> BTreeSet<DataId> {
+    match &node.kind {
+        CoreNodeKind::Custom(n) => n.run_config.outputs.clone(),
+        CoreNodeKind::Runtime(n) => n
+            .operators
+            .iter()
+            .flat_map(|operator| {
+                operator
+                    .config
+                    .outputs
+                    .iter()
+                    .map(|output_id| DataId::from(format!("{}/{output_id}", operator.id)))
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATAFLOW: &str = r#"
+nodes:
+  - id: camera
+    path: camera
+    inputs:
+      tick: dora/timer/millis/100
+    outputs:
+      - image
+      - depth
+  - id: detector
+    path: detector
+    inputs:
+      image: camera/image
+    outputs:
+      - boxes
+  - id: plot
+    operators:
+      - id: op
+        python: plot.py
+        inputs:
+          image: camera/image
+          boxes: detector/boxes
+        outputs:
+          - frame
+"#;
+
+    fn topology(node_id: &str) -> eyre::Result<NodeTopology> {
+        let descriptor = Descriptor::parse(DATAFLOW.as_bytes().to_vec()).unwrap();
+        NodeTopology::from_descriptor(&descriptor, &NodeId::from(node_id.to_owned()))
+    }
+
+    fn ids<'a>(ids: impl IntoIterator<Item = &'a NodeId>) -> Vec<&'a str> {
+        ids.into_iter().map(|id| id.as_ref()).collect()
+    }
+
+    fn peer(node_id: &str, input_id: &str) -> InputPeer {
+        InputPeer {
+            node_id: NodeId::from(node_id.to_owned()),
+            input_id: DataId::from(input_id.to_owned()),
+        }
+    }
+
+    #[test]
+    fn declared_inputs_and_outputs() {
+        let topology = topology("detector").unwrap();
+
+        assert_eq!(topology.resolved_node().unwrap().id.as_ref(), "detector");
+        let inputs: Vec<_> = topology.inputs().keys().map(|id| id.as_str()).collect();
+        assert_eq!(inputs, ["image"]);
+        let outputs: Vec<_> = topology.outputs().iter().map(|id| id.as_str()).collect();
+        assert_eq!(outputs, ["boxes"]);
+    }
+
+    #[test]
+    fn runtime_node_ids_are_prefixed_with_operator() {
+        let topology = topology("plot").unwrap();
+
+        let inputs: Vec<_> = topology.inputs().keys().map(|id| id.as_str()).collect();
+        assert_eq!(inputs, ["op/boxes", "op/image"]);
+        let outputs: Vec<_> = topology.outputs().iter().map(|id| id.as_str()).collect();
+        assert_eq!(outputs, ["op/frame"]);
+    }
+
+    #[test]
+    fn upstream_nodes_exclude_dora_inputs() {
+        assert!(topology("camera").unwrap().upstream_nodes().is_empty());
+        assert_eq!(
+            ids(topology("detector").unwrap().upstream_nodes()),
+            ["camera"]
+        );
+        assert_eq!(
+            ids(topology("plot").unwrap().upstream_nodes()),
+            ["camera", "detector"]
+        );
+    }
+
+    #[test]
+    fn downstream_peers_of_outputs() {
+        let topology = topology("camera").unwrap();
+
+        let image = topology.downstream(&DataId::from("image".to_owned()));
+        assert_eq!(
+            image,
+            Some(&BTreeSet::from([
+                peer("detector", "image"),
+                peer("plot", "op/image")
+            ]))
+        );
+        // declared outputs without receivers are still known
+        let depth = topology.downstream(&DataId::from("depth".to_owned()));
+        assert_eq!(depth, Some(&BTreeSet::new()));
+        assert_eq!(topology.downstream(&DataId::from("other".to_owned())), None);
+    }
+
+    #[test]
+    fn downstream_nodes_receive_any_output() {
+        assert_eq!(
+            ids(topology("camera").unwrap().downstream_nodes()),
+            ["detector", "plot"]
+        );
+        assert_eq!(
+            ids(topology("detector").unwrap().downstream_nodes()),
+            ["plot"]
+        );
+        assert!(topology("plot").unwrap().downstream_nodes().is_empty());
+    }
+
+    #[test]
+    fn unknown_node_is_rejected() {
+        let err = topology("speaker").unwrap_err();
+        assert!(err.to_string().contains("`speaker` not found"), "{err}");
+    }
+
+    #[test]
+    fn empty_topology_has_no_peers() {
+        let topology = NodeTopology::empty();
+
+        assert!(topology.resolved_node().is_none());
+        assert!(topology.inputs().is_empty());
+        assert!(topology.upstream_nodes().is_empty());
+        assert!(topology.downstream_nodes().is_empty());
+    }
+}
@@ -10,7 +10,8 @@ pub fn register_operator(item: TokenStream) -> TokenStream {
     // `syn` crate
     let item = TokenStream2::from(item);
     // generate the dora wrapper functions
-    let generated = register_operator_impl(&item).unwrap_or_else(|err| err.to_compile_error());
+    let generated = register_operator_impl(&item, quote! { dora_operator_api::raw })
+        .unwrap_or_else(|err| err.to_compile_error());
     // output the generated functions
     let tokens = quote! {
         #generated
@@ -19,8 +20,21 @@ pub fn register_operator(item: TokenStream) -> TokenStream {
     tokens.into()
 }
 
+/// Registers an operator that implements `AsyncDoraOperator`.
+///
+/// Requires the `async` feature of `dora-operator-api`.
+#[proc_macro]
+pub fn register_async_operator(item: TokenStream) -> TokenStream {
+    let item = TokenStream2::from(item);
+    let generated = register_operator_impl(&item, quote! { dora_operator_api::asynchronous })
+        .unwrap_or_else(|err| err.to_compile_error());
+    generated.into()
+}
+
 /// Generates the wrapper functions for the annotated function.
-fn register_operator_impl(item: &TokenStream2) -> syn::Result<TokenStream2> {
+///
+/// The wrappers call the functions of the same name in the given `raw` module.
+fn register_operator_impl(item: &TokenStream2, raw: TokenStream2) -> syn::Result<TokenStream2> {
     // parse the type given to the `register_operator` macro
     let operator_ty: syn::TypePath = syn::parse2(item.clone())
         .map_err(|e| syn::Error::new(e.span(), "expected type as argument"))?;
@@ -28,7 +42,7 @@ fn register_operator_impl(item: &TokenStream2) -> syn::Result<TokenStream2> {
     let init = quote! {
         #[no_mangle]
         pub unsafe extern "C" fn dora_init_operator() -> dora_operator_api::types::DoraInitResult {
-            dora_operator_api::raw::dora_init_operator::<#operator_ty>()
+            #raw::dora_init_operator::<#operator_ty>()
         }
 
         const _DORA_INIT_OPERATOR: dora_operator_api::types::DoraInitOperator = dora_operator_api::types::DoraInitOperator {
@@ -41,7 +55,7 @@ fn register_operator_impl(item: &TokenStream2) -> syn::Result<TokenStream2> {
         pub unsafe extern "C" fn dora_drop_operator(operator_context: *mut std::ffi::c_void)
             -> dora_operator_api::types::DoraResult
         {
-            dora_operator_api::raw::dora_drop_operator::<#operator_ty>(operator_context)
+            #raw::dora_drop_operator::<#operator_ty>(operator_context)
         }
 
         const _DORA_DROP_OPERATOR: dora_operator_api::types::DoraDropOperator = dora_operator_api::types::DoraDropOperator {
@@ -56,7 +70,7 @@ fn register_operator_impl(item: &TokenStream2) -> syn::Result<TokenStream2> {
             send_output: &dora_operator_api::types::SendOutput,
             operator_context: *mut std::ffi::c_void,
         ) -> dora_operator_api::types::OnEventResult {
-            dora_operator_api::raw::dora_on_event::<#operator_ty>(
+            #raw::dora_on_event::<#operator_ty>(
                 event, send_output, operator_context
             )
         }
@@ -66,9 +80,63 @@ fn register_operator_impl(item: &TokenStream2) -> syn::Result<TokenStream2> {
         };
     };
 
+    let version = quote! {
+        #[no_mangle]
+        #[allow(non_upper_case_globals)]
+        pub static dora_operator_api_version: u32 =
+            dora_operator_api::types::DORA_OPERATOR_API_VERSION;
+
+        #[no_mangle]
+        #[allow(non_upper_case_globals)]
+        pub static dora_operator_rustc_version: dora_operator_api::types::RustcVersion =
+            dora_operator_api::types::RustcVersion::CURRENT;
+    };
+
+    let lifecycle = quote! {
+        #[no_mangle]
+        pub unsafe extern "C" fn dora_on_start(
+            config: &dora_operator_api::types::RawOperatorConfig,
+            send_output: &dora_operator_api::types::SendOutput,
+            operator_context: *mut std::ffi::c_void,
+        ) -> dora_operator_api::types::DoraResult {
+            #raw::dora_on_start::<#operator_ty>(config, send_output, operator_context)
+        }
+
+        const _DORA_ON_START: dora_operator_api::types::DoraOnStart = dora_operator_api::types::DoraOnStart {
+            on_start: dora_operator_api::types::OnStartFn(dora_on_start),
+        };
+
+        #[no_mangle]
+        pub unsafe extern "C" fn dora_on_stop(
+            send_output: &dora_operator_api::types::SendOutput,
+            operator_context: *mut std::ffi::c_void,
+        ) -> dora_operator_api::types::DoraResult {
+            #raw::dora_on_stop::<#operator_ty>(send_output, operator_context)
+        }
+
+        const _DORA_ON_STOP: dora_operator_api::types::DoraOnStop = dora_operator_api::types::DoraOnStop {
+            on_stop: dora_operator_api::types::OnStopFn(dora_on_stop),
+        };
+
+        #[no_mangle]
+        pub unsafe extern "C" fn dora_on_error(
+            error: &dora_operator_api::types::safer_ffi::String,
+            send_output: &dora_operator_api::types::SendOutput,
+            operator_context: *mut std::ffi::c_void,
+        ) -> dora_operator_api::types::DoraResult {
+            #raw::dora_on_error::<#operator_ty>(error, send_output, operator_context)
+        }
+
+        const _DORA_ON_ERROR: dora_operator_api::types::DoraOnError = dora_operator_api::types::DoraOnError {
+            on_error: dora_operator_api::types::OnErrorFn(dora_on_error),
+        };
+    };
+
     Ok(quote! {
+        #version
         #init
         #drop
         #on_event
+        #lifecycle
     })
 }
@@ -0,0 +1,315 @@
+//! Support for operators with an async `on_event` method.
+//!
+//! Async operators implement the [`AsyncDoraOperator`] trait and are registered
+//! through [`register_async_operator`](crate::register_async_operator):
+//!
+//! ```ignore
+//! use dora_operator_api::{
+//!     register_async_operator, AsyncDoraOperator, DoraOutputSender, DoraStatus, Event,
+//! };
+//!
+//! register_async_operator!(ModelClient);
+//!
+//! #[derive(Default)]
+//! struct ModelClient;
+//!
+//! impl AsyncDoraOperator for ModelClient {
+//!     async fn on_event(
+//!         &self,
+//!         event: &Event<'_>,
+//!         output_sender: &mut DoraOutputSender<'_>,
+//!     ) -> Result<DoraStatus, String> {
+//!         if let Event::Input { id, data } = event {
+//!             // e.g. query a model server without blocking the runtime
+//!         }
+//!         Ok(DoraStatus::Continue)
+//!     }
+//! }
+//! ```
+//!
+//! Events are handled on a tokio executor that is owned by the operator
+//! library, since the executor of the runtime can't be shared across the
+//! shared library boundary. The runtime hands an event over to the executor
+//! and continues with the next event right away. Up to
+//! [`AsyncDoraOperator::MAX_CONCURRENT_EVENTS`] events are handled
+//! concurrently, further events wait until a handler finishes.
+//!
+//! A `Stop` or `StopAll` status returned by a handler and errors returned by a
+//! handler are reported to the runtime on the next event. The final
+//! [`Event::Stop`] and the [`AsyncDoraOperator::on_stop`] hook are only
+//! handled after all pending handlers finished.
+
+use std::{
+    ffi::c_void,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use dora_operator_api_types::{
+    safer_ffi, DoraInitResult, DoraResult, DoraStatus, OnEventResult, RawEvent, RawOperatorConfig,
+    SendOutput,
+};
+use tokio::sync::Semaphore;
+
+use crate::{
+    raw::{config_from_raw, event_from_raw, into_dora_result},
+    ArrowData, DoraOutputSender, Event, OperatorConfig,
+};
+
+pub trait AsyncDoraOperator: Default + Send + Sync + 'static {
+    /// Maximum number of events that are handled concurrently.
+    ///
+    /// Set this to `1` if events need to be handled in order.
+    const MAX_CONCURRENT_EVENTS: usize = 16;
+
+    /// See [`DoraOperator::on_start`](crate::DoraOperator::on_start).
+    fn on_start(
+        &self,
+        config: &OperatorConfig,
+        output_sender: &mut DoraOutputSender<'_>,
+    ) -> impl Future<Output = Result<(), String>> + Send {
+        let _ = (config, output_sender);
+        async { Ok(()) }
+    }
+
+    fn on_event(
+        &self,
+        event: &Event<'_>,
+        output_sender: &mut DoraOutputSender<'_>,
+    ) -> impl Future<Output = Result<DoraStatus, String>> + Send;
+
+    /// See [`DoraOperator::on_stop`](crate::DoraOperator::on_stop).
+    fn on_stop(
+        &self,
+        output_sender: &mut DoraOutputSender<'_>,
+    ) -> impl Future<Output = Result<(), String>> + Send {
+        let _ = output_sender;
+        async { Ok(()) }
+    }
+
+    /// See [`DoraOperator::on_error`](crate::DoraOperator::on_error).
+    fn on_error(
+        &self,
+        error: &str,
+        output_sender: &mut DoraOutputSender<'_>,
+    ) -> impl Future<Output = ()> + Send {
+        let _ = (error, output_sender);
+        async {}
+    }
+}
+
+struct AsyncOperatorContext<O> {
+    operator: Arc<O>,
+    runtime: tokio::runtime::Runtime,
+    permits: Arc<Semaphore>,
+    /// First error or non-`Continue` status returned by a handler.
+    outcome: Arc<Mutex<Option<Result<DoraStatus, String>>>>,
+}
+
+/// Owned version of [`Event`], which can be moved into a spawned task.
+enum OwnedEvent {
+    Input { id: String, data: ArrowData },
+    InputParseError { id: String, error: String },
+    InputClosed { id: String },
+    Timer { token: u64 },
+    Stop,
+}
+
+impl OwnedEvent {
+    fn new(event: Event<'_>) -> Self {
+        match event {
+            Event::Input { id, data } => OwnedEvent::Input {
+                id: id.to_owned(),
+                data,
+            },
+            Event::InputParseError { id, error } => OwnedEvent::InputParseError {
+                id: id.to_owned(),
+                error,
+            },
+            Event::InputClosed { id } => OwnedEvent::InputClosed { id: id.to_owned() },
+            Event::Timer { token } => OwnedEvent::Timer { token },
+            Event::Stop => OwnedEvent::Stop,
+        }
+    }
+
+    fn as_event(&self) -> Event<'_> {
+        match self {
+            OwnedEvent::Input { id, data } => Event::Input {
+                id,
+                data: ArrowData(data.0.clone()),
+            },
+            OwnedEvent::InputParseError { id, error } => Event::InputParseError {
+                id,
+                error: error.clone(),
+            },
+            OwnedEvent::InputClosed { id } => Event::InputClosed { id },
+            OwnedEvent::Timer { token } => Event::Timer { token: *token },
+            OwnedEvent::Stop => Event::Stop,
+        }
+    }
+}
+
+pub unsafe fn dora_init_operator<O: AsyncDoraOperator>() -> DoraInitResult {
+    let runtime = match tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(err) => {
+            return DoraInitResult {
+                result: DoraResult::from_error(format!("failed to build tokio runtime: {err}")),
+                operator_context: std::ptr::null_mut(),
+            }
+        }
+    };
+    let context = AsyncOperatorContext {
+        operator: Arc::new(O::default()),
+        runtime,
+        permits: Arc::new(Semaphore::new(O::MAX_CONCURRENT_EVENTS.max(1))),
+        outcome: Default::default(),
+    };
+    let ptr: *mut AsyncOperatorContext<O> = Box::leak(Box::new(context));
+    DoraInitResult {
+        result: DoraResult { error: None },
+        operator_context: ptr.cast(),
+    }
+}
+
+pub unsafe fn dora_drop_operator<O>(operator_context: *mut c_void) -> DoraResult {
+    let raw: *mut AsyncOperatorContext<O> = operator_context.cast();
+    let context = unsafe { Box::from_raw(raw) };
+    // don't wait for handlers that are still running
+    let AsyncOperatorContext { runtime, .. } = *context;
+    runtime.shutdown_background();
+    DoraResult { error: None }
+}
+
+pub unsafe fn dora_on_event<O: AsyncDoraOperator>(
+    event: &mut RawEvent,
+    send_output: &SendOutput,
+    operator_context: *mut c_void,
+) -> OnEventResult {
+    let context: &AsyncOperatorContext<O> = unsafe { &*operator_context.cast() };
+
+    if let Some(outcome) = context.outcome.lock().unwrap().take() {
+        return on_event_result(outcome);
+    }
+
+    let event = match unsafe { event_from_raw(event) } {
+        Ok(event) => event.map(OwnedEvent::new),
+        Err(error) => {
+            return OnEventResult {
+                result: DoraResult::from_error(error),
+                status: DoraStatus::Continue,
+            }
+        }
+    };
+    let Some(event) = event else {
+        // ignore unknown events
+        return on_event_result(Ok(DoraStatus::Continue));
+    };
+
+    if let OwnedEvent::Stop = event {
+        // wait until all pending handlers are done
+        let permits = O::MAX_CONCURRENT_EVENTS.max(1) as u32;
+        let _all = context
+            .runtime
+            .block_on(context.permits.acquire_many(permits))
+            .expect("semaphore is never closed");
+        if let Some(outcome) = context.outcome.lock().unwrap().take() {
+            return on_event_result(outcome);
+        }
+        let mut output_sender = DoraOutputSender(send_output);
+        let result = context.runtime.block_on(
+            context
+                .operator
+                .on_event(&event.as_event(), &mut output_sender),
+        );
+        return on_event_result(result);
+    }
+
+    let permit = context
+        .runtime
+        .block_on(context.permits.clone().acquire_owned())
+        .expect("semaphore is never closed");
+    let operator = context.operator.clone();
+    let send_output = SendOutput {
+        send_output: send_output.send_output.clone(),
+        schedule_timer: send_output.schedule_timer.clone(),
+        share_device_buffer: send_output.share_device_buffer.clone(),
+        send_device_buffer: send_output.send_device_buffer.clone(),
+        device_buffer: send_output.device_buffer.clone(),
+        device_context: send_output.device_context.clone(),
+    };
+    let outcome = context.outcome.clone();
+    context.runtime.spawn(async move {
+        let mut output_sender = DoraOutputSender(&send_output);
+        let result = operator
+            .on_event(&event.as_event(), &mut output_sender)
+            .await;
+        if !matches!(result, Ok(DoraStatus::Continue)) {
+            outcome.lock().unwrap().get_or_insert(result);
+        }
+        drop(permit);
+    });
+
+    on_event_result(Ok(DoraStatus::Continue))
+}
+
+pub unsafe fn dora_on_start<O: AsyncDoraOperator>(
+    config: &RawOperatorConfig,
+    send_output: &SendOutput,
+    operator_context: *mut c_void,
+) -> DoraResult {
+    let context: &AsyncOperatorContext<O> = unsafe { &*operator_context.cast() };
+    let config = config_from_raw(config);
+    let mut output_sender = DoraOutputSender(send_output);
+    let result = context
+        .runtime
+        .block_on(context.operator.on_start(&config, &mut output_sender));
+    into_dora_result(result)
+}
+
+pub unsafe fn dora_on_stop<O: AsyncDoraOperator>(
+    send_output: &SendOutput,
+    operator_context: *mut c_void,
+) -> DoraResult {
+    let context: &AsyncOperatorContext<O> = unsafe { &*operator_context.cast() };
+    // wait until all pending handlers are done
+    let permits = O::MAX_CONCURRENT_EVENTS.max(1) as u32;
+    let _all = context
+        .runtime
+        .block_on(context.permits.acquire_many(permits))
+        .expect("semaphore is never closed");
+    let mut output_sender = DoraOutputSender(send_output);
+    let result = context
+        .runtime
+        .block_on(context.operator.on_stop(&mut output_sender));
+    into_dora_result(result)
+}
+
+pub unsafe fn dora_on_error<O: AsyncDoraOperator>(
+    error: &safer_ffi::String,
+    send_output: &SendOutput,
+    operator_context: *mut c_void,
+) -> DoraResult {
+    let context: &AsyncOperatorContext<O> = unsafe { &*operator_context.cast() };
+    let mut output_sender = DoraOutputSender(send_output);
+    context
+        .runtime
+        .block_on(context.operator.on_error(error, &mut output_sender));
+    DoraResult::SUCCESS
+}
+
+fn on_event_result(result: Result<DoraStatus, String>) -> OnEventResult {
+    match result {
+        Ok(status) => OnEventResult {
+            result: DoraResult { error: None },
+            status,
+        },
+        Err(error) => OnEventResult {
+            result: DoraResult::from_error(error),
+            status: DoraStatus::Stop,
+        },
+    }
+}
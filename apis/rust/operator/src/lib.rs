@@ -8,6 +8,10 @@
 //! It is composed of an `on_event` method that defines the behaviour
 //! of the operator when there is an event such as receiving an input for example.
 //!
+//! Operators that do network I/O, e.g. requests to model servers or
+//! databases, can implement the [`AsyncDoraOperator`] trait instead (requires
+//! the `async` feature).
+//!
 //! Try it out with:
 //!
 //! ```bash
@@ -18,38 +22,102 @@
 #![warn(unsafe_op_in_unsafe_fn)]
 #![allow(clippy::missing_safety_doc)]
 
+#[cfg(feature = "async")]
+pub use asynchronous::AsyncDoraOperator;
 pub use dora_arrow_convert::*;
+#[cfg(feature = "async")]
+pub use dora_operator_api_macros::register_async_operator;
 pub use dora_operator_api_macros::register_operator;
 pub use dora_operator_api_types as types;
+use std::{ffi::c_void, time::Duration};
 pub use types::DoraStatus;
 use types::{
     arrow::{self, array::Array},
-    Metadata, Output, SendOutput,
+    DeviceBufferOutput, DeviceBufferView, DeviceContextRequest, DeviceKind, Metadata, Output,
+    RawDeviceBuffer, ScheduleTimer, SendOutput,
 };
 
+#[cfg(feature = "async")]
+pub mod asynchronous;
 pub mod raw;
 
 #[derive(Debug)]
 #[non_exhaustive]
 pub enum Event<'a> {
-    Input { id: &'a str, data: ArrowData },
-    InputParseError { id: &'a str, error: String },
-    InputClosed { id: &'a str },
+    Input {
+        id: &'a str,
+        data: ArrowData,
+    },
+    InputParseError {
+        id: &'a str,
+        error: String,
+    },
+    InputClosed {
+        id: &'a str,
+    },
+    /// A timer scheduled through [`DoraOutputSender::schedule`] expired.
+    Timer {
+        token: u64,
+    },
     Stop,
 }
 
+/// Configuration of an operator as defined in the dataflow.
+#[derive(Debug, Clone, Default)]
+pub struct OperatorConfig {
+    pub id: String,
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+}
+
 pub trait DoraOperator: Default {
+    /// Called once before the first event, e.g. to acquire resources.
+    ///
+    /// Returning an error stops the operator without calling [`Self::on_stop`].
+    fn on_start(
+        &mut self,
+        config: &OperatorConfig,
+        output_sender: &mut DoraOutputSender,
+    ) -> Result<(), String> {
+        let _ = (config, output_sender);
+        Ok(())
+    }
+
     #[allow(clippy::result_unit_err)] // we use a () error type only for testing
     fn on_event(
         &mut self,
         event: &Event,
         output_sender: &mut DoraOutputSender,
     ) -> Result<DoraStatus, String>;
+
+    /// Called once after the last event, e.g. to release resources or to send
+    /// final outputs.
+    ///
+    /// This is called whatever the reason for the stop was, including errors
+    /// returned by [`Self::on_event`].
+    fn on_stop(&mut self, output_sender: &mut DoraOutputSender) -> Result<(), String> {
+        let _ = output_sender;
+        Ok(())
+    }
+
+    /// Called when [`Self::on_event`] returned an error, before the operator
+    /// is stopped.
+    fn on_error(&mut self, error: &str, output_sender: &mut DoraOutputSender) {
+        let _ = (error, output_sender);
+    }
 }
 
 pub struct DoraOutputSender<'a>(&'a SendOutput);
 
-impl DoraOutputSender<'_> {
+impl<'a> DoraOutputSender<'a> {
+    /// Creates a sender that passes outputs to the given callback.
+    ///
+    /// Operators get their sender from the runtime, this is only needed to
+    /// call operators directly, e.g. in tests.
+    pub fn new(send_output: &'a SendOutput) -> Self {
+        Self(send_output)
+    }
+
     ///  Send an output from the operator:
     ///  - `id` is the `output_id` as defined in your dataflow.
     ///  - `data` is the data that should be sent
@@ -66,4 +134,79 @@ impl DoraOutputSender<'_> {
         });
         result.into_result()
     }
+
+    /// Requests an [`Event::Timer`] with the given `token` after `delay`.
+    ///
+    /// This allows dynamic timeouts or retries without declaring a
+    /// `dora/timer` input in the dataflow. The token identifies the timer
+    /// when it expires. Pending timers are discarded when the operator stops.
+    pub fn schedule(&mut self, delay: Duration, token: u64) -> Result<(), String> {
+        let result = self.0.schedule_timer.call(ScheduleTimer {
+            delay_ns: delay.as_nanos().try_into().unwrap_or(u64::MAX),
+            token,
+        });
+        result.into_result()
+    }
+
+    /// Shares the given device buffer with the other operators of the runtime
+    /// node and returns its handle.
+    ///
+    /// The returned handle holds one reference to the buffer, which is handed
+    /// over to the receivers by [`Self::send_device_buffer`]. Operators that
+    /// receive the buffer hold a reference as long as they keep the input
+    /// data. Once the last reference is dropped, the runtime calls the
+    /// `release` function of the buffer.
+    ///
+    /// # Safety
+    ///
+    /// The buffer must stay valid until `release` is called, and `release`
+    /// must be safe to call from any operator thread.
+    pub unsafe fn share_device_buffer(&mut self, buffer: RawDeviceBuffer) -> Result<u64, String> {
+        match self.0.share_device_buffer.call(buffer) {
+            0 => Err("failed to share device buffer".into()),
+            handle => Ok(handle),
+        }
+    }
+
+    /// Sends the shared device buffer with the given handle to the operators
+    /// of the same runtime node, without copying it to the host.
+    ///
+    /// This consumes the reference that the handle holds. The receivers get
+    /// the handle as a `UInt64` input, see [`Self::device_buffer`].
+    pub fn send_device_buffer(&mut self, id: String, handle: u64) -> Result<(), String> {
+        let result = self.0.send_device_buffer.call(DeviceBufferOutput {
+            id: id.into(),
+            handle,
+        });
+        result.into_result()
+    }
+
+    /// Looks up the shared device buffer with the given handle.
+    ///
+    /// The returned view is valid as long as the input that carried the
+    /// handle is kept.
+    pub fn device_buffer(&self, handle: u64) -> Option<DeviceBufferView> {
+        let view = self.0.device_buffer.call(handle);
+        (!view.ptr.is_null()).then_some(view)
+    }
+
+    /// Returns the device context that is shared by the operators of the
+    /// runtime node, e.g. a `CUcontext` or a `cl_context`.
+    ///
+    /// If no context was registered for the device yet, the given `context`
+    /// is registered and returned. Otherwise, the registered context is
+    /// returned and the caller is responsible for the given one. Pass a null
+    /// pointer to only look up the registered context.
+    pub fn device_context(
+        &mut self,
+        device: DeviceKind,
+        device_id: i32,
+        context: *mut c_void,
+    ) -> *mut c_void {
+        self.0.device_context.call(DeviceContextRequest {
+            device,
+            device_id,
+            context,
+        })
+    }
 }
@@ -1,6 +1,7 @@
-use crate::{DoraOperator, DoraOutputSender, DoraStatus, Event};
+use crate::{DoraOperator, DoraOutputSender, DoraStatus, Event, OperatorConfig};
 use dora_operator_api_types::{
-    arrow, DoraInitResult, DoraResult, OnEventResult, RawEvent, SendOutput,
+    arrow, safer_ffi, DoraInitResult, DoraResult, OnEventResult, RawEvent, RawOperatorConfig,
+    SendOutput,
 };
 use std::ffi::c_void;
 
@@ -37,12 +38,88 @@ pub unsafe fn dora_on_event<O: DoraOperator>(
 
     let operator: &mut O = unsafe { &mut *operator_context.cast() };
 
-    let event_variant = if let Some(input) = &mut event.input {
-        let Some(data_array) = input.data_array.take() else {
+    let event_variant = match unsafe { event_from_raw(event) } {
+        Ok(Some(event)) => event,
+        // ignore unknown events
+        Ok(None) => {
             return OnEventResult {
-                result: DoraResult::from_error("data already taken".to_string()),
+                result: DoraResult { error: None },
                 status: DoraStatus::Continue,
-            };
+            }
+        }
+        Err(error) => {
+            return OnEventResult {
+                result: DoraResult::from_error(error),
+                status: DoraStatus::Continue,
+            }
+        }
+    };
+    match operator.on_event(&event_variant, &mut output_sender) {
+        Ok(status) => OnEventResult {
+            result: DoraResult { error: None },
+            status,
+        },
+        Err(error) => OnEventResult {
+            result: DoraResult::from_error(error),
+            status: DoraStatus::Stop,
+        },
+    }
+}
+
+pub unsafe fn dora_on_start<O: DoraOperator>(
+    config: &RawOperatorConfig,
+    send_output: &SendOutput,
+    operator_context: *mut c_void,
+) -> DoraResult {
+    let mut output_sender = DoraOutputSender(send_output);
+    let operator: &mut O = unsafe { &mut *operator_context.cast() };
+    let config = config_from_raw(config);
+    into_dora_result(operator.on_start(&config, &mut output_sender))
+}
+
+pub unsafe fn dora_on_stop<O: DoraOperator>(
+    send_output: &SendOutput,
+    operator_context: *mut c_void,
+) -> DoraResult {
+    let mut output_sender = DoraOutputSender(send_output);
+    let operator: &mut O = unsafe { &mut *operator_context.cast() };
+    into_dora_result(operator.on_stop(&mut output_sender))
+}
+
+pub unsafe fn dora_on_error<O: DoraOperator>(
+    error: &safer_ffi::String,
+    send_output: &SendOutput,
+    operator_context: *mut c_void,
+) -> DoraResult {
+    let mut output_sender = DoraOutputSender(send_output);
+    let operator: &mut O = unsafe { &mut *operator_context.cast() };
+    operator.on_error(error, &mut output_sender);
+    DoraResult::SUCCESS
+}
+
+pub(crate) fn config_from_raw(config: &RawOperatorConfig) -> OperatorConfig {
+    OperatorConfig {
+        id: config.id.to_string(),
+        inputs: config.inputs.iter().map(|id| id.to_string()).collect(),
+        outputs: config.outputs.iter().map(|id| id.to_string()).collect(),
+    }
+}
+
+pub(crate) fn into_dora_result(result: Result<(), String>) -> DoraResult {
+    match result {
+        Ok(()) => DoraResult::SUCCESS,
+        Err(error) => DoraResult::from_error(error),
+    }
+}
+
+/// Converts the given raw event, returning `None` for unknown events.
+///
+/// Takes the input data out of the raw event, so this can only be called once
+/// per event.
+pub(crate) unsafe fn event_from_raw(event: &mut RawEvent) -> Result<Option<Event<'_>>, String> {
+    let event = if let Some(input) = &mut event.input {
+        let Some(data_array) = input.data_array.take() else {
+            return Err("data already taken".to_string());
         };
         let data = unsafe { arrow::ffi::from_ffi(data_array, &input.schema) };
 
@@ -58,23 +135,12 @@ pub unsafe fn dora_on_event<O: DoraOperator>(
         }
     } else if let Some(input_id) = &event.input_closed {
         Event::InputClosed { id: input_id }
+    } else if let Some(timer) = &event.timer {
+        Event::Timer { token: timer.token }
     } else if event.stop {
         Event::Stop
     } else {
-        // ignore unknown events
-        return OnEventResult {
-            result: DoraResult { error: None },
-            status: DoraStatus::Continue,
-        };
+        return Ok(None);
     };
-    match operator.on_event(&event_variant, &mut output_sender) {
-        Ok(status) => OnEventResult {
-            result: DoraResult { error: None },
-            status,
-        },
-        Err(error) => OnEventResult {
-            result: DoraResult::from_error(error),
-            status: DoraStatus::Stop,
-        },
-    }
+    Ok(Some(event))
 }
@@ -18,6 +18,44 @@ use safer_ffi::{
 };
 use std::{ops::Deref, path::Path};
 
+/// Version of the operator ABI defined by this crate.
+///
+/// Operators export it as `dora_operator_api_version` symbol, so that the
+/// runtime can refuse to load operators that were built against an
+/// incompatible version. Increase it on every change to the types or functions
+/// that are passed between the runtime and operators.
+pub const DORA_OPERATOR_API_VERSION: u32 = 4;
+
+/// Version of the Rust compiler that this crate was compiled with.
+pub const RUSTC_VERSION: &str = env!("DORA_RUSTC_VERSION");
+
+/// Nul-padded Rust compiler version, exported by Rust operators as
+/// `dora_operator_rustc_version` symbol.
+///
+/// Rust operators exchange some types with their Rust layout, which is only
+/// stable when the runtime and the operator are compiled by the same compiler.
+#[repr(C)]
+pub struct RustcVersion(pub [u8; 128]);
+
+impl RustcVersion {
+    pub const CURRENT: Self = {
+        let version = RUSTC_VERSION.as_bytes();
+        let mut buffer = [0; 128];
+        let mut i = 0;
+        // keep the last byte as nul terminator
+        while i < version.len() && i < buffer.len() - 1 {
+            buffer[i] = version[i];
+            i += 1;
+        }
+        Self(buffer)
+    };
+
+    pub fn as_str(&self) -> &str {
+        let len = self.0.iter().position(|&b| b == 0).unwrap_or(self.0.len());
+        std::str::from_utf8(&self.0[..len]).unwrap_or("<invalid UTF-8>")
+    }
+}
+
 #[derive_ReprC]
 #[ffi_export]
 #[repr(C)]
@@ -90,6 +128,75 @@ pub struct OnEventFn(
     ) -> OnEventResult,
 );
 
+/// Optional, called once before the first event.
+#[derive_ReprC]
+#[ffi_export]
+#[repr(C)]
+pub struct DoraOnStart {
+    pub on_start: OnStartFn,
+}
+
+#[derive_ReprC]
+#[ffi_export]
+#[repr(transparent)]
+pub struct OnStartFn(
+    pub  unsafe extern "C" fn(
+        config: &RawOperatorConfig,
+        send_output: &SendOutput,
+        operator_context: *mut std::ffi::c_void,
+    ) -> DoraResult,
+);
+
+/// Optional, called once after the last event, whatever the reason for the
+/// stop was.
+#[derive_ReprC]
+#[ffi_export]
+#[repr(C)]
+pub struct DoraOnStop {
+    pub on_stop: OnStopFn,
+}
+
+#[derive_ReprC]
+#[ffi_export]
+#[repr(transparent)]
+pub struct OnStopFn(
+    pub  unsafe extern "C" fn(
+        send_output: &SendOutput,
+        operator_context: *mut std::ffi::c_void,
+    ) -> DoraResult,
+);
+
+/// Optional, called when handling an event failed, before the operator is
+/// stopped.
+#[derive_ReprC]
+#[ffi_export]
+#[repr(C)]
+pub struct DoraOnError {
+    pub on_error: OnErrorFn,
+}
+
+#[derive_ReprC]
+#[ffi_export]
+#[repr(transparent)]
+pub struct OnErrorFn(
+    pub  unsafe extern "C" fn(
+        error: &safer_ffi::String,
+        send_output: &SendOutput,
+        operator_context: *mut std::ffi::c_void,
+    ) -> DoraResult,
+);
+
+/// Configuration of an operator as defined in the dataflow.
+#[derive_ReprC]
+#[ffi_export]
+#[repr(C)]
+#[derive(Debug)]
+pub struct RawOperatorConfig {
+    pub id: safer_ffi::String,
+    pub inputs: safer_ffi::Vec<safer_ffi::String>,
+    pub outputs: safer_ffi::Vec<safer_ffi::String>,
+}
+
 #[derive_ReprC]
 #[ffi_export]
 #[repr(C)]
@@ -99,6 +206,15 @@ pub struct RawEvent {
     pub input_closed: Option<safer_ffi::String>,
     pub stop: bool,
     pub error: Option<safer_ffi::String>,
+    pub timer: Option<safer_ffi::boxed::Box<Timer>>,
+}
+
+#[derive_ReprC]
+#[ffi_export]
+#[repr(C)]
+#[derive(Debug)]
+pub struct Timer {
+    pub token: u64,
 }
 
 #[derive_ReprC]
@@ -124,6 +240,89 @@ pub struct Metadata {
 #[repr(C)]
 pub struct SendOutput {
     pub send_output: ArcDynFn1<DoraResult, Output>,
+    pub schedule_timer: ArcDynFn1<DoraResult, ScheduleTimer>,
+    /// Registers a device buffer and returns its handle, or `0` on error.
+    pub share_device_buffer: ArcDynFn1<u64, RawDeviceBuffer>,
+    pub send_device_buffer: ArcDynFn1<DoraResult, DeviceBufferOutput>,
+    /// Looks up a device buffer by handle, see [`DeviceBufferView`].
+    pub device_buffer: ArcDynFn1<DeviceBufferView, u64>,
+    pub device_context: ArcDynFn1<*mut std::ffi::c_void, DeviceContextRequest>,
+}
+
+#[derive_ReprC]
+#[ffi_export]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum DeviceKind {
+    Cuda = 0,
+    OpenCl = 1,
+}
+
+/// Device memory that is shared between the operators of a runtime node.
+///
+/// The runtime counts the references to the buffer and calls `release` with
+/// `ptr` and `release_context` once the last reference was dropped.
+#[derive_ReprC]
+#[ffi_export]
+#[repr(C)]
+pub struct RawDeviceBuffer {
+    pub device: DeviceKind,
+    pub device_id: i32,
+    pub ptr: *mut std::ffi::c_void,
+    pub len: usize,
+    pub release:
+        unsafe extern "C" fn(ptr: *mut std::ffi::c_void, release_context: *mut std::ffi::c_void),
+    pub release_context: *mut std::ffi::c_void,
+}
+
+/// Borrowed view of a shared device buffer.
+///
+/// The `ptr` is null if the handle is unknown. The view is valid as long as
+/// the caller holds a reference to the buffer, e.g. the input that carried
+/// the handle.
+#[derive_ReprC]
+#[ffi_export]
+#[repr(C)]
+#[derive(Debug)]
+pub struct DeviceBufferView {
+    pub device: DeviceKind,
+    pub device_id: i32,
+    pub ptr: *mut std::ffi::c_void,
+    pub len: usize,
+}
+
+/// Sends the shared device buffer with the given handle to the operators of
+/// the same runtime node that are connected to output `id`.
+#[derive_ReprC]
+#[ffi_export]
+#[repr(C)]
+#[derive(Debug)]
+pub struct DeviceBufferOutput {
+    pub id: safer_ffi::String,
+    pub handle: u64,
+}
+
+/// Gets the device context that is shared by the operators of a runtime
+/// node, or registers `context` if there is none yet.
+#[derive_ReprC]
+#[ffi_export]
+#[repr(C)]
+#[derive(Debug)]
+pub struct DeviceContextRequest {
+    pub device: DeviceKind,
+    pub device_id: i32,
+    /// Context to register, may be null to only look up the existing one.
+    pub context: *mut std::ffi::c_void,
+}
+
+/// Requests a [`Timer`] event with the given token after `delay_ns` nanoseconds.
+#[derive_ReprC]
+#[ffi_export]
+#[repr(C)]
+#[derive(Debug)]
+pub struct ScheduleTimer {
+    pub delay_ns: u64,
+    pub token: u64,
 }
 
 #[derive_ReprC]
@@ -205,6 +404,49 @@ pub unsafe fn dora_send_operator_output(
     }
 }
 
+#[ffi_export]
+pub fn dora_schedule_timer(send_output: &SendOutput, delay_ns: u64, token: u64) -> DoraResult {
+    send_output
+        .schedule_timer
+        .call(ScheduleTimer { delay_ns, token })
+}
+
+#[ffi_export]
+pub fn dora_share_device_buffer(send_output: &SendOutput, buffer: RawDeviceBuffer) -> u64 {
+    send_output.share_device_buffer.call(buffer)
+}
+
+#[ffi_export]
+pub fn dora_send_device_buffer(
+    send_output: &SendOutput,
+    id: safer_ffi::char_p::char_p_ref<'_>,
+    handle: u64,
+) -> DoraResult {
+    send_output.send_device_buffer.call(DeviceBufferOutput {
+        id: id.to_str().to_owned().into(),
+        handle,
+    })
+}
+
+#[ffi_export]
+pub fn dora_device_buffer(send_output: &SendOutput, handle: u64) -> DeviceBufferView {
+    send_output.device_buffer.call(handle)
+}
+
+#[ffi_export]
+pub fn dora_device_context(
+    send_output: &SendOutput,
+    device: DeviceKind,
+    device_id: i32,
+    context: *mut std::ffi::c_void,
+) -> *mut std::ffi::c_void {
+    send_output.device_context.call(DeviceContextRequest {
+        device,
+        device_id,
+        context,
+    })
+}
+
 pub fn generate_headers(target_file: &Path) -> ::std::io::Result<()> {
     ::safer_ffi::headers::builder()
         .to_file(target_file)?
@@ -0,0 +1,767 @@
+use dora_coordinator::{ControlEvent, Event};
+use dora_core::{
+    config::{DataId, InputMapping, NodeId},
+    descriptor::{CoreNodeKind, Descriptor, DescriptorExt, Node, DYNAMIC_SOURCE},
+    topics::LOCALHOST,
+};
+use dora_daemon::Daemon;
+use dora_message::{
+    cli_to_coordinator::ControlRequest,
+    coordinator_to_cli::{ControlRequestReply, DataflowResult, NodeError},
+    metadata::Metadata,
+};
+use dora_node_api::{arrow::array::Array, ArrowData, DoraNode, EventStream, NodeToken};
+use eyre::{bail, eyre, Context, ContextCompat};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tokio::{
+    runtime::Runtime,
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+};
+use tokio_stream::wrappers::ReceiverStream;
+use uuid::Uuid;
+
+/// ID of the node that represents the test in the dataflow.
+pub const TEST_NODE: &str = "test";
+
+/// Interval in which the coordinator is asked whether the dataflow finished.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// How long dropping a [`RunningDataflow`] waits for the dataflow to stop.
+const DROP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Message of a captured output.
+#[derive(Debug)]
+pub struct Output {
+    /// The captured output, as `node/output`.
+    pub id: DataId,
+    pub metadata: Metadata,
+    pub data: ArrowData,
+}
+
+/// Error of [`RunningDataflow::recv`] and [`RunningDataflow::recv_from`] once
+/// all captured outputs are closed, e.g. because their nodes finished.
+///
+/// Use [`eyre::Report::is`] or `downcast_ref` to check for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputsClosed;
+
+impl std::fmt::Display for OutputsClosed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("all captured outputs are closed")
+    }
+}
+
+impl std::error::Error for OutputsClosed {}
+
+/// A dataflow that is run inside of the test process.
+///
+/// Nothing is spawned until [`Self::start`] is called.
+pub struct TestDataflow {
+    descriptor: Descriptor,
+    working_dir: Option<PathBuf>,
+    captured: BTreeSet<DataId>,
+    coordinator: bool,
+}
+
+impl TestDataflow {
+    /// Creates a test for the given dataflow YAML.
+    pub fn from_yaml(yaml: &str) -> eyre::Result<Self> {
+        let descriptor = Descriptor::parse(yaml.as_bytes().to_vec())?;
+        Ok(Self::from_descriptor(descriptor))
+    }
+
+    /// Creates a test for the given dataflow YAML file.
+    ///
+    /// The directory of the file is used as working directory.
+    pub fn from_dataflow(path: &Path) -> eyre::Result<Self> {
+        let descriptor = Descriptor::blocking_read(path)
+            .wrap_err_with(|| format!("failed to read `{}`", path.display()))?;
+        let working_dir = path
+            .canonicalize()
+            .wrap_err("failed to canonicalize dataflow path")?
+            .parent()
+            .context("canonicalized dataflow path has no parent")?
+            .to_owned();
+        Ok(Self::from_descriptor(descriptor).working_dir(working_dir))
+    }
+
+    /// Creates a test for the given dataflow descriptor.
+    pub fn from_descriptor(descriptor: Descriptor) -> Self {
+        Self {
+            descriptor,
+            working_dir: None,
+            captured: BTreeSet::new(),
+            coordinator: false,
+        }
+    }
+
+    /// Sets the directory that relative paths of the dataflow are resolved
+    /// against.
+    ///
+    /// Defaults to the current directory, which is the directory of the
+    /// tested package when run through `cargo test`.
+    pub fn working_dir(mut self, working_dir: impl Into<PathBuf>) -> Self {
+        self.working_dir = Some(working_dir.into());
+        self
+    }
+
+    /// Runs the dataflow through a coordinator, like `dora start` does.
+    ///
+    /// By default, the dataflow is run by a daemon alone, like `dora run`
+    /// does.
+    pub fn with_coordinator(mut self) -> Self {
+        self.coordinator = true;
+        self
+    }
+
+    /// Captures the given output, given as `node/output`, so that the test
+    /// can receive its messages.
+    pub fn capture(mut self, output: &str) -> eyre::Result<Self> {
+        let Some((node_id, _)) = output.split_once('/') else {
+            bail!("captured outputs must have the form `<node_id>/<output_id>`, got `{output}`");
+        };
+        if !self
+            .descriptor
+            .nodes
+            .iter()
+            .any(|node| node.id.as_ref() == node_id)
+        {
+            bail!("captured output `{output}` belongs to unknown node `{node_id}`");
+        }
+        self.captured.insert(DataId::from(output.to_owned()));
+        Ok(self)
+    }
+
+    /// Spawns the dataflow and connects the test to it.
+    ///
+    /// Returns once all nodes of the dataflow are ready.
+    pub fn start(self) -> eyre::Result<RunningDataflow> {
+        let Self {
+            mut descriptor,
+            working_dir,
+            captured,
+            coordinator,
+        } = self;
+        let working_dir = match working_dir {
+            Some(working_dir) => working_dir,
+            None => std::env::current_dir().wrap_err("failed to get current directory")?,
+        };
+        let injected = injected_outputs(&descriptor)?;
+        descriptor.nodes.push(test_node(&injected, &captured)?);
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .wrap_err("failed to create tokio runtime")?;
+        let token = NodeToken::generate();
+        let (backend, listen_port) = if coordinator {
+            runtime.block_on(Backend::start_with_coordinator(
+                descriptor,
+                working_dir,
+                token.clone(),
+            ))?
+        } else {
+            runtime.block_on(Backend::start_daemon(
+                descriptor,
+                working_dir,
+                token.clone(),
+            ))?
+        };
+        let mut dataflow = RunningDataflow {
+            runtime,
+            backend: Some(backend),
+            node: None,
+            events: None,
+            injected,
+            captured,
+            pending: VecDeque::new(),
+        };
+
+        // waits until all other nodes are ready
+        let (node, events) = DoraNode::init_dynamic(
+            NodeId::from(TEST_NODE.to_owned()),
+            (LOCALHOST, listen_port).into(),
+            Some(token),
+        )
+        .wrap_err("failed to connect test node to the daemon")?;
+        dataflow.node = Some(node);
+        dataflow.events = Some(events);
+        Ok(dataflow)
+    }
+}
+
+/// A dataflow started through [`TestDataflow::start`].
+///
+/// Dropping it stops the dataflow.
+pub struct RunningDataflow {
+    runtime: Runtime,
+    backend: Option<Backend>,
+    node: Option<DoraNode>,
+    events: Option<EventStream>,
+    injected: BTreeSet<DataId>,
+    captured: BTreeSet<DataId>,
+    /// Received outputs that were skipped by [`Self::recv_from`].
+    pending: VecDeque<Output>,
+}
+
+impl RunningDataflow {
+    /// Sends the given data on output `test/<output>`, i.e. to all inputs
+    /// that are mapped to it.
+    pub fn send(&mut self, output: &str, data: impl Array) -> eyre::Result<()> {
+        if !self.injected.contains(output) {
+            bail!("no input of the dataflow is mapped to `{TEST_NODE}/{output}`");
+        }
+        self.node
+            .as_mut()
+            .context("test node is disconnected")?
+            .send_output(DataId::from(output.to_owned()), Default::default(), data)
+    }
+
+    /// Receives the next message of any captured output.
+    ///
+    /// Fails with [`OutputsClosed`] once all captured outputs are closed.
+    pub fn recv(&mut self, timeout: Duration) -> eyre::Result<Output> {
+        if let Some(output) = self.pending.pop_front() {
+            return Ok(output);
+        }
+        self.next_output(Instant::now() + timeout)
+    }
+
+    /// Receives the next message of the given captured output, given as
+    /// `node/output`.
+    ///
+    /// Messages of other captured outputs that arrive in the meantime are
+    /// kept for [`Self::recv`]. Fails with [`OutputsClosed`] once all
+    /// captured outputs are closed.
+    pub fn recv_from(&mut self, output: &str, timeout: Duration) -> eyre::Result<Output> {
+        if !self.captured.contains(output) {
+            bail!("output `{output}` is not captured");
+        }
+        if let Some(index) = self.pending.iter().position(|o| o.id.as_str() == output) {
+            return Ok(self.pending.remove(index).unwrap());
+        }
+        let deadline = Instant::now() + timeout;
+        loop {
+            let next = self
+                .next_output(deadline)
+                .wrap_err_with(|| format!("failed to receive `{output}`"))?;
+            if next.id.as_str() == output {
+                return Ok(next);
+            }
+            self.pending.push_back(next);
+        }
+    }
+
+    /// Waits until all nodes of the dataflow finished.
+    ///
+    /// The outputs of the test are closed first, so that nodes that run until
+    /// their inputs are closed can finish.
+    pub fn wait(mut self, timeout: Duration) -> eyre::Result<TestResult> {
+        self.finish(false, timeout)
+    }
+
+    /// Stops the dataflow and waits until all nodes finished.
+    pub fn stop(mut self, timeout: Duration) -> eyre::Result<TestResult> {
+        self.finish(true, timeout)
+    }
+
+    fn next_output(&mut self, deadline: Instant) -> eyre::Result<Output> {
+        let events = self.events.as_mut().context("test node is disconnected")?;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                bail!("no captured output received before the timeout");
+            }
+            match events.recv_timeout(remaining) {
+                Some(dora_node_api::Event::Input { id, metadata, data }) => {
+                    return Ok(Output { id, metadata, data })
+                }
+                Some(dora_node_api::Event::Stop) => bail!("dataflow was stopped"),
+                Some(dora_node_api::Event::Error(err)) => {
+                    if Instant::now() >= deadline {
+                        bail!("no captured output received before the timeout");
+                    }
+                    bail!("test node received an error: {err}");
+                }
+                Some(_) => {}
+                None => return Err(OutputsClosed.into()),
+            }
+        }
+    }
+
+    fn finish(&mut self, stop: bool, timeout: Duration) -> eyre::Result<TestResult> {
+        // the test node finishes when it disconnects
+        self.node.take();
+        self.events.take();
+        let backend = self.backend.take().context("dataflow already finished")?;
+        let result = self.runtime.block_on(backend.finish(stop, timeout))?;
+        Ok(TestResult::new(result))
+    }
+}
+
+impl Drop for RunningDataflow {
+    fn drop(&mut self) {
+        if self.backend.is_some() {
+            if let Err(err) = self.finish(true, DROP_TIMEOUT) {
+                tracing::warn!("failed to stop test dataflow: {err:?}");
+            }
+        }
+    }
+}
+
+/// Results of the nodes of a finished dataflow, without the test node.
+#[derive(Debug)]
+pub struct TestResult {
+    pub uuid: Uuid,
+    pub node_results: BTreeMap<NodeId, Result<(), NodeError>>,
+}
+
+impl TestResult {
+    fn new(result: DataflowResult) -> Self {
+        let DataflowResult {
+            uuid,
+            mut node_results,
+            ..
+        } = result;
+        node_results.remove(&NodeId::from(TEST_NODE.to_owned()));
+        Self { uuid, node_results }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.node_results.values().all(|result| result.is_ok())
+    }
+
+    /// Result of the given node, or `None` if the dataflow has no such node.
+    pub fn node(&self, node_id: &str) -> Option<&Result<(), NodeError>> {
+        self.node_results.get(&NodeId::from(node_id.to_owned()))
+    }
+
+    /// Panics if any node failed.
+    #[track_caller]
+    pub fn assert_success(&self) {
+        let failed: Vec<_> = self
+            .node_results
+            .iter()
+            .filter_map(|(node_id, result)| result.as_ref().err().map(|err| (node_id, err)))
+            .map(|(node_id, err)| format!("  {node_id}: {err}"))
+            .collect();
+        if !failed.is_empty() {
+            panic!(
+                "nodes of dataflow {} failed:\n{}",
+                self.uuid,
+                failed.join("\n")
+            );
+        }
+    }
+
+    /// Panics if the given node didn't finish successfully.
+    #[track_caller]
+    pub fn assert_node_succeeded(&self, node_id: &str) {
+        match self.node(node_id) {
+            Some(Ok(())) => {}
+            Some(Err(err)) => panic!("node `{node_id}` failed: {err}"),
+            None => panic!("dataflow has no node `{node_id}`"),
+        }
+    }
+
+    /// Returns the error of the given node, panicking if it didn't fail.
+    #[track_caller]
+    pub fn assert_node_failed(&self, node_id: &str) -> &NodeError {
+        match self.node(node_id) {
+            Some(Err(err)) => err,
+            Some(Ok(())) => panic!("node `{node_id}` succeeded unexpectedly"),
+            None => panic!("dataflow has no node `{node_id}`"),
+        }
+    }
+}
+
+enum Backend {
+    /// Daemon without coordinator, like `dora run`.
+    Daemon {
+        result: JoinHandle<eyre::Result<DataflowResult>>,
+        /// Stops the dataflow when sent to or dropped.
+        stop: oneshot::Sender<()>,
+    },
+    /// Coordinator with a single daemon, like `dora up` and `dora start`.
+    Coordinator {
+        uuid: Uuid,
+        control: mpsc::Sender<Event>,
+        coordinator: JoinHandle<eyre::Result<()>>,
+        daemon: JoinHandle<eyre::Result<()>>,
+    },
+}
+
+impl Backend {
+    /// Returns the backend and the port on which the daemon listens for the
+    /// test node, which authenticates with the given `token`.
+    async fn start_daemon(
+        descriptor: Descriptor,
+        working_dir: PathBuf,
+        token: NodeToken,
+    ) -> eyre::Result<(Self, u16)> {
+        let (listen_port_tx, listen_port) = oneshot::channel();
+        let (stop, stop_rx) = oneshot::channel();
+        let result = tokio::spawn(Daemon::run_dataflow_in_process(
+            descriptor,
+            working_dir,
+            listen_port_tx,
+            Some(token),
+            async {
+                let _ = stop_rx.await;
+            },
+        ));
+        match listen_port.await {
+            Ok(listen_port) => Ok((Self::Daemon { result, stop }, listen_port)),
+            Err(_) => {
+                let err = match result.await {
+                    Ok(result) => result
+                        .err()
+                        .unwrap_or_else(|| eyre!("daemon exited before spawning the dataflow")),
+                    Err(err) => eyre!(err).wrap_err("daemon panicked"),
+                };
+                Err(err.wrap_err("failed to spawn dataflow"))
+            }
+        }
+    }
+
+    async fn start_with_coordinator(
+        descriptor: Descriptor,
+        working_dir: PathBuf,
+        token: NodeToken,
+    ) -> eyre::Result<(Self, u16)> {
+        let (control, control_rx) = mpsc::channel(10);
+        let (port, coordinator) = dora_coordinator::start_in_process(
+            (LOCALHOST, 0).into(),
+            (LOCALHOST, 0).into(),
+            ReceiverStream::new(control_rx),
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .wrap_err("failed to start coordinator")?;
+        let coordinator = tokio::spawn(coordinator);
+
+        let (listen_port_tx, listen_port) = oneshot::channel();
+        let daemon = tokio::spawn(Daemon::run_in_process(
+            (LOCALHOST, port).into(),
+            String::new(),
+            (LOCALHOST, 0).into(),
+            0,
+            listen_port_tx,
+            Some(token),
+        ));
+        let Ok(listen_port) = listen_port.await else {
+            let err = match daemon.await {
+                Ok(result) => result
+                    .err()
+                    .unwrap_or_else(|| eyre!("daemon exited before registering")),
+                Err(err) => eyre!(err).wrap_err("daemon panicked"),
+            };
+            return Err(err.wrap_err("failed to start daemon"));
+        };
+
+        let reply = control_request(
+            &control,
+            ControlRequest::Start {
+                dataflow: descriptor,
+                name: None,
+                local_working_dir: working_dir,
+                signed: None,
+            },
+        )
+        .await
+        .wrap_err("failed to start dataflow")?;
+        let uuid = match reply {
+            ControlRequestReply::DataflowStarted { uuid } => uuid,
+            other => bail!("unexpected reply to start request: {other:?}"),
+        };
+        let backend = Self::Coordinator {
+            uuid,
+            control,
+            coordinator,
+            daemon,
+        };
+        Ok((backend, listen_port))
+    }
+
+    async fn finish(self, stop: bool, timeout: Duration) -> eyre::Result<DataflowResult> {
+        match self {
+            Backend::Daemon {
+                mut result,
+                stop: stop_tx,
+            } => {
+                let stop_tx = if stop {
+                    let _ = stop_tx.send(());
+                    None
+                } else {
+                    Some(stop_tx)
+                };
+                match tokio::time::timeout(timeout, &mut result).await {
+                    Ok(result) => result.wrap_err("daemon panicked")?,
+                    Err(_) => {
+                        drop(stop_tx);
+                        let _ = tokio::time::timeout(DROP_TIMEOUT, result).await;
+                        bail!("dataflow did not finish within {timeout:?}");
+                    }
+                }
+            }
+            Backend::Coordinator {
+                uuid,
+                control,
+                coordinator,
+                daemon,
+            } => {
+                let result = tokio::time::timeout(timeout, wait_for_dataflow(&control, uuid, stop))
+                    .await
+                    .unwrap_or_else(|_| Err(eyre!("dataflow did not finish within {timeout:?}")));
+
+                // stops the dataflow if it is still running
+                let _ = control_request(&control, ControlRequest::Destroy).await;
+                let _ = tokio::time::timeout(DROP_TIMEOUT, async {
+                    let _ = coordinator.await;
+                    let _ = daemon.await;
+                })
+                .await;
+                result
+            }
+        }
+    }
+}
+
+async fn wait_for_dataflow(
+    control: &mpsc::Sender<Event>,
+    uuid: Uuid,
+    stop: bool,
+) -> eyre::Result<DataflowResult> {
+    let request = |dataflow_uuid| {
+        if stop {
+            ControlRequest::Stop {
+                dataflow_uuid,
+                grace_duration: None,
+            }
+        } else {
+            ControlRequest::Check { dataflow_uuid }
+        }
+    };
+    loop {
+        match control_request(control, request(uuid)).await? {
+            ControlRequestReply::DataflowStopped { result, .. } => return Ok(result),
+            ControlRequestReply::DataflowStarted { .. } => {}
+            other => bail!("unexpected reply while waiting for dataflow: {other:?}"),
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+async fn control_request(
+    control: &mpsc::Sender<Event>,
+    request: ControlRequest,
+) -> eyre::Result<ControlRequestReply> {
+    let (reply_sender, reply) = oneshot::channel();
+    control
+        .send(Event::Control(ControlEvent::IncomingRequest {
+            request,
+            reply_sender,
+        }))
+        .await
+        .map_err(|_| eyre!("coordinator exited"))?;
+    match reply.await.map_err(|_| eyre!("coordinator exited"))?? {
+        ControlRequestReply::Error(err) => bail!("{err}"),
+        reply => Ok(reply),
+    }
+}
+
+/// Outputs of the test node, i.e. the outputs that inputs of the dataflow
+/// are mapped to.
+fn injected_outputs(descriptor: &Descriptor) -> eyre::Result<BTreeSet<DataId>> {
+    let mut outputs = BTreeSet::new();
+    for node in descriptor.resolve_aliases_and_set_defaults()? {
+        if node.id.as_ref() == TEST_NODE {
+            bail!("node ID `{TEST_NODE}` is reserved for the test");
+        }
+        let inputs: Vec<_> = match &node.kind {
+            CoreNodeKind::Custom(node) => node.run_config.inputs.values().collect(),
+            CoreNodeKind::Runtime(node) => node
+                .operators
+                .iter()
+                .flat_map(|operator| operator.config.inputs.values())
+                .collect(),
+        };
+        for input in inputs {
+            if let InputMapping::User(mapping) = &input.mapping {
+                if mapping.source.as_ref() == TEST_NODE {
+                    outputs.insert(mapping.output.clone());
+                }
+            }
+        }
+    }
+    Ok(outputs)
+}
+
+/// Dynamic node through which the test sends and receives messages.
+///
+/// Captured outputs are mapped to inputs with the same name, e.g.
+/// `camera/image: camera/image`. These inputs are lossless, so that a slow
+/// test doesn't miss captured messages.
+fn test_node(injected: &BTreeSet<DataId>, captured: &BTreeSet<DataId>) -> eyre::Result<Node> {
+    let inputs: BTreeMap<_, _> = captured
+        .iter()
+        .map(|output| {
+            let input = serde_json::json!({
+                "source": output.to_string(),
+                "queue_policy": "lossless",
+            });
+            (output.to_string(), input)
+        })
+        .collect();
+    serde_json::from_value(serde_json::json!({
+        "id": TEST_NODE,
+        "path": DYNAMIC_SOURCE,
+        "inputs": inputs,
+        "outputs": injected,
+    }))
+    .wrap_err("failed to create test node")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dora_core::config::QueuePolicy;
+    use dora_message::{
+        common::{NodeErrorCause, NodeExitStatus},
+        uhlc::HLC,
+    };
+
+    const DATAFLOW: &str = r#"
+nodes:
+  - id: doubler
+    path: doubler
+    inputs:
+      number: test/number
+      tick: dora/timer/millis/100
+    outputs:
+      - doubled
+  - id: sink
+    path: sink
+    inputs:
+      doubled: doubler/doubled
+      number: test/number
+      offset: test/offset
+"#;
+
+    fn failed(code: i32) -> NodeError {
+        NodeError {
+            timestamp: HLC::default().new_timestamp(),
+            cause: NodeErrorCause::Other {
+                stderr: String::new(),
+            },
+            exit_status: NodeExitStatus::ExitCode(code),
+            flight_recording: None,
+            crash_report: None,
+        }
+    }
+
+    fn result(node_results: &[(&str, Result<(), NodeError>)]) -> TestResult {
+        TestResult::new(DataflowResult {
+            uuid: Uuid::new_v4(),
+            timestamp: HLC::default().new_timestamp(),
+            node_results: node_results
+                .iter()
+                .map(|(node_id, result)| (NodeId::from(node_id.to_string()), result.clone()))
+                .collect(),
+            stop_requests: BTreeMap::new(),
+        })
+    }
+
+    #[test]
+    fn capture_requires_known_node() {
+        let dataflow = TestDataflow::from_yaml(DATAFLOW).unwrap();
+        let dataflow = dataflow.capture("doubler/doubled").unwrap();
+        assert!(dataflow.captured.contains("doubler/doubled"));
+
+        let err = dataflow.capture("camera/image").err().unwrap();
+        assert!(err.to_string().contains("unknown node `camera`"), "{err}");
+    }
+
+    #[test]
+    fn capture_requires_node_and_output() {
+        let dataflow = TestDataflow::from_yaml(DATAFLOW).unwrap();
+        let err = dataflow.capture("doubled").err().unwrap();
+        assert!(err.to_string().contains("<node_id>/<output_id>"), "{err}");
+    }
+
+    #[test]
+    fn injected_outputs_are_inputs_mapped_to_test_node() {
+        let dataflow = TestDataflow::from_yaml(DATAFLOW).unwrap();
+        let injected = injected_outputs(&dataflow.descriptor).unwrap();
+        let expected: BTreeSet<_> = ["number", "offset"]
+            .into_iter()
+            .map(|id| DataId::from(id.to_owned()))
+            .collect();
+        assert_eq!(injected, expected);
+    }
+
+    #[test]
+    fn test_node_id_is_reserved() {
+        let dataflow = TestDataflow::from_yaml(
+            r#"
+nodes:
+  - id: test
+    path: test
+"#,
+        )
+        .unwrap();
+        let err = injected_outputs(&dataflow.descriptor).unwrap_err();
+        assert!(err.to_string().contains("reserved"), "{err}");
+    }
+
+    #[test]
+    fn test_node_receives_captured_outputs() {
+        let injected = BTreeSet::from([DataId::from("number".to_owned())]);
+        let captured = BTreeSet::from([DataId::from("doubler/doubled".to_owned())]);
+        let node = test_node(&injected, &captured).unwrap();
+
+        assert_eq!(node.id.as_ref(), TEST_NODE);
+        assert_eq!(node.path.as_deref(), Some(DYNAMIC_SOURCE));
+        let outputs: Vec<_> = node.outputs.iter().map(|id| id.as_str()).collect();
+        assert_eq!(outputs, ["number"]);
+        let input = &node.inputs[&DataId::from("doubler/doubled".to_owned())];
+        assert_eq!(input.mapping.to_string(), "doubler/doubled");
+        assert_eq!(input.queue_policy, QueuePolicy::Lossless);
+    }
+
+    #[test]
+    fn result_excludes_test_node() {
+        let result = result(&[("doubler", Ok(())), (TEST_NODE, Err(failed(1)))]);
+        assert!(result.node(TEST_NODE).is_none());
+        assert!(result.is_ok());
+        result.assert_success();
+        result.assert_node_succeeded("doubler");
+    }
+
+    #[test]
+    fn result_reports_failed_node() {
+        let result = result(&[("doubler", Ok(())), ("sink", Err(failed(3)))]);
+        assert!(!result.is_ok());
+        let err = result.assert_node_failed("sink");
+        assert!(matches!(err.exit_status, NodeExitStatus::ExitCode(3)));
+    }
+
+    #[test]
+    #[should_panic(expected = "sink: exited with code 3")]
+    fn assert_success_lists_failed_nodes() {
+        result(&[("doubler", Ok(())), ("sink", Err(failed(3)))]).assert_success();
+    }
+
+    #[test]
+    #[should_panic(expected = "dataflow has no node `camera`")]
+    fn assert_on_unknown_node() {
+        result(&[("doubler", Ok(()))]).assert_node_succeeded("camera");
+    }
+}
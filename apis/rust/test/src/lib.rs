@@ -0,0 +1,49 @@
+//! Integration tests of whole dataflows, run inside of the test process.
+//!
+//! [`TestDataflow`] runs a dataflow on a daemon that is started inside of the
+//! test process, optionally together with a coordinator. So tests don't
+//! depend on a `dora` installation or on example scripts that are run in a
+//! separate process. The test itself takes the place of a node with ID
+//! [`TEST_NODE`]:
+//!
+//! - inputs that are mapped to `test/<output>` in the descriptor are fed by
+//!   the test through [`RunningDataflow::send`],
+//! - outputs that are captured through [`TestDataflow::capture`] are received
+//!   by the test through [`RunningDataflow::recv`], with a timeout.
+//!
+//! Once the dataflow is finished, the [`TestResult`] contains the result of
+//! each node.
+//!
+//! ```no_run
+//! use dora_node_api::arrow::array::UInt64Array;
+//! use dora_test::TestDataflow;
+//! use std::time::Duration;
+//!
+//! let mut dataflow = TestDataflow::from_yaml(
+//!     r#"
+//! nodes:
+//!   - id: doubler
+//!     path: ../../target/debug/doubler
+//!     inputs:
+//!       number: test/number
+//!     outputs:
+//!       - doubled
+//! "#,
+//! )
+//! .unwrap()
+//! .capture("doubler/doubled")
+//! .unwrap()
+//! .start()
+//! .unwrap();
+//!
+//! dataflow.send("number", UInt64Array::from(vec![21])).unwrap();
+//! let output = dataflow.recv(Duration::from_secs(5)).unwrap();
+//! assert_eq!(output.id.as_str(), "doubler/doubled");
+//!
+//! let result = dataflow.wait(Duration::from_secs(5)).unwrap();
+//! result.assert_success();
+//! ```
+
+pub use dataflow::{Output, OutputsClosed, RunningDataflow, TestDataflow, TestResult, TEST_NODE};
+
+mod dataflow;
@@ -0,0 +1,724 @@
+use aligned_vec::{AVec, ConstAlign};
+use dora_core::{
+    config::{DataId, NodeId, NodeRunConfig},
+    descriptor::{CoreNodeKind, Descriptor, DescriptorExt},
+    uhlc::{self, HLC},
+};
+use dora_message::{
+    daemon_to_node::{DaemonCommunication, DaemonReply, NodeConfig, NodeDropEvent, NodeEvent},
+    metadata::Metadata,
+    node_to_daemon::{
+        DaemonRequest, DataMessage, DropToken, NodeToken, OutputMessage, Timestamped,
+    },
+};
+use dora_node_api::{
+    arrow::array::{make_array, Array},
+    arrow_utils::{copy_array_into_sample, required_data_size},
+    ArrowData, DoraNode, EventStream, RawData,
+};
+use eyre::{bail, eyre, Context};
+use shared_memory_extended::ShmemConf;
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    io::{Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    path::Path,
+    process::Command,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+use uuid::{NoContext, Timestamp, Uuid};
+
+/// Source node of the inputs of nodes created through [`MockDaemon::new`].
+const MOCK_SOURCE: &str = "mock";
+
+/// Output sent by the node under test.
+#[derive(Debug)]
+pub struct Output {
+    pub id: DataId,
+    pub metadata: Metadata,
+    pub data: ArrowData,
+}
+
+/// Serves a single node in place of a dora daemon.
+///
+/// The mock daemon listens on a local TCP port. Nodes connect to it through
+/// the config returned by [`Self::node_config`], which is also used by
+/// [`Self::init_node`] and [`Self::command`]. Events are delivered to the
+/// node in the order in which they are scripted.
+///
+/// Dropping the mock daemon closes the event stream of the node.
+pub struct MockDaemon {
+    node_config: NodeConfig,
+    shared: Arc<Shared>,
+    socket_addr: SocketAddr,
+}
+
+impl MockDaemon {
+    /// Creates a mock daemon for a node with the given inputs and outputs.
+    pub fn new(node_id: &str, inputs: &[&str], outputs: &[&str]) -> eyre::Result<Self> {
+        let mut yaml = format!("nodes:\n  - id: {node_id}\n    path: {node_id}\n");
+        if !inputs.is_empty() {
+            yaml.push_str("    inputs:\n");
+            for input in inputs {
+                yaml.push_str(&format!("      {input}: {MOCK_SOURCE}/{input}\n"));
+            }
+        }
+        if !outputs.is_empty() {
+            yaml.push_str("    outputs:\n");
+            for output in outputs {
+                yaml.push_str(&format!("      - {output}\n"));
+            }
+        }
+        let descriptor = Descriptor::parse(yaml.into_bytes())
+            .wrap_err("failed to create dataflow descriptor for mock node")?;
+        Self::from_descriptor(descriptor, node_id)
+    }
+
+    /// Creates a mock daemon for the given node of a dataflow YAML file.
+    pub fn from_dataflow(dataflow: &Path, node_id: &str) -> eyre::Result<Self> {
+        let descriptor = Descriptor::blocking_read(dataflow)?;
+        Self::from_descriptor(descriptor, node_id)
+    }
+
+    /// Creates a mock daemon for the given node of a dataflow.
+    pub fn from_descriptor(descriptor: Descriptor, node_id: &str) -> eyre::Result<Self> {
+        let node_id = NodeId::from(node_id.to_owned());
+        let node = descriptor
+            .resolve_aliases_and_set_defaults()?
+            .into_iter()
+            .find(|node| node.id == node_id)
+            .ok_or_else(|| eyre!("node `{node_id}` not found in dataflow"))?;
+        let run_config = match node.kind {
+            CoreNodeKind::Custom(node) => node.run_config,
+            CoreNodeKind::Runtime(_) => {
+                bail!("runtime nodes are not supported, test operators through `OperatorHarness`")
+            }
+        };
+
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .wrap_err("failed to bind mock daemon socket")?;
+        let socket_addr = listener
+            .local_addr()
+            .wrap_err("failed to get mock daemon address")?;
+        let shared = Arc::new(Shared::new(node_id.clone(), &run_config));
+        let listener_shared = shared.clone();
+        std::thread::spawn(move || {
+            for connection in listener.incoming() {
+                if listener_shared.state().shutdown {
+                    break;
+                }
+                let Ok(connection) = connection else {
+                    continue;
+                };
+                let shared = listener_shared.clone();
+                std::thread::spawn(move || handle_connection(connection, &shared));
+            }
+        });
+
+        let node_config = NodeConfig {
+            dataflow_id: Uuid::new_v7(Timestamp::now(NoContext)),
+            node_id,
+            run_config,
+            daemon_communication: DaemonCommunication::Tcp { socket_addr },
+            token: NodeToken::generate(),
+            dataflow_descriptor: descriptor,
+            dynamic: false,
+        };
+        Ok(Self {
+            node_config,
+            shared,
+            socket_addr,
+        })
+    }
+
+    pub fn node_config(&self) -> NodeConfig {
+        self.node_config.clone()
+    }
+
+    /// Initializes the node in the current process.
+    pub fn init_node(&self) -> eyre::Result<(DoraNode, EventStream)> {
+        DoraNode::init(self.node_config())
+    }
+
+    /// Prepares a command that runs the given node executable against this
+    /// mock daemon.
+    pub fn command(&self, program: impl AsRef<std::ffi::OsStr>) -> eyre::Result<Command> {
+        let config =
+            serde_yaml::to_string(&self.node_config).wrap_err("failed to serialize node config")?;
+        let mut command = Command::new(program);
+        command.env("DORA_NODE_CONFIG", config);
+        Ok(command)
+    }
+
+    /// Creates a timestamp for the given time since the UNIX epoch.
+    ///
+    /// Use this with [`Self::send_input_with_timestamp`] to test time-based
+    /// logic of nodes. Timestamps should not lie in the future, since nodes
+    /// warn about clock drift in this case.
+    pub fn timestamp(&self, time_since_epoch: Duration) -> uhlc::Timestamp {
+        uhlc::Timestamp::new(
+            uhlc::NTP64::from(time_since_epoch),
+            *self.shared.clock.get_id(),
+        )
+    }
+
+    /// Sends an input with the current time as timestamp.
+    pub fn send_input(&self, input_id: &str, data: impl Array) -> eyre::Result<()> {
+        self.send_input_with_timestamp(input_id, data, self.shared.clock.new_timestamp())
+    }
+
+    /// Sends an input with the given timestamp.
+    pub fn send_input_with_timestamp(
+        &self,
+        input_id: &str,
+        data: impl Array,
+        timestamp: uhlc::Timestamp,
+    ) -> eyre::Result<()> {
+        let input_id = DataId::from(input_id.to_owned());
+        if !self.node_config.run_config.inputs.contains_key(&input_id) {
+            bail!("node has no input `{input_id}`");
+        }
+
+        let data = data.into_data();
+        let mut sample: AVec<u8, ConstAlign<128>> =
+            AVec::__from_elem(128, 0, required_data_size(&data));
+        let type_info = copy_array_into_sample(&mut sample, &data);
+        let metadata = Metadata::from_parameters(timestamp, type_info, Default::default());
+        self.shared.push_event(
+            NodeEvent::Input {
+                id: input_id,
+                metadata,
+                data: Some(DataMessage::Vec(sample)),
+            },
+            timestamp,
+        );
+        Ok(())
+    }
+
+    /// Closes the given input, like a daemon does when the sending node exits.
+    ///
+    /// Once all inputs are closed, the event stream of the node ends.
+    pub fn close_input(&self, input_id: &str) {
+        let input_id = DataId::from(input_id.to_owned());
+        let all_closed = {
+            let mut state = self.shared.state();
+            state.open_inputs.remove(&input_id) && state.open_inputs.is_empty()
+        };
+        let timestamp = self.shared.clock.new_timestamp();
+        self.shared
+            .push_event(NodeEvent::InputClosed { id: input_id }, timestamp);
+        if all_closed {
+            self.shared
+                .push_event(NodeEvent::AllInputsClosed, timestamp);
+        }
+    }
+
+    /// Sends a `Stop` event to the node.
+    pub fn stop(&self) {
+        self.shared
+            .push_event(NodeEvent::Stop, self.shared.clock.new_timestamp());
+    }
+
+    /// Waits until the node sends an output, up to the given timeout.
+    pub fn recv_output(&self, timeout: Duration) -> Option<Output> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.shared.state();
+        loop {
+            if let Some(output) = state.outputs.pop_front() {
+                return Some(output);
+            }
+            let remaining = deadline.checked_duration_since(Instant::now())?;
+            state = self
+                .shared
+                .changed
+                .wait_timeout(state, remaining)
+                .unwrap()
+                .0;
+        }
+    }
+
+    /// Returns all outputs that were sent and not received yet.
+    pub fn outputs(&self) -> Vec<Output> {
+        self.shared.state().outputs.drain(..).collect()
+    }
+
+    /// Returns the outputs that the node closed.
+    pub fn closed_outputs(&self) -> BTreeSet<DataId> {
+        self.shared.state().closed_outputs.clone()
+    }
+
+    /// Returns the reason if the node requested to stop the dataflow.
+    pub fn stop_request(&self) -> Option<String> {
+        self.shared.state().stop_request.clone()
+    }
+
+    /// Returns the number of inputs that the node dropped because their
+    /// queue was full, as reported through backpressure requests.
+    pub fn dropped_inputs(&self) -> BTreeMap<DataId, u32> {
+        self.shared.state().dropped_inputs.clone()
+    }
+}
+
+impl Drop for MockDaemon {
+    fn drop(&mut self) {
+        self.shared.state().shutdown = true;
+        self.shared.changed.notify_all();
+        // wake up the listener thread
+        let _ = TcpStream::connect(self.socket_addr);
+    }
+}
+
+struct Shared {
+    node_id: NodeId,
+    clock: HLC,
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    events: VecDeque<Timestamped<NodeEvent>>,
+    open_inputs: BTreeSet<DataId>,
+    outputs: VecDeque<Output>,
+    finished_drop_tokens: Vec<DropToken>,
+    closed_outputs: BTreeSet<DataId>,
+    stop_request: Option<String>,
+    blackboard: BTreeMap<String, Vec<u8>>,
+    blackboard_watches: BTreeSet<String>,
+    dropped_inputs: BTreeMap<DataId, u32>,
+    shutdown: bool,
+}
+
+impl Shared {
+    fn new(node_id: NodeId, run_config: &NodeRunConfig) -> Self {
+        Self {
+            node_id,
+            clock: HLC::default(),
+            state: Mutex::new(State {
+                open_inputs: run_config.inputs.keys().cloned().collect(),
+                ..Default::default()
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    fn push_event(&self, event: NodeEvent, timestamp: uhlc::Timestamp) {
+        self.state().events.push_back(Timestamped {
+            inner: event,
+            timestamp,
+        });
+        self.changed.notify_all();
+    }
+
+    /// Waits until the given condition is true or the mock daemon is dropped.
+    fn wait_until(&self, condition: impl Fn(&State) -> bool) -> MutexGuard<'_, State> {
+        let mut state = self.state();
+        while !condition(&state) && !state.shutdown {
+            state = self.changed.wait(state).unwrap();
+        }
+        state
+    }
+
+    fn handle_request(&self, request: DaemonRequest) -> DaemonReply {
+        match request {
+            DaemonRequest::Register(request) => DaemonReply::Result(request.check_version()),
+            DaemonRequest::Subscribe
+            | DaemonRequest::SubscribeDrop
+            | DaemonRequest::OutputsDone
+            | DaemonRequest::EventStreamDropped
+            | DaemonRequest::ReportHealth { .. }
+            | DaemonRequest::SaveState { .. }
+            | DaemonRequest::Resign { .. }
+            | DaemonRequest::SubscribeNodeOutput { .. } => DaemonReply::Result(Ok(())),
+            DaemonRequest::RestoredState => DaemonReply::RestoredState(None),
+            DaemonRequest::BlackboardGet { key } => {
+                DaemonReply::BlackboardValue(self.state().blackboard.get(&key).cloned())
+            }
+            DaemonRequest::BlackboardSet { key, value } => {
+                let mut state = self.state();
+                let previous = match &value {
+                    Some(value) => state.blackboard.insert(key.clone(), value.clone()),
+                    None => state.blackboard.remove(&key),
+                };
+                let watched = state
+                    .blackboard_watches
+                    .iter()
+                    .any(|prefix| key.starts_with(prefix.as_str()));
+                if previous != value && watched {
+                    state.events.push_back(Timestamped {
+                        inner: NodeEvent::BlackboardChanged { key, value },
+                        timestamp: self.clock.new_timestamp(),
+                    });
+                    self.changed.notify_all();
+                }
+                DaemonReply::Result(Ok(()))
+            }
+            DaemonRequest::Campaign { group } => {
+                // the node is the only candidate
+                self.push_event(
+                    NodeEvent::LeadershipChanged {
+                        group,
+                        leader: Some(self.node_id.clone()),
+                        is_leader: true,
+                    },
+                    self.clock.new_timestamp(),
+                );
+                DaemonReply::Result(Ok(()))
+            }
+            DaemonRequest::BlackboardWatch { prefix } => {
+                self.state().blackboard_watches.insert(prefix);
+                DaemonReply::Result(Ok(()))
+            }
+            DaemonRequest::CloseOutputs(outputs) => {
+                self.state().closed_outputs.extend(outputs);
+                DaemonReply::Result(Ok(()))
+            }
+            DaemonRequest::StopDataflow { reason } => {
+                self.state().stop_request = Some(reason);
+                DaemonReply::Result(Ok(()))
+            }
+            DaemonRequest::SendMessage {
+                output_id,
+                metadata,
+                data,
+            } => {
+                self.receive_output(output_id, metadata, data);
+                DaemonReply::Empty
+            }
+            DaemonRequest::SendMessages { messages } => {
+                for OutputMessage {
+                    output_id,
+                    metadata,
+                    data,
+                } in messages
+                {
+                    self.receive_output(output_id, metadata, data);
+                }
+                DaemonReply::Empty
+            }
+            DaemonRequest::NextEvent { .. } => {
+                // an empty reply closes the event stream
+                let mut state = self.wait_until(|state| !state.events.is_empty());
+                DaemonReply::NextEvents(state.events.drain(..).collect())
+            }
+            DaemonRequest::NextFinishedDropTokens => {
+                let mut state = self.wait_until(|state| !state.finished_drop_tokens.is_empty());
+                let events = state
+                    .finished_drop_tokens
+                    .drain(..)
+                    .map(|drop_token| Timestamped {
+                        inner: NodeDropEvent::OutputDropped { drop_token },
+                        timestamp: self.clock.new_timestamp(),
+                    })
+                    .collect();
+                DaemonReply::NextDropEvents(events)
+            }
+            DaemonRequest::ReportBackpressure {
+                input_id, dropped, ..
+            } => {
+                *self.state().dropped_inputs.entry(input_id).or_default() += dropped;
+                DaemonReply::Empty
+            }
+            DaemonRequest::ReportDropTokens { .. } | DaemonRequest::Log { .. } => {
+                DaemonReply::Empty
+            }
+            DaemonRequest::NodeConfig { .. } => DaemonReply::NodeConfig {
+                result: Err("not supported by the mock daemon".into()),
+            },
+        }
+    }
+
+    fn receive_output(&self, output_id: DataId, metadata: Metadata, data: Option<DataMessage>) {
+        let data = match self.read_data(data, &metadata) {
+            Ok(data) => data,
+            Err(err) => {
+                tracing::warn!("failed to read output `{output_id}`: {err:?}");
+                return;
+            }
+        };
+        self.state().outputs.push_back(Output {
+            id: output_id,
+            metadata,
+            data,
+        });
+        self.changed.notify_all();
+    }
+
+    fn read_data(&self, data: Option<DataMessage>, metadata: &Metadata) -> eyre::Result<ArrowData> {
+        let raw = match data {
+            None => RawData::Empty,
+            Some(DataMessage::Vec(data)) => RawData::Vec(data),
+            Some(DataMessage::SharedMemory {
+                shared_memory_id,
+                len,
+                drop_token,
+            }) => {
+                let memory = ShmemConf::new()
+                    .os_id(shared_memory_id)
+                    .open()
+                    .wrap_err("failed to map shared memory output")?;
+                let data = AVec::from_slice(128, &unsafe { memory.as_slice() }[..len]);
+                // the data was copied, so the sender can reuse the memory
+                self.state().finished_drop_tokens.push(drop_token);
+                RawData::Vec(data)
+            }
+        };
+        let data = raw.into_arrow_array(&metadata.type_info)?;
+        Ok(ArrowData(make_array(data)))
+    }
+}
+
+fn handle_connection(mut connection: TcpStream, shared: &Shared) {
+    let _ = connection.set_nodelay(true);
+    loop {
+        let request = match tcp_receive(&mut connection) {
+            Ok(raw) => match bincode::deserialize::<Timestamped<DaemonRequest>>(&raw) {
+                Ok(request) => request,
+                Err(err) => {
+                    tracing::warn!("mock daemon received invalid request: {err}");
+                    break;
+                }
+            },
+            Err(_) => break,
+        };
+        if let Err(err) = shared.clock.update_with_timestamp(&request.timestamp) {
+            tracing::warn!("failed to update HLC: {err}");
+        }
+        let bincode_reply = request.inner.expects_tcp_bincode_reply();
+        let json_reply = request.inner.expects_tcp_json_reply();
+        let reply = shared.handle_request(request.inner);
+        let serialized = if bincode_reply {
+            bincode::serialize(&reply).map_err(|err| eyre!(err))
+        } else if json_reply {
+            serde_json::to_vec(&reply).map_err(|err| eyre!(err))
+        } else {
+            continue;
+        };
+        let result = serialized
+            .and_then(|reply| tcp_send(&mut connection, &reply).wrap_err("failed to send reply"));
+        if let Err(err) = result {
+            tracing::warn!("mock daemon failed to reply: {err:?}");
+            break;
+        }
+    }
+}
+
+fn tcp_send(connection: &mut impl Write, message: &[u8]) -> std::io::Result<()> {
+    let len_raw = (message.len() as u64).to_le_bytes();
+    connection.write_all(&len_raw)?;
+    connection.write_all(message)?;
+    connection.flush()
+}
+
+fn tcp_receive(connection: &mut impl Read) -> std::io::Result<Vec<u8>> {
+    let len = {
+        let mut raw = [0; 8];
+        connection.read_exact(&mut raw)?;
+        u64::from_le_bytes(raw) as usize
+    };
+    let mut message = vec![0; len];
+    connection.read_exact(&mut message)?;
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dora_node_api::{arrow::array::UInt64Array, Event, IntoArrow, ZERO_COPY_THRESHOLD};
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn next_event(events: &mut EventStream) -> Event {
+        events
+            .recv_timeout(TIMEOUT)
+            .expect("event stream closed unexpectedly")
+    }
+
+    #[test]
+    fn inputs_are_delivered_with_timestamp() {
+        let daemon = MockDaemon::new("doubler", &["number"], &["doubled"]).unwrap();
+        let (_node, mut events) = daemon.init_node().unwrap();
+
+        let timestamp = daemon.timestamp(Duration::from_secs(1_700_000_000));
+        daemon
+            .send_input_with_timestamp("number", UInt64Array::from(vec![21]), timestamp)
+            .unwrap();
+        match next_event(&mut events) {
+            Event::Input { id, metadata, data } => {
+                assert_eq!(id.as_str(), "number");
+                assert_eq!(metadata.timestamp(), timestamp);
+                assert_eq!(u64::try_from(&data).unwrap(), 21);
+            }
+            other => panic!("expected input, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn unknown_inputs_are_rejected() {
+        let daemon = MockDaemon::new("doubler", &["number"], &["doubled"]).unwrap();
+        let err = daemon
+            .send_input("image", UInt64Array::from(vec![1]))
+            .unwrap_err();
+        assert!(err.to_string().contains("no input `image`"), "{err}");
+    }
+
+    #[test]
+    fn outputs_are_captured() {
+        let daemon = MockDaemon::new("doubler", &[], &["doubled"]).unwrap();
+        let (mut node, _events) = daemon.init_node().unwrap();
+
+        node.send_output(
+            DataId::from("doubled".to_owned()),
+            Default::default(),
+            42u64.into_arrow(),
+        )
+        .unwrap();
+        let output = daemon.recv_output(TIMEOUT).expect("no output received");
+        assert_eq!(output.id.as_str(), "doubled");
+        assert_eq!(u64::try_from(&output.data).unwrap(), 42);
+        assert!(daemon.outputs().is_empty());
+        assert!(daemon.recv_output(Duration::from_millis(10)).is_none());
+    }
+
+    #[test]
+    fn shared_memory_outputs_are_captured() {
+        let daemon = MockDaemon::new("camera", &[], &["image"]).unwrap();
+        let (mut node, _events) = daemon.init_node().unwrap();
+
+        let image: Vec<u8> = (0..ZERO_COPY_THRESHOLD * 2).map(|i| i as u8).collect();
+        node.send_output(
+            DataId::from("image".to_owned()),
+            Default::default(),
+            image.clone().into_arrow(),
+        )
+        .unwrap();
+        let output = daemon.recv_output(TIMEOUT).expect("no output received");
+        assert_eq!(Vec::<u8>::try_from(&output.data).unwrap(), image);
+    }
+
+    #[test]
+    fn closing_all_inputs_ends_event_stream() {
+        let daemon = MockDaemon::new("sink", &["a", "b"], &[]).unwrap();
+        let (_node, mut events) = daemon.init_node().unwrap();
+
+        daemon.close_input("a");
+        match next_event(&mut events) {
+            Event::InputClosed { id } => assert_eq!(id.as_str(), "a"),
+            other => panic!("expected closed input, got {other:?}"),
+        }
+        daemon.close_input("b");
+        match next_event(&mut events) {
+            Event::InputClosed { id } => assert_eq!(id.as_str(), "b"),
+            other => panic!("expected closed input, got {other:?}"),
+        }
+        assert!(events.recv_timeout(TIMEOUT).is_none());
+    }
+
+    #[test]
+    fn queue_overflows_are_reported_with_timeout() {
+        let descriptor = Descriptor::parse(
+            b"nodes:\n  - id: sink\n    path: sink\n    inputs:\n      tick:\n        source: mock/tick\n        queue_size: 1\n"
+                .to_vec(),
+        )
+        .unwrap();
+        let daemon = MockDaemon::from_descriptor(descriptor, "sink").unwrap();
+        let (_node, mut events) = daemon.init_node().unwrap();
+
+        for i in 0..5u64 {
+            daemon
+                .send_input("tick", UInt64Array::from(vec![i]))
+                .unwrap();
+        }
+        // backpressure is reported at most every 100ms
+        std::thread::sleep(Duration::from_millis(200));
+        match next_event(&mut events) {
+            Event::Input { data, .. } => assert_eq!(u64::try_from(&data).unwrap(), 4),
+            other => panic!("expected input, got {other:?}"),
+        }
+        // the node doesn't wait for a reply to its report
+        let deadline = Instant::now() + TIMEOUT;
+        while daemon.dropped_inputs().is_empty() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(
+            daemon.dropped_inputs(),
+            BTreeMap::from([(DataId::from("tick".to_owned()), 4)])
+        );
+    }
+
+    #[test]
+    fn stop_is_delivered() {
+        let daemon = MockDaemon::new("sink", &["a"], &[]).unwrap();
+        let (_node, mut events) = daemon.init_node().unwrap();
+
+        daemon.stop();
+        assert!(matches!(next_event(&mut events), Event::Stop));
+    }
+
+    #[test]
+    fn closed_outputs_and_stop_requests_are_recorded() {
+        let daemon = MockDaemon::new("source", &[], &["a", "b"]).unwrap();
+        let (mut node, _events) = daemon.init_node().unwrap();
+        assert!(daemon.closed_outputs().is_empty());
+        assert_eq!(daemon.stop_request(), None);
+
+        node.close_outputs(vec![DataId::from("a".to_owned())])
+            .unwrap();
+        node.stop_dataflow("done").unwrap();
+        assert_eq!(
+            daemon.closed_outputs(),
+            BTreeSet::from([DataId::from("a".to_owned())])
+        );
+        assert_eq!(daemon.stop_request().as_deref(), Some("done"));
+    }
+
+    #[test]
+    fn blackboard_values_are_stored() {
+        let daemon = MockDaemon::new("node", &[], &[]).unwrap();
+        let (mut node, _events) = daemon.init_node().unwrap();
+
+        assert_eq!(node.blackboard_get("mode").unwrap(), None);
+        node.blackboard_set("mode", b"auto".to_vec()).unwrap();
+        assert_eq!(node.blackboard_get("mode").unwrap(), Some(b"auto".to_vec()));
+        node.blackboard_remove("mode").unwrap();
+        assert_eq!(node.blackboard_get("mode").unwrap(), None);
+    }
+
+    #[test]
+    fn watched_blackboard_changes_are_delivered() {
+        let daemon = MockDaemon::new("node", &["tick"], &[]).unwrap();
+        let (mut node, mut events) = daemon.init_node().unwrap();
+
+        node.blackboard_watch("robot/").unwrap();
+        node.blackboard_set("other", b"ignored".to_vec()).unwrap();
+        node.blackboard_set("robot/mode", b"auto".to_vec()).unwrap();
+        // unchanged values are not reported again
+        node.blackboard_set("robot/mode", b"auto".to_vec()).unwrap();
+        node.blackboard_remove("robot/mode").unwrap();
+
+        match next_event(&mut events) {
+            Event::BlackboardChanged { key, value } => {
+                assert_eq!(key, "robot/mode");
+                assert_eq!(value, Some(b"auto".to_vec()));
+            }
+            other => panic!("expected blackboard change, got {other:?}"),
+        }
+        match next_event(&mut events) {
+            Event::BlackboardChanged { key, value } => {
+                assert_eq!(key, "robot/mode");
+                assert_eq!(value, None);
+            }
+            other => panic!("expected blackboard change, got {other:?}"),
+        }
+        // times out, as no other change is reported
+        assert!(matches!(
+            events.recv_timeout(Duration::from_millis(100)),
+            Some(Event::Error(_))
+        ));
+    }
+}
@@ -0,0 +1,37 @@
+//! Utilities for unit-testing dora nodes and operators.
+//!
+//! Testing a node or an operator normally requires a running daemon and a
+//! dataflow YAML file. This crate provides test doubles instead:
+//!
+//! - [`MockDaemon`] serves a single node, either in the same process through
+//!   [`MockDaemon::init_node`] or as separate process through
+//!   [`MockDaemon::command`]. Tests feed inputs with controlled timestamps
+//!   and assert on the outputs that the node sends.
+//! - [`OperatorHarness`] calls a Rust operator directly and collects its
+//!   outputs.
+//!
+//! ```no_run
+//! use dora_node_api::{arrow::array::UInt64Array, Event};
+//! use dora_testing::MockDaemon;
+//! use std::time::Duration;
+//!
+//! let daemon = MockDaemon::new("doubler", &["number"], &["doubled"]).unwrap();
+//! let mut node = daemon
+//!     .command("target/debug/doubler")
+//!     .unwrap()
+//!     .spawn()
+//!     .unwrap();
+//!
+//! daemon.send_input("number", UInt64Array::from(vec![21])).unwrap();
+//! let output = daemon.recv_output(Duration::from_secs(5)).unwrap();
+//! assert_eq!(output.id.as_str(), "doubled");
+//!
+//! daemon.stop();
+//! node.wait().unwrap();
+//! ```
+
+pub use daemon::{MockDaemon, Output};
+pub use operator::{OperatorHarness, OperatorOutput, ScheduledTimer};
+
+mod daemon;
+mod operator;
@@ -0,0 +1,202 @@
+use dora_operator_api::{
+    types::{
+        arrow::{
+            self,
+            array::{make_array, Array},
+        },
+        safer_ffi::closure::ArcDynFn1,
+        DeviceBufferOutput, DeviceBufferView, DeviceContextRequest, DeviceKind, DoraResult,
+        Output as RawOutput, RawDeviceBuffer, ScheduleTimer, SendOutput,
+    },
+    ArrowData, DoraOperator, DoraOutputSender, DoraStatus, Event, OperatorConfig,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Output sent by an operator under test.
+#[derive(Debug)]
+pub struct OperatorOutput {
+    pub id: String,
+    pub data: ArrowData,
+}
+
+/// Timer requested by an operator under test.
+///
+/// The harness doesn't run timers, tests fire them through
+/// [`OperatorHarness::timer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledTimer {
+    pub delay: Duration,
+    pub token: u64,
+}
+
+/// Calls the `on_event` method of a Rust operator and collects its outputs.
+///
+/// ```
+/// use dora_operator_api::{
+///     types::arrow::array::UInt8Array, DoraOperator, DoraOutputSender, DoraStatus, Event,
+/// };
+/// use dora_testing::OperatorHarness;
+///
+/// #[derive(Default)]
+/// struct Echo;
+///
+/// impl DoraOperator for Echo {
+///     fn on_event(
+///         &mut self,
+///         event: &Event,
+///         output_sender: &mut DoraOutputSender,
+///     ) -> Result<DoraStatus, String> {
+///         if let Event::Input { data, .. } = event {
+///             output_sender.send("echo".into(), data.0.clone())?;
+///         }
+///         Ok(DoraStatus::Continue)
+///     }
+/// }
+///
+/// let mut harness = OperatorHarness::<Echo>::new();
+/// harness.input("in", UInt8Array::from(vec![1, 2, 3])).unwrap();
+/// let outputs = harness.outputs();
+/// assert_eq!(outputs.len(), 1);
+/// assert_eq!(outputs[0].id, "echo");
+/// ```
+pub struct OperatorHarness<O> {
+    operator: O,
+    send_output: SendOutput,
+    outputs: Arc<Mutex<Vec<OperatorOutput>>>,
+    timers: Arc<Mutex<Vec<ScheduledTimer>>>,
+}
+
+impl<O: DoraOperator> OperatorHarness<O> {
+    /// Creates a harness for the default instance of the operator.
+    pub fn new() -> Self {
+        Self::with_operator(O::default())
+    }
+
+    /// Creates a harness for the given operator instance.
+    pub fn with_operator(operator: O) -> Self {
+        let outputs = Arc::new(Mutex::new(Vec::new()));
+        let collected = outputs.clone();
+        let callback = Arc::new(move |output: RawOutput| {
+            let RawOutput {
+                id,
+                data_array,
+                schema,
+                ..
+            } = output;
+            match unsafe { arrow::ffi::from_ffi(data_array, &schema) } {
+                Ok(data) => {
+                    collected.lock().unwrap().push(OperatorOutput {
+                        id: String::from(id),
+                        data: ArrowData(make_array(data)),
+                    });
+                    DoraResult::SUCCESS
+                }
+                Err(err) => DoraResult::from_error(err.to_string()),
+            }
+        });
+        let timers = Arc::new(Mutex::new(Vec::new()));
+        let scheduled = timers.clone();
+        let schedule_timer = Arc::new(move |timer: ScheduleTimer| {
+            scheduled.lock().unwrap().push(ScheduledTimer {
+                delay: Duration::from_nanos(timer.delay_ns),
+                token: timer.token,
+            });
+            DoraResult::SUCCESS
+        });
+        Self {
+            operator,
+            send_output: SendOutput {
+                send_output: ArcDynFn1::new(callback),
+                schedule_timer: ArcDynFn1::new(schedule_timer),
+                // device buffers can only be shared inside a runtime node
+                share_device_buffer: ArcDynFn1::new(Arc::new(|_: RawDeviceBuffer| 0)),
+                send_device_buffer: ArcDynFn1::new(Arc::new(|_: DeviceBufferOutput| {
+                    DoraResult::from_error("device buffers are not supported by the harness".into())
+                })),
+                device_buffer: ArcDynFn1::new(Arc::new(|_: u64| DeviceBufferView {
+                    device: DeviceKind::Cuda,
+                    device_id: 0,
+                    ptr: std::ptr::null_mut(),
+                    len: 0,
+                })),
+                device_context: ArcDynFn1::new(Arc::new(|request: DeviceContextRequest| {
+                    request.context
+                })),
+            },
+            outputs,
+            timers,
+        }
+    }
+
+    /// Calls the `on_start` hook of the operator.
+    pub fn on_start(&mut self, config: &OperatorConfig) -> Result<(), String> {
+        let mut output_sender = DoraOutputSender::new(&self.send_output);
+        self.operator.on_start(config, &mut output_sender)
+    }
+
+    /// Passes the given event to the operator.
+    pub fn on_event(&mut self, event: &Event) -> Result<DoraStatus, String> {
+        let mut output_sender = DoraOutputSender::new(&self.send_output);
+        self.operator.on_event(event, &mut output_sender)
+    }
+
+    /// Passes an input with the given ID and data to the operator.
+    pub fn input(&mut self, id: &str, data: impl Array) -> Result<DoraStatus, String> {
+        let data = ArrowData(make_array(data.into_data()));
+        self.on_event(&Event::Input { id, data })
+    }
+
+    /// Notifies the operator that the given input was closed.
+    pub fn input_closed(&mut self, id: &str) -> Result<DoraStatus, String> {
+        self.on_event(&Event::InputClosed { id })
+    }
+
+    /// Passes a `Timer` event with the given token to the operator.
+    pub fn timer(&mut self, token: u64) -> Result<DoraStatus, String> {
+        self.on_event(&Event::Timer { token })
+    }
+
+    /// Passes a `Stop` event to the operator.
+    pub fn stop(&mut self) -> Result<DoraStatus, String> {
+        self.on_event(&Event::Stop)
+    }
+
+    /// Calls the `on_error` hook of the operator.
+    pub fn on_error(&mut self, error: &str) {
+        let mut output_sender = DoraOutputSender::new(&self.send_output);
+        self.operator.on_error(error, &mut output_sender)
+    }
+
+    /// Calls the `on_stop` hook of the operator.
+    pub fn on_stop(&mut self) -> Result<(), String> {
+        let mut output_sender = DoraOutputSender::new(&self.send_output);
+        self.operator.on_stop(&mut output_sender)
+    }
+
+    /// Returns the outputs sent since the last call, in send order.
+    pub fn outputs(&mut self) -> Vec<OperatorOutput> {
+        std::mem::take(&mut *self.outputs.lock().unwrap())
+    }
+
+    /// Returns the timers that were scheduled since the last call.
+    pub fn scheduled_timers(&mut self) -> Vec<ScheduledTimer> {
+        std::mem::take(&mut *self.timers.lock().unwrap())
+    }
+
+    pub fn operator(&self) -> &O {
+        &self.operator
+    }
+
+    pub fn operator_mut(&mut self) -> &mut O {
+        &mut self.operator
+    }
+}
+
+impl<O: DoraOperator> Default for OperatorHarness<O> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
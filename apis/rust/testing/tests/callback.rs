@@ -0,0 +1,168 @@
+use std::{
+    cell::RefCell,
+    time::{Duration, Instant},
+};
+
+use dora_node_api::{arrow::array::UInt64Array, CallbackNode};
+use dora_testing::MockDaemon;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn callback_node(daemon: &MockDaemon) -> CallbackNode<'static> {
+    let (node, events) = daemon.init_node().unwrap();
+    CallbackNode::new(node, events)
+}
+
+#[test]
+fn inputs_are_dispatched_to_their_handler() {
+    let daemon = MockDaemon::new("node", &["a", "b", "unhandled"], &["out"]).unwrap();
+    let received = RefCell::new(Vec::new());
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            // wait for each input to be handled to get a deterministic order
+            for (input, value) in [("a", 1), ("b", 2), ("unhandled", 3), ("a", 4)] {
+                daemon
+                    .send_input(input, UInt64Array::from(vec![value]))
+                    .unwrap();
+                if input != "unhandled" {
+                    daemon.recv_output(TIMEOUT).expect("input was not handled");
+                }
+            }
+            daemon.stop();
+        });
+
+        let mut node = callback_node(&daemon);
+        node.on_input("a", |node, metadata, data| {
+            received.borrow_mut().push(("a", u64::try_from(&data)?));
+            node.send_output("out".to_owned().into(), metadata.parameters, data.0)
+        })
+        .on_input("b", |node, metadata, data| {
+            received.borrow_mut().push(("b", u64::try_from(&data)?));
+            node.send_output("out".to_owned().into(), metadata.parameters, data.0)
+        });
+        node.run().unwrap();
+    });
+
+    assert_eq!(received.into_inner(), [("a", 1), ("b", 2), ("a", 4)]);
+}
+
+#[test]
+fn failing_handler_stops_the_node() {
+    let daemon = MockDaemon::new("node", &["a"], &[]).unwrap();
+    daemon.send_input("a", UInt64Array::from(vec![1])).unwrap();
+    daemon.send_input("a", UInt64Array::from(vec![2])).unwrap();
+
+    let calls = RefCell::new(0);
+    let mut node = callback_node(&daemon);
+    node.on_input("a", |_node, _metadata, _data| {
+        *calls.borrow_mut() += 1;
+        eyre::bail!("invalid input")
+    });
+    let err = node.run().unwrap_err();
+
+    assert_eq!(err.to_string(), "handler for input `a` failed");
+    assert_eq!(calls.into_inner(), 1);
+}
+
+#[test]
+fn stop_event_ends_the_event_loop() {
+    let daemon = MockDaemon::new("node", &["a"], &["out"]).unwrap();
+    let events = RefCell::new(Vec::new());
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            daemon.send_input("a", UInt64Array::from(vec![1])).unwrap();
+            daemon.recv_output(TIMEOUT).expect("input was not handled");
+            daemon.stop();
+            daemon.send_input("a", UInt64Array::from(vec![2])).unwrap();
+        });
+
+        let mut node = callback_node(&daemon);
+        node.on_input("a", |node, metadata, data| {
+            events
+                .borrow_mut()
+                .push(format!("input {}", u64::try_from(&data)?));
+            node.send_output("out".to_owned().into(), metadata.parameters, data.0)
+        })
+        .on_stop(|_node| {
+            events.borrow_mut().push("stop".to_owned());
+            Ok(())
+        });
+        node.run().unwrap();
+    });
+
+    // inputs that arrive after the stop event are not handled anymore
+    assert_eq!(events.into_inner(), ["input 1", "stop"]);
+}
+
+#[test]
+fn closing_all_inputs_ends_the_event_loop() {
+    let daemon = MockDaemon::new("node", &["a", "b"], &[]).unwrap();
+    daemon.close_input("a");
+    daemon.send_input("b", UInt64Array::from(vec![1])).unwrap();
+    daemon.close_input("b");
+
+    let events = RefCell::new(Vec::new());
+    let mut node = callback_node(&daemon);
+    node.on_input("b", |_node, _metadata, _data| {
+        events.borrow_mut().push("input b".to_owned());
+        Ok(())
+    })
+    .on_input_closed(|_node, id| {
+        events.borrow_mut().push(format!("closed {id}"));
+        Ok(())
+    })
+    .on_stop(|_node| {
+        events.borrow_mut().push("stop".to_owned());
+        Ok(())
+    });
+    node.run().unwrap();
+
+    assert_eq!(events.into_inner(), ["closed a", "input b", "closed b"]);
+}
+
+#[test]
+fn due_timers_run_in_deadline_order() {
+    const FAST: Duration = Duration::from_millis(20);
+    const SLOW: Duration = Duration::from_millis(50);
+
+    let daemon = MockDaemon::new("node", &["a"], &[]).unwrap();
+    let ticks = RefCell::new(Vec::new());
+    let start = Instant::now();
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            std::thread::sleep(Duration::from_millis(300));
+            daemon.stop();
+        });
+
+        let mut node = callback_node(&daemon);
+        node.on_timer(SLOW, |_node| {
+            ticks.borrow_mut().push((SLOW, start.elapsed()));
+            Ok(())
+        })
+        .on_timer(FAST, |_node| {
+            ticks.borrow_mut().push((FAST, start.elapsed()));
+            Ok(())
+        });
+        node.run().unwrap();
+    });
+
+    let ticks = ticks.into_inner();
+    assert_eq!(ticks.first().map(|(interval, _)| *interval), Some(FAST));
+    for interval in [FAST, SLOW] {
+        let elapsed: Vec<_> = ticks
+            .iter()
+            .filter(|(i, _)| *i == interval)
+            .map(|(_, elapsed)| *elapsed)
+            .collect();
+        assert!(elapsed.len() >= 2, "{interval:?} timer ran {elapsed:?}");
+        // timers never run before they are due
+        for (n, elapsed) in (1..).zip(&elapsed) {
+            assert!(
+                *elapsed >= interval * n,
+                "{interval:?} timer ran {elapsed:?}"
+            );
+        }
+    }
+    let fast_ticks = ticks.iter().filter(|(i, _)| *i == FAST).count();
+    assert!(fast_ticks > ticks.len() - fast_ticks, "{ticks:?}");
+}
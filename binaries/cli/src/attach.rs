@@ -9,6 +9,7 @@ use notify::event::ModifyKind;
 use notify::{Config, Event as NotifyEvent, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use std::{
     collections::HashMap,
+    fmt::Write as _,
     net::{SocketAddr, TcpStream},
 };
 use std::{path::PathBuf, sync::mpsc, time::Duration};
@@ -30,6 +31,9 @@ pub fn attach_dataflow(
 
     // Generate path hashmap
     let mut node_path_lookup = HashMap::new();
+    // Python operators are also reloaded when a module next to them changes,
+    // since the runtime reloads changed helper modules as well.
+    let mut python_dir_lookup: HashMap<PathBuf, Vec<_>> = HashMap::new();
 
     let nodes = dataflow.resolve_aliases_and_set_defaults()?;
 
@@ -46,17 +50,26 @@ pub fn attach_dataflow(
             CoreNodeKind::Custom(_cn) => (),
             CoreNodeKind::Runtime(rn) => {
                 for op in rn.operators.iter() {
-                    if let dora_core::descriptor::OperatorSource::Python(python_source) =
-                        &op.config.source
-                    {
-                        let path = resolve_path(&python_source.source, &working_dir)
-                            .wrap_err_with(|| {
-                                format!("failed to resolve node source `{}`", python_source.source)
-                            })?;
-                        node_path_lookup
-                            .insert(path, (dataflow_id, node.id.clone(), Some(op.id.clone())));
+                    let (source, is_python) = match &op.config.source {
+                        dora_core::descriptor::OperatorSource::Python(python_source) => {
+                            (&python_source.source, true)
+                        }
+                        dora_core::descriptor::OperatorSource::Wasm(source) => (source, false),
+                        // Reloading shared library operators is not supported. See: https://github.com/dora-rs/dora/pull/239#discussion_r1154313139
+                        dora_core::descriptor::OperatorSource::SharedLibrary(_) => continue,
+                    };
+                    let path = resolve_path(source, &working_dir)
+                        .wrap_err_with(|| format!("failed to resolve node source `{source}`"))?;
+                    let target = (dataflow_id, node.id.clone(), Some(op.id.clone()));
+                    if is_python {
+                        if let Some(dir) = path.parent() {
+                            python_dir_lookup
+                                .entry(dir.to_owned())
+                                .or_default()
+                                .push(target.clone());
+                        }
                     }
-                    // Reloading non-python operator is not supported. See: https://github.com/dora-rs/dora/pull/239#discussion_r1154313139
+                    node_path_lookup.insert(path, target);
                 }
             }
         }
@@ -65,8 +78,17 @@ pub fn attach_dataflow(
     // Setup dataflow file watcher if reload option is set.
     let watcher_tx = tx.clone();
     let _watcher = if hot_reload {
-        let hash = node_path_lookup.clone();
-        let paths = hash.keys();
+        let mut watched_paths: Vec<PathBuf> = python_dir_lookup.keys().cloned().collect();
+        watched_paths.extend(
+            node_path_lookup
+                .keys()
+                .filter(|path| {
+                    !python_dir_lookup
+                        .keys()
+                        .any(|dir| path.parent() == Some(dir.as_path()))
+                })
+                .cloned(),
+        );
         let notifier = move |event| {
             if let Ok(NotifyEvent {
                 paths,
@@ -75,7 +97,18 @@ pub fn attach_dataflow(
             }) = event
             {
                 for path in paths {
-                    if let Some((dataflow_id, node_id, operator_id)) = node_path_lookup.get(&path) {
+                    let targets: Vec<_> = match node_path_lookup.get(&path) {
+                        Some(target) => vec![target],
+                        None if path.extension().is_some_and(|ext| ext == "py") => {
+                            python_dir_lookup
+                                .iter()
+                                .filter(|(dir, _)| path.starts_with(dir))
+                                .flat_map(|(_, targets)| targets)
+                                .collect()
+                        }
+                        None => Vec::new(),
+                    };
+                    for (dataflow_id, node_id, operator_id) in targets {
                         watcher_tx
                             .send(AttachEvent::Control(ControlRequest::Reload {
                                 dataflow_id: *dataflow_id,
@@ -95,7 +128,7 @@ pub fn attach_dataflow(
             Config::default().with_poll_interval(Duration::from_secs(1)),
         )?;
 
-        for path in paths {
+        for path in &watched_paths {
             watcher.watch(path, RecursiveMode::Recursive)?;
         }
         Some(watcher)
@@ -129,6 +162,7 @@ pub fn attach_dataflow(
         stream: TcpStream::connect(coordinator_socket)
             .wrap_err("failed to connect to dora coordinator")?,
     };
+    crate::authenticate_log_session(&mut log_session)?;
     log_session
         .send(
             &serde_json::to_vec(&ControlRequest::LogSubscribe {
@@ -164,6 +198,7 @@ pub fn attach_dataflow(
                     file: _,
                     line: _,
                     message,
+                    fields,
                 } = log_message;
                 let level = match level {
                     log::Level::Error => "ERROR".red(),
@@ -180,7 +215,15 @@ pub fn attach_dataflow(
                     None => "".normal(),
                 };
 
-                println!("{level}{node}{target}: {message}");
+                let fields = fields
+                    .iter()
+                    .fold(String::new(), |mut out, (key, value)| {
+                        let _ = write!(out, " {key}={value:?}");
+                        out
+                    })
+                    .dimmed();
+
+                println!("{level}{node}{target}: {message}{fields}");
                 continue;
             }
             Ok(AttachEvent::Log(Err(err))) => {
@@ -1,6 +1,9 @@
 use dora_core::{
     config::OperatorId,
-    descriptor::{Descriptor, DescriptorExt, NodeExt, SINGLE_OPERATOR_DEFAULT_ID},
+    descriptor::{
+        matching_target, CustomNodeExt, Descriptor, DescriptorExt, NodeExt,
+        SINGLE_OPERATOR_DEFAULT_ID,
+    },
 };
 use eyre::{eyre, Context};
 use std::{path::Path, process::Command};
@@ -21,10 +24,15 @@ pub fn build(dataflow: String, uv: bool) -> eyre::Result<()> {
 
     for node in descriptor.nodes {
         match node.kind()? {
-            dora_core::descriptor::NodeKind::Standard(_) => {
-                run_build_command(node.build.as_deref(), working_dir, uv).with_context(|| {
-                    format!("build command failed for standard node `{}`", node.id)
-                })?
+            dora_core::descriptor::NodeKind::Standard(_)
+            | dora_core::descriptor::NodeKind::Container => {
+                // build for the machine that `dora build` runs on
+                let build = matching_target(&node.targets, None)
+                    .and_then(|(_, target)| target.build.as_ref())
+                    .or(node.build.as_ref());
+                run_build_command(build.map(String::as_str), working_dir, uv).with_context(
+                    || format!("build command failed for standard node `{}`", node.id),
+                )?
             }
             dora_core::descriptor::NodeKind::Runtime(runtime_node) => {
                 for operator in &runtime_node.operators {
@@ -38,6 +46,8 @@ pub fn build(dataflow: String, uv: bool) -> eyre::Result<()> {
                 }
             }
             dora_core::descriptor::NodeKind::Custom(custom_node) => {
+                let mut custom_node = custom_node.clone();
+                custom_node.select_target(None);
                 run_build_command(custom_node.build.as_deref(), working_dir, uv).with_context(
                     || format!("build command failed for custom node `{}`", node.id),
                 )?
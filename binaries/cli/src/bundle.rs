@@ -0,0 +1,320 @@
+//! Packaged dataflows, see `dora bundle`.
+//!
+//! A `.dora` bundle is a gzip-compressed tar archive of the dataflow
+//! directory: the descriptor, its detached signature if any, and the node
+//! files that the descriptor references, at their paths relative to the
+//! descriptor. This includes the files of all `targets`, so a bundle can
+//! contain executables for multiple architectures. The `dora-bundle.yml`
+//! manifest at the root of the archive records the bundle metadata and the
+//! SHA-256 checksum of each file, which are verified on unpacking.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    path::{Component, Path, PathBuf},
+};
+
+use dora_core::{
+    adjust_shared_library_path,
+    descriptor::{
+        resolve_path, source_is_url, Descriptor, DescriptorExt, OperatorSource, DYNAMIC_SOURCE,
+        SHELL_SOURCE,
+    },
+};
+use eyre::{bail, Context, ContextCompat, Result};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use sha2::{Digest, Sha256};
+
+const MANIFEST_FILE: &str = "dora-bundle.yml";
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    format_version: u32,
+    /// Name of the bundled dataflow, defaults to the descriptor file stem.
+    name: String,
+    /// User-defined version of the bundle, e.g. `1.2.0`.
+    version: Option<String>,
+    /// Version of dora that created the bundle.
+    dora_version: String,
+    /// Path of the descriptor within the bundle.
+    descriptor: PathBuf,
+    /// SHA-256 checksums of the bundled files, by path within the bundle.
+    files: BTreeMap<PathBuf, String>,
+}
+
+/// Packages the dataflow and its node files into a bundle.
+pub fn bundle(
+    dataflow: &Path,
+    output: Option<PathBuf>,
+    name: Option<String>,
+    version: Option<String>,
+) -> Result<()> {
+    let descriptor = Descriptor::blocking_read(dataflow)
+        .wrap_err_with(|| format!("failed to read `{}`", dataflow.display()))?;
+    let dataflow = dataflow
+        .canonicalize()
+        .context("failed to canonicalize dataflow path")?;
+    let working_dir = dataflow.parent().context("dataflow path has no parent")?;
+    let descriptor_name = PathBuf::from(dataflow.file_name().context("no file name")?);
+
+    let mut files = vec![descriptor_name.clone()];
+    let mut signature = descriptor_name.clone().into_os_string();
+    signature.push(".sig");
+    if working_dir.join(&signature).exists() {
+        files.push(signature.into());
+    }
+    for source in node_files(&descriptor)? {
+        match resolve_path(&source, working_dir) {
+            Ok(path) => match path.strip_prefix(working_dir) {
+                Ok(relative) => files.push(relative.to_owned()),
+                Err(_) => {
+                    eprintln!("Not bundling `{source}`, which is outside of the dataflow directory")
+                }
+            },
+            Err(_) => eprintln!("Not bundling `{source}`, which was not found"),
+        }
+    }
+    files.sort();
+    files.dedup();
+
+    let name = match name {
+        Some(name) => name,
+        None => dataflow
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .context("invalid dataflow file name")?
+            .to_owned(),
+    };
+    let mut manifest = Manifest {
+        format_version: FORMAT_VERSION,
+        name,
+        version,
+        dora_version: env!("CARGO_PKG_VERSION").to_owned(),
+        descriptor: descriptor_name,
+        files: BTreeMap::new(),
+    };
+    for file in &files {
+        let checksum = file_checksum(&working_dir.join(file))?;
+        manifest.files.insert(file.clone(), checksum);
+    }
+
+    let output = output.unwrap_or_else(|| match &manifest.version {
+        Some(version) => PathBuf::from(format!("{}-{version}.dora", manifest.name)),
+        None => PathBuf::from(format!("{}.dora", manifest.name)),
+    });
+    let out_file = File::create(&output)
+        .wrap_err_with(|| format!("failed to create `{}`", output.display()))?;
+    let mut archive = tar::Builder::new(GzEncoder::new(out_file, Compression::default()));
+    let manifest_yaml = serde_yaml::to_string(&manifest).context("failed to serialize manifest")?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest_yaml.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    archive
+        .append_data(&mut header, MANIFEST_FILE, manifest_yaml.as_bytes())
+        .context("failed to write manifest")?;
+    for file in &files {
+        archive
+            .append_path_with_name(working_dir.join(file), file)
+            .wrap_err_with(|| format!("failed to add `{}` to bundle", file.display()))?;
+    }
+    archive
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .wrap_err_with(|| format!("failed to write `{}`", output.display()))?;
+
+    println!(
+        "Bundled {} files into `{}`",
+        manifest.files.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// Unpacks the bundle and returns the path of the unpacked descriptor.
+///
+/// Bundles are unpacked to `dora/bundles/<checksum of the bundle>` in the
+/// cache directory of the user, which is reused when the same bundle is
+/// started again.
+pub fn unpack(bundle: &Path) -> Result<PathBuf> {
+    let checksum = file_checksum(bundle)?;
+    let bundles_dir = bundles_dir()?;
+    let target_dir = bundles_dir.join(&checksum);
+
+    if target_dir.exists() {
+        // the unpacked files might have been modified since
+        if let Err(err) = verify(&target_dir) {
+            eprintln!("Unpacking bundle again, the cached copy is invalid: {err:?}");
+            std::fs::remove_dir_all(&target_dir)
+                .wrap_err("failed to remove invalid unpacked bundle")?;
+        }
+    }
+    if !target_dir.exists() {
+        let partial_dir = bundles_dir.join(format!("{checksum}.part"));
+        if partial_dir.exists() {
+            std::fs::remove_dir_all(&partial_dir)
+                .wrap_err("failed to remove partially unpacked bundle")?;
+        }
+        std::fs::create_dir_all(&partial_dir)
+            .wrap_err_with(|| format!("failed to create `{}`", partial_dir.display()))?;
+        let file = File::open(bundle)
+            .wrap_err_with(|| format!("failed to open `{}`", bundle.display()))?;
+        tar::Archive::new(GzDecoder::new(file))
+            .unpack(&partial_dir)
+            .wrap_err_with(|| format!("failed to unpack `{}`", bundle.display()))?;
+        verify(&partial_dir).wrap_err_with(|| format!("invalid bundle `{}`", bundle.display()))?;
+        std::fs::rename(&partial_dir, &target_dir)
+            .wrap_err("failed to move unpacked bundle into place")?;
+    }
+
+    let manifest = read_manifest(&target_dir)?;
+    let version = manifest.version.as_deref().unwrap_or("unversioned");
+    eprintln!(
+        "Unpacked bundle `{}` ({version}) to `{}`",
+        manifest.name,
+        target_dir.display()
+    );
+    Ok(target_dir.join(manifest.descriptor))
+}
+
+/// Returns the directory for unpacked bundles, which only the current user
+/// can access.
+fn bundles_dir() -> Result<PathBuf> {
+    let dir = dirs::cache_dir()
+        .context("failed to determine the cache directory of the user")?
+        .join("dora")
+        .join("bundles");
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder
+        .create(&dir)
+        .wrap_err_with(|| format!("failed to create `{}`", dir.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        // the directory might have been created with other permissions
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))
+            .wrap_err_with(|| format!("failed to set permissions of `{}`", dir.display()))?;
+    }
+    Ok(dir)
+}
+
+/// Checks that the unpacked bundle consists of exactly the files of its
+/// manifest, with the recorded checksums.
+fn verify(dir: &Path) -> Result<()> {
+    let manifest = read_manifest(dir)?;
+    if manifest.format_version != FORMAT_VERSION {
+        bail!(
+            "unsupported bundle format version {}, expected {FORMAT_VERSION}",
+            manifest.format_version
+        );
+    }
+    if !manifest.files.contains_key(&manifest.descriptor) {
+        bail!(
+            "descriptor `{}` is not part of the bundle",
+            manifest.descriptor.display()
+        );
+    }
+    for (file, expected) in &manifest.files {
+        if !file
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            bail!("invalid file path `{}`", file.display());
+        }
+        let actual = file_checksum(&dir.join(file))?;
+        if &actual != expected {
+            bail!(
+                "checksum mismatch for `{}`: expected `{expected}`, got `{actual}`",
+                file.display()
+            );
+        }
+    }
+    let mut unpacked = Vec::new();
+    list_files(dir, Path::new(""), &mut unpacked)?;
+    for file in unpacked {
+        if file != Path::new(MANIFEST_FILE) && !manifest.files.contains_key(&file) {
+            bail!("file `{}` is not part of the manifest", file.display());
+        }
+    }
+    Ok(())
+}
+
+/// Collects the paths of all non-directory entries below `dir`, relative to
+/// the root directory of the bundle.
+fn list_files(dir: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = std::fs::read_dir(dir.join(relative))
+        .wrap_err_with(|| format!("failed to read `{}`", dir.join(relative).display()))?;
+    for entry in entries {
+        let entry = entry.context("failed to read directory entry")?;
+        let path = relative.join(entry.file_name());
+        let file_type = entry
+            .file_type()
+            .wrap_err_with(|| format!("failed to get file type of `{}`", path.display()))?;
+        if file_type.is_symlink() {
+            // `dora bundle` stores the targets of symlinks, so they only
+            // occur in modified bundles
+            bail!("unexpected symlink `{}`", path.display());
+        } else if file_type.is_dir() {
+            list_files(dir, &path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn read_manifest(dir: &Path) -> Result<Manifest> {
+    let raw = std::fs::read(dir.join(MANIFEST_FILE)).context("bundle has no manifest")?;
+    serde_yaml::from_slice(&raw).context("failed to parse bundle manifest")
+}
+
+/// Sources of the nodes and operators of the dataflow, including the
+/// sources of all `targets`.
+fn node_files(descriptor: &Descriptor) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    for node in &descriptor.nodes {
+        if node.container.is_some() {
+            // the sources of container nodes are part of their image
+            continue;
+        }
+        files.extend(node.path.clone());
+        files.extend(node.targets.values().filter_map(|t| t.path.clone()));
+        if let Some(custom) = &node.custom {
+            files.push(custom.source.clone());
+            files.extend(custom.targets.values().filter_map(|t| t.path.clone()));
+        }
+        let operators = node
+            .operators
+            .iter()
+            .flat_map(|runtime| runtime.operators.iter().map(|op| &op.config))
+            .chain(node.operator.iter().map(|op| &op.config));
+        for config in operators {
+            match &config.source {
+                OperatorSource::SharedLibrary(path) => {
+                    let path = adjust_shared_library_path(Path::new(path))?;
+                    files.push(path.to_string_lossy().into_owned());
+                }
+                OperatorSource::Python(python) => files.push(python.source.clone()),
+                OperatorSource::Wasm(path) => files.push(path.clone()),
+            }
+        }
+    }
+    files.retain(|source| {
+        source != DYNAMIC_SOURCE && source != SHELL_SOURCE && !source_is_url(source)
+    });
+    Ok(files)
+}
+
+fn file_checksum(path: &Path) -> Result<String> {
+    let mut file =
+        File::open(path).wrap_err_with(|| format!("failed to open `{}`", path.display()))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)
+        .wrap_err_with(|| format!("failed to compute checksum of `{}`", path.display()))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
@@ -0,0 +1,34 @@
+use communication_layer_request_reply::TcpRequestReplyConnection;
+use dora_message::{cli_to_coordinator::ControlRequest, coordinator_to_cli::ControlRequestReply};
+use eyre::{bail, Context, Result};
+use uuid::Uuid;
+
+/// Creates a checkpoint of a running dataflow on all of its machines.
+pub fn checkpoint(session: &mut TcpRequestReplyConnection, dataflow_uuid: Uuid) -> Result<()> {
+    let reply_raw = session
+        .request(&serde_json::to_vec(&ControlRequest::Checkpoint { dataflow_uuid }).unwrap())
+        .wrap_err("failed to send Checkpoint request message")?;
+
+    let reply = serde_json::from_slice(&reply_raw).wrap_err("failed to parse reply")?;
+    let (checkpoint_id, checkpoints) = match reply {
+        ControlRequestReply::CheckpointCreated {
+            checkpoint_id,
+            checkpoints,
+            ..
+        } => (checkpoint_id, checkpoints),
+        ControlRequestReply::Error(err) => bail!("{err}"),
+        other => bail!("unexpected reply to checkpoint: {other:?}"),
+    };
+
+    println!("Checkpoint `{checkpoint_id}` of dataflow `{dataflow_uuid}` created");
+    for checkpoint in checkpoints {
+        println!(
+            "Machine `{}`: stored {} node states and {} queued inputs to {}",
+            checkpoint.machine_id,
+            checkpoint.node_states,
+            checkpoint.queued_inputs,
+            checkpoint.path.display()
+        );
+    }
+    Ok(())
+}
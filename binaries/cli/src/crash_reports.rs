@@ -0,0 +1,90 @@
+use communication_layer_request_reply::TcpRequestReplyConnection;
+use dora_core::config::NodeId;
+use dora_message::{
+    cli_to_coordinator::ControlRequest,
+    common::signal_name,
+    coordinator_to_cli::{ControlRequestReply, CrashReport, NodeExitStatus},
+};
+use eyre::{bail, Context, Result};
+use uuid::Uuid;
+
+/// Prints the crash reports of the given dataflow, optionally only the ones
+/// of a single node.
+pub fn crash_reports(
+    session: &mut TcpRequestReplyConnection,
+    uuid: Option<Uuid>,
+    name: Option<String>,
+    node: Option<NodeId>,
+    json: bool,
+) -> Result<()> {
+    let reports = {
+        let reply_raw = session
+            .request(
+                &serde_json::to_vec(&ControlRequest::CrashReports { uuid, name, node }).unwrap(),
+            )
+            .wrap_err("failed to send CrashReports request message")?;
+
+        let reply = serde_json::from_slice(&reply_raw).wrap_err("failed to parse reply")?;
+        match reply {
+            ControlRequestReply::CrashReports(reports) => reports,
+            ControlRequestReply::Error(err) => bail!("{err}"),
+            other => bail!("unexpected reply to crash reports: {other:?}"),
+        }
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+    } else if reports.is_empty() {
+        println!("No crash reports found");
+    } else {
+        for report in &reports {
+            print_report(report);
+        }
+    }
+
+    Ok(())
+}
+
+fn print_report(report: &CrashReport) {
+    let exit_status = match &report.exit_status {
+        NodeExitStatus::Signal(signal) => format!("killed by signal {}", signal_name(*signal)),
+        other => format!("{other:?}"),
+    };
+    println!(
+        "Node `{}` on machine `{}` {exit_status} at {}",
+        report.node_id, report.machine_id, report.timestamp
+    );
+
+    match (&report.core_dump_location, report.core_dumped) {
+        (Some(location), _) => println!("  Core dump: {location}"),
+        (None, true) => println!("  Core dump: written, but location is unknown"),
+        (None, false) => println!("  Core dump: none"),
+    }
+    if let Some(usage) = &report.resource_usage {
+        println!(
+            "  CPU time: {:.2}s user, {:.2}s system",
+            usage.user_time.as_secs_f64(),
+            usage.system_time.as_secs_f64()
+        );
+    }
+    if let Some(path) = &report.flight_recording {
+        println!("  Recent daemon events: {}", path.display());
+    }
+
+    if !report.recent_inputs.is_empty() {
+        println!("  Recent inputs:");
+        for input in &report.recent_inputs {
+            println!(
+                "    {} {} ({}, len {})",
+                input.timestamp, input.input_id, input.data_type, input.len
+            );
+        }
+    }
+    if !report.log_lines.is_empty() {
+        println!("  Last output lines:");
+        for line in &report.log_lines {
+            println!("    | {line}");
+        }
+    }
+    println!();
+}
@@ -0,0 +1,188 @@
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use communication_layer_request_reply::TcpRequestReplyConnection;
+use dora_core::descriptor::{
+    resolve_path, source_is_url, Descriptor, DYNAMIC_SOURCE, SHELL_SOURCE,
+};
+use dora_message::{
+    cli_to_coordinator::{ArtifactId, ArtifactStatus, ControlRequest},
+    coordinator_to_cli::ControlRequestReply,
+};
+use eyre::{bail, Context, ContextCompat, Result};
+use sha2::{Digest, Sha256};
+
+/// Size of the parts that artifacts are uploaded in.
+const CHUNK_SIZE: usize = 256 * 1024;
+
+/// Uploads the local node executables of the dataflow to the daemons of
+/// their machines.
+///
+/// The checksums of the uploaded files are pinned through the `sha256` field
+/// of the nodes, which the daemons use to find the files in their artifact
+/// cache. The files of `targets` entries are uploaded too, except for entries
+/// of other machines of the dataflow.
+pub fn distribute(
+    dataflow: &mut Descriptor,
+    working_dir: &Path,
+    session: &mut TcpRequestReplyConnection,
+) -> Result<()> {
+    let default_machine = dataflow.deploy.machine.clone().unwrap_or_default();
+    let machines: BTreeSet<String> = dataflow
+        .nodes
+        .iter()
+        .map(|node| {
+            node.deploy
+                .machine
+                .clone()
+                .unwrap_or_else(|| default_machine.clone())
+        })
+        .collect();
+    for node in &mut dataflow.nodes {
+        if node.container.is_some() {
+            // the sources of container nodes are part of their image
+            continue;
+        }
+        let machine = node
+            .deploy
+            .machine
+            .clone()
+            .unwrap_or_else(|| default_machine.clone());
+        let (source, sha256, targets) = match (&node.path, &mut node.custom) {
+            (Some(path), _) => (path, &mut node.sha256, &mut node.targets),
+            (None, Some(custom)) => (&custom.source, &mut custom.sha256, &mut custom.targets),
+            (None, None) => continue,
+        };
+
+        let mut files = vec![(source.clone(), sha256)];
+        for (key, target) in targets {
+            if let Some(path) = &target.path {
+                if key == &machine || !machines.contains(key) {
+                    files.push((path.clone(), &mut target.sha256));
+                }
+            }
+        }
+        for (source, sha256) in files {
+            if source == DYNAMIC_SOURCE || source == SHELL_SOURCE || source_is_url(&source) {
+                continue;
+            }
+            let Ok(path) = resolve_path(&source, working_dir) else {
+                // e.g. a file that only exists on the target machine
+                continue;
+            };
+            let checksum = file_checksum(&path)?;
+            match sha256 {
+                Some(pinned) if !pinned.eq_ignore_ascii_case(&checksum) => bail!(
+                    "checksum of `{}` doesn't match the `sha256` of node `{}`",
+                    path.display(),
+                    node.id
+                ),
+                _ => *sha256 = Some(checksum.clone()),
+            }
+            let file_name = Path::new(&source)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .with_context(|| format!("invalid source `{source}` of node `{}`", node.id))?;
+            let artifact = ArtifactId {
+                sha256: checksum,
+                file_name: file_name.to_owned(),
+            };
+            upload(session, &machine, &artifact, &path).wrap_err_with(|| {
+                format!(
+                    "failed to upload `{}` to machine `{machine}`",
+                    path.display()
+                )
+            })?;
+        }
+    }
+    Ok(())
+}
+
+fn upload(
+    session: &mut TcpRequestReplyConnection,
+    machine_id: &str,
+    artifact: &ArtifactId,
+    path: &Path,
+) -> Result<()> {
+    let mut offset = match request(
+        session,
+        ControlRequest::ArtifactStatus {
+            machine_id: machine_id.to_owned(),
+            artifact: artifact.clone(),
+        },
+    )? {
+        ArtifactStatus::Complete => return Ok(()),
+        ArtifactStatus::Partial { received } => received,
+    };
+    if offset > 0 {
+        eprintln!(
+            "Resuming upload of `{}` to machine `{machine_id}` at byte {offset}",
+            artifact.file_name
+        );
+    } else {
+        eprintln!(
+            "Uploading `{}` to machine `{machine_id}`",
+            artifact.file_name
+        );
+    }
+
+    let mut file = File::open(path).wrap_err("failed to open file")?;
+    let len = file
+        .metadata()
+        .wrap_err("failed to read file metadata")?
+        .len();
+    loop {
+        file.seek(SeekFrom::Start(offset))
+            .wrap_err("failed to seek file")?;
+        let mut data = Vec::with_capacity(CHUNK_SIZE);
+        (&mut file)
+            .take(CHUNK_SIZE as u64)
+            .read_to_end(&mut data)
+            .wrap_err("failed to read file")?;
+        let last = offset + data.len() as u64 >= len;
+        let status = request(
+            session,
+            ControlRequest::UploadArtifact {
+                machine_id: machine_id.to_owned(),
+                artifact: artifact.clone(),
+                offset,
+                data,
+                last,
+            },
+        )?;
+        match status {
+            ArtifactStatus::Complete => return Ok(()),
+            ArtifactStatus::Partial { received } if received == offset || received > len => {
+                bail!("upload makes no progress, daemon reports {received} received bytes")
+            }
+            ArtifactStatus::Partial { received } => offset = received,
+        }
+    }
+}
+
+fn request(
+    session: &mut TcpRequestReplyConnection,
+    request: ControlRequest,
+) -> Result<ArtifactStatus> {
+    let reply_raw = session
+        .request(&serde_json::to_vec(&request).unwrap())
+        .wrap_err("failed to send artifact request message")?;
+    match serde_json::from_slice(&reply_raw).wrap_err("failed to parse reply")? {
+        ControlRequestReply::ArtifactStatus(status) => Ok(status),
+        ControlRequestReply::Error(err) => bail!("{err}"),
+        other => bail!("unexpected reply to artifact request: {other:?}"),
+    }
+}
+
+fn file_checksum(path: &Path) -> Result<String> {
+    let mut file =
+        File::open(path).wrap_err_with(|| format!("failed to open `{}`", path.display()))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)
+        .wrap_err_with(|| format!("failed to compute checksum of `{}`", path.display()))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
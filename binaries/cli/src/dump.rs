@@ -0,0 +1,40 @@
+use std::{collections::BTreeMap, path::Path};
+
+use communication_layer_request_reply::TcpRequestReplyConnection;
+use dora_message::{cli_to_coordinator::ControlRequest, coordinator_to_cli::ControlRequestReply};
+use eyre::{bail, Context, Result};
+
+/// Writes the internal state of all connected daemons as a single JSON object,
+/// keyed by machine ID, to the given file or to stdout.
+pub fn dump(session: &mut TcpRequestReplyConnection, output: Option<&Path>) -> Result<()> {
+    let states = {
+        let reply_raw = session
+            .request(&serde_json::to_vec(&ControlRequest::DumpDaemonStates).unwrap())
+            .wrap_err("failed to send DumpDaemonStates request message")?;
+
+        let reply = serde_json::from_slice(&reply_raw).wrap_err("failed to parse reply")?;
+        match reply {
+            ControlRequestReply::DaemonStates(states) => states,
+            ControlRequestReply::Error(err) => bail!("{err}"),
+            other => bail!("unexpected reply to dump daemon states: {other:?}"),
+        }
+    };
+
+    let states = states
+        .into_iter()
+        .map(|(machine_id, state)| {
+            let state: serde_json::Value = serde_json::from_slice(&state)
+                .wrap_err_with(|| format!("failed to parse state of machine `{machine_id}`"))?;
+            Ok((machine_id, state))
+        })
+        .collect::<Result<BTreeMap<_, _>>>()?;
+    let json = serde_json::to_string_pretty(&states)?;
+
+    match output {
+        Some(path) => std::fs::write(path, json)
+            .wrap_err_with(|| format!("failed to write `{}`", path.display()))?,
+        None => println!("{json}"),
+    }
+
+    Ok(())
+}
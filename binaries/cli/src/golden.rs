@@ -0,0 +1,42 @@
+use std::path::Path;
+
+use dora_recording::{GoldenTrace, Normalization};
+use eyre::{bail, Context, Result};
+
+/// Writes the normalized outputs of the given recording to the golden trace
+/// file.
+pub fn update(golden: &Path, recording: &Path, normalization: Normalization) -> Result<()> {
+    let trace = GoldenTrace::from_recording(recording, normalization)
+        .wrap_err_with(|| format!("failed to read recording `{}`", recording.display()))?;
+    trace.write(golden)?;
+    let messages: usize = trace.outputs.values().map(Vec::len).sum();
+    println!(
+        "Wrote golden trace with {messages} messages on {} outputs to {}",
+        trace.outputs.len(),
+        golden.display()
+    );
+    Ok(())
+}
+
+/// Compares the given recording against the golden trace and fails if they
+/// differ.
+pub fn compare(golden: &Path, recording: &Path) -> Result<()> {
+    let expected = GoldenTrace::read(golden)?;
+    let actual = GoldenTrace::from_recording(recording, expected.normalization.clone())
+        .wrap_err_with(|| format!("failed to read recording `{}`", recording.display()))?;
+    let diff = expected.compare(&actual)?;
+    if !diff.is_empty() {
+        print!("{diff}");
+        bail!(
+            "{} outputs differ from golden trace `{}`",
+            diff.outputs.len(),
+            golden.display()
+        );
+    }
+    println!(
+        "Recording matches golden trace `{}` ({} outputs)",
+        golden.display(),
+        expected.outputs.len()
+    );
+    Ok(())
+}
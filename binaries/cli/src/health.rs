@@ -0,0 +1,107 @@
+use std::{collections::BTreeMap, io::Write};
+
+use communication_layer_request_reply::TcpRequestReplyConnection;
+use dora_message::{
+    cli_to_coordinator::ControlRequest,
+    coordinator_to_cli::{ControlRequestReply, DataflowHealth},
+};
+use eyre::{bail, Context, Result};
+use tabwriter::TabWriter;
+use uuid::Uuid;
+
+pub fn health(
+    session: &mut TcpRequestReplyConnection,
+    dataflow_uuid: Uuid,
+    prometheus: bool,
+) -> Result<()> {
+    let health = query_health(session, dataflow_uuid)?;
+
+    if prometheus {
+        print!("{}", dora_metrics::prometheus::format_health(&health));
+    } else {
+        print_summary(&health)?;
+    }
+
+    Ok(())
+}
+
+pub fn query_health(
+    session: &mut TcpRequestReplyConnection,
+    dataflow_uuid: Uuid,
+) -> Result<DataflowHealth> {
+    let reply_raw = session
+        .request(&serde_json::to_vec(&ControlRequest::Health { dataflow_uuid }).unwrap())
+        .wrap_err("failed to send Health request message")?;
+
+    let reply = serde_json::from_slice(&reply_raw).wrap_err("failed to parse reply")?;
+    match reply {
+        ControlRequestReply::DataflowHealth(health) => Ok(health),
+        ControlRequestReply::Error(err) => bail!("{err}"),
+        other => bail!("unexpected reply to dataflow health: {other:?}"),
+    }
+}
+
+fn print_summary(health: &DataflowHealth) -> Result<()> {
+    println!("{}: {}\n", health.id, health.status());
+
+    let queued_inputs: BTreeMap<_, _> = health
+        .queues
+        .values()
+        .flat_map(|metrics| &metrics.queued_inputs)
+        .collect();
+    let dropped_inputs: BTreeMap<_, u64> = health
+        .queues
+        .values()
+        .flat_map(|metrics| &metrics.dropped_inputs)
+        .map(|(node_id, inputs)| (node_id, inputs.values().sum()))
+        .collect();
+
+    let mut tw = TabWriter::new(vec![]);
+    tw.write_all(b"Node\tStatus\tQueued\tDropped\tMessage\tMetrics\n")?;
+    for (node_id, node_health) in &health.nodes {
+        let queued = queued_inputs
+            .get(node_id)
+            .map(|queued| queued.to_string())
+            .unwrap_or_else(|| "-".to_owned());
+        let dropped = dropped_inputs.get(node_id).copied().unwrap_or_default();
+        let Some(node_health) = node_health else {
+            tw.write_all(format!("{node_id}\t-\t{queued}\t{dropped}\t\t\n").as_bytes())?;
+            continue;
+        };
+        let message = node_health.message.as_deref().unwrap_or_default();
+        let metrics = node_health
+            .counters
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .chain(
+                node_health
+                    .gauges
+                    .iter()
+                    .map(|(name, value)| format!("{name}={value}")),
+            )
+            .collect::<Vec<_>>()
+            .join(" ");
+        let status = node_health.status;
+        tw.write_all(
+            format!("{node_id}\t{status}\t{queued}\t{dropped}\t{message}\t{metrics}\n").as_bytes(),
+        )?;
+    }
+    tw.flush()?;
+    let formatted = String::from_utf8(tw.into_inner()?)?;
+
+    println!("{formatted}");
+
+    for (machine_id, metrics) in &health.queues {
+        let machine = if machine_id.is_empty() {
+            "default machine"
+        } else {
+            machine_id
+        };
+        println!(
+            "In-flight shared memory on {machine}: {} bytes",
+            metrics.in_flight_shared_memory_bytes
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use std::{fmt::Write as _, io::Write, time::Duration};
+
+use communication_layer_request_reply::TcpRequestReplyConnection;
+use dora_message::{
+    cli_to_coordinator::ControlRequest,
+    coordinator_to_cli::{ControlRequestReply, DataflowEdgeLatencies, LATENCY_BUCKET_BOUNDS_US},
+};
+use eyre::{bail, Context, Result};
+use tabwriter::TabWriter;
+use uuid::Uuid;
+
+pub fn latency(
+    session: &mut TcpRequestReplyConnection,
+    dataflow_uuid: Uuid,
+    prometheus: bool,
+) -> Result<()> {
+    let latencies = {
+        let reply_raw = session
+            .request(&serde_json::to_vec(&ControlRequest::EdgeLatencies { dataflow_uuid }).unwrap())
+            .wrap_err("failed to send EdgeLatencies request message")?;
+
+        let reply = serde_json::from_slice(&reply_raw).wrap_err("failed to parse reply")?;
+        match reply {
+            ControlRequestReply::EdgeLatencies(latencies) => latencies,
+            ControlRequestReply::Error(err) => bail!("{err}"),
+            other => bail!("unexpected reply to edge latencies: {other:?}"),
+        }
+    };
+
+    if prometheus {
+        print!("{}", format_prometheus(&latencies));
+    } else {
+        print_summary(&latencies)?;
+    }
+
+    Ok(())
+}
+
+fn print_summary(latencies: &DataflowEdgeLatencies) -> Result<()> {
+    println!("{}\n", latencies.id);
+
+    let mut tw = TabWriter::new(vec![]);
+    tw.write_all(b"Output\tInput\tMessages\tMean\tp50\tp90\tp99\tMax\n")?;
+    for edge in &latencies.edges {
+        let histogram = &edge.histogram;
+        let format = |latency: Option<Duration>| match latency {
+            Some(latency) => format!("{latency:.1?}"),
+            None => "-".to_owned(),
+        };
+        tw.write_all(
+            format!(
+                "{}/{}\t{}/{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                edge.source,
+                edge.output,
+                edge.target,
+                edge.input,
+                histogram.count,
+                format(histogram.mean()),
+                format(histogram.quantile(0.5)),
+                format(histogram.quantile(0.9)),
+                format(histogram.quantile(0.99)),
+                format((histogram.count > 0).then(|| Duration::from_micros(histogram.max_us))),
+            )
+            .as_bytes(),
+        )?;
+    }
+    tw.flush()?;
+    let formatted = String::from_utf8(tw.into_inner()?)?;
+
+    println!("{formatted}");
+
+    Ok(())
+}
+
+/// Formats the histograms in the Prometheus text exposition format.
+fn format_prometheus(latencies: &DataflowEdgeLatencies) -> String {
+    let dataflow = latencies.id.uuid;
+    let mut out = String::new();
+
+    let _ = writeln!(
+        out,
+        "# HELP dora_edge_latency_seconds Time from sending a message until its delivery to the receiving node."
+    );
+    let _ = writeln!(out, "# TYPE dora_edge_latency_seconds histogram");
+    for edge in &latencies.edges {
+        let labels = format!(
+            "dataflow=\"{dataflow}\",source=\"{}\",output=\"{}\",target=\"{}\",input=\"{}\"",
+            edge.source, edge.output, edge.target, edge.input
+        );
+        let histogram = &edge.histogram;
+        let mut cumulative = 0;
+        for (bound, count) in LATENCY_BUCKET_BOUNDS_US.iter().zip(&histogram.buckets) {
+            cumulative += count;
+            let le = *bound as f64 / 1e6;
+            let _ = writeln!(
+                out,
+                "dora_edge_latency_seconds_bucket{{{labels},le=\"{le}\"}} {cumulative}"
+            );
+        }
+        let _ = writeln!(
+            out,
+            "dora_edge_latency_seconds_bucket{{{labels},le=\"+Inf\"}} {}",
+            histogram.count
+        );
+        let _ = writeln!(
+            out,
+            "dora_edge_latency_seconds_sum{{{labels}}} {}",
+            histogram.sum_us as f64 / 1e6
+        );
+        let _ = writeln!(
+            out,
+            "dora_edge_latency_seconds_count{{{labels}}} {}",
+            histogram.count
+        );
+    }
+
+    out
+}
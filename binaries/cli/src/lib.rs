@@ -1,20 +1,24 @@
 use attach::attach_dataflow;
 use colored::Colorize;
-use communication_layer_request_reply::{RequestReplyLayer, TcpLayer, TcpRequestReplyConnection};
-use dora_coordinator::Event;
+use communication_layer_request_reply::{
+    RequestReplyLayer, TcpConnection, TcpLayer, TcpRequestReplyConnection,
+};
+use dora_coordinator::{AccessControl, Event, LifecycleSink};
 use dora_core::{
-    descriptor::{source_is_url, Descriptor, DescriptorExt},
+    config::NodeId,
+    descriptor::{signing, source_is_url, Descriptor, DescriptorExt, SignedDescriptor},
     topics::{
         DORA_COORDINATOR_PORT_CONTROL_DEFAULT, DORA_COORDINATOR_PORT_DEFAULT,
         DORA_DAEMON_LOCAL_LISTEN_PORT_DEFAULT,
     },
 };
-use dora_daemon::Daemon;
+use dora_daemon::{Daemon, Footprint};
 use dora_download::download_file;
 use dora_message::{
-    cli_to_coordinator::ControlRequest,
+    cli_to_coordinator::{ControlRequest, RecordingOptions, ReplayControl},
     coordinator_to_cli::{ControlRequestReply, DataflowList, DataflowResult, DataflowStatus},
 };
+use dora_recording::Normalization;
 #[cfg(feature = "tracing")]
 use dora_tracing::set_up_tracing;
 use dora_tracing::{set_up_tracing_opts, FileLogging};
@@ -34,11 +38,24 @@ use uuid::Uuid;
 
 mod attach;
 mod build;
+mod bundle;
 mod check;
+mod checkpoint;
+mod crash_reports;
+mod distribute;
+mod dump;
 mod formatting;
+mod golden;
 mod graph;
+mod health;
+mod latency;
+mod live_graph;
 mod logs;
+mod record;
+mod replay;
+mod sign;
 mod template;
+mod top;
 mod up;
 
 const LOCALHOST: IpAddr = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
@@ -126,7 +143,7 @@ enum Command {
     },
     /// Start the given dataflow path. Attach a name to the running dataflow by using --name.
     Start {
-        /// Path to the dataflow descriptor file
+        /// Path to the dataflow descriptor file or `.dora` bundle
         #[clap(value_name = "PATH")]
         dataflow: String,
         /// Assign a name to the dataflow
@@ -147,6 +164,13 @@ enum Command {
         /// Enable hot reloading (Python only)
         #[clap(long, action)]
         hot_reload: bool,
+        /// Resume the dataflow from a checkpoint created through `dora checkpoint`
+        #[clap(long, value_name = "PATH", value_hint = clap::ValueHint::DirPath)]
+        restore: Option<PathBuf>,
+        /// Upload the node executables to the daemons of their machines
+        /// instead of expecting them at the same path on every machine
+        #[clap(long, action)]
+        distribute: bool,
     },
     /// Stop the given dataflow UUID. If no id is provided, you will be able to choose between the running dataflows.
     Stop {
@@ -155,7 +179,8 @@ enum Command {
         /// Name of the dataflow that should be stopped
         #[clap(long)]
         name: Option<String>,
-        /// Kill the dataflow if it doesn't stop after the given duration
+        /// Terminate the nodes that don't stop within the given duration,
+        /// overriding their `grace_duration_ms` [default: 15s]
         #[clap(long, value_name = "DURATION")]
         #[arg(value_parser = parse)]
         grace_duration: Option<Duration>,
@@ -186,6 +211,172 @@ enum Command {
         /// Show logs for the given node
         #[clap(value_name = "NAME")]
         node: String,
+        /// Keep printing new output of the node as it arrives
+        #[clap(long, short = 'f', action)]
+        follow: bool,
+        /// Address of the dora coordinator
+        #[clap(long, value_name = "IP", default_value_t = LOCALHOST)]
+        coordinator_addr: IpAddr,
+        /// Port number of the coordinator control server
+        #[clap(long, value_name = "PORT", default_value_t = DORA_COORDINATOR_PORT_CONTROL_DEFAULT)]
+        coordinator_port: u16,
+    },
+    /// Show the health reported by the nodes of a running dataflow.
+    Health {
+        /// Identifier of the dataflow
+        #[clap(value_name = "UUID_OR_NAME")]
+        dataflow: Option<String>,
+        /// Print the health in the Prometheus text exposition format
+        #[clap(long, action)]
+        prometheus: bool,
+        /// Address of the dora coordinator
+        #[clap(long, value_name = "IP", default_value_t = LOCALHOST)]
+        coordinator_addr: IpAddr,
+        /// Port number of the coordinator control server
+        #[clap(long, value_name = "PORT", default_value_t = DORA_COORDINATOR_PORT_CONTROL_DEFAULT)]
+        coordinator_port: u16,
+    },
+    /// Show the latency distribution of each connection of a running dataflow.
+    ///
+    /// The latency of a message is measured from the time it was sent until
+    /// the daemon of the receiving node delivers it.
+    Latency {
+        /// Identifier of the dataflow
+        #[clap(value_name = "UUID_OR_NAME")]
+        dataflow: Option<String>,
+        /// Print the histograms in the Prometheus text exposition format
+        #[clap(long, action)]
+        prometheus: bool,
+        /// Address of the dora coordinator
+        #[clap(long, value_name = "IP", default_value_t = LOCALHOST)]
+        coordinator_addr: IpAddr,
+        /// Port number of the coordinator control server
+        #[clap(long, value_name = "PORT", default_value_t = DORA_COORDINATOR_PORT_CONTROL_DEFAULT)]
+        coordinator_port: u16,
+    },
+    /// Print the graph of a running dataflow with live message rates and node
+    /// states as JSON, e.g. for external visualization tools.
+    LiveGraph {
+        /// Identifier of the dataflow
+        #[clap(value_name = "UUID_OR_NAME")]
+        dataflow: Option<String>,
+        /// Print the graph again as a single JSON line after every given duration
+        #[clap(long, value_name = "DURATION")]
+        #[arg(value_parser = parse)]
+        watch: Option<Duration>,
+        /// Address of the dora coordinator
+        #[clap(long, value_name = "IP", default_value_t = LOCALHOST)]
+        coordinator_addr: IpAddr,
+        /// Port number of the coordinator control server
+        #[clap(long, value_name = "PORT", default_value_t = DORA_COORDINATOR_PORT_CONTROL_DEFAULT)]
+        coordinator_port: u16,
+    },
+    /// Show the CPU, memory, and IO usage of the nodes of a running dataflow.
+    ///
+    /// The usage is sampled from the cgroups that the daemons place the
+    /// nodes in, which is only supported on Linux with cgroup v2.
+    Top {
+        /// Identifier of the dataflow
+        #[clap(value_name = "UUID_OR_NAME")]
+        dataflow: Option<String>,
+        /// Refresh the usage after every given duration
+        #[clap(long, value_name = "DURATION")]
+        #[arg(value_parser = parse)]
+        watch: Option<Duration>,
+        /// Address of the dora coordinator
+        #[clap(long, value_name = "IP", default_value_t = LOCALHOST)]
+        coordinator_addr: IpAddr,
+        /// Port number of the coordinator control server
+        #[clap(long, value_name = "PORT", default_value_t = DORA_COORDINATOR_PORT_CONTROL_DEFAULT)]
+        coordinator_port: u16,
+    },
+    /// Dump the internal state of all connected daemons as JSON.
+    ///
+    /// Useful to attach to bug reports about stuck dataflows.
+    Dump {
+        /// Write the state to the given file instead of stdout
+        #[clap(long, short, value_name = "PATH")]
+        output: Option<PathBuf>,
+        /// Address of the dora coordinator
+        #[clap(long, value_name = "IP", default_value_t = LOCALHOST)]
+        coordinator_addr: IpAddr,
+        /// Port number of the coordinator control server
+        #[clap(long, value_name = "PORT", default_value_t = DORA_COORDINATOR_PORT_CONTROL_DEFAULT)]
+        coordinator_port: u16,
+    },
+    /// Show the crash reports of the nodes of a dataflow that were terminated
+    /// by a signal.
+    CrashReports {
+        /// Identifier of the dataflow, which may also be finished already
+        #[clap(value_name = "UUID_OR_NAME")]
+        dataflow: Option<String>,
+        /// Only show the crash reports of the given node
+        #[clap(long, value_name = "NAME")]
+        node: Option<NodeId>,
+        /// Print the full reports as JSON
+        #[clap(long, action)]
+        json: bool,
+        /// Address of the dora coordinator
+        #[clap(long, value_name = "IP", default_value_t = LOCALHOST)]
+        coordinator_addr: IpAddr,
+        /// Port number of the coordinator control server
+        #[clap(long, value_name = "PORT", default_value_t = DORA_COORDINATOR_PORT_CONTROL_DEFAULT)]
+        coordinator_port: u16,
+    },
+    /// Record the outputs of a running dataflow to disk.
+    ///
+    /// Each daemon writes the outputs of its nodes to
+    /// `out/<dataflow>/recordings/<recording>/<machine>` in the working
+    /// directory of the dataflow, until the recording is stopped with `--stop`
+    /// or the dataflow finishes.
+    ///
+    /// Outputs are selected through glob patterns like `camera/*` or
+    /// `*/image`, which are matched against `node/output`. A pattern without
+    /// `/` selects all outputs of the matching nodes.
+    Record {
+        /// Identifier of the dataflow
+        #[clap(value_name = "UUID_OR_NAME")]
+        dataflow: Option<String>,
+        /// Only record the outputs that match the given pattern
+        #[clap(long = "output", value_name = "PATTERN", conflicts_with = "stop")]
+        outputs: Vec<String>,
+        /// Don't record the outputs that match the given pattern
+        #[clap(long = "exclude", value_name = "PATTERN", conflicts_with = "stop")]
+        exclude: Vec<String>,
+        /// Only record every n-th message of the matching outputs, e.g. `camera/*=10`
+        #[clap(long = "downsample", value_name = "PATTERN=N", conflicts_with = "stop")]
+        #[arg(value_parser = record::parse_downsampling)]
+        downsample: Vec<(String, u64)>,
+        /// Delete the oldest messages once the recording on a machine exceeds
+        /// the given size in bytes
+        #[clap(long, value_name = "BYTES", conflicts_with = "stop")]
+        max_size: Option<u64>,
+        /// Only keep the messages of the given time span before the most
+        /// recent message, e.g. `5m`
+        #[clap(long, value_name = "DURATION", conflicts_with = "stop")]
+        #[arg(value_parser = parse)]
+        keep_last: Option<Duration>,
+        /// Stop the active recording
+        #[clap(long, action)]
+        stop: bool,
+        /// Address of the dora coordinator
+        #[clap(long, value_name = "IP", default_value_t = LOCALHOST)]
+        coordinator_addr: IpAddr,
+        /// Port number of the coordinator control server
+        #[clap(long, value_name = "PORT", default_value_t = DORA_COORDINATOR_PORT_CONTROL_DEFAULT)]
+        coordinator_port: u16,
+    },
+    /// Store a checkpoint of a running dataflow.
+    ///
+    /// Each daemon writes the latest saved states of its nodes and the inputs
+    /// that are queued for them to
+    /// `out/<dataflow>/checkpoints/<checkpoint>/<machine>` in the working
+    /// directory of the dataflow. Use `dora start --restore` to resume the
+    /// dataflow from the checkpoint.
+    Checkpoint {
+        /// Identifier of the dataflow
+        #[clap(value_name = "UUID_OR_NAME")]
+        dataflow: Option<String>,
         /// Address of the dora coordinator
         #[clap(long, value_name = "IP", default_value_t = LOCALHOST)]
         coordinator_addr: IpAddr,
@@ -193,11 +384,127 @@ enum Command {
         #[clap(long, value_name = "PORT", default_value_t = DORA_COORDINATOR_PORT_CONTROL_DEFAULT)]
         coordinator_port: u16,
     },
+    /// Control the replay of a running dataflow.
+    ///
+    /// Applies to the dataflows that replay a recording through the `replay`
+    /// key of their descriptor. While paused, `--step` sends the next recorded
+    /// message.
+    #[clap(group(clap::ArgGroup::new("control").required(true)))]
+    Replay {
+        /// Identifier of the dataflow
+        #[clap(value_name = "UUID_OR_NAME")]
+        dataflow: Option<String>,
+        /// Pause the replay
+        #[clap(long, action, group = "control")]
+        pause: bool,
+        /// Resume a paused replay
+        #[clap(long, action, group = "control")]
+        resume: bool,
+        /// Send the next recorded message, pausing the replay if necessary
+        #[clap(long, action, group = "control")]
+        step: bool,
+        /// Replay the recording with the given speed multiplier, e.g. `0.5`
+        #[clap(long, value_name = "FACTOR", group = "control")]
+        speed: Option<f64>,
+        /// Address of the dora coordinator
+        #[clap(long, value_name = "IP", default_value_t = LOCALHOST)]
+        coordinator_addr: IpAddr,
+        /// Port number of the coordinator control server
+        #[clap(long, value_name = "PORT", default_value_t = DORA_COORDINATOR_PORT_CONTROL_DEFAULT)]
+        coordinator_port: u16,
+    },
+    /// Convert a recording to an MCAP file, e.g. for Foxglove Studio.
+    ///
+    /// Each recorded output becomes a channel with topic `/<node>/<output>`,
+    /// whose messages contain the recorded data as JSON.
+    Export {
+        /// Directory of the recording, or of the part of a single machine
+        #[clap(value_name = "PATH", value_hint = clap::ValueHint::DirPath)]
+        recording: PathBuf,
+        /// Path of the MCAP file [default: `<recording>.mcap`]
+        #[clap(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Compare a recording against a golden trace, for regression tests.
+    ///
+    /// The golden trace contains the normalized outputs of a reference run.
+    /// It is created from the recording if it doesn't exist yet or if
+    /// `--update` is given. Otherwise, the recording is normalized with the
+    /// rules stored in the golden trace and all differences are reported per
+    /// output. Fails if any output differs.
+    Golden {
+        /// Path of the golden trace file
+        #[clap(value_name = "GOLDEN", value_hint = clap::ValueHint::FilePath)]
+        golden: PathBuf,
+        /// Directory of the recording, or of the part of a single machine
+        #[clap(value_name = "RECORDING", value_hint = clap::ValueHint::DirPath)]
+        recording: PathBuf,
+        /// Replace the golden trace with the given recording
+        #[clap(long, action)]
+        update: bool,
+        /// Round floating point values to the given number of decimal places
+        #[clap(long, value_name = "N", requires = "update")]
+        float_precision: Option<u32>,
+        /// Leave out the given metadata parameter, e.g. a timestamp
+        #[clap(long, value_name = "KEY", requires = "update")]
+        ignore_parameter: Vec<String>,
+        /// Leave out the outputs matching the pattern, e.g. `camera/*`
+        #[clap(long, value_name = "PATTERN", requires = "update")]
+        ignore_output: Vec<String>,
+        /// Compare the send times of messages with the given tolerance, e.g.
+        /// `50ms`
+        #[clap(long, value_name = "DURATION", requires = "update")]
+        #[arg(value_parser = parse)]
+        time_tolerance: Option<Duration>,
+    },
     // Metrics,
     // Stats,
     // Get,
     // Upgrade,
+    /// Sign a dataflow descriptor, for coordinators and daemons that verify
+    /// signatures.
+    ///
+    /// Signs the descriptor with the Ed25519 key at `--key` and writes the
+    /// signature to a detached `<dataflow>.sig` file, or to the end of the
+    /// descriptor with `--embed`. The public key must be listed in the
+    /// `--trusted-keys` file of the coordinator and the daemons.
+    Sign {
+        /// Path to the dataflow descriptor file
+        #[clap(value_name = "PATH", value_hint = clap::ValueHint::FilePath)]
+        dataflow: PathBuf,
+        /// Path of the signing key
+        #[clap(long, value_name = "FILE", value_hint = clap::ValueHint::FilePath)]
+        key: PathBuf,
+        /// Generate a new signing key at `--key` and its public key at `<key>.pub`
+        #[clap(long, action)]
+        generate_key: bool,
+        /// Append the signature to the descriptor instead of a detached file
+        #[clap(long, action)]
+        embed: bool,
+    },
+    /// Package a dataflow and its node files into a `.dora` bundle.
+    ///
+    /// The bundle contains the descriptor, its detached signature, and all
+    /// local node files, including the files of `targets` for other
+    /// architectures. It can be started through `dora start <bundle>.dora`.
+    Bundle {
+        /// Path to the dataflow descriptor file
+        #[clap(value_name = "PATH", value_hint = clap::ValueHint::FilePath)]
+        dataflow: PathBuf,
+        /// Path of the bundle, defaults to `<name>-<version>.dora`
+        #[clap(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+        /// Name of the bundle, defaults to the descriptor file name
+        #[clap(long)]
+        name: Option<String>,
+        /// Version of the bundle, e.g. `1.2.0`
+        #[clap(long)]
+        version: Option<String>,
+    },
     /// Run daemon
+    ///
+    /// Uses the `dora-daemon-local` socket instead of `--local-listen-port`
+    /// when started through a systemd socket unit.
     Daemon {
         /// Unique identifier for the machine (required for distributed dataflows)
         #[clap(long)]
@@ -206,6 +513,9 @@ enum Command {
         #[clap(long, default_value_t = SocketAddr::new(LISTEN_WILDCARD, 0))]
         inter_daemon_addr: SocketAddr,
         /// Local listen port for event such as dynamic node.
+        ///
+        /// Dynamic nodes are only accepted if they present the token that is set
+        /// in the `DORA_DYNAMIC_NODE_TOKEN` environment variable of the daemon.
         #[clap(long, default_value_t = DORA_DAEMON_LOCAL_LISTEN_PORT_DEFAULT)]
         local_listen_port: u16,
         /// Address and port number of the dora coordinator
@@ -216,13 +526,32 @@ enum Command {
         coordinator_port: u16,
         #[clap(long, hide = true)]
         run_dataflow: Option<PathBuf>,
+        /// File with the base64 encoded public keys that dataflows can be
+        /// signed with, one per line
+        #[clap(long, value_name = "FILE", value_hint = clap::ValueHint::FilePath)]
+        trusted_keys: Option<PathBuf>,
+        /// Refuse dataflows that are not signed by a trusted key (production mode)
+        #[clap(long, action, requires = "trusted_keys")]
+        require_signed: bool,
         /// Suppresses all log output to stdout.
         #[clap(long)]
         quiet: bool,
     },
     /// Run runtime
     Runtime,
+    /// Run a WebAssembly node (used by the daemon for nodes with a `.wasm` source)
+    #[clap(hide = true)]
+    WasmNode {
+        /// Path to the WebAssembly module
+        module: PathBuf,
+        /// Arguments that are passed to the module
+        #[clap(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
     /// Run coordinator
+    ///
+    /// Uses the `dora-control` socket instead of `--control-port` when
+    /// started through a systemd socket unit.
     Coordinator {
         /// Network interface to bind to for daemon communication
         #[clap(long, default_value_t = LISTEN_WILDCARD)]
@@ -239,6 +568,27 @@ enum Command {
         /// Suppresses all log output to stdout.
         #[clap(long)]
         quiet: bool,
+        /// Where to export lifecycle events, e.g. started dataflows or operator
+        /// restarts: a file path for JSON lines, an `http(s)://` URL to post the
+        /// events to (e.g. Grafana's `/api/annotations`), or `otel`
+        #[clap(long, value_name = "SINK")]
+        lifecycle_events: Option<LifecycleSink>,
+        /// YAML file with the access tokens of the control clients and their
+        /// roles (`viewer`, `operator`, or `admin`). Clients send their token
+        /// through the `DORA_ACCESS_TOKEN` environment variable
+        #[clap(long, value_name = "FILE", value_hint = clap::ValueHint::FilePath)]
+        access_tokens: Option<PathBuf>,
+        /// File with the base64 encoded public keys that dataflows can be
+        /// signed with, one per line
+        #[clap(long, value_name = "FILE", value_hint = clap::ValueHint::FilePath)]
+        trusted_keys: Option<PathBuf>,
+        /// Refuse dataflows that are not signed by a trusted key (production mode)
+        #[clap(long, action, requires = "trusted_keys")]
+        require_signed: bool,
+        /// IP address under which Kubernetes pods reach this coordinator,
+        /// required for nodes with a `kubernetes` deploy config
+        #[clap(long, value_name = "IP")]
+        kubernetes_advertise_addr: Option<IpAddr>,
     },
 }
 
@@ -298,7 +648,7 @@ fn run(args: Args) -> eyre::Result<()> {
             set_up_tracing_opts(name, stdout, file)
                 .context("failed to set up tracing subscriber")?;
         }
-        Command::Runtime => {
+        Command::Runtime | Command::WasmNode { .. } => {
             // Do not set the runtime in the cli.
         }
         Command::Coordinator { quiet, .. } => {
@@ -373,27 +723,244 @@ fn run(args: Args) -> eyre::Result<()> {
         Command::Logs {
             dataflow,
             node,
+            follow,
             coordinator_addr,
             coordinator_port,
         } => {
-            let mut session = connect_to_coordinator((coordinator_addr, coordinator_port).into())
+            let coordinator_socket = (coordinator_addr, coordinator_port).into();
+            let mut session = connect_to_coordinator(coordinator_socket)
                 .wrap_err("failed to connect to dora coordinator")?;
             let list = query_running_dataflows(&mut *session)
                 .wrap_err("failed to query running dataflows")?;
-            if let Some(dataflow) = dataflow {
+            let active = list.get_active();
+            let uuid = if let Some(dataflow) = dataflow {
                 let uuid = Uuid::parse_str(&dataflow).ok();
                 let name = if uuid.is_some() { None } else { Some(dataflow) };
-                logs::logs(&mut *session, uuid, name, node)?
+                let running = match (&uuid, &name) {
+                    (Some(uuid), _) => Some(*uuid),
+                    (None, Some(name)) => active
+                        .iter()
+                        .find(|d| d.name.as_deref() == Some(name.as_str()))
+                        .map(|d| d.uuid),
+                    (None, None) => None,
+                };
+                logs::logs(&mut *session, uuid, name, node.clone())?;
+                running
             } else {
-                let active = list.get_active();
                 let uuid = match &active[..] {
                     [] => bail!("No dataflows are running"),
                     [uuid] => uuid.clone(),
                     _ => inquire::Select::new("Choose dataflow to show logs:", active).prompt()?,
                 };
-                logs::logs(&mut *session, Some(uuid.uuid), None, node)?
+                logs::logs(&mut *session, Some(uuid.uuid), None, node.clone())?;
+                Some(uuid.uuid)
+            };
+            if follow {
+                let Some(uuid) = uuid else {
+                    bail!("can only follow the logs of running dataflows")
+                };
+                logs::follow(coordinator_socket, uuid, &node)?
+            }
+        }
+        Command::Health {
+            dataflow,
+            prometheus,
+            coordinator_addr,
+            coordinator_port,
+        } => {
+            let mut session = connect_to_coordinator((coordinator_addr, coordinator_port).into())
+                .wrap_err("failed to connect to dora coordinator")?;
+            let uuid = select_running_dataflow(
+                &mut *session,
+                dataflow,
+                "Choose dataflow to show health:",
+            )?;
+            health::health(&mut *session, uuid, prometheus)?
+        }
+        Command::Latency {
+            dataflow,
+            prometheus,
+            coordinator_addr,
+            coordinator_port,
+        } => {
+            let mut session = connect_to_coordinator((coordinator_addr, coordinator_port).into())
+                .wrap_err("failed to connect to dora coordinator")?;
+            let uuid = select_running_dataflow(
+                &mut *session,
+                dataflow,
+                "Choose dataflow to show latencies:",
+            )?;
+            latency::latency(&mut *session, uuid, prometheus)?
+        }
+        Command::Dump {
+            output,
+            coordinator_addr,
+            coordinator_port,
+        } => {
+            let mut session = connect_to_coordinator((coordinator_addr, coordinator_port).into())
+                .wrap_err("failed to connect to dora coordinator")?;
+            dump::dump(&mut *session, output.as_deref())?
+        }
+        Command::CrashReports {
+            dataflow,
+            node,
+            json,
+            coordinator_addr,
+            coordinator_port,
+        } => {
+            let mut session = connect_to_coordinator((coordinator_addr, coordinator_port).into())
+                .wrap_err("failed to connect to dora coordinator")?;
+            let (uuid, name) = match dataflow {
+                Some(dataflow) => match Uuid::parse_str(&dataflow) {
+                    Ok(uuid) => (Some(uuid), None),
+                    Err(_) => (None, Some(dataflow)),
+                },
+                None => {
+                    let uuid = select_running_dataflow(
+                        &mut *session,
+                        None,
+                        "Choose dataflow to show crash reports of:",
+                    )?;
+                    (Some(uuid), None)
+                }
+            };
+            crash_reports::crash_reports(&mut *session, uuid, name, node, json)?
+        }
+        Command::Record {
+            dataflow,
+            outputs,
+            exclude,
+            downsample,
+            max_size,
+            keep_last,
+            stop,
+            coordinator_addr,
+            coordinator_port,
+        } => {
+            let mut session = connect_to_coordinator((coordinator_addr, coordinator_port).into())
+                .wrap_err("failed to connect to dora coordinator")?;
+            let uuid = select_running_dataflow(
+                &mut *session,
+                dataflow,
+                if stop {
+                    "Choose dataflow to stop recording:"
+                } else {
+                    "Choose dataflow to record:"
+                },
+            )?;
+            if stop {
+                record::stop(&mut *session, uuid)?
+            } else {
+                let options = RecordingOptions {
+                    include: outputs,
+                    exclude,
+                    downsample,
+                    max_bytes: max_size,
+                    keep_last,
+                };
+                record::start(&mut *session, uuid, options)?
+            }
+        }
+        Command::Checkpoint {
+            dataflow,
+            coordinator_addr,
+            coordinator_port,
+        } => {
+            let mut session = connect_to_coordinator((coordinator_addr, coordinator_port).into())
+                .wrap_err("failed to connect to dora coordinator")?;
+            let uuid =
+                select_running_dataflow(&mut *session, dataflow, "Choose dataflow to checkpoint:")?;
+            checkpoint::checkpoint(&mut *session, uuid)?
+        }
+        Command::Replay {
+            dataflow,
+            pause,
+            resume,
+            step,
+            speed,
+            coordinator_addr,
+            coordinator_port,
+        } => {
+            let control = match (pause, resume, step, speed) {
+                (true, _, _, _) => ReplayControl::Pause,
+                (_, true, _, _) => ReplayControl::Resume,
+                (_, _, true, _) => ReplayControl::Step,
+                (_, _, _, Some(speed)) => ReplayControl::SetSpeed(speed),
+                (false, false, false, None) => unreachable!("clap requires one replay control"),
+            };
+            let mut session = connect_to_coordinator((coordinator_addr, coordinator_port).into())
+                .wrap_err("failed to connect to dora coordinator")?;
+            let uuid =
+                select_running_dataflow(&mut *session, dataflow, "Choose dataflow to control:")?;
+            replay::control(&mut *session, uuid, control)?
+        }
+        Command::Export { recording, output } => {
+            let output = output.unwrap_or_else(|| recording.with_extension("mcap"));
+            record::export(&recording, &output)?
+        }
+        Command::Golden {
+            golden,
+            recording,
+            update,
+            float_precision,
+            ignore_parameter,
+            ignore_output,
+            time_tolerance,
+        } => {
+            let normalization = Normalization {
+                float_precision,
+                ignore_parameters: ignore_parameter,
+                ignore_outputs: ignore_output,
+                time_tolerance_ms: time_tolerance.map(|t| t.as_millis() as u64),
+            };
+            if update || !golden.exists() {
+                golden::update(&golden, &recording, normalization)?
+            } else {
+                golden::compare(&golden, &recording)?
             }
         }
+        Command::Sign {
+            dataflow,
+            key,
+            generate_key,
+            embed,
+        } => sign::sign(&dataflow, &key, generate_key, embed)?,
+        Command::Bundle {
+            dataflow,
+            output,
+            name,
+            version,
+        } => bundle::bundle(&dataflow, output, name, version)?,
+        Command::Top {
+            dataflow,
+            watch,
+            coordinator_addr,
+            coordinator_port,
+        } => {
+            let mut session = connect_to_coordinator((coordinator_addr, coordinator_port).into())
+                .wrap_err("failed to connect to dora coordinator")?;
+            let uuid = select_running_dataflow(
+                &mut *session,
+                dataflow,
+                "Choose dataflow to show the resource usage of:",
+            )?;
+            top::top(&mut *session, uuid, watch)?
+        }
+        Command::LiveGraph {
+            dataflow,
+            watch,
+            coordinator_addr,
+            coordinator_port,
+        } => {
+            let mut session = connect_to_coordinator((coordinator_addr, coordinator_port).into())
+                .wrap_err("failed to connect to dora coordinator")?;
+            let uuid = select_running_dataflow(
+                &mut *session,
+                dataflow,
+                "Choose dataflow to show the live graph of:",
+            )?;
+            live_graph::live_graph(&mut *session, uuid, watch)?
+        }
         Command::Start {
             dataflow,
             name,
@@ -402,10 +969,23 @@ fn run(args: Args) -> eyre::Result<()> {
             attach,
             detach,
             hot_reload,
+            restore,
+            distribute,
         } => {
-            let dataflow = resolve_dataflow(dataflow).context("could not resolve dataflow")?;
-            let dataflow_descriptor =
+            let mut dataflow = resolve_dataflow(dataflow).context("could not resolve dataflow")?;
+            if dataflow.extension().is_some_and(|ext| ext == "dora") {
+                dataflow = bundle::unpack(&dataflow).context("failed to unpack bundle")?;
+            }
+            let mut dataflow_descriptor =
                 Descriptor::blocking_read(&dataflow).wrap_err("Failed to read yaml dataflow")?;
+            let signed =
+                signing::read_signed(&dataflow).wrap_err("failed to read dataflow signature")?;
+            if let Some(restore) = restore {
+                let restore = std::env::current_dir()
+                    .context("failed to get current dir")?
+                    .join(restore);
+                dataflow_descriptor.restore = Some(restore);
+            }
             let working_dir = dataflow
                 .canonicalize()
                 .context("failed to canonicalize dataflow path")?
@@ -416,10 +996,22 @@ fn run(args: Args) -> eyre::Result<()> {
             let coordinator_socket = (coordinator_addr, coordinator_port).into();
             let mut session = connect_to_coordinator(coordinator_socket)
                 .wrap_err("failed to connect to dora coordinator")?;
+            if distribute {
+                if signed.is_some() {
+                    // the daemons run the signed content, which doesn't
+                    // contain the checksums that are pinned here
+                    eprintln!(
+                        "Note: the nodes of signed dataflows must set `sha256` to use the uploaded files"
+                    );
+                }
+                distribute::distribute(&mut dataflow_descriptor, &working_dir, &mut *session)
+                    .wrap_err("failed to distribute node executables")?;
+            }
             let dataflow_id = start_dataflow(
                 dataflow_descriptor.clone(),
                 name,
                 working_dir,
+                signed,
                 &mut *session,
             )?;
 
@@ -483,7 +1075,16 @@ fn run(args: Args) -> eyre::Result<()> {
             control_interface,
             control_port,
             quiet,
+            lifecycle_events,
+            trusted_keys,
+            require_signed,
+            access_tokens,
+            kubernetes_advertise_addr,
         } => {
+            let signature_policy = sign::signature_policy(trusted_keys, require_signed)?;
+            let access_control = access_tokens
+                .map(|path| AccessControl::read(&path))
+                .transpose()?;
             let rt = Builder::new_multi_thread()
                 .enable_all()
                 .build()
@@ -491,9 +1092,16 @@ fn run(args: Args) -> eyre::Result<()> {
             rt.block_on(async {
                 let bind = SocketAddr::new(interface, port);
                 let bind_control = SocketAddr::new(control_interface, control_port);
-                let (port, task) =
-                    dora_coordinator::start(bind, bind_control, futures::stream::empty::<Event>())
-                        .await?;
+                let (port, task) = dora_coordinator::start(
+                    bind,
+                    bind_control,
+                    futures::stream::empty::<Event>(),
+                    lifecycle_events,
+                    signature_policy,
+                    access_control,
+                    kubernetes_advertise_addr,
+                )
+                .await?;
                 if !quiet {
                     println!("Listening for incoming daemon connection on {port}");
                 }
@@ -508,8 +1116,11 @@ fn run(args: Args) -> eyre::Result<()> {
             local_listen_port,
             machine_id,
             run_dataflow,
+            trusted_keys,
+            require_signed,
             quiet: _,
         } => {
+            let signature_policy = sign::signature_policy(trusted_keys, require_signed)?;
             let rt = Builder::new_multi_thread()
                 .enable_all()
                 .build()
@@ -525,17 +1136,25 @@ fn run(args: Args) -> eyre::Result<()> {
                             );
                         }
 
+                        if let Some(policy) = &signature_policy {
+                            let descriptor = Descriptor::blocking_read(&dataflow_path)?;
+                            let signed = signing::read_signed(&dataflow_path)?;
+                            policy.check(descriptor, signed.as_ref())?;
+                        }
                         let result = Daemon::run_dataflow(&dataflow_path, false).await?;
                         handle_dataflow_result(result, None)
                     }
                     None => {
-                        Daemon::run(SocketAddr::new(coordinator_addr, coordinator_port), machine_id.unwrap_or_default(), inter_daemon_addr, local_listen_port).await
+                        Daemon::run(SocketAddr::new(coordinator_addr, coordinator_port), machine_id.unwrap_or_default(), inter_daemon_addr, local_listen_port, signature_policy, Footprint::Default).await
                     }
                 }
             })
             .context("failed to run dora-daemon")?
         }
         Command::Runtime => dora_runtime::main().context("Failed to run dora-runtime")?,
+        Command::WasmNode { module, args } => {
+            dora_runtime::wasm::main(&module, args).context("Failed to run WASM node")?
+        }
     };
 
     Ok(())
@@ -545,6 +1164,7 @@ fn start_dataflow(
     dataflow: Descriptor,
     name: Option<String>,
     local_working_dir: PathBuf,
+    signed: Option<SignedDescriptor>,
     session: &mut TcpRequestReplyConnection,
 ) -> Result<Uuid, eyre::ErrReport> {
     let reply_raw = session
@@ -553,6 +1173,7 @@ fn start_dataflow(
                 dataflow,
                 name,
                 local_working_dir,
+                signed,
             })
             .unwrap(),
         )
@@ -611,7 +1232,42 @@ fn stop_dataflow(
     }
 }
 
+/// Returns the UUID of the running dataflow with the given UUID or name.
+///
+/// If no dataflow is given, the only running dataflow is selected or the
+/// user is asked to choose one.
+fn select_running_dataflow(
+    session: &mut TcpRequestReplyConnection,
+    dataflow: Option<String>,
+    prompt: &str,
+) -> eyre::Result<Uuid> {
+    let active = query_running_dataflows(session)
+        .wrap_err("failed to query running dataflows")?
+        .get_active();
+    let uuid = match dataflow {
+        Some(dataflow) => match Uuid::parse_str(&dataflow) {
+            Ok(uuid) => uuid,
+            Err(_) => match active
+                .iter()
+                .find(|d| d.name.as_deref() == Some(dataflow.as_str()))
+            {
+                Some(d) => d.uuid,
+                None => bail!("no running dataflow with name `{dataflow}`"),
+            },
+        },
+        None => match &active[..] {
+            [] => bail!("No dataflows are running"),
+            [entry] => entry.uuid,
+            _ => inquire::Select::new(prompt, active).prompt()?.uuid,
+        },
+    };
+    Ok(uuid)
+}
+
 fn handle_dataflow_result(result: DataflowResult, uuid: Option<Uuid>) -> Result<(), eyre::Error> {
+    for (node_id, reason) in &result.stop_requests {
+        println!("Dataflow was stopped on request of node `{node_id}`: {reason}");
+    }
     if result.is_ok() {
         Ok(())
     } else {
@@ -664,6 +1320,10 @@ fn list(session: &mut TcpRequestReplyConnection) -> Result<(), eyre::ErrReport>
             DataflowStatus::Finished => "Succeeded",
             DataflowStatus::Failed => "Failed",
         };
+        let status = match entry.stop_requests.iter().next() {
+            Some((node_id, reason)) => format!("{status} (stopped by `{node_id}`: {reason})"),
+            None => status.to_owned(),
+        };
         tw.write_all(format!("{uuid}\t{name}\t{status}\n").as_bytes())?;
     }
     tw.flush()?;
@@ -689,10 +1349,42 @@ fn query_running_dataflows(session: &mut TcpRequestReplyConnection) -> eyre::Res
     Ok(ids)
 }
 
+/// Access token that is sent to coordinators with access control enabled.
+const ACCESS_TOKEN_ENV: &str = "DORA_ACCESS_TOKEN";
+
 fn connect_to_coordinator(
     coordinator_addr: SocketAddr,
-) -> std::io::Result<Box<TcpRequestReplyConnection>> {
-    TcpLayer::new().connect(coordinator_addr)
+) -> eyre::Result<Box<TcpRequestReplyConnection>> {
+    let mut session = TcpLayer::new().connect(coordinator_addr)?;
+    if let Ok(token) = std::env::var(ACCESS_TOKEN_ENV) {
+        let reply_raw = session
+            .request(&serde_json::to_vec(&ControlRequest::Authenticate { token }).unwrap())
+            .wrap_err("failed to send authentication request")?;
+        check_authenticated(&reply_raw)?;
+    }
+    Ok(session)
+}
+
+/// Authenticates a connection for log messages, like [`connect_to_coordinator`].
+fn authenticate_log_session(session: &mut TcpConnection) -> eyre::Result<()> {
+    if let Ok(token) = std::env::var(ACCESS_TOKEN_ENV) {
+        session
+            .send(&serde_json::to_vec(&ControlRequest::Authenticate { token }).unwrap())
+            .wrap_err("failed to send authentication request")?;
+        let reply_raw = session
+            .receive()
+            .wrap_err("failed to receive authentication reply")?;
+        check_authenticated(&reply_raw)?;
+    }
+    Ok(())
+}
+
+fn check_authenticated(reply_raw: &[u8]) -> eyre::Result<()> {
+    match serde_json::from_slice(reply_raw).wrap_err("failed to parse reply")? {
+        ControlRequestReply::Authenticated { .. } => Ok(()),
+        ControlRequestReply::Error(err) => bail!("authentication failed: {err}"),
+        other => bail!("unexpected authentication reply: {other:?}"),
+    }
 }
 
 fn resolve_dataflow(dataflow: String) -> eyre::Result<PathBuf> {
@@ -0,0 +1,47 @@
+use std::{thread, time::Duration};
+
+use communication_layer_request_reply::TcpRequestReplyConnection;
+use dora_message::{
+    cli_to_coordinator::ControlRequest,
+    coordinator_to_cli::{ControlRequestReply, DataflowGraph},
+};
+use eyre::{bail, Context, Result};
+use uuid::Uuid;
+
+/// Prints the live graph of the dataflow as JSON.
+///
+/// With `watch`, a new graph is printed as a single JSON line every `interval`
+/// until the dataflow is no longer running.
+pub fn live_graph(
+    session: &mut TcpRequestReplyConnection,
+    dataflow_uuid: Uuid,
+    watch: Option<Duration>,
+) -> Result<()> {
+    let Some(interval) = watch else {
+        let graph = query_live_graph(session, dataflow_uuid)?;
+        println!("{}", serde_json::to_string_pretty(&graph)?);
+        return Ok(());
+    };
+
+    loop {
+        let graph = query_live_graph(session, dataflow_uuid)?;
+        println!("{}", serde_json::to_string(&graph)?);
+        thread::sleep(interval);
+    }
+}
+
+fn query_live_graph(
+    session: &mut TcpRequestReplyConnection,
+    dataflow_uuid: Uuid,
+) -> Result<DataflowGraph> {
+    let reply_raw = session
+        .request(&serde_json::to_vec(&ControlRequest::LiveGraph { dataflow_uuid }).unwrap())
+        .wrap_err("failed to send LiveGraph request message")?;
+
+    let reply = serde_json::from_slice(&reply_raw).wrap_err("failed to parse reply")?;
+    match reply {
+        ControlRequestReply::LiveGraph(graph) => Ok(graph),
+        ControlRequestReply::Error(err) => bail!("{err}"),
+        other => bail!("unexpected reply to live graph: {other:?}"),
+    }
+}
@@ -1,6 +1,9 @@
-use communication_layer_request_reply::TcpRequestReplyConnection;
-use dora_message::{cli_to_coordinator::ControlRequest, coordinator_to_cli::ControlRequestReply};
+use communication_layer_request_reply::{TcpConnection, TcpRequestReplyConnection};
+use dora_message::{
+    cli_to_coordinator::ControlRequest, common::LogMessage, coordinator_to_cli::ControlRequestReply,
+};
 use eyre::{bail, Context, Result};
+use std::net::{SocketAddr, TcpStream};
 use uuid::Uuid;
 
 use bat::{Input, PrettyPrinter};
@@ -43,3 +46,39 @@ pub fn logs(
 
     Ok(())
 }
+
+/// Prints the output and the log records of the given node as they arrive,
+/// until the dataflow finishes.
+pub fn follow(coordinator_socket: SocketAddr, dataflow_id: Uuid, node: &str) -> Result<()> {
+    let mut log_session = TcpConnection {
+        stream: TcpStream::connect(coordinator_socket)
+            .wrap_err("failed to connect to dora coordinator")?,
+    };
+    crate::authenticate_log_session(&mut log_session)?;
+    log_session
+        .send(
+            &serde_json::to_vec(&ControlRequest::LogSubscribe {
+                dataflow_id,
+                level: log::LevelFilter::Trace,
+            })
+            .wrap_err("failed to serialize message")?,
+        )
+        .wrap_err("failed to send log subscribe request to coordinator")?;
+
+    while let Ok(raw) = log_session.receive() {
+        let message: LogMessage =
+            serde_json::from_slice(&raw).context("failed to parse log message")?;
+        if message.node_id.as_ref().map(|id| id.to_string()).as_deref() != Some(node) {
+            continue;
+        }
+        match message.target.as_deref() {
+            // captured output of the node process
+            Some("stdout") | Some("stderr") => println!("{}", message.message),
+            target => {
+                let target = target.map(|t| format!(" {t}")).unwrap_or_default();
+                println!("{:5}{target}: {}", message.level, message.message)
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use std::path::Path;
+
+use communication_layer_request_reply::TcpRequestReplyConnection;
+use dora_message::{
+    cli_to_coordinator::{ControlRequest, RecordingOptions},
+    coordinator_to_cli::ControlRequestReply,
+};
+use eyre::{bail, Context, Result};
+use uuid::Uuid;
+
+/// Starts recording the outputs of a running dataflow that are selected by the
+/// given options.
+pub fn start(
+    session: &mut TcpRequestReplyConnection,
+    dataflow_uuid: Uuid,
+    options: RecordingOptions,
+) -> Result<()> {
+    let reply_raw = session
+        .request(
+            &serde_json::to_vec(&ControlRequest::StartRecording {
+                dataflow_uuid,
+                options,
+            })
+            .unwrap(),
+        )
+        .wrap_err("failed to send StartRecording request message")?;
+
+    let reply = serde_json::from_slice(&reply_raw).wrap_err("failed to parse reply")?;
+    match reply {
+        ControlRequestReply::RecordingStarted { uuid, recording_id } => {
+            println!("Recording `{recording_id}` of dataflow `{uuid}` started");
+        }
+        ControlRequestReply::Error(err) => bail!("{err}"),
+        other => bail!("unexpected reply to start recording: {other:?}"),
+    }
+    Ok(())
+}
+
+/// Stops the active recording of a running dataflow.
+pub fn stop(session: &mut TcpRequestReplyConnection, dataflow_uuid: Uuid) -> Result<()> {
+    let reply_raw = session
+        .request(&serde_json::to_vec(&ControlRequest::StopRecording { dataflow_uuid }).unwrap())
+        .wrap_err("failed to send StopRecording request message")?;
+
+    let reply = serde_json::from_slice(&reply_raw).wrap_err("failed to parse reply")?;
+    let recordings = match reply {
+        ControlRequestReply::RecordingStopped { recordings, .. } => recordings,
+        ControlRequestReply::Error(err) => bail!("{err}"),
+        other => bail!("unexpected reply to stop recording: {other:?}"),
+    };
+
+    for recording in recordings {
+        println!(
+            "Machine `{}`: recorded {} messages ({} bytes) to {}",
+            recording.machine_id,
+            recording.messages,
+            recording.bytes,
+            recording.path.display()
+        );
+        if recording.dropped_messages > 0 {
+            println!(
+                "  {} messages were dropped because the recorder couldn't keep up",
+                recording.dropped_messages
+            );
+        }
+        if recording.deleted_messages > 0 {
+            println!(
+                "  {} older messages were deleted because of the size or age limit",
+                recording.deleted_messages
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Converts a recording to an MCAP file.
+pub fn export(recording: &Path, output: &Path) -> Result<()> {
+    let export = dora_recording::export_mcap(recording, output).wrap_err_with(|| {
+        format!(
+            "failed to export recording `{}` to MCAP",
+            recording.display()
+        )
+    })?;
+    println!(
+        "Exported {} messages on {} channels to {}",
+        export.messages,
+        export.channels,
+        export.path.display()
+    );
+    if let Some(err) = export.first_conversion_error {
+        println!(
+            "  the data of {} messages couldn't be converted and was omitted, e.g.: {err}",
+            export.conversion_errors
+        );
+    }
+    Ok(())
+}
+
+/// Parses a downsampling option of the form `PATTERN=N`.
+pub fn parse_downsampling(value: &str) -> Result<(String, u64)> {
+    let Some((pattern, n)) = value.rsplit_once('=') else {
+        bail!("expected `PATTERN=N`, got `{value}`");
+    };
+    let n = n
+        .parse()
+        .wrap_err_with(|| format!("invalid downsampling factor `{n}`"))?;
+    if n == 0 {
+        bail!("downsampling factor must be at least 1");
+    }
+    Ok((pattern.to_owned(), n))
+}
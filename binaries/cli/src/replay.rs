@@ -0,0 +1,44 @@
+use communication_layer_request_reply::TcpRequestReplyConnection;
+use dora_message::{
+    cli_to_coordinator::{ControlRequest, ReplayControl},
+    coordinator_to_cli::ControlRequestReply,
+};
+use eyre::{bail, Context, Result};
+use uuid::Uuid;
+
+/// Pauses, resumes, steps or changes the speed of the replay of a running
+/// dataflow.
+pub fn control(
+    session: &mut TcpRequestReplyConnection,
+    dataflow_uuid: Uuid,
+    control: ReplayControl,
+) -> Result<()> {
+    let reply_raw = session
+        .request(
+            &serde_json::to_vec(&ControlRequest::ControlReplay {
+                dataflow_uuid,
+                control,
+            })
+            .unwrap(),
+        )
+        .wrap_err("failed to send ControlReplay request message")?;
+
+    let reply = serde_json::from_slice(&reply_raw).wrap_err("failed to parse reply")?;
+    match reply {
+        ControlRequestReply::ReplayControlled { .. } => {}
+        ControlRequestReply::Error(err) => bail!("{err}"),
+        other => bail!("unexpected reply to replay control: {other:?}"),
+    }
+
+    match control {
+        ReplayControl::Pause => println!("Replay of dataflow `{dataflow_uuid}` paused"),
+        ReplayControl::Resume => println!("Replay of dataflow `{dataflow_uuid}` resumed"),
+        ReplayControl::Step => {
+            println!("Replay of dataflow `{dataflow_uuid}` advanced by one message")
+        }
+        ReplayControl::SetSpeed(speed) => {
+            println!("Replay of dataflow `{dataflow_uuid}` set to {speed}x speed")
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use std::path::{Path, PathBuf};
+
+use dora_core::descriptor::signing::{self, DescriptorSigner, SignaturePolicy, TrustedKeys};
+use eyre::Context;
+
+/// Signs the dataflow with the given key, optionally generating it first.
+pub fn sign(dataflow: &Path, key: &Path, generate_key: bool, embed: bool) -> eyre::Result<()> {
+    let signer = if generate_key {
+        let signer = DescriptorSigner::generate(key)?;
+        println!(
+            "Generated signing key `{}`, add the public key to the trusted keys:\n{}",
+            key.display(),
+            signer.public_key()
+        );
+        signer
+    } else {
+        DescriptorSigner::read(key)?
+    };
+    let path = signing::sign_file(dataflow, &signer, embed)
+        .wrap_err_with(|| format!("failed to sign `{}`", dataflow.display()))?;
+    println!("Wrote signature to `{}`", path.display());
+    Ok(())
+}
+
+pub fn signature_policy(
+    trusted_keys: Option<PathBuf>,
+    require_signed: bool,
+) -> eyre::Result<Option<SignaturePolicy>> {
+    trusted_keys
+        .map(|path| {
+            Ok(SignaturePolicy {
+                trusted_keys: TrustedKeys::read(&path)?,
+                require_signed,
+            })
+        })
+        .transpose()
+}
@@ -10,6 +10,15 @@ class Operator:
         """Called on initialisation"""
         pass
 
+    def on_start(self, config, send_output):
+        """Optional, called once before the first event.
+
+        Args:
+            config: Dict with the `id`, `inputs`, and `outputs` of the operator.
+            send_output: Same as for `on_event`.
+        """
+        pass
+
     def on_event(
         self,
         dora_event,
@@ -40,6 +49,10 @@ class Operator:
 
         return DoraStatus.CONTINUE
 
+    def on_stop(self, send_output):
+        """Optional, called once after the last event, e.g. to send final outputs."""
+        pass
+
     def __del__(self):
         """Called before being deleted"""
         pass
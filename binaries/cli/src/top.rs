@@ -0,0 +1,86 @@
+use std::{io::Write, thread, time::Duration};
+
+use communication_layer_request_reply::TcpRequestReplyConnection;
+use dora_message::coordinator_to_cli::DataflowHealth;
+use eyre::{Context, Result};
+use tabwriter::TabWriter;
+use uuid::Uuid;
+
+use crate::health::query_health;
+
+/// Prints the resource usage of the nodes of the dataflow.
+///
+/// With `watch`, the table is refreshed every `interval` until the dataflow
+/// is no longer running.
+pub fn top(
+    session: &mut TcpRequestReplyConnection,
+    dataflow_uuid: Uuid,
+    watch: Option<Duration>,
+) -> Result<()> {
+    let Some(interval) = watch else {
+        let health = query_health(session, dataflow_uuid)?;
+        return print_usage(&health);
+    };
+
+    loop {
+        let health = query_health(session, dataflow_uuid)?;
+        // clear the terminal and move the cursor to the top left
+        print!("\x1b[2J\x1b[H");
+        print_usage(&health)?;
+        thread::sleep(interval);
+    }
+}
+
+fn print_usage(health: &DataflowHealth) -> Result<()> {
+    println!("{}\n", health.id);
+
+    let mut tw = TabWriter::new(vec![]);
+    tw.write_all(b"Node\tCPU\tCPU time\tMemory\tIO read\tIO write\n")?;
+    for node_id in health.nodes.keys() {
+        let Some(usage) = health.resources.get(node_id) else {
+            tw.write_all(format!("{node_id}\t-\t-\t-\t-\t-\n").as_bytes())?;
+            continue;
+        };
+        let cpu_time = usage.cpu_time.as_secs_f64();
+        tw.write_all(
+            format!(
+                "{node_id}\t{:.1}%\t{cpu_time:.1}s\t{}\t{}\t{}\n",
+                usage.cpu_percent,
+                format_bytes(usage.memory_bytes),
+                format_bytes(usage.io_read_bytes),
+                format_bytes(usage.io_write_bytes),
+            )
+            .as_bytes(),
+        )?;
+    }
+    tw.flush()?;
+    let formatted = String::from_utf8(tw.into_inner()?).context("invalid table")?;
+    print!("{formatted}");
+
+    if health.resources.is_empty() {
+        println!(
+            "\nNo resource usage reported. Nodes are only accounted when their \
+             daemon can place them into cgroups (Linux with cgroup v2)."
+        );
+    }
+    std::io::stdout().flush()?;
+    Ok(())
+}
+
+fn format_bytes(bytes: Option<u64>) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let Some(bytes) = bytes else {
+        return "-".to_owned();
+    };
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
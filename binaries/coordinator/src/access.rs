@@ -0,0 +1,125 @@
+//! Role-based access control for the control API.
+
+use std::path::Path;
+
+use dora_message::cli_to_coordinator::Role;
+use eyre::{bail, Context};
+
+/// Identity that an access token is bound to.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Identity {
+    pub name: String,
+    pub role: Role,
+    token: String,
+}
+
+/// The access tokens that are accepted by the coordinator.
+///
+/// Read from a YAML file, e.g.:
+///
+/// ```yaml
+/// - name: ci
+///   role: operator
+///   token: 9c1185a5c5e9fc54612808977ee8f548
+/// ```
+#[derive(Debug, Clone)]
+pub struct AccessControl {
+    identities: Vec<Identity>,
+}
+
+impl AccessControl {
+    pub fn read(path: &Path) -> eyre::Result<Self> {
+        let file = std::fs::read(path)
+            .wrap_err_with(|| format!("failed to read access tokens `{}`", path.display()))?;
+        let identities: Vec<Identity> = serde_yaml::from_slice(&file)
+            .wrap_err_with(|| format!("failed to parse access tokens `{}`", path.display()))?;
+        Self::new(identities)
+            .wrap_err_with(|| format!("invalid access tokens `{}`", path.display()))
+    }
+
+    fn new(identities: Vec<Identity>) -> eyre::Result<Self> {
+        if identities.is_empty() {
+            bail!("no access tokens defined");
+        }
+        for identity in &identities {
+            if identity.token.len() < 16 {
+                bail!(
+                    "access token of `{}` is too short, use at least 16 characters",
+                    identity.name
+                );
+            }
+        }
+        Ok(Self { identities })
+    }
+
+    /// Returns the identity that the token is bound to.
+    pub fn authenticate(&self, token: &str) -> Option<&Identity> {
+        // compare all tokens in constant time to not leak timing information
+        self.identities
+            .iter()
+            .filter(|identity| constant_time_eq(identity.token.as_bytes(), token.as_bytes()))
+            .last()
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn access_control() -> AccessControl {
+        let identities = serde_yaml::from_str(
+            "[
+                { name: dashboard, role: viewer, token: viewer-token-0123456789 },
+                { name: ci, role: operator, token: operator-token-0123456789 },
+                { name: root, role: admin, token: admin-token-0123456789 },
+            ]",
+        )
+        .unwrap();
+        AccessControl::new(identities).unwrap()
+    }
+
+    #[test]
+    fn authenticates_known_tokens() {
+        let access_control = access_control();
+        let identity = access_control
+            .authenticate("operator-token-0123456789")
+            .unwrap();
+        assert_eq!(identity.name, "ci");
+        assert_eq!(identity.role, Role::Operator);
+    }
+
+    #[test]
+    fn rejects_unknown_tokens() {
+        let access_control = access_control();
+        assert!(access_control.authenticate("").is_none());
+        assert!(access_control.authenticate("viewer-token").is_none());
+        assert!(access_control
+            .authenticate("viewer-token-0123456789-suffix")
+            .is_none());
+        assert!(access_control
+            .authenticate("viewer-token-0123456788")
+            .is_none());
+    }
+
+    #[test]
+    fn rejects_short_tokens() {
+        let identities =
+            serde_yaml::from_str("[{ name: ci, role: operator, token: too-short }]").unwrap();
+        assert!(AccessControl::new(identities).is_err());
+        assert!(AccessControl::new(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn constant_time_eq_compares_lengths() {
+        assert!(constant_time_eq(b"token", b"token"));
+        assert!(!constant_time_eq(b"token", b"tokem"));
+        assert!(!constant_time_eq(b"token", b"token2"));
+        assert!(!constant_time_eq(b"token", b"tok"));
+        assert!(!constant_time_eq(b"", b"token"));
+    }
+}
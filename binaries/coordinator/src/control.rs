@@ -1,8 +1,13 @@
 use crate::{
+    access::{AccessControl, Identity},
     tcp_utils::{tcp_receive, tcp_send},
     Event,
 };
-use dora_message::{cli_to_coordinator::ControlRequest, coordinator_to_cli::ControlRequestReply};
+use dora_core::socket_activation;
+use dora_message::{
+    cli_to_coordinator::{ControlRequest, Role},
+    coordinator_to_cli::ControlRequestReply,
+};
 use eyre::{eyre, Context};
 use futures::{
     future::{self, Either},
@@ -10,7 +15,7 @@ use futures::{
     FutureExt, Stream, StreamExt,
 };
 use futures_concurrency::future::Race;
-use std::{io::ErrorKind, net::SocketAddr};
+use std::{io::ErrorKind, net::SocketAddr, sync::Arc};
 use tokio::{
     net::{TcpListener, TcpStream},
     sync::{mpsc, oneshot},
@@ -22,11 +27,17 @@ use uuid::Uuid;
 pub(crate) async fn control_events(
     control_listen_addr: SocketAddr,
     tasks: &FuturesUnordered<JoinHandle<()>>,
+    access_control: Option<AccessControl>,
 ) -> eyre::Result<impl Stream<Item = Event>> {
     let (tx, rx) = mpsc::channel(10);
 
     let (finish_tx, mut finish_rx) = mpsc::channel(1);
-    tasks.push(tokio::spawn(listen(control_listen_addr, tx, finish_tx)));
+    tasks.push(tokio::spawn(listen(
+        control_listen_addr,
+        tx,
+        finish_tx,
+        access_control.map(Arc::new),
+    )));
     tasks.push(tokio::spawn(async move {
         while let Some(()) = finish_rx.recv().await {}
     }));
@@ -38,10 +49,18 @@ async fn listen(
     control_listen_addr: SocketAddr,
     tx: mpsc::Sender<ControlEvent>,
     _finish_tx: mpsc::Sender<()>,
+    access_control: Option<Arc<AccessControl>>,
 ) {
-    let result = TcpListener::bind(control_listen_addr)
-        .await
-        .wrap_err("failed to listen for control messages");
+    let result = match socket_activation::take_listener(socket_activation::CONTROL_SOCKET_NAME) {
+        Ok(Some(listener)) => {
+            tracing::info!("using control socket passed by systemd");
+            TcpListener::from_std(listener).wrap_err("failed to use control socket")
+        }
+        Ok(None) => TcpListener::bind(control_listen_addr)
+            .await
+            .wrap_err("failed to listen for control messages"),
+        Err(err) => Err(err),
+    };
     let incoming = match result {
         Ok(incoming) => incoming,
         Err(err) => {
@@ -63,7 +82,12 @@ async fn listen(
         match connection.wrap_err("failed to connect") {
             Ok((connection, _)) => {
                 let tx = tx.clone();
-                tokio::spawn(handle_requests(connection, tx, _finish_tx.clone()));
+                tokio::spawn(handle_requests(
+                    connection,
+                    tx,
+                    _finish_tx.clone(),
+                    access_control.clone(),
+                ));
             }
             Err(err) => {
                 if tx.blocking_send(err.into()).is_err() {
@@ -78,7 +102,10 @@ async fn handle_requests(
     mut connection: TcpStream,
     tx: mpsc::Sender<ControlEvent>,
     _finish_tx: mpsc::Sender<()>,
+    access_control: Option<Arc<AccessControl>>,
 ) {
+    // without access control, all clients are admins
+    let mut identity: Option<Identity> = None;
     loop {
         let next_request = tcp_receive(&mut connection).map(Either::Left);
         let coordinator_stopped = tx.closed().map(Either::Right);
@@ -100,8 +127,12 @@ async fn handle_requests(
             },
         };
 
-        let request =
-            serde_json::from_slice(&raw).wrap_err("failed to deserialize incoming message");
+        let request = serde_json::from_slice(&raw)
+            .wrap_err("failed to deserialize incoming message")
+            .and_then(|request| match &access_control {
+                Some(access_control) => authorize(request, access_control, &mut identity),
+                None => Ok(request),
+            });
 
         if let Ok(ControlRequest::LogSubscribe { dataflow_id, level }) = request {
             let _ = tx
@@ -115,6 +146,16 @@ async fn handle_requests(
         }
 
         let result = match request {
+            Ok(ControlRequest::Authenticate { .. }) => Ok(match &identity {
+                Some(identity) => ControlRequestReply::Authenticated {
+                    name: identity.name.clone(),
+                    role: identity.role,
+                },
+                None => ControlRequestReply::Authenticated {
+                    name: "anonymous".into(),
+                    role: Role::Admin,
+                },
+            }),
             Ok(request) => handle_request(request, &tx).await,
             Err(err) => Err(err),
         };
@@ -149,6 +190,51 @@ async fn handle_requests(
     }
 }
 
+/// Authenticates the connection or checks that its role allows the request.
+fn authorize(
+    request: ControlRequest,
+    access_control: &AccessControl,
+    identity: &mut Option<Identity>,
+) -> eyre::Result<ControlRequest> {
+    if let ControlRequest::Authenticate { token } = &request {
+        match access_control.authenticate(token) {
+            Some(authenticated) => {
+                tracing::debug!(
+                    "control connection authenticated as `{}`",
+                    authenticated.name
+                );
+                *identity = Some(authenticated.clone());
+            }
+            None => {
+                tracing::warn!("rejected control connection with invalid access token");
+                *identity = None;
+                eyre::bail!("invalid access token");
+            }
+        }
+        return Ok(request);
+    }
+
+    let required = request.required_role();
+    match identity {
+        None => {
+            eyre::bail!("authentication required, set the `DORA_ACCESS_TOKEN` environment variable")
+        }
+        Some(identity) if identity.role < required => {
+            tracing::warn!(
+                "denied request of `{}` with role `{}`, which requires role `{required}`",
+                identity.name,
+                identity.role
+            );
+            eyre::bail!(
+                "permission denied: `{}` has role `{}`, but this requires role `{required}`",
+                identity.name,
+                identity.role
+            )
+        }
+        Some(_) => Ok(request),
+    }
+}
+
 async fn handle_request(
     request: ControlRequest,
     tx: &mpsc::Sender<ControlEvent>,
@@ -187,3 +273,110 @@ impl From<eyre::Report> for ControlEvent {
         ControlEvent::Error(err)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use dora_core::descriptor::Descriptor;
+
+    use super::*;
+    use crate::access::tests::access_control;
+
+    fn start() -> ControlRequest {
+        ControlRequest::Start {
+            dataflow: serde_yaml::from_str::<Descriptor>("nodes: []").unwrap(),
+            name: None,
+            local_working_dir: PathBuf::from("."),
+            signed: None,
+        }
+    }
+
+    fn stop() -> ControlRequest {
+        ControlRequest::Stop {
+            dataflow_uuid: Uuid::nil(),
+            grace_duration: None,
+        }
+    }
+
+    /// Authenticates with the given token and checks whether the requests
+    /// are allowed afterwards.
+    fn allowed(token: &str, requests: Vec<ControlRequest>) -> Vec<bool> {
+        let access_control = access_control();
+        let mut identity = None;
+        let authenticate = ControlRequest::Authenticate {
+            token: token.to_owned(),
+        };
+        authorize(authenticate, &access_control, &mut identity).unwrap();
+        requests
+            .into_iter()
+            .map(|request| authorize(request, &access_control, &mut identity).is_ok())
+            .collect()
+    }
+
+    #[test]
+    fn viewer_can_only_read() {
+        assert_eq!(
+            allowed(
+                "viewer-token-0123456789",
+                vec![
+                    ControlRequest::List,
+                    start(),
+                    stop(),
+                    ControlRequest::Destroy
+                ]
+            ),
+            [true, false, false, false]
+        );
+    }
+
+    #[test]
+    fn operator_cannot_destroy() {
+        assert_eq!(
+            allowed(
+                "operator-token-0123456789",
+                vec![
+                    ControlRequest::List,
+                    start(),
+                    stop(),
+                    ControlRequest::Destroy
+                ]
+            ),
+            [true, true, true, false]
+        );
+    }
+
+    #[test]
+    fn admin_can_destroy() {
+        assert_eq!(
+            allowed("admin-token-0123456789", vec![ControlRequest::Destroy]),
+            [true]
+        );
+    }
+
+    #[test]
+    fn requires_authentication() {
+        let access_control = access_control();
+        let mut identity = None;
+        assert!(authorize(ControlRequest::List, &access_control, &mut identity).is_err());
+        assert!(authorize(stop(), &access_control, &mut identity).is_err());
+    }
+
+    #[test]
+    fn invalid_token_resets_identity() {
+        let access_control = access_control();
+        let mut identity = None;
+        let authenticate = |token: &str| ControlRequest::Authenticate {
+            token: token.to_owned(),
+        };
+        authorize(
+            authenticate("admin-token-0123456789"),
+            &access_control,
+            &mut identity,
+        )
+        .unwrap();
+        assert!(authorize(authenticate("unknown"), &access_control, &mut identity).is_err());
+        assert!(identity.is_none());
+        assert!(authorize(ControlRequest::List, &access_control, &mut identity).is_err());
+    }
+}
@@ -0,0 +1,150 @@
+//! Leader election between redundant replicas of a node.
+//!
+//! Nodes campaign in named groups through `DoraNode::campaign`. The leader of
+//! a group is the running candidate that campaigned first. Candidates are
+//! removed when they resign, when they exit, and when the daemon of their
+//! machine disconnects, so that the next candidate takes over.
+
+use std::collections::BTreeMap;
+
+use dora_core::config::NodeId;
+
+#[derive(Default)]
+pub struct Elections {
+    /// Candidates of each group and their machine, in the order in which
+    /// they campaigned.
+    groups: BTreeMap<String, Vec<(NodeId, String)>>,
+}
+
+impl Elections {
+    /// Adds the node as candidate of the group and returns the leader.
+    pub fn campaign(&mut self, group: String, node_id: NodeId, machine_id: String) -> NodeId {
+        let candidates = self.groups.entry(group).or_default();
+        if !candidates.iter().any(|(id, _)| *id == node_id) {
+            candidates.push((node_id, machine_id));
+        }
+        candidates[0].0.clone()
+    }
+
+    /// Removes the node from the given group, or from all groups if `group`
+    /// is `None`.
+    ///
+    /// Returns the groups whose leader changed, with the new leader.
+    pub fn resign(
+        &mut self,
+        group: Option<&str>,
+        node_id: &NodeId,
+    ) -> Vec<(String, Option<NodeId>)> {
+        self.remove_candidates(|name, id, _| group.map_or(true, |g| g == name) && id == node_id)
+    }
+
+    /// Removes all candidates that run on the given machine.
+    ///
+    /// Returns the groups whose leader changed, with the new leader.
+    pub fn remove_machine(&mut self, machine_id: &str) -> Vec<(String, Option<NodeId>)> {
+        self.remove_candidates(|_, _, machine| machine == machine_id)
+    }
+
+    fn remove_candidates(
+        &mut self,
+        remove: impl Fn(&str, &NodeId, &str) -> bool,
+    ) -> Vec<(String, Option<NodeId>)> {
+        let mut changed = Vec::new();
+        for (group, candidates) in &mut self.groups {
+            let leader = candidates.first().map(|(id, _)| id.clone());
+            candidates.retain(|(id, machine)| !remove(group, id, machine));
+            let new_leader = candidates.first().map(|(id, _)| id.clone());
+            if new_leader != leader {
+                changed.push((group.clone(), new_leader));
+            }
+        }
+        self.groups.retain(|_, candidates| !candidates.is_empty());
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str) -> NodeId {
+        NodeId::from(id.to_owned())
+    }
+
+    #[test]
+    fn first_candidate_stays_leader() {
+        let mut elections = Elections::default();
+        // candidates on different machines campaign at the same time, but the
+        // coordinator orders them, so there is always exactly one leader
+        assert_eq!(
+            elections.campaign("g".into(), node("a"), "m1".into()),
+            node("a")
+        );
+        assert_eq!(
+            elections.campaign("g".into(), node("b"), "m2".into()),
+            node("a")
+        );
+        // campaigning again doesn't change the order
+        assert_eq!(
+            elections.campaign("g".into(), node("b"), "m2".into()),
+            node("a")
+        );
+        assert_eq!(
+            elections.campaign("g".into(), node("a"), "m1".into()),
+            node("a")
+        );
+    }
+
+    #[test]
+    fn only_leader_changes_are_reported() {
+        let mut elections = Elections::default();
+        elections.campaign("g".into(), node("a"), "m1".into());
+        elections.campaign("g".into(), node("b"), "m1".into());
+        elections.campaign("g".into(), node("c"), "m1".into());
+
+        assert_eq!(elections.resign(Some("g"), &node("b")), []);
+        assert_eq!(
+            elections.resign(Some("g"), &node("a")),
+            [("g".to_owned(), Some(node("c")))]
+        );
+        assert_eq!(
+            elections.resign(Some("g"), &node("c")),
+            [("g".to_owned(), None)]
+        );
+        assert!(elections.groups.is_empty());
+    }
+
+    #[test]
+    fn leader_of_disconnected_machine_steps_down() {
+        let mut elections = Elections::default();
+        elections.campaign("g".into(), node("a"), "m1".into());
+        elections.campaign("g".into(), node("b"), "m2".into());
+        elections.campaign("h".into(), node("c"), "m2".into());
+        elections.campaign("h".into(), node("d"), "m1".into());
+
+        assert_eq!(
+            elections.remove_machine("m1"),
+            [("g".to_owned(), Some(node("b")))]
+        );
+        // a restarted candidate of the disconnected machine doesn't take the
+        // leadership back
+        assert_eq!(
+            elections.campaign("g".into(), node("a"), "m1".into()),
+            node("b")
+        );
+    }
+
+    #[test]
+    fn resign_from_all_groups() {
+        let mut elections = Elections::default();
+        elections.campaign("g".into(), node("a"), "m1".into());
+        elections.campaign("h".into(), node("b"), "m1".into());
+        elections.campaign("h".into(), node("a"), "m1".into());
+
+        assert_eq!(elections.resign(None, &node("a")), [("g".to_owned(), None)]);
+        assert_eq!(
+            elections.campaign("h".into(), node("c"), "m1".into()),
+            node("b")
+        );
+    }
+}
@@ -0,0 +1,414 @@
+//! Running nodes as pods of a Kubernetes cluster.
+//!
+//! Nodes with a `kubernetes` deploy config run in their own pod, next to a
+//! `dora-daemon` that connects back to the coordinator under the machine ID
+//! of the node. The coordinator creates the pods when the dataflow is
+//! started, waits until their daemons are registered, and then spawns the
+//! dataflow like on any other machine. The pods are deleted when the
+//! dataflow finishes.
+//!
+//! The Kubernetes API is accessed through the service account of the
+//! coordinator pod if the coordinator runs in the cluster. Otherwise, the API
+//! URL is read from `DORA_KUBERNETES_API`, e.g. `http://127.0.0.1:8001` for
+//! `kubectl proxy`.
+
+use std::{
+    collections::BTreeSet,
+    net::SocketAddr,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use dora_message::descriptor::{Descriptor, KubernetesConfig, ResolvedNode, SignedDescriptor};
+use eyre::{bail, Context};
+use serde_json::{json, Value};
+use uuid::{NoContext, Timestamp, Uuid};
+
+const SERVICE_ACCOUNT_DIR: &str = "/var/run/secrets/kubernetes.io/serviceaccount";
+/// Time until all pods of a dataflow must be registered, including the time
+/// to pull their images.
+pub const POD_START_TIMEOUT: Duration = Duration::from_secs(300);
+
+struct Client {
+    api: String,
+    token: Option<String>,
+    http: reqwest::Client,
+}
+
+impl Client {
+    fn from_env() -> eyre::Result<Self> {
+        if let Ok(api) = std::env::var("DORA_KUBERNETES_API") {
+            return Ok(Self {
+                api: api.trim_end_matches('/').to_owned(),
+                token: None,
+                http: reqwest::Client::new(),
+            });
+        }
+        let Ok(host) = std::env::var("KUBERNETES_SERVICE_HOST") else {
+            bail!(
+                "the coordinator doesn't run in a Kubernetes cluster, \
+                set `DORA_KUBERNETES_API` to the URL of the Kubernetes API"
+            );
+        };
+        let port = std::env::var("KUBERNETES_SERVICE_PORT").unwrap_or_else(|_| "443".into());
+        let token = std::fs::read_to_string(format!("{SERVICE_ACCOUNT_DIR}/token"))
+            .wrap_err("failed to read service account token")?;
+        let ca = std::fs::read(format!("{SERVICE_ACCOUNT_DIR}/ca.crt"))
+            .wrap_err("failed to read cluster CA certificate")?;
+        let http = reqwest::Client::builder()
+            .add_root_certificate(
+                reqwest::Certificate::from_pem(&ca).wrap_err("invalid cluster CA certificate")?,
+            )
+            .build()?;
+        let host = if host.contains(':') {
+            format!("[{host}]")
+        } else {
+            host
+        };
+        Ok(Self {
+            api: format!("https://{host}:{port}"),
+            token: Some(token.trim().to_owned()),
+            http,
+        })
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.http.request(method, format!("{}{path}", self.api));
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    async fn create_pod(&self, pod: &Pod, manifest: &Value) -> eyre::Result<()> {
+        let response = self
+            .request(
+                reqwest::Method::POST,
+                &format!("/api/v1/namespaces/{}/pods", pod.namespace),
+            )
+            .json(manifest)
+            .send()
+            .await
+            .wrap_err("failed to send request to Kubernetes API")?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            bail!("Kubernetes API returned {status}: {body}");
+        }
+        Ok(())
+    }
+
+    async fn delete_pod(&self, pod: &Pod) -> eyre::Result<()> {
+        let response = self
+            .request(
+                reqwest::Method::DELETE,
+                &format!("/api/v1/namespaces/{}/pods/{}", pod.namespace, pod.name),
+            )
+            .send()
+            .await
+            .wrap_err("failed to send request to Kubernetes API")?;
+        // the pod might have been deleted by someone else already
+        if !response.status().is_success() && response.status() != reqwest::StatusCode::NOT_FOUND {
+            bail!("Kubernetes API returned {}", response.status());
+        }
+        Ok(())
+    }
+}
+
+struct Pod {
+    namespace: String,
+    name: String,
+}
+
+/// The pods of the nodes of a dataflow that run in Kubernetes.
+pub struct DataflowPods {
+    client: Client,
+    pods: Vec<Pod>,
+    /// Machine IDs that the daemons in the pods register with.
+    machines: BTreeSet<String>,
+}
+
+impl DataflowPods {
+    /// Creates a pod for each node of the dataflow with a `kubernetes`
+    /// config.
+    ///
+    /// Returns `None` if the dataflow has no such nodes.
+    pub async fn create(
+        nodes: &[ResolvedNode],
+        advertise_addr: Option<SocketAddr>,
+    ) -> eyre::Result<Option<Self>> {
+        let pod_nodes: Vec<_> = nodes
+            .iter()
+            .filter_map(|node| Some((node, node.deploy.kubernetes.as_ref()?)))
+            .collect();
+        if pod_nodes.is_empty() {
+            return Ok(None);
+        }
+        let Some(advertise_addr) = advertise_addr else {
+            bail!(
+                "dataflow has nodes that run in Kubernetes, \
+                but the coordinator was started without `--kubernetes-advertise-addr`"
+            );
+        };
+
+        let mut pods = Self {
+            client: Client::from_env()?,
+            pods: Vec::new(),
+            machines: BTreeSet::new(),
+        };
+        // distinguishes the pods of different runs of the same dataflow
+        let suffix = Uuid::new_v7(Timestamp::now(NoContext)).simple().to_string();
+        let suffix = &suffix[suffix.len() - 8..];
+        for (node, config) in pod_nodes {
+            let (pod, manifest) = pod_manifest(node, config, suffix, advertise_addr);
+            let result = pods.client.create_pod(&pod, &manifest).await;
+            if let Err(err) = result {
+                pods.delete().await;
+                return Err(err)
+                    .wrap_err_with(|| format!("failed to create pod for node `{}`", node.id));
+            }
+            tracing::info!("created pod `{}` for node `{}`", pod.name, node.id);
+            pods.pods.push(pod);
+            pods.machines.insert(node.deploy.machine.clone());
+        }
+        Ok(Some(pods))
+    }
+
+    pub fn machines(&self) -> &BTreeSet<String> {
+        &self.machines
+    }
+
+    /// Deletes all pods, logging errors instead of returning them.
+    pub async fn delete(self) {
+        for pod in &self.pods {
+            match self.client.delete_pod(pod).await {
+                Ok(()) => tracing::info!("deleted pod `{}`", pod.name),
+                Err(err) => tracing::warn!("failed to delete pod `{}`: {err:?}", pod.name),
+            }
+        }
+    }
+}
+
+/// A dataflow that is started once the daemons in its pods are registered.
+pub struct PendingDataflow {
+    pub dataflow: Descriptor,
+    pub working_dir: PathBuf,
+    pub name: Option<String>,
+    pub signed: Option<SignedDescriptor>,
+    pub pods: DataflowPods,
+    pub created: Instant,
+}
+
+fn pod_manifest(
+    node: &ResolvedNode,
+    config: &KubernetesConfig,
+    suffix: &str,
+    advertise_addr: SocketAddr,
+) -> (Pod, Value) {
+    // pod names and label values must be valid DNS labels
+    let mut label: String = node
+        .id
+        .to_string()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    label.truncate(63 - "dora--".len() - suffix.len());
+    let label = label.trim_matches('-').to_owned();
+    let pod = Pod {
+        namespace: config
+            .namespace
+            .clone()
+            .unwrap_or_else(|| "default".to_owned()),
+        name: format!("dora-{label}-{suffix}"),
+    };
+
+    let manifest = json!({
+        "apiVersion": "v1",
+        "kind": "Pod",
+        "metadata": {
+            "name": pod.name,
+            "namespace": pod.namespace,
+            "labels": {
+                "app.kubernetes.io/managed-by": "dora",
+                "dora-rs.org/node": label,
+            },
+        },
+        "spec": {
+            "restartPolicy": "Never",
+            "nodeSelector": config.node_selector,
+            "containers": [{
+                "name": "node",
+                "image": config.image,
+                "command": [
+                    "dora-daemon",
+                    "--machine-id",
+                    node.deploy.machine,
+                    "--coordinator-addr",
+                    advertise_addr.ip().to_string(),
+                    "--coordinator-port",
+                    advertise_addr.port().to_string(),
+                ],
+            }],
+        },
+    });
+    (pod, manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use dora_core::descriptor::DescriptorExt;
+
+    use super::*;
+
+    fn resolve(yaml: &str) -> Vec<ResolvedNode> {
+        Descriptor::parse(yaml.as_bytes().to_vec())
+            .unwrap()
+            .resolve_aliases_and_set_defaults()
+            .unwrap()
+    }
+
+    #[test]
+    fn render_pod_manifests() {
+        let nodes = resolve(
+            r#"
+_unstable_deploy:
+  kubernetes:
+    image: registry.example.com/dora:1.0
+nodes:
+  - id: Camera_Driver
+    path: camera
+    outputs:
+      - image
+  - id: planner
+    path: planner
+    inputs:
+      image: Camera_Driver/image
+    _unstable_deploy:
+      kubernetes:
+        image: registry.example.com/planner:1.0
+        namespace: robots
+        node_selector:
+          gpu: "true"
+  - id: local
+    path: local
+    _unstable_deploy:
+      machine: base
+"#,
+        );
+        let advertise_addr = "10.0.0.1:53290".parse().unwrap();
+
+        let manifests: Vec<_> = nodes
+            .iter()
+            .filter_map(|node| {
+                let config = node.deploy.kubernetes.as_ref()?;
+                let (pod, manifest) = pod_manifest(node, config, "0123abcd", advertise_addr);
+                Some((pod.namespace, pod.name, manifest))
+            })
+            .collect();
+
+        assert_eq!(
+            manifests,
+            [
+                (
+                    "default".to_owned(),
+                    "dora-camera-driver-0123abcd".to_owned(),
+                    json!({
+                        "apiVersion": "v1",
+                        "kind": "Pod",
+                        "metadata": {
+                            "name": "dora-camera-driver-0123abcd",
+                            "namespace": "default",
+                            "labels": {
+                                "app.kubernetes.io/managed-by": "dora",
+                                "dora-rs.org/node": "camera-driver",
+                            },
+                        },
+                        "spec": {
+                            "restartPolicy": "Never",
+                            "nodeSelector": {},
+                            "containers": [{
+                                "name": "node",
+                                "image": "registry.example.com/dora:1.0",
+                                "command": [
+                                    "dora-daemon",
+                                    "--machine-id",
+                                    "k8s-Camera_Driver",
+                                    "--coordinator-addr",
+                                    "10.0.0.1",
+                                    "--coordinator-port",
+                                    "53290",
+                                ],
+                            }],
+                        },
+                    }),
+                ),
+                (
+                    "robots".to_owned(),
+                    "dora-planner-0123abcd".to_owned(),
+                    json!({
+                        "apiVersion": "v1",
+                        "kind": "Pod",
+                        "metadata": {
+                            "name": "dora-planner-0123abcd",
+                            "namespace": "robots",
+                            "labels": {
+                                "app.kubernetes.io/managed-by": "dora",
+                                "dora-rs.org/node": "planner",
+                            },
+                        },
+                        "spec": {
+                            "restartPolicy": "Never",
+                            "nodeSelector": { "gpu": "true" },
+                            "containers": [{
+                                "name": "node",
+                                "image": "registry.example.com/planner:1.0",
+                                "command": [
+                                    "dora-daemon",
+                                    "--machine-id",
+                                    "k8s-planner",
+                                    "--coordinator-addr",
+                                    "10.0.0.1",
+                                    "--coordinator-port",
+                                    "53290",
+                                ],
+                            }],
+                        },
+                    }),
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn pod_names_are_dns_labels() {
+        let id = format!("_{}_", "Very.Long.Node".repeat(10));
+        let nodes = resolve(&format!(
+            r#"
+nodes:
+  - id: {id}
+    path: node
+    _unstable_deploy:
+      kubernetes:
+        image: node:1.0
+"#
+        ));
+        let node = &nodes[0];
+        let config = node.deploy.kubernetes.as_ref().unwrap();
+        let (pod, manifest) =
+            pod_manifest(node, config, "0123abcd", "[::1]:53290".parse().unwrap());
+
+        assert!(pod.name.len() <= 63, "{}", pod.name);
+        assert!(pod.name.starts_with("dora-very-long-nodevery-long-node"));
+        assert!(pod.name.ends_with("-0123abcd"));
+        assert!(pod
+            .name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-'));
+        assert_eq!(manifest["metadata"]["name"], pod.name);
+        assert_eq!(
+            manifest["spec"]["containers"][0]["command"][4], "::1",
+            "IPv6 coordinator address must not be bracketed"
+        );
+    }
+}
@@ -2,29 +2,38 @@ use crate::{
     run::spawn_dataflow,
     tcp_utils::{tcp_receive, tcp_send},
 };
+pub use access::AccessControl;
 pub use control::ControlEvent;
 use dora_core::{
     config::{NodeId, OperatorId},
+    descriptor::{signing::SignaturePolicy, DescriptorExt},
     uhlc::{self, HLC},
 };
 use dora_message::{
-    cli_to_coordinator::ControlRequest,
+    cli_to_coordinator::{ControlRequest, RecordingOptions, ReplayControl},
+    common::glob_match,
     coordinator_to_cli::{
-        ControlRequestReply, DataflowIdAndName, DataflowList, DataflowListEntry, DataflowResult,
-        DataflowStatus, LogMessage,
+        ArtifactStatus, CheckpointSummary, ControlRequestReply, CrashReport, DataflowEdgeLatencies,
+        DataflowGraph, DataflowHealth, DataflowIdAndName, DataflowList, DataflowListEntry,
+        DataflowResult, DataflowStatus, EdgeLatency, GraphNode, LogMessage, NodeHealth,
+        NodeResourceUsage, QueueMetrics, RecordingSummary, DATAFLOW_GRAPH_FORMAT_VERSION,
+    },
+    coordinator_to_daemon::{
+        DaemonCoordinatorEvent, NodeListener, RegisterResult, SpawnDataflowNodes, Timestamped,
     },
-    coordinator_to_daemon::{DaemonCoordinatorEvent, RegisterResult, Timestamped},
     daemon_to_coordinator::{DaemonCoordinatorReply, DataflowDaemonResult},
-    descriptor::{Descriptor, ResolvedNode},
+    descriptor::{Descriptor, ResolvedNode, SignedDescriptor},
 };
 use eyre::{bail, eyre, ContextCompat, Result, WrapErr};
 use futures::{future::join_all, stream::FuturesUnordered, Future, Stream, StreamExt};
 use futures_concurrency::stream::Merge;
+pub use lifecycle::LifecycleSink;
+use lifecycle::{LifecycleEvent, LifecycleEventKind, LifecycleEvents};
 use log_subscriber::LogSubscriber;
 use run::SpawnedDataflow;
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap},
-    net::SocketAddr,
+    net::{IpAddr, SocketAddr},
     path::PathBuf,
     sync::Arc,
     time::{Duration, Instant},
@@ -33,7 +42,11 @@ use tokio::{net::TcpStream, sync::mpsc, task::JoinHandle};
 use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
 use uuid::Uuid;
 
+mod access;
 mod control;
+mod election;
+mod kubernetes;
+mod lifecycle;
 mod listener;
 mod log_subscriber;
 mod run;
@@ -43,6 +56,41 @@ pub async fn start(
     bind: SocketAddr,
     bind_control: SocketAddr,
     external_events: impl Stream<Item = Event> + Unpin,
+    lifecycle_sink: Option<LifecycleSink>,
+    signature_policy: Option<SignaturePolicy>,
+    access_control: Option<AccessControl>,
+    kubernetes_advertise_addr: Option<IpAddr>,
+) -> Result<(u16, impl Future<Output = eyre::Result<()>>), eyre::ErrReport> {
+    let ctrlc_events = set_up_ctrlc_handler()?;
+    start_in_process(
+        bind,
+        bind_control,
+        (external_events, ctrlc_events).merge(),
+        lifecycle_sink,
+        signature_policy,
+        access_control,
+        kubernetes_advertise_addr,
+    )
+    .await
+}
+
+/// Like [`start`], but without setting up a ctrl-c handler, e.g. for running
+/// the coordinator inside of a test process.
+///
+/// Control requests can be sent through `external_events` as
+/// [`Event::Control`].
+///
+/// Nodes with a `kubernetes` deploy config are only supported if
+/// `kubernetes_advertise_addr` is set. It's the IP address under which the
+/// pods reach the coordinator.
+pub async fn start_in_process(
+    bind: SocketAddr,
+    bind_control: SocketAddr,
+    external_events: impl Stream<Item = Event> + Unpin,
+    lifecycle_sink: Option<LifecycleSink>,
+    signature_policy: Option<SignaturePolicy>,
+    access_control: Option<AccessControl>,
+    kubernetes_advertise_addr: Option<IpAddr>,
 ) -> Result<(u16, impl Future<Output = eyre::Result<()>>), eyre::ErrReport> {
     let listener = listener::create_listener(bind).await?;
     let port = listener
@@ -56,23 +104,22 @@ pub async fn start(
     });
 
     let mut tasks = FuturesUnordered::new();
-    let control_events = control::control_events(bind_control, &tasks)
+    let control_events = control::control_events(bind_control, &tasks, access_control)
         .await
         .wrap_err("failed to create control events")?;
 
-    // Setup ctrl-c handler
-    let ctrlc_events = set_up_ctrlc_handler()?;
-
-    let events = (
-        external_events,
-        new_daemon_connections,
-        control_events,
-        ctrlc_events,
-    )
-        .merge();
+    let events = (external_events, new_daemon_connections, control_events).merge();
+    let kubernetes_advertise_addr = kubernetes_advertise_addr.map(|ip| SocketAddr::new(ip, port));
 
     let future = async move {
-        start_inner(events, &tasks).await?;
+        start_inner(
+            events,
+            &tasks,
+            lifecycle_sink,
+            signature_policy,
+            kubernetes_advertise_addr,
+        )
+        .await?;
 
         tracing::debug!("coordinator main loop finished, waiting on spawned tasks");
         while let Some(join_result) = tasks.next().await {
@@ -86,6 +133,32 @@ pub async fn start(
     Ok((port, future))
 }
 
+/// Runtime nodes report operator restarts through log records that are
+/// marked with a `lifecycle_event` field.
+fn lifecycle_event_from_log(
+    message: &LogMessage,
+    dataflow: &RunningDataflow,
+) -> Option<LifecycleEvent> {
+    if message.fields.get("lifecycle_event").map(String::as_str) != Some("node_restarted") {
+        return None;
+    }
+    let node_id = message.node_id.clone()?;
+    let operator_id = message
+        .fields
+        .get("operator_id")
+        .map(|id| OperatorId::from(id.clone()));
+    Some(
+        LifecycleEvent::new(LifecycleEventKind::NodeRestarted, message.message.clone())
+            .dataflow(dataflow.uuid, dataflow.name.clone())
+            .node(node_id, operator_id),
+    )
+}
+
+/// Name of the dataflow, or its UUID if it has no name.
+fn dataflow_label(uuid: Uuid, name: &Option<String>) -> String {
+    name.clone().unwrap_or_else(|| uuid.to_string())
+}
+
 // Resolve the dataflow name.
 fn resolve_name(
     name: String,
@@ -124,8 +197,12 @@ fn resolve_name(
 async fn start_inner(
     events: impl Stream<Item = Event> + Unpin,
     tasks: &FuturesUnordered<JoinHandle<()>>,
+    lifecycle_sink: Option<LifecycleSink>,
+    signature_policy: Option<SignaturePolicy>,
+    kubernetes_advertise_addr: Option<SocketAddr>,
 ) -> eyre::Result<()> {
     let clock = Arc::new(HLC::default());
+    let lifecycle_events = LifecycleEvents::new(lifecycle_sink, tasks);
 
     let (daemon_events_tx, daemon_events) = tokio::sync::mpsc::channel(2);
     let mut daemon_events_tx = Some(daemon_events_tx);
@@ -146,6 +223,11 @@ async fn start_inner(
         HashMap::new();
     let mut archived_dataflows: HashMap<Uuid, ArchivedDataflow> = HashMap::new();
     let mut daemon_connections: HashMap<_, DaemonConnection> = HashMap::new();
+    // dataflows that wait until the daemons in their Kubernetes pods are registered
+    let mut pending_dataflows: Vec<(
+        kubernetes::PendingDataflow,
+        tokio::sync::oneshot::Sender<eyre::Result<ControlRequestReply>>,
+    )> = Vec::new();
 
     while let Some(event) = events.next().await {
         if event.log() {
@@ -207,6 +289,79 @@ async fn start_inner(
                                     "closing previous connection `{machine_id}` on new register"
                                 );
                             }
+                            lifecycle_events.emit(
+                                LifecycleEvent::new(
+                                    LifecycleEventKind::DaemonConnected,
+                                    format!("daemon `{machine_id}` connected"),
+                                )
+                                .machine(&machine_id),
+                            );
+
+                            // a restarted daemon needs to re-adopt the nodes of
+                            // dataflows that are still running on its machine
+                            for dataflow in running_dataflows.values() {
+                                if !dataflow.machines.contains(&machine_id) {
+                                    continue;
+                                }
+                                let result = run::adopt_dataflow_on_machine(
+                                    &dataflow.spawn_command,
+                                    &dataflow.blackboard,
+                                    &machine_id,
+                                    &mut daemon_connections,
+                                    &clock,
+                                )
+                                .await;
+                                match result {
+                                    Ok(()) => tracing::info!(
+                                        "daemon `{machine_id}` re-adopted dataflow `{}`",
+                                        dataflow.uuid
+                                    ),
+                                    Err(err) => tracing::warn!("{err:?}"),
+                                }
+                            }
+
+                            let (ready, waiting) = std::mem::take(&mut pending_dataflows)
+                                .into_iter()
+                                .partition(|(pending, _): &(kubernetes::PendingDataflow, _)| {
+                                    pending
+                                        .pods
+                                        .machines()
+                                        .iter()
+                                        .all(|m| daemon_connections.contains_key(m))
+                                });
+                            pending_dataflows = waiting;
+                            for (pending, reply_sender) in ready {
+                                let kubernetes::PendingDataflow {
+                                    dataflow,
+                                    working_dir,
+                                    name,
+                                    signed,
+                                    pods,
+                                    created: _,
+                                } = pending;
+                                let result = start_dataflow(
+                                    dataflow,
+                                    working_dir,
+                                    name,
+                                    signed,
+                                    &mut daemon_connections,
+                                    &clock,
+                                )
+                                .await;
+                                let reply = match result {
+                                    Ok(mut dataflow) => {
+                                        let uuid = dataflow.uuid;
+                                        dataflow.pods = Some(pods);
+                                        running_dataflows.insert(uuid, dataflow);
+                                        Ok(ControlRequestReply::DataflowStarted { uuid })
+                                    }
+                                    Err(err) => {
+                                        tasks.push(tokio::spawn(pods.delete()));
+                                        Err(err)
+                                    }
+                                };
+                                let _ = reply_sender.send(reply);
+                            }
                         }
                         (Err(err), _) => {
                             tracing::warn!("failed to register daemon connection for machine `{machine_id}`: {err}");
@@ -240,6 +395,16 @@ async fn start_inner(
                                     timestamp: clock.new_timestamp(),
                                 })
                                 .wrap_err("failed to serialize AllNodesReady message")?;
+                                lifecycle_events.emit(
+                                    LifecycleEvent::new(
+                                        LifecycleEventKind::DataflowStarted,
+                                        format!(
+                                            "dataflow `{}` started",
+                                            dataflow_label(uuid, &dataflow.name)
+                                        ),
+                                    )
+                                    .dataflow(uuid, dataflow.name.clone()),
+                                );
 
                                 // notify all machines that run parts of the dataflow
                                 for machine_id in &dataflow.machines {
@@ -286,18 +451,34 @@ async fn start_inner(
                                     .entry(uuid)
                                     .or_insert_with(|| ArchivedDataflow::from(entry.get()));
                                 let finished_dataflow = entry.remove();
-                                let reply = ControlRequestReply::DataflowStopped {
-                                    uuid,
-                                    result: dataflow_results
-                                        .get(&uuid)
-                                        .map(|r| dataflow_result(r, uuid, &clock))
-                                        .unwrap_or_else(|| {
-                                            DataflowResult::ok_empty(uuid, clock.new_timestamp())
-                                        }),
+                                let result = dataflow_results
+                                    .get(&uuid)
+                                    .map(|r| dataflow_result(r, uuid, &clock))
+                                    .unwrap_or_else(|| {
+                                        DataflowResult::ok_empty(uuid, clock.new_timestamp())
+                                    });
+                                let outcome = if result.is_ok() {
+                                    "successfully"
+                                } else {
+                                    "with errors"
                                 };
+                                lifecycle_events.emit(
+                                    LifecycleEvent::new(
+                                        LifecycleEventKind::DataflowStopped,
+                                        format!(
+                                            "dataflow `{}` stopped {outcome}",
+                                            dataflow_label(uuid, &finished_dataflow.name)
+                                        ),
+                                    )
+                                    .dataflow(uuid, finished_dataflow.name.clone()),
+                                );
+                                let reply = ControlRequestReply::DataflowStopped { uuid, result };
                                 for sender in finished_dataflow.reply_senders {
                                     let _ = sender.send(Ok(reply.clone()));
                                 }
+                                if let Some(pods) = finished_dataflow.pods {
+                                    tasks.push(tokio::spawn(pods.delete()));
+                                }
                             }
                         }
                         std::collections::hash_map::Entry::Vacant(_) => {
@@ -305,6 +486,125 @@ async fn start_inner(
                         }
                     }
                 }
+                DataflowEvent::StopRequested { node_id, reason } => {
+                    tracing::info!(
+                        "node `{node_id}` requested stop of dataflow `{uuid}`: {reason}"
+                    );
+                    let result = stop_dataflow(
+                        &mut running_dataflows,
+                        uuid,
+                        &mut daemon_connections,
+                        clock.new_timestamp(),
+                        None,
+                    )
+                    .await;
+                    if let Err(err) = result {
+                        tracing::warn!(
+                            "{:?}",
+                            err.wrap_err(format!(
+                                "failed to stop dataflow `{uuid}` on request of node `{node_id}`"
+                            ))
+                        );
+                    }
+                }
+                DataflowEvent::NodeHealthReported { node_id, health } => {
+                    match running_dataflows.get_mut(&uuid) {
+                        Some(dataflow) => {
+                            dataflow.node_health.insert(node_id, health);
+                        }
+                        None => {
+                            tracing::warn!("dataflow not running on NodeHealthReported");
+                        }
+                    }
+                }
+                DataflowEvent::QueueMetricsReported {
+                    machine_id,
+                    metrics,
+                } => match running_dataflows.get_mut(&uuid) {
+                    Some(dataflow) => {
+                        dataflow.queue_metrics.insert(machine_id, metrics);
+                    }
+                    None => {
+                        tracing::warn!("dataflow not running on QueueMetricsReported");
+                    }
+                },
+                DataflowEvent::ResourceUsageReported { usage } => {
+                    match running_dataflows.get_mut(&uuid) {
+                        Some(dataflow) => {
+                            dataflow.resource_usage.extend(usage);
+                        }
+                        None => {
+                            tracing::warn!("dataflow not running on ResourceUsageReported");
+                        }
+                    }
+                }
+                DataflowEvent::BlackboardSet { key, value } => {
+                    let Some(dataflow) = running_dataflows.get_mut(&uuid) else {
+                        tracing::warn!("dataflow not running on BlackboardSet");
+                        continue;
+                    };
+                    match &value {
+                        Some(value) => dataflow.blackboard.insert(key.clone(), value.clone()),
+                        None => dataflow.blackboard.remove(&key),
+                    };
+                    let message = serde_json::to_vec(&Timestamped {
+                        inner: DaemonCoordinatorEvent::BlackboardUpdate {
+                            dataflow_id: uuid,
+                            key,
+                            value,
+                        },
+                        timestamp: clock.new_timestamp(),
+                    })
+                    .wrap_err("failed to serialize BlackboardUpdate message")?;
+
+                    // replicate the write to all machines, including the one
+                    // that it originated from, so that they apply concurrent
+                    // writes in the same order
+                    send_to_dataflow_machines(dataflow, &mut daemon_connections, &message).await;
+                }
+                DataflowEvent::Campaign {
+                    machine_id,
+                    node_id,
+                    group,
+                } => {
+                    let Some(dataflow) = running_dataflows.get_mut(&uuid) else {
+                        tracing::warn!("dataflow not running on Campaign");
+                        continue;
+                    };
+                    let leader = dataflow
+                        .elections
+                        .campaign(group.clone(), node_id, machine_id);
+                    // also sent if the leader didn't change, to notify the new
+                    // candidate
+                    announce_leader(
+                        dataflow,
+                        &mut daemon_connections,
+                        &clock,
+                        group,
+                        Some(leader),
+                    )
+                    .await?;
+                }
+                DataflowEvent::Resign { node_id, group } => {
+                    let Some(dataflow) = running_dataflows.get_mut(&uuid) else {
+                        tracing::warn!("dataflow not running on Resign");
+                        continue;
+                    };
+                    for (group, leader) in dataflow.elections.resign(group.as_deref(), &node_id) {
+                        announce_leader(dataflow, &mut daemon_connections, &clock, group, leader)
+                            .await?;
+                    }
+                }
+                DataflowEvent::NodeListenerChanged { node_id, listener } => {
+                    let Some(dataflow) = running_dataflows.get_mut(&uuid) else {
+                        tracing::warn!("dataflow not running on NodeListenerChanged");
+                        continue;
+                    };
+                    dataflow
+                        .spawn_command
+                        .node_listeners
+                        .insert(node_id, listener);
+                }
             },
 
             Event::Control(event) => match event {
@@ -317,6 +617,7 @@ async fn start_inner(
                             dataflow,
                             name,
                             local_working_dir,
+                            signed,
                         } => {
                             let name = name.or_else(|| names::Generator::default().next());
 
@@ -330,22 +631,52 @@ async fn start_inner(
                                         bail!("there is already a running dataflow with name `{name}`");
                                     }
                                 }
+                                let dataflow = match &signature_policy {
+                                    Some(policy) => policy
+                                        .check(dataflow, signed.as_ref())
+                                        .wrap_err("dataflow signature check failed")?,
+                                    None => dataflow,
+                                };
+                                let nodes = dataflow.resolve_aliases_and_set_defaults()?;
+                                if let Some(pods) = kubernetes::DataflowPods::create(
+                                    &nodes,
+                                    kubernetes_advertise_addr,
+                                )
+                                .await?
+                                {
+                                    return Ok(Err(kubernetes::PendingDataflow {
+                                        dataflow,
+                                        working_dir: local_working_dir,
+                                        name,
+                                        signed,
+                                        pods,
+                                        created: Instant::now(),
+                                    }));
+                                }
                                 let dataflow = start_dataflow(
                                     dataflow,
                                     local_working_dir,
                                     name,
+                                    signed,
                                     &mut daemon_connections,
                                     &clock,
                                 )
                                 .await?;
-                                Ok(dataflow)
+                                Ok(Ok(dataflow))
                             };
-                            let reply = inner.await.map(|dataflow| {
-                                let uuid = dataflow.uuid;
-                                running_dataflows.insert(uuid, dataflow);
-                                ControlRequestReply::DataflowStarted { uuid }
-                            });
-                            let _ = reply_sender.send(reply);
+                            match inner.await {
+                                Ok(Ok(dataflow)) => {
+                                    let uuid = dataflow.uuid;
+                                    running_dataflows.insert(uuid, dataflow);
+                                    let _ = reply_sender
+                                        .send(Ok(ControlRequestReply::DataflowStarted { uuid }));
+                                }
+                                // replied once the daemons in the pods are registered
+                                Ok(Err(pending)) => pending_dataflows.push((pending, reply_sender)),
+                                Err(err) => {
+                                    let _ = reply_sender.send(Err(err));
+                                }
+                            }
                         }
                         ControlRequest::Check { dataflow_uuid } => {
                             let status = match &running_dataflows.get(&dataflow_uuid) {
@@ -376,8 +707,8 @@ async fn start_inner(
                                 reload_dataflow(
                                     &running_dataflows,
                                     dataflow_id,
-                                    node_id,
-                                    operator_id,
+                                    node_id.clone(),
+                                    operator_id.clone(),
                                     &mut daemon_connections,
                                     clock.new_timestamp(),
                                 )
@@ -390,6 +721,26 @@ async fn start_inner(
                                     .map(|()| ControlRequestReply::DataflowReloaded {
                                         uuid: dataflow_id,
                                     });
+                            if reply.is_ok() {
+                                let name = running_dataflows
+                                    .get(&dataflow_id)
+                                    .and_then(|d| d.name.clone());
+                                let target = match &operator_id {
+                                    Some(operator_id) => format!("{node_id}/{operator_id}"),
+                                    None => node_id.to_string(),
+                                };
+                                lifecycle_events.emit(
+                                    LifecycleEvent::new(
+                                        LifecycleEventKind::ReloadApplied,
+                                        format!(
+                                            "reloaded `{target}` of dataflow `{}`",
+                                            dataflow_label(dataflow_id, &name)
+                                        ),
+                                    )
+                                    .dataflow(dataflow_id, name)
+                                    .node(node_id, operator_id),
+                                );
+                            }
                             let _ = reply_sender.send(reply);
                         }
                         ControlRequest::Stop {
@@ -513,6 +864,7 @@ async fn start_inner(
                                     name: d.name.clone(),
                                 },
                                 status: DataflowStatus::Running,
+                                stop_requests: BTreeMap::new(),
                             });
                             let finished_failed =
                                 dataflow_results.iter().map(|(&uuid, results)| {
@@ -524,7 +876,15 @@ async fn start_inner(
                                     } else {
                                         DataflowStatus::Failed
                                     };
-                                    DataflowListEntry { id, status }
+                                    let stop_requests = results
+                                        .values()
+                                        .flat_map(|r| r.stop_requests.clone())
+                                        .collect();
+                                    DataflowListEntry {
+                                        id,
+                                        status,
+                                        stop_requests,
+                                    }
                                 });
 
                             let reply = Ok(ControlRequestReply::DataflowList(DataflowList(
@@ -532,6 +892,189 @@ async fn start_inner(
                             )));
                             let _ = reply_sender.send(reply);
                         }
+                        ControlRequest::Health { dataflow_uuid } => {
+                            let reply = match running_dataflows.get(&dataflow_uuid) {
+                                Some(dataflow) => {
+                                    Ok(ControlRequestReply::DataflowHealth(DataflowHealth {
+                                        id: DataflowIdAndName {
+                                            uuid: dataflow.uuid,
+                                            name: dataflow.name.clone(),
+                                        },
+                                        nodes: dataflow
+                                            .nodes
+                                            .iter()
+                                            .map(|node| {
+                                                (
+                                                    node.id.clone(),
+                                                    dataflow.node_health.get(&node.id).cloned(),
+                                                )
+                                            })
+                                            .collect(),
+                                        queues: dataflow.queue_metrics.clone(),
+                                        resources: dataflow.resource_usage.clone(),
+                                    }))
+                                }
+                                None => {
+                                    Err(eyre!("no running dataflow with UUID `{dataflow_uuid}`"))
+                                }
+                            };
+                            let _ = reply_sender.send(reply);
+                        }
+                        ControlRequest::EdgeLatencies { dataflow_uuid } => {
+                            let reply = match running_dataflows.get(&dataflow_uuid) {
+                                Some(dataflow) => retrieve_edge_latencies(
+                                    dataflow,
+                                    &mut daemon_connections,
+                                    clock.new_timestamp(),
+                                )
+                                .await
+                                .map(|edges| {
+                                    ControlRequestReply::EdgeLatencies(DataflowEdgeLatencies {
+                                        id: DataflowIdAndName {
+                                            uuid: dataflow.uuid,
+                                            name: dataflow.name.clone(),
+                                        },
+                                        edges,
+                                    })
+                                }),
+                                None => {
+                                    Err(eyre!("no running dataflow with UUID `{dataflow_uuid}`"))
+                                }
+                            };
+                            let _ = reply_sender.send(reply);
+                        }
+                        ControlRequest::LiveGraph { dataflow_uuid } => {
+                            let reply = match running_dataflows.get(&dataflow_uuid) {
+                                Some(dataflow) => retrieve_live_graph(
+                                    dataflow,
+                                    &mut daemon_connections,
+                                    clock.new_timestamp(),
+                                )
+                                .await
+                                .map(ControlRequestReply::LiveGraph),
+                                None => {
+                                    Err(eyre!("no running dataflow with UUID `{dataflow_uuid}`"))
+                                }
+                            };
+                            let _ = reply_sender.send(reply);
+                        }
+                        ControlRequest::DumpDaemonStates => {
+                            let reply = retrieve_daemon_states(
+                                &mut daemon_connections,
+                                clock.new_timestamp(),
+                            )
+                            .await
+                            .map(ControlRequestReply::DaemonStates);
+                            let _ = reply_sender.send(reply);
+                        }
+                        ControlRequest::CrashReports { uuid, name, node } => {
+                            let dataflow_uuid = if let Some(uuid) = uuid {
+                                Ok(uuid)
+                            } else if let Some(name) = name {
+                                resolve_name(name, &running_dataflows, &archived_dataflows)
+                            } else {
+                                Err(eyre!("No uuid"))
+                            };
+
+                            let reply = match dataflow_uuid {
+                                Ok(uuid) => retrieve_crash_reports(
+                                    &running_dataflows,
+                                    &archived_dataflows,
+                                    uuid,
+                                    node,
+                                    &mut daemon_connections,
+                                    clock.new_timestamp(),
+                                )
+                                .await
+                                .map(ControlRequestReply::CrashReports),
+                                Err(err) => Err(err),
+                            };
+                            let _ = reply_sender.send(reply);
+                        }
+                        ControlRequest::StartRecording {
+                            dataflow_uuid,
+                            options,
+                        } => {
+                            let reply = match running_dataflows.get(&dataflow_uuid) {
+                                Some(dataflow) => start_recording(
+                                    dataflow,
+                                    options,
+                                    &mut daemon_connections,
+                                    &clock,
+                                )
+                                .await
+                                .map(|recording_id| ControlRequestReply::RecordingStarted {
+                                    uuid: dataflow_uuid,
+                                    recording_id,
+                                }),
+                                None => {
+                                    Err(eyre!("no running dataflow with UUID `{dataflow_uuid}`"))
+                                }
+                            };
+                            let _ = reply_sender.send(reply);
+                        }
+                        ControlRequest::StopRecording { dataflow_uuid } => {
+                            let reply = match running_dataflows.get(&dataflow_uuid) {
+                                Some(dataflow) => stop_recording(
+                                    dataflow,
+                                    &mut daemon_connections,
+                                    clock.new_timestamp(),
+                                )
+                                .await
+                                .map(|recordings| ControlRequestReply::RecordingStopped {
+                                    uuid: dataflow_uuid,
+                                    recordings,
+                                }),
+                                None => {
+                                    Err(eyre!("no running dataflow with UUID `{dataflow_uuid}`"))
+                                }
+                            };
+                            let _ = reply_sender.send(reply);
+                        }
+                        ControlRequest::Checkpoint { dataflow_uuid } => {
+                            let checkpoint_id =
+                                Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).to_string();
+                            let reply = match running_dataflows.get(&dataflow_uuid) {
+                                Some(dataflow) => create_checkpoint(
+                                    dataflow,
+                                    checkpoint_id.clone(),
+                                    &mut daemon_connections,
+                                    clock.new_timestamp(),
+                                )
+                                .await
+                                .map(|checkpoints| ControlRequestReply::CheckpointCreated {
+                                    uuid: dataflow_uuid,
+                                    checkpoint_id,
+                                    checkpoints,
+                                }),
+                                None => {
+                                    Err(eyre!("no running dataflow with UUID `{dataflow_uuid}`"))
+                                }
+                            };
+                            let _ = reply_sender.send(reply);
+                        }
+                        ControlRequest::ControlReplay {
+                            dataflow_uuid,
+                            control,
+                        } => {
+                            let reply =
+                                match running_dataflows.get(&dataflow_uuid) {
+                                    Some(dataflow) => control_replay(
+                                        dataflow,
+                                        control,
+                                        &mut daemon_connections,
+                                        clock.new_timestamp(),
+                                    )
+                                    .await
+                                    .map(|()| ControlRequestReply::ReplayControlled {
+                                        uuid: dataflow_uuid,
+                                    }),
+                                    None => Err(eyre!(
+                                        "no running dataflow with UUID `{dataflow_uuid}`"
+                                    )),
+                                };
+                            let _ = reply_sender.send(reply);
+                        }
                         ControlRequest::DaemonConnected => {
                             let running = !daemon_connections.is_empty();
                             let _ = reply_sender
@@ -543,11 +1086,52 @@ async fn start_inner(
                             ));
                             let _ = reply_sender.send(reply);
                         }
+                        ControlRequest::ArtifactStatus {
+                            machine_id,
+                            artifact,
+                        } => {
+                            let reply = transfer_artifact(
+                                &machine_id,
+                                DaemonCoordinatorEvent::ArtifactStatus { artifact },
+                                &mut daemon_connections,
+                                clock.new_timestamp(),
+                            )
+                            .await
+                            .map(ControlRequestReply::ArtifactStatus);
+                            let _ = reply_sender.send(reply);
+                        }
+                        ControlRequest::UploadArtifact {
+                            machine_id,
+                            artifact,
+                            offset,
+                            data,
+                            last,
+                        } => {
+                            let reply = transfer_artifact(
+                                &machine_id,
+                                DaemonCoordinatorEvent::UploadArtifact {
+                                    artifact,
+                                    offset,
+                                    data,
+                                    last,
+                                },
+                                &mut daemon_connections,
+                                clock.new_timestamp(),
+                            )
+                            .await
+                            .map(ControlRequestReply::ArtifactStatus);
+                            let _ = reply_sender.send(reply);
+                        }
                         ControlRequest::LogSubscribe { .. } => {
                             let _ = reply_sender.send(Err(eyre::eyre!(
                                 "LogSubscribe request should be handled separately"
                             )));
                         }
+                        ControlRequest::Authenticate { .. } => {
+                            let _ = reply_sender.send(Err(eyre::eyre!(
+                                "Authenticate request should be handled separately"
+                            )));
+                        }
                     }
                 }
                 ControlEvent::Error(err) => tracing::error!("{err:?}"),
@@ -564,6 +1148,27 @@ async fn start_inner(
                 }
             },
             Event::DaemonHeartbeatInterval => {
+                let (expired, waiting) = std::mem::take(&mut pending_dataflows)
+                    .into_iter()
+                    .partition(|(pending, _): &(kubernetes::PendingDataflow, _)| {
+                        pending.created.elapsed() > kubernetes::POD_START_TIMEOUT
+                    });
+                pending_dataflows = waiting;
+                for (pending, reply_sender) in expired {
+                    let missing: Vec<_> = pending
+                        .pods
+                        .machines()
+                        .iter()
+                        .filter(|m| !daemon_connections.contains_key(*m))
+                        .cloned()
+                        .collect();
+                    let _ = reply_sender.send(Err(eyre!(
+                        "the daemons of the Kubernetes pods {missing:?} didn't register within {:?}",
+                        kubernetes::POD_START_TIMEOUT
+                    )));
+                    tasks.push(tokio::spawn(pending.pods.delete()));
+                }
+
                 let mut disconnected = BTreeSet::new();
                 for (machine_id, connection) in &mut daemon_connections {
                     if connection.last_heartbeat.elapsed() > Duration::from_secs(15) {
@@ -595,6 +1200,26 @@ async fn start_inner(
                     tracing::error!("Disconnecting daemons that failed watchdog: {disconnected:?}");
                     for machine_id in disconnected {
                         daemon_connections.remove(&machine_id);
+                        // candidates on the machine can't act as leader anymore
+                        for dataflow in running_dataflows.values_mut() {
+                            for (group, leader) in dataflow.elections.remove_machine(&machine_id) {
+                                announce_leader(
+                                    dataflow,
+                                    &mut daemon_connections,
+                                    &clock,
+                                    group,
+                                    leader,
+                                )
+                                .await?;
+                            }
+                        }
+                        lifecycle_events.emit(
+                            LifecycleEvent::new(
+                                LifecycleEventKind::DaemonDisconnected,
+                                format!("daemon `{machine_id}` disconnected"),
+                            )
+                            .machine(machine_id),
+                        );
                     }
                 }
             }
@@ -616,6 +1241,9 @@ async fn start_inner(
             }
             Event::Log(message) => {
                 if let Some(dataflow) = running_dataflows.get_mut(&message.dataflow_id) {
+                    if let Some(event) = lifecycle_event_from_log(&message, dataflow) {
+                        lifecycle_events.emit(event);
+                    }
                     for subscriber in &mut dataflow.log_subscribers {
                         let send_result = tokio::time::timeout(
                             Duration::from_millis(100),
@@ -632,6 +1260,15 @@ async fn start_inner(
         }
     }
 
+    // don't leave pods behind when the coordinator is stopped
+    let pods = pending_dataflows
+        .into_iter()
+        .map(|(pending, _)| pending.pods)
+        .chain(running_dataflows.into_values().filter_map(|d| d.pods));
+    for pods in pods {
+        tasks.push(tokio::spawn(pods.delete()));
+    }
+
     tracing::info!("stopped");
 
     Ok(())
@@ -643,8 +1280,10 @@ fn dataflow_result(
     clock: &uhlc::HLC,
 ) -> DataflowResult {
     let mut node_results = BTreeMap::new();
+    let mut stop_requests = BTreeMap::new();
     for result in results.values() {
         node_results.extend(result.node_results.clone());
+        stop_requests.extend(result.stop_requests.clone());
         if let Err(err) = clock.update_with_timestamp(&result.timestamp) {
             tracing::warn!("failed to update HLC: {err}");
         }
@@ -654,6 +1293,7 @@ fn dataflow_result(
         uuid: dataflow_uuid,
         timestamp: clock.new_timestamp(),
         node_results,
+        stop_requests,
     }
 }
 
@@ -663,6 +1303,54 @@ struct DaemonConnection {
     last_heartbeat: Instant,
 }
 
+/// Sends the message to the daemons of all machines of the dataflow, logging
+/// errors instead of returning them.
+async fn send_to_dataflow_machines(
+    dataflow: &RunningDataflow,
+    daemon_connections: &mut HashMap<String, DaemonConnection>,
+    message: &[u8],
+) {
+    for machine_id in &dataflow.machines {
+        let Some(connection) = daemon_connections.get_mut(machine_id) else {
+            tracing::warn!("no daemon connection found for machine `{machine_id}`");
+            continue;
+        };
+        if let Err(err) = tcp_send(&mut connection.stream, message).await {
+            tracing::warn!("failed to send message to machine `{machine_id}`: {err:?}");
+        }
+    }
+}
+
+async fn announce_leader(
+    dataflow: &RunningDataflow,
+    daemon_connections: &mut HashMap<String, DaemonConnection>,
+    clock: &HLC,
+    group: String,
+    leader: Option<NodeId>,
+) -> eyre::Result<()> {
+    match &leader {
+        Some(leader) => tracing::info!(
+            "node `{leader}` is the leader of `{group}` in dataflow `{}`",
+            dataflow.uuid
+        ),
+        None => tracing::info!(
+            "election group `{group}` of dataflow `{}` has no leader",
+            dataflow.uuid
+        ),
+    }
+    let message = serde_json::to_vec(&Timestamped {
+        inner: DaemonCoordinatorEvent::LeaderElected {
+            dataflow_id: dataflow.uuid,
+            group,
+            leader,
+        },
+        timestamp: clock.new_timestamp(),
+    })
+    .wrap_err("failed to serialize LeaderElected message")?;
+    send_to_dataflow_machines(dataflow, daemon_connections, &message).await;
+    Ok(())
+}
+
 async fn handle_destroy(
     running_dataflows: &mut HashMap<Uuid, RunningDataflow>,
     daemon_connections: &mut HashMap<String, DaemonConnection>,
@@ -715,6 +1403,24 @@ struct RunningDataflow {
     reply_senders: Vec<tokio::sync::oneshot::Sender<eyre::Result<ControlRequestReply>>>,
 
     log_subscribers: Vec<LogSubscriber>,
+
+    /// Latest health report of each node.
+    node_health: BTreeMap<NodeId, NodeHealth>,
+    /// Latest queue metrics reported by each machine.
+    queue_metrics: BTreeMap<String, QueueMetrics>,
+    /// Latest resource usage reported for each node.
+    resource_usage: BTreeMap<NodeId, NodeResourceUsage>,
+
+    /// Resent with `adopt: true` when a daemon of the dataflow registers again
+    /// after a restart.
+    spawn_command: SpawnDataflowNodes,
+    /// Pods of the nodes that run in Kubernetes, deleted when the dataflow
+    /// finishes.
+    pods: Option<kubernetes::DataflowPods>,
+    /// Contents of the dataflow blackboard, in the order in which the writes
+    /// were broadcast to the daemons.
+    blackboard: BTreeMap<String, Vec<u8>>,
+    elections: election::Elections,
 }
 
 struct ArchivedDataflow {
@@ -831,6 +1537,388 @@ async fn reload_dataflow(
     Ok(())
 }
 
+/// Collects the edge latencies that the daemons of the dataflow measured.
+///
+/// Each daemon only knows the edges to its local nodes, so the results are
+/// disjoint.
+async fn retrieve_edge_latencies(
+    dataflow: &RunningDataflow,
+    daemon_connections: &mut HashMap<String, DaemonConnection>,
+    timestamp: uhlc::Timestamp,
+) -> eyre::Result<Vec<EdgeLatency>> {
+    let message = serde_json::to_vec(&Timestamped {
+        inner: DaemonCoordinatorEvent::EdgeLatencies {
+            dataflow_id: dataflow.uuid,
+        },
+        timestamp,
+    })?;
+
+    let mut edges = Vec::new();
+    for machine_id in &dataflow.machines {
+        let daemon_connection = daemon_connections
+            .get_mut(machine_id.as_str())
+            .wrap_err("no daemon connection")?;
+        tcp_send(&mut daemon_connection.stream, &message)
+            .await
+            .wrap_err("failed to send edge latencies message to daemon")?;
+
+        // wait for reply
+        let reply_raw = tcp_receive(&mut daemon_connection.stream)
+            .await
+            .wrap_err("failed to retrieve edge latencies reply from daemon")?;
+        match serde_json::from_slice(&reply_raw)
+            .wrap_err("failed to deserialize edge latencies reply from daemon")?
+        {
+            DaemonCoordinatorReply::EdgeLatencies(result) => {
+                edges.extend(result.map_err(|err| eyre!(err))?)
+            }
+            other => bail!("unexpected reply after sending edge latencies: {other:?}"),
+        }
+    }
+    Ok(edges)
+}
+
+/// Starts a message recording on all machines of the dataflow and returns
+/// the ID of the recording.
+async fn start_recording(
+    dataflow: &RunningDataflow,
+    options: RecordingOptions,
+    daemon_connections: &mut HashMap<String, DaemonConnection>,
+    clock: &HLC,
+) -> eyre::Result<String> {
+    for pattern in options.patterns() {
+        let node_pattern = pattern.split_once('/').map_or(pattern, |(node, _)| node);
+        if !dataflow
+            .nodes
+            .iter()
+            .any(|node| glob_match(node_pattern, node.id.as_ref()))
+        {
+            bail!("invalid recording pattern `{pattern}`: no matching node in dataflow");
+        }
+    }
+
+    let recording_id = Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).to_string();
+    let message = serde_json::to_vec(&Timestamped {
+        inner: DaemonCoordinatorEvent::StartRecording {
+            dataflow_id: dataflow.uuid,
+            recording_id: recording_id.clone(),
+            options,
+        },
+        timestamp: clock.new_timestamp(),
+    })?;
+
+    let mut result = Ok(());
+    for machine_id in &dataflow.machines {
+        result = send_start_recording(machine_id, &message, daemon_connections).await;
+        if result.is_err() {
+            break;
+        }
+    }
+    if let Err(err) = result {
+        // don't leave a partial recording running
+        if let Err(stop_err) =
+            stop_recording(dataflow, daemon_connections, clock.new_timestamp()).await
+        {
+            tracing::warn!("{stop_err:?}");
+        }
+        return Err(err);
+    }
+    Ok(recording_id)
+}
+
+async fn send_start_recording(
+    machine_id: &str,
+    message: &[u8],
+    daemon_connections: &mut HashMap<String, DaemonConnection>,
+) -> eyre::Result<()> {
+    let daemon_connection = daemon_connections
+        .get_mut(machine_id)
+        .wrap_err_with(|| format!("no daemon connection to machine `{machine_id}`"))?;
+    tcp_send(&mut daemon_connection.stream, message)
+        .await
+        .wrap_err("failed to send start recording message to daemon")?;
+
+    // wait for reply
+    let reply_raw = tcp_receive(&mut daemon_connection.stream)
+        .await
+        .wrap_err("failed to retrieve start recording reply from daemon")?;
+    match serde_json::from_slice(&reply_raw)
+        .wrap_err("failed to deserialize start recording reply from daemon")?
+    {
+        DaemonCoordinatorReply::StartRecordingResult(result) => result
+            .map_err(|err| eyre!(err))
+            .wrap_err_with(|| format!("failed to start recording on machine `{machine_id}`")),
+        other => bail!("unexpected reply after sending start recording: {other:?}"),
+    }
+}
+
+/// Stops the message recording on all machines of the dataflow.
+async fn stop_recording(
+    dataflow: &RunningDataflow,
+    daemon_connections: &mut HashMap<String, DaemonConnection>,
+    timestamp: uhlc::Timestamp,
+) -> eyre::Result<Vec<RecordingSummary>> {
+    let message = serde_json::to_vec(&Timestamped {
+        inner: DaemonCoordinatorEvent::StopRecording {
+            dataflow_id: dataflow.uuid,
+        },
+        timestamp,
+    })?;
+
+    let mut recordings = Vec::new();
+    for machine_id in &dataflow.machines {
+        let daemon_connection = daemon_connections
+            .get_mut(machine_id.as_str())
+            .wrap_err_with(|| format!("no daemon connection to machine `{machine_id}`"))?;
+        tcp_send(&mut daemon_connection.stream, &message)
+            .await
+            .wrap_err("failed to send stop recording message to daemon")?;
+
+        // wait for reply
+        let reply_raw = tcp_receive(&mut daemon_connection.stream)
+            .await
+            .wrap_err("failed to retrieve stop recording reply from daemon")?;
+        match serde_json::from_slice(&reply_raw)
+            .wrap_err("failed to deserialize stop recording reply from daemon")?
+        {
+            DaemonCoordinatorReply::StopRecordingResult(result) => {
+                let summary = result.map_err(|err| eyre!(err)).wrap_err_with(|| {
+                    format!("failed to stop recording on machine `{machine_id}`")
+                })?;
+                recordings.extend(summary);
+            }
+            other => bail!("unexpected reply after sending stop recording: {other:?}"),
+        }
+    }
+    if recordings.is_empty() {
+        bail!("dataflow `{}` is not being recorded", dataflow.uuid);
+    }
+    Ok(recordings)
+}
+
+/// Instructs all daemons of the dataflow to store a checkpoint of their local
+/// nodes.
+async fn create_checkpoint(
+    dataflow: &RunningDataflow,
+    checkpoint_id: String,
+    daemon_connections: &mut HashMap<String, DaemonConnection>,
+    timestamp: uhlc::Timestamp,
+) -> eyre::Result<Vec<CheckpointSummary>> {
+    let message = serde_json::to_vec(&Timestamped {
+        inner: DaemonCoordinatorEvent::Checkpoint {
+            dataflow_id: dataflow.uuid,
+            checkpoint_id,
+        },
+        timestamp,
+    })?;
+
+    let mut checkpoints = Vec::new();
+    for machine_id in &dataflow.machines {
+        let daemon_connection = daemon_connections
+            .get_mut(machine_id.as_str())
+            .wrap_err_with(|| format!("no daemon connection to machine `{machine_id}`"))?;
+        tcp_send(&mut daemon_connection.stream, &message)
+            .await
+            .wrap_err("failed to send checkpoint message to daemon")?;
+
+        // wait for reply
+        let reply_raw = tcp_receive(&mut daemon_connection.stream)
+            .await
+            .wrap_err("failed to retrieve checkpoint reply from daemon")?;
+        match serde_json::from_slice(&reply_raw)
+            .wrap_err("failed to deserialize checkpoint reply from daemon")?
+        {
+            DaemonCoordinatorReply::CheckpointResult(result) => {
+                let summary = result.map_err(|err| eyre!(err)).wrap_err_with(|| {
+                    format!("failed to create checkpoint on machine `{machine_id}`")
+                })?;
+                checkpoints.push(summary);
+            }
+            other => bail!("unexpected reply after sending checkpoint: {other:?}"),
+        }
+    }
+    Ok(checkpoints)
+}
+
+/// Forwards a replay control to all daemons of the dataflow.
+///
+/// Fails if none of the daemons is replaying a recording.
+async fn control_replay(
+    dataflow: &RunningDataflow,
+    control: ReplayControl,
+    daemon_connections: &mut HashMap<String, DaemonConnection>,
+    timestamp: uhlc::Timestamp,
+) -> eyre::Result<()> {
+    if let ReplayControl::SetSpeed(speed) = control {
+        if !speed.is_finite() || speed <= 0.0 {
+            bail!("replay speed must be a positive number, got {speed}");
+        }
+    }
+    let message = serde_json::to_vec(&Timestamped {
+        inner: DaemonCoordinatorEvent::ControlReplay {
+            dataflow_id: dataflow.uuid,
+            control,
+        },
+        timestamp,
+    })?;
+
+    let mut replaying = false;
+    for machine_id in &dataflow.machines {
+        let daemon_connection = daemon_connections
+            .get_mut(machine_id.as_str())
+            .wrap_err_with(|| format!("no daemon connection to machine `{machine_id}`"))?;
+        tcp_send(&mut daemon_connection.stream, &message)
+            .await
+            .wrap_err("failed to send replay control message to daemon")?;
+
+        // wait for reply
+        let reply_raw = tcp_receive(&mut daemon_connection.stream)
+            .await
+            .wrap_err("failed to retrieve replay control reply from daemon")?;
+        match serde_json::from_slice(&reply_raw)
+            .wrap_err("failed to deserialize replay control reply from daemon")?
+        {
+            DaemonCoordinatorReply::ControlReplayResult(result) => {
+                replaying |= result.map_err(|err| eyre!(err)).wrap_err_with(|| {
+                    format!("failed to control replay on machine `{machine_id}`")
+                })?;
+            }
+            other => bail!("unexpected reply after sending replay control: {other:?}"),
+        }
+    }
+    if !replaying {
+        bail!("dataflow `{}` is not replaying a recording", dataflow.uuid);
+    }
+    Ok(())
+}
+
+/// Forwards an artifact request to the daemon of the given machine.
+async fn transfer_artifact(
+    machine_id: &str,
+    event: DaemonCoordinatorEvent,
+    daemon_connections: &mut HashMap<String, DaemonConnection>,
+    timestamp: uhlc::Timestamp,
+) -> eyre::Result<ArtifactStatus> {
+    let message = serde_json::to_vec(&Timestamped {
+        inner: event,
+        timestamp,
+    })?;
+    let daemon_connection = daemon_connections
+        .get_mut(machine_id)
+        .wrap_err_with(|| format!("no daemon connection to machine `{machine_id}`"))?;
+    tcp_send(&mut daemon_connection.stream, &message)
+        .await
+        .wrap_err("failed to send artifact message to daemon")?;
+
+    // wait for reply
+    let reply_raw = tcp_receive(&mut daemon_connection.stream)
+        .await
+        .wrap_err("failed to retrieve artifact reply from daemon")?;
+    match serde_json::from_slice(&reply_raw)
+        .wrap_err("failed to deserialize artifact reply from daemon")?
+    {
+        DaemonCoordinatorReply::ArtifactStatus(result) => result
+            .map_err(|err| eyre!(err))
+            .wrap_err_with(|| format!("failed to transfer artifact to machine `{machine_id}`")),
+        other => bail!("unexpected reply after sending artifact message: {other:?}"),
+    }
+}
+
+/// Combines the graph of the dataflow with the live state reported by its
+/// daemons.
+async fn retrieve_live_graph(
+    dataflow: &RunningDataflow,
+    daemon_connections: &mut HashMap<String, DaemonConnection>,
+    timestamp: uhlc::Timestamp,
+) -> eyre::Result<DataflowGraph> {
+    let message = serde_json::to_vec(&Timestamped {
+        inner: DaemonCoordinatorEvent::LiveGraph {
+            dataflow_id: dataflow.uuid,
+        },
+        timestamp,
+    })?;
+
+    let mut node_states = BTreeMap::new();
+    let mut edges = Vec::new();
+    for machine_id in &dataflow.machines {
+        let daemon_connection = daemon_connections
+            .get_mut(machine_id.as_str())
+            .wrap_err("no daemon connection")?;
+        tcp_send(&mut daemon_connection.stream, &message)
+            .await
+            .wrap_err("failed to send live graph message to daemon")?;
+
+        // wait for reply
+        let reply_raw = tcp_receive(&mut daemon_connection.stream)
+            .await
+            .wrap_err("failed to retrieve live graph reply from daemon")?;
+        match serde_json::from_slice(&reply_raw)
+            .wrap_err("failed to deserialize live graph reply from daemon")?
+        {
+            DaemonCoordinatorReply::LiveGraph(result) => {
+                let graph = result.map_err(|err| eyre!(err))?;
+                node_states.extend(graph.nodes);
+                edges.extend(graph.edges);
+            }
+            other => bail!("unexpected reply after sending live graph: {other:?}"),
+        }
+    }
+
+    let nodes = dataflow
+        .nodes
+        .iter()
+        .map(|node| GraphNode {
+            id: node.id.clone(),
+            name: node.name.clone(),
+            machine: node.deploy.machine.clone(),
+            state: node_states.get(&node.id).copied(),
+            health: dataflow.node_health.get(&node.id).map(|h| h.status),
+        })
+        .collect();
+
+    Ok(DataflowGraph {
+        format_version: DATAFLOW_GRAPH_FORMAT_VERSION,
+        uuid: dataflow.uuid,
+        name: dataflow.name.clone(),
+        nodes,
+        edges,
+    })
+}
+
+async fn retrieve_daemon_states(
+    daemon_connections: &mut HashMap<String, DaemonConnection>,
+    timestamp: uhlc::Timestamp,
+) -> eyre::Result<BTreeMap<String, Vec<u8>>> {
+    let message = serde_json::to_vec(&Timestamped {
+        inner: DaemonCoordinatorEvent::DumpState,
+        timestamp,
+    })?;
+
+    let mut states = BTreeMap::new();
+    for (machine_id, daemon_connection) in daemon_connections {
+        tcp_send(&mut daemon_connection.stream, &message)
+            .await
+            .wrap_err("failed to send dump state message to daemon")?;
+
+        // wait for reply
+        let reply_raw = tcp_receive(&mut daemon_connection.stream)
+            .await
+            .wrap_err("failed to retrieve state dump from daemon")?;
+        match serde_json::from_slice(&reply_raw)
+            .wrap_err("failed to deserialize state dump reply from daemon")?
+        {
+            DaemonCoordinatorReply::StateDump(result) => {
+                let state = result
+                    .map_err(|err| eyre!(err))
+                    .wrap_err_with(|| format!("failed to dump state of machine `{machine_id}`"))?;
+                states.insert(machine_id.clone(), state);
+            }
+            other => bail!("unexpected reply after sending dump state: {other:?}"),
+        }
+    }
+    Ok(states)
+}
+
 async fn retrieve_logs(
     running_dataflows: &HashMap<Uuid, RunningDataflow>,
     archived_dataflows: &HashMap<Uuid, ArchivedDataflow>,
@@ -895,10 +1983,75 @@ async fn retrieve_logs(
     reply_logs.map_err(|err| eyre!(err))
 }
 
+async fn retrieve_crash_reports(
+    running_dataflows: &HashMap<Uuid, RunningDataflow>,
+    archived_dataflows: &HashMap<Uuid, ArchivedDataflow>,
+    dataflow_id: Uuid,
+    node_id: Option<NodeId>,
+    daemon_connections: &mut HashMap<String, DaemonConnection>,
+    timestamp: uhlc::Timestamp,
+) -> eyre::Result<Vec<CrashReport>> {
+    let nodes = if let Some(dataflow) = archived_dataflows.get(&dataflow_id) {
+        dataflow.nodes.clone()
+    } else if let Some(dataflow) = running_dataflows.get(&dataflow_id) {
+        dataflow.nodes.clone()
+    } else {
+        bail!("No dataflow found with UUID `{dataflow_id}`")
+    };
+
+    let machine_ids: BTreeSet<String> = nodes
+        .iter()
+        .filter(|node| node_id.as_ref().map_or(true, |id| &node.id == id))
+        .map(|node| node.deploy.machine.clone())
+        .collect();
+    if machine_ids.is_empty() {
+        if let Some(node_id) = &node_id {
+            bail!("No machine contains {dataflow_id}/{node_id}")
+        }
+    }
+
+    let message = serde_json::to_vec(&Timestamped {
+        inner: DaemonCoordinatorEvent::CrashReports {
+            dataflow_id,
+            node_id,
+        },
+        timestamp,
+    })?;
+
+    let mut reports = Vec::new();
+    for machine_id in machine_ids {
+        let daemon_connection = daemon_connections
+            .get_mut(machine_id.as_str())
+            .wrap_err_with(|| format!("no daemon connection to machine `{machine_id}`"))?;
+        tcp_send(&mut daemon_connection.stream, &message)
+            .await
+            .wrap_err("failed to send crash reports message to daemon")?;
+
+        // wait for reply
+        let reply_raw = tcp_receive(&mut daemon_connection.stream)
+            .await
+            .wrap_err("failed to retrieve crash reports reply from daemon")?;
+        match serde_json::from_slice(&reply_raw)
+            .wrap_err("failed to deserialize crash reports reply from daemon")?
+        {
+            DaemonCoordinatorReply::CrashReports(result) => {
+                let machine_reports = result.map_err(|err| eyre!(err)).wrap_err_with(|| {
+                    format!("failed to retrieve crash reports of machine `{machine_id}`")
+                })?;
+                reports.extend(machine_reports);
+            }
+            other => bail!("unexpected reply after sending crash reports: {other:?}"),
+        }
+    }
+    reports.sort_by_key(|report| report.timestamp);
+    Ok(reports)
+}
+
 async fn start_dataflow(
     dataflow: Descriptor,
     working_dir: PathBuf,
     name: Option<String>,
+    signed: Option<SignedDescriptor>,
     daemon_connections: &mut HashMap<String, DaemonConnection>,
     clock: &HLC,
 ) -> eyre::Result<RunningDataflow> {
@@ -906,7 +2059,8 @@ async fn start_dataflow(
         uuid,
         machines,
         nodes,
-    } = spawn_dataflow(dataflow, working_dir, daemon_connections, clock).await?;
+        spawn_command,
+    } = spawn_dataflow(dataflow, working_dir, signed, daemon_connections, clock).await?;
     Ok(RunningDataflow {
         uuid,
         name,
@@ -920,6 +2074,13 @@ async fn start_dataflow(
         nodes,
         reply_senders: Vec::new(),
         log_subscribers: Vec::new(),
+        node_health: BTreeMap::new(),
+        queue_metrics: BTreeMap::new(),
+        resource_usage: BTreeMap::new(),
+        spawn_command,
+        pods: None,
+        blackboard: BTreeMap::new(),
+        elections: Default::default(),
     })
 }
 
@@ -1009,6 +2170,38 @@ pub enum DataflowEvent {
         machine_id: String,
         exited_before_subscribe: Vec<NodeId>,
     },
+    StopRequested {
+        node_id: NodeId,
+        reason: String,
+    },
+    NodeHealthReported {
+        node_id: NodeId,
+        health: NodeHealth,
+    },
+    QueueMetricsReported {
+        machine_id: String,
+        metrics: QueueMetrics,
+    },
+    ResourceUsageReported {
+        usage: BTreeMap<NodeId, NodeResourceUsage>,
+    },
+    BlackboardSet {
+        key: String,
+        value: Option<Vec<u8>>,
+    },
+    Campaign {
+        machine_id: String,
+        node_id: NodeId,
+        group: String,
+    },
+    Resign {
+        node_id: NodeId,
+        group: Option<String>,
+    },
+    NodeListenerChanged {
+        node_id: NodeId,
+        listener: NodeListener,
+    },
 }
 
 #[derive(Debug)]
@@ -1021,7 +2214,7 @@ pub enum DaemonRequest {
     },
 }
 
-fn set_up_ctrlc_handler() -> Result<impl Stream<Item = Event>, eyre::ErrReport> {
+fn set_up_ctrlc_handler() -> Result<impl Stream<Item = Event> + Unpin, eyre::ErrReport> {
     let (ctrlc_tx, ctrlc_rx) = mpsc::channel(1);
 
     let mut ctrlc_sent = false;
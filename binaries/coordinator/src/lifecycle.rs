@@ -0,0 +1,240 @@
+//! Export of lifecycle events, e.g. for annotating metric dashboards.
+//!
+//! The coordinator reports when dataflows start and stop, when operators are
+//! restarted after a panic, when a reload was applied, and when daemons connect
+//! or disconnect. The events are written to the sink that is passed through
+//! `dora coordinator --lifecycle-events <SINK>`:
+//!
+//! - a file path: the events are appended as JSON lines
+//! - an `http://` or `https://` URL: each event is `POST`ed as JSON. The `time`,
+//!   `tags`, and `text` fields match the Grafana annotations API, so the URL can
+//!   point to Grafana's `/api/annotations` endpoint directly.
+//! - `otel`: each event is recorded as a `lifecycle_event` span with target
+//!   `dora_lifecycle`, which is exported through the OpenTelemetry pipeline that
+//!   is configured through `DORA_JAEGER_TRACING`.
+
+use std::{
+    path::PathBuf,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use dora_core::config::{NodeId, OperatorId};
+use eyre::Context;
+use futures::stream::FuturesUnordered;
+use serde_json::{json, Value};
+use tokio::{io::AsyncWriteExt, sync::mpsc, task::JoinHandle};
+use uuid::{NoContext, Timestamp, Uuid};
+
+/// Destination of the lifecycle events.
+#[derive(Debug, Clone)]
+pub enum LifecycleSink {
+    /// Append the events as JSON lines to the given file.
+    File(PathBuf),
+    /// Post each event as JSON to the given URL.
+    Http(String),
+    /// Record the events as tracing events, which are exported to OpenTelemetry.
+    OpenTelemetry,
+}
+
+impl FromStr for LifecycleSink {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "otel" {
+            Ok(Self::OpenTelemetry)
+        } else if s.starts_with("http://") || s.starts_with("https://") {
+            Ok(Self::Http(s.to_owned()))
+        } else if s.is_empty() {
+            eyre::bail!("lifecycle event sink must not be empty")
+        } else {
+            let path = s.strip_prefix("file://").unwrap_or(s);
+            Ok(Self::File(path.into()))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum LifecycleEventKind {
+    DataflowStarted,
+    DataflowStopped,
+    ReloadApplied,
+    NodeRestarted,
+    DaemonConnected,
+    DaemonDisconnected,
+}
+
+impl LifecycleEventKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::DataflowStarted => "dataflow_started",
+            Self::DataflowStopped => "dataflow_stopped",
+            Self::ReloadApplied => "reload_applied",
+            Self::NodeRestarted => "node_restarted",
+            Self::DaemonConnected => "daemon_connected",
+            Self::DaemonDisconnected => "daemon_disconnected",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LifecycleEvent {
+    kind: LifecycleEventKind,
+    text: String,
+    dataflow: Option<(Uuid, Option<String>)>,
+    machine_id: Option<String>,
+    node_id: Option<NodeId>,
+    operator_id: Option<OperatorId>,
+}
+
+impl LifecycleEvent {
+    pub fn new(kind: LifecycleEventKind, text: impl Into<String>) -> Self {
+        Self {
+            kind,
+            text: text.into(),
+            dataflow: None,
+            machine_id: None,
+            node_id: None,
+            operator_id: None,
+        }
+    }
+
+    pub fn dataflow(mut self, uuid: Uuid, name: Option<String>) -> Self {
+        self.dataflow = Some((uuid, name));
+        self
+    }
+
+    pub fn machine(mut self, machine_id: impl Into<String>) -> Self {
+        self.machine_id = Some(machine_id.into());
+        self
+    }
+
+    pub fn node(mut self, node_id: NodeId, operator_id: Option<OperatorId>) -> Self {
+        self.node_id = Some(node_id);
+        self.operator_id = operator_id;
+        self
+    }
+
+    fn to_json(&self) -> Value {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+
+        let mut tags = vec!["dora".to_owned(), self.kind.as_str().to_owned()];
+        let (dataflow_id, dataflow_name) = match &self.dataflow {
+            Some((uuid, name)) => {
+                tags.push(format!("dataflow:{}", name.as_deref().unwrap_or("unnamed")));
+                (Some(uuid), name.as_ref())
+            }
+            None => (None, None),
+        };
+        if let Some(node_id) = &self.node_id {
+            tags.push(format!("node:{node_id}"));
+        }
+
+        json!({
+            "id": Uuid::new_v7(Timestamp::now(NoContext)),
+            "time": time,
+            "kind": self.kind.as_str(),
+            "text": self.text,
+            "tags": tags,
+            "dataflow_id": dataflow_id,
+            "dataflow_name": dataflow_name,
+            "machine_id": self.machine_id,
+            "node_id": self.node_id,
+            "operator_id": self.operator_id,
+        })
+    }
+}
+
+/// Handle to emit lifecycle events.
+///
+/// The events are exported by a background task, so that a slow sink does not
+/// block the coordinator.
+pub struct LifecycleEvents {
+    sender: Option<mpsc::UnboundedSender<Value>>,
+}
+
+impl LifecycleEvents {
+    pub fn new(sink: Option<LifecycleSink>, tasks: &FuturesUnordered<JoinHandle<()>>) -> Self {
+        let sender = sink.map(|sink| {
+            let (tx, rx) = mpsc::unbounded_channel();
+            tasks.push(tokio::spawn(export(sink, rx)));
+            tx
+        });
+        Self { sender }
+    }
+
+    pub fn emit(&self, event: LifecycleEvent) {
+        if let Some(sender) = &self.sender {
+            if sender.send(event.to_json()).is_err() {
+                tracing::warn!("lifecycle event exporter stopped, dropping {event:?}");
+            }
+        }
+    }
+}
+
+async fn export(sink: LifecycleSink, mut events: mpsc::UnboundedReceiver<Value>) {
+    match sink {
+        LifecycleSink::File(path) => {
+            let mut file = match tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .await
+            {
+                Ok(file) => file,
+                Err(err) => {
+                    tracing::error!(
+                        "failed to open lifecycle event file `{}`: {err}",
+                        path.display()
+                    );
+                    return;
+                }
+            };
+            while let Some(event) = events.recv().await {
+                let mut line = event.to_string();
+                line.push('\n');
+                if let Err(err) = file.write_all(line.as_bytes()).await {
+                    tracing::warn!("failed to write lifecycle event: {err}");
+                }
+            }
+            let _ = file.flush().await;
+        }
+        LifecycleSink::Http(url) => {
+            let client = reqwest::Client::new();
+            while let Some(event) = events.recv().await {
+                let result = client
+                    .post(&url)
+                    .json(&event)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .wrap_err_with(|| format!("failed to post lifecycle event to `{url}`"));
+                if let Err(err) = result {
+                    tracing::warn!("{err:?}");
+                }
+            }
+        }
+        LifecycleSink::OpenTelemetry => {
+            while let Some(event) = events.recv().await {
+                // the OpenTelemetry layer only exports events within spans
+                let span = tracing::info_span!(
+                    target: "dora_lifecycle",
+                    "lifecycle_event",
+                    kind = event["kind"].as_str(),
+                    dataflow_id = event["dataflow_id"].as_str(),
+                    node_id = event["node_id"].as_str(),
+                );
+                let _enter = span.enter();
+                tracing::info!(
+                    target: "dora_lifecycle",
+                    event = %event,
+                    "{}",
+                    event["text"].as_str().unwrap_or_default()
+                );
+            }
+        }
+    }
+}
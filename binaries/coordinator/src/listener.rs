@@ -100,6 +100,112 @@ pub async fn handle_connection(
                         break;
                     }
                 }
+                DaemonEvent::StopDataflowRequested {
+                    dataflow_id,
+                    node_id,
+                    reason,
+                } => {
+                    let event = Event::Dataflow {
+                        uuid: dataflow_id,
+                        event: DataflowEvent::StopRequested { node_id, reason },
+                    };
+                    if events_tx.send(event).await.is_err() {
+                        break;
+                    }
+                }
+                DaemonEvent::NodeHealthReported {
+                    dataflow_id,
+                    node_id,
+                    health,
+                } => {
+                    let event = Event::Dataflow {
+                        uuid: dataflow_id,
+                        event: DataflowEvent::NodeHealthReported { node_id, health },
+                    };
+                    if events_tx.send(event).await.is_err() {
+                        break;
+                    }
+                }
+                DaemonEvent::QueueMetricsReported {
+                    dataflow_id,
+                    metrics,
+                } => {
+                    let event = Event::Dataflow {
+                        uuid: dataflow_id,
+                        event: DataflowEvent::QueueMetricsReported {
+                            machine_id,
+                            metrics,
+                        },
+                    };
+                    if events_tx.send(event).await.is_err() {
+                        break;
+                    }
+                }
+                DaemonEvent::ResourceUsageReported { dataflow_id, usage } => {
+                    let event = Event::Dataflow {
+                        uuid: dataflow_id,
+                        event: DataflowEvent::ResourceUsageReported { usage },
+                    };
+                    if events_tx.send(event).await.is_err() {
+                        break;
+                    }
+                }
+                DaemonEvent::BlackboardSet {
+                    dataflow_id,
+                    key,
+                    value,
+                } => {
+                    let event = Event::Dataflow {
+                        uuid: dataflow_id,
+                        event: DataflowEvent::BlackboardSet { key, value },
+                    };
+                    if events_tx.send(event).await.is_err() {
+                        break;
+                    }
+                }
+                DaemonEvent::Campaign {
+                    dataflow_id,
+                    node_id,
+                    group,
+                } => {
+                    let event = Event::Dataflow {
+                        uuid: dataflow_id,
+                        event: DataflowEvent::Campaign {
+                            machine_id,
+                            node_id,
+                            group,
+                        },
+                    };
+                    if events_tx.send(event).await.is_err() {
+                        break;
+                    }
+                }
+                DaemonEvent::Resign {
+                    dataflow_id,
+                    node_id,
+                    group,
+                } => {
+                    let event = Event::Dataflow {
+                        uuid: dataflow_id,
+                        event: DataflowEvent::Resign { node_id, group },
+                    };
+                    if events_tx.send(event).await.is_err() {
+                        break;
+                    }
+                }
+                DaemonEvent::NodeListenerChanged {
+                    dataflow_id,
+                    node_id,
+                    listener,
+                } => {
+                    let event = Event::Dataflow {
+                        uuid: dataflow_id,
+                        event: DataflowEvent::NodeListenerChanged { node_id, listener },
+                    };
+                    if events_tx.send(event).await.is_err() {
+                        break;
+                    }
+                }
             },
         };
     }
@@ -3,11 +3,13 @@ use crate::{
     DaemonConnection,
 };
 
-use dora_core::{descriptor::DescriptorExt, uhlc::HLC};
+use dora_core::{config::NodeId, descriptor::DescriptorExt, uhlc::HLC};
 use dora_message::{
-    coordinator_to_daemon::{DaemonCoordinatorEvent, SpawnDataflowNodes, Timestamped},
+    coordinator_to_daemon::{
+        DaemonCoordinatorEvent, EncryptionKey, NodeListener, SpawnDataflowNodes, Timestamped,
+    },
     daemon_to_coordinator::DaemonCoordinatorReply,
-    descriptor::{Descriptor, ResolvedNode},
+    descriptor::{Descriptor, ResolvedNode, SignedDescriptor},
 };
 use eyre::{bail, eyre, ContextCompat, WrapErr};
 use std::{
@@ -16,10 +18,11 @@ use std::{
 };
 use uuid::{NoContext, Timestamp, Uuid};
 
-#[tracing::instrument(skip(daemon_connections, clock))]
+#[tracing::instrument(skip(signed, daemon_connections, clock))]
 pub(super) async fn spawn_dataflow(
     dataflow: Descriptor,
     working_dir: PathBuf,
+    signed: Option<SignedDescriptor>,
     daemon_connections: &mut HashMap<String, DaemonConnection>,
     clock: &HLC,
 ) -> eyre::Result<SpawnedDataflow> {
@@ -49,24 +52,38 @@ pub(super) async fn spawn_dataflow(
         })
         .collect::<Result<BTreeMap<_, _>, _>>()?;
 
-    let spawn_command = SpawnDataflowNodes {
+    // the key is shared by all daemons of the dataflow
+    let encryption_key = if dataflow.security.encrypt_remote {
+        Some(EncryptionKey::generate()?)
+    } else {
+        None
+    };
+
+    let mut spawn_command = SpawnDataflowNodes {
         dataflow_id: uuid,
         working_dir,
         nodes: nodes.clone(),
         machine_listen_ports,
         dataflow_descriptor: dataflow,
         uv: false,
+        adopt: false,
+        encryption_key,
+        signed_descriptor: signed,
+        blackboard: BTreeMap::new(),
+        node_listeners: BTreeMap::new(),
     };
     let message = serde_json::to_vec(&Timestamped {
-        inner: DaemonCoordinatorEvent::Spawn(spawn_command),
+        inner: DaemonCoordinatorEvent::Spawn(spawn_command.clone()),
         timestamp: clock.new_timestamp(),
     })?;
 
     for machine in &machines {
         tracing::trace!("Spawning dataflow `{uuid}` on machine `{machine}`");
-        spawn_dataflow_on_machine(daemon_connections, machine, &message)
+        let node_listeners = spawn_dataflow_on_machine(daemon_connections, machine, &message)
             .await
             .wrap_err_with(|| format!("failed to spawn dataflow on machine `{machine}`"))?;
+        // needed for re-adopting the nodes after a daemon restart
+        spawn_command.node_listeners.extend(node_listeners);
     }
 
     tracing::info!("successfully spawned dataflow `{uuid}`");
@@ -75,14 +92,47 @@ pub(super) async fn spawn_dataflow(
         uuid,
         machines,
         nodes,
+        spawn_command,
     })
 }
 
+/// Asks a restarted daemon to re-adopt the still running nodes of a dataflow.
+///
+/// The daemon sets up the node communication again without spawning the node
+/// processes, which then reconnect on their own.
+#[tracing::instrument(skip(spawn_command, daemon_connections, clock), fields(dataflow_id = %spawn_command.dataflow_id))]
+pub(super) async fn adopt_dataflow_on_machine(
+    spawn_command: &SpawnDataflowNodes,
+    blackboard: &BTreeMap<String, Vec<u8>>,
+    machine: &str,
+    daemon_connections: &mut HashMap<String, DaemonConnection>,
+    clock: &HLC,
+) -> eyre::Result<()> {
+    let mut spawn_command = spawn_command.clone();
+    spawn_command.adopt = true;
+    // the restarted daemon lost its replica of the blackboard
+    spawn_command.blackboard = blackboard.clone();
+    // the listen port of the restarted daemon might have changed
+    for (machine_id, socket) in &mut spawn_command.machine_listen_ports {
+        if let Some(connection) = daemon_connections.get(machine_id) {
+            *socket = connection.listen_socket;
+        }
+    }
+    let message = serde_json::to_vec(&Timestamped {
+        inner: DaemonCoordinatorEvent::Spawn(spawn_command),
+        timestamp: clock.new_timestamp(),
+    })?;
+    spawn_dataflow_on_machine(daemon_connections, machine, &message)
+        .await
+        .wrap_err_with(|| format!("failed to re-adopt dataflow on machine `{machine}`"))?;
+    Ok(())
+}
+
 async fn spawn_dataflow_on_machine(
     daemon_connections: &mut HashMap<String, DaemonConnection>,
     machine: &str,
     message: &[u8],
-) -> Result<(), eyre::ErrReport> {
+) -> Result<BTreeMap<NodeId, NodeListener>, eyre::ErrReport> {
     let daemon_connection = daemon_connections
         .get_mut(machine)
         .wrap_err_with(|| format!("no daemon connection for machine `{machine}`"))?;
@@ -97,14 +147,14 @@ async fn spawn_dataflow_on_machine(
     {
         DaemonCoordinatorReply::SpawnResult(result) => result
             .map_err(|e| eyre!(e))
-            .wrap_err("daemon returned an error")?,
+            .wrap_err("daemon returned an error"),
         _ => bail!("unexpected reply"),
     }
-    Ok(())
 }
 
 pub struct SpawnedDataflow {
     pub uuid: Uuid,
     pub machines: BTreeSet<String>,
     pub nodes: Vec<ResolvedNode>,
+    pub spawn_command: SpawnDataflowNodes,
 }
@@ -0,0 +1,42 @@
+//! Activation of the environment of a node, e.g. through `nix develop` or
+//! `conda run`.
+
+use std::ffi::OsStr;
+
+/// Wraps the command into a shell that runs it behind the `activate` prefix.
+///
+/// Only the program and arguments are taken over from the given command, so
+/// this must be called before any other settings are applied to it.
+pub fn wrap(command: &tokio::process::Command, activate: &str) -> tokio::process::Command {
+    let command = command.as_std();
+    let program = command.get_program();
+    let args = command.get_args();
+
+    if cfg!(windows) {
+        let line = std::iter::once(program)
+            .chain(args)
+            .map(quote)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut wrapped = tokio::process::Command::new("cmd");
+        wrapped.args(["/C", &format!("{activate} {line}")]);
+        wrapped
+    } else {
+        // pass the command as positional parameters, so that it doesn't need
+        // to be escaped
+        let mut wrapped = tokio::process::Command::new("sh");
+        wrapped.args(["-c", &format!("{activate} \"$@\""), "sh"]);
+        wrapped.arg(program);
+        wrapped.args(args);
+        wrapped
+    }
+}
+
+fn quote(arg: &OsStr) -> String {
+    let arg = arg.to_string_lossy();
+    if arg.contains(char::is_whitespace) {
+        format!("\"{arg}\"")
+    } else {
+        arg.into_owned()
+    }
+}
@@ -0,0 +1,159 @@
+//! Cache of the node files that were uploaded through `dora start --distribute`.
+//!
+//! Artifacts are stored at `<cache>/<sha256>/<file name>`, so they are
+//! addressed by their content and keep the extension of the original file.
+//! Uploads are written to a `.part` file first, which allows resuming
+//! interrupted uploads. The file is moved to its final path once its
+//! checksum was verified.
+//!
+//! The cache directory is set through the `DORA_ARTIFACT_CACHE` environment
+//! variable and defaults to `dora/artifacts` in the state directory of the
+//! user. Only the user that runs the daemon can access it.
+
+use std::path::{Path, PathBuf};
+
+use dora_message::common::{ArtifactId, ArtifactStatus};
+use eyre::{bail, Context, ContextCompat};
+use tokio::io::AsyncWriteExt;
+
+use crate::spawn::verify_checksum;
+
+fn cache_dir() -> eyre::Result<PathBuf> {
+    match std::env::var_os("DORA_ARTIFACT_CACHE") {
+        Some(dir) => Ok(PathBuf::from(dir)),
+        None => dirs::state_dir()
+            .or_else(dirs::data_local_dir)
+            .map(|dir| dir.join("dora").join("artifacts"))
+            .context("failed to determine the state directory of the user"),
+    }
+}
+
+/// Creates the cache directory, which only the current user can access.
+async fn create_cache_dir() -> eyre::Result<()> {
+    let dir = cache_dir()?;
+    let mut builder = tokio::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    builder.mode(0o700);
+    builder
+        .create(&dir)
+        .await
+        .wrap_err_with(|| format!("failed to create `{}`", dir.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        // the directory might have been created with other permissions
+        tokio::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))
+            .await
+            .wrap_err_with(|| format!("failed to set permissions of `{}`", dir.display()))?;
+    }
+    Ok(())
+}
+
+/// Returns the path of the cached artifact, if it exists.
+pub fn cached(sha256: &str, source: &str) -> Option<PathBuf> {
+    let file_name = Path::new(source).file_name()?.to_str()?;
+    let artifact = ArtifactId {
+        sha256: sha256.to_lowercase(),
+        file_name: file_name.to_owned(),
+    };
+    let (path, _) = paths(&artifact).ok()?;
+    path.is_file().then_some(path)
+}
+
+pub async fn status(artifact: &ArtifactId) -> eyre::Result<ArtifactStatus> {
+    let (path, part_path) = paths(artifact)?;
+    if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+        return Ok(ArtifactStatus::Complete);
+    }
+    let received = match tokio::fs::metadata(&part_path).await {
+        Ok(metadata) => metadata.len(),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => 0,
+        Err(err) => return Err(err).wrap_err("failed to read partial artifact"),
+    };
+    Ok(ArtifactStatus::Partial { received })
+}
+
+/// Appends the data to the partial artifact, if it starts at the end of the
+/// already received data.
+///
+/// The artifact is verified and completed after the `last` part.
+pub async fn write(
+    artifact: &ArtifactId,
+    offset: u64,
+    data: &[u8],
+    last: bool,
+) -> eyre::Result<ArtifactStatus> {
+    let received = match status(artifact).await? {
+        ArtifactStatus::Complete => return Ok(ArtifactStatus::Complete),
+        ArtifactStatus::Partial { received } => received,
+    };
+    if offset != received {
+        // the upload resumes at the reported offset
+        return Ok(ArtifactStatus::Partial { received });
+    }
+
+    let (path, part_path) = paths(artifact)?;
+    create_cache_dir().await?;
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .wrap_err_with(|| format!("failed to create `{}`", dir.display()))?;
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&part_path)
+        .await
+        .wrap_err_with(|| format!("failed to open `{}`", part_path.display()))?;
+    file.write_all(data)
+        .await
+        .wrap_err("failed to write artifact")?;
+    file.flush().await.wrap_err("failed to write artifact")?;
+    drop(file);
+
+    if !last {
+        return Ok(ArtifactStatus::Partial {
+            received: received + data.len() as u64,
+        });
+    }
+
+    if let Err(err) = verify_checksum(&part_path, &artifact.sha256).await {
+        // start over on the next upload
+        let _ = tokio::fs::remove_file(&part_path).await;
+        return Err(err);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        tokio::fs::set_permissions(&part_path, std::fs::Permissions::from_mode(0o755))
+            .await
+            .wrap_err("failed to make artifact executable")?;
+    }
+    tokio::fs::rename(&part_path, &path)
+        .await
+        .wrap_err("failed to move artifact to the cache")?;
+    tracing::info!(
+        "received artifact `{}` ({})",
+        artifact.file_name,
+        artifact.sha256
+    );
+    Ok(ArtifactStatus::Complete)
+}
+
+/// Final and partial path of the artifact.
+fn paths(artifact: &ArtifactId) -> eyre::Result<(PathBuf, PathBuf)> {
+    let ArtifactId { sha256, file_name } = artifact;
+    if sha256.len() != 64 || !sha256.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')) {
+        bail!("invalid artifact checksum `{sha256}`");
+    }
+    if file_name.is_empty()
+        || file_name == "."
+        || file_name == ".."
+        || file_name.contains(['/', '\\'])
+    {
+        bail!("invalid artifact file name `{file_name}`");
+    }
+    let dir = cache_dir()?.join(sha256);
+    Ok((dir.join(file_name), dir.join(format!("{file_name}.part"))))
+}
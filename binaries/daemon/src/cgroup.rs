@@ -0,0 +1,256 @@
+//! Per-node cgroups for resource accounting, using cgroup v2.
+//!
+//! The daemon creates a `dora-nodes` cgroup below its own cgroup and places
+//! each spawned node into a child cgroup of it. This way, the CPU time,
+//! memory, and IO of all processes and threads of a node are accounted
+//! together. Since only leaf cgroups can contain processes once controllers
+//! are enabled, the daemon moves itself into a `dora-daemon` child cgroup
+//! first.
+//!
+//! The cgroups also enforce the `resources` limits of the nodes.
+//!
+//! This requires write access to the cgroup of the daemon, e.g. through
+//! `Delegate=yes` in its systemd unit. Nodes run without a cgroup if the
+//! setup fails.
+
+#[cfg(not(target_os = "linux"))]
+use dora_message::{common::NodeResourceUsage, id::NodeId, DataflowId};
+
+#[cfg(target_os = "linux")]
+pub use linux::{NodeCgroup, OomKills};
+
+#[cfg(not(target_os = "linux"))]
+#[derive(Debug)]
+pub struct NodeCgroup(());
+
+#[cfg(not(target_os = "linux"))]
+impl NodeCgroup {
+    pub fn create(_dataflow_id: DataflowId, _node_id: &NodeId) -> Option<Self> {
+        None
+    }
+
+    pub fn apply(&self, _command: &mut tokio::process::Command) -> eyre::Result<()> {
+        Ok(())
+    }
+
+    pub fn sample(&mut self) -> Option<NodeResourceUsage> {
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::{
+        fs::File,
+        io::Write,
+        path::{Path, PathBuf},
+        sync::OnceLock,
+        time::{Duration, Instant},
+    };
+
+    use dora_core::descriptor::ResourceLimits;
+    use dora_message::{common::NodeResourceUsage, id::NodeId, DataflowId};
+    use eyre::Context;
+
+    const CGROUP_MOUNT: &str = "/sys/fs/cgroup";
+    const CONTROLLERS: [&str; 3] = ["cpu", "memory", "io"];
+    /// Period of the CPU bandwidth limit in microseconds, the kernel default.
+    const CPU_PERIOD_US: u64 = 100_000;
+
+    #[derive(Debug)]
+    pub struct NodeCgroup {
+        path: PathBuf,
+        last_sample: Option<(Instant, Duration)>,
+    }
+
+    impl NodeCgroup {
+        /// Creates the cgroup for the given node, if cgroups are available.
+        pub fn create(dataflow_id: DataflowId, node_id: &NodeId) -> Option<Self> {
+            let root = nodes_root()?;
+            let path = root.join(format!("{dataflow_id}-{node_id}"));
+            match std::fs::create_dir(&path) {
+                Ok(()) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {}
+                Err(err) => {
+                    tracing::warn!("failed to create cgroup `{}`: {err}", path.display());
+                    return None;
+                }
+            }
+            Some(Self {
+                path,
+                last_sample: None,
+            })
+        }
+
+        /// Sets up the command so that the spawned process starts in this
+        /// cgroup, before it can spawn any threads or child processes.
+        pub fn apply(&self, command: &mut tokio::process::Command) -> eyre::Result<()> {
+            let procs_path = self.path.join("cgroup.procs");
+            let procs = File::options()
+                .write(true)
+                .open(&procs_path)
+                .wrap_err_with(|| format!("failed to open `{}`", procs_path.display()))?;
+            // SAFETY: the closure only writes to a file that was opened before
+            // forking, which is async-signal-safe
+            unsafe {
+                command.pre_exec(move || (&procs).write_all(b"0"));
+            }
+            Ok(())
+        }
+
+        /// Limits the memory and CPU usage of the processes in the cgroup.
+        pub fn set_limits(&self, limits: &ResourceLimits) -> eyre::Result<()> {
+            if let Some(memory_mb) = limits.memory_mb {
+                self.write("memory.max", &(memory_mb * 1024 * 1024).to_string())?;
+                // the node could exceed the limit through swap otherwise
+                if let Err(err) = self.write("memory.swap.max", "0") {
+                    tracing::debug!("{err:?}");
+                }
+                // kill all processes of the node, not only the largest one
+                self.write("memory.oom.group", "1")?;
+            }
+            if let Some(cpus) = limits.cpus {
+                let quota = ((cpus * CPU_PERIOD_US as f64) as u64).max(1000);
+                self.write("cpu.max", &format!("{quota} {CPU_PERIOD_US}"))?;
+            }
+            Ok(())
+        }
+
+        /// Counter of the processes of the cgroup that were killed because
+        /// they exceeded the memory limit.
+        pub fn oom_kills(&self) -> OomKills {
+            OomKills(self.path.join("memory.events"))
+        }
+
+        fn write(&self, file: &str, value: &str) -> eyre::Result<()> {
+            let path = self.path.join(file);
+            std::fs::write(&path, value)
+                .wrap_err_with(|| format!("failed to write `{value}` to `{}`", path.display()))
+        }
+
+        /// Reads the current resource usage of the cgroup.
+        pub fn sample(&mut self) -> Option<NodeResourceUsage> {
+            let cpu_stat = std::fs::read_to_string(self.path.join("cpu.stat")).ok()?;
+            let cpu_time = Duration::from_micros(stat_value(&cpu_stat, "usage_usec")?);
+            let now = Instant::now();
+            let cpu_percent = match self.last_sample.replace((now, cpu_time)) {
+                Some((last, last_cpu_time)) if now > last => {
+                    cpu_time.saturating_sub(last_cpu_time).as_secs_f64()
+                        / (now - last).as_secs_f64()
+                        * 100.0
+                }
+                _ => 0.0,
+            };
+            let memory_bytes = std::fs::read_to_string(self.path.join("memory.current"))
+                .ok()
+                .and_then(|s| s.trim().parse().ok());
+            let io = std::fs::read_to_string(self.path.join("io.stat")).ok();
+            let io_bytes = |key: &str| {
+                io.as_deref().map(|io| {
+                    io.split_whitespace()
+                        .filter_map(|field| field.strip_prefix(key)?.strip_prefix('='))
+                        .filter_map(|value| value.parse::<u64>().ok())
+                        .sum()
+                })
+            };
+            Some(NodeResourceUsage {
+                cpu_time,
+                cpu_percent,
+                memory_bytes,
+                io_read_bytes: io_bytes("rbytes"),
+                io_write_bytes: io_bytes("wbytes"),
+            })
+        }
+    }
+
+    impl Drop for NodeCgroup {
+        fn drop(&mut self) {
+            // fails if processes of the node are still running
+            if let Err(err) = std::fs::remove_dir(&self.path) {
+                tracing::debug!("failed to remove cgroup `{}`: {err}", self.path.display());
+            }
+        }
+    }
+
+    /// Reads the `oom_kill` counter of a node cgroup.
+    ///
+    /// Unlike the [`NodeCgroup`], this can be moved to the task that waits for
+    /// the exit of the node.
+    pub struct OomKills(PathBuf);
+
+    impl OomKills {
+        pub fn read(&self) -> Option<u64> {
+            let events = std::fs::read_to_string(&self.0).ok()?;
+            stat_value(&events, "oom_kill")
+        }
+    }
+
+    /// The `dora-nodes` cgroup, which is set up on first use.
+    fn nodes_root() -> Option<&'static Path> {
+        static ROOT: OnceLock<Option<PathBuf>> = OnceLock::new();
+        ROOT.get_or_init(|| match set_up() {
+            Ok(root) => {
+                tracing::debug!("placing nodes into cgroups below `{}`", root.display());
+                Some(root)
+            }
+            Err(err) => {
+                tracing::info!("not placing nodes into cgroups: {err:?}");
+                None
+            }
+        })
+        .as_deref()
+    }
+
+    fn set_up() -> eyre::Result<PathBuf> {
+        let own = std::fs::read_to_string("/proc/self/cgroup")
+            .wrap_err("failed to read `/proc/self/cgroup`")?;
+        let own = own
+            .lines()
+            .find_map(|line| line.strip_prefix("0::"))
+            .ok_or_else(|| eyre::eyre!("cgroup v2 is not available"))?;
+        let own = Path::new(CGROUP_MOUNT).join(own.trim_start_matches('/'));
+        // hybrid setups mount a tmpfs at `/sys/fs/cgroup`, in which the
+        // cgroup files below would be created as regular files
+        if !own.join("cgroup.controllers").exists() {
+            eyre::bail!("`{}` is not a cgroup v2 directory", own.display());
+        }
+
+        let daemon = own.join("dora-daemon");
+        std::fs::create_dir_all(&daemon)
+            .wrap_err_with(|| format!("failed to create `{}`", daemon.display()))?;
+        std::fs::write(daemon.join("cgroup.procs"), std::process::id().to_string())
+            .wrap_err("failed to move daemon into its own cgroup")?;
+        enable_controllers(&own);
+
+        let nodes = own.join("dora-nodes");
+        std::fs::create_dir_all(&nodes)
+            .wrap_err_with(|| format!("failed to create `{}`", nodes.display()))?;
+        enable_controllers(&nodes);
+        Ok(nodes)
+    }
+
+    /// Enables the accounting controllers for the children of the cgroup.
+    ///
+    /// CPU time is accounted even without the `cpu` controller, the other
+    /// numbers are left out if their controller is not available.
+    fn enable_controllers(cgroup: &Path) {
+        for controller in CONTROLLERS {
+            if let Err(err) = std::fs::write(
+                cgroup.join("cgroup.subtree_control"),
+                format!("+{controller}"),
+            ) {
+                tracing::debug!(
+                    "failed to enable `{controller}` controller in `{}`: {err}",
+                    cgroup.display()
+                );
+            }
+        }
+    }
+
+    fn stat_value(stat: &str, key: &str) -> Option<u64> {
+        stat.lines().find_map(|line| {
+            let (k, v) = line.split_once(' ')?;
+            (k == key).then(|| v.trim().parse().ok()).flatten()
+        })
+    }
+}
@@ -0,0 +1,442 @@
+//! Dataflow-wide checkpoints, created on request of the coordinator.
+//!
+//! A checkpoint combines the state that each local node stored most recently
+//! through `DoraNode::save_state`, the inputs that were queued for the local
+//! nodes but not received by them yet, and the clock state of the daemon.
+//! Together with the checkpoints of the other daemons of the dataflow, it
+//! allows restarting a long-running dataflow roughly where it left off, e.g.
+//! after planned maintenance. Messages that are in transit between daemons
+//! when the checkpoint is created are not part of it.
+//!
+//! Each daemon writes its part to
+//! `out/<dataflow>/checkpoints/<checkpoint>/<machine>` in the working directory
+//! of the dataflow. Dataflows are restored through the `restore` key of their
+//! descriptor: the restored states are available to the nodes through
+//! `DoraNode::restored_state` and the queued inputs are delivered again when
+//! the dataflow starts.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use dora_core::{
+    config::{DataId, NodeId},
+    uhlc,
+};
+use dora_message::{
+    common::{CheckpointSummary, DataMessage, Timestamped},
+    daemon_to_node::NodeEvent,
+    metadata::Metadata,
+    DataflowId,
+};
+use eyre::{bail, Context};
+use shared_memory_server::ShmemConf;
+use tokio::sync::{mpsc::UnboundedSender, oneshot};
+
+/// Version of the checkpoint format, stored in the index.
+const FORMAT_VERSION: u32 = 1;
+const INDEX_FILE: &str = "checkpoint.json";
+
+/// Requests a copy of the inputs that are queued in the listener of a node.
+pub type QueueSnapshotRequests = UnboundedSender<oneshot::Sender<Vec<QueuedInput>>>;
+
+/// Directory of a checkpoint on the given machine, relative to the working
+/// directory of the dataflow.
+pub fn checkpoint_dir(
+    working_dir: &Path,
+    dataflow_id: &DataflowId,
+    checkpoint_id: &str,
+    machine_id: &str,
+) -> PathBuf {
+    working_dir
+        .join("out")
+        .join(dataflow_id.to_string())
+        .join("checkpoints")
+        .join(checkpoint_id)
+        .join(machine_dir_name(machine_id))
+}
+
+fn machine_dir_name(machine_id: &str) -> &str {
+    if machine_id.is_empty() {
+        "default"
+    } else {
+        machine_id
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct CheckpointIndex {
+    version: u32,
+    dataflow_id: DataflowId,
+    checkpoint_id: String,
+    machine_id: String,
+    /// Time of the daemon clock when the checkpoint was created.
+    timestamp: uhlc::Timestamp,
+    /// Simulated time of the dataflow, if it runs on simulated time.
+    simulated_time: Option<Duration>,
+    /// File of each node, relative to the checkpoint directory.
+    nodes: BTreeMap<NodeId, String>,
+}
+
+/// An input that was queued for a node when the checkpoint was created.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct QueuedInput {
+    pub id: DataId,
+    pub metadata: Metadata,
+    pub data: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct NodeCheckpoint {
+    /// The state that the node stored most recently.
+    pub state: Option<Vec<u8>>,
+    pub queued_inputs: Vec<QueuedInput>,
+}
+
+pub struct Checkpoint {
+    pub timestamp: uhlc::Timestamp,
+    pub simulated_time: Option<Duration>,
+    pub nodes: BTreeMap<NodeId, NodeCheckpoint>,
+}
+
+impl Checkpoint {
+    /// Combines the given node states with snapshots of the input queues of
+    /// the local nodes.
+    pub async fn capture(
+        timestamp: uhlc::Timestamp,
+        simulated_time: Option<Duration>,
+        node_states: BTreeMap<NodeId, Vec<u8>>,
+        queue_snapshots: BTreeMap<NodeId, QueueSnapshotRequests>,
+    ) -> Self {
+        // request all snapshots first, so that they are taken at about the
+        // same time
+        let requests: Vec<_> = queue_snapshots
+            .into_iter()
+            .filter_map(|(node_id, requests)| {
+                let (reply_sender, reply) = oneshot::channel();
+                requests.send(reply_sender).ok().map(|()| (node_id, reply))
+            })
+            .collect();
+
+        let mut nodes: BTreeMap<_, _> = node_states
+            .into_iter()
+            .map(|(node_id, state)| {
+                let node = NodeCheckpoint {
+                    state: Some(state),
+                    queued_inputs: Vec::new(),
+                };
+                (node_id, node)
+            })
+            .collect();
+        for (node_id, reply) in requests {
+            // the listener is gone if the node exited in the meantime
+            if let Ok(queued_inputs) = reply.await {
+                nodes.entry(node_id).or_default().queued_inputs = queued_inputs;
+            }
+        }
+
+        Self {
+            timestamp,
+            simulated_time,
+            nodes,
+        }
+    }
+
+    /// Writes the checkpoint to the given directory.
+    pub fn write(
+        &self,
+        dir: &Path,
+        dataflow_id: DataflowId,
+        checkpoint_id: String,
+        machine_id: String,
+    ) -> eyre::Result<CheckpointSummary> {
+        std::fs::create_dir_all(dir)
+            .wrap_err_with(|| format!("failed to create `{}`", dir.display()))?;
+
+        let mut files = BTreeMap::new();
+        for (node_id, node) in &self.nodes {
+            let file_name = format!("{node_id}.bin");
+            let path = dir.join(&file_name);
+            let file = File::create(&path)
+                .wrap_err_with(|| format!("failed to create `{}`", path.display()))?;
+            bincode::serialize_into(BufWriter::new(file), node)
+                .wrap_err_with(|| format!("failed to write checkpoint of node `{node_id}`"))?;
+            files.insert(node_id.clone(), file_name);
+        }
+
+        // the index is written last, so that incomplete checkpoints are not
+        // restored
+        let index = CheckpointIndex {
+            version: FORMAT_VERSION,
+            dataflow_id,
+            checkpoint_id,
+            machine_id: machine_id.clone(),
+            timestamp: self.timestamp,
+            simulated_time: self.simulated_time,
+            nodes: files,
+        };
+        let index_path = dir.join(INDEX_FILE);
+        let file = File::create(&index_path)
+            .wrap_err_with(|| format!("failed to create `{}`", index_path.display()))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &index)
+            .wrap_err("failed to write checkpoint index")?;
+
+        Ok(CheckpointSummary {
+            machine_id,
+            path: dir.to_owned(),
+            node_states: self.nodes.values().filter(|n| n.state.is_some()).count(),
+            queued_inputs: self
+                .nodes
+                .values()
+                .map(|n| n.queued_inputs.len() as u64)
+                .sum(),
+        })
+    }
+
+    /// Reads the part of the checkpoint that was written by the given machine.
+    ///
+    /// The directory is either the directory of the whole checkpoint or of
+    /// the part of a single machine.
+    pub fn read(dir: &Path, machine_id: &str) -> eyre::Result<Self> {
+        let dir = if dir.join(INDEX_FILE).is_file() {
+            dir.to_owned()
+        } else {
+            dir.join(machine_dir_name(machine_id))
+        };
+        let index_path = dir.join(INDEX_FILE);
+        let file = File::open(&index_path).wrap_err_with(|| {
+            format!("no complete checkpoint found at `{}`", index_path.display())
+        })?;
+        let index: CheckpointIndex = serde_json::from_reader(BufReader::new(file))
+            .wrap_err_with(|| format!("failed to parse `{}`", index_path.display()))?;
+        if index.version != FORMAT_VERSION {
+            bail!(
+                "unsupported checkpoint format version {} (expected {FORMAT_VERSION})",
+                index.version
+            );
+        }
+
+        let mut nodes = BTreeMap::new();
+        for (node_id, file_name) in index.nodes {
+            let path = dir.join(file_name);
+            let file = File::open(&path)
+                .wrap_err_with(|| format!("failed to open `{}`", path.display()))?;
+            let node = bincode::deserialize_from(BufReader::new(file))
+                .wrap_err_with(|| format!("failed to read checkpoint of node `{node_id}`"))?;
+            nodes.insert(node_id, node);
+        }
+
+        Ok(Self {
+            timestamp: index.timestamp,
+            simulated_time: index.simulated_time,
+            nodes,
+        })
+    }
+}
+
+/// Copies the inputs of the given queued events, including their data.
+///
+/// Must be called while the events are still queued, as the sender might
+/// reuse shared memory regions once the receiver got the input. The inputs of
+/// batches are restored as separate inputs.
+pub fn queued_inputs<'a>(
+    events: impl Iterator<Item = &'a Timestamped<NodeEvent>>,
+) -> Vec<QueuedInput> {
+    events
+        .flat_map(|event| match &event.inner {
+            NodeEvent::Input { id, metadata, data } => vec![(id, metadata, data)],
+            NodeEvent::InputBatch { inputs } => inputs
+                .iter()
+                .map(|input| (&input.id, &input.metadata, &input.data))
+                .collect(),
+            _ => Vec::new(),
+        })
+        .filter_map(|(id, metadata, data)| {
+            let data = match data {
+                None => None,
+                Some(DataMessage::Vec(data)) => Some(data.to_vec()),
+                Some(DataMessage::SharedMemory {
+                    shared_memory_id,
+                    len,
+                    ..
+                }) => match ShmemConf::new().os_id(shared_memory_id).open() {
+                    Ok(memory) => Some(unsafe { memory.as_slice() }[..*len].to_vec()),
+                    Err(err) => {
+                        tracing::warn!("failed to map queued input `{id}`: {err}");
+                        return None;
+                    }
+                },
+            };
+            Some(QueuedInput {
+                id: id.clone(),
+                metadata: metadata.clone(),
+                data,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use dora_core::{metadata::ArrowTypeInfoExt, uhlc::HLC};
+    use dora_message::metadata::ArrowTypeInfo;
+    use uuid::{NoContext, Timestamp, Uuid};
+
+    use super::*;
+
+    /// Temporary directory that is removed on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let id = Uuid::new_v7(Timestamp::now(NoContext));
+            let dir = std::env::temp_dir().join(format!("dora-checkpoint-test-{id}"));
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn node(id: &str) -> NodeId {
+        NodeId::from(id.to_owned())
+    }
+
+    /// Writes a checkpoint with a node state and a queued input of another
+    /// node to the directory of machine `a`.
+    fn write_checkpoint(root: &Path) -> (PathBuf, Checkpoint) {
+        let clock = HLC::default();
+        let checkpoint = Checkpoint {
+            timestamp: clock.new_timestamp(),
+            simulated_time: Some(Duration::from_secs(3)),
+            nodes: BTreeMap::from([
+                (
+                    node("counter"),
+                    NodeCheckpoint {
+                        state: Some(vec![1, 2, 3]),
+                        queued_inputs: Vec::new(),
+                    },
+                ),
+                (
+                    node("sink"),
+                    NodeCheckpoint {
+                        state: None,
+                        queued_inputs: vec![QueuedInput {
+                            id: "value".to_owned().into(),
+                            metadata: Metadata::new(
+                                clock.new_timestamp(),
+                                ArrowTypeInfo::byte_array(2),
+                            ),
+                            data: Some(vec![4, 5]),
+                        }],
+                    },
+                ),
+            ]),
+        };
+        let dir = checkpoint_dir(root, &DataflowId::nil(), "cp", "a");
+        let summary = checkpoint
+            .write(&dir, DataflowId::nil(), "cp".to_owned(), "a".to_owned())
+            .unwrap();
+        assert_eq!(summary.node_states, 1);
+        assert_eq!(summary.queued_inputs, 1);
+        (dir, checkpoint)
+    }
+
+    fn assert_same(read: &Checkpoint, written: &Checkpoint) {
+        assert_eq!(read.timestamp, written.timestamp);
+        assert_eq!(read.simulated_time, written.simulated_time);
+        assert_eq!(format!("{:?}", read.nodes), format!("{:?}", written.nodes));
+    }
+
+    #[test]
+    fn restore_written_checkpoint() {
+        let root = TempDir::new();
+        let (dir, written) = write_checkpoint(&root.0);
+
+        // from the directory of the machine
+        assert_same(&Checkpoint::read(&dir, "a").unwrap(), &written);
+        // from the directory of the whole checkpoint
+        let checkpoint_dir = dir.parent().unwrap();
+        assert_same(&Checkpoint::read(checkpoint_dir, "a").unwrap(), &written);
+        // other machines have no part in it
+        assert!(Checkpoint::read(checkpoint_dir, "b").is_err());
+    }
+
+    #[test]
+    fn default_machine_has_its_own_directory() {
+        let dir = checkpoint_dir(Path::new("/work"), &DataflowId::nil(), "cp", "");
+        assert!(dir.ends_with("checkpoints/cp/default"));
+    }
+
+    #[test]
+    fn reject_checkpoint_without_index() {
+        let root = TempDir::new();
+        let (dir, _) = write_checkpoint(&root.0);
+        // e.g. the daemon crashed before writing the index
+        std::fs::remove_file(dir.join(INDEX_FILE)).unwrap();
+
+        let err = Checkpoint::read(&dir, "a").err().unwrap();
+        assert!(
+            format!("{err}").starts_with("no complete checkpoint found"),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn reject_corrupt_index() {
+        let root = TempDir::new();
+        let (dir, _) = write_checkpoint(&root.0);
+        let index = std::fs::read_to_string(dir.join(INDEX_FILE)).unwrap();
+        std::fs::write(dir.join(INDEX_FILE), &index[..index.len() / 2]).unwrap();
+
+        let err = Checkpoint::read(&dir, "a").err().unwrap();
+        assert!(format!("{err}").starts_with("failed to parse"), "{err:?}");
+    }
+
+    #[test]
+    fn reject_other_format_version() {
+        let root = TempDir::new();
+        let (dir, _) = write_checkpoint(&root.0);
+        let index = std::fs::read_to_string(dir.join(INDEX_FILE)).unwrap();
+        let version = format!("\"version\": {FORMAT_VERSION},");
+        assert!(index.contains(&version));
+        let index = index.replace(&version, &format!("\"version\": {},", FORMAT_VERSION + 1));
+        std::fs::write(dir.join(INDEX_FILE), index).unwrap();
+
+        let err = Checkpoint::read(&dir, "a").err().unwrap();
+        assert!(
+            format!("{err}").starts_with("unsupported checkpoint format version"),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn reject_truncated_node_file() {
+        let root = TempDir::new();
+        let (dir, _) = write_checkpoint(&root.0);
+        let path = dir.join("sink.bin");
+        let data = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &data[..data.len() - 1]).unwrap();
+
+        let err = Checkpoint::read(&dir, "a").err().unwrap();
+        assert_eq!(format!("{err}"), "failed to read checkpoint of node `sink`");
+    }
+
+    #[test]
+    fn reject_missing_node_file() {
+        let root = TempDir::new();
+        let (dir, _) = write_checkpoint(&root.0);
+        std::fs::remove_file(dir.join("counter.bin")).unwrap();
+
+        let err = Checkpoint::read(&dir, "a").err().unwrap();
+        assert!(format!("{err}").starts_with("failed to open"), "{err:?}");
+    }
+}
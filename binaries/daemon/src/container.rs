@@ -0,0 +1,129 @@
+//! Running nodes in containers through Docker or Podman.
+//!
+//! The node process is the client of the container engine, which stays
+//! attached to the container and forwards its output and stop signals. The
+//! container shares the network and IPC namespaces of the machine, so the
+//! node can reach the daemon through any local communication type.
+
+use std::path::Path;
+
+use dora_core::descriptor::{ContainerConfig, ContainerEngine, CONTAINER_SOURCE};
+use dora_message::daemon_to_node::NodeConfig;
+use eyre::Context;
+
+use crate::user::NodeUser;
+
+/// Removes the container of a node when the node is dropped, in case the
+/// client of the container engine was killed before the container exited.
+#[derive(Debug)]
+pub struct ContainerGuard {
+    engine: ContainerEngine,
+    name: String,
+}
+
+impl Drop for ContainerGuard {
+    fn drop(&mut self) {
+        let result = std::process::Command::new(self.engine.executable())
+            .args(["rm", "--force", &self.name])
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn();
+        match result {
+            // wait in the background to not leave a zombie process behind
+            Ok(mut child) => {
+                std::thread::spawn(move || child.wait());
+            }
+            Err(err) => {
+                tracing::warn!("failed to remove container `{}`: {err}", self.name)
+            }
+        }
+    }
+}
+
+/// Creates the command that runs the node in its container.
+///
+/// The given environment variables are passed through from the command to
+/// the container, so they must be set on the returned command.
+pub fn command(
+    config: &ContainerConfig,
+    node_config: &NodeConfig,
+    source: &str,
+    args: Option<&str>,
+    working_dir: &Path,
+    user: Option<&NodeUser>,
+    env_names: impl IntoIterator<Item = String>,
+) -> eyre::Result<(tokio::process::Command, ContainerGuard)> {
+    let engine = match config.engine {
+        Some(engine) => engine,
+        None if which::which(ContainerEngine::Podman.executable()).is_ok() => {
+            ContainerEngine::Podman
+        }
+        None => ContainerEngine::Docker,
+    };
+    let executable = which::which(engine.executable())
+        .wrap_err_with(|| format!("failed to find `{}`", engine.executable()))?;
+    let name = format!("dora-{}-{}", node_config.dataflow_id, node_config.node_id);
+
+    let mut command = tokio::process::Command::new(executable);
+    command.args(["run", "--rm", "--init", "--name", &name]);
+    command.args(["--network", "host", "--ipc", "host"]);
+    command.args(["--volume", "/dev/shm:/dev/shm"]);
+    #[cfg(unix)]
+    if let dora_message::daemon_to_node::DaemonCommunication::UnixDomain { socket_file } =
+        &node_config.daemon_communication
+    {
+        if let Some(dir) = socket_file.parent() {
+            command.arg("--volume");
+            command.arg(format!("{0}:{0}", dir.display()));
+        }
+    }
+
+    // run as the node user, so that the daemon can access the shared memory
+    // that the node allocates
+    #[cfg(unix)]
+    {
+        let (uid, gid) = match user {
+            Some(user) => (user.uid, user.gid),
+            // SAFETY: `geteuid` and `getegid` have no preconditions
+            None => unsafe { (libc::geteuid(), libc::getegid()) },
+        };
+        command.args(["--user", &format!("{uid}:{gid}")]);
+        // SAFETY: `geteuid` has no preconditions
+        if engine == ContainerEngine::Podman && unsafe { libc::geteuid() } != 0 {
+            // map the user of the daemon to the same ID in rootless containers
+            command.arg("--userns=keep-id");
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = user;
+
+    for mount in &config.mounts {
+        command.arg("--volume");
+        command.arg(resolve_mount(mount, working_dir));
+    }
+    for name in env_names {
+        command.args(["--env", &name]);
+    }
+    command.args(&config.args);
+    command.arg(&config.image);
+
+    if source != CONTAINER_SOURCE {
+        command.arg(source);
+    }
+    if let Some(args) = args {
+        command.args(args.split_ascii_whitespace());
+    }
+
+    Ok((command, ContainerGuard { engine, name }))
+}
+
+/// Makes relative host paths of the mount relative to the working directory.
+fn resolve_mount(mount: &str, working_dir: &Path) -> String {
+    match mount.split_once(':') {
+        Some((host, rest)) if Path::new(host).is_relative() => {
+            format!("{}:{rest}", working_dir.join(host).display())
+        }
+        _ => mount.to_owned(),
+    }
+}
@@ -0,0 +1,265 @@
+//! Crash reports of nodes that were terminated by a signal.
+//!
+//! When a node crashes, the stderr excerpt in the node error is often not
+//! enough to find the cause. The daemon therefore keeps the most recent output
+//! lines and input metadata of each local node and bundles them, together with
+//! the resource usage and the core dump location of the process, into a crash
+//! report. Each report is written to
+//! `out/<dataflow_id>/crash_reports/<node_id>_<timestamp>.json` in the working
+//! directory, from where the CLI can retrieve it through the coordinator.
+
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+};
+
+use dora_core::config::{DataId, NodeId};
+use dora_message::{
+    common::{CrashReport, InputSummary, ResourceUsage},
+    metadata::Metadata,
+};
+use eyre::Context;
+use uuid::Uuid;
+
+/// Number of output lines that are kept per node.
+const RECENT_LOG_LINES: usize = 50;
+/// Number of inputs whose metadata is kept per node.
+const RECENT_INPUTS: usize = 20;
+
+/// Bounded record of the recent output lines and inputs of a node.
+#[derive(Default)]
+pub struct CrashContext {
+    log_lines: VecDeque<String>,
+    inputs: VecDeque<InputSummary>,
+}
+
+impl CrashContext {
+    pub fn record_output(&mut self, text: &str) {
+        for line in text.lines() {
+            if self.log_lines.len() == RECENT_LOG_LINES {
+                self.log_lines.pop_front();
+            }
+            self.log_lines.push_back(line.to_owned());
+        }
+    }
+
+    pub fn record_input(&mut self, input_id: &DataId, metadata: &Metadata) {
+        if self.inputs.len() == RECENT_INPUTS {
+            self.inputs.pop_front();
+        }
+        self.inputs.push_back(InputSummary {
+            input_id: input_id.clone(),
+            timestamp: metadata.timestamp(),
+            data_type: format!("{:?}", metadata.type_info.data_type),
+            len: metadata.type_info.len,
+        });
+    }
+
+    pub fn log_lines(&self) -> Vec<String> {
+        self.log_lines.iter().cloned().collect()
+    }
+
+    pub fn recent_inputs(&self) -> Vec<InputSummary> {
+        self.inputs.iter().cloned().collect()
+    }
+}
+
+/// Information about the exit of a node process that is only available
+/// before the process is reaped.
+#[derive(Debug, Default)]
+pub struct ExitInfo {
+    pub core_dumped: bool,
+    pub core_dump_location: Option<String>,
+    pub resource_usage: Option<ResourceUsage>,
+}
+
+/// Waits until the process with the given ID exited, without reaping it, and
+/// collects the information that is needed for a crash report.
+///
+/// The process must still be reaped afterwards, e.g. through
+/// [`tokio::process::Child::wait`]. Only supported on Linux, on other
+/// platforms this returns immediately without any information.
+pub async fn wait_for_exit(pid: u32, working_dir: &Path) -> ExitInfo {
+    #[cfg(target_os = "linux")]
+    {
+        let core_dumped = match linux::wait_without_reaping(pid).await {
+            Ok(core_dumped) => core_dumped,
+            Err(err) => {
+                tracing::warn!("failed to wait for exit of process {pid}: {err}");
+                return ExitInfo::default();
+            }
+        };
+        ExitInfo {
+            core_dumped,
+            core_dump_location: core_dumped
+                .then(|| linux::core_dump_location(pid, working_dir))
+                .flatten(),
+            resource_usage: linux::resource_usage(pid),
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (pid, working_dir);
+        ExitInfo::default()
+    }
+}
+
+/// Writes the given crash report and returns the path of the file.
+pub async fn write(working_dir: &Path, report: &CrashReport) -> eyre::Result<PathBuf> {
+    let dir = reports_dir(working_dir, &report.dataflow_id);
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .context("could not create crash report dir")?;
+
+    let path = dir.join(format!(
+        "{}_{}.json",
+        report.node_id,
+        report.timestamp.get_time().as_u64()
+    ));
+    let json = serde_json::to_vec_pretty(report).context("failed to serialize crash report")?;
+    tokio::fs::write(&path, json)
+        .await
+        .wrap_err_with(|| format!("failed to write crash report `{}`", path.display()))?;
+    Ok(path)
+}
+
+/// Reads the crash reports of the given dataflow, optionally only the ones of
+/// a single node.
+pub async fn read_all(
+    working_dir: &Path,
+    dataflow_id: &Uuid,
+    node_id: Option<&NodeId>,
+) -> eyre::Result<Vec<CrashReport>> {
+    let dir = reports_dir(working_dir, dataflow_id);
+    let mut entries = match tokio::fs::read_dir(&dir).await {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            return Err(err)
+                .wrap_err_with(|| format!("failed to read crash report dir `{}`", dir.display()))
+        }
+    };
+
+    let mut reports = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().map_or(true, |ext| ext != "json") {
+            continue;
+        }
+        let raw = tokio::fs::read(&path)
+            .await
+            .wrap_err_with(|| format!("failed to read crash report `{}`", path.display()))?;
+        let report: CrashReport = serde_json::from_slice(&raw)
+            .wrap_err_with(|| format!("failed to parse crash report `{}`", path.display()))?;
+        if node_id.map_or(true, |node_id| &report.node_id == node_id) {
+            reports.push(report);
+        }
+    }
+    reports.sort_by_key(|report| report.timestamp);
+    Ok(reports)
+}
+
+fn reports_dir(working_dir: &Path, dataflow_id: &Uuid) -> PathBuf {
+    working_dir
+        .join("out")
+        .join(dataflow_id.to_string())
+        .join("crash_reports")
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::{io, path::Path, time::Duration};
+
+    use dora_message::common::ResourceUsage;
+    use tokio::signal::unix::{signal, SignalKind};
+
+    /// Waits until the process exited, but leaves it as a zombie so that its
+    /// `/proc` entry is still available. Returns whether a core dump was
+    /// written.
+    pub async fn wait_without_reaping(pid: u32) -> io::Result<bool> {
+        let mut sigchld = signal(SignalKind::child())?;
+        loop {
+            if let Some(core_dumped) = check_exit(pid)? {
+                return Ok(core_dumped);
+            }
+            if sigchld.recv().await.is_none() {
+                return Err(io::Error::other("SIGCHLD stream closed"));
+            }
+        }
+    }
+
+    /// Returns whether a core dump was written if the process exited, without
+    /// reaping it.
+    fn check_exit(pid: u32) -> io::Result<Option<bool>> {
+        loop {
+            // SAFETY: `siginfo_t` is a plain C struct that is valid when zeroed
+            let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+            // SAFETY: `info` is a valid pointer for the duration of the call
+            let result = unsafe {
+                libc::waitid(
+                    libc::P_PID,
+                    pid as libc::id_t,
+                    &mut info,
+                    libc::WEXITED | libc::WNOHANG | libc::WNOWAIT,
+                )
+            };
+            if result < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err);
+            }
+            // SAFETY: the `si_pid` field is set by `waitid`
+            let exited = unsafe { info.si_pid() } != 0;
+            return Ok(exited.then_some(info.si_code == libc::CLD_DUMPED));
+        }
+    }
+
+    /// Reads the CPU times of an exited, but not yet reaped process.
+    pub fn resource_usage(pid: u32) -> Option<ResourceUsage> {
+        let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+        // the command name can contain spaces, so start after its closing paren
+        let fields: Vec<_> = stat
+            .get(stat.rfind(')')? + 1..)?
+            .split_whitespace()
+            .collect();
+        // SAFETY: `sysconf` has no preconditions
+        let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        if ticks_per_second <= 0 {
+            return None;
+        }
+        let to_duration =
+            |ticks: u64| Duration::from_secs_f64(ticks as f64 / ticks_per_second as f64);
+        // `utime` and `stime` are the 14th and 15th field of the stat file
+        Some(ResourceUsage {
+            user_time: to_duration(fields.get(11)?.parse().ok()?),
+            system_time: to_duration(fields.get(12)?.parse().ok()?),
+        })
+    }
+
+    /// Derives the location of the core dump of the given process from the
+    /// core pattern of the system.
+    pub fn core_dump_location(pid: u32, working_dir: &Path) -> Option<String> {
+        let pattern = std::fs::read_to_string("/proc/sys/kernel/core_pattern").ok()?;
+        let pattern = pattern.trim();
+        if let Some(handler) = pattern.strip_prefix('|') {
+            if handler.contains("systemd-coredump") {
+                return Some(format!(
+                    "stored by systemd-coredump, see `coredumpctl info {pid}`"
+                ));
+            }
+            return Some(format!("piped to `{handler}`"));
+        }
+
+        let mut path = pattern.replace("%p", &pid.to_string()).replace("%%", "%");
+        let uses_pid = std::fs::read_to_string("/proc/sys/kernel/core_uses_pid")
+            .map(|value| value.trim() == "1")
+            .unwrap_or(false);
+        if uses_pid && !pattern.contains("%p") {
+            path.push_str(&format!(".{pid}"));
+        }
+        // relative paths are relative to the working directory of the node
+        Some(working_dir.join(path).display().to_string())
+    }
+}
@@ -0,0 +1,177 @@
+//! JSON snapshot of the internal state of the daemon.
+//!
+//! The snapshot is meant to be attached to bug reports about stuck dataflows,
+//! so it contains the bookkeeping of all running dataflows, e.g. the pending
+//! drop tokens and whether the channels to the nodes are still open. Message
+//! data is not included.
+
+use std::collections::BTreeMap;
+
+use serde_json::{json, Value};
+
+use crate::{Daemon, OutputId, RunningDataflow};
+
+pub fn dump_state(daemon: &Daemon) -> Value {
+    let dataflows: BTreeMap<_, _> = daemon
+        .running
+        .iter()
+        .map(|(id, dataflow)| {
+            let mut state = dump_dataflow(dataflow);
+            state["working_dir"] = json!(daemon
+                .working_dir
+                .get(id)
+                .map(|dir| dir.display().to_string()));
+            (id.to_string(), state)
+        })
+        .collect();
+    let node_results: BTreeMap<_, _> = daemon
+        .dataflow_node_results
+        .iter()
+        .map(|(id, results)| {
+            let results: BTreeMap<_, _> = results
+                .iter()
+                .map(|(node_id, result)| {
+                    let result = match result {
+                        Ok(()) => json!("ok"),
+                        Err(err) => json!(err.to_string()),
+                    };
+                    (node_id.to_string(), result)
+                })
+                .collect();
+            (id.to_string(), results)
+        })
+        .collect();
+
+    json!({
+        "machine_id": daemon.machine_id,
+        "coordinator_connection": {
+            "connected": daemon.coordinator_connection.is_some(),
+            "last_heartbeat_secs_ago": daemon.last_coordinator_heartbeat.elapsed().as_secs_f64(),
+        },
+        "inter_daemon_connections": daemon.inter_daemon_connections.keys().collect::<Vec<_>>(),
+        "exit_when_done": daemon.exit_when_done.as_ref().map(|nodes| {
+            nodes
+                .iter()
+                .map(|(dataflow_id, node_id)| format!("{dataflow_id}/{node_id}"))
+                .collect::<Vec<_>>()
+        }),
+        "dataflows": dataflows,
+        "dataflow_node_results": node_results,
+    })
+}
+
+fn dump_dataflow(dataflow: &RunningDataflow) -> Value {
+    let running_nodes: BTreeMap<_, _> = dataflow
+        .running_nodes
+        .iter()
+        .map(|(node_id, node)| {
+            let pid = node.pid.as_ref().and_then(|pid| pid.0);
+            (node_id.to_string(), json!({ "pid": pid }))
+        })
+        .collect();
+    let subscribe_channels: BTreeMap<_, _> = dataflow
+        .subscribe_channels
+        .iter()
+        .map(|(node_id, channel)| {
+            (
+                node_id.to_string(),
+                json!({ "closed": channel.is_closed() }),
+            )
+        })
+        .collect();
+    let drop_channels: BTreeMap<_, _> = dataflow
+        .drop_channels
+        .iter()
+        .map(|(node_id, channel)| {
+            (
+                node_id.to_string(),
+                json!({ "closed": channel.is_closed() }),
+            )
+        })
+        .collect();
+    let open_inputs: BTreeMap<_, _> = dataflow
+        .open_inputs
+        .iter()
+        .map(|(node_id, inputs)| (node_id.to_string(), inputs))
+        .collect();
+    let mappings: BTreeMap<_, _> = dataflow
+        .mappings
+        .iter()
+        .map(|(output_id, inputs)| {
+            let inputs: Vec<_> = inputs
+                .iter()
+                .map(|(node_id, input_id)| format!("{node_id}/{input_id}"))
+                .collect();
+            (format_output_id(output_id), inputs)
+        })
+        .collect();
+    let open_external_mappings: BTreeMap<_, _> = dataflow
+        .open_external_mappings
+        .iter()
+        .map(|(output_id, machines)| {
+            let machines: BTreeMap<_, _> = machines
+                .iter()
+                .map(|(machine, inputs)| {
+                    let inputs: Vec<_> = inputs
+                        .iter()
+                        .map(|(node_id, input_id)| format!("{node_id}/{input_id}"))
+                        .collect();
+                    (machine, inputs)
+                })
+                .collect();
+            (format_output_id(output_id), machines)
+        })
+        .collect();
+    let timers: BTreeMap<_, _> = dataflow
+        .timers
+        .iter()
+        .map(|(interval, inputs)| {
+            let inputs: Vec<_> = inputs
+                .iter()
+                .map(|(node_id, input_id)| format!("{node_id}/{input_id}"))
+                .collect();
+            (format!("{interval:?}"), inputs)
+        })
+        .collect();
+    let pending_drop_tokens: Vec<_> = dataflow
+        .pending_drop_tokens
+        .iter()
+        .map(|(token, info)| {
+            json!({
+                "token": token,
+                "owner": info.owner,
+                "pending_nodes": info.pending_nodes,
+                "len": info.len,
+            })
+        })
+        .collect();
+
+    json!({
+        "adopted": dataflow.adopted,
+        "stop_sent": dataflow.stop_sent,
+        "pending_nodes": dataflow.pending_nodes.dump_state(),
+        "running_nodes": running_nodes,
+        "dynamic_nodes": dataflow.dynamic_nodes,
+        "runtime_nodes": dataflow.runtime_nodes,
+        "remote_nodes": dataflow.remote_nodes,
+        "subscribe_channels": subscribe_channels,
+        "drop_channels": drop_channels,
+        "open_inputs": open_inputs,
+        "mappings": mappings,
+        "open_external_mappings": open_external_mappings,
+        "timers": timers,
+        "pending_drop_tokens": pending_drop_tokens,
+        "queue_metrics": dataflow.queue_metrics(),
+        "cascading_error_causes": dataflow.cascading_error_causes.caused_by,
+        "grace_duration_kills": dataflow
+            .grace_duration_kills
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect::<Vec<_>>(),
+        "node_health": dataflow.node_health,
+    })
+}
+
+fn format_output_id(OutputId(node_id, output_id): &OutputId) -> String {
+    format!("{node_id}/{output_id}")
+}
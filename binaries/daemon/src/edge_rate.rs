@@ -0,0 +1,60 @@
+use std::time::{Duration, Instant};
+
+/// Length of the window over which the message rate is measured.
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// Counts the messages delivered over an edge to estimate its message rate.
+pub struct EdgeRate {
+    messages: u64,
+    last_message: Instant,
+    window_start: Instant,
+    window_messages: u64,
+    /// Rate of the last complete window, in messages per second.
+    rate: f64,
+}
+
+impl EdgeRate {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            messages: 0,
+            last_message: now,
+            window_start: now,
+            window_messages: 0,
+            rate: 0.0,
+        }
+    }
+
+    pub fn record(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed >= WINDOW {
+            self.rate = self.window_messages as f64 / elapsed.as_secs_f64();
+            self.window_start = now;
+            self.window_messages = 0;
+        }
+        self.window_messages += 1;
+        self.messages += 1;
+        self.last_message = now;
+    }
+
+    pub fn messages(&self) -> u64 {
+        self.messages
+    }
+
+    /// The recent message rate, in messages per second.
+    ///
+    /// The rate decays towards zero when no more messages are delivered.
+    pub fn rate(&self, now: Instant) -> f64 {
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed >= WINDOW {
+            self.window_messages as f64 / elapsed.as_secs_f64()
+        } else {
+            self.rate
+        }
+    }
+
+    /// Time since the last delivered message, or `None` if no message was
+    /// delivered yet.
+    pub fn last_message_age(&self, now: Instant) -> Option<Duration> {
+        (self.messages > 0).then(|| now.duration_since(self.last_message))
+    }
+}
@@ -0,0 +1,171 @@
+//! Leader election between redundant replicas of a node.
+//!
+//! Nodes campaign in named groups through `DoraNode::campaign`. The
+//! coordinator elects the leader of each group across all machines and
+//! announces it to the daemons, which forward it to their local candidates.
+//! Without a coordinator, the daemon elects the leader among its local
+//! candidates itself, using the same rule: the running candidate that
+//! campaigned first is the leader.
+
+use std::collections::BTreeMap;
+
+use dora_core::config::NodeId;
+use dora_message::daemon_to_node::NodeEvent;
+
+#[derive(Default)]
+pub struct LocalElections {
+    groups: BTreeMap<String, Group>,
+}
+
+#[derive(Default)]
+struct Group {
+    /// Local candidates in the order in which they campaigned.
+    candidates: Vec<NodeId>,
+    /// Leader that each local candidate was last notified of.
+    notified: BTreeMap<NodeId, Option<NodeId>>,
+}
+
+impl LocalElections {
+    pub fn campaign(&mut self, group: String, node_id: NodeId) {
+        let group = self.groups.entry(group).or_default();
+        if !group.candidates.contains(&node_id) {
+            group.candidates.push(node_id);
+        }
+    }
+
+    /// Removes the node from the given group, or from all groups if `group`
+    /// is `None`.
+    ///
+    /// Returns the groups that the node was a candidate in.
+    pub fn resign(&mut self, group: Option<&str>, node_id: &NodeId) -> Vec<String> {
+        let mut left = Vec::new();
+        for (name, state) in &mut self.groups {
+            if group.is_some_and(|group| group != name) {
+                continue;
+            }
+            let len = state.candidates.len();
+            state.candidates.retain(|c| c != node_id);
+            state.notified.remove(node_id);
+            if state.candidates.len() != len {
+                left.push(name.clone());
+            }
+        }
+        self.groups.retain(|_, g| !g.candidates.is_empty());
+        left
+    }
+
+    /// Leader of the group among the local candidates, for dataflows that
+    /// run without a coordinator.
+    pub fn local_leader(&self, group: &str) -> Option<NodeId> {
+        self.groups.get(group)?.candidates.first().cloned()
+    }
+
+    /// Records the leader of the group and returns the events for the local
+    /// candidates that weren't notified of it yet.
+    pub fn set_leader(&mut self, group: &str, leader: Option<NodeId>) -> Vec<(NodeId, NodeEvent)> {
+        let Some(state) = self.groups.get_mut(group) else {
+            return Vec::new();
+        };
+        let mut events = Vec::new();
+        for candidate in &state.candidates {
+            if state.notified.get(candidate) == Some(&leader) {
+                continue;
+            }
+            state.notified.insert(candidate.clone(), leader.clone());
+            let event = NodeEvent::LeadershipChanged {
+                group: group.to_owned(),
+                leader: leader.clone(),
+                is_leader: leader.as_ref() == Some(candidate),
+            };
+            events.push((candidate.clone(), event));
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str) -> NodeId {
+        NodeId::from(id.to_owned())
+    }
+
+    /// Notified candidates and whether they lead, as `(candidate, leader, is_leader)`.
+    fn notifications(events: Vec<(NodeId, NodeEvent)>) -> Vec<(NodeId, Option<NodeId>, bool)> {
+        events
+            .into_iter()
+            .map(|(candidate, event)| match event {
+                NodeEvent::LeadershipChanged {
+                    leader, is_leader, ..
+                } => (candidate, leader, is_leader),
+                other => panic!("unexpected event {other:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn local_leader_is_first_candidate() {
+        let mut elections = LocalElections::default();
+        elections.campaign("g".into(), node("a"));
+        elections.campaign("g".into(), node("b"));
+        elections.campaign("g".into(), node("a"));
+        assert_eq!(elections.local_leader("g"), Some(node("a")));
+
+        assert_eq!(elections.resign(Some("g"), &node("a")), ["g"]);
+        assert_eq!(elections.local_leader("g"), Some(node("b")));
+        assert_eq!(elections.resign(None, &node("b")), ["g"]);
+        assert_eq!(elections.local_leader("g"), None);
+    }
+
+    #[test]
+    fn candidates_are_notified_once_per_leader() {
+        let mut elections = LocalElections::default();
+        elections.campaign("g".into(), node("a"));
+        elections.campaign("g".into(), node("b"));
+
+        assert_eq!(
+            notifications(elections.set_leader("g", Some(node("a")))),
+            [
+                (node("a"), Some(node("a")), true),
+                (node("b"), Some(node("a")), false),
+            ]
+        );
+        assert_eq!(
+            notifications(elections.set_leader("g", Some(node("a")))),
+            []
+        );
+
+        // late candidates are notified of the current leader
+        elections.campaign("g".into(), node("c"));
+        assert_eq!(
+            notifications(elections.set_leader("g", Some(node("a")))),
+            [(node("c"), Some(node("a")), false)]
+        );
+    }
+
+    #[test]
+    fn stale_leader_steps_down() {
+        let mut elections = LocalElections::default();
+        elections.campaign("g".into(), node("a"));
+        elections.set_leader("g", Some(node("a")));
+
+        // the coordinator elected a candidate of another machine, e.g. after
+        // this daemon reconnected
+        assert_eq!(
+            notifications(elections.set_leader("g", Some(node("remote")))),
+            [(node("a"), Some(node("remote")), false)]
+        );
+        assert_eq!(
+            notifications(elections.set_leader("g", None)),
+            [(node("a"), None, false)]
+        );
+    }
+
+    #[test]
+    fn unknown_group_has_no_candidates() {
+        let mut elections = LocalElections::default();
+        assert!(elections.set_leader("g", Some(node("a"))).is_empty());
+        assert!(elections.resign(Some("g"), &node("a")).is_empty());
+    }
+}
@@ -0,0 +1,434 @@
+//! Encryption of the outputs that are forwarded to other daemons.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use aligned_vec::{AVec, ConstAlign};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Key, XChaCha20Poly1305, XNonce,
+};
+use dora_core::config::{DataId, NodeId};
+use dora_message::{
+    coordinator_to_daemon::EncryptionKey,
+    daemon_to_daemon::{EventTag, InterDaemonEvent},
+    metadata::Metadata,
+    DataflowId,
+};
+use eyre::{bail, eyre, Context};
+use serde::Serialize;
+
+/// Encrypts and authenticates outputs with the key of a dataflow.
+///
+/// The nonces are chosen randomly, which is safe for XChaCha20-Poly1305
+/// because of its extended nonce size.
+///
+/// Each message carries an authenticated sequence number that increases per
+/// output. Received messages whose sequence number is not larger than the
+/// previous one of the same output are rejected as replays.
+///
+/// Other events are sent unencrypted, but authenticated through an
+/// [`EventTag`] with a sequence number that increases per sending daemon.
+pub struct OutputCipher {
+    cipher: XChaCha20Poly1305,
+    /// Sequence number of the next message that is sent.
+    ///
+    /// Starts at the current time, so that the sequence numbers keep
+    /// increasing when the daemon is restarted.
+    next_sequence: u64,
+    /// Sequence number of the last message that was received per output.
+    received: BTreeMap<(NodeId, DataId), u64>,
+    /// Sequence number of the last authenticated event per sending machine.
+    received_events: BTreeMap<String, u64>,
+}
+
+/// The fields of the unencrypted inter-daemon events that are authenticated.
+#[derive(Debug, Serialize)]
+pub enum AuthenticatedEvent<'a> {
+    InputsClosed {
+        dataflow_id: &'a DataflowId,
+        inputs: &'a BTreeSet<(NodeId, DataId)>,
+    },
+    Backpressure {
+        dataflow_id: &'a DataflowId,
+        node_id: &'a NodeId,
+        output_id: &'a DataId,
+        level: f32,
+    },
+}
+
+impl OutputCipher {
+    pub fn new(key: &EncryptionKey) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        Self {
+            cipher: XChaCha20Poly1305::new(Key::from_slice(&key.0)),
+            next_sequence: u64::try_from(now).unwrap_or(u64::MAX / 2),
+            received: BTreeMap::new(),
+            received_events: BTreeMap::new(),
+        }
+    }
+
+    pub fn encrypt(
+        &mut self,
+        dataflow_id: DataflowId,
+        node_id: NodeId,
+        output_id: DataId,
+        metadata: &Metadata,
+        data: Option<&[u8]>,
+    ) -> eyre::Result<InterDaemonEvent> {
+        let plaintext =
+            bincode::serialize(&(metadata, data)).wrap_err("failed to serialize output")?;
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        let aad = associated_data(&dataflow_id, &node_id, &output_id, sequence)?;
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &plaintext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| eyre!("failed to encrypt output"))?;
+        let mut nonce_bytes = [0; 24];
+        nonce_bytes.copy_from_slice(&nonce);
+        Ok(InterDaemonEvent::EncryptedOutput {
+            dataflow_id,
+            node_id,
+            output_id,
+            sequence,
+            nonce: nonce_bytes,
+            ciphertext,
+        })
+    }
+
+    /// Decrypts an output and checks that it was sent for the given dataflow
+    /// and output and that it is not a replay of an earlier message.
+    pub fn decrypt(
+        &mut self,
+        dataflow_id: &DataflowId,
+        node_id: &NodeId,
+        output_id: &DataId,
+        sequence: u64,
+        nonce: &[u8; 24],
+        ciphertext: &[u8],
+    ) -> eyre::Result<(Metadata, Option<AVec<u8, ConstAlign<128>>>)> {
+        let aad = associated_data(dataflow_id, node_id, output_id, sequence)?;
+        let plaintext = self
+            .cipher
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| {
+                eyre!("failed to decrypt output, it was modified or encrypted with another key")
+            })?;
+        let last = self
+            .received
+            .entry((node_id.clone(), output_id.clone()))
+            .or_default();
+        if sequence <= *last {
+            bail!("rejecting replayed output `{node_id}/{output_id}` (sequence number {sequence})");
+        }
+        *last = sequence;
+        let (metadata, data): (Metadata, Option<Vec<u8>>) =
+            bincode::deserialize(&plaintext).wrap_err("failed to deserialize decrypted output")?;
+        Ok((metadata, data.map(|data| AVec::from_slice(128, &data))))
+    }
+
+    /// Authenticates an event that the daemon of the given machine sends
+    /// unencrypted.
+    pub fn authenticate(
+        &mut self,
+        sender: &str,
+        event: &AuthenticatedEvent,
+    ) -> eyre::Result<EventTag> {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        let aad = bincode::serialize(&(sender, sequence, event))
+            .wrap_err("failed to serialize authenticated event")?;
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let tag = self
+            .cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: &[],
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| eyre!("failed to authenticate event"))?;
+        let mut nonce_bytes = [0; 24];
+        nonce_bytes.copy_from_slice(&nonce);
+        Ok(EventTag {
+            sender: sender.to_owned(),
+            sequence,
+            nonce: nonce_bytes,
+            tag,
+        })
+    }
+
+    /// Checks that the event was authenticated with the key of the dataflow
+    /// and that it is not a replay of an earlier event of the same sender.
+    pub fn verify(
+        &mut self,
+        tag: Option<&EventTag>,
+        event: &AuthenticatedEvent,
+    ) -> eyre::Result<()> {
+        let Some(tag) = tag else {
+            bail!("rejecting unauthenticated event {event:?}");
+        };
+        let aad = bincode::serialize(&(&tag.sender, tag.sequence, event))
+            .wrap_err("failed to serialize authenticated event")?;
+        let plaintext = self
+            .cipher
+            .decrypt(
+                XNonce::from_slice(&tag.nonce),
+                Payload {
+                    msg: &tag.tag,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| {
+                eyre!(
+                    "rejecting event {event:?}, it was modified or authenticated with another key"
+                )
+            })?;
+        if !plaintext.is_empty() {
+            bail!("rejecting event {event:?} with unexpected payload");
+        }
+        let last = self.received_events.entry(tag.sender.clone()).or_default();
+        if tag.sequence <= *last {
+            bail!(
+                "rejecting replayed event {event:?} of `{}` (sequence number {})",
+                tag.sender,
+                tag.sequence
+            );
+        }
+        *last = tag.sequence;
+        Ok(())
+    }
+}
+
+fn associated_data(
+    dataflow_id: &DataflowId,
+    node_id: &NodeId,
+    output_id: &DataId,
+    sequence: u64,
+) -> eyre::Result<Vec<u8>> {
+    bincode::serialize(&(dataflow_id, node_id, output_id, sequence))
+        .wrap_err("failed to serialize output ID")
+}
+
+#[cfg(test)]
+mod tests {
+    use dora_core::{metadata::ArrowTypeInfoExt, uhlc::HLC};
+    use dora_message::metadata::ArrowTypeInfo;
+
+    use super::*;
+
+    struct Output {
+        dataflow_id: DataflowId,
+        node_id: NodeId,
+        output_id: DataId,
+        sequence: u64,
+        nonce: [u8; 24],
+        ciphertext: Vec<u8>,
+    }
+
+    fn dataflow_id() -> DataflowId {
+        DataflowId::new_v7(uuid::Timestamp::now(uuid::NoContext))
+    }
+
+    fn cipher(key: u8) -> OutputCipher {
+        OutputCipher::new(&EncryptionKey([key; 32]))
+    }
+
+    fn encrypt(cipher: &mut OutputCipher, data: &[u8]) -> Output {
+        let metadata = Metadata::new(
+            HLC::default().new_timestamp(),
+            ArrowTypeInfo::byte_array(data.len()),
+        );
+        let event = cipher
+            .encrypt(
+                dataflow_id(),
+                "camera".to_owned().into(),
+                "image".to_owned().into(),
+                &metadata,
+                Some(data),
+            )
+            .unwrap();
+        let InterDaemonEvent::EncryptedOutput {
+            dataflow_id,
+            node_id,
+            output_id,
+            sequence,
+            nonce,
+            ciphertext,
+        } = event
+        else {
+            panic!("expected an encrypted output");
+        };
+        Output {
+            dataflow_id,
+            node_id,
+            output_id,
+            sequence,
+            nonce,
+            ciphertext,
+        }
+    }
+
+    fn decrypt(cipher: &mut OutputCipher, output: &Output) -> eyre::Result<Option<Vec<u8>>> {
+        let (_, data) = cipher.decrypt(
+            &output.dataflow_id,
+            &output.node_id,
+            &output.output_id,
+            output.sequence,
+            &output.nonce,
+            &output.ciphertext,
+        )?;
+        Ok(data.map(|data| data.to_vec()))
+    }
+
+    #[test]
+    fn roundtrip() {
+        let output = encrypt(&mut cipher(1), b"data");
+        assert_eq!(
+            decrypt(&mut cipher(1), &output).unwrap().as_deref(),
+            Some(&b"data"[..])
+        );
+    }
+
+    #[test]
+    fn rejects_tampered_ciphertext() {
+        let mut output = encrypt(&mut cipher(1), b"data");
+        output.ciphertext[0] ^= 1;
+        assert!(decrypt(&mut cipher(1), &output).is_err());
+    }
+
+    #[test]
+    fn rejects_other_ids() {
+        let changes: [fn(&mut Output); 4] = [
+            |o| o.dataflow_id = dataflow_id(),
+            |o| o.node_id = "lidar".to_owned().into(),
+            |o| o.output_id = "depth".to_owned().into(),
+            |o| o.sequence += 1,
+        ];
+        for change in changes {
+            let mut output = encrypt(&mut cipher(1), b"data");
+            change(&mut output);
+            assert!(decrypt(&mut cipher(1), &output).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_key() {
+        let output = encrypt(&mut cipher(1), b"data");
+        assert!(decrypt(&mut cipher(2), &output).is_err());
+    }
+
+    #[test]
+    fn rejects_replayed_outputs() {
+        let mut sender = cipher(1);
+        let mut receiver = cipher(1);
+        let first = encrypt(&mut sender, b"first");
+        let second = encrypt(&mut sender, b"second");
+
+        assert!(decrypt(&mut receiver, &first).is_ok());
+        assert!(decrypt(&mut receiver, &second).is_ok());
+        assert!(decrypt(&mut receiver, &second).is_err());
+        assert!(decrypt(&mut receiver, &first).is_err());
+    }
+
+    fn camera_image() -> (NodeId, DataId) {
+        ("camera".to_owned().into(), "image".to_owned().into())
+    }
+
+    fn backpressure<'a>(
+        dataflow_id: &'a DataflowId,
+        (node_id, output_id): &'a (NodeId, DataId),
+        level: f32,
+    ) -> AuthenticatedEvent<'a> {
+        AuthenticatedEvent::Backpressure {
+            dataflow_id,
+            node_id,
+            output_id,
+            level,
+        }
+    }
+
+    #[test]
+    fn authenticated_event_roundtrip() {
+        let (dataflow_id, output) = (dataflow_id(), camera_image());
+        let event = backpressure(&dataflow_id, &output, 0.5);
+        let tag = cipher(1).authenticate("machine-a", &event).unwrap();
+
+        assert!(cipher(1).verify(Some(&tag), &event).is_ok());
+    }
+
+    #[test]
+    fn rejects_unauthenticated_events() {
+        let dataflow_id = dataflow_id();
+        let inputs = BTreeSet::from([("detector".to_owned().into(), "image".to_owned().into())]);
+        let event = AuthenticatedEvent::InputsClosed {
+            dataflow_id: &dataflow_id,
+            inputs: &inputs,
+        };
+
+        assert!(cipher(1).verify(None, &event).is_err());
+    }
+
+    #[test]
+    fn rejects_modified_events() {
+        let (dataflow_id, output) = (dataflow_id(), camera_image());
+        let mut sender = cipher(1);
+        let tag = sender
+            .authenticate("machine-a", &backpressure(&dataflow_id, &output, 0.5))
+            .unwrap();
+
+        let other_dataflow = self::dataflow_id();
+        assert!(cipher(1)
+            .verify(Some(&tag), &backpressure(&dataflow_id, &output, 1.0))
+            .is_err());
+        assert!(cipher(1)
+            .verify(Some(&tag), &backpressure(&other_dataflow, &output, 0.5))
+            .is_err());
+        let mut other_sender = tag.clone();
+        other_sender.sender = "machine-b".to_owned();
+        assert!(cipher(1)
+            .verify(
+                Some(&other_sender),
+                &backpressure(&dataflow_id, &output, 0.5)
+            )
+            .is_err());
+        assert!(cipher(2)
+            .verify(Some(&tag), &backpressure(&dataflow_id, &output, 0.5))
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_replayed_events() {
+        let (dataflow_id, output) = (dataflow_id(), camera_image());
+        let event = backpressure(&dataflow_id, &output, 0.5);
+        let mut sender = cipher(1);
+        let mut receiver = cipher(1);
+        let first = sender.authenticate("machine-a", &event).unwrap();
+        let second = sender.authenticate("machine-a", &event).unwrap();
+
+        assert!(receiver.verify(Some(&first), &event).is_ok());
+        assert!(receiver.verify(Some(&second), &event).is_ok());
+        assert!(receiver.verify(Some(&second), &event).is_err());
+        assert!(receiver.verify(Some(&first), &event).is_err());
+    }
+}
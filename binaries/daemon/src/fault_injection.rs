@@ -0,0 +1,328 @@
+//! Injection of message faults and node kills for robustness tests.
+//!
+//! Dataflows that set the `faults` section of their descriptor are subject to
+//! the configured faults: messages are dropped, duplicated, reordered, or
+//! delayed on their way to local receivers, and nodes are killed at the given
+//! times. Each edge has its own random number generator, seeded from the
+//! configured seed and the name of the edge, so the injected faults only
+//! depend on the sequence of messages on the edge.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use dora_core::{
+    config::NodeId,
+    descriptor::{EdgeFaults, FaultConfig, NodeKill},
+};
+use dora_message::{daemon_to_coordinator::Timestamped, daemon_to_node::NodeEvent};
+use tokio::sync::mpsc::error::TrySendError;
+
+use crate::{node_communication::EventSender, InputId, OutputId};
+
+/// Fault that is injected for a single delivery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FaultAction {
+    Deliver,
+    Drop,
+    Duplicate,
+    /// Deliver the message after the next message of the edge.
+    Reorder,
+    Delay(Duration),
+}
+
+pub enum Delivery {
+    /// The message was sent or will be sent later.
+    Delivered,
+    /// The message was dropped on purpose.
+    Dropped,
+    /// The channel of the receiver is closed.
+    Closed,
+}
+
+pub struct FaultInjector {
+    seed: u64,
+    edges: BTreeMap<String, EdgeFaults>,
+    kill: Vec<NodeKill>,
+    /// Faults and random number generator of each edge with faults, `None`
+    /// for edges without faults.
+    edge_states: BTreeMap<(OutputId, InputId), Option<(EdgeFaults, SplitMix64)>>,
+    /// Reordered messages, which are delivered after the next message of
+    /// their edge.
+    held: BTreeMap<(OutputId, InputId), Timestamped<NodeEvent>>,
+}
+
+impl FaultInjector {
+    pub fn new(config: &FaultConfig) -> Self {
+        Self {
+            seed: config.seed,
+            edges: config.edges.clone(),
+            kill: config.kill.clone(),
+            edge_states: BTreeMap::new(),
+            held: BTreeMap::new(),
+        }
+    }
+
+    pub fn kills(&self) -> &[NodeKill] {
+        &self.kill
+    }
+
+    /// Delivers a message over the given edge to the given channel of the
+    /// receiver, unless a fault is injected.
+    pub fn deliver(
+        &mut self,
+        channel: &EventSender,
+        edge: &(OutputId, InputId),
+        event: Timestamped<NodeEvent>,
+        shared_memory: bool,
+    ) -> Delivery {
+        let action = self.decide(&edge.0, &edge.1, shared_memory);
+        let result = match action {
+            FaultAction::Deliver => channel.send(event),
+            FaultAction::Drop => return Delivery::Dropped,
+            FaultAction::Duplicate => channel
+                .send(event.clone())
+                .and_then(|()| channel.send(event)),
+            FaultAction::Reorder => {
+                // the message is delivered after the next message
+                match self.held.insert(edge.clone(), event) {
+                    Some(previous) => channel.send(previous),
+                    None => Ok(()),
+                }
+            }
+            FaultAction::Delay(delay) => {
+                let channel = channel.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    let _ = channel.send(event);
+                });
+                Ok(())
+            }
+        };
+        match result {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => return Delivery::Dropped,
+            Err(TrySendError::Closed(_)) => return Delivery::Closed,
+        }
+        if !matches!(action, FaultAction::Reorder) {
+            if let Some(held) = self.held.remove(edge) {
+                let _ = channel.send(held);
+            }
+        }
+        Delivery::Delivered
+    }
+
+    /// Decides whether a message of an input batch is delivered over the
+    /// given edge.
+    ///
+    /// Batches are delivered as a unit, so their messages are only subject
+    /// to drop faults.
+    pub fn keep_in_batch(&mut self, edge: &(OutputId, InputId), shared_memory: bool) -> bool {
+        self.decide(&edge.0, &edge.1, shared_memory) != FaultAction::Drop
+    }
+
+    /// Decides which fault is injected for the delivery of a message over
+    /// the given edge.
+    fn decide(
+        &mut self,
+        output_id: &OutputId,
+        receiver: &InputId,
+        shared_memory: bool,
+    ) -> FaultAction {
+        let edge = (output_id.clone(), receiver.clone());
+        if !self.edge_states.contains_key(&edge) {
+            let state = self.edge_state(output_id, receiver);
+            self.edge_states.insert(edge.clone(), state);
+        }
+        let Some((faults, rng)) = self.edge_states.get_mut(&edge).and_then(|s| s.as_mut()) else {
+            return FaultAction::Deliver;
+        };
+
+        let mut threshold = 0.0;
+        let roll = rng.next_f64();
+        threshold += faults.drop;
+        if roll < threshold {
+            return FaultAction::Drop;
+        }
+        threshold += faults.duplicate;
+        if roll < threshold {
+            // the receiver would report the drop token twice
+            return if shared_memory {
+                FaultAction::Deliver
+            } else {
+                FaultAction::Duplicate
+            };
+        }
+        threshold += faults.reorder;
+        if roll < threshold {
+            return FaultAction::Reorder;
+        }
+        threshold += faults.delay;
+        if roll < threshold {
+            return FaultAction::Delay(Duration::from_millis(faults.delay_ms));
+        }
+        FaultAction::Deliver
+    }
+
+    fn edge_state(
+        &self,
+        OutputId(node_id, output_id): &OutputId,
+        (receiver_id, input_id): &InputId,
+    ) -> Option<(EdgeFaults, SplitMix64)> {
+        let output = (node_id.as_ref(), output_id.as_ref());
+        let input = (receiver_id.as_ref(), input_id.as_ref());
+        let exact = self
+            .edges
+            .iter()
+            .find(|(key, _)| FaultConfig::parse_edge(key) == Some((output, Some(input))));
+        let all_receivers = || {
+            self.edges
+                .iter()
+                .find(|(key, _)| FaultConfig::parse_edge(key) == Some((output, None)))
+        };
+        let (_, faults) = exact.or_else(all_receivers)?;
+
+        let edge_name = format!("{node_id}/{output_id} -> {receiver_id}/{input_id}");
+        let rng = SplitMix64(self.seed ^ fnv1a(edge_name.as_bytes()));
+        Some((faults.clone(), rng))
+    }
+
+    /// Returns all reordered messages of the given node that are still held
+    /// back, e.g. because the node finished.
+    pub fn take_held_of(
+        &mut self,
+        node_id: &NodeId,
+    ) -> Vec<((OutputId, InputId), Timestamped<NodeEvent>)> {
+        let edges: Vec<_> = self
+            .held
+            .keys()
+            .filter(|(OutputId(source, _), _)| source == node_id)
+            .cloned()
+            .collect();
+        edges
+            .into_iter()
+            .filter_map(|edge| self.held.remove(&edge).map(|event| (edge, event)))
+            .collect()
+    }
+}
+
+/// Small deterministic random number generator, see
+/// <https://prng.di.unimi.it/splitmix64.c>.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniformly distributed number in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Stable hash of the edge name, so that the seeds don't change between
+/// runs or Rust versions.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn injector(seed: u64, edges: &str) -> FaultInjector {
+        let mut config: FaultConfig = serde_yaml::from_str(edges).unwrap();
+        config.seed = seed;
+        FaultInjector::new(&config)
+    }
+
+    fn edge(output: &str, input: &str) -> (OutputId, InputId) {
+        let (node, output) = output.split_once('/').unwrap();
+        let (receiver, input) = input.split_once('/').unwrap();
+        (
+            OutputId(node.to_owned().into(), output.to_owned().into()),
+            (receiver.to_owned().into(), input.to_owned().into()),
+        )
+    }
+
+    fn decisions(injector: &mut FaultInjector, edge: &(OutputId, InputId)) -> Vec<FaultAction> {
+        (0..200)
+            .map(|_| injector.decide(&edge.0, &edge.1, false))
+            .collect()
+    }
+
+    const FAULTS: &str = "
+edges:
+  camera/image: { drop: 0.2, duplicate: 0.2, reorder: 0.2, delay: 0.2, delay_ms: 5 }
+";
+
+    #[test]
+    fn same_seed_gives_same_faults() {
+        let edge = edge("camera/image", "detector/image");
+        let first = decisions(&mut injector(7, FAULTS), &edge);
+        assert_eq!(first, decisions(&mut injector(7, FAULTS), &edge));
+        assert_ne!(first, decisions(&mut injector(8, FAULTS), &edge));
+        for action in [
+            FaultAction::Deliver,
+            FaultAction::Drop,
+            FaultAction::Duplicate,
+            FaultAction::Reorder,
+            FaultAction::Delay(Duration::from_millis(5)),
+        ] {
+            assert!(first.contains(&action), "{action:?} was never chosen");
+        }
+    }
+
+    #[test]
+    fn edges_have_independent_streams() {
+        let detector = edge("camera/image", "detector/image");
+        let logger = edge("camera/image", "logger/image");
+
+        let mut injector_a = injector(7, FAULTS);
+        let detector_alone = decisions(&mut injector_a, &detector);
+
+        // interleaving the decisions of another edge doesn't change the
+        // faults of the edge
+        let mut injector_b = injector(7, FAULTS);
+        let mut detector_interleaved = Vec::new();
+        let mut logger_interleaved = Vec::new();
+        for _ in 0..200 {
+            logger_interleaved.push(injector_b.decide(&logger.0, &logger.1, false));
+            detector_interleaved.push(injector_b.decide(&detector.0, &detector.1, false));
+        }
+        assert_eq!(detector_alone, detector_interleaved);
+        assert_ne!(detector_interleaved, logger_interleaved);
+    }
+
+    #[test]
+    fn zero_probability_never_fires() {
+        let mut injector = injector(7, "edges: { camera/image: { drop: 0.0 } }");
+        let edge = edge("camera/image", "detector/image");
+        assert!(decisions(&mut injector, &edge)
+            .iter()
+            .all(|action| *action == FaultAction::Deliver));
+    }
+
+    #[test]
+    fn full_probability_always_fires() {
+        let mut injector = injector(7, "edges: { camera/image: { drop: 1.0 } }");
+        let edge = edge("camera/image", "detector/image");
+        assert!(decisions(&mut injector, &edge)
+            .iter()
+            .all(|action| *action == FaultAction::Drop));
+    }
+
+    #[test]
+    fn edges_without_faults_deliver() {
+        let mut injector = injector(7, "edges: { camera/image: { drop: 1.0 } }");
+        let edge = edge("lidar/points", "detector/points");
+        assert!(decisions(&mut injector, &edge)
+            .iter()
+            .all(|action| *action == FaultAction::Deliver));
+    }
+}
@@ -0,0 +1,288 @@
+//! Bounded record of the recent daemon events of a dataflow.
+//!
+//! Failures that only happen intermittently are hard to reconstruct from the
+//! logs alone. Each running dataflow therefore keeps the most recent events
+//! that the daemon handled for it, e.g. coordinator requests, node messages,
+//! and drop token transitions. When a node fails, the recorded events are
+//! written to `out/<dataflow_id>/flight_recorder_<node_id>.txt` in the
+//! working directory and the file is referenced in the node error.
+
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use dora_core::{config::NodeId, uhlc};
+use dora_message::{
+    coordinator_to_daemon::DaemonCoordinatorEvent, daemon_to_daemon::InterDaemonEvent,
+};
+use eyre::Context;
+use uuid::Uuid;
+
+use crate::{DaemonNodeEvent, Footprint};
+
+/// Maximum number of events that are kept per dataflow.
+const CAPACITY: usize = 1000;
+/// Maximum number of events that are kept per dataflow by a daemon with a
+/// [`Footprint::Minimal`].
+const MINIMAL_CAPACITY: usize = 100;
+
+pub struct FlightRecorder {
+    entries: VecDeque<(uhlc::Timestamp, String)>,
+    capacity: usize,
+    /// Number of events that were evicted because the recorder was full.
+    evicted: u64,
+}
+
+impl FlightRecorder {
+    pub fn new(footprint: Footprint) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity: match footprint {
+                Footprint::Default => CAPACITY,
+                Footprint::Minimal => MINIMAL_CAPACITY,
+            },
+            evicted: 0,
+        }
+    }
+
+    pub fn record(&mut self, timestamp: uhlc::Timestamp, event: impl Into<String>) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+            self.evicted += 1;
+        }
+        self.entries.push_back((timestamp, event.into()));
+    }
+
+    /// Writes the recorded events to the flight recorder file of the given
+    /// node and returns the path of the file.
+    pub async fn dump(
+        &self,
+        working_dir: &Path,
+        dataflow_id: &Uuid,
+        node_id: &NodeId,
+    ) -> eyre::Result<PathBuf> {
+        let path = dump_path(working_dir, dataflow_id, node_id);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .context("could not create dataflow_dir")?;
+        }
+
+        let mut text = String::new();
+        if self.evicted > 0 {
+            writeln!(text, "[{} earlier events omitted]", self.evicted)?;
+        }
+        for (timestamp, event) in &self.entries {
+            writeln!(text, "{timestamp} {event}")?;
+        }
+        tokio::fs::write(&path, text)
+            .await
+            .wrap_err_with(|| format!("failed to write flight recorder `{}`", path.display()))?;
+        Ok(path)
+    }
+}
+
+fn dump_path(working_dir: &Path, dataflow_id: &Uuid, node_id: &NodeId) -> PathBuf {
+    let dataflow_dir = working_dir.join("out").join(dataflow_id.to_string());
+    dataflow_dir.join(format!("flight_recorder_{node_id}.txt"))
+}
+
+/// Short description of a coordinator event, if it belongs to a dataflow.
+pub fn describe_coordinator_event(event: &DaemonCoordinatorEvent) -> Option<(Uuid, String)> {
+    match event {
+        DaemonCoordinatorEvent::Spawn(spawn) => Some((
+            spawn.dataflow_id,
+            format!(
+                "coordinator: spawn {} nodes{}",
+                spawn.nodes.len(),
+                if spawn.adopt { " (adopt)" } else { "" }
+            ),
+        )),
+        DaemonCoordinatorEvent::AllNodesReady {
+            dataflow_id,
+            exited_before_subscribe,
+        } => Some((
+            *dataflow_id,
+            format!("coordinator: all nodes ready (exited before subscribe: {exited_before_subscribe:?})"),
+        )),
+        DaemonCoordinatorEvent::StopDataflow {
+            dataflow_id,
+            grace_duration,
+        } => Some((
+            *dataflow_id,
+            format!("coordinator: stop dataflow (grace duration: {grace_duration:?})"),
+        )),
+        DaemonCoordinatorEvent::ReloadDataflow {
+            dataflow_id,
+            node_id,
+            operator_id,
+        } => Some((
+            *dataflow_id,
+            format!("coordinator: reload `{node_id}` (operator: {operator_id:?})"),
+        )),
+        DaemonCoordinatorEvent::StartRecording {
+            dataflow_id,
+            recording_id,
+            options,
+        } => Some((
+            *dataflow_id,
+            format!(
+                "coordinator: start recording `{recording_id}` (include: {:?}, exclude: {:?})",
+                options.include, options.exclude
+            ),
+        )),
+        DaemonCoordinatorEvent::StopRecording { dataflow_id } => {
+            Some((*dataflow_id, "coordinator: stop recording".to_owned()))
+        }
+        DaemonCoordinatorEvent::Checkpoint {
+            dataflow_id,
+            checkpoint_id,
+        } => Some((
+            *dataflow_id,
+            format!("coordinator: checkpoint `{checkpoint_id}`"),
+        )),
+        DaemonCoordinatorEvent::ControlReplay {
+            dataflow_id,
+            control,
+        } => Some((
+            *dataflow_id,
+            format!("coordinator: replay control {control:?}"),
+        )),
+        DaemonCoordinatorEvent::BlackboardUpdate {
+            dataflow_id, key, ..
+        } => Some((
+            *dataflow_id,
+            format!("coordinator: blackboard update `{key}`"),
+        )),
+        DaemonCoordinatorEvent::LeaderElected {
+            dataflow_id,
+            group,
+            leader,
+        } => Some((
+            *dataflow_id,
+            format!("coordinator: leader of `{group}` is {leader:?}"),
+        )),
+        DaemonCoordinatorEvent::Logs { .. }
+        | DaemonCoordinatorEvent::EdgeLatencies { .. }
+        | DaemonCoordinatorEvent::LiveGraph { .. }
+        | DaemonCoordinatorEvent::DumpState
+        | DaemonCoordinatorEvent::CrashReports { .. }
+        | DaemonCoordinatorEvent::ArtifactStatus { .. }
+        | DaemonCoordinatorEvent::UploadArtifact { .. }
+        | DaemonCoordinatorEvent::Destroy
+        | DaemonCoordinatorEvent::Heartbeat => None,
+    }
+}
+
+/// Short description of a node event, without the message data.
+pub fn describe_node_event(node_id: &NodeId, event: &DaemonNodeEvent) -> String {
+    let description = match event {
+        DaemonNodeEvent::OutputsDone { .. } => "outputs done".to_owned(),
+        DaemonNodeEvent::Subscribe { .. } => "subscribe".to_owned(),
+        DaemonNodeEvent::SubscribeDrop { .. } => "subscribe drop".to_owned(),
+        DaemonNodeEvent::CloseOutputs { outputs, .. } => format!("close outputs {outputs:?}"),
+        DaemonNodeEvent::SendOut {
+            output_id, data, ..
+        } => format!(
+            "send out `{output_id}`{}",
+            data.as_ref()
+                .and_then(|d| d.drop_token())
+                .map(|token| format!(" (drop token {token:?})"))
+                .unwrap_or_default()
+        ),
+        DaemonNodeEvent::SendOutMultiple { messages, .. } => {
+            format!("send out {} messages", messages.len())
+        }
+        DaemonNodeEvent::ReportDrop { tokens } => format!("report drop {tokens:?}"),
+        DaemonNodeEvent::ReportBackpressure {
+            input_id,
+            level,
+            dropped,
+        } => {
+            format!("report backpressure on `{input_id}` (level {level:.2}, {dropped} dropped)")
+        }
+        DaemonNodeEvent::ReportDroppedInputs { counts } => {
+            format!("report dropped inputs {counts:?}")
+        }
+        DaemonNodeEvent::EventStreamDropped { .. } => "event stream dropped".to_owned(),
+        DaemonNodeEvent::StopDataflow { reason, .. } => format!("stop dataflow: {reason}"),
+        DaemonNodeEvent::ReportHealth { health, .. } => {
+            format!("report health {:?}", health.status)
+        }
+        DaemonNodeEvent::SaveState { state, .. } => format!("save state ({} bytes)", state.len()),
+        DaemonNodeEvent::RestoredState { .. } => "request restored state".to_owned(),
+        DaemonNodeEvent::BlackboardGet { key, .. } => format!("blackboard get `{key}`"),
+        DaemonNodeEvent::BlackboardSet {
+            key,
+            value: Some(value),
+            ..
+        } => format!("blackboard set `{key}` ({} bytes)", value.len()),
+        DaemonNodeEvent::BlackboardSet {
+            key, value: None, ..
+        } => format!("blackboard remove `{key}`"),
+        DaemonNodeEvent::BlackboardWatch { prefix, .. } => {
+            format!("blackboard watch `{prefix}`")
+        }
+        DaemonNodeEvent::Campaign { group, .. } => format!("campaign in `{group}`"),
+        DaemonNodeEvent::Resign { group, .. } => format!("resign from `{group}`"),
+        DaemonNodeEvent::SubscribeNodeOutput { nodes, .. } if nodes.is_empty() => {
+            "subscribe to output of all nodes".to_owned()
+        }
+        DaemonNodeEvent::SubscribeNodeOutput { nodes, .. } => {
+            format!("subscribe to output of {nodes:?}")
+        }
+        DaemonNodeEvent::Log { level, .. } => format!("log ({})", level.as_str()),
+    };
+    format!("node `{node_id}`: {description}")
+}
+
+/// Short description of an event sent by a remote daemon.
+pub fn describe_inter_daemon_event(event: &InterDaemonEvent) -> (Uuid, String) {
+    match event {
+        InterDaemonEvent::Output {
+            dataflow_id,
+            node_id,
+            output_id,
+            ..
+        } => (
+            *dataflow_id,
+            format!("remote daemon: output `{node_id}/{output_id}`"),
+        ),
+        InterDaemonEvent::EncryptedOutput {
+            dataflow_id,
+            node_id,
+            output_id,
+            ..
+        } => (
+            *dataflow_id,
+            format!("remote daemon: encrypted output `{node_id}/{output_id}`"),
+        ),
+        InterDaemonEvent::OutputBatch {
+            dataflow_id,
+            outputs,
+        } => (
+            *dataflow_id,
+            format!("remote daemon: batch of {} outputs", outputs.len()),
+        ),
+        InterDaemonEvent::InputsClosed {
+            dataflow_id,
+            inputs,
+            ..
+        } => (
+            *dataflow_id,
+            format!("remote daemon: inputs closed {inputs:?}"),
+        ),
+        InterDaemonEvent::Backpressure {
+            dataflow_id,
+            node_id,
+            output_id,
+            level,
+            ..
+        } => (
+            *dataflow_id,
+            format!("remote daemon: backpressure on `{node_id}/{output_id}` (level {level:.2})"),
+        ),
+    }
+}
@@ -36,6 +36,13 @@ impl InterDaemonConnection {
     pub fn socket(&self) -> SocketAddr {
         self.socket
     }
+
+    /// Whether a connection to the other daemon is currently open.
+    ///
+    /// Connections are only opened when the first event is sent.
+    pub fn is_connected(&self) -> bool {
+        self.connection.is_some()
+    }
 }
 
 #[tracing::instrument(skip(inter_daemon_connections))]
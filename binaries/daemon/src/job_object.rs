@@ -0,0 +1,202 @@
+//! Windows job objects, which group a node process with its child processes.
+//!
+//! This is the Windows equivalent of the process groups that nodes are
+//! spawned in on Unix: killing the job kills the whole process tree of the
+//! node, and the tree is also killed when the job is dropped. Jobs also
+//! enforce the `resources` limits of the nodes.
+
+use dora_core::descriptor::ResourceLimits;
+use eyre::bail;
+use windows_sys::Win32::{
+    Foundation::{CloseHandle, HANDLE, INVALID_HANDLE_VALUE},
+    System::{
+        Diagnostics::ToolHelp::{
+            CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+        },
+        JobObjects::{
+            AssignProcessToJobObject, CreateJobObjectW, JobObjectCpuRateControlInformation,
+            JobObjectExtendedLimitInformation, QueryInformationJobObject, SetInformationJobObject,
+            TerminateJobObject, JOBOBJECTINFOCLASS, JOBOBJECT_CPU_RATE_CONTROL_INFORMATION,
+            JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JOB_OBJECT_CPU_RATE_CONTROL_ENABLE,
+            JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP, JOB_OBJECT_LIMIT_JOB_MEMORY,
+            JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+        },
+        Threading::{
+            OpenProcess, OpenThread, ResumeThread, PROCESS_SET_QUOTA, PROCESS_TERMINATE,
+            THREAD_SUSPEND_RESUME,
+        },
+    },
+};
+
+/// Exit code of the processes that are killed through their job.
+///
+/// Matches the exit code that `TerminateProcess` is called with by the
+/// standard library.
+const KILLED_EXIT_CODE: u32 = 1;
+
+#[derive(Debug)]
+pub struct JobObject(HANDLE);
+
+// SAFETY: job object handles can be used from any thread
+unsafe impl Send for JobObject {}
+unsafe impl Sync for JobObject {}
+
+impl JobObject {
+    /// Creates a job object and assigns the process with the given ID to it.
+    ///
+    /// Child processes that the node spawns afterwards are part of the job
+    /// too. The process should be spawned with `CREATE_SUSPENDED` and only be
+    /// resumed through [`resume_process`] after this, so that it can't spawn
+    /// child processes outside of the job.
+    pub fn assign(pid: u32) -> eyre::Result<Self> {
+        // SAFETY: null pointers are allowed for the attributes and the name
+        let handle = unsafe { CreateJobObjectW(std::ptr::null(), std::ptr::null()) };
+        if handle.is_null() {
+            bail!(
+                "failed to create job object: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+        let job = Self(handle);
+        job.set_memory_limit(None)?;
+
+        // SAFETY: `OpenProcess` has no preconditions
+        let process = unsafe { OpenProcess(PROCESS_SET_QUOTA | PROCESS_TERMINATE, 0, pid) };
+        if process.is_null() {
+            bail!(
+                "failed to open process {pid}: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+        // SAFETY: both handles are valid, the process handle is closed afterwards
+        let ok = unsafe { AssignProcessToJobObject(job.0, process) };
+        let err = std::io::Error::last_os_error();
+        unsafe { CloseHandle(process) };
+        if ok == 0 {
+            bail!("failed to assign process {pid} to job object: {err}");
+        }
+        Ok(job)
+    }
+
+    /// Limits the memory and CPU usage of the processes of the job.
+    ///
+    /// Allocations beyond the memory limit fail. The CPU usage is capped
+    /// relative to the number of CPUs of the machine.
+    pub fn set_limits(&self, limits: &ResourceLimits) -> eyre::Result<()> {
+        if let Some(memory_mb) = limits.memory_mb {
+            self.set_memory_limit(Some(memory_mb * 1024 * 1024))?;
+        }
+        if let Some(cpus) = limits.cpus {
+            let available = std::thread::available_parallelism().map_or(1, |n| n.get());
+            // in 1/100 of a percent of the CPU time of the whole machine
+            let rate = ((cpus / available as f64 * 10_000.0) as u32).clamp(1, 10_000);
+            // SAFETY: the struct is plain data, for which zero is a valid value
+            let mut info: JOBOBJECT_CPU_RATE_CONTROL_INFORMATION = unsafe { std::mem::zeroed() };
+            info.ControlFlags =
+                JOB_OBJECT_CPU_RATE_CONTROL_ENABLE | JOB_OBJECT_CPU_RATE_CONTROL_HARD_CAP;
+            info.Anonymous.CpuRate = rate;
+            self.set_information(JobObjectCpuRateControlInformation, &info)
+                .map_err(|err| eyre::eyre!("failed to set CPU limit of job object: {err}"))?;
+        }
+        Ok(())
+    }
+
+    /// Whether the processes of the job reached the memory limit of the job
+    /// at some point.
+    pub fn memory_limit_reached(&self) -> bool {
+        // SAFETY: the struct is plain data, for which zero is a valid value
+        let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = unsafe { std::mem::zeroed() };
+        // SAFETY: `info` is valid for the given size
+        let ok = unsafe {
+            QueryInformationJobObject(
+                self.0,
+                JobObjectExtendedLimitInformation,
+                &mut info as *mut _ as *mut _,
+                std::mem::size_of_val(&info) as u32,
+                std::ptr::null_mut(),
+            )
+        };
+        ok != 0
+            && info.BasicLimitInformation.LimitFlags & JOB_OBJECT_LIMIT_JOB_MEMORY != 0
+            && info.PeakJobMemoryUsed >= info.JobMemoryLimit
+    }
+
+    fn set_memory_limit(&self, limit_bytes: Option<u64>) -> eyre::Result<()> {
+        // SAFETY: the struct is plain data, for which zero is a valid value
+        let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = unsafe { std::mem::zeroed() };
+        info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+        if let Some(limit) = limit_bytes {
+            info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_JOB_MEMORY;
+            info.JobMemoryLimit = limit as usize;
+        }
+        self.set_information(JobObjectExtendedLimitInformation, &info)
+            .map_err(|err| eyre::eyre!("failed to configure job object: {err}"))
+    }
+
+    fn set_information<T>(&self, class: JOBOBJECTINFOCLASS, info: &T) -> std::io::Result<()> {
+        // SAFETY: `info` is valid for the given size
+        let ok = unsafe {
+            SetInformationJobObject(
+                self.0,
+                class,
+                info as *const T as *const _,
+                std::mem::size_of::<T>() as u32,
+            )
+        };
+        if ok == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Kills all processes of the job.
+    pub fn kill(&self) -> bool {
+        // SAFETY: the handle is valid until drop
+        unsafe { TerminateJobObject(self.0, KILLED_EXIT_CODE) != 0 }
+    }
+}
+
+/// Resumes the threads of a process that was spawned with `CREATE_SUSPENDED`.
+pub fn resume_process(pid: u32) -> eyre::Result<()> {
+    // SAFETY: `CreateToolhelp32Snapshot` has no preconditions
+    let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0) };
+    if snapshot == INVALID_HANDLE_VALUE {
+        bail!(
+            "failed to list threads of process {pid}: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    // SAFETY: the struct is plain data, for which zero is a valid value
+    let mut entry: THREADENTRY32 = unsafe { std::mem::zeroed() };
+    entry.dwSize = std::mem::size_of::<THREADENTRY32>() as u32;
+    let mut result = Ok(());
+    // SAFETY: the snapshot handle is valid and `entry` has the right size
+    let mut found = unsafe { Thread32First(snapshot, &mut entry) } != 0;
+    while found {
+        if entry.th32OwnerProcessID == pid {
+            // SAFETY: `OpenThread` has no preconditions
+            let thread = unsafe { OpenThread(THREAD_SUSPEND_RESUME, 0, entry.th32ThreadID) };
+            if thread.is_null() {
+                result = Err(std::io::Error::last_os_error());
+            } else {
+                // SAFETY: the thread handle is valid, it is closed afterwards
+                if unsafe { ResumeThread(thread) } == u32::MAX {
+                    result = Err(std::io::Error::last_os_error());
+                }
+                unsafe { CloseHandle(thread) };
+            }
+        }
+        // SAFETY: see `Thread32First`
+        found = unsafe { Thread32Next(snapshot, &mut entry) } != 0;
+    }
+    // SAFETY: the handle is valid and not used afterwards
+    unsafe { CloseHandle(snapshot) };
+    result.map_err(|err| eyre::eyre!("failed to resume process {pid}: {err}"))
+}
+
+impl Drop for JobObject {
+    fn drop(&mut self) {
+        // SAFETY: the handle is valid and not used afterwards
+        unsafe { CloseHandle(self.0) };
+    }
+}
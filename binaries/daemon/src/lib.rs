@@ -4,27 +4,35 @@ use crossbeam::queue::ArrayQueue;
 use dora_core::{
     config::{DataId, Input, InputMapping, NodeId, NodeRunConfig, OperatorId},
     descriptor::{
-        read_as_descriptor, CoreNodeKind, Descriptor, DescriptorExt, ResolvedNode, RuntimeNode,
-        DYNAMIC_SOURCE,
+        read_as_descriptor, signing::SignaturePolicy, CoreNodeKind, Descriptor, DescriptorExt,
+        EventQueueConfig, ResolvedNode, RuntimeNode, StopSignal, TimeMode, DYNAMIC_SOURCE,
     },
     topics::LOCALHOST,
     uhlc::{self, HLC},
 };
 use dora_message::{
-    common::{DataMessage, DropToken, LogLevel, NodeError, NodeErrorCause, NodeExitStatus},
+    common::{
+        CrashReport, DaemonLiveGraph, DataMessage, DropToken, EdgeLatency, EdgeStats, HealthStatus,
+        LatencyHistogram, LogLevel, NodeError, NodeErrorCause, NodeExitStatus, NodeHealth,
+        NodeState, NodeToken, QueueMetrics,
+    },
     coordinator_to_cli::DataflowResult,
-    coordinator_to_daemon::{DaemonCoordinatorEvent, SpawnDataflowNodes},
+    coordinator_to_daemon::{
+        DaemonCoordinatorEvent, EncryptionKey, NodeListener, RecordingOptions, SpawnDataflowNodes,
+    },
     daemon_to_coordinator::{
         CoordinatorRequest, DaemonCoordinatorReply, DaemonEvent, DataflowDaemonResult, LogMessage,
     },
     daemon_to_daemon::InterDaemonEvent,
-    daemon_to_node::{DaemonReply, NodeConfig, NodeDropEvent, NodeEvent},
+    daemon_to_node::{BatchedInput, DaemonReply, NodeConfig, NodeDropEvent, NodeEvent},
     metadata::{self, ArrowTypeInfo},
-    node_to_daemon::{DynamicNodeEvent, Timestamped},
+    node_to_daemon::{DynamicNodeEvent, OutputMessage, Timestamped, DYNAMIC_NODE_TOKEN_ENV},
     DataflowId,
 };
 use dora_node_api::{arrow::datatypes::DataType, Parameter};
+use edge_rate::EdgeRate;
 use eyre::{bail, eyre, Context, ContextCompat, Result};
+use fault_injection::Delivery;
 use futures::{future, stream, FutureExt, TryFutureExt};
 use futures_concurrency::stream::Merge;
 use inter_daemon::InterDaemonConnection;
@@ -37,40 +45,90 @@ use std::{
     net::SocketAddr,
     path::{Path, PathBuf},
     pin::pin,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 use sysinfo::Pid;
 use tokio::{
-    fs::File,
-    io::AsyncReadExt,
     net::TcpStream,
     sync::{
-        mpsc::{self, UnboundedSender},
+        mpsc::{self, error::TrySendError, UnboundedSender},
         oneshot::{self, Sender},
+        Notify,
     },
 };
 use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
-use tracing::{error, warn};
+use trace_sampling::{Hop, TraceSampling};
+use tracing::{error, level_filters::LevelFilter, warn};
 use uuid::{NoContext, Timestamp, Uuid};
 
+mod activate;
+mod artifacts;
+mod cgroup;
+mod checkpoint;
+mod container;
 mod coordinator;
+mod crash_report;
+#[cfg(feature = "state-dump")]
+mod dump;
+mod edge_rate;
+mod election;
+mod encryption;
+mod fault_injection;
+mod flight_recorder;
 mod inter_daemon;
+#[cfg(windows)]
+mod job_object;
 mod local_listener;
 mod log;
 mod node_communication;
+mod numa;
 mod pending;
+mod queue_metrics;
+mod recording;
+mod replay;
+mod resource_limits;
+mod restart;
+mod sandbox;
+mod scheduling;
+mod signal;
+mod sim_time;
+mod slow_consumer;
 mod socket_stream_utils;
 mod spawn;
+mod status;
+mod timer_throttle;
+mod trace_sampling;
+mod user;
 
 #[cfg(feature = "telemetry")]
-use dora_tracing::telemetry::serialize_context;
+use dora_tracing::telemetry::{deserialize_context, serialize_context};
 #[cfg(feature = "telemetry")]
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
-use crate::pending::DataflowStatus;
+use crate::{
+    encryption::AuthenticatedEvent,
+    node_communication::{queue_is_full, waits_for, EventSender},
+    pending::DataflowStatus,
+};
 
 const STDERR_LOG_LINES: usize = 10;
+/// Time that nodes get to exit after the dataflow was stopped, unless
+/// configured otherwise.
+const DEFAULT_GRACE_DURATION: Duration = Duration::from_secs(15);
+/// Time between the `SIGTERM` to a node that exceeded its grace duration and
+/// killing it.
+const TERMINATE_TIMEOUT: Duration = Duration::from_secs(2);
+/// Minimum interval between two backpressure events for the same output.
+const BACKPRESSURE_NOTIFY_INTERVAL: Duration = Duration::from_millis(100);
+/// Inter-daemon sends that take longer than this are reported as backpressure
+/// to the sending node.
+const SLOW_INTER_DAEMON_SEND: Duration = Duration::from_millis(20);
+/// Inter-daemon send duration that corresponds to the maximum backpressure level.
+const SATURATED_INTER_DAEMON_SEND: Duration = Duration::from_millis(200);
 
 pub struct Daemon {
     running: HashMap<DataflowId, RunningDataflow>,
@@ -87,11 +145,37 @@ pub struct Daemon {
     exit_when_done: Option<BTreeSet<(Uuid, NodeId)>>,
     /// used to record dataflow results when `exit_when_done` is used
     dataflow_node_results: BTreeMap<Uuid, BTreeMap<NodeId, Result<(), NodeError>>>,
+    /// Nodes that requested their dataflow to stop, with the reason they gave.
+    dataflow_stop_requests: BTreeMap<Uuid, BTreeMap<NodeId, String>>,
+    /// Plain `log_level`s of the local nodes, by dataflow and node ID.
+    node_log_levels: BTreeMap<(String, String), LevelFilter>,
+    /// Signature checks of the dataflows spawned by the coordinator.
+    signature_policy: Option<SignaturePolicy>,
+    /// Token that dynamic nodes must present to get their config, see
+    /// [`DYNAMIC_NODE_TOKEN_ENV`].
+    dynamic_node_token: Option<NodeToken>,
+    footprint: Footprint,
 
     clock: Arc<uhlc::HLC>,
 }
 
-type DaemonRunResult = BTreeMap<Uuid, BTreeMap<NodeId, Result<(), NodeError>>>;
+/// Memory footprint of the daemon, chosen by the executable that runs it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Footprint {
+    /// Daemon of `dora daemon`, which also runs operators and WASM nodes.
+    #[default]
+    Default,
+    /// Daemon of the standalone `dora-daemon` executable for embedded targets.
+    ///
+    /// Internal buffers are kept small, and operators and WASM nodes are run
+    /// through the `dora` executable in `PATH`.
+    Minimal,
+}
+
+struct DaemonRunResult {
+    node_results: BTreeMap<Uuid, BTreeMap<NodeId, Result<(), NodeError>>>,
+    stop_requests: BTreeMap<Uuid, BTreeMap<NodeId, String>>,
+}
 
 impl Daemon {
     pub async fn run(
@@ -99,6 +183,8 @@ impl Daemon {
         machine_id: String,
         inter_daemon_addr: SocketAddr,
         local_listen_port: u16,
+        signature_policy: Option<SignaturePolicy>,
+        footprint: Footprint,
     ) -> eyre::Result<()> {
         let clock = Arc::new(HLC::default());
 
@@ -119,7 +205,7 @@ impl Daemon {
                     tracing::info!("received ctrl-c signal -> stopping daemon");
                     return Ok(());
                 }
-                future::Either::Right((events, _)) => events?,
+                future::Either::Right((events, _)) => events?.0,
             }
         };
         Self::run_general(
@@ -127,6 +213,47 @@ impl Daemon {
             Some(coordinator_addr),
             machine_id,
             None,
+            signature_policy,
+            NodeToken::from_env(DYNAMIC_NODE_TOKEN_ENV),
+            footprint,
+            clock,
+        )
+        .await
+        .map(|_| ())
+    }
+
+    /// Like [`Self::run`], but without setting up a ctrl-c handler, e.g. for
+    /// running the daemon inside of a test process.
+    ///
+    /// The port of the local listener for dynamic nodes is sent through
+    /// `listen_port` once the daemon is registered at the coordinator.
+    /// Dynamic nodes must present the given `dynamic_node_token`.
+    pub async fn run_in_process(
+        coordinator_addr: SocketAddr,
+        machine_id: String,
+        inter_daemon_addr: SocketAddr,
+        local_listen_port: u16,
+        listen_port: oneshot::Sender<u16>,
+        dynamic_node_token: Option<NodeToken>,
+    ) -> eyre::Result<()> {
+        let clock = Arc::new(HLC::default());
+        let (incoming_events, port) = set_up_event_stream(
+            coordinator_addr,
+            &machine_id,
+            inter_daemon_addr,
+            local_listen_port,
+            &clock,
+        )
+        .await?;
+        let _ = listen_port.send(port);
+        Self::run_general(
+            incoming_events,
+            Some(coordinator_addr),
+            machine_id,
+            None,
+            None,
+            dynamic_node_token,
+            Footprint::Default,
             clock,
         )
         .await
@@ -142,10 +269,110 @@ impl Daemon {
             .to_owned();
 
         let descriptor = read_as_descriptor(dataflow_path).await?;
+        let clock = Arc::new(HLC::default());
+        let ctrlc_events = ReceiverStream::new(set_up_ctrlc_handler(clock.clone())?);
+        let dataflow_id = Uuid::new_v7(Timestamp::now(NoContext));
+        let (spawned, _) = oneshot::channel();
+        Self::run_local_dataflow(
+            descriptor,
+            working_dir,
+            uv,
+            dataflow_id,
+            ctrlc_events,
+            None,
+            clock,
+            spawned,
+        )
+        .await
+    }
+
+    /// Runs the given dataflow without a coordinator and without setting up a
+    /// ctrl-c handler, e.g. inside of a test process.
+    ///
+    /// Dynamic nodes of the dataflow connect through a local listener on a
+    /// free port, which is sent through `listen_port` once the nodes are
+    /// spawned. They must present the given `dynamic_node_token`. The
+    /// dataflow is stopped when `stop` resolves.
+    pub async fn run_dataflow_in_process(
+        descriptor: Descriptor,
+        working_dir: PathBuf,
+        listen_port: oneshot::Sender<u16>,
+        dynamic_node_token: Option<NodeToken>,
+        stop: impl std::future::Future<Output = ()> + Send + 'static,
+    ) -> eyre::Result<DataflowResult> {
+        let clock = Arc::new(HLC::default());
+        let dataflow_id = Uuid::new_v7(Timestamp::now(NoContext));
+
+        let (events_tx, events_rx) = flume::bounded(10);
+        let port = local_listener::spawn_listener_loop(
+            (LOCALHOST, 0).into(),
+            false,
+            String::new(),
+            events_tx,
+        )
+        .await?;
+        let dynamic_node_events = events_rx.into_stream().map(|e| Timestamped {
+            inner: Event::DynamicNode(e.inner),
+            timestamp: e.timestamp,
+        });
+
+        let (stop_tx, stop_rx) = mpsc::channel(1);
+        let stop_clock = clock.clone();
+        tokio::spawn(async move {
+            stop.await;
+            let (reply_tx, reply_rx) = oneshot::channel();
+            let event = Timestamped {
+                inner: Event::Coordinator(CoordinatorEvent {
+                    event: DaemonCoordinatorEvent::StopDataflow {
+                        dataflow_id,
+                        grace_duration: None,
+                    },
+                    reply_tx,
+                }),
+                timestamp: stop_clock.new_timestamp(),
+            };
+            if stop_tx.send(event).await.is_ok() {
+                let _ = reply_rx.await;
+            }
+        });
+
+        let (spawned_tx, spawned_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            if spawned_rx.await.is_ok() {
+                let _ = listen_port.send(port);
+            }
+        });
+        Self::run_local_dataflow(
+            descriptor,
+            working_dir,
+            false,
+            dataflow_id,
+            (dynamic_node_events, ReceiverStream::new(stop_rx)).merge(),
+            dynamic_node_token,
+            clock,
+            spawned_tx,
+        )
+        .await
+    }
+
+    /// Spawns the given dataflow and runs it to completion, without a
+    /// coordinator.
+    ///
+    /// `spawned` is notified once the nodes of the dataflow were spawned.
+    #[allow(clippy::too_many_arguments)]
+    async fn run_local_dataflow(
+        descriptor: Descriptor,
+        working_dir: PathBuf,
+        uv: bool,
+        dataflow_id: DataflowId,
+        external_events: impl Stream<Item = Timestamped<Event>> + Unpin,
+        dynamic_node_token: Option<NodeToken>,
+        clock: Arc<HLC>,
+        spawned: oneshot::Sender<()>,
+    ) -> eyre::Result<DataflowResult> {
         descriptor.check(&working_dir)?;
         let nodes = descriptor.resolve_aliases_and_set_defaults()?;
 
-        let dataflow_id = Uuid::new_v7(Timestamp::now(NoContext));
         let spawn_command = SpawnDataflowNodes {
             dataflow_id,
             working_dir,
@@ -153,15 +380,19 @@ impl Daemon {
             machine_listen_ports: BTreeMap::new(),
             dataflow_descriptor: descriptor,
             uv,
+            adopt: false,
+            // there are no other daemons to exchange messages with
+            encryption_key: None,
+            signed_descriptor: None,
+            blackboard: BTreeMap::new(),
+            node_listeners: BTreeMap::new(),
         };
 
-        let clock = Arc::new(HLC::default());
-
-        let ctrlc_events = ReceiverStream::new(set_up_ctrlc_handler(clock.clone())?);
-
+        // dynamic nodes have no exit result, they only disconnect
         let exit_when_done = spawn_command
             .nodes
             .iter()
+            .filter(|n| !n.kind.dynamic())
             .map(|n| (spawn_command.dataflow_id, n.id.clone()))
             .collect();
         let (reply_tx, reply_rx) = oneshot::channel();
@@ -175,21 +406,26 @@ impl Daemon {
                 timestamp,
             }
         });
-        let events = (coordinator_events, ctrlc_events).merge();
+        let events = (coordinator_events, external_events).merge();
         let run_result = Self::run_general(
             Box::pin(events),
             None,
             "".to_string(),
             Some(exit_when_done),
+            None,
+            dynamic_node_token,
+            Footprint::Default,
             clock.clone(),
         );
 
         let spawn_result = reply_rx
             .map_err(|err| eyre!("failed to receive spawn result: {err}"))
-            .and_then(|r| async {
+            .and_then(|r| async move {
                 match r {
                     Some(DaemonCoordinatorReply::SpawnResult(result)) => {
-                        result.map_err(|err| eyre!(err))
+                        result.map_err(|err| eyre!(err))?;
+                        let _ = spawned.send(());
+                        Ok(())
                     }
                     _ => Err(eyre!("unexpected spawn reply")),
                 }
@@ -201,16 +437,25 @@ impl Daemon {
             uuid: dataflow_id,
             timestamp: clock.new_timestamp(),
             node_results: dataflow_results
+                .node_results
                 .remove(&dataflow_id)
                 .context("no node results for dataflow_id")?,
+            stop_requests: dataflow_results
+                .stop_requests
+                .remove(&dataflow_id)
+                .unwrap_or_default(),
         })
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn run_general(
         external_events: impl Stream<Item = Timestamped<Event>> + Unpin,
         coordinator_addr: Option<SocketAddr>,
         machine_id: String,
         exit_when_done: Option<BTreeSet<(Uuid, NodeId)>>,
+        signature_policy: Option<SignaturePolicy>,
+        dynamic_node_token: Option<NodeToken>,
+        footprint: Footprint,
         clock: Arc<HLC>,
     ) -> eyre::Result<DaemonRunResult> {
         let coordinator_connection = match coordinator_addr {
@@ -237,6 +482,11 @@ impl Daemon {
             machine_id,
             exit_when_done,
             dataflow_node_results: BTreeMap::new(),
+            dataflow_stop_requests: BTreeMap::new(),
+            node_log_levels: BTreeMap::new(),
+            signature_policy,
+            dynamic_node_token,
+            footprint,
             clock,
         };
 
@@ -268,7 +518,12 @@ impl Daemon {
 
             match inner {
                 Event::Coordinator(CoordinatorEvent { event, reply_tx }) => {
+                    let description = flight_recorder::describe_coordinator_event(&event);
                     let status = self.handle_coordinator_event(event, reply_tx).await?;
+                    // recorded afterwards because `Spawn` creates the dataflow
+                    if let Some((dataflow_id, description)) = description {
+                        self.record_event(dataflow_id, description);
+                    }
 
                     match status {
                         RunStatus::Continue => {}
@@ -276,13 +531,22 @@ impl Daemon {
                     }
                 }
                 Event::Daemon(event) => {
+                    let (dataflow_id, description) =
+                        flight_recorder::describe_inter_daemon_event(&event);
+                    self.record_event(dataflow_id, description);
                     self.handle_inter_daemon_event(event).await?;
                 }
                 Event::Node {
                     dataflow_id: dataflow,
                     node_id,
                     event,
-                } => self.handle_node_event(event, dataflow, node_id).await?,
+                } => {
+                    self.record_event(
+                        dataflow,
+                        flight_recorder::describe_node_event(&node_id, &event),
+                    );
+                    self.handle_node_event(event, dataflow, node_id).await?
+                }
                 Event::Dora(event) => match self.handle_dora_event(event).await? {
                     RunStatus::Continue => {}
                     RunStatus::Exit => break,
@@ -305,6 +569,9 @@ impl Daemon {
                             bail!("lost connection to coordinator")
                         }
                     }
+                    self.report_queue_metrics().await?;
+                    self.report_resource_usage().await?;
+                    self.publish_status();
                 }
                 Event::CtrlC => {
                     tracing::info!("received ctrlc signal -> stopping all dataflows");
@@ -318,10 +585,216 @@ impl Daemon {
                     tracing::warn!("received second ctrlc signal -> exit immediately");
                     bail!("received second ctrl-c signal");
                 }
+                Event::ForwardSignal(signal) => {
+                    tracing::info!("received {signal} -> forwarding it to subscribed nodes");
+                    for dataflow in self.running.values() {
+                        dataflow.forward_signal(signal);
+                    }
+                }
+            }
+        }
+
+        Ok(DaemonRunResult {
+            node_results: self.dataflow_node_results,
+            stop_requests: self.dataflow_stop_requests,
+        })
+    }
+
+    fn start_recording(
+        &mut self,
+        dataflow_id: DataflowId,
+        recording_id: String,
+        options: RecordingOptions,
+    ) -> eyre::Result<()> {
+        let dataflow = self
+            .running
+            .get_mut(&dataflow_id)
+            .wrap_err_with(|| format!("no running dataflow with ID `{dataflow_id}`"))?;
+        let working_dir = self
+            .working_dir
+            .get(&dataflow_id)
+            .wrap_err_with(|| format!("no working dir for dataflow `{dataflow_id}`"))?;
+        if dataflow.recorder.is_some() {
+            bail!("dataflow `{dataflow_id}` is already being recorded");
+        }
+        let recorder = recording::Recorder::start(
+            working_dir,
+            dataflow_id,
+            recording_id,
+            self.machine_id.clone(),
+            options,
+            self.footprint,
+        )
+        .wrap_err("failed to start recording")?;
+        dataflow.recorder = Some(recorder);
+        Ok(())
+    }
+
+    /// Prepares a checkpoint of the local nodes of the given dataflow.
+    ///
+    /// Returns the directory of the checkpoint and a future that captures the
+    /// queued inputs of the nodes.
+    fn capture_checkpoint(
+        &self,
+        dataflow_id: DataflowId,
+        checkpoint_id: &str,
+    ) -> eyre::Result<(
+        PathBuf,
+        impl std::future::Future<Output = checkpoint::Checkpoint>,
+    )> {
+        let dataflow = self
+            .running
+            .get(&dataflow_id)
+            .wrap_err_with(|| format!("no running dataflow with ID `{dataflow_id}`"))?;
+        let working_dir = self
+            .working_dir
+            .get(&dataflow_id)
+            .wrap_err_with(|| format!("no working dir for dataflow `{dataflow_id}`"))?;
+        let dir =
+            checkpoint::checkpoint_dir(working_dir, &dataflow_id, checkpoint_id, &self.machine_id);
+        let capture = checkpoint::Checkpoint::capture(
+            self.clock.new_timestamp(),
+            dataflow.simulated_clock.as_ref().map(|clock| clock.now()),
+            dataflow.node_states.clone(),
+            dataflow.queue_snapshots.clone(),
+        );
+        Ok((dir, capture))
+    }
+
+    /// Adds the given event to the flight recorder of the dataflow.
+    fn record_event(&mut self, dataflow_id: DataflowId, description: String) {
+        if let Some(dataflow) = self.running.get_mut(&dataflow_id) {
+            dataflow
+                .flight_recorder
+                .record(self.clock.new_timestamp(), description);
+        }
+    }
+
+    /// Sends a health report to the local nodes that subscribed to
+    /// `dora/status`.
+    fn publish_status(&self) {
+        for dataflow in self.running.values() {
+            if dataflow.status_subscribers.is_empty() {
+                continue;
+            }
+            let report = status::dataflow_status(self, dataflow);
+            let (metadata, data) = status::to_message(&report, &self.clock);
+            for (receiver_id, input_id) in &dataflow.status_subscribers {
+                if !dataflow
+                    .open_inputs
+                    .get(receiver_id)
+                    .is_some_and(|open| open.contains(input_id))
+                {
+                    continue;
+                }
+                let Some(channel) = dataflow.subscribe_channels.get(receiver_id) else {
+                    continue;
+                };
+                // closed channels are removed when the next timer tick or output is sent
+                let _ = send_with_timestamp(
+                    channel,
+                    NodeEvent::Input {
+                        id: input_id.clone(),
+                        metadata: metadata.clone(),
+                        data: Some(data.clone()),
+                    },
+                    &self.clock,
+                );
+            }
+        }
+    }
+
+    /// Checks the queue metrics of all running dataflows against the warning
+    /// thresholds and reports them to the coordinator.
+    async fn report_queue_metrics(&mut self) -> eyre::Result<()> {
+        let now = Instant::now();
+        let mut reports = Vec::new();
+        for (dataflow_id, dataflow) in &mut self.running {
+            let metrics = dataflow.queue_metrics();
+            let mut warnings: Vec<_> = dataflow
+                .queue_monitor
+                .check(&metrics)
+                .into_iter()
+                .map(|(node_id, message)| (node_id, message, BTreeMap::new()))
+                .collect();
+            let consumers = dataflow.consumer_states(&metrics, now);
+            warnings.extend(
+                dataflow
+                    .slow_consumers
+                    .check(&consumers, now)
+                    .into_iter()
+                    .map(|w| (Some(w.node_id), w.message, w.fields)),
+            );
+            reports.push((*dataflow_id, metrics, warnings));
+        }
+
+        for (dataflow_id, metrics, warnings) in reports {
+            for (node_id, message, fields) in warnings {
+                self.send_log_message(LogMessage {
+                    dataflow_id,
+                    node_id,
+                    level: LogLevel::Warn,
+                    target: None,
+                    module_path: None,
+                    file: None,
+                    line: None,
+                    message,
+                    fields,
+                })
+                .await?;
+            }
+            if let Some(connection) = &mut self.coordinator_connection {
+                let msg = serde_json::to_vec(&Timestamped {
+                    inner: CoordinatorRequest::Event {
+                        machine_id: self.machine_id.clone(),
+                        event: DaemonEvent::QueueMetricsReported {
+                            dataflow_id,
+                            metrics,
+                        },
+                    },
+                    timestamp: self.clock.new_timestamp(),
+                })?;
+                socket_stream_send(connection, &msg)
+                    .await
+                    .wrap_err("failed to send queue metrics to dora-coordinator")?;
             }
         }
+        Ok(())
+    }
 
-        Ok(self.dataflow_node_results)
+    /// Samples the resource usage of the nodes that run in a cgroup and
+    /// reports it to the coordinator.
+    async fn report_resource_usage(&mut self) -> eyre::Result<()> {
+        let Some(connection) = &mut self.coordinator_connection else {
+            return Ok(());
+        };
+        for (dataflow_id, dataflow) in &mut self.running {
+            let usage: BTreeMap<_, _> = dataflow
+                .running_nodes
+                .iter_mut()
+                .filter_map(|(node_id, node)| {
+                    let usage = node.cgroup.as_mut()?.sample()?;
+                    Some((node_id.clone(), usage))
+                })
+                .collect();
+            if usage.is_empty() {
+                continue;
+            }
+            let msg = serde_json::to_vec(&Timestamped {
+                inner: CoordinatorRequest::Event {
+                    machine_id: self.machine_id.clone(),
+                    event: DaemonEvent::ResourceUsageReported {
+                        dataflow_id: *dataflow_id,
+                        usage,
+                    },
+                },
+                timestamp: self.clock.new_timestamp(),
+            })?;
+            socket_stream_send(connection, &msg)
+                .await
+                .wrap_err("failed to send resource usage to dora-coordinator")?;
+        }
+        Ok(())
     }
 
     async fn send_log_message(&mut self, message: LogMessage) -> eyre::Result<()> {
@@ -382,11 +855,40 @@ impl Daemon {
             DaemonCoordinatorEvent::Spawn(SpawnDataflowNodes {
                 dataflow_id,
                 working_dir,
-                nodes,
+                mut nodes,
                 machine_listen_ports,
-                dataflow_descriptor,
+                mut dataflow_descriptor,
                 uv,
+                adopt,
+                encryption_key,
+                signed_descriptor,
+                blackboard,
+                node_listeners,
             }) => {
+                if let Some(policy) = &self.signature_policy {
+                    // don't trust the coordinator, run what was signed instead
+                    let verified = policy
+                        .check(dataflow_descriptor, signed_descriptor.as_ref())
+                        .and_then(|descriptor| {
+                            let nodes = descriptor.resolve_aliases_and_set_defaults()?;
+                            Ok((descriptor, nodes))
+                        });
+                    match verified {
+                        Ok(verified) => (dataflow_descriptor, nodes) = verified,
+                        Err(err) => {
+                            let err = err.wrap_err("dataflow signature check failed");
+                            tracing::error!("{err:?}");
+                            let reply =
+                                DaemonCoordinatorReply::SpawnResult(Err(format!("{err:?}")));
+                            let _ = reply_tx.send(Some(reply)).map_err(|_| {
+                                error!(
+                                    "could not send `SpawnResult` reply from daemon to coordinator"
+                                )
+                            });
+                            return Ok(RunStatus::Continue);
+                        }
+                    }
+                }
                 match dataflow_descriptor.communication.remote {
                     dora_core::config::RemoteCommunicationConfig::Tcp => {}
                 }
@@ -410,11 +912,25 @@ impl Daemon {
                     std::env::current_dir().wrap_err("failed to get current working dir")?
                 };
 
-                let result = self
-                    .spawn_dataflow(dataflow_id, working_dir, nodes, dataflow_descriptor, uv)
-                    .await;
-                if let Err(err) = &result {
-                    tracing::error!("{err:?}");
+                let params = SpawnDataflowParams {
+                    dataflow_id,
+                    working_dir,
+                    nodes,
+                    dataflow_descriptor,
+                    uv,
+                    adopt,
+                    node_listeners,
+                    encryption_key,
+                };
+                let result = self.spawn_dataflow(params).await;
+                match &result {
+                    Ok(_) => {
+                        if let Some(dataflow) = self.running.get_mut(&dataflow_id) {
+                            // carry over the blackboard of the dataflow on re-adoption
+                            dataflow.blackboard = blackboard;
+                        }
+                    }
+                    Err(err) => tracing::error!("{err:?}"),
                 }
                 let reply =
                     DaemonCoordinatorReply::SpawnResult(result.map_err(|err| format!("{err:?}")));
@@ -461,23 +977,9 @@ impl Daemon {
                     Some(working_dir) => {
                         let working_dir = working_dir.clone();
                         tokio::spawn(async move {
-                            let logs = async {
-                                let mut file =
-                                    File::open(log::log_path(&working_dir, &dataflow_id, &node_id))
-                                        .await
-                                        .wrap_err(format!(
-                                            "Could not open log file: {:#?}",
-                                            log::log_path(&working_dir, &dataflow_id, &node_id)
-                                        ))?;
-
-                                let mut contents = vec![];
-                                file.read_to_end(&mut contents)
-                                    .await
-                                    .wrap_err("Could not read content of log file")?;
-                                Result::<Vec<u8>, eyre::Report>::Ok(contents)
-                            }
-                            .await
-                            .map_err(|err| format!("{err:?}"));
+                            let logs = log::read_log(&working_dir, &dataflow_id, &node_id)
+                                .await
+                                .map_err(|err| format!("{err:?}"));
                             let _ = reply_tx
                                 .send(Some(DaemonCoordinatorReply::Logs(logs)))
                                 .map_err(|_| {
@@ -496,6 +998,181 @@ impl Daemon {
                 }
                 RunStatus::Continue
             }
+            DaemonCoordinatorEvent::EdgeLatencies { dataflow_id } => {
+                let reply = match self.running.get(&dataflow_id) {
+                    Some(dataflow) => Ok(dataflow
+                        .edge_latencies
+                        .iter()
+                        .map(|((OutputId(source, output), (target, input)), histogram)| {
+                            EdgeLatency {
+                                source: source.clone(),
+                                output: output.clone(),
+                                target: target.clone(),
+                                input: input.clone(),
+                                histogram: histogram.clone(),
+                            }
+                        })
+                        .collect()),
+                    None => Err(format!("no running dataflow with ID `{dataflow_id}`")),
+                };
+                let _ = reply_tx
+                    .send(Some(DaemonCoordinatorReply::EdgeLatencies(reply)))
+                    .map_err(|_| {
+                        error!("could not send edge latencies reply from daemon to coordinator")
+                    });
+                RunStatus::Continue
+            }
+            DaemonCoordinatorEvent::LiveGraph { dataflow_id } => {
+                let node_results = self.dataflow_node_results.get(&dataflow_id);
+                let reply = match (self.running.get(&dataflow_id), node_results) {
+                    (Some(dataflow), _) => Ok(dataflow.live_graph(node_results, Instant::now())),
+                    // the dataflow might have finished on this machine already
+                    (None, Some(node_results)) => Ok(DaemonLiveGraph {
+                        nodes: node_results
+                            .iter()
+                            .map(|(node_id, result)| (node_id.clone(), node_state(result)))
+                            .collect(),
+                        edges: Vec::new(),
+                    }),
+                    (None, None) => Err(format!("no running dataflow with ID `{dataflow_id}`")),
+                };
+                let _ = reply_tx
+                    .send(Some(DaemonCoordinatorReply::LiveGraph(reply)))
+                    .map_err(|_| {
+                        error!("could not send live graph reply from daemon to coordinator")
+                    });
+                RunStatus::Continue
+            }
+            DaemonCoordinatorEvent::DumpState => {
+                #[cfg(feature = "state-dump")]
+                let reply = serde_json::to_vec(&dump::dump_state(self))
+                    .map_err(|err| format!("failed to serialize daemon state: {err}"));
+                #[cfg(not(feature = "state-dump"))]
+                let reply = Err("daemon was built without the `state-dump` feature".to_owned());
+                let _ = reply_tx
+                    .send(Some(DaemonCoordinatorReply::StateDump(reply)))
+                    .map_err(|_| error!("could not send state dump from daemon to coordinator"));
+                RunStatus::Continue
+            }
+            DaemonCoordinatorEvent::CrashReports {
+                dataflow_id,
+                node_id,
+            } => {
+                match self.working_dir.get(&dataflow_id) {
+                    Some(working_dir) => {
+                        let working_dir = working_dir.clone();
+                        tokio::spawn(async move {
+                            let reports = crash_report::read_all(
+                                &working_dir,
+                                &dataflow_id,
+                                node_id.as_ref(),
+                            )
+                            .await
+                            .map_err(|err| format!("{err:?}"));
+                            let _ = reply_tx
+                                .send(Some(DaemonCoordinatorReply::CrashReports(reports)))
+                                .map_err(|_| {
+                                    error!(
+                                        "could not send crash reports from daemon to coordinator"
+                                    )
+                                });
+                        });
+                    }
+                    None => {
+                        let reply = Err(format!("unknown dataflow `{dataflow_id}`"));
+                        let _ = reply_tx
+                            .send(Some(DaemonCoordinatorReply::CrashReports(reply)))
+                            .map_err(|_| {
+                                error!("could not send crash reports from daemon to coordinator")
+                            });
+                    }
+                }
+                RunStatus::Continue
+            }
+            DaemonCoordinatorEvent::StartRecording {
+                dataflow_id,
+                recording_id,
+                options,
+            } => {
+                let result = self
+                    .start_recording(dataflow_id, recording_id, options)
+                    .map_err(|err| format!("{err:?}"));
+                let _ = reply_tx
+                    .send(Some(DaemonCoordinatorReply::StartRecordingResult(result)))
+                    .map_err(|_| {
+                        error!("could not send start recording reply from daemon to coordinator")
+                    });
+                RunStatus::Continue
+            }
+            DaemonCoordinatorEvent::StopRecording { dataflow_id } => {
+                let recorder = self
+                    .running
+                    .get_mut(&dataflow_id)
+                    .wrap_err_with(|| format!("no running dataflow with ID `{dataflow_id}`"))
+                    .map(|dataflow| dataflow.recorder.take());
+                let machine_id = self.machine_id.clone();
+                tokio::spawn(async move {
+                    let result = match recorder {
+                        Ok(Some(recorder)) => recorder.stop(machine_id).await.map(Some),
+                        Ok(None) => Ok(None),
+                        Err(err) => Err(err),
+                    };
+                    let _ = reply_tx
+                        .send(Some(DaemonCoordinatorReply::StopRecordingResult(
+                            result.map_err(|err| format!("{err:?}")),
+                        )))
+                        .map_err(|_| {
+                            error!("could not send stop recording reply from daemon to coordinator")
+                        });
+                });
+                RunStatus::Continue
+            }
+            DaemonCoordinatorEvent::Checkpoint {
+                dataflow_id,
+                checkpoint_id,
+            } => {
+                let capture = self.capture_checkpoint(dataflow_id, &checkpoint_id);
+                let machine_id = self.machine_id.clone();
+                tokio::spawn(async move {
+                    let result = async {
+                        let (dir, capture) = capture?;
+                        let checkpoint = capture.await;
+                        tokio::task::spawn_blocking(move || {
+                            checkpoint.write(&dir, dataflow_id, checkpoint_id, machine_id)
+                        })
+                        .await
+                        .context("checkpoint writer panicked")?
+                    };
+                    let _ = reply_tx
+                        .send(Some(DaemonCoordinatorReply::CheckpointResult(
+                            result.await.map_err(|err| format!("{err:?}")),
+                        )))
+                        .map_err(|_| {
+                            error!("could not send checkpoint reply from daemon to coordinator")
+                        });
+                });
+                RunStatus::Continue
+            }
+            DaemonCoordinatorEvent::ControlReplay {
+                dataflow_id,
+                control,
+            } => {
+                let result = match self.running.get(&dataflow_id) {
+                    Some(dataflow) => match &dataflow.replay {
+                        Some(replay) => replay.control(control).map(|()| true),
+                        None => Ok(false),
+                    },
+                    None => Err(eyre!("no running dataflow with ID `{dataflow_id}`")),
+                };
+                let _ = reply_tx
+                    .send(Some(DaemonCoordinatorReply::ControlReplayResult(
+                        result.map_err(|err| format!("{err:?}")),
+                    )))
+                    .map_err(|_| {
+                        error!("could not send replay control reply from daemon to coordinator")
+                    });
+                RunStatus::Continue
+            }
             DaemonCoordinatorEvent::ReloadDataflow {
                 dataflow_id,
                 node_id,
@@ -509,6 +1186,34 @@ impl Daemon {
                     .map_err(|_| error!("could not send reload reply from daemon to coordinator"));
                 RunStatus::Continue
             }
+            DaemonCoordinatorEvent::BlackboardUpdate {
+                dataflow_id,
+                key,
+                value,
+            } => {
+                match self.running.get_mut(&dataflow_id) {
+                    Some(dataflow) => dataflow.update_blackboard(key, value, &self.clock),
+                    None => tracing::warn!(
+                        "received blackboard update for unknown dataflow `{dataflow_id}`"
+                    ),
+                }
+                let _ = reply_tx.send(None);
+                RunStatus::Continue
+            }
+            DaemonCoordinatorEvent::LeaderElected {
+                dataflow_id,
+                group,
+                leader,
+            } => {
+                match self.running.get_mut(&dataflow_id) {
+                    Some(dataflow) => dataflow.announce_leader(&group, leader, &self.clock),
+                    None => tracing::warn!(
+                        "received leader election for unknown dataflow `{dataflow_id}`"
+                    ),
+                }
+                let _ = reply_tx.send(None);
+                RunStatus::Continue
+            }
             DaemonCoordinatorEvent::StopDataflow {
                 dataflow_id,
                 grace_duration,
@@ -539,6 +1244,37 @@ impl Daemon {
 
                 RunStatus::Continue
             }
+            DaemonCoordinatorEvent::ArtifactStatus { artifact } => {
+                tokio::spawn(async move {
+                    let status = artifacts::status(&artifact)
+                        .await
+                        .map_err(|err| format!("{err:?}"));
+                    let _ = reply_tx
+                        .send(Some(DaemonCoordinatorReply::ArtifactStatus(status)))
+                        .map_err(|_| {
+                            error!("could not send artifact status from daemon to coordinator")
+                        });
+                });
+                RunStatus::Continue
+            }
+            DaemonCoordinatorEvent::UploadArtifact {
+                artifact,
+                offset,
+                data,
+                last,
+            } => {
+                tokio::spawn(async move {
+                    let status = artifacts::write(&artifact, offset, &data, last)
+                        .await
+                        .map_err(|err| format!("{err:?}"));
+                    let _ = reply_tx
+                        .send(Some(DaemonCoordinatorReply::ArtifactStatus(status)))
+                        .map_err(|_| {
+                            error!("could not send artifact status from daemon to coordinator")
+                        });
+                });
+                RunStatus::Continue
+            }
             DaemonCoordinatorEvent::Destroy => {
                 tracing::info!("received destroy command -> exiting");
                 let (notify_tx, notify_rx) = oneshot::channel();
@@ -565,18 +1301,146 @@ impl Daemon {
     }
 
     async fn handle_inter_daemon_event(&mut self, event: InterDaemonEvent) -> eyre::Result<()> {
+        match event {
+            event
+            @ (InterDaemonEvent::Output { .. } | InterDaemonEvent::EncryptedOutput { .. }) => {
+                if let Err(err) = self.receive_remote_output(event, None).await {
+                    tracing::warn!("{err:?}")
+                }
+                Ok(())
+            }
+            InterDaemonEvent::OutputBatch {
+                dataflow_id,
+                outputs,
+            } => {
+                let mut batch = InputBatches::new();
+                for output in outputs {
+                    let output_dataflow = match &output {
+                        InterDaemonEvent::Output { dataflow_id, .. }
+                        | InterDaemonEvent::EncryptedOutput { dataflow_id, .. } => {
+                            Some(*dataflow_id)
+                        }
+                        _ => None,
+                    };
+                    let result = if output_dataflow == Some(dataflow_id) {
+                        self.receive_remote_output(output, Some(&mut batch)).await
+                    } else {
+                        Err(eyre!(
+                            "output batch of dataflow `{dataflow_id}` contains {output:?}"
+                        ))
+                    };
+                    if let Err(err) = result {
+                        tracing::warn!("{err:?}")
+                    }
+                }
+                let result = match self.running.get_mut(&dataflow_id) {
+                    Some(dataflow) => deliver_input_batches(dataflow, batch, &self.clock).await,
+                    None => Err(eyre!("no running dataflow with ID `{dataflow_id}`")),
+                };
+                if let Err(err) =
+                    result.wrap_err("failed to forward remote output batch to local receivers")
+                {
+                    tracing::warn!("{err:?}")
+                }
+                Ok(())
+            }
+            InterDaemonEvent::InputsClosed {
+                dataflow_id,
+                inputs,
+                tag,
+            } => {
+                tracing::debug!(?dataflow_id, ?inputs, "received InputsClosed event");
+                let inner = async {
+                    let dataflow = self.running.get_mut(&dataflow_id).wrap_err_with(|| {
+                        format!("send out failed: no running dataflow with ID `{dataflow_id}`")
+                    })?;
+                    if let Some(cipher) = &mut dataflow.cipher {
+                        cipher.verify(
+                            tag.as_ref(),
+                            &AuthenticatedEvent::InputsClosed {
+                                dataflow_id: &dataflow_id,
+                                inputs: &inputs,
+                            },
+                        )?;
+                    }
+                    for (receiver_id, input_id) in &inputs {
+                        close_input(dataflow, receiver_id, input_id, &self.clock);
+                    }
+                    Result::<(), eyre::Report>::Ok(())
+                };
+                if let Err(err) = inner
+                    .await
+                    .wrap_err("failed to handle InputsClosed event sent by coordinator")
+                {
+                    tracing::warn!("{err:?}")
+                }
+                Ok(())
+            }
+            InterDaemonEvent::Backpressure {
+                dataflow_id,
+                node_id,
+                output_id,
+                level,
+                tag,
+            } => {
+                let Some(dataflow) = self.running.get_mut(&dataflow_id) else {
+                    tracing::warn!("received backpressure for unknown dataflow `{dataflow_id}`");
+                    return Ok(());
+                };
+                if let Some(cipher) = &mut dataflow.cipher {
+                    let event = AuthenticatedEvent::Backpressure {
+                        dataflow_id: &dataflow_id,
+                        node_id: &node_id,
+                        output_id: &output_id,
+                        level,
+                    };
+                    if let Err(err) = cipher.verify(tag.as_ref(), &event) {
+                        tracing::warn!("{err:?}");
+                        return Ok(());
+                    }
+                }
+                dataflow.send_backpressure(&node_id, output_id, level, &self.clock);
+                Ok(())
+            }
+        }
+    }
+
+    /// Forwards an output of a node on another machine to the local receivers.
+    ///
+    /// If a `batch` is given, the inputs are added to it instead, see
+    /// [`deliver_input_batches`].
+    async fn receive_remote_output(
+        &mut self,
+        event: InterDaemonEvent,
+        batch: Option<&mut InputBatches>,
+    ) -> eyre::Result<()> {
         match event {
             InterDaemonEvent::Output {
                 dataflow_id,
                 node_id,
                 output_id,
-                metadata,
+                mut metadata,
                 data,
             } => {
                 let inner = async {
                     let dataflow = self.running.get_mut(&dataflow_id).wrap_err_with(|| {
                         format!("send out failed: no running dataflow with ID `{dataflow_id}`")
                     })?;
+                    if dataflow.cipher.is_some() {
+                        bail!("rejecting unencrypted output `{node_id}/{output_id}`");
+                    }
+                    trace_hop(
+                        &mut dataflow.trace_sampling,
+                        Hop::Forward,
+                        &mut metadata,
+                        || {
+                            tracing::trace_span!(
+                                "remote_daemon_receive",
+                                node = %node_id,
+                                output = %output_id
+                            )
+                        },
+                    );
                     send_output_to_local_receivers(
                         node_id.clone(),
                         output_id.clone(),
@@ -584,52 +1448,134 @@ impl Daemon {
                         &metadata,
                         data.map(DataMessage::Vec),
                         &self.clock,
+                        batch,
                     )
                     .await?;
                     Result::<_, eyre::Report>::Ok(())
                 };
-                if let Err(err) = inner
+                inner
                     .await
                     .wrap_err("failed to forward remote output to local receivers")
-                {
-                    tracing::warn!("{err:?}")
-                }
-                Ok(())
             }
-            InterDaemonEvent::InputsClosed {
+            InterDaemonEvent::EncryptedOutput {
                 dataflow_id,
-                inputs,
+                node_id,
+                output_id,
+                sequence,
+                nonce,
+                ciphertext,
             } => {
-                tracing::debug!(?dataflow_id, ?inputs, "received InputsClosed event");
                 let inner = async {
                     let dataflow = self.running.get_mut(&dataflow_id).wrap_err_with(|| {
                         format!("send out failed: no running dataflow with ID `{dataflow_id}`")
                     })?;
-                    for (receiver_id, input_id) in &inputs {
-                        close_input(dataflow, receiver_id, input_id, &self.clock);
-                    }
-                    Result::<(), eyre::Report>::Ok(())
+                    let cipher = dataflow.cipher.as_mut().wrap_err_with(|| {
+                        format!(
+                            "received encrypted output, but dataflow `{dataflow_id}` has no key"
+                        )
+                    })?;
+                    let (mut metadata, data) = cipher.decrypt(
+                        &dataflow_id,
+                        &node_id,
+                        &output_id,
+                        sequence,
+                        &nonce,
+                        &ciphertext,
+                    )?;
+                    trace_hop(
+                        &mut dataflow.trace_sampling,
+                        Hop::Forward,
+                        &mut metadata,
+                        || {
+                            tracing::trace_span!(
+                                "remote_daemon_receive",
+                                node = %node_id,
+                                output = %output_id
+                            )
+                        },
+                    );
+                    send_output_to_local_receivers(
+                        node_id.clone(),
+                        output_id.clone(),
+                        dataflow,
+                        &metadata,
+                        data.map(DataMessage::Vec),
+                        &self.clock,
+                        batch,
+                    )
+                    .await?;
+                    Result::<_, eyre::Report>::Ok(())
                 };
-                if let Err(err) = inner
+                inner
                     .await
-                    .wrap_err("failed to handle InputsClosed event sent by coordinator")
-                {
-                    tracing::warn!("{err:?}")
-                }
-                Ok(())
+                    .wrap_err("failed to forward encrypted remote output to local receivers")
             }
+            other => bail!("unexpected inter-daemon event in output batch: {other:?}"),
         }
     }
 
     async fn spawn_dataflow(
         &mut self,
-        dataflow_id: uuid::Uuid,
-        working_dir: PathBuf,
-        nodes: Vec<ResolvedNode>,
-        dataflow_descriptor: Descriptor,
-        uv: bool,
-    ) -> eyre::Result<()> {
-        let dataflow = RunningDataflow::new(dataflow_id, self.machine_id.clone());
+        params: SpawnDataflowParams,
+    ) -> eyre::Result<BTreeMap<NodeId, NodeListener>> {
+        let SpawnDataflowParams {
+            dataflow_id,
+            working_dir,
+            nodes,
+            dataflow_descriptor,
+            uv,
+            adopt,
+            mut node_listeners,
+            encryption_key,
+        } = params;
+        let mut dataflow =
+            RunningDataflow::new(dataflow_id, self.machine_id.clone(), self.footprint);
+        dataflow.adopted = adopt;
+        if dataflow_descriptor.security.encrypt_remote {
+            let key = encryption_key.wrap_err(
+                "dataflow requires encryption of remote outputs, but the coordinator sent no key",
+            )?;
+            dataflow.cipher = Some(encryption::OutputCipher::new(&key));
+        }
+        dataflow.trace_sampling = TraceSampling::new(&dataflow_descriptor.telemetry);
+        dataflow.event_queues = dataflow_descriptor.event_queues.clone();
+        dataflow.faults = dataflow_descriptor
+            .faults
+            .as_ref()
+            .map(fault_injection::FaultInjector::new);
+        if dataflow_descriptor.time.mode == TimeMode::Simulated {
+            dataflow.simulated_clock =
+                Some(sim_time::SimulatedClock::new(&dataflow_descriptor.time)?);
+        }
+        if let (Some(restore), false) = (&dataflow_descriptor.restore, adopt) {
+            let dir = working_dir.join(restore);
+            let checkpoint = checkpoint::Checkpoint::read(&dir, &self.machine_id)
+                .wrap_err_with(|| format!("failed to read checkpoint `{}`", dir.display()))?;
+            tracing::info!(
+                "restoring dataflow `{dataflow_id}` from checkpoint `{}`",
+                dir.display()
+            );
+            // the new timestamps must be later than the restored ones
+            if let Err(err) = self.clock.update_with_timestamp(&checkpoint.timestamp) {
+                tracing::warn!("failed to update HLC with checkpoint timestamp: {err}");
+            }
+            if let (Some(clock), Some(time)) =
+                (&dataflow.simulated_clock, checkpoint.simulated_time)
+            {
+                clock.advance_to(time);
+            }
+            for (node_id, node) in checkpoint.nodes {
+                if let Some(state) = node.state {
+                    // kept for the next checkpoint unless the node stores a
+                    // new state
+                    dataflow.node_states.insert(node_id.clone(), state.clone());
+                    dataflow.restored_states.insert(node_id.clone(), state);
+                }
+                if !node.queued_inputs.is_empty() {
+                    dataflow.restored_inputs.insert(node_id, node.queued_inputs);
+                }
+            }
+        }
         let dataflow = match self.running.entry(dataflow_id) {
             std::collections::hash_map::Entry::Vacant(entry) => {
                 self.working_dir.insert(dataflow_id, working_dir.clone());
@@ -640,11 +1586,49 @@ impl Daemon {
             }
         };
 
+        if let (Some(keep_runs), false) = (dataflow_descriptor.logs.keep_runs, adopt) {
+            if let Err(err) = log::remove_old_runs(&working_dir, &dataflow_id, keep_runs).await {
+                tracing::warn!("failed to remove log files of old dataflow runs: {err:?}");
+            }
+        }
+
+        // the outputs of replayed nodes are sent by the daemon itself
+        let replays = |node: &ResolvedNode| {
+            dataflow_descriptor.replay.as_ref().map_or(false, |replay| {
+                replay.replaces(&node.id, &node_inputs(node))
+            })
+        };
+        dataflow.blockable_sources = nodes
+            .iter()
+            .filter(|node| node.deploy.machine == self.machine_id)
+            .filter(|node| node.kind.dynamic() || !replays(node))
+            .map(|node| node.id.clone())
+            .collect();
+
         let mut log_messages = Vec::new();
+        let mut spawned_listeners = BTreeMap::new();
         for node in nodes {
             let local = node.deploy.machine == self.machine_id;
-
             let inputs = node_inputs(&node);
+
+            let replayed = dataflow_descriptor
+                .replay
+                .as_ref()
+                .map_or(false, |replay| replay.replaces(&node.id, &inputs));
+            if local && replayed && !node.kind.dynamic() {
+                // the outputs of the node are read from the recording instead
+                dataflow.replayed_nodes.insert(node.id.clone());
+                continue;
+            }
+
+            if let (true, Some(log_level)) = (local, &node.log_level) {
+                // full filters only apply to the node process itself
+                if let Ok(level) = log_level.parse() {
+                    self.node_log_levels
+                        .insert((dataflow_id.to_string(), node.id.to_string()), level);
+                }
+            }
+
             for (input_id, input) in inputs {
                 if local {
                     dataflow
@@ -661,12 +1645,23 @@ impl Daemon {
                                 .insert((node.id.clone(), input_id));
                         }
                         InputMapping::Timer { interval } => {
+                            if let Some(throttle) = input.throttle {
+                                dataflow.timer_throttles.insert(
+                                    (node.id.clone(), input_id.clone()),
+                                    timer_throttle::Throttle::new(throttle),
+                                );
+                            }
                             dataflow
                                 .timers
                                 .entry(interval)
                                 .or_default()
                                 .insert((node.id.clone(), input_id));
                         }
+                        InputMapping::Status => {
+                            dataflow
+                                .status_subscribers
+                                .insert((node.id.clone(), input_id));
+                        }
                     }
                 } else if let InputMapping::User(mapping) = input.mapping {
                     dataflow
@@ -679,9 +1674,12 @@ impl Daemon {
                 }
             }
             if local {
+                if let CoreNodeKind::Runtime(_) = node.kind {
+                    dataflow.runtime_nodes.insert(node.id.clone());
+                }
                 if node.kind.dynamic() {
                     dataflow.dynamic_nodes.insert(node.id.clone());
-                } else {
+                } else if !adopt {
                     dataflow.pending_nodes.insert(node.id.clone());
                 }
 
@@ -691,6 +1689,31 @@ impl Daemon {
                     .entry(node.id.clone())
                     .or_insert_with(|| Arc::new(ArrayQueue::new(STDERR_LOG_LINES)))
                     .clone();
+                let log_file = log::NodeLogFile::open(
+                    &working_dir,
+                    &dataflow_id,
+                    &node_id,
+                    &dataflow_descriptor.logs,
+                )
+                .await
+                .wrap_err_with(|| format!("failed to open log file of node `{node_id}`"))?;
+                dataflow.log_files.insert(node_id.clone(), log_file.clone());
+                if !adopt {
+                    if let Some(restartable) =
+                        restart::RestartableNode::new(&node, &dataflow_descriptor, uv)
+                    {
+                        dataflow
+                            .restartable_nodes
+                            .insert(node_id.clone(), restartable);
+                    }
+                }
+                let previous_listener = match adopt {
+                    true => Some(node_listeners.remove(&node_id).wrap_err_with(|| {
+                        format!("coordinator sent no listener for re-adopting node `{node_id}`")
+                    })?),
+                    false => None,
+                };
+                let unblockable_inputs = unblockable_inputs(&node, &dataflow.blockable_sources);
                 match spawn::spawn_node(
                     dataflow_id,
                     &working_dir,
@@ -699,12 +1722,26 @@ impl Daemon {
                     dataflow_descriptor.clone(),
                     self.clock.clone(),
                     node_stderr_most_recent,
+                    log_file,
                     uv,
+                    previous_listener,
+                    self.footprint,
+                    unblockable_inputs,
                 )
                 .await
                 .wrap_err_with(|| format!("failed to spawn node `{node_id}`"))
                 {
                     Ok(running_node) => {
+                        spawned_listeners.insert(
+                            node_id.clone(),
+                            NodeListener {
+                                communication: running_node
+                                    .node_config
+                                    .daemon_communication
+                                    .clone(),
+                                token: running_node.node_config.token.clone(),
+                            },
+                        );
                         dataflow.running_nodes.insert(node_id, running_node);
                     }
                     Err(err) => {
@@ -717,6 +1754,7 @@ impl Daemon {
                             file: None,
                             line: None,
                             message: format!("{err:?}"),
+                            fields: Default::default(),
                         });
                         let messages = dataflow
                             .pending_nodes
@@ -732,14 +1770,59 @@ impl Daemon {
                 }
             } else {
                 dataflow.pending_nodes.set_external_nodes(true);
+                dataflow
+                    .remote_nodes
+                    .insert(node.id.clone(), node.deploy.machine.clone());
+            }
+        }
+        apply_node_log_levels(&self.node_log_levels);
+
+        if let (Some(replay), false) = (
+            &dataflow_descriptor.replay,
+            dataflow.replayed_nodes.is_empty(),
+        ) {
+            let recording = working_dir.join(&replay.recording);
+            tracing::info!(
+                "replaying nodes {:?} of dataflow `{dataflow_id}` from `{}`",
+                dataflow.replayed_nodes,
+                recording.display()
+            );
+            dataflow.replay = Some(replay::Replay::new(
+                recording,
+                dataflow.replayed_nodes.clone(),
+                replay.timing,
+                dataflow.simulated_clock.clone(),
+                self.footprint,
+            ));
+            if !adopt {
+                // there might be no other local node whose subscription
+                // starts the dataflow
+                let status = dataflow
+                    .pending_nodes
+                    .handle_spawn_done(
+                        &mut self.coordinator_connection,
+                        &self.clock,
+                        &mut dataflow.cascading_error_causes,
+                    )
+                    .await?;
+                if let DataflowStatus::AllNodesReady = status {
+                    tracing::info!("all nodes are ready, starting dataflow `{dataflow_id}`");
+                    dataflow.start(&self.events_tx, &self.clock).await?;
+                }
             }
         }
 
+        if adopt {
+            // the nodes were already synchronized before the daemon restart
+            tracing::info!("re-adopted running dataflow `{dataflow_id}`");
+            dataflow.start(&self.events_tx, &self.clock).await?;
+        }
+
         for log_message in log_messages {
             self.send_log_message(log_message).await?;
         }
 
-        Ok(())
+        Ok(spawned_listeners)
     }
 
     async fn handle_dynamic_node_event(
@@ -748,9 +1831,33 @@ impl Daemon {
     ) -> eyre::Result<()> {
         match event {
             DynamicNodeEventWrapper {
-                event: DynamicNodeEvent::NodeConfig { node_id },
+                event: DynamicNodeEvent::NodeConfig { node_id, token },
                 reply_tx,
             } => {
+                // any local process can connect to the local listener
+                let authorized = match (&self.dynamic_node_token, &token) {
+                    (Some(expected), Some(token)) => expected.matches(token),
+                    _ => false,
+                };
+                if !authorized {
+                    let err = match (&self.dynamic_node_token, &token) {
+                        (None, _) => format!(
+                            "dynamic nodes are disabled, set `{DYNAMIC_NODE_TOKEN_ENV}` \
+                            for the daemon and the node to enable them"
+                        ),
+                        (Some(_), None) => format!(
+                            "dynamic node `{node_id}` must present a token, set \
+                            `{DYNAMIC_NODE_TOKEN_ENV}` to the token of the daemon"
+                        ),
+                        (Some(_), Some(_)) => {
+                            format!("dynamic node `{node_id}` presented an invalid token")
+                        }
+                    };
+                    warn!("rejected config request for dynamic node `{node_id}`: {err}");
+                    let reply = DaemonReply::NodeConfig { result: Err(err) };
+                    let _ = reply_tx.send(Some(reply));
+                    return Ok(());
+                }
                 let number_node_id = self
                     .running
                     .iter()
@@ -801,6 +1908,7 @@ impl Daemon {
         }
     }
 
+    #[tracing::instrument(skip_all, fields(%dataflow_id, %node_id), level = "trace")]
     async fn handle_node_event(
         &mut self,
         event: DaemonNodeEvent,
@@ -810,6 +1918,10 @@ impl Daemon {
         match event {
             DaemonNodeEvent::Subscribe {
                 event_sender,
+                queued_inputs,
+                inputs_taken,
+                input_latency,
+                queue_snapshots,
                 reply_sender,
             } => {
                 let dataflow = self.running.get_mut(&dataflow_id).ok_or_else(|| {
@@ -820,9 +1932,41 @@ impl Daemon {
                     Err(err) => {
                         let _ = reply_sender.send(DaemonReply::Result(Err(err)));
                     }
+                    Ok(dataflow)
+                        if dataflow.adopted
+                            || dataflow
+                                .restartable_nodes
+                                .get(&node_id)
+                                .is_some_and(|n| n.restarts() > 0)
+                                && !dataflow.pending_nodes.is_pending(&node_id) =>
+                    {
+                        tracing::info!("node `{node_id}` resubscribed");
+                        Self::subscribe(
+                            dataflow,
+                            node_id.clone(),
+                            event_sender,
+                            queued_inputs,
+                            inputs_taken,
+                            input_latency,
+                            queue_snapshots,
+                            &self.clock,
+                        )
+                        .await;
+                        let _ = reply_sender.send(DaemonReply::Result(Ok(())));
+                    }
                     Ok(dataflow) => {
                         tracing::info!("node `{node_id}` is ready");
-                        Self::subscribe(dataflow, node_id.clone(), event_sender, &self.clock).await;
+                        Self::subscribe(
+                            dataflow,
+                            node_id.clone(),
+                            event_sender,
+                            queued_inputs,
+                            inputs_taken,
+                            input_latency,
+                            queue_snapshots,
+                            &self.clock,
+                        )
+                        .await;
 
                         let status = dataflow
                             .pending_nodes
@@ -875,6 +2019,7 @@ impl Daemon {
                     send_input_closed_events(
                         dataflow,
                         &mut self.inter_daemon_connections,
+                        &self.machine_id,
                         |OutputId(source_id, output_id)| {
                             source_id == &node_id && outputs.contains(output_id)
                         },
@@ -887,9 +2032,14 @@ impl Daemon {
                 let _ = reply_sender.send(DaemonReply::Result(reply));
             }
             DaemonNodeEvent::OutputsDone { reply_sender } => {
+                let mut adopted_node_done = false;
                 let result = match self.running.get_mut(&dataflow_id) {
+                    // the outputs of restartable nodes stay open until it's
+                    // clear that the node isn't restarted
+                    Some(dataflow) if dataflow.restartable_nodes.contains_key(&node_id) && !dataflow.stop_sent => Ok(()),
                     Some(dataflow) => {
-                        Self::handle_outputs_done(dataflow, &mut self.inter_daemon_connections, &node_id, &self.clock)
+                        adopted_node_done = dataflow.adopted && !dataflow.dynamic_nodes.contains(&node_id);
+                        Self::handle_outputs_done(dataflow, &mut self.inter_daemon_connections, &self.machine_id, &node_id, &self.clock)
                     .await
                     },
                     None => Err(eyre!("failed to get downstream nodes: no running dataflow with ID `{dataflow_id}`")),
@@ -898,15 +2048,89 @@ impl Daemon {
                 let _ = reply_sender.send(DaemonReply::Result(
                     result.map_err(|err| format!("{err:?}")),
                 ));
+
+                if adopted_node_done {
+                    // We didn't spawn the process of adopted nodes, so we can't
+                    // wait for it to exit. Treat the end of its outputs as exit
+                    // instead.
+                    let event = Timestamped {
+                        inner: DoraEvent::SpawnedNodeResult {
+                            dataflow_id,
+                            node_id,
+                            exit_status: NodeExitStatus::Success,
+                            exit_info: Default::default(),
+                            exceeded_memory_limit_mb: None,
+                        }
+                        .into(),
+                        timestamp: self.clock.new_timestamp(),
+                    };
+                    if self.events_tx.send(event).await.is_err() {
+                        tracing::warn!("failed to report finish of adopted node");
+                    }
+                }
             }
             DaemonNodeEvent::SendOut {
                 output_id,
                 metadata,
                 data,
-            } => self
-                .send_out(dataflow_id, node_id, output_id, metadata, data)
+                reply_sender,
+            } => {
+                self.send_out(
+                    dataflow_id,
+                    node_id.clone(),
+                    output_id.clone(),
+                    metadata,
+                    data,
+                    None,
+                )
                 .await
-                .context("failed to send out")?,
+                .context("failed to send out")?;
+                if let Some(reply_sender) = reply_sender {
+                    self.reply_when_receivers_ready(
+                        dataflow_id,
+                        &node_id,
+                        vec![output_id],
+                        reply_sender,
+                    );
+                }
+            }
+            DaemonNodeEvent::SendOutMultiple {
+                messages,
+                reply_sender,
+            } => {
+                let output_ids: Vec<_> = messages.iter().map(|m| m.output_id.clone()).collect();
+                // collect the inputs of each receiver first, so that receivers
+                // get the batch as a single event
+                let mut batch = OutputBatch::default();
+                for OutputMessage {
+                    output_id,
+                    metadata,
+                    data,
+                } in messages
+                {
+                    self.send_out(
+                        dataflow_id,
+                        node_id.clone(),
+                        output_id,
+                        metadata,
+                        data,
+                        Some(&mut batch),
+                    )
+                    .await
+                    .context("failed to send out")?;
+                }
+                self.send_out_batch(dataflow_id, batch)
+                    .await
+                    .context("failed to send out batch")?;
+                if let Some(reply_sender) = reply_sender {
+                    self.reply_when_receivers_ready(
+                        dataflow_id,
+                        &node_id,
+                        output_ids,
+                        reply_sender,
+                    );
+                }
+            }
             DaemonNodeEvent::ReportDrop { tokens } => {
                 let dataflow = self.running.get_mut(&dataflow_id).wrap_err_with(|| {
                     format!(
@@ -935,6 +2159,29 @@ impl Daemon {
                     Err(err) => tracing::warn!("{err:?}"),
                 }
             }
+            DaemonNodeEvent::ReportBackpressure {
+                input_id,
+                level,
+                dropped,
+            } => {
+                if let Some(dataflow) = self.running.get_mut(&dataflow_id) {
+                    dataflow.record_dropped_inputs(&node_id, [(input_id.clone(), dropped.into())]);
+                }
+                if let Err(err) = self
+                    .forward_backpressure(dataflow_id, &node_id, &input_id, level)
+                    .await
+                    .wrap_err_with(|| {
+                        format!("failed to forward backpressure of input `{node_id}/{input_id}`")
+                    })
+                {
+                    tracing::warn!("{err:?}");
+                }
+            }
+            DaemonNodeEvent::ReportDroppedInputs { counts } => {
+                if let Some(dataflow) = self.running.get_mut(&dataflow_id) {
+                    dataflow.record_dropped_inputs(&node_id, counts);
+                }
+            }
             DaemonNodeEvent::EventStreamDropped { reply_sender } => {
                 let inner = async {
                     let dataflow = self
@@ -948,41 +2195,632 @@ impl Daemon {
                 let reply = inner.await.map_err(|err| format!("{err:?}"));
                 let _ = reply_sender.send(DaemonReply::Result(reply));
             }
-        }
-        Ok(())
-    }
-
-    async fn send_reload(
-        &mut self,
-        dataflow_id: Uuid,
-        node_id: NodeId,
-        operator_id: Option<OperatorId>,
-    ) -> Result<(), eyre::ErrReport> {
-        let dataflow = self.running.get_mut(&dataflow_id).wrap_err_with(|| {
-            format!("Reload failed: no running dataflow with ID `{dataflow_id}`")
-        })?;
-        if let Some(channel) = dataflow.subscribe_channels.get(&node_id) {
-            match send_with_timestamp(channel, NodeEvent::Reload { operator_id }, &self.clock) {
-                Ok(()) => {}
-                Err(_) => {
-                    dataflow.subscribe_channels.remove(&node_id);
-                }
+            DaemonNodeEvent::StopDataflow {
+                reason,
+                reply_sender,
+            } => {
+                let result = self
+                    .handle_node_stop_request(dataflow_id, &node_id, reason)
+                    .await;
+                let _ = reply_sender.send(DaemonReply::Result(
+                    result.map_err(|err| format!("{err:?}")),
+                ));
             }
-        }
-        Ok(())
-    }
-
-    async fn send_out(
-        &mut self,
+            DaemonNodeEvent::ReportHealth {
+                health,
+                reply_sender,
+            } => {
+                let result = self
+                    .handle_node_health_report(dataflow_id, &node_id, health)
+                    .await;
+                let _ = reply_sender.send(DaemonReply::Result(
+                    result.map_err(|err| format!("{err:?}")),
+                ));
+            }
+            DaemonNodeEvent::SaveState {
+                state,
+                reply_sender,
+            } => {
+                let result = match self.running.get_mut(&dataflow_id) {
+                    Some(dataflow) => {
+                        dataflow.node_states.insert(node_id, state);
+                        Ok(())
+                    }
+                    None => Err(format!("no running dataflow with ID `{dataflow_id}`")),
+                };
+                let _ = reply_sender.send(DaemonReply::Result(result));
+            }
+            DaemonNodeEvent::RestoredState { reply_sender } => {
+                let reply = match self.running.get(&dataflow_id) {
+                    Some(dataflow) => {
+                        DaemonReply::RestoredState(dataflow.restored_states.get(&node_id).cloned())
+                    }
+                    None => DaemonReply::Result(Err(format!(
+                        "no running dataflow with ID `{dataflow_id}`"
+                    ))),
+                };
+                let _ = reply_sender.send(reply);
+            }
+            DaemonNodeEvent::BlackboardGet { key, reply_sender } => {
+                let reply = match self.running.get(&dataflow_id) {
+                    Some(dataflow) => {
+                        DaemonReply::BlackboardValue(dataflow.blackboard.get(&key).cloned())
+                    }
+                    None => DaemonReply::Result(Err(format!(
+                        "no running dataflow with ID `{dataflow_id}`"
+                    ))),
+                };
+                let _ = reply_sender.send(reply);
+            }
+            DaemonNodeEvent::BlackboardSet {
+                key,
+                value,
+                reply_sender,
+            } => {
+                let result = self.handle_blackboard_set(dataflow_id, key, value).await;
+                let _ = reply_sender.send(DaemonReply::Result(
+                    result.map_err(|err| format!("{err:?}")),
+                ));
+            }
+            DaemonNodeEvent::BlackboardWatch {
+                prefix,
+                reply_sender,
+            } => {
+                let result = match self.running.get_mut(&dataflow_id) {
+                    Some(dataflow) => {
+                        dataflow
+                            .blackboard_watches
+                            .entry(node_id)
+                            .or_default()
+                            .insert(prefix);
+                        Ok(())
+                    }
+                    None => Err(format!("no running dataflow with ID `{dataflow_id}`")),
+                };
+                let _ = reply_sender.send(DaemonReply::Result(result));
+            }
+            DaemonNodeEvent::Campaign {
+                group,
+                reply_sender,
+            } => {
+                let result = self.handle_campaign(dataflow_id, node_id, group).await;
+                let _ = reply_sender.send(DaemonReply::Result(
+                    result.map_err(|err| format!("{err:?}")),
+                ));
+            }
+            DaemonNodeEvent::Resign {
+                group,
+                reply_sender,
+            } => {
+                let result = self.handle_resign(dataflow_id, &node_id, Some(group)).await;
+                let _ = reply_sender.send(DaemonReply::Result(
+                    result.map_err(|err| format!("{err:?}")),
+                ));
+            }
+            DaemonNodeEvent::SubscribeNodeOutput {
+                nodes,
+                reply_sender,
+            } => {
+                let result = match self.running.get_mut(&dataflow_id) {
+                    Some(dataflow) => {
+                        dataflow.output_subscribers.insert(node_id, nodes);
+                        Ok(())
+                    }
+                    None => Err(format!(
+                        "failed to subscribe to node output: no running dataflow with ID `{dataflow_id}`"
+                    )),
+                };
+                let _ = reply_sender.send(DaemonReply::Result(result));
+            }
+            DaemonNodeEvent::Log {
+                level,
+                target,
+                message,
+                fields,
+            } => {
+                let result = self
+                    .handle_node_log(dataflow_id, &node_id, level, target, message, fields)
+                    .await;
+                if let Err(err) = result {
+                    tracing::warn!("{err:?}")
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Stores a structured log record of a node in its log file and forwards it to
+    /// the coordinator.
+    ///
+    /// Records exceeding the per-node rate limit are dropped. The number of dropped
+    /// records is reported once the node is allowed to log again.
+    async fn handle_node_log(
+        &mut self,
+        dataflow_id: DataflowId,
+        node_id: &NodeId,
+        level: LogLevel,
+        target: Option<String>,
+        message: String,
+        fields: BTreeMap<String, String>,
+    ) -> eyre::Result<()> {
+        let dataflow = self
+            .running
+            .get_mut(&dataflow_id)
+            .wrap_err_with(|| format!("no running dataflow with ID `{dataflow_id}`"))?;
+        let decision = dataflow
+            .log_rate_limiters
+            .entry(node_id.clone())
+            .or_default()
+            .check();
+        let dropped_before = match decision {
+            log::RateLimitDecision::Accept { dropped_before } => dropped_before,
+            log::RateLimitDecision::Drop => return Ok(()),
+        };
+
+        let mut records = Vec::new();
+        if dropped_before > 0 {
+            records.push(LogMessage {
+                dataflow_id,
+                node_id: Some(node_id.clone()),
+                level: LogLevel::Warn,
+                target: None,
+                module_path: None,
+                file: None,
+                line: None,
+                message: format!("dropped {dropped_before} log messages because of rate limit"),
+                fields: Default::default(),
+            });
+        }
+        records.push(LogMessage {
+            dataflow_id,
+            node_id: Some(node_id.clone()),
+            level,
+            target,
+            module_path: None,
+            file: None,
+            line: None,
+            message,
+            fields,
+        });
+
+        let log_file = dataflow.log_files.get(node_id).cloned();
+        for record in records {
+            if let Some(log_file) = &log_file {
+                let line = log::format_log_record(
+                    record.level,
+                    record.target.as_deref(),
+                    &record.message,
+                    &record.fields,
+                );
+                log_file
+                    .lock()
+                    .await
+                    .write(&line)
+                    .await
+                    .wrap_err("failed to store log record")?;
+            }
+            self.send_log_message(record).await?;
+        }
+        Ok(())
+    }
+
+    /// Forwards lines that a node printed to stdout or stderr to the coordinator,
+    /// which passes them on to the CLIs that subscribed to the logs of the dataflow.
+    ///
+    /// The lines count against the same rate limit as structured log records.
+    async fn forward_node_output(
+        &mut self,
+        dataflow_id: DataflowId,
+        node_id: &NodeId,
+        stream: log::NodeStdio,
+        text: String,
+    ) -> eyre::Result<()> {
+        let dataflow = self
+            .running
+            .get_mut(&dataflow_id)
+            .wrap_err_with(|| format!("no running dataflow with ID `{dataflow_id}`"))?;
+        let decision = dataflow
+            .log_rate_limiters
+            .entry(node_id.clone())
+            .or_default()
+            .check();
+        if let log::RateLimitDecision::Drop = decision {
+            return Ok(());
+        }
+        let message = text.trim_end_matches('\n');
+        if message.is_empty() {
+            return Ok(());
+        }
+        self.send_log_message(LogMessage {
+            dataflow_id,
+            node_id: Some(node_id.clone()),
+            level: LogLevel::Info,
+            target: Some(stream.as_str().to_owned()),
+            module_path: None,
+            file: None,
+            line: None,
+            message: message.to_owned(),
+            fields: Default::default(),
+        })
+        .await
+    }
+
+    /// Applies a blackboard write of a local node and replicates it through
+    /// the coordinator.
+    async fn handle_blackboard_set(
+        &mut self,
+        dataflow_id: DataflowId,
+        key: String,
+        value: Option<Vec<u8>>,
+    ) -> eyre::Result<()> {
+        let dataflow = self
+            .running
+            .get_mut(&dataflow_id)
+            .wrap_err_with(|| format!("no running dataflow with ID `{dataflow_id}`"))?;
+        dataflow.update_blackboard(key.clone(), value.clone(), &self.clock);
+
+        if let Some(connection) = &mut self.coordinator_connection {
+            let msg = serde_json::to_vec(&Timestamped {
+                inner: CoordinatorRequest::Event {
+                    machine_id: self.machine_id.clone(),
+                    event: DaemonEvent::BlackboardSet {
+                        dataflow_id,
+                        key,
+                        value,
+                    },
+                },
+                timestamp: self.clock.new_timestamp(),
+            })?;
+            socket_stream_send(connection, &msg)
+                .await
+                .wrap_err("failed to send blackboard write to dora-coordinator")?;
+        }
+        Ok(())
+    }
+
+    async fn handle_campaign(
+        &mut self,
+        dataflow_id: DataflowId,
+        node_id: NodeId,
+        group: String,
+    ) -> eyre::Result<()> {
+        let dataflow = self
+            .running
+            .get_mut(&dataflow_id)
+            .wrap_err_with(|| format!("no running dataflow with ID `{dataflow_id}`"))?;
+        dataflow.elections.campaign(group.clone(), node_id.clone());
+
+        match &mut self.coordinator_connection {
+            Some(connection) => {
+                let msg = serde_json::to_vec(&Timestamped {
+                    inner: CoordinatorRequest::Event {
+                        machine_id: self.machine_id.clone(),
+                        event: DaemonEvent::Campaign {
+                            dataflow_id,
+                            node_id,
+                            group,
+                        },
+                    },
+                    timestamp: self.clock.new_timestamp(),
+                })?;
+                socket_stream_send(connection, &msg)
+                    .await
+                    .wrap_err("failed to send campaign to dora-coordinator")?;
+            }
+            None => {
+                let leader = dataflow.elections.local_leader(&group);
+                dataflow.announce_leader(&group, leader, &self.clock);
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the node from the given election group, or from all groups if
+    /// `group` is `None`, and hands over the leadership.
+    async fn handle_resign(
+        &mut self,
+        dataflow_id: DataflowId,
+        node_id: &NodeId,
+        group: Option<String>,
+    ) -> eyre::Result<()> {
+        let dataflow = self
+            .running
+            .get_mut(&dataflow_id)
+            .wrap_err_with(|| format!("no running dataflow with ID `{dataflow_id}`"))?;
+        let left = dataflow.elections.resign(group.as_deref(), node_id);
+        // adopted nodes might have campaigned through the previous daemon
+        // instance, so the coordinator needs to know when they exit
+        let exited_adopted = group.is_none() && dataflow.adopted;
+        if left.is_empty() && !exited_adopted {
+            return Ok(());
+        }
+
+        match &mut self.coordinator_connection {
+            Some(connection) => {
+                let msg = serde_json::to_vec(&Timestamped {
+                    inner: CoordinatorRequest::Event {
+                        machine_id: self.machine_id.clone(),
+                        event: DaemonEvent::Resign {
+                            dataflow_id,
+                            node_id: node_id.clone(),
+                            group,
+                        },
+                    },
+                    timestamp: self.clock.new_timestamp(),
+                })?;
+                socket_stream_send(connection, &msg)
+                    .await
+                    .wrap_err("failed to send resignation to dora-coordinator")?;
+            }
+            None => {
+                for group in left {
+                    let leader = dataflow.elections.local_leader(&group);
+                    dataflow.announce_leader(&group, leader, &self.clock);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Stores the latest health report of a node and forwards it to the coordinator.
+    ///
+    /// Changes of the health status are also logged, so that they are visible when
+    /// running without a coordinator.
+    async fn handle_node_health_report(
+        &mut self,
+        dataflow_id: DataflowId,
+        node_id: &NodeId,
+        health: NodeHealth,
+    ) -> eyre::Result<()> {
+        let dataflow = self
+            .running
+            .get_mut(&dataflow_id)
+            .wrap_err_with(|| format!("no running dataflow with ID `{dataflow_id}`"))?;
+        let previous = dataflow.node_health.insert(node_id.clone(), health.clone());
+
+        let previous_status = previous.map(|h| h.status).unwrap_or_default();
+        if health.status != previous_status {
+            let level = match health.status {
+                HealthStatus::Ok => LogLevel::Info,
+                HealthStatus::Degraded => LogLevel::Warn,
+                HealthStatus::Error => LogLevel::Error,
+            };
+            let message = match &health.message {
+                Some(message) => format!("node health changed to {}: {message}", health.status),
+                None => format!("node health changed to {}", health.status),
+            };
+            self.send_log_message(LogMessage {
+                dataflow_id,
+                node_id: Some(node_id.clone()),
+                level,
+                target: None,
+                module_path: None,
+                file: None,
+                line: None,
+                message,
+                fields: Default::default(),
+            })
+            .await?;
+        }
+
+        if let Some(connection) = &mut self.coordinator_connection {
+            let msg = serde_json::to_vec(&Timestamped {
+                inner: CoordinatorRequest::Event {
+                    machine_id: self.machine_id.clone(),
+                    event: DaemonEvent::NodeHealthReported {
+                        dataflow_id,
+                        node_id: node_id.clone(),
+                        health,
+                    },
+                },
+                timestamp: self.clock.new_timestamp(),
+            })?;
+            socket_stream_send(connection, &msg)
+                .await
+                .wrap_err("failed to send node health report to dora-coordinator")?;
+        }
+        Ok(())
+    }
+
+    /// Handles a request by a node to stop the whole dataflow.
+    ///
+    /// If the daemon is connected to a coordinator, the request is forwarded to it so
+    /// that the dataflow is stopped on all machines. Otherwise, the local dataflow is
+    /// stopped directly.
+    async fn handle_node_stop_request(
+        &mut self,
+        dataflow_id: DataflowId,
+        node_id: &NodeId,
+        reason: String,
+    ) -> eyre::Result<()> {
+        self.send_log_message(LogMessage {
+            dataflow_id,
+            node_id: Some(node_id.clone()),
+            level: LogLevel::Info,
+            target: None,
+            module_path: None,
+            file: None,
+            line: None,
+            message: format!("node requested dataflow stop: {reason}"),
+            fields: Default::default(),
+        })
+        .await?;
+        // reported as part of the dataflow result
+        self.dataflow_stop_requests
+            .entry(dataflow_id)
+            .or_default()
+            .insert(node_id.clone(), reason.clone());
+
+        match &mut self.coordinator_connection {
+            Some(connection) => {
+                let msg = serde_json::to_vec(&Timestamped {
+                    inner: CoordinatorRequest::Event {
+                        machine_id: self.machine_id.clone(),
+                        event: DaemonEvent::StopDataflowRequested {
+                            dataflow_id,
+                            node_id: node_id.clone(),
+                            reason,
+                        },
+                    },
+                    timestamp: self.clock.new_timestamp(),
+                })?;
+                socket_stream_send(connection, &msg)
+                    .await
+                    .wrap_err("failed to send stop request to dora-coordinator")?;
+            }
+            None => {
+                let dataflow = self
+                    .running
+                    .get_mut(&dataflow_id)
+                    .wrap_err_with(|| format!("no running dataflow with ID `{dataflow_id}`"))?;
+                dataflow
+                    .stop_all(&mut self.coordinator_connection, &self.clock, None)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_reload(
+        &mut self,
+        dataflow_id: Uuid,
+        node_id: NodeId,
+        operator_id: Option<OperatorId>,
+    ) -> Result<(), eyre::ErrReport> {
+        let dataflow = self.running.get_mut(&dataflow_id).wrap_err_with(|| {
+            format!("Reload failed: no running dataflow with ID `{dataflow_id}`")
+        })?;
+        if let Some(channel) = dataflow.subscribe_channels.get(&node_id) {
+            match send_with_timestamp(channel, NodeEvent::Reload { operator_id }, &self.clock) {
+                Ok(()) => {}
+                Err(_) => {
+                    dataflow.subscribe_channels.remove(&node_id);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Replies to a node that sent the given outputs once all their local
+    /// receivers have room in their event queues.
+    ///
+    /// The node is blocked until then, see `BackpressureStrategy::Block`. It
+    /// is never blocked by a receiver that waits for it, directly or through
+    /// other blocked nodes, as the nodes would block each other forever.
+    fn reply_when_receivers_ready(
+        &self,
+        dataflow_id: Uuid,
+        node_id: &NodeId,
+        output_ids: Vec<DataId>,
+        reply_sender: oneshot::Sender<DaemonReply>,
+    ) {
+        let Some(dataflow) = self.running.get(&dataflow_id) else {
+            let _ = reply_sender.send(DaemonReply::Empty);
+            return;
+        };
+        let capacity = dataflow.event_queues.capacity();
+        let full_receivers: Vec<_> = output_ids
+            .into_iter()
+            .filter_map(|output_id| dataflow.mappings.get(&OutputId(node_id.clone(), output_id)))
+            .flatten()
+            .map(|(receiver_id, _)| receiver_id)
+            // a node that waits for itself would never continue
+            .filter(|receiver_id| *receiver_id != node_id)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter_map(|receiver_id| {
+                let queued_inputs = dataflow.queued_inputs.get(receiver_id)?;
+                let inputs_taken = dataflow.inputs_taken.get(receiver_id)?;
+                let channel = dataflow.subscribe_channels.get(receiver_id)?;
+                queue_is_full(queued_inputs, channel, capacity).then(|| {
+                    (
+                        receiver_id.clone(),
+                        queued_inputs.clone(),
+                        inputs_taken.clone(),
+                        channel.clone(),
+                    )
+                })
+            })
+            .collect();
+        if full_receivers.is_empty() {
+            let _ = reply_sender.send(DaemonReply::Empty);
+            return;
+        }
+        let mut blocked_senders = dataflow.blocked_senders.lock().unwrap();
+        if let Some((receiver_id, ..)) = full_receivers
+            .iter()
+            .find(|(receiver_id, ..)| waits_for(&blocked_senders, receiver_id, node_id))
+        {
+            tracing::debug!(
+                "not blocking node `{node_id}` on the full event queue of \
+                `{receiver_id}`, which waits for `{node_id}`"
+            );
+            let _ = reply_sender.send(DaemonReply::Empty);
+            return;
+        }
+        blocked_senders.insert(
+            node_id.clone(),
+            full_receivers.iter().map(|(id, ..)| id.clone()).collect(),
+        );
+        drop(blocked_senders);
+
+        let blocked_senders = dataflow.blocked_senders.clone();
+        let node_id = node_id.clone();
+        tokio::spawn(async move {
+            // receivers that exit don't block the sender anymore
+            while let Some((_, queued_inputs, inputs_taken, channel)) =
+                full_receivers
+                    .iter()
+                    .find(|(_, queued_inputs, _, channel)| {
+                        queue_is_full(queued_inputs, channel, capacity) && !channel.is_closed()
+                    })
+            {
+                wait_for_queue_space(queued_inputs, inputs_taken, channel, capacity).await;
+            }
+            blocked_senders.lock().unwrap().remove(&node_id);
+            let _ = reply_sender.send(DaemonReply::Empty);
+        });
+    }
+
+    /// Sends the output to its local and remote receivers.
+    ///
+    /// If a `batch` is given, the output is added to it instead, so that all
+    /// outputs of the batch can be delivered as a unit through
+    /// [`Self::send_out_batch`].
+    async fn send_out(
+        &mut self,
         dataflow_id: Uuid,
         node_id: NodeId,
         output_id: DataId,
-        metadata: dora_message::metadata::Metadata,
+        mut metadata: dora_message::metadata::Metadata,
         data: Option<DataMessage>,
+        mut batch: Option<&mut OutputBatch>,
     ) -> Result<(), eyre::ErrReport> {
         let dataflow = self.running.get_mut(&dataflow_id).wrap_err_with(|| {
             format!("send out failed: no running dataflow with ID `{dataflow_id}`")
         })?;
+        if let Some(simulated_clock) = &dataflow.simulated_clock {
+            metadata = match &mut batch {
+                // all outputs of a batch keep sharing the same timestamp
+                Some(batch) => {
+                    let timestamp = *batch
+                        .simulated_timestamp
+                        .get_or_insert_with(|| simulated_clock.new_timestamp());
+                    metadata::Metadata::from_parameters(
+                        timestamp,
+                        metadata.type_info,
+                        metadata.parameters,
+                    )
+                }
+                None => simulated_clock.restamp(metadata),
+            };
+        }
+        trace_hop(
+            &mut dataflow.trace_sampling,
+            Hop::Forward,
+            &mut metadata,
+            || tracing::trace_span!("daemon_receive", node = %node_id, output = %output_id),
+        );
         let data_bytes = send_output_to_local_receivers(
             node_id.clone(),
             output_id.clone(),
@@ -990,42 +2828,198 @@ impl Daemon {
             &metadata,
             data,
             &self.clock,
+            batch.as_mut().map(|batch| &mut batch.local),
         )
         .await?;
 
         let output_id = OutputId(node_id, output_id);
+        if let Some(recorder) = &mut dataflow.recorder {
+            recorder.record(&output_id, &metadata, data_bytes.as_deref());
+        }
         let remote_receivers: Vec<_> = dataflow
             .open_external_mappings
             .get(&output_id)
             .map(|m| m.keys().cloned().collect())
             .unwrap_or_default();
         if !remote_receivers.is_empty() {
+            trace_hop(
+                &mut dataflow.trace_sampling,
+                Hop::Forward,
+                &mut metadata,
+                || {
+                    tracing::trace_span!(
+                        "remote_forward",
+                        node = %output_id.0,
+                        output = %output_id.1
+                    )
+                },
+            );
+            let inner = match &mut dataflow.cipher {
+                Some(cipher) => cipher.encrypt(
+                    dataflow_id,
+                    output_id.0.clone(),
+                    output_id.1.clone(),
+                    &metadata,
+                    data_bytes.as_deref(),
+                )?,
+                None => InterDaemonEvent::Output {
+                    dataflow_id,
+                    node_id: output_id.0.clone(),
+                    output_id: output_id.1.clone(),
+                    metadata,
+                    data: data_bytes,
+                },
+            };
+            if let Some(batch) = batch {
+                batch.add_remote(output_id, remote_receivers, inner);
+                return Ok(());
+            }
+            let event = Timestamped {
+                inner,
+                timestamp: self.clock.new_timestamp(),
+            };
+            let send_start = Instant::now();
+            inter_daemon::send_inter_daemon_event(
+                &remote_receivers,
+                &mut self.inter_daemon_connections,
+                &event,
+            )
+            .await
+            .wrap_err("failed to forward output to remote receivers")?;
+
+            // a slow send means that the link to the remote machines is saturated
+            let send_duration = send_start.elapsed();
+            if send_duration > SLOW_INTER_DAEMON_SEND {
+                let level = (send_duration.as_secs_f32()
+                    / SATURATED_INTER_DAEMON_SEND.as_secs_f32())
+                .min(1.0);
+                dataflow.send_backpressure(&output_id.0, output_id.1, level, &self.clock);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Delivers the outputs that were added to the batch through
+    /// [`Self::send_out`].
+    async fn send_out_batch(
+        &mut self,
+        dataflow_id: Uuid,
+        batch: OutputBatch,
+    ) -> Result<(), eyre::ErrReport> {
+        let dataflow = self.running.get_mut(&dataflow_id).wrap_err_with(|| {
+            format!("send out failed: no running dataflow with ID `{dataflow_id}`")
+        })?;
+        deliver_input_batches(dataflow, batch.local, &self.clock).await?;
+
+        for (machine, outputs) in batch.remote {
             let event = Timestamped {
-                inner: InterDaemonEvent::Output {
+                inner: InterDaemonEvent::OutputBatch {
                     dataflow_id,
-                    node_id: output_id.0,
-                    output_id: output_id.1,
-                    metadata,
-                    data: data_bytes,
+                    outputs,
                 },
                 timestamp: self.clock.new_timestamp(),
             };
+            let send_start = Instant::now();
             inter_daemon::send_inter_daemon_event(
-                &remote_receivers,
+                &[machine],
                 &mut self.inter_daemon_connections,
                 &event,
             )
             .await
-            .wrap_err("failed to forward output to remote receivers")?;
+            .wrap_err("failed to forward output batch to remote receivers")?;
+
+            // a slow send means that the link to the remote machine is saturated
+            let send_duration = send_start.elapsed();
+            if send_duration > SLOW_INTER_DAEMON_SEND {
+                let level = (send_duration.as_secs_f32()
+                    / SATURATED_INTER_DAEMON_SEND.as_secs_f32())
+                .min(1.0);
+                for OutputId(node_id, output_id) in &batch.remote_outputs {
+                    dataflow.send_backpressure(node_id, output_id.clone(), level, &self.clock);
+                }
+            }
         }
 
         Ok(())
     }
 
+    /// Notifies the node that produces the given input that the input's queue
+    /// overflowed.
+    ///
+    /// If the producing node runs on another machine, the report is forwarded
+    /// to its daemon.
+    async fn forward_backpressure(
+        &mut self,
+        dataflow_id: Uuid,
+        receiver_id: &NodeId,
+        input_id: &DataId,
+        level: f32,
+    ) -> eyre::Result<()> {
+        let dataflow = self
+            .running
+            .get_mut(&dataflow_id)
+            .wrap_err_with(|| format!("no running dataflow with ID `{dataflow_id}`"))?;
+        let input = (receiver_id.clone(), input_id.clone());
+        let Some(OutputId(source_id, output_id)) = dataflow
+            .mappings
+            .iter()
+            .find(|(_, receivers)| receivers.contains(&input))
+            .map(|(output_id, _)| output_id.clone())
+        else {
+            // timer inputs can't be slowed down
+            return Ok(());
+        };
+
+        match dataflow.remote_nodes.get(&source_id) {
+            Some(machine) => {
+                let tag = dataflow
+                    .cipher
+                    .as_mut()
+                    .map(|cipher| {
+                        cipher.authenticate(
+                            &self.machine_id,
+                            &AuthenticatedEvent::Backpressure {
+                                dataflow_id: &dataflow_id,
+                                node_id: &source_id,
+                                output_id: &output_id,
+                                level,
+                            },
+                        )
+                    })
+                    .transpose()?;
+                let event = Timestamped {
+                    inner: InterDaemonEvent::Backpressure {
+                        dataflow_id,
+                        node_id: source_id,
+                        output_id,
+                        level,
+                        tag,
+                    },
+                    timestamp: self.clock.new_timestamp(),
+                };
+                inter_daemon::send_inter_daemon_event(
+                    &[machine.clone()],
+                    &mut self.inter_daemon_connections,
+                    &event,
+                )
+                .await
+                .wrap_err("failed to forward backpressure to remote daemon")?;
+            }
+            None => dataflow.send_backpressure(&source_id, output_id, level, &self.clock),
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
     async fn subscribe(
         dataflow: &mut RunningDataflow,
         node_id: NodeId,
-        event_sender: UnboundedSender<Timestamped<NodeEvent>>,
+        event_sender: EventSender,
+        queued_inputs: Arc<AtomicUsize>,
+        inputs_taken: Arc<Notify>,
+        input_latency: Arc<AtomicU64>,
+        queue_snapshots: checkpoint::QueueSnapshotRequests,
         clock: &HLC,
     ) {
         // some inputs might have been closed already -> report those events
@@ -1061,6 +3055,16 @@ impl Daemon {
             let _ = send_with_timestamp(&event_sender, NodeEvent::Stop, clock);
         }
 
+        dataflow
+            .queued_inputs
+            .insert(node_id.clone(), queued_inputs);
+        dataflow.inputs_taken.insert(node_id.clone(), inputs_taken);
+        dataflow
+            .input_latencies
+            .insert(node_id.clone(), input_latency);
+        dataflow
+            .queue_snapshots
+            .insert(node_id.clone(), queue_snapshots);
         dataflow.subscribe_channels.insert(node_id, event_sender);
     }
 
@@ -1068,12 +3072,14 @@ impl Daemon {
     async fn handle_outputs_done(
         dataflow: &mut RunningDataflow,
         inter_daemon_connections: &mut BTreeMap<String, InterDaemonConnection>,
+        machine_id: &str,
         node_id: &NodeId,
         clock: &HLC,
     ) -> eyre::Result<()> {
         send_input_closed_events(
             dataflow,
             inter_daemon_connections,
+            machine_id,
             |OutputId(source_id, _)| source_id == node_id,
             clock,
         )
@@ -1083,6 +3089,9 @@ impl Daemon {
     }
 
     async fn handle_node_stop(&mut self, dataflow_id: Uuid, node_id: &NodeId) -> eyre::Result<()> {
+        // the next candidate takes over the leader elections of the node
+        self.handle_resign(dataflow_id, node_id, None).await?;
+
         let dataflow = self.running.get_mut(&dataflow_id).wrap_err_with(|| {
             format!("failed to get downstream nodes: no running dataflow with ID `{dataflow_id}`")
         })?;
@@ -1097,21 +3106,34 @@ impl Daemon {
             )
             .await?;
 
+        if let Some(faults) = &mut dataflow.faults {
+            for ((_, (receiver_id, _)), event) in faults.take_held_of(node_id) {
+                if let Some(channel) = dataflow.subscribe_channels.get(&receiver_id) {
+                    let _ = channel.send(event);
+                }
+            }
+        }
         Self::handle_outputs_done(
             dataflow,
             &mut self.inter_daemon_connections,
+            &self.machine_id,
             node_id,
             &self.clock,
         )
         .await?;
 
+        dataflow.queued_inputs.remove(node_id);
+        dataflow.inputs_taken.remove(node_id);
+        dataflow.input_latencies.remove(node_id);
+        dataflow.crash_contexts.remove(node_id);
         if let Some(mut pid) = dataflow.running_nodes.remove(node_id).and_then(|n| n.pid) {
             pid.mark_as_stopped()
         }
-        if dataflow
-            .running_nodes
-            .iter()
-            .all(|(_id, n)| n.node_config.dynamic)
+        if dataflow.replayed_nodes.is_empty()
+            && dataflow
+                .running_nodes
+                .iter()
+                .all(|(_id, n)| n.node_config.dynamic)
         {
             let result = DataflowDaemonResult {
                 timestamp: self.clock.new_timestamp(),
@@ -1120,6 +3142,11 @@ impl Daemon {
                     .get(&dataflow.id)
                     .context("failed to get dataflow node results")?
                     .clone(),
+                stop_requests: self
+                    .dataflow_stop_requests
+                    .get(&dataflow.id)
+                    .cloned()
+                    .unwrap_or_default(),
             };
 
             tracing::info!(
@@ -1142,6 +3169,12 @@ impl Daemon {
                     .wrap_err("failed to report dataflow finish to dora-coordinator")?;
             }
             self.running.remove(&dataflow_id);
+            let dataflow_id = dataflow_id.to_string();
+            let len = self.node_log_levels.len();
+            self.node_log_levels.retain(|(id, _), _| *id != dataflow_id);
+            if self.node_log_levels.len() != len {
+                apply_node_log_levels(&self.node_log_levels);
+            }
         }
 
         for log_message in log_messages {
@@ -1151,6 +3184,216 @@ impl Daemon {
         Ok(())
     }
 
+    /// Records the final result of a local node that exited.
+    async fn handle_node_result(
+        &mut self,
+        dataflow_id: Uuid,
+        node_id: NodeId,
+        node_result: Result<(), NodeError>,
+    ) -> eyre::Result<RunStatus> {
+        self.dataflow_node_results
+            .entry(dataflow_id)
+            .or_default()
+            .insert(node_id.clone(), node_result);
+
+        self.handle_node_stop(dataflow_id, &node_id).await?;
+
+        if let Some(exit_when_done) = &mut self.exit_when_done {
+            exit_when_done.remove(&(dataflow_id, node_id));
+            if exit_when_done.is_empty() {
+                tracing::info!("exiting daemon because all required dataflows are finished");
+                return Ok(RunStatus::Exit);
+            }
+        }
+        Ok(RunStatus::Continue)
+    }
+
+    /// Cleans up after a node that exited and spawns it again after the
+    /// backoff delay of its restart policy.
+    ///
+    /// The node stays in `running_nodes` and its outputs stay open, so that
+    /// the dataflow isn't considered finished in the meantime.
+    async fn schedule_node_restart(
+        &mut self,
+        dataflow_id: Uuid,
+        node_id: NodeId,
+        restart: restart::ScheduledRestart,
+    ) -> eyre::Result<()> {
+        // the next candidate takes over the leader elections of the node
+        self.handle_resign(dataflow_id, &node_id, None).await?;
+
+        let dataflow = self.running.get_mut(&dataflow_id).wrap_err_with(|| {
+            format!("failed to restart node: no running dataflow with ID `{dataflow_id}`")
+        })?;
+        if let Some(node) = dataflow.running_nodes.get_mut(&node_id) {
+            if let Some(mut pid) = node.pid.take() {
+                pid.mark_as_stopped();
+            }
+            node.cgroup = None;
+            node._container = None;
+        }
+        dataflow.subscribe_channels.remove(&node_id);
+        dataflow.drop_channels.remove(&node_id);
+        dataflow.queued_inputs.remove(&node_id);
+        dataflow.inputs_taken.remove(&node_id);
+        dataflow.input_latencies.remove(&node_id);
+        dataflow.queue_snapshots.remove(&node_id);
+        let restarts = dataflow
+            .restartable_nodes
+            .get(&node_id)
+            .map(|n| n.restarts())
+            .unwrap_or_default();
+        self.record_event(
+            dataflow_id,
+            format!("restarting node `{node_id}` in {:?}", restart.delay),
+        );
+
+        let events_tx = self.events_tx.clone();
+        let clock = self.clock.clone();
+        let task = {
+            let node_id = node_id.clone();
+            async move {
+                tokio::select! {
+                    _ = tokio::time::sleep(restart.delay) => {}
+                    _ = restart.skip_backoff => {}
+                }
+                let event = Timestamped {
+                    inner: DoraEvent::RestartNode {
+                        dataflow_id,
+                        node_id,
+                    }
+                    .into(),
+                    timestamp: clock.new_timestamp(),
+                };
+                let _ = events_tx.send(event).await;
+            }
+        };
+        tokio::spawn(task);
+
+        self.send_log_message(LogMessage {
+            dataflow_id,
+            node_id: Some(node_id.clone()),
+            level: LogLevel::Warn,
+            target: None,
+            module_path: None,
+            file: None,
+            line: None,
+            message: format!(
+                "restarting node `{node_id}` in {:?} (restart {restarts})",
+                restart.delay
+            ),
+            fields: [("restarts".to_owned(), restarts.to_string())].into(),
+        })
+        .await
+    }
+
+    /// Spawns a node again after the backoff delay of its restart policy.
+    ///
+    /// If the dataflow was stopped in the meantime or the node fails to spawn,
+    /// the last exit of the node becomes its final result.
+    async fn restart_node(
+        &mut self,
+        dataflow_id: Uuid,
+        node_id: NodeId,
+    ) -> eyre::Result<RunStatus> {
+        let Some(dataflow) = self.running.get_mut(&dataflow_id) else {
+            return Ok(RunStatus::Continue);
+        };
+        let Some(restartable) = dataflow.restartable_nodes.get_mut(&node_id) else {
+            return Ok(RunStatus::Continue);
+        };
+        if dataflow.stop_sent {
+            let Some(node_result) = restartable.take_pending_result() else {
+                return Ok(RunStatus::Continue);
+            };
+            return self
+                .handle_node_result(dataflow_id, node_id, node_result)
+                .await;
+        }
+
+        let spawned = match (
+            self.working_dir.get(&dataflow_id),
+            dataflow.log_files.get(&node_id),
+        ) {
+            (Some(working_dir), Some(log_file)) => {
+                let node_stderr_most_recent = dataflow
+                    .node_stderr_most_recent
+                    .entry(node_id.clone())
+                    .or_insert_with(|| Arc::new(ArrayQueue::new(STDERR_LOG_LINES)))
+                    .clone();
+                spawn::spawn_node(
+                    dataflow_id,
+                    working_dir,
+                    restartable.node.clone(),
+                    self.events_tx.clone(),
+                    restartable.descriptor.clone(),
+                    self.clock.clone(),
+                    node_stderr_most_recent,
+                    log_file.clone(),
+                    restartable.uv,
+                    None,
+                    self.footprint,
+                    unblockable_inputs(&restartable.node, &dataflow.blockable_sources),
+                )
+                .await
+                .wrap_err_with(|| format!("failed to restart node `{node_id}`"))
+            }
+            _ => Err(eyre!(
+                "failed to restart node `{node_id}`: missing working directory or log file"
+            )),
+        };
+        let node_result = restartable.take_pending_result();
+
+        match spawned {
+            Ok(running_node) => {
+                let listener = NodeListener {
+                    communication: running_node.node_config.daemon_communication.clone(),
+                    token: running_node.node_config.token.clone(),
+                };
+                dataflow.running_nodes.insert(node_id.clone(), running_node);
+                self.record_event(dataflow_id, format!("restarted node `{node_id}`"));
+                if let Some(connection) = &mut self.coordinator_connection {
+                    let msg = serde_json::to_vec(&Timestamped {
+                        inner: CoordinatorRequest::Event {
+                            machine_id: self.machine_id.clone(),
+                            event: DaemonEvent::NodeListenerChanged {
+                                dataflow_id,
+                                node_id,
+                                listener,
+                            },
+                        },
+                        timestamp: self.clock.new_timestamp(),
+                    })?;
+                    socket_stream_send(connection, &msg)
+                        .await
+                        .wrap_err("failed to send node listener to dora-coordinator")?;
+                }
+                Ok(RunStatus::Continue)
+            }
+            Err(err) => {
+                self.send_log_message(LogMessage {
+                    dataflow_id,
+                    node_id: Some(node_id.clone()),
+                    level: LogLevel::Error,
+                    target: None,
+                    module_path: None,
+                    file: None,
+                    line: None,
+                    message: format!("{err:?}"),
+                    fields: Default::default(),
+                })
+                .await?;
+                match node_result {
+                    Some(node_result) => {
+                        self.handle_node_result(dataflow_id, node_id, node_result)
+                            .await
+                    }
+                    None => Ok(RunStatus::Continue),
+                }
+            }
+        }
+    }
+
     async fn handle_dora_event(&mut self, event: DoraEvent) -> eyre::Result<RunStatus> {
         match event {
             DoraEvent::Timer {
@@ -1168,16 +3411,41 @@ impl Daemon {
                 };
 
                 let mut closed = Vec::new();
+                let mut throttle_changes = Vec::new();
                 for (receiver_id, input_id) in subscribers {
                     let Some(channel) = dataflow.subscribe_channels.get(receiver_id) else {
                         continue;
                     };
 
+                    let mut metadata = metadata.clone();
+                    let throttle_key = (receiver_id.clone(), input_id.clone());
+                    if let Some(throttle) = dataflow.timer_throttles.get_mut(&throttle_key) {
+                        let queued_inputs = dataflow
+                            .queued_inputs
+                            .get(receiver_id)
+                            .map_or(0, |q| q.load(Ordering::Relaxed) as u64);
+                        let latency = dataflow
+                            .input_latencies
+                            .get(receiver_id)
+                            .map_or(0, |l| l.load(Ordering::Relaxed));
+                        let tick = throttle.tick(queued_inputs, Duration::from_micros(latency));
+                        if let Some(previous) = tick.changed_from {
+                            throttle_changes.push((throttle_key, previous, throttle.factor()));
+                        }
+                        if !tick.deliver {
+                            continue;
+                        }
+                        metadata.parameters.insert(
+                            "dora_throttle_factor".to_string(),
+                            Parameter::Integer(throttle.factor().into()),
+                        );
+                    }
+
                     let send_result = send_with_timestamp(
                         channel,
                         NodeEvent::Input {
                             id: input_id.clone(),
-                            metadata: metadata.clone(),
+                            metadata,
                             data: None,
                         },
                         &self.clock,
@@ -1192,6 +3460,40 @@ impl Daemon {
                 for id in closed {
                     dataflow.subscribe_channels.remove(id);
                 }
+
+                for ((node_id, input_id), previous, factor) in throttle_changes {
+                    let (level, message) = if factor > previous {
+                        (
+                            LogLevel::Warn,
+                            format!(
+                                "node can't keep up, reducing rate of timer input \
+                                `{input_id}` to 1/{factor}"
+                            ),
+                        )
+                    } else if factor > 1 {
+                        (
+                            LogLevel::Info,
+                            format!("raising rate of timer input `{input_id}` to 1/{factor}"),
+                        )
+                    } else {
+                        (
+                            LogLevel::Info,
+                            format!("restored full rate of timer input `{input_id}`"),
+                        )
+                    };
+                    self.send_log_message(LogMessage {
+                        dataflow_id,
+                        node_id: Some(node_id),
+                        level,
+                        target: None,
+                        module_path: None,
+                        file: None,
+                        line: None,
+                        message,
+                        fields: Default::default(),
+                    })
+                    .await?;
+                }
             }
             DoraEvent::Logs {
                 dataflow_id,
@@ -1240,15 +3542,62 @@ impl Daemon {
                     dataflow.subscribe_channels.remove(id);
                 }
             }
+            DoraEvent::NodeOutput {
+                dataflow_id,
+                node_id,
+                stream,
+                text,
+            } => {
+                if let Some(dataflow) = self.running.get_mut(&dataflow_id) {
+                    dataflow
+                        .crash_contexts
+                        .entry(node_id.clone())
+                        .or_default()
+                        .record_output(&text);
+                    dataflow.send_node_output(&node_id, stream, &text, &self.clock);
+                }
+                // without a coordinator, the output is already printed by the daemon itself
+                if self.coordinator_connection.is_some() {
+                    let result = self
+                        .forward_node_output(dataflow_id, &node_id, stream, text)
+                        .await;
+                    if let Err(err) = result {
+                        tracing::warn!("{err:?}")
+                    }
+                }
+            }
             DoraEvent::SpawnedNodeResult {
                 dataflow_id,
                 node_id,
                 exit_status,
+                exit_info,
+                exceeded_memory_limit_mb,
             } => {
+                self.record_event(
+                    dataflow_id,
+                    format!("node `{node_id}` exited: {exit_status:?}"),
+                );
                 let node_result = match exit_status {
                     NodeExitStatus::Success => Ok(()),
                     exit_status => {
                         let dataflow = self.running.get(&dataflow_id);
+                        let flight_recording = match (dataflow, self.working_dir.get(&dataflow_id))
+                        {
+                            (Some(dataflow), Some(working_dir)) => match dataflow
+                                .flight_recorder
+                                .dump(working_dir, &dataflow_id, &node_id)
+                                .await
+                            {
+                                Ok(path) => Some(path),
+                                Err(err) => {
+                                    tracing::warn!(
+                                        "failed to dump flight recorder of `{node_id}`: {err:?}"
+                                    );
+                                    None
+                                }
+                            },
+                            _ => None,
+                        };
                         let caused_by_node = dataflow
                             .and_then(|dataflow| {
                                 dataflow.cascading_error_causes.error_caused_by(&node_id)
@@ -1257,14 +3606,50 @@ impl Daemon {
                         let grace_duration_kill = dataflow
                             .map(|d| d.grace_duration_kills.contains(&node_id))
                             .unwrap_or_default();
+                        let crash_report =
+                            match (&exit_status, dataflow, self.working_dir.get(&dataflow_id)) {
+                                (NodeExitStatus::Signal(_), Some(dataflow), Some(working_dir))
+                                    if !grace_duration_kill =>
+                                {
+                                    let context = dataflow.crash_contexts.get(&node_id);
+                                    let report = CrashReport {
+                                        dataflow_id,
+                                        node_id: node_id.clone(),
+                                        machine_id: self.machine_id.clone(),
+                                        timestamp: self.clock.new_timestamp(),
+                                        exit_status: exit_status.clone(),
+                                        core_dumped: exit_info.core_dumped,
+                                        core_dump_location: exit_info.core_dump_location,
+                                        resource_usage: exit_info.resource_usage,
+                                        log_lines: context
+                                            .map(|c| c.log_lines())
+                                            .unwrap_or_default(),
+                                        recent_inputs: context
+                                            .map(|c| c.recent_inputs())
+                                            .unwrap_or_default(),
+                                        flight_recording: flight_recording.clone(),
+                                    };
+                                    match crash_report::write(working_dir, &report).await {
+                                        Ok(path) => Some(path),
+                                        Err(err) => {
+                                            tracing::warn!("failed to write crash report: {err:?}");
+                                            None
+                                        }
+                                    }
+                                }
+                                _ => None,
+                            };
 
-                        let cause = match caused_by_node {
-                            Some(caused_by_node) => {
+                        let cause = match (caused_by_node, exceeded_memory_limit_mb) {
+                            (Some(caused_by_node), _) => {
                                 tracing::info!("marking `{node_id}` as cascading error caused by `{caused_by_node}`");
                                 NodeErrorCause::Cascading { caused_by_node }
                             }
-                            None if grace_duration_kill => NodeErrorCause::GraceDuration,
-                            None => {
+                            (None, _) if grace_duration_kill => NodeErrorCause::GraceDuration,
+                            (None, Some(limit_mb)) => {
+                                NodeErrorCause::MemoryLimitExceeded { limit_mb }
+                            }
+                            (None, None) => {
                                 let cause = dataflow
                                     .and_then(|d| d.node_stderr_most_recent.get(&node_id))
                                     .map(|queue| {
@@ -1287,6 +3672,8 @@ impl Daemon {
                             timestamp: self.clock.new_timestamp(),
                             cause,
                             exit_status,
+                            flight_recording,
+                            crash_report,
                         })
                     }
                 };
@@ -1307,23 +3694,142 @@ impl Daemon {
                         Ok(()) => format!("{node_id} finished successfully"),
                         Err(err) => format!("{err}"),
                     },
+                    fields: Default::default(),
                 })
                 .await?;
 
-                self.dataflow_node_results
-                    .entry(dataflow_id)
-                    .or_default()
-                    .insert(node_id.clone(), node_result);
-
-                self.handle_node_stop(dataflow_id, &node_id).await?;
+                let scheduled_restart = self.running.get_mut(&dataflow_id).and_then(|dataflow| {
+                    match dataflow.restartable_nodes.get_mut(&node_id) {
+                        Some(restartable) if !dataflow.stop_sent => {
+                            restartable.schedule_restart(&node_result)
+                        }
+                        _ => None,
+                    }
+                });
+                match scheduled_restart {
+                    Some(restart) => {
+                        self.schedule_node_restart(dataflow_id, node_id, restart)
+                            .await?;
+                    }
+                    None => {
+                        return self
+                            .handle_node_result(dataflow_id, node_id, node_result)
+                            .await;
+                    }
+                }
+            }
+            DoraEvent::RestartNode {
+                dataflow_id,
+                node_id,
+            } => return self.restart_node(dataflow_id, node_id).await,
+            DoraEvent::KillNode {
+                dataflow_id,
+                node_id,
+            } => {
+                let Some(dataflow) = self.running.get_mut(&dataflow_id) else {
+                    return Ok(RunStatus::Continue);
+                };
+                let killed = dataflow
+                    .running_nodes
+                    .get_mut(&node_id)
+                    .and_then(|node| node.pid.as_mut())
+                    .map_or(false, |pid| pid.kill());
+                if killed {
+                    self.record_event(dataflow_id, format!("injected kill of node `{node_id}`"));
+                    self.send_log_message(LogMessage {
+                        dataflow_id,
+                        node_id: Some(node_id.clone()),
+                        level: LogLevel::Warn,
+                        target: None,
+                        module_path: None,
+                        file: None,
+                        line: None,
+                        message: format!("killed node `{node_id}` for fault injection"),
+                        fields: [("fault_injection".to_owned(), "kill".to_owned())].into(),
+                    })
+                    .await?;
+                }
+            }
+            DoraEvent::ReplayOutput {
+                dataflow_id,
+                node_id,
+                output_id,
+                metadata,
+                data,
+            } => {
+                if !self.running.contains_key(&dataflow_id) {
+                    tracing::warn!("Replay event for unknown dataflow `{dataflow_id}`");
+                    return Ok(RunStatus::Continue);
+                }
+                // the receivers see the replayed messages as new messages
+                let metadata = metadata::Metadata::from_parameters(
+                    self.clock.new_timestamp(),
+                    metadata.type_info,
+                    metadata.parameters,
+                );
+                let data = data.map(|data| DataMessage::Vec(AVec::from_slice(128, &data)));
+                self.send_out(dataflow_id, node_id, output_id, metadata, data, None)
+                    .await?;
+            }
+            DoraEvent::ReplayFinished {
+                dataflow_id,
+                result,
+            } => {
+                let Some(dataflow) = self.running.get_mut(&dataflow_id) else {
+                    tracing::warn!("Replay event for unknown dataflow `{dataflow_id}`");
+                    return Ok(RunStatus::Continue);
+                };
+                dataflow.replay = None;
+                let replayed_nodes: Vec<_> = dataflow.replayed_nodes.iter().cloned().collect();
+                self.record_event(dataflow_id, format!("replay finished: {result:?}"));
+
+                for node_id in replayed_nodes {
+                    let node_result = result.clone().map_err(|err| NodeError {
+                        timestamp: self.clock.new_timestamp(),
+                        cause: NodeErrorCause::Other {
+                            stderr: err.clone(),
+                        },
+                        exit_status: NodeExitStatus::IoError(err),
+                        flight_recording: None,
+                        crash_report: None,
+                    });
+                    self.send_log_message(LogMessage {
+                        dataflow_id,
+                        node_id: Some(node_id.clone()),
+                        level: if node_result.is_ok() {
+                            LogLevel::Info
+                        } else {
+                            LogLevel::Error
+                        },
+                        target: None,
+                        module_path: None,
+                        file: None,
+                        line: None,
+                        message: match &node_result {
+                            Ok(()) => format!("replay of {node_id} finished"),
+                            Err(err) => format!("replay of {node_id} failed: {err}"),
+                        },
+                        fields: Default::default(),
+                    })
+                    .await?;
 
-                if let Some(exit_when_done) = &mut self.exit_when_done {
-                    exit_when_done.remove(&(dataflow_id, node_id));
-                    if exit_when_done.is_empty() {
-                        tracing::info!(
-                            "exiting daemon because all required dataflows are finished"
-                        );
-                        return Ok(RunStatus::Exit);
+                    if let Some(dataflow) = self.running.get_mut(&dataflow_id) {
+                        dataflow.replayed_nodes.remove(&node_id);
+                    }
+                    self.dataflow_node_results
+                        .entry(dataflow_id)
+                        .or_default()
+                        .insert(node_id.clone(), node_result);
+                    self.handle_node_stop(dataflow_id, &node_id).await?;
+
+                    if let Some(exit_when_done) = &mut self.exit_when_done {
+                        exit_when_done.remove(&(dataflow_id, node_id));
+                        if exit_when_done.is_empty() {
+                            tracing::info!(
+                                "exiting daemon because all required dataflows are finished"
+                            );
+                            return Ok(RunStatus::Exit);
+                        }
                     }
                 }
             }
@@ -1338,7 +3844,7 @@ async fn set_up_event_stream(
     inter_daemon_addr: SocketAddr,
     local_listen_port: u16,
     clock: &Arc<HLC>,
-) -> eyre::Result<(impl Stream<Item = Timestamped<Event>> + Unpin)> {
+) -> eyre::Result<(impl Stream<Item = Timestamped<Event>> + Unpin, u16)> {
     let (events_tx, events_rx) = flume::bounded(10);
     let listen_port =
         inter_daemon::spawn_listener_loop(inter_daemon_addr, machine_id.clone(), events_tx).await?;
@@ -1360,8 +3866,9 @@ async fn set_up_event_stream(
                 },
             );
     let (events_tx, events_rx) = flume::bounded(10);
-    let _listen_port = local_listener::spawn_listener_loop(
+    let local_listen_port = local_listener::spawn_listener_loop(
         (LOCALHOST, local_listen_port).into(),
+        true,
         machine_id.clone(),
         events_tx,
     )
@@ -1371,9 +3878,156 @@ async fn set_up_event_stream(
         timestamp: e.timestamp,
     });
     let incoming = (coordinator_events, daemon_events, dynamic_node_events).merge();
-    Ok(incoming)
+    Ok((incoming, local_listen_port))
+}
+
+/// Outputs of a `send_outputs` batch that are delivered together.
+#[derive(Default)]
+struct OutputBatch {
+    /// Inputs of each local receiver, in the order of the outputs.
+    local: InputBatches,
+    /// Output events for each remote machine.
+    remote: BTreeMap<String, Vec<InterDaemonEvent>>,
+    /// Outputs of the batch that have remote receivers.
+    remote_outputs: Vec<OutputId>,
+    /// Timestamp of the batch if the dataflow uses a simulated clock.
+    simulated_timestamp: Option<uhlc::Timestamp>,
+}
+
+impl OutputBatch {
+    fn add_remote(&mut self, output_id: OutputId, machines: Vec<String>, event: InterDaemonEvent) {
+        if let Some((last, others)) = machines.split_last() {
+            for machine in others {
+                self.remote
+                    .entry(machine.clone())
+                    .or_default()
+                    .push(event.clone());
+            }
+            self.remote.entry(last.clone()).or_default().push(event);
+            self.remote_outputs.push(output_id);
+        }
+    }
+}
+
+type InputBatches = BTreeMap<NodeId, Vec<BatchedDelivery>>;
+
+/// Input of a batch that is delivered to a local receiver.
+struct BatchedDelivery {
+    edge: (OutputId, InputId),
+    latency: Duration,
+    input: BatchedInput,
+    timestamp: uhlc::Timestamp,
+    shared_memory: bool,
+}
+
+/// Sends the inputs of each receiver as a single event.
+///
+/// The receivers were already added to the pending nodes of the drop tokens
+/// of the inputs. They are removed again if their inputs are not delivered.
+async fn deliver_input_batches(
+    dataflow: &mut RunningDataflow,
+    batches: InputBatches,
+    clock: &HLC,
+) -> eyre::Result<()> {
+    for (receiver_id, deliveries) in batches {
+        let (deliveries, mut undelivered): (Vec<_>, Vec<_>) =
+            deliveries.into_iter().partition(|delivery| {
+                dataflow.faults.as_mut().map_or(true, |faults| {
+                    faults.keep_in_batch(&delivery.edge, delivery.shared_memory)
+                })
+            });
+        let delivered = match (
+            dataflow.subscribe_channels.get(&receiver_id),
+            deliveries.first(),
+        ) {
+            (Some(channel), Some(first)) => {
+                let timestamp = first.timestamp;
+                let mut inputs: Vec<_> = deliveries.iter().map(|d| d.input.clone()).collect();
+                // single inputs keep their queue policy
+                let inner = match inputs.len() {
+                    1 => inputs.remove(0).into(),
+                    _ => NodeEvent::InputBatch { inputs },
+                };
+                match channel.send(Timestamped { inner, timestamp }) {
+                    Ok(()) => true,
+                    Err(TrySendError::Full(_)) => false,
+                    Err(TrySendError::Closed(_)) => {
+                        dataflow.subscribe_channels.remove(&receiver_id);
+                        false
+                    }
+                }
+            }
+            _ => false,
+        };
+        if delivered {
+            let now = Instant::now();
+            for delivery in &deliveries {
+                record_delivery(
+                    &mut dataflow.edge_latencies,
+                    &mut dataflow.edge_rates,
+                    delivery.edge.clone(),
+                    delivery.latency,
+                    now,
+                );
+            }
+        } else {
+            undelivered.extend(deliveries);
+        }
+        for delivery in undelivered {
+            if let Some(token) = delivery.input.data.as_ref().and_then(|d| d.drop_token()) {
+                if let Some(info) = dataflow.pending_drop_tokens.get_mut(&token) {
+                    info.pending_nodes.remove(&receiver_id);
+                }
+                dataflow.check_drop_token(token, clock).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn record_delivery(
+    edge_latencies: &mut BTreeMap<(OutputId, InputId), LatencyHistogram>,
+    edge_rates: &mut BTreeMap<(OutputId, InputId), EdgeRate>,
+    edge: (OutputId, InputId),
+    latency: Duration,
+    now: Instant,
+) {
+    edge_latencies
+        .entry(edge.clone())
+        .or_default()
+        .record(latency);
+    edge_rates
+        .entry(edge)
+        .or_insert_with(|| EdgeRate::new(now))
+        .record(now);
+}
+
+/// Adds the receiver to the nodes that still have access to the shared
+/// memory of the given drop token.
+fn add_pending_node(
+    pending_drop_tokens: &mut HashMap<DropToken, DropTokenInformation>,
+    token: DropToken,
+    owner: &NodeId,
+    receiver_id: &NodeId,
+    len: usize,
+) {
+    pending_drop_tokens
+        .entry(token)
+        .or_insert_with(|| DropTokenInformation {
+            owner: owner.clone(),
+            pending_nodes: Default::default(),
+            len,
+            created: Instant::now(),
+        })
+        .pending_nodes
+        .insert(receiver_id.clone());
 }
 
+/// Returns the raw data of the output if it's needed for recording it or for
+/// forwarding it to other machines.
+///
+/// If a `batch` is given, the inputs of the local receivers are added to it
+/// instead of sending them, see [`deliver_input_batches`].
 async fn send_output_to_local_receivers(
     node_id: NodeId,
     output_id: DataId,
@@ -1381,38 +4035,144 @@ async fn send_output_to_local_receivers(
     metadata: &metadata::Metadata,
     data: Option<DataMessage>,
     clock: &HLC,
+    mut batch: Option<&mut InputBatches>,
 ) -> Result<Option<AVec<u8, ConstAlign<128>>>, eyre::ErrReport> {
-    let timestamp = metadata.timestamp();
     let empty_set = BTreeSet::new();
     let output_id = OutputId(node_id, output_id);
+    let timestamp = match &dataflow.simulated_clock {
+        Some(simulated_clock) => {
+            simulated_clock.handle_output(&output_id, metadata);
+            // simulated timestamps might lie in the future, which the clocks
+            // of the receivers would reject
+            clock.new_timestamp()
+        }
+        None => metadata.timestamp(),
+    };
+    // on Windows, named shared memory is freed when its last handle is
+    // closed, so keep it open until all receivers are done with it, even if
+    // the sending node exits in the meantime
+    #[cfg(windows)]
+    if let Some(DataMessage::SharedMemory {
+        shared_memory_id,
+        drop_token,
+        ..
+    }) = &data
+    {
+        if dataflow
+            .mappings
+            .get(&output_id)
+            .is_some_and(|receivers| !receivers.is_empty())
+        {
+            match ShmemConf::new().os_id(shared_memory_id).open() {
+                Ok(memory) => {
+                    dataflow
+                        .retained_shared_memory
+                        .insert(*drop_token, RetainedSharedMemory(memory));
+                }
+                Err(err) => {
+                    tracing::warn!("failed to retain shared memory `{shared_memory_id}`: {err}")
+                }
+            }
+        }
+    }
     let local_receivers = dataflow.mappings.get(&output_id).unwrap_or(&empty_set);
-    let OutputId(node_id, _) = output_id;
+    let node_id = output_id.0.clone();
+    let shared_memory_len = match &data {
+        Some(DataMessage::SharedMemory { len, .. }) => *len,
+        _ => 0,
+    };
+    let data_is_shared_memory = matches!(data, Some(DataMessage::SharedMemory { .. }));
     let mut closed = Vec::new();
     for (receiver_id, input_id) in local_receivers {
+        if receiver_id == &node_id && dataflow.runtime_nodes.contains(&node_id) {
+            // operators of a runtime node receive the outputs of their sibling
+            // operators from the runtime directly
+            continue;
+        }
         if let Some(channel) = dataflow.subscribe_channels.get(receiver_id) {
+            let now = match &dataflow.simulated_clock {
+                Some(simulated_clock) => simulated_clock.new_timestamp(),
+                None => clock.new_timestamp(),
+            };
+            let latency = metadata.age_at(now);
+            let edge = (output_id.clone(), (receiver_id.clone(), input_id.clone()));
+            dataflow
+                .crash_contexts
+                .entry(receiver_id.clone())
+                .or_default()
+                .record_input(input_id, metadata);
+            let mut metadata = metadata.clone();
+            trace_hop(
+                &mut dataflow.trace_sampling,
+                Hop::Delivery(&output_id),
+                &mut metadata,
+                || tracing::trace_span!("node_delivery", receiver = %receiver_id, input = %input_id),
+            );
+            if let Some(batch) = &mut batch {
+                // register the receiver now, the drop token is checked below
+                if let Some(token) = data.as_ref().and_then(|d| d.drop_token()) {
+                    add_pending_node(
+                        &mut dataflow.pending_drop_tokens,
+                        token,
+                        &node_id,
+                        receiver_id,
+                        shared_memory_len,
+                    );
+                }
+                batch
+                    .entry(receiver_id.clone())
+                    .or_default()
+                    .push(BatchedDelivery {
+                        edge,
+                        latency,
+                        input: BatchedInput {
+                            id: input_id.clone(),
+                            metadata,
+                            data: data.clone(),
+                        },
+                        timestamp,
+                        shared_memory: data_is_shared_memory,
+                    });
+                continue;
+            }
             let item = NodeEvent::Input {
                 id: input_id.clone(),
-                metadata: metadata.clone(),
+                metadata,
                 data: data.clone(),
             };
-            match channel.send(Timestamped {
+            let event = Timestamped {
                 inner: item,
                 timestamp,
-            }) {
-                Ok(()) => {
+            };
+            let delivery = match &mut dataflow.faults {
+                Some(faults) => faults.deliver(channel, &edge, event, data_is_shared_memory),
+                None => match channel.send(event) {
+                    Ok(()) => Delivery::Delivered,
+                    Err(TrySendError::Full(_)) => Delivery::Dropped,
+                    Err(TrySendError::Closed(_)) => Delivery::Closed,
+                },
+            };
+            match delivery {
+                Delivery::Delivered => {
+                    record_delivery(
+                        &mut dataflow.edge_latencies,
+                        &mut dataflow.edge_rates,
+                        edge,
+                        latency,
+                        Instant::now(),
+                    );
                     if let Some(token) = data.as_ref().and_then(|d| d.drop_token()) {
-                        dataflow
-                            .pending_drop_tokens
-                            .entry(token)
-                            .or_insert_with(|| DropTokenInformation {
-                                owner: node_id.clone(),
-                                pending_nodes: Default::default(),
-                            })
-                            .pending_nodes
-                            .insert(receiver_id.clone());
+                        add_pending_node(
+                            &mut dataflow.pending_drop_tokens,
+                            token,
+                            &node_id,
+                            receiver_id,
+                            shared_memory_len,
+                        );
                     }
                 }
-                Err(_) => {
+                Delivery::Dropped => {}
+                Delivery::Closed => {
                     closed.push(receiver_id);
                 }
             }
@@ -1421,8 +4181,18 @@ async fn send_output_to_local_receivers(
     for id in closed {
         dataflow.subscribe_channels.remove(id);
     }
+    // local receivers map the shared memory themselves, the bytes are only
+    // needed for recording and for forwarding to other machines
+    let bytes_needed = dataflow.recorder.is_some()
+        || dataflow
+            .open_external_mappings
+            .get(&output_id)
+            .is_some_and(|mappings| !mappings.is_empty());
     let (data_bytes, drop_token) = match data {
         None => (None, None),
+        Some(DataMessage::SharedMemory { drop_token, .. }) if !bytes_needed => {
+            (None, Some(drop_token))
+        }
         Some(DataMessage::SharedMemory {
             shared_memory_id,
             len,
@@ -1445,6 +4215,8 @@ async fn send_output_to_local_receivers(
             .or_insert_with(|| DropTokenInformation {
                 owner: node_id.clone(),
                 pending_nodes: Default::default(),
+                len: shared_memory_len,
+                created: Instant::now(),
             });
         // check if all local subscribers are finished with the token
         dataflow.check_drop_token(token, clock).await?;
@@ -1452,6 +4224,54 @@ async fn send_output_to_local_receivers(
     Ok(data_bytes)
 }
 
+/// Records a hop of a message through the daemons as a span.
+///
+/// The span becomes a child of the `open_telemetry_context` of the message
+/// and replaces it, so that the spans of all hops form a single trace, even
+/// across machines. Messages without a context are not traced.
+///
+/// Depending on the telemetry configuration of the dataflow, no span is
+/// recorded for the hop or the context is removed to end the trace.
+#[cfg(feature = "telemetry")]
+fn trace_hop(
+    sampling: &mut TraceSampling,
+    hop: Hop,
+    metadata: &mut metadata::Metadata,
+    span: impl FnOnce() -> tracing::Span,
+) {
+    let parent = metadata.open_telemetry_context();
+    if parent.is_empty() {
+        return;
+    }
+    match sampling.admit(hop, parent.len()) {
+        trace_sampling::Admission::Record => {}
+        trace_sampling::Admission::Skip => return,
+        trace_sampling::Admission::Stop => {
+            metadata.parameters.remove("open_telemetry_context");
+            return;
+        }
+    }
+    let span = span();
+    if span.is_disabled() {
+        // keep the previous context to not break the trace
+        return;
+    }
+    span.set_parent(deserialize_context(&parent));
+    metadata.parameters.insert(
+        "open_telemetry_context".to_string(),
+        Parameter::String(serialize_context(&span.context())),
+    );
+}
+
+#[cfg(not(feature = "telemetry"))]
+fn trace_hop(
+    _sampling: &mut TraceSampling,
+    _hop: Hop,
+    _metadata: &mut metadata::Metadata,
+    _span: impl FnOnce() -> tracing::Span,
+) {
+}
+
 fn node_inputs(node: &ResolvedNode) -> BTreeMap<DataId, Input> {
     match &node.kind {
         CoreNodeKind::Custom(n) => n.run_config.inputs.clone(),
@@ -1459,9 +4279,26 @@ fn node_inputs(node: &ResolvedNode) -> BTreeMap<DataId, Input> {
     }
 }
 
+/// Inputs of the node whose senders the daemon can't block while the event
+/// queue of the node is full, see `BackpressureStrategy::Block`.
+fn unblockable_inputs(
+    node: &ResolvedNode,
+    blockable_sources: &BTreeSet<NodeId>,
+) -> BTreeSet<DataId> {
+    node_inputs(node)
+        .into_iter()
+        .filter(|(_, input)| match &input.mapping {
+            InputMapping::User(mapping) => !blockable_sources.contains(&mapping.source),
+            InputMapping::Timer { .. } | InputMapping::Status => true,
+        })
+        .map(|(input_id, _)| input_id)
+        .collect()
+}
+
 async fn send_input_closed_events<F>(
     dataflow: &mut RunningDataflow,
     inter_daemon_connections: &mut BTreeMap<String, InterDaemonConnection>,
+    machine_id: &str,
     mut filter: F,
     clock: &HLC,
 ) -> eyre::Result<()>
@@ -1487,10 +4324,24 @@ where
     }
     if !external_node_inputs.is_empty() {
         for (target_machine, inputs) in external_node_inputs {
+            let tag = dataflow
+                .cipher
+                .as_mut()
+                .map(|cipher| {
+                    cipher.authenticate(
+                        machine_id,
+                        &AuthenticatedEvent::InputsClosed {
+                            dataflow_id: &dataflow.id,
+                            inputs: &inputs,
+                        },
+                    )
+                })
+                .transpose()?;
             let event = Timestamped {
                 inner: InterDaemonEvent::InputsClosed {
                     dataflow_id: dataflow.id,
                     inputs,
+                    tag,
                 },
                 timestamp: clock.new_timestamp(),
             };
@@ -1532,38 +4383,200 @@ fn close_input(
     }
 }
 
+/// Dataflow that the daemon should spawn, see [`SpawnDataflowNodes`].
+struct SpawnDataflowParams {
+    dataflow_id: DataflowId,
+    working_dir: PathBuf,
+    nodes: Vec<ResolvedNode>,
+    dataflow_descriptor: Descriptor,
+    uv: bool,
+    /// Re-adopt the still running nodes instead of spawning them.
+    adopt: bool,
+    /// Listeners of the re-adopted nodes.
+    node_listeners: BTreeMap<NodeId, NodeListener>,
+    /// Key for the outputs that are forwarded to other daemons.
+    encryption_key: Option<EncryptionKey>,
+}
+
 #[derive(Debug)]
 struct RunningNode {
     pid: Option<ProcessId>,
     node_config: NodeConfig,
+    /// Signal that is sent to the node on stop, in addition to the in-band
+    /// [`NodeEvent::Stop`].
+    stop_signal: Option<StopSignal>,
+    /// Signals that the daemon forwards to the node when it receives them.
+    forward_signals: Vec<StopSignal>,
+    /// Time that the node gets to exit after the dataflow was stopped.
+    grace_duration: Option<Duration>,
+    /// Cgroup that the node was placed in, for resource accounting.
+    cgroup: Option<cgroup::NodeCgroup>,
+    /// Removes the container of the node when it's dropped.
+    _container: Option<container::ContainerGuard>,
 }
 
 #[derive(Debug)]
-struct ProcessId(Option<u32>);
+struct ProcessId(
+    Option<u32>,
+    /// Start time of the process, so that a reused process ID isn't mistaken
+    /// for the node after it exited.
+    Option<u64>,
+    /// Kills the child processes of the node together with it.
+    #[cfg(windows)]
+    Option<Arc<job_object::JobObject>>,
+);
 
 impl ProcessId {
     pub fn new(process_id: u32) -> Self {
-        Self(Some(process_id))
+        let mut system = sysinfo::System::new();
+        let start_time = system
+            .refresh_process(Pid::from_u32(process_id))
+            .then(|| system.process(Pid::from_u32(process_id)))
+            .flatten()
+            .map(|process| process.start_time());
+        #[cfg(windows)]
+        {
+            let job = job_object::JobObject::assign(process_id)
+                .inspect_err(|err| {
+                    warn!("child processes of {process_id} might outlive it: {err:?}")
+                })
+                .ok()
+                .map(Arc::new);
+            Self(Some(process_id), start_time, job)
+        }
+        #[cfg(not(windows))]
+        Self(Some(process_id), start_time)
     }
 
     pub fn mark_as_stopped(&mut self) {
         self.0 = None;
     }
 
-    pub fn kill(&mut self) -> bool {
-        if let Some(pid) = self.0 {
-            let mut system = sysinfo::System::new();
-            system.refresh_processes();
+    #[cfg(windows)]
+    pub fn job(&self) -> Option<&Arc<job_object::JobObject>> {
+        self.2.as_ref()
+    }
 
-            if let Some(process) = system.process(Pid::from(pid as usize)) {
-                process.kill();
+    /// Looks up the process of the node, if it's still running.
+    ///
+    /// Only refreshes the information of this process instead of scanning
+    /// the whole process table, but still does blocking file system
+    /// accesses on some platforms.
+    fn process<'a>(&self, system: &'a mut sysinfo::System) -> Option<&'a sysinfo::Process> {
+        let pid = Pid::from_u32(self.0?);
+        if !system.refresh_process(pid) {
+            return None;
+        }
+        system
+            .process(pid)
+            .filter(|process| self.1.map_or(true, |start| process.start_time() == start))
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.process(&mut sysinfo::System::new()).is_some()
+    }
+
+    /// Sends the signal to the process group of the node, if it's still
+    /// running.
+    pub fn signal(&self, signal: StopSignal) -> eyre::Result<()> {
+        match self.0 {
+            Some(pid) => crate::signal::send_to_node(pid, signal)
+                .wrap_err_with(|| format!("failed to send {signal} to process {pid}")),
+            None => Ok(()),
+        }
+    }
+
+    pub fn kill(&mut self) -> bool {
+        #[cfg(windows)]
+        if let (Some(_), Some(job)) = (self.0, &self.2) {
+            if job.kill() {
                 self.mark_as_stopped();
                 return true;
             }
         }
+        let mut system = sysinfo::System::new();
+        if let Some(process) = self.process(&mut system) {
+            process.kill();
+            self.mark_as_stopped();
+            return true;
+        }
 
         false
     }
+
+    /// Runs the given function on the blocking thread pool, as the process
+    /// lookups must not block the runtime.
+    async fn blocking<T: Send + 'static>(
+        mut self,
+        f: impl FnOnce(&mut Self) -> T + Send + 'static,
+    ) -> Option<(Self, T)> {
+        tokio::task::spawn_blocking(move || {
+            let result = f(&mut self);
+            (self, result)
+        })
+        .await
+        .ok()
+    }
+}
+
+/// Waits until the receiver has room in its event queue or exits.
+async fn wait_for_queue_space(
+    queued_inputs: &AtomicUsize,
+    inputs_taken: &Notify,
+    channel: &EventSender,
+    capacity: usize,
+) {
+    loop {
+        let taken = inputs_taken.notified();
+        tokio::pin!(taken);
+        // register before checking the queue, so that no notification is missed
+        taken.as_mut().enable();
+        if !queue_is_full(queued_inputs, channel, capacity) || channel.is_closed() {
+            return;
+        }
+        tokio::select! {
+            () = taken => {}
+            () = channel.closed() => {}
+        }
+    }
+}
+
+/// Terminates a node that is still running after its grace duration, first
+/// through `SIGTERM` and then by killing it.
+async fn terminate_after_grace_duration(
+    node_id: NodeId,
+    pid: ProcessId,
+    grace_duration: Duration,
+    grace_duration_kills: Arc<crossbeam_skiplist::SkipSet<NodeId>>,
+) {
+    tokio::time::sleep(grace_duration).await;
+    let running = pid.blocking(|pid| {
+        let running = pid.is_running();
+        if !running {
+            // don't look up the process again on drop
+            pid.mark_as_stopped();
+        }
+        running
+    });
+    let Some((pid, true)) = running.await else {
+        return;
+    };
+    // mark the node first, so that its exit is reported as caused by dora
+    grace_duration_kills.insert(node_id.clone());
+    warn!("{node_id} didn't stop within the {grace_duration:#?} grace period, sending SIGTERM");
+    if let Err(err) = pid.signal(StopSignal::Terminate) {
+        warn!("{node_id}: {err:?}");
+    }
+
+    tokio::time::sleep(TERMINATE_TIMEOUT).await;
+    let killed = pid.blocking(|pid| {
+        let killed = pid.kill();
+        pid.mark_as_stopped();
+        killed
+    });
+    if let Some((_, true)) = killed.await {
+        warn!("{node_id} was killed because it didn't exit within {TERMINATE_TIMEOUT:#?} after SIGTERM");
+    }
 }
 
 impl Drop for ProcessId {
@@ -1582,22 +4595,42 @@ pub struct RunningDataflow {
     /// Local nodes that are not started yet
     pending_nodes: PendingNodes,
 
-    subscribe_channels: HashMap<NodeId, UnboundedSender<Timestamped<NodeEvent>>>,
+    subscribe_channels: HashMap<NodeId, EventSender>,
     drop_channels: HashMap<NodeId, UnboundedSender<Timestamped<NodeDropEvent>>>,
     mappings: HashMap<OutputId, BTreeSet<InputId>>,
     timers: BTreeMap<Duration, BTreeSet<InputId>>,
+    /// Local inputs that receive the `dora/status` reports.
+    status_subscribers: BTreeSet<InputId>,
     open_inputs: BTreeMap<NodeId, BTreeSet<DataId>>,
     running_nodes: BTreeMap<NodeId, RunningNode>,
 
+    /// Whether the dataflow was re-adopted after a daemon restart.
+    ///
+    /// The processes of adopted nodes were spawned by the previous daemon
+    /// instance and reconnect on their own. Since they are not child processes
+    /// of this daemon, crashes of adopted nodes are not detected and they can't
+    /// be killed after the stop grace duration.
+    adopted: bool,
+
     /// List of all dynamic node IDs.
     ///
     /// We want to treat dynamic nodes differently in some cases, so we need
     /// to know which nodes are dynamic.
     dynamic_nodes: BTreeSet<NodeId>,
+    /// Local runtime nodes, which pass data between their operators directly.
+    runtime_nodes: BTreeSet<NodeId>,
 
     open_external_mappings: HashMap<OutputId, BTreeMap<String, BTreeSet<InputId>>>,
+    /// Machine of each node that runs on a different daemon.
+    remote_nodes: BTreeMap<NodeId, String>,
+    /// Time of the last backpressure event sent for each local output.
+    last_backpressure: HashMap<OutputId, Instant>,
 
     pending_drop_tokens: HashMap<DropToken, DropTokenInformation>,
+    /// Shared memory regions of the pending drop tokens, see
+    /// [`RetainedSharedMemory`].
+    #[cfg(windows)]
+    retained_shared_memory: HashMap<DropToken, RetainedSharedMemory>,
 
     /// Keep handles to all timer tasks of this dataflow to cancel them on drop.
     _timer_handles: Vec<futures::future::RemoteHandle<()>>,
@@ -1613,10 +4646,89 @@ pub struct RunningDataflow {
     grace_duration_kills: Arc<crossbeam_skiplist::SkipSet<NodeId>>,
 
     node_stderr_most_recent: BTreeMap<NodeId, Arc<ArrayQueue<String>>>,
+
+    /// Latest health report of each local node.
+    node_health: BTreeMap<NodeId, NodeHealth>,
+    log_rate_limiters: BTreeMap<NodeId, log::LogRateLimiter>,
+    /// Latencies of the messages delivered to local nodes, from the send
+    /// timestamp of the message until the delivery by this daemon.
+    edge_latencies: BTreeMap<(OutputId, InputId), LatencyHistogram>,
+    /// Message rates of the edges to local nodes.
+    edge_rates: BTreeMap<(OutputId, InputId), EdgeRate>,
+    /// Log file of each local node, shared with the task that captures the
+    /// output of the node process.
+    log_files: BTreeMap<NodeId, log::SharedNodeLogFile>,
+    /// Recent events of this dataflow, dumped when a node fails.
+    flight_recorder: flight_recorder::FlightRecorder,
+    /// Number of inputs queued in the listener of each local node.
+    queued_inputs: BTreeMap<NodeId, Arc<AtomicUsize>>,
+    /// Notified when a local node takes inputs from its listener.
+    inputs_taken: BTreeMap<NodeId, Arc<Notify>>,
+    /// Time in microseconds that the inputs of each local node last waited
+    /// in its listener.
+    input_latencies: BTreeMap<NodeId, Arc<AtomicU64>>,
+    /// Rate reduction of the timer inputs with a `throttle` config.
+    timer_throttles: BTreeMap<(NodeId, DataId), timer_throttle::Throttle>,
+    queue_monitor: queue_metrics::QueueMonitor,
+    slow_consumers: slow_consumer::SlowConsumerDetector,
+    /// Recent output lines and inputs of each local node, for crash reports.
+    crash_contexts: BTreeMap<NodeId, crash_report::CrashContext>,
+    trace_sampling: TraceSampling,
+    /// Active message recording, started by the coordinator.
+    recorder: Option<recording::Recorder>,
+    /// Local nodes that are replaced by a recording and whose replay is not
+    /// finished yet.
+    replayed_nodes: BTreeSet<NodeId>,
+    replay: Option<replay::Replay>,
+    /// Clock of the dataflow if it runs on simulated time.
+    simulated_clock: Option<Arc<sim_time::SimulatedClock>>,
+    /// Advances the simulated clock, unless it is advanced by a simulator
+    /// node.
+    _clock_driver: Option<futures::future::RemoteHandle<()>>,
+    /// Injected faults, for robustness tests.
+    faults: Option<fault_injection::FaultInjector>,
+    /// Encrypts the outputs that are exchanged with other daemons, if the
+    /// dataflow enables `security.encrypt_remote`.
+    cipher: Option<encryption::OutputCipher>,
+    /// Latest state that each local node stored for checkpoints.
+    node_states: BTreeMap<NodeId, Vec<u8>>,
+    queue_snapshots: BTreeMap<NodeId, checkpoint::QueueSnapshotRequests>,
+    /// Node states of the checkpoint that the dataflow was restored from.
+    restored_states: BTreeMap<NodeId, Vec<u8>>,
+    /// Queued inputs of the restored checkpoint, delivered on start.
+    restored_inputs: BTreeMap<NodeId, Vec<checkpoint::QueuedInput>>,
+    /// Local replica of the dataflow blackboard.
+    ///
+    /// Writes of local nodes are applied immediately and forwarded to the
+    /// coordinator, which broadcasts them to all daemons of the dataflow in a
+    /// single order. This way, concurrent writes converge to the same value on
+    /// all machines.
+    blackboard: BTreeMap<String, Vec<u8>>,
+    /// Key prefixes that each local node watches for blackboard changes.
+    blackboard_watches: BTreeMap<NodeId, BTreeSet<String>>,
+    /// Leader elections that local nodes campaign in.
+    elections: election::LocalElections,
+    /// Local nodes with a restart policy.
+    restartable_nodes: BTreeMap<NodeId, restart::RestartableNode>,
+    /// Local nodes that receive the stdout and stderr output of other local
+    /// nodes, with the nodes that they subscribed to (all if empty).
+    output_subscribers: BTreeMap<NodeId, BTreeSet<NodeId>>,
+    /// Bound of the event queues of the local nodes.
+    event_queues: EventQueueConfig,
+    /// Local nodes whose outputs are sent by their own processes, which the
+    /// daemon can block while the event queues of the receivers are full.
+    blockable_sources: BTreeSet<NodeId>,
+    /// Receivers that each blocked node waits for, see
+    /// [`Daemon::reply_when_receivers_ready`].
+    blocked_senders: Arc<std::sync::Mutex<BTreeMap<NodeId, BTreeSet<NodeId>>>>,
+    /// Number of messages of each input of the local nodes that were dropped
+    /// because of its `queue_policy` or a full event queue, by the listener
+    /// or by the node itself.
+    dropped_inputs: BTreeMap<NodeId, BTreeMap<DataId, u64>>,
 }
 
 impl RunningDataflow {
-    fn new(dataflow_id: Uuid, machine_id: String) -> RunningDataflow {
+    fn new(dataflow_id: Uuid, machine_id: String, footprint: Footprint) -> RunningDataflow {
         Self {
             id: dataflow_id,
             pending_nodes: PendingNodes::new(dataflow_id, machine_id),
@@ -1624,17 +4736,115 @@ impl RunningDataflow {
             drop_channels: HashMap::new(),
             mappings: HashMap::new(),
             timers: BTreeMap::new(),
+            status_subscribers: BTreeSet::new(),
             open_inputs: BTreeMap::new(),
             running_nodes: BTreeMap::new(),
+            adopted: false,
             dynamic_nodes: BTreeSet::new(),
+            runtime_nodes: BTreeSet::new(),
             open_external_mappings: HashMap::new(),
+            remote_nodes: BTreeMap::new(),
+            last_backpressure: HashMap::new(),
             pending_drop_tokens: HashMap::new(),
+            #[cfg(windows)]
+            retained_shared_memory: HashMap::new(),
             _timer_handles: Vec::new(),
             stop_sent: false,
             empty_set: BTreeSet::new(),
             cascading_error_causes: Default::default(),
             grace_duration_kills: Default::default(),
             node_stderr_most_recent: BTreeMap::new(),
+            node_health: BTreeMap::new(),
+            log_rate_limiters: BTreeMap::new(),
+            log_files: BTreeMap::new(),
+            edge_latencies: BTreeMap::new(),
+            edge_rates: BTreeMap::new(),
+            flight_recorder: flight_recorder::FlightRecorder::new(footprint),
+            queued_inputs: BTreeMap::new(),
+            inputs_taken: BTreeMap::new(),
+            input_latencies: BTreeMap::new(),
+            timer_throttles: BTreeMap::new(),
+            queue_monitor: Default::default(),
+            slow_consumers: Default::default(),
+            crash_contexts: BTreeMap::new(),
+            trace_sampling: TraceSampling::new(&Default::default()),
+            recorder: None,
+            replayed_nodes: BTreeSet::new(),
+            replay: None,
+            simulated_clock: None,
+            _clock_driver: None,
+            faults: None,
+            cipher: None,
+            node_states: BTreeMap::new(),
+            queue_snapshots: BTreeMap::new(),
+            restored_states: BTreeMap::new(),
+            restored_inputs: BTreeMap::new(),
+            blackboard: BTreeMap::new(),
+            blackboard_watches: BTreeMap::new(),
+            elections: Default::default(),
+            restartable_nodes: BTreeMap::new(),
+            output_subscribers: BTreeMap::new(),
+            event_queues: Default::default(),
+            blockable_sources: BTreeSet::new(),
+            blocked_senders: Default::default(),
+            dropped_inputs: BTreeMap::new(),
+        }
+    }
+
+    /// Notifies the local candidates of the group about its leader.
+    fn announce_leader(&mut self, group: &str, leader: Option<NodeId>, clock: &HLC) {
+        for (node_id, event) in self.elections.set_leader(group, leader) {
+            if let Some(channel) = self.subscribe_channels.get(&node_id) {
+                let _ = send_with_timestamp(channel, event, clock);
+            }
+        }
+    }
+
+    /// Forwards output of a local node to the nodes that subscribed to it.
+    fn send_node_output(&self, node_id: &NodeId, stream: log::NodeStdio, text: &str, clock: &HLC) {
+        for (subscriber, nodes) in &self.output_subscribers {
+            if subscriber == node_id || !(nodes.is_empty() || nodes.contains(node_id)) {
+                continue;
+            }
+            if let Some(channel) = self.subscribe_channels.get(subscriber) {
+                let event = NodeEvent::NodeOutput {
+                    node_id: node_id.clone(),
+                    stream: stream.as_str().to_owned(),
+                    text: text.to_owned(),
+                };
+                let _ = send_with_timestamp(channel, event, clock);
+            }
+        }
+    }
+
+    /// Applies a blackboard write and notifies the watching nodes if the
+    /// value changed.
+    fn update_blackboard(&mut self, key: String, value: Option<Vec<u8>>, clock: &HLC) {
+        let previous = match &value {
+            Some(value) => self.blackboard.insert(key.clone(), value.clone()),
+            None => self.blackboard.remove(&key),
+        };
+        if previous == value {
+            return;
+        }
+        for (node_id, prefixes) in &self.blackboard_watches {
+            if !prefixes
+                .iter()
+                .any(|prefix| key.starts_with(prefix.as_str()))
+            {
+                continue;
+            }
+            let Some(channel) = self.subscribe_channels.get(node_id) else {
+                continue;
+            };
+            let _ = send_with_timestamp(
+                channel,
+                NodeEvent::BlackboardChanged {
+                    key: key.clone(),
+                    value: value.clone(),
+                },
+                clock,
+            );
         }
     }
 
@@ -1643,30 +4853,73 @@ impl RunningDataflow {
         events_tx: &mpsc::Sender<Timestamped<Event>>,
         clock: &Arc<HLC>,
     ) -> eyre::Result<()> {
+        if let Some(simulated_clock) = &self.simulated_clock {
+            self._clock_driver = simulated_clock.drive();
+        }
+        for (node_id, inputs) in std::mem::take(&mut self.restored_inputs) {
+            let Some(channel) = self.subscribe_channels.get(&node_id) else {
+                continue;
+            };
+            let open_inputs = self.open_inputs.get(&node_id);
+            for input in inputs {
+                if !open_inputs.is_some_and(|open| open.contains(&input.id)) {
+                    continue;
+                }
+                let event = NodeEvent::Input {
+                    id: input.id,
+                    metadata: input.metadata,
+                    data: input
+                        .data
+                        .map(|data| DataMessage::Vec(AVec::from_slice(128, &data))),
+                };
+                let _ = send_with_timestamp(channel, event, clock);
+            }
+        }
         for interval in self.timers.keys().copied() {
             let events_tx = events_tx.clone();
             let dataflow_id = self.id;
             let clock = clock.clone();
+            let simulated_clock = self.simulated_clock.clone();
+            #[cfg(feature = "telemetry")]
+            let mut sampler = self.trace_sampling.root_sampler();
             let task = async move {
                 let mut interval_stream = tokio::time::interval(interval);
+                let mut next_simulated_tick = simulated_clock.as_ref().map(|c| c.now());
                 let hlc = HLC::default();
                 loop {
-                    interval_stream.tick().await;
+                    let timestamp = match (&simulated_clock, &mut next_simulated_tick) {
+                        (Some(simulated_clock), Some(next_tick)) => {
+                            simulated_clock.sleep_until(*next_tick).await;
+                            let timestamp = simulated_clock.timestamp_at(*next_tick);
+                            *next_tick += interval;
+                            timestamp
+                        }
+                        _ => {
+                            interval_stream.tick().await;
+                            hlc.new_timestamp()
+                        }
+                    };
 
-                    let span = tracing::span!(tracing::Level::TRACE, "tick");
-                    let _ = span.enter();
+                    // only the sampled ticks start a trace
+                    #[cfg(feature = "telemetry")]
+                    let context = if sampler.sample() {
+                        let span = tracing::span!(tracing::Level::TRACE, "tick");
+                        let _ = span.enter();
+                        serialize_context(&span.context())
+                    } else {
+                        String::new()
+                    };
+                    #[cfg(not(feature = "telemetry"))]
+                    let context = String::new();
 
                     let mut parameters = BTreeMap::new();
                     parameters.insert(
                         "open_telemetry_context".to_string(),
-                        #[cfg(feature = "telemetry")]
-                        Parameter::String(serialize_context(&span.context())),
-                        #[cfg(not(feature = "telemetry"))]
-                        Parameter::String("".into()),
+                        Parameter::String(context),
                     );
 
                     let metadata = metadata::Metadata::from_parameters(
-                        hlc.new_timestamp(),
+                        timestamp,
                         empty_type_info(),
                         parameters,
                     );
@@ -1689,6 +4942,42 @@ impl RunningDataflow {
             tokio::spawn(task);
             self._timer_handles.push(handle);
         }
+        if let Some(replay) = &mut self.replay {
+            replay.start(self.id, events_tx, clock);
+        }
+        let kills = self.faults.as_ref().map(|f| f.kills()).unwrap_or_default();
+        for kill in kills {
+            if !self.running_nodes.contains_key(&kill.node) {
+                continue;
+            }
+            let events_tx = events_tx.clone();
+            let dataflow_id = self.id;
+            let node_id = kill.node.clone();
+            let after = Duration::from_millis(kill.after_ms);
+            let clock = clock.clone();
+            let simulated_clock = self.simulated_clock.clone();
+            let task = async move {
+                match simulated_clock {
+                    Some(simulated_clock) => {
+                        let start = simulated_clock.now();
+                        simulated_clock.sleep_until(start + after).await
+                    }
+                    None => tokio::time::sleep(after).await,
+                }
+                let event = Timestamped {
+                    inner: DoraEvent::KillNode {
+                        dataflow_id,
+                        node_id,
+                    }
+                    .into(),
+                    timestamp: clock.new_timestamp(),
+                };
+                let _ = events_tx.send(event).await;
+            };
+            let (task, handle) = task.remote_handle();
+            tokio::spawn(task);
+            self._timer_handles.push(handle);
+        }
 
         Ok(())
     }
@@ -1711,49 +5000,228 @@ impl RunningDataflow {
         for (_node_id, channel) in self.subscribe_channels.drain() {
             let _ = send_with_timestamp(&channel, NodeEvent::Stop, clock);
         }
-
-        let running_processes: Vec<_> = self
-            .running_nodes
-            .iter_mut()
-            .map(|(id, n)| (id.clone(), n.pid.take()))
-            .collect();
-        let grace_duration_kills = self.grace_duration_kills.clone();
-        tokio::spawn(async move {
-            let duration = grace_duration.unwrap_or(Duration::from_millis(15000));
-            tokio::time::sleep(duration).await;
-
-            for (node, pid) in running_processes {
-                if let Some(mut pid) = pid {
-                    if pid.kill() {
-                        grace_duration_kills.insert(node.clone());
-                        warn!(
-                            "{node} was killed due to not stopping within the {:#?} grace period",
-                            duration
-                        )
-                    }
+        for (node_id, node) in &self.running_nodes {
+            if let (Some(signal), Some(pid)) = (node.stop_signal, &node.pid) {
+                if let Err(err) = pid.signal(signal) {
+                    warn!("{node_id}: {err:?}");
                 }
             }
-        });
+        }
+        if let Some(replay) = &mut self.replay {
+            if !replay.stop() {
+                // the replay was never started, so it won't report its end
+                self.replayed_nodes.clear();
+            }
+        }
+
+        for (node_id, node) in &mut self.running_nodes {
+            let Some(pid) = node.pid.take() else {
+                continue;
+            };
+            let duration = grace_duration
+                .or(node.grace_duration)
+                .unwrap_or(DEFAULT_GRACE_DURATION);
+            tokio::spawn(terminate_after_grace_duration(
+                node_id.clone(),
+                pid,
+                duration,
+                self.grace_duration_kills.clone(),
+            ));
+        }
+        // nodes that wait for a restart are not restarted anymore
+        for node in self.restartable_nodes.values_mut() {
+            node.skip_backoff();
+        }
         self.stop_sent = true;
         Ok(())
     }
 
+    /// Sends the signal to the running nodes that were spawned by this daemon
+    /// and opted in to it through `forward_signals`.
+    fn forward_signal(&self, signal: StopSignal) {
+        for (node_id, node) in &self.running_nodes {
+            if !node.forward_signals.contains(&signal) {
+                continue;
+            }
+            if let Some(pid) = &node.pid {
+                if let Err(err) = pid.signal(signal) {
+                    warn!("{node_id}: {err:?}");
+                }
+            }
+        }
+    }
+
     fn open_inputs(&self, node_id: &NodeId) -> &BTreeSet<DataId> {
         self.open_inputs.get(node_id).unwrap_or(&self.empty_set)
     }
 
+    /// Notifies a local node that a receiver of its output can't keep up.
+    ///
+    /// Reports for the same output are rate-limited to one per
+    /// [`BACKPRESSURE_NOTIFY_INTERVAL`].
+    fn send_backpressure(&mut self, node_id: &NodeId, output_id: DataId, level: f32, clock: &HLC) {
+        let Some(channel) = self.subscribe_channels.get(node_id) else {
+            return;
+        };
+        let output_id = OutputId(node_id.clone(), output_id);
+        let now = Instant::now();
+        if let Some(last) = self.last_backpressure.get(&output_id) {
+            if now.duration_since(*last) < BACKPRESSURE_NOTIFY_INTERVAL {
+                return;
+            }
+        }
+        let event = NodeEvent::Backpressure {
+            output_id: output_id.1.clone(),
+            level,
+        };
+        if send_with_timestamp(channel, event, clock).is_ok() {
+            self.last_backpressure.insert(output_id, now);
+        }
+    }
+
+    /// Live state of the local nodes and the edges to their inputs.
+    ///
+    /// The `node_results` of exited nodes take precedence over the state of
+    /// running nodes.
+    fn live_graph(
+        &self,
+        node_results: Option<&BTreeMap<NodeId, Result<(), NodeError>>>,
+        now: Instant,
+    ) -> DaemonLiveGraph {
+        let mut nodes: BTreeMap<_, _> = self
+            .running_nodes
+            .keys()
+            .map(|node_id| {
+                let state = if self.pending_nodes.is_pending(node_id) {
+                    NodeState::Starting
+                } else {
+                    NodeState::Running
+                };
+                (node_id.clone(), state)
+            })
+            .collect();
+        for (node_id, result) in node_results.into_iter().flatten() {
+            nodes.insert(node_id.clone(), node_state(result));
+        }
+
+        let edges = self
+            .mappings
+            .iter()
+            .flat_map(|(output_id, inputs)| inputs.iter().map(move |input| (output_id, input)))
+            .map(|(output_id, input_id)| {
+                let rate = self.edge_rates.get(&(output_id.clone(), input_id.clone()));
+                let OutputId(source, output) = output_id;
+                let (target, input) = input_id;
+                EdgeStats {
+                    source: source.clone(),
+                    output: output.clone(),
+                    target: target.clone(),
+                    input: input.clone(),
+                    messages: rate.map(|r| r.messages()).unwrap_or_default(),
+                    rate_hz: rate.map(|r| r.rate(now)).unwrap_or_default(),
+                    last_message_age_ms: rate
+                        .and_then(|r| r.last_message_age(now))
+                        .map(|age| age.as_millis() as u64),
+                }
+            })
+            .collect();
+
+        DaemonLiveGraph { nodes, edges }
+    }
+
+    fn record_dropped_inputs(
+        &mut self,
+        node_id: &NodeId,
+        counts: impl IntoIterator<Item = (DataId, u64)>,
+    ) {
+        let dropped = self.dropped_inputs.entry(node_id.clone()).or_default();
+        for (input_id, count) in counts {
+            *dropped.entry(input_id).or_default() += count;
+        }
+    }
+
+    fn queue_metrics(&self) -> QueueMetrics {
+        QueueMetrics {
+            queued_inputs: self
+                .queued_inputs
+                .iter()
+                .map(|(node_id, queued)| (node_id.clone(), queued.load(Ordering::Relaxed) as u64))
+                .collect(),
+            in_flight_shared_memory_bytes: self
+                .pending_drop_tokens
+                .values()
+                .map(|info| info.len as u64)
+                .sum(),
+            dropped_inputs: self.dropped_inputs.clone(),
+        }
+    }
+
+    /// Collects the input state of the local nodes for the slow consumer
+    /// detection.
+    fn consumer_states(
+        &self,
+        metrics: &QueueMetrics,
+        now: Instant,
+    ) -> BTreeMap<NodeId, slow_consumer::ConsumerState> {
+        let mut consumers: BTreeMap<_, _> = metrics
+            .queued_inputs
+            .iter()
+            .map(|(node_id, &queued_inputs)| {
+                let state = slow_consumer::ConsumerState {
+                    queued_inputs,
+                    inputs: Vec::new(),
+                    oldest_drop_token: None,
+                };
+                (node_id.clone(), state)
+            })
+            .collect();
+        for ((output_id, (node_id, input_id)), rate) in &self.edge_rates {
+            if let Some(state) = consumers.get_mut(node_id) {
+                state.inputs.push(slow_consumer::IncomingEdge {
+                    input_id: input_id.clone(),
+                    producer: output_id.clone(),
+                    rate: rate.rate(now),
+                });
+            }
+        }
+        for info in self.pending_drop_tokens.values() {
+            let age = now.duration_since(info.created);
+            for node_id in &info.pending_nodes {
+                if let Some(state) = consumers.get_mut(node_id) {
+                    if state
+                        .oldest_drop_token
+                        .as_ref()
+                        .map_or(true, |(a, _)| age > *a)
+                    {
+                        state.oldest_drop_token = Some((age, info.owner.clone()));
+                    }
+                }
+            }
+        }
+        consumers
+    }
+
     async fn check_drop_token(&mut self, token: DropToken, clock: &HLC) -> eyre::Result<()> {
         match self.pending_drop_tokens.entry(token) {
             std::collections::hash_map::Entry::Occupied(entry) => {
                 if entry.get().pending_nodes.is_empty() {
                     let (drop_token, info) = entry.remove_entry();
+                    #[cfg(windows)]
+                    self.retained_shared_memory.remove(&drop_token);
+                    self.flight_recorder.record(
+                        clock.new_timestamp(),
+                        format!(
+                            "drop token {drop_token:?} released, notifying `{}`",
+                            info.owner
+                        ),
+                    );
                     let result = match self.drop_channels.get_mut(&info.owner) {
-                        Some(channel) => send_with_timestamp(
-                            channel,
-                            NodeDropEvent::OutputDropped { drop_token },
-                            clock,
-                        )
-                        .wrap_err("send failed"),
+                        Some(channel) => channel
+                            .send(Timestamped {
+                                inner: NodeDropEvent::OutputDropped { drop_token },
+                                timestamp: clock.new_timestamp(),
+                            })
+                            .wrap_err("send failed"),
                         None => Err(eyre!("no subscribe channel for node `{}`", &info.owner)),
                     };
                     if let Err(err) = result.wrap_err_with(|| {
@@ -1775,6 +5243,23 @@ impl RunningDataflow {
     }
 }
 
+/// Applies the `log_level`s of the nodes to the spans of the daemon.
+fn apply_node_log_levels(levels: &BTreeMap<(String, String), LevelFilter>) {
+    #[cfg(feature = "tracing")]
+    if let Err(err) = dora_tracing::set_node_log_levels(levels) {
+        tracing::warn!("{err:?}");
+    }
+    #[cfg(not(feature = "tracing"))]
+    let _ = levels;
+}
+
+fn node_state(result: &Result<(), NodeError>) -> NodeState {
+    match result {
+        Ok(()) => NodeState::Finished,
+        Err(_) => NodeState::Failed,
+    }
+}
+
 fn empty_type_info() -> ArrowTypeInfo {
     ArrowTypeInfo {
         data_type: DataType::Null,
@@ -1787,16 +5272,29 @@ fn empty_type_info() -> ArrowTypeInfo {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct OutputId(NodeId, DataId);
 type InputId = (NodeId, DataId);
 
+/// Open handle to a shared memory region of another process, which keeps the
+/// region alive on Windows.
+#[cfg(windows)]
+struct RetainedSharedMemory(#[allow(dead_code)] shared_memory_server::Shmem);
+
+// SAFETY: the region is never accessed through this handle
+#[cfg(windows)]
+unsafe impl Send for RetainedSharedMemory {}
+
 struct DropTokenInformation {
     /// The node that created the associated drop token.
     owner: NodeId,
     /// Contains the set of pending nodes that still have access to the input
     /// associated with a drop token.
     pending_nodes: BTreeSet<NodeId>,
+    /// Size of the shared memory region, in bytes.
+    len: usize,
+    /// Time at which the token was first sent to a local node.
+    created: Instant,
 }
 
 #[derive(Debug)]
@@ -1813,6 +5311,8 @@ pub enum Event {
     HeartbeatInterval,
     CtrlC,
     SecondCtrlC,
+    /// Signal received by the daemon that should be forwarded to all nodes.
+    ForwardSignal(StopSignal),
 }
 
 impl From<DoraEvent> for Event {
@@ -1827,7 +5327,16 @@ pub enum DaemonNodeEvent {
         reply_sender: oneshot::Sender<DaemonReply>,
     },
     Subscribe {
-        event_sender: UnboundedSender<Timestamped<NodeEvent>>,
+        event_sender: EventSender,
+        /// Number of inputs queued in the listener of the node.
+        queued_inputs: Arc<AtomicUsize>,
+        /// Notified when the node takes inputs from its listener.
+        inputs_taken: Arc<Notify>,
+        /// Time in microseconds that the inputs last taken by the node waited
+        /// in its listener.
+        input_latency: Arc<AtomicU64>,
+        /// Requests the inputs queued in the listener of the node.
+        queue_snapshots: checkpoint::QueueSnapshotRequests,
         reply_sender: oneshot::Sender<DaemonReply>,
     },
     SubscribeDrop {
@@ -1842,13 +5351,76 @@ pub enum DaemonNodeEvent {
         output_id: DataId,
         metadata: metadata::Metadata,
         data: Option<DataMessage>,
+        /// Set if the sender is blocked while the queues of its receivers
+        /// are full.
+        reply_sender: Option<oneshot::Sender<DaemonReply>>,
+    },
+    SendOutMultiple {
+        messages: Vec<OutputMessage>,
+        reply_sender: Option<oneshot::Sender<DaemonReply>>,
     },
     ReportDrop {
         tokens: Vec<DropToken>,
     },
+    ReportBackpressure {
+        input_id: DataId,
+        level: f32,
+        dropped: u32,
+    },
+    /// Messages that the listener dropped from the event queue of the node,
+    /// per input.
+    ReportDroppedInputs {
+        counts: BTreeMap<DataId, u64>,
+    },
     EventStreamDropped {
         reply_sender: oneshot::Sender<DaemonReply>,
     },
+    StopDataflow {
+        reason: String,
+        reply_sender: oneshot::Sender<DaemonReply>,
+    },
+    ReportHealth {
+        health: NodeHealth,
+        reply_sender: oneshot::Sender<DaemonReply>,
+    },
+    SaveState {
+        state: Vec<u8>,
+        reply_sender: oneshot::Sender<DaemonReply>,
+    },
+    RestoredState {
+        reply_sender: oneshot::Sender<DaemonReply>,
+    },
+    BlackboardGet {
+        key: String,
+        reply_sender: oneshot::Sender<DaemonReply>,
+    },
+    BlackboardSet {
+        key: String,
+        value: Option<Vec<u8>>,
+        reply_sender: oneshot::Sender<DaemonReply>,
+    },
+    BlackboardWatch {
+        prefix: String,
+        reply_sender: oneshot::Sender<DaemonReply>,
+    },
+    Campaign {
+        group: String,
+        reply_sender: oneshot::Sender<DaemonReply>,
+    },
+    Resign {
+        group: String,
+        reply_sender: oneshot::Sender<DaemonReply>,
+    },
+    SubscribeNodeOutput {
+        nodes: BTreeSet<NodeId>,
+        reply_sender: oneshot::Sender<DaemonReply>,
+    },
+    Log {
+        level: LogLevel,
+        target: Option<String>,
+        message: String,
+        fields: BTreeMap<String, String>,
+    },
 }
 
 #[derive(Debug)]
@@ -1868,6 +5440,40 @@ pub enum DoraEvent {
         dataflow_id: DataflowId,
         node_id: NodeId,
         exit_status: NodeExitStatus,
+        exit_info: crash_report::ExitInfo,
+        /// Memory limit of the node in megabytes, if the node exceeded it.
+        exceeded_memory_limit_mb: Option<u64>,
+    },
+    /// Lines that a spawned node printed to stdout or stderr.
+    NodeOutput {
+        dataflow_id: DataflowId,
+        node_id: NodeId,
+        stream: log::NodeStdio,
+        text: String,
+    },
+    /// A recorded output of a replayed node.
+    ReplayOutput {
+        dataflow_id: DataflowId,
+        node_id: NodeId,
+        output_id: DataId,
+        metadata: metadata::Metadata,
+        data: Option<Vec<u8>>,
+    },
+    /// The replay of the recording ended, either because all messages were
+    /// sent or because it was stopped.
+    ReplayFinished {
+        dataflow_id: DataflowId,
+        result: Result<(), String>,
+    },
+    /// Kill a node as configured in the `faults` of the dataflow.
+    KillNode {
+        dataflow_id: DataflowId,
+        node_id: NodeId,
+    },
+    /// The backoff delay of a node restart is over.
+    RestartNode {
+        dataflow_id: DataflowId,
+        node_id: NodeId,
     },
 }
 
@@ -1877,11 +5483,11 @@ enum RunStatus {
     Exit,
 }
 
-fn send_with_timestamp<T>(
-    sender: &UnboundedSender<Timestamped<T>>,
-    event: T,
+fn send_with_timestamp(
+    sender: &EventSender,
+    event: NodeEvent,
     clock: &HLC,
-) -> Result<(), mpsc::error::SendError<Timestamped<T>>> {
+) -> Result<(), TrySendError<Timestamped<NodeEvent>>> {
     sender.send(Timestamped {
         inner: event,
         timestamp: clock.new_timestamp(),
@@ -1892,6 +5498,7 @@ fn set_up_ctrlc_handler(
     clock: Arc<HLC>,
 ) -> eyre::Result<tokio::sync::mpsc::Receiver<Timestamped<Event>>> {
     let (ctrlc_tx, ctrlc_rx) = mpsc::channel(1);
+    signal::forward_signals(ctrlc_tx.clone(), clock.clone())?;
 
     let mut ctrlc_sent = 0;
     ctrlc::set_handler(move || {
@@ -1,4 +1,5 @@
 use crate::socket_stream_utils::{socket_stream_receive, socket_stream_send};
+use dora_core::socket_activation;
 use dora_message::{
     daemon_to_node::DaemonReply,
     node_to_daemon::{DaemonRequest, DynamicNodeEvent, Timestamped},
@@ -16,16 +17,32 @@ pub struct DynamicNodeEventWrapper {
     pub reply_tx: oneshot::Sender<Option<DaemonReply>>,
 }
 
+/// Binds a listener for dynamic nodes at the given address.
+///
+/// The listener socket passed by systemd is used instead if there is one
+/// and `use_activated_socket` is set.
 pub async fn spawn_listener_loop(
     bind: SocketAddr,
+    use_activated_socket: bool,
     machine_id: String,
     events_tx: flume::Sender<Timestamped<DynamicNodeEventWrapper>>,
 ) -> eyre::Result<u16> {
-    let socket = match TcpListener::bind(bind).await {
-        Ok(socket) => socket,
-        Err(err) => {
-            return Err(eyre::Report::new(err).wrap_err("failed to create local TCP listener"))
+    let activated = if use_activated_socket {
+        socket_activation::take_listener(socket_activation::DAEMON_LOCAL_SOCKET_NAME)?
+    } else {
+        None
+    };
+    let socket = match activated {
+        Some(listener) => {
+            tracing::info!("using local listener socket passed by systemd");
+            TcpListener::from_std(listener).wrap_err("failed to use local listener socket")?
         }
+        None => match TcpListener::bind(bind).await {
+            Ok(socket) => socket,
+            Err(err) => {
+                return Err(eyre::Report::new(err).wrap_err("failed to create local TCP listener"))
+            }
+        },
     };
     let listen_port = socket
         .local_addr()
@@ -69,53 +86,52 @@ async fn handle_connection_loop(
     }
 
     loop {
-        match receive_message(&mut connection).await {
+        let (event, timestamp) = match receive_message(&mut connection).await {
             Ok(Some(Timestamped {
-                inner: DaemonRequest::NodeConfig { node_id },
+                inner: DaemonRequest::NodeConfig { node_id, token },
                 timestamp,
-            })) => {
-                let (reply_tx, reply_rx) = oneshot::channel();
-                if events_tx
-                    .send_async(Timestamped {
-                        inner: DynamicNodeEventWrapper {
-                            event: DynamicNodeEvent::NodeConfig { node_id },
-                            reply_tx,
-                        },
-                        timestamp,
-                    })
-                    .await
-                    .is_err()
-                {
-                    break;
-                }
-                let Ok(reply) = reply_rx.await else {
-                    tracing::warn!("daemon sent no reply");
-                    continue;
-                };
-                if let Some(reply) = reply {
-                    let serialized = match serde_json::to_vec(&reply)
-                        .wrap_err("failed to serialize DaemonReply")
-                    {
-                        Ok(r) => r,
-                        Err(err) => {
-                            tracing::error!("{err:?}");
-                            continue;
-                        }
-                    };
-                    if let Err(err) = socket_stream_send(&mut connection, &serialized).await {
-                        tracing::warn!("failed to send reply: {err}");
-                        continue;
-                    };
-                }
-            }
+            })) => (DynamicNodeEvent::NodeConfig { node_id, token }, timestamp),
             Ok(None) => break,
             Err(err) => {
                 tracing::warn!("{err:?}");
                 break;
             }
-            _ => tracing::warn!(
-                "Unexpected Daemon Request that is not yet by Additional local listener controls"
-            ),
+            _ => {
+                tracing::warn!(
+                    "Unexpected Daemon Request that is not yet by Additional local listener controls"
+                );
+                continue;
+            }
+        };
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+        if events_tx
+            .send_async(Timestamped {
+                inner: DynamicNodeEventWrapper { event, reply_tx },
+                timestamp,
+            })
+            .await
+            .is_err()
+        {
+            break;
+        }
+        let Ok(reply) = reply_rx.await else {
+            tracing::warn!("daemon sent no reply");
+            continue;
+        };
+        if let Some(reply) = reply {
+            let serialized =
+                match serde_json::to_vec(&reply).wrap_err("failed to serialize DaemonReply") {
+                    Ok(r) => r,
+                    Err(err) => {
+                        tracing::error!("{err:?}");
+                        continue;
+                    }
+                };
+            if let Err(err) = socket_stream_send(&mut connection, &serialized).await {
+                tracing::warn!("failed to send reply: {err}");
+                continue;
+            };
         }
     }
 }
@@ -1,9 +1,360 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
 
-use dora_core::config::NodeId;
+use dora_core::{config::NodeId, descriptor::LogsConfig};
+use dora_message::common::LogLevel;
+use eyre::Context;
+use tokio::io::AsyncWriteExt;
 use uuid::Uuid;
 
+/// Maximum number of structured log records that a node can send per [`LOG_RATE_WINDOW`].
+const LOG_RATE_LIMIT: u32 = 100;
+const LOG_RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Size after which a node log file is rotated, if not configured otherwise.
+const DEFAULT_MAX_LOG_FILE_SIZE: u64 = 10 * 1024 * 1024;
+/// Number of rotated log files that are kept per node, if not configured otherwise.
+const DEFAULT_MAX_ROTATED_LOG_FILES: usize = 5;
+
 pub fn log_path(working_dir: &Path, dataflow_id: &Uuid, node_id: &NodeId) -> PathBuf {
     let dataflow_dir = working_dir.join("out").join(dataflow_id.to_string());
     dataflow_dir.join(format!("log_{node_id}.txt"))
 }
+
+/// Path of an older, rotated log file of a node.
+///
+/// Index `1` is the most recently rotated file.
+fn rotated_log_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(format!(".{index}"));
+    path.with_file_name(name)
+}
+
+/// Log file of a node, which receives both the captured output of the node
+/// process and the log records that the daemon stores for the node.
+///
+/// The file is rotated once it exceeds the configured size. Rotated files
+/// are renamed to `log_<node_id>.txt.1`, `log_<node_id>.txt.2`, and so on,
+/// up to the configured number of files.
+pub struct NodeLogFile {
+    path: PathBuf,
+    file: tokio::fs::File,
+    size: u64,
+    max_size: u64,
+    max_rotated_files: usize,
+}
+
+pub type SharedNodeLogFile = Arc<tokio::sync::Mutex<NodeLogFile>>;
+
+impl NodeLogFile {
+    pub async fn open(
+        working_dir: &Path,
+        dataflow_id: &Uuid,
+        node_id: &NodeId,
+        config: &LogsConfig,
+    ) -> eyre::Result<SharedNodeLogFile> {
+        let path = log_path(working_dir, dataflow_id, node_id);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .context("could not create dataflow_dir")?;
+        }
+        // open in append mode to continue the log after a daemon restart
+        let file = open_append(&path).await?;
+        let size = file
+            .metadata()
+            .await
+            .wrap_err_with(|| format!("failed to read size of log file `{}`", path.display()))?
+            .len();
+        Ok(Arc::new(tokio::sync::Mutex::new(Self {
+            path,
+            file,
+            size,
+            max_size: config.max_file_size.unwrap_or(DEFAULT_MAX_LOG_FILE_SIZE),
+            max_rotated_files: config
+                .max_rotated_files
+                .unwrap_or(DEFAULT_MAX_ROTATED_LOG_FILES),
+        })))
+    }
+
+    /// Appends the given text to the log file, rotating it first if the
+    /// text doesn't fit anymore.
+    pub async fn write(&mut self, text: &str) -> eyre::Result<()> {
+        if self.size > 0 && self.size + text.len() as u64 > self.max_size {
+            self.rotate().await?;
+        }
+        self.file
+            .write_all(text.as_bytes())
+            .await
+            .wrap_err_with(|| format!("failed to write to log file `{}`", self.path.display()))?;
+        self.size += text.len() as u64;
+        Ok(())
+    }
+
+    /// Makes sure that all written data has been synced to disk.
+    pub async fn sync(&mut self) -> eyre::Result<()> {
+        self.file
+            .sync_all()
+            .await
+            .wrap_err_with(|| format!("failed to sync log file `{}`", self.path.display()))
+    }
+
+    async fn rotate(&mut self) -> eyre::Result<()> {
+        if self.max_rotated_files == 0 {
+            self.file
+                .set_len(0)
+                .await
+                .wrap_err_with(|| format!("failed to truncate `{}`", self.path.display()))?;
+        } else {
+            let oldest = rotated_log_path(&self.path, self.max_rotated_files);
+            if tokio::fs::try_exists(&oldest).await.unwrap_or(false) {
+                tokio::fs::remove_file(&oldest)
+                    .await
+                    .wrap_err_with(|| format!("failed to remove `{}`", oldest.display()))?;
+            }
+            for index in (1..self.max_rotated_files).rev() {
+                let from = rotated_log_path(&self.path, index);
+                if tokio::fs::try_exists(&from).await.unwrap_or(false) {
+                    tokio::fs::rename(&from, rotated_log_path(&self.path, index + 1))
+                        .await
+                        .wrap_err_with(|| format!("failed to rotate `{}`", from.display()))?;
+                }
+            }
+            tokio::fs::rename(&self.path, rotated_log_path(&self.path, 1))
+                .await
+                .wrap_err_with(|| format!("failed to rotate `{}`", self.path.display()))?;
+            self.file = open_append(&self.path).await?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+}
+
+async fn open_append(path: &Path) -> eyre::Result<tokio::fs::File> {
+    tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .wrap_err_with(|| format!("failed to open log file `{}`", path.display()))
+}
+
+/// Reads the log of a node, including the rotated log files that still exist.
+pub async fn read_log(
+    working_dir: &Path,
+    dataflow_id: &Uuid,
+    node_id: &NodeId,
+) -> eyre::Result<Vec<u8>> {
+    let path = log_path(working_dir, dataflow_id, node_id);
+    let mut rotated = Vec::new();
+    for index in 1.. {
+        let rotated_path = rotated_log_path(&path, index);
+        if !tokio::fs::try_exists(&rotated_path).await.unwrap_or(false) {
+            break;
+        }
+        rotated.push(rotated_path);
+    }
+
+    let mut contents = Vec::new();
+    // oldest file first
+    for rotated_path in rotated.iter().rev() {
+        contents.extend(
+            tokio::fs::read(rotated_path)
+                .await
+                .wrap_err_with(|| format!("could not read `{}`", rotated_path.display()))?,
+        );
+    }
+    contents.extend(
+        tokio::fs::read(&path)
+            .await
+            .wrap_err_with(|| format!("Could not open log file: {:#?}", path))?,
+    );
+    Ok(contents)
+}
+
+/// Removes the log files of older dataflow runs, keeping the given number of
+/// most recent runs in addition to the current one.
+///
+/// Only the log files that the daemon writes are removed. Directories of
+/// older runs are removed as well if nothing else is left in them.
+pub async fn remove_old_runs(
+    working_dir: &Path,
+    current: &Uuid,
+    keep_runs: usize,
+) -> eyre::Result<()> {
+    let out_dir = working_dir.join("out");
+    let mut entries = match tokio::fs::read_dir(&out_dir).await {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => {
+            return Err(err).wrap_err_with(|| format!("failed to read `{}`", out_dir.display()))
+        }
+    };
+    let mut runs = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let is_run = entry.file_type().await?.is_dir()
+            && entry
+                .file_name()
+                .to_str()
+                .and_then(|name| Uuid::parse_str(name).ok())
+                .is_some_and(|id| &id != current);
+        if is_run {
+            let modified = entry.metadata().await?.modified()?;
+            runs.push((modified, entry.path()));
+        }
+    }
+    // most recent first
+    runs.sort_by(|a, b| b.0.cmp(&a.0));
+
+    for (_, run_dir) in runs.into_iter().skip(keep_runs) {
+        let mut files = tokio::fs::read_dir(&run_dir).await?;
+        while let Some(file) = files.next_entry().await? {
+            let name = file.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with("log_") || name.starts_with("flight_recorder_") {
+                tokio::fs::remove_file(file.path())
+                    .await
+                    .wrap_err_with(|| format!("failed to remove `{}`", file.path().display()))?;
+            }
+        }
+        // fails if other files are left, e.g. recordings
+        let _ = tokio::fs::remove_dir(&run_dir).await;
+    }
+    Ok(())
+}
+
+/// Formats a structured log record as a single log file line.
+pub fn format_log_record(
+    level: LogLevel,
+    target: Option<&str>,
+    message: &str,
+    fields: &BTreeMap<String, String>,
+) -> String {
+    let mut line = format!("{:5}", level.as_str());
+    if let Some(target) = target {
+        line.push(' ');
+        line.push_str(target);
+    }
+    line.push_str(": ");
+    line.push_str(message);
+    for (key, value) in fields {
+        line.push_str(&format!(" {key}={value:?}"));
+    }
+    line.push('\n');
+    line
+}
+
+/// Output stream of a node process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeStdio {
+    Stdout,
+    Stderr,
+}
+
+impl NodeStdio {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NodeStdio::Stdout => "stdout",
+            NodeStdio::Stderr => "stderr",
+        }
+    }
+}
+
+/// Prefixes every line of the given node output with the capture time and
+/// the name of the stream, e.g. `2024-10-01T12:00:00.123Z stderr: message`.
+pub fn format_node_output(stream: NodeStdio, time: SystemTime, text: &str) -> String {
+    let time = chrono::DateTime::<chrono::Utc>::from(time)
+        .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    let mut formatted = String::new();
+    for line in text.lines() {
+        formatted.push_str(&time);
+        formatted.push(' ');
+        formatted.push_str(stream.as_str());
+        formatted.push_str(": ");
+        formatted.push_str(line);
+        formatted.push('\n');
+    }
+    formatted
+}
+
+/// Limits the number of structured log records that a node can send.
+pub struct LogRateLimiter {
+    window_start: Instant,
+    count: u32,
+    dropped: u64,
+}
+
+pub enum RateLimitDecision {
+    Accept {
+        /// Number of records that were dropped in the previous window(s).
+        dropped_before: u64,
+    },
+    Drop,
+}
+
+impl Default for LogRateLimiter {
+    fn default() -> Self {
+        Self {
+            window_start: Instant::now(),
+            count: 0,
+            dropped: 0,
+        }
+    }
+}
+
+impl LogRateLimiter {
+    pub fn check(&mut self) -> RateLimitDecision {
+        if self.window_start.elapsed() >= LOG_RATE_WINDOW {
+            self.window_start = Instant::now();
+            self.count = 0;
+        }
+        if self.count < LOG_RATE_LIMIT {
+            self.count += 1;
+            RateLimitDecision::Accept {
+                dropped_before: std::mem::take(&mut self.dropped),
+            }
+        } else {
+            self.dropped += 1;
+            RateLimitDecision::Drop
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accepted(decision: RateLimitDecision) -> Option<u64> {
+        match decision {
+            RateLimitDecision::Accept { dropped_before } => Some(dropped_before),
+            RateLimitDecision::Drop => None,
+        }
+    }
+
+    #[test]
+    fn drops_records_above_limit() {
+        let mut limiter = LogRateLimiter::default();
+        for _ in 0..LOG_RATE_LIMIT {
+            assert_eq!(accepted(limiter.check()), Some(0));
+        }
+        assert_eq!(accepted(limiter.check()), None);
+        assert_eq!(accepted(limiter.check()), None);
+    }
+
+    #[test]
+    fn reports_dropped_records_in_next_window() {
+        let mut limiter = LogRateLimiter::default();
+        for _ in 0..LOG_RATE_LIMIT + 3 {
+            limiter.check();
+        }
+
+        // start the next window
+        limiter.window_start = Instant::now() - LOG_RATE_WINDOW;
+        assert_eq!(accepted(limiter.check()), Some(3));
+        assert_eq!(accepted(limiter.check()), Some(0));
+    }
+}
@@ -0,0 +1,92 @@
+//! Standalone `dora-daemon` executable with a small footprint.
+//!
+//! Unlike `dora daemon`, this executable doesn't link the coordinator, the
+//! operator runtime, the telemetry exporters, or the JSON state dumps, and it
+//! runs the daemon on a single-threaded runtime with smaller internal buffers.
+//! This allows running the daemon on small embedded boards, next to the
+//! actual workload. Build it through:
+//!
+//! ```bash
+//! cargo build -p dora-daemon --profile embedded --no-default-features --features minimal
+//! ```
+//!
+//! Operators and WASM nodes are spawned through the `dora` executable, which
+//! must be installed on the machine if the dataflow uses them.
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+};
+
+use clap::Parser;
+use dora_core::{
+    descriptor::signing::{SignaturePolicy, TrustedKeys},
+    topics::{DORA_COORDINATOR_PORT_DEFAULT, DORA_DAEMON_LOCAL_LISTEN_PORT_DEFAULT, LOCALHOST},
+};
+use dora_daemon::{Daemon, Footprint};
+use eyre::Context;
+use tracing_subscriber::EnvFilter;
+
+#[derive(Debug, Parser)]
+#[clap(name = "dora-daemon", version)]
+struct Args {
+    /// Unique identifier for the machine (required for distributed dataflows)
+    #[clap(long)]
+    machine_id: Option<String>,
+    /// The inter daemon IP address and port this daemon will bind to.
+    #[clap(long, default_value_t = SocketAddr::new(IpAddr::from([0, 0, 0, 0]), 0))]
+    inter_daemon_addr: SocketAddr,
+    /// Local listen port for event such as dynamic node.
+    ///
+    /// Dynamic nodes are only accepted if they present the token that is set
+    /// in the `DORA_DYNAMIC_NODE_TOKEN` environment variable of the daemon.
+    #[clap(long, default_value_t = DORA_DAEMON_LOCAL_LISTEN_PORT_DEFAULT)]
+    local_listen_port: u16,
+    /// Address and port number of the dora coordinator
+    #[clap(long, short, default_value_t = LOCALHOST)]
+    coordinator_addr: IpAddr,
+    /// Port number of the coordinator control server
+    #[clap(long, default_value_t = DORA_COORDINATOR_PORT_DEFAULT)]
+    coordinator_port: u16,
+    /// File with the base64 encoded public keys that dataflows can be
+    /// signed with, one per line
+    #[clap(long, value_name = "FILE")]
+    trusted_keys: Option<PathBuf>,
+    /// Refuse dataflows that are not signed by a trusted key (production mode)
+    #[clap(long, action, requires = "trusted_keys")]
+    require_signed: bool,
+}
+
+fn main() -> eyre::Result<()> {
+    let args = Args::parse();
+
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")),
+        )
+        .init();
+
+    let signature_policy = args
+        .trusted_keys
+        .map(|path| {
+            eyre::Ok(SignaturePolicy {
+                trusted_keys: TrustedKeys::read(&path)?,
+                require_signed: args.require_signed,
+            })
+        })
+        .transpose()?;
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .context("tokio runtime failed")?;
+    rt.block_on(Daemon::run(
+        SocketAddr::new(args.coordinator_addr, args.coordinator_port),
+        args.machine_id.unwrap_or_default(),
+        args.inter_daemon_addr,
+        args.local_listen_port,
+        signature_policy,
+        Footprint::Minimal,
+    ))
+    .context("failed to run dora-daemon")
+}
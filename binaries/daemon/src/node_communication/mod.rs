@@ -1,22 +1,29 @@
-use crate::{DaemonNodeEvent, Event};
+use crate::{
+    checkpoint::{self, QueuedInput},
+    DaemonNodeEvent, Event,
+};
 use dora_core::{
-    config::{DataId, LocalCommunicationConfig, NodeId},
+    config::{DataId, LocalCommunicationConfig, NodeId, QueuePolicy},
+    descriptor::{BackpressureStrategy, EventQueueConfig},
     topics::LOCALHOST,
     uhlc,
 };
 use dora_message::{
     common::{DropToken, Timestamped},
     daemon_to_node::{DaemonCommunication, DaemonReply, NodeDropEvent, NodeEvent},
-    node_to_daemon::DaemonRequest,
+    node_to_daemon::{DaemonRequest, NodeRegisterRequest, NodeToken},
     DataflowId,
 };
 use eyre::{eyre, Context};
 use futures::{future, task, Future};
 use shared_memory_server::{ShmemConf, ShmemServer};
 use std::{
-    collections::{BTreeMap, VecDeque},
+    collections::{BTreeMap, BTreeSet, VecDeque},
     mem,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
     task::Poll,
 };
 #[cfg(unix)]
@@ -24,8 +31,12 @@ use tokio::net::UnixListener;
 use tokio::{
     net::TcpListener,
     sync::{
-        mpsc::{self, UnboundedReceiver},
-        oneshot,
+        mpsc::{
+            self,
+            error::{TryRecvError, TrySendError},
+            UnboundedReceiver,
+        },
+        oneshot, Notify,
     },
 };
 
@@ -35,17 +46,41 @@ pub mod tcp;
 #[cfg(unix)]
 pub mod unix_domain;
 
+/// Starts the listener for the given node.
+///
+/// Only connections that register as this node and present the given token
+/// are accepted.
+///
+/// If `previous` is set, the listener is set up at the same address again, so
+/// that a node that was spawned before a daemon restart can reconnect to it.
+#[allow(clippy::too_many_arguments)]
 pub async fn spawn_listener_loop(
     dataflow_id: &DataflowId,
     node_id: &NodeId,
+    token: NodeToken,
     daemon_tx: &mpsc::Sender<Timestamped<Event>>,
     config: LocalCommunicationConfig,
+    event_queue: EventQueueConfig,
+    input_policies: InputPolicies,
     queue_sizes: BTreeMap<DataId, usize>,
     clock: Arc<uhlc::HLC>,
+    previous: Option<&DaemonCommunication>,
 ) -> eyre::Result<DaemonCommunication> {
+    let expected = Arc::new(ExpectedNode {
+        dataflow_id: *dataflow_id,
+        node_id: node_id.clone(),
+        token,
+        event_queue,
+        input_policies,
+    });
     match config {
         LocalCommunicationConfig::Tcp => {
-            let socket = match TcpListener::bind((LOCALHOST, 0)).await {
+            let socket_addr = match previous {
+                None => (LOCALHOST, 0).into(),
+                Some(DaemonCommunication::Tcp { socket_addr }) => *socket_addr,
+                Some(other) => eyre::bail!("node was connected through {other:?} before"),
+            };
+            let socket = match TcpListener::bind(socket_addr).await {
                 Ok(socket) => socket,
                 Err(err) => {
                     return Err(
@@ -60,29 +95,47 @@ pub async fn spawn_listener_loop(
             let event_loop_node_id = format!("{dataflow_id}/{node_id}");
             let daemon_tx = daemon_tx.clone();
             tokio::spawn(async move {
-                tcp::listener_loop(socket, daemon_tx, queue_sizes, clock).await;
+                tcp::listener_loop(socket, daemon_tx, expected, queue_sizes, clock).await;
                 tracing::debug!("event listener loop finished for `{event_loop_node_id}`");
             });
 
             Ok(DaemonCommunication::Tcp { socket_addr })
         }
         LocalCommunicationConfig::Shmem => {
-            let daemon_control_region = ShmemConf::new()
-                .size(4096)
-                .create()
-                .wrap_err("failed to allocate daemon_control_region")?;
-            let daemon_events_region = ShmemConf::new()
-                .size(4096)
-                .create()
-                .wrap_err("failed to allocate daemon_events_region")?;
-            let daemon_drop_region = ShmemConf::new()
-                .size(4096)
-                .create()
-                .wrap_err("failed to allocate daemon_drop_region")?;
-            let daemon_events_close_region = ShmemConf::new()
-                .size(4096)
-                .create()
-                .wrap_err("failed to allocate daemon_drop_region")?;
+            let previous_ids = match previous {
+                None => None,
+                Some(DaemonCommunication::Shmem {
+                    daemon_control_region_id,
+                    daemon_events_region_id,
+                    daemon_drop_region_id,
+                    daemon_events_close_region_id,
+                }) => Some([
+                    daemon_control_region_id,
+                    daemon_events_region_id,
+                    daemon_drop_region_id,
+                    daemon_events_close_region_id,
+                ]),
+                Some(other) => eyre::bail!("node was connected through {other:?} before"),
+            };
+            let region = |index: usize| match previous_ids {
+                // the regions are left behind if the previous daemon crashed
+                Some(ids) => ShmemConf::new()
+                    .os_id(ids[index])
+                    .open()
+                    .map(|mut region| {
+                        region.set_owner(true);
+                        region
+                    })
+                    .or_else(|_| ShmemConf::new().size(4096).os_id(ids[index]).create()),
+                None => ShmemConf::new().size(4096).create(),
+            };
+            let daemon_control_region =
+                region(0).wrap_err("failed to allocate daemon_control_region")?;
+            let daemon_events_region =
+                region(1).wrap_err("failed to allocate daemon_events_region")?;
+            let daemon_drop_region = region(2).wrap_err("failed to allocate daemon_drop_region")?;
+            let daemon_events_close_region =
+                region(3).wrap_err("failed to allocate daemon_drop_region")?;
             let daemon_control_region_id = daemon_control_region.get_os_id().to_owned();
             let daemon_events_region_id = daemon_events_region.get_os_id().to_owned();
             let daemon_drop_region_id = daemon_drop_region.get_os_id().to_owned();
@@ -92,9 +145,16 @@ pub async fn spawn_listener_loop(
                 let server = unsafe { ShmemServer::new(daemon_control_region) }
                     .wrap_err("failed to create control server")?;
                 let daemon_tx = daemon_tx.clone();
+                let expected = expected.clone();
                 let queue_sizes = queue_sizes.clone();
                 let clock = clock.clone();
-                tokio::spawn(shmem::listener_loop(server, daemon_tx, queue_sizes, clock));
+                tokio::spawn(shmem::listener_loop(
+                    server,
+                    daemon_tx,
+                    expected,
+                    queue_sizes,
+                    clock,
+                ));
             }
 
             {
@@ -102,10 +162,11 @@ pub async fn spawn_listener_loop(
                     .wrap_err("failed to create events server")?;
                 let event_loop_node_id = format!("{dataflow_id}/{node_id}");
                 let daemon_tx = daemon_tx.clone();
+                let expected = expected.clone();
                 let queue_sizes = queue_sizes.clone();
                 let clock = clock.clone();
                 tokio::task::spawn(async move {
-                    shmem::listener_loop(server, daemon_tx, queue_sizes, clock).await;
+                    shmem::listener_loop(server, daemon_tx, expected, queue_sizes, clock).await;
                     tracing::debug!("event listener loop finished for `{event_loop_node_id}`");
                 });
             }
@@ -115,10 +176,11 @@ pub async fn spawn_listener_loop(
                     .wrap_err("failed to create drop server")?;
                 let drop_loop_node_id = format!("{dataflow_id}/{node_id}");
                 let daemon_tx = daemon_tx.clone();
+                let expected = expected.clone();
                 let queue_sizes = queue_sizes.clone();
                 let clock = clock.clone();
                 tokio::task::spawn(async move {
-                    shmem::listener_loop(server, daemon_tx, queue_sizes, clock).await;
+                    shmem::listener_loop(server, daemon_tx, expected, queue_sizes, clock).await;
                     tracing::debug!("drop listener loop finished for `{drop_loop_node_id}`");
                 });
             }
@@ -130,7 +192,7 @@ pub async fn spawn_listener_loop(
                 let daemon_tx = daemon_tx.clone();
                 let clock = clock.clone();
                 tokio::task::spawn(async move {
-                    shmem::listener_loop(server, daemon_tx, queue_sizes, clock).await;
+                    shmem::listener_loop(server, daemon_tx, expected, queue_sizes, clock).await;
                     tracing::debug!(
                         "events close listener loop finished for `{drop_loop_node_id}`"
                     );
@@ -153,6 +215,16 @@ pub async fn spawn_listener_loop(
                 std::fs::create_dir_all(&tmpfile_dir).context("could not create tmp dir")?;
             }
             let socket_file = tmpfile_dir.join(format!("{}.sock", node_id));
+            match previous {
+                None => {}
+                Some(DaemonCommunication::UnixDomain {
+                    socket_file: previous_file,
+                }) if *previous_file == socket_file => {
+                    // left behind by the previous daemon
+                    let _ = std::fs::remove_file(&socket_file);
+                }
+                Some(other) => eyre::bail!("node was connected through {other:?} before"),
+            }
             let socket = match UnixListener::bind(&socket_file) {
                 Ok(socket) => socket,
                 Err(err) => {
@@ -164,7 +236,7 @@ pub async fn spawn_listener_loop(
             let event_loop_node_id = format!("{dataflow_id}/{node_id}");
             let daemon_tx = daemon_tx.clone();
             tokio::spawn(async move {
-                unix_domain::listener_loop(socket, daemon_tx, queue_sizes, clock).await;
+                unix_domain::listener_loop(socket, daemon_tx, expected, queue_sizes, clock).await;
                 tracing::debug!("event listener loop finished for `{event_loop_node_id}`");
             });
 
@@ -177,13 +249,58 @@ pub async fn spawn_listener_loop(
     }
 }
 
+/// The node that is allowed to register on a listener.
+#[derive(Debug)]
+pub(crate) struct ExpectedNode {
+    dataflow_id: DataflowId,
+    node_id: NodeId,
+    token: NodeToken,
+    /// Bound of the event queue of the node.
+    event_queue: EventQueueConfig,
+    input_policies: InputPolicies,
+}
+
+impl ExpectedNode {
+    fn check(&self, request: &NodeRegisterRequest) -> Result<(), String> {
+        request.check_version()?;
+        if request.dataflow_id != self.dataflow_id || request.node_id != self.node_id {
+            return Err(format!(
+                "listener belongs to node {}/{}",
+                self.dataflow_id, self.node_id
+            ));
+        }
+        if !request.token.matches(&self.token) {
+            return Err("invalid node token".into());
+        }
+        Ok(())
+    }
+}
+
 struct Listener {
     dataflow_id: DataflowId,
     node_id: NodeId,
     daemon_tx: mpsc::Sender<Timestamped<Event>>,
-    subscribed_events: Option<UnboundedReceiver<Timestamped<NodeEvent>>>,
+    subscribed_events: Option<EventReceiver>,
     subscribed_drop_events: Option<UnboundedReceiver<Timestamped<NodeDropEvent>>>,
     queue: VecDeque<Box<Option<Timestamped<NodeEvent>>>>,
+    /// Number of `Input` events in `queue`, shared with the daemon for metrics.
+    queued_inputs: Arc<AtomicUsize>,
+    /// Notified when the node takes inputs from `queue`, which wakes up the
+    /// senders that the daemon blocks until there is room in the queue.
+    inputs_taken: Arc<Notify>,
+    /// Maximum number of `Input` events in `queue`.
+    ///
+    /// With `BackpressureStrategy::Block`, the daemon blocks the senders
+    /// instead, so the listener only enforces it for unblockable inputs.
+    event_queue: EventQueueConfig,
+    input_policies: InputPolicies,
+    /// Inputs that were dropped from `queue`, to be reported to the daemon.
+    dropped: DroppedInputs,
+    /// Time in microseconds that the inputs last taken by the node waited in
+    /// `queue`, shared with the daemon for timer throttling.
+    input_latency: Arc<AtomicU64>,
+    /// Requests for the queued inputs, for dataflow checkpoints.
+    queue_snapshot_requests: Option<UnboundedReceiver<oneshot::Sender<Vec<QueuedInput>>>>,
     clock: Arc<uhlc::HLC>,
 }
 
@@ -191,6 +308,7 @@ impl Listener {
     pub(crate) async fn run<C: Connection>(
         mut connection: C,
         daemon_tx: mpsc::Sender<Timestamped<Event>>,
+        expected: &ExpectedNode,
         hlc: Arc<uhlc::HLC>,
     ) {
         // receive the first message
@@ -216,7 +334,7 @@ impl Listener {
 
         match message.inner {
             DaemonRequest::Register(register_request) => {
-                let result = register_request.check_version();
+                let result = expected.check(&register_request);
                 let send_result = connection
                     .send_reply(DaemonReply::Result(result.clone()))
                     .await
@@ -232,6 +350,13 @@ impl Listener {
                             subscribed_events: None,
                             subscribed_drop_events: None,
                             queue: VecDeque::new(),
+                            queued_inputs: Default::default(),
+                            inputs_taken: Default::default(),
+                            event_queue: expected.event_queue.clone(),
+                            input_policies: expected.input_policies.clone(),
+                            dropped: Default::default(),
+                            input_latency: Default::default(),
+                            queue_snapshot_requests: None,
                             clock: hlc.clone(),
                         };
                         match listener
@@ -244,7 +369,9 @@ impl Listener {
                         }
                     }
                     (Err(err), _) => {
-                        tracing::warn!("failed to register node {dataflow_id}/{node_id}: {err}");
+                        tracing::warn!(
+                            "rejected registration as node {dataflow_id}/{node_id}: {err}"
+                        );
                     }
                     (Ok(()), Err(err)) => {
                         tracing::warn!(
@@ -280,7 +407,14 @@ impl Listener {
                     future::Either::Right((message, _)) => break message,
                 };
 
-                self.queue.push_back(Box::new(Some(event)));
+                enqueue(
+                    &mut self.queue,
+                    &self.queued_inputs,
+                    &self.event_queue,
+                    &self.input_policies,
+                    &mut self.dropped,
+                    event,
+                );
                 self.handle_events().await?;
             };
 
@@ -288,6 +422,11 @@ impl Listener {
                 Ok(Some(message)) => {
                     if let Err(err) = self.handle_message(message, &mut connection).await {
                         tracing::warn!("{err:?}");
+                        if self.daemon_tx.is_closed() {
+                            // the daemon stopped, so the node would wait forever for
+                            // the reply; closing the connection lets it fail instead
+                            break;
+                        }
                     }
                 }
                 Err(err) => {
@@ -304,13 +443,25 @@ impl Listener {
     async fn handle_events(&mut self) -> eyre::Result<()> {
         if let Some(events) = &mut self.subscribed_events {
             while let Ok(event) = events.try_recv() {
-                self.queue.push_back(Box::new(Some(event)));
+                enqueue(
+                    &mut self.queue,
+                    &self.queued_inputs,
+                    &self.event_queue,
+                    &self.input_policies,
+                    &mut self.dropped,
+                    event,
+                );
             }
+            // inputs that the daemon dropped while the channel was full
+            self.dropped.extend(events.take_dropped());
         }
+        let dropped = mem::take(&mut self.dropped);
+        self.report_drop_tokens(dropped.tokens).await?;
+        self.report_dropped_inputs(dropped.counts).await?;
         Ok(())
     }
 
-    #[tracing::instrument(skip(self, connection), fields(%self.dataflow_id, %self.node_id), level = "trace")]
+    #[tracing::instrument(skip(self, connection), fields(dataflow_id = %self.dataflow_id, node_id = %self.node_id), level = "trace")]
     async fn handle_message<C: Connection>(
         &mut self,
         message: Timestamped<DaemonRequest>,
@@ -359,19 +510,46 @@ impl Listener {
                 metadata,
                 data,
             } => {
+                let (reply_sender, reply) = self.blocking_reply();
                 let event = crate::DaemonNodeEvent::SendOut {
                     output_id,
                     metadata,
                     data,
+                    reply_sender,
+                };
+                self.process_daemon_event(event, reply, connection).await?;
+            }
+            DaemonRequest::SendMessages { messages } => {
+                let (reply_sender, reply) = self.blocking_reply();
+                let event = crate::DaemonNodeEvent::SendOutMultiple {
+                    messages,
+                    reply_sender,
+                };
+                self.process_daemon_event(event, reply, connection).await?;
+            }
+            DaemonRequest::ReportBackpressure {
+                input_id,
+                level,
+                dropped,
+            } => {
+                let event = crate::DaemonNodeEvent::ReportBackpressure {
+                    input_id,
+                    level,
+                    dropped,
                 };
                 self.process_daemon_event(event, None, connection).await?;
             }
             DaemonRequest::Subscribe => {
-                let (tx, rx) = mpsc::unbounded_channel();
+                let (tx, rx) = event_channel(&self.event_queue, &self.input_policies);
+                let (snapshot_tx, snapshot_rx) = mpsc::unbounded_channel();
                 let (reply_sender, reply) = oneshot::channel();
                 self.process_daemon_event(
                     DaemonNodeEvent::Subscribe {
                         event_sender: tx,
+                        queued_inputs: self.queued_inputs.clone(),
+                        inputs_taken: self.inputs_taken.clone(),
+                        input_latency: self.input_latency.clone(),
+                        queue_snapshots: snapshot_tx,
                         reply_sender,
                     },
                     Some(reply),
@@ -379,6 +557,7 @@ impl Listener {
                 )
                 .await?;
                 self.subscribed_events = Some(rx);
+                self.queue_snapshot_requests = Some(snapshot_rx);
             }
             DaemonRequest::SubscribeDrop => {
                 let (tx, rx) = mpsc::unbounded_channel();
@@ -402,18 +581,32 @@ impl Listener {
                     .into_iter()
                     .filter_map(|e| *e)
                     .collect();
+                let taken_inputs = queued_events.iter().map(|e| e.inner.input_count()).sum();
+                self.queued_inputs
+                    .fetch_sub(taken_inputs, Ordering::Relaxed);
+                if taken_inputs > 0 {
+                    self.inputs_taken.notify_waiters();
+                }
+                let now = self.clock.new_timestamp().get_time().to_duration();
+                let latency = queued_events
+                    .iter()
+                    .filter(|e| e.inner.input_count() > 0)
+                    .map(|e| now.saturating_sub(e.timestamp.get_time().to_duration()))
+                    .max()
+                    .unwrap_or_default();
+                self.input_latency
+                    .store(latency.as_micros() as u64, Ordering::Relaxed);
                 let reply = if queued_events.is_empty() {
-                    match self.subscribed_events.as_mut() {
+                    if self.subscribed_events.is_some() {
                         // wait for next event
-                        Some(events) => match events.recv().await {
+                        match self.wait_for_event().await {
                             Some(event) => DaemonReply::NextEvents(vec![event]),
                             None => DaemonReply::NextEvents(vec![]),
-                        },
-                        None => {
-                            DaemonReply::Result(Err("Ignoring event request because no subscribe \
-                                message was sent yet"
-                                .into()))
                         }
+                    } else {
+                        DaemonReply::Result(Err("Ignoring event request because no subscribe \
+                            message was sent yet"
+                            .into()))
                     }
                 } else {
                     DaemonReply::NextEvents(queued_events)
@@ -457,10 +650,159 @@ impl Listener {
                 )
                 .await?;
             }
+            DaemonRequest::StopDataflow { reason } => {
+                let (reply_sender, reply) = oneshot::channel();
+                self.process_daemon_event(
+                    DaemonNodeEvent::StopDataflow {
+                        reason,
+                        reply_sender,
+                    },
+                    Some(reply),
+                    connection,
+                )
+                .await?;
+            }
+            DaemonRequest::SaveState { state } => {
+                let (reply_sender, reply) = oneshot::channel();
+                self.process_daemon_event(
+                    DaemonNodeEvent::SaveState {
+                        state,
+                        reply_sender,
+                    },
+                    Some(reply),
+                    connection,
+                )
+                .await?;
+            }
+            DaemonRequest::RestoredState => {
+                let (reply_sender, reply) = oneshot::channel();
+                self.process_daemon_event(
+                    DaemonNodeEvent::RestoredState { reply_sender },
+                    Some(reply),
+                    connection,
+                )
+                .await?;
+            }
+            DaemonRequest::BlackboardGet { key } => {
+                let (reply_sender, reply) = oneshot::channel();
+                self.process_daemon_event(
+                    DaemonNodeEvent::BlackboardGet { key, reply_sender },
+                    Some(reply),
+                    connection,
+                )
+                .await?;
+            }
+            DaemonRequest::BlackboardSet { key, value } => {
+                let (reply_sender, reply) = oneshot::channel();
+                self.process_daemon_event(
+                    DaemonNodeEvent::BlackboardSet {
+                        key,
+                        value,
+                        reply_sender,
+                    },
+                    Some(reply),
+                    connection,
+                )
+                .await?;
+            }
+            DaemonRequest::BlackboardWatch { prefix } => {
+                let (reply_sender, reply) = oneshot::channel();
+                self.process_daemon_event(
+                    DaemonNodeEvent::BlackboardWatch {
+                        prefix,
+                        reply_sender,
+                    },
+                    Some(reply),
+                    connection,
+                )
+                .await?;
+            }
+            DaemonRequest::Campaign { group } => {
+                let (reply_sender, reply) = oneshot::channel();
+                self.process_daemon_event(
+                    DaemonNodeEvent::Campaign {
+                        group,
+                        reply_sender,
+                    },
+                    Some(reply),
+                    connection,
+                )
+                .await?;
+            }
+            DaemonRequest::Resign { group } => {
+                let (reply_sender, reply) = oneshot::channel();
+                self.process_daemon_event(
+                    DaemonNodeEvent::Resign {
+                        group,
+                        reply_sender,
+                    },
+                    Some(reply),
+                    connection,
+                )
+                .await?;
+            }
+            DaemonRequest::SubscribeNodeOutput { nodes } => {
+                let (reply_sender, reply) = oneshot::channel();
+                self.process_daemon_event(
+                    DaemonNodeEvent::SubscribeNodeOutput {
+                        nodes,
+                        reply_sender,
+                    },
+                    Some(reply),
+                    connection,
+                )
+                .await?;
+            }
+            DaemonRequest::Log {
+                level,
+                target,
+                message,
+                fields,
+            } => {
+                let event = crate::DaemonNodeEvent::Log {
+                    level,
+                    target,
+                    message,
+                    fields,
+                };
+                self.process_daemon_event(event, None, connection).await?;
+            }
+            DaemonRequest::ReportHealth { health } => {
+                let (reply_sender, reply) = oneshot::channel();
+                self.process_daemon_event(
+                    DaemonNodeEvent::ReportHealth {
+                        health,
+                        reply_sender,
+                    },
+                    Some(reply),
+                    connection,
+                )
+                .await?;
+            }
         }
         Ok(())
     }
 
+    /// Reply channel for the outputs of the node if the daemon blocks it while
+    /// the queues of the receivers are full.
+    ///
+    /// The listener doesn't handle further requests of the node until the
+    /// daemon replied.
+    fn blocking_reply(
+        &self,
+    ) -> (
+        Option<oneshot::Sender<DaemonReply>>,
+        Option<oneshot::Receiver<DaemonReply>>,
+    ) {
+        match self.event_queue.backpressure {
+            BackpressureStrategy::Block => {
+                let (reply_sender, reply) = oneshot::channel();
+                (Some(reply_sender), Some(reply))
+            }
+            BackpressureStrategy::DropOldest | BackpressureStrategy::DropIncoming => (None, None),
+        }
+    }
+
     async fn report_drop_tokens(&mut self, drop_tokens: Vec<DropToken>) -> eyre::Result<()> {
         if !drop_tokens.is_empty() {
             let event = Event::Node {
@@ -482,6 +824,25 @@ impl Listener {
         Ok(())
     }
 
+    async fn report_dropped_inputs(&mut self, counts: BTreeMap<DataId, u64>) -> eyre::Result<()> {
+        if !counts.is_empty() {
+            let event = Event::Node {
+                dataflow_id: self.dataflow_id,
+                node_id: self.node_id.clone(),
+                event: DaemonNodeEvent::ReportDroppedInputs { counts },
+            };
+            let event = Timestamped {
+                inner: event,
+                timestamp: self.clock.new_timestamp(),
+            };
+            self.daemon_tx
+                .send(event)
+                .await
+                .map_err(|_| eyre!("failed to report dropped inputs to daemon"))?;
+        }
+        Ok(())
+    }
+
     async fn process_daemon_event<C: Connection>(
         &mut self,
         event: DaemonNodeEvent,
@@ -531,6 +892,7 @@ impl Listener {
     /// This behavior can be useful when waiting for multiple event sources at once.
     fn next_event(&mut self) -> impl Future<Output = Timestamped<NodeEvent>> + Unpin + '_ {
         let poll = |cx: &mut task::Context<'_>| {
+            self.answer_queue_snapshot_requests(cx);
             if let Some(events) = &mut self.subscribed_events {
                 match events.poll_recv(cx) {
                     Poll::Ready(Some(event)) => Poll::Ready(event),
@@ -542,6 +904,415 @@ impl Listener {
         };
         future::poll_fn(poll)
     }
+
+    /// Sends a copy of the queued inputs to the daemon, including the inputs
+    /// that were not moved from the event channel to the queue yet.
+    fn answer_queue_snapshot_requests(&mut self, cx: &mut task::Context<'_>) {
+        let Some(requests) = &mut self.queue_snapshot_requests else {
+            return;
+        };
+        while let Poll::Ready(Some(reply_sender)) = requests.poll_recv(cx) {
+            if let Some(events) = &mut self.subscribed_events {
+                while let Ok(event) = events.try_recv() {
+                    enqueue(
+                        &mut self.queue,
+                        &self.queued_inputs,
+                        &self.event_queue,
+                        &self.input_policies,
+                        &mut self.dropped,
+                        event,
+                    );
+                }
+            }
+            let queued =
+                checkpoint::queued_inputs(self.queue.iter().filter_map(|e| e.as_ref().as_ref()));
+            let _ = reply_sender.send(queued);
+        }
+    }
+
+    /// Waits for the next subscribed event while the queue is empty.
+    ///
+    /// Queue snapshot requests are answered with an empty queue in the
+    /// meantime, as the node already received all queued events.
+    async fn wait_for_event(&mut self) -> Option<Timestamped<NodeEvent>> {
+        let events = self.subscribed_events.as_mut()?;
+        let requests = &mut self.queue_snapshot_requests;
+        loop {
+            let next_request = async {
+                match requests {
+                    Some(requests) => requests.recv().await,
+                    None => future::pending().await,
+                }
+            };
+            tokio::select! {
+                event = events.recv() => return event,
+                Some(reply_sender) = next_request => {
+                    let _ = reply_sender.send(Vec::new());
+                }
+            }
+        }
+    }
+}
+
+/// How the event queue of a node handles each of its inputs.
+#[derive(Debug, Clone, Default)]
+pub struct InputPolicies {
+    /// `queue_policy` of each input.
+    pub queue_policies: BTreeMap<DataId, QueuePolicy>,
+    /// Inputs whose senders the daemon can't block, i.e. timers and nodes
+    /// that don't run as local processes.
+    ///
+    /// With `BackpressureStrategy::Block`, these inputs replace each other
+    /// once the queue is full.
+    pub unblockable: BTreeSet<DataId>,
+}
+
+impl InputPolicies {
+    fn queue_policy(&self, id: &DataId) -> QueuePolicy {
+        self.queue_policies.get(id).copied().unwrap_or_default()
+    }
+
+    fn is_unblockable(&self, event: &NodeEvent) -> bool {
+        match event {
+            NodeEvent::Input { id, .. } => self.unblockable.contains(id),
+            NodeEvent::InputBatch { inputs } => inputs
+                .iter()
+                .any(|input| self.unblockable.contains(&input.id)),
+            _ => false,
+        }
+    }
+}
+
+/// Adds the event to the queue of the node.
+///
+/// A queued input with `queue_policy: keep_latest` is replaced by the next
+/// message of the input. If the queue already holds the maximum number of
+/// inputs, an input is dropped according to the backpressure strategy, except
+/// for lossless inputs. With `BackpressureStrategy::Block`, only unblockable
+/// inputs are dropped, the oldest one for each new one.
+///
+/// Input batches are kept or dropped as a whole. They are lossless if one of
+/// their inputs is lossless and are never replaced.
+fn enqueue(
+    queue: &mut VecDeque<Box<Option<Timestamped<NodeEvent>>>>,
+    queued_inputs: &AtomicUsize,
+    config: &EventQueueConfig,
+    input_policies: &InputPolicies,
+    dropped: &mut DroppedInputs,
+    event: Timestamped<NodeEvent>,
+) {
+    let policy = |e: &Timestamped<NodeEvent>| match &e.inner {
+        NodeEvent::Input { id, .. } => Some(input_policies.queue_policy(id)),
+        NodeEvent::InputBatch { inputs } => Some(
+            if inputs
+                .iter()
+                .any(|input| input_policies.queue_policy(&input.id) == QueuePolicy::Lossless)
+            {
+                QueuePolicy::Lossless
+            } else {
+                QueuePolicy::DropOldest
+            },
+        ),
+        _ => None,
+    };
+    let Some(event_policy) = policy(&event) else {
+        queue.push_back(Box::new(Some(event)));
+        return;
+    };
+    if event_policy == QueuePolicy::KeepLatest {
+        let same_input = |e: &Timestamped<NodeEvent>| match (&e.inner, &event.inner) {
+            (NodeEvent::Input { id, .. }, NodeEvent::Input { id: new_id, .. }) => id == new_id,
+            _ => false,
+        };
+        let previous = queue
+            .iter()
+            .position(|e| e.as_ref().as_ref().is_some_and(same_input))
+            .and_then(|index| queue.remove(index))
+            .and_then(|e| *e);
+        if let Some(previous) = previous {
+            dropped.record(previous.inner);
+            queue.push_back(Box::new(Some(event)));
+            return;
+        }
+    }
+    let count = event.inner.input_count();
+    if event_policy == QueuePolicy::Lossless
+        || queued_inputs.load(Ordering::Relaxed) < config.capacity()
+    {
+        queued_inputs.fetch_add(count, Ordering::Relaxed);
+        queue.push_back(Box::new(Some(event)));
+        return;
+    }
+    let block = config.backpressure == BackpressureStrategy::Block;
+    match config.backpressure {
+        BackpressureStrategy::Block if !input_policies.is_unblockable(&event.inner) => {
+            // the daemon blocks the senders until the node takes its inputs
+            queued_inputs.fetch_add(count, Ordering::Relaxed);
+            queue.push_back(Box::new(Some(event)));
+        }
+        BackpressureStrategy::DropIncoming => dropped.record(event.inner),
+        BackpressureStrategy::DropOldest | BackpressureStrategy::Block => {
+            let oldest = queue
+                .iter()
+                .position(|e| {
+                    e.as_ref().as_ref().is_some_and(|e| {
+                        policy(e).is_some_and(|p| p != QueuePolicy::Lossless)
+                            && (!block || input_policies.is_unblockable(&e.inner))
+                    })
+                })
+                .and_then(|index| queue.remove(index))
+                .and_then(|e| *e);
+            queue.push_back(Box::new(Some(event)));
+            queued_inputs.fetch_add(count, Ordering::Relaxed);
+            // there is no oldest input if only lossless or blocked inputs are queued
+            if let Some(oldest) = oldest {
+                queued_inputs.fetch_sub(oldest.inner.input_count(), Ordering::Relaxed);
+                dropped.record(oldest.inner);
+            }
+        }
+    }
+}
+
+/// Inputs that the listener dropped from the queue of the node.
+#[derive(Debug, Default)]
+struct DroppedInputs {
+    /// Drop tokens of the dropped inputs, which the node will never report.
+    tokens: Vec<DropToken>,
+    /// Number of dropped messages of each input.
+    counts: BTreeMap<DataId, u64>,
+}
+
+impl DroppedInputs {
+    fn extend(&mut self, other: DroppedInputs) {
+        self.tokens.extend(other.tokens);
+        for (id, count) in other.counts {
+            *self.counts.entry(id).or_default() += count;
+        }
+    }
+
+    fn record(&mut self, event: NodeEvent) {
+        match event {
+            NodeEvent::Input { id, data, .. } => {
+                tracing::trace!("dropped queued message of input `{id}`");
+                if let Some(token) = data.as_ref().and_then(|d| d.drop_token()) {
+                    self.tokens.push(token);
+                }
+                *self.counts.entry(id).or_default() += 1;
+            }
+            NodeEvent::InputBatch { inputs } => {
+                for input in inputs {
+                    self.record(input.into());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Creates the channel through which the daemon sends events to a listener.
+///
+/// The channel holds at most as many events as the event queue of the node,
+/// so a listener that doesn't keep up with the daemon can't make the daemon
+/// buffer an unbounded number of events.
+fn event_channel(
+    config: &EventQueueConfig,
+    input_policies: &InputPolicies,
+) -> (EventSender, EventReceiver) {
+    // `mpsc::channel` panics for a zero capacity or more than `usize::MAX >> 3` permits
+    let capacity = config.capacity().clamp(1, usize::MAX >> 3);
+    let (sender, receiver) = mpsc::channel(capacity);
+    let in_flight_inputs = Arc::new(AtomicUsize::new(0));
+    let overflow = Arc::new(std::sync::Mutex::new(Overflow::default()));
+    (
+        EventSender {
+            sender,
+            config: config.clone(),
+            input_policies: Arc::new(input_policies.clone()),
+            overflow: overflow.clone(),
+            overflow_inputs: Default::default(),
+            in_flight_inputs: in_flight_inputs.clone(),
+        },
+        EventReceiver {
+            receiver,
+            in_flight_inputs,
+            overflow,
+        },
+    )
+}
+
+/// Sends events to the listener of a node.
+///
+/// Keeps track of the inputs that the listener didn't receive yet, as these
+/// are not part of its queue yet.
+///
+/// When the channel is full, events wait in an overflow queue and are
+/// delivered in order once the listener has room for them, without blocking
+/// the daemon. Inputs are added to the overflow queue like to the queue of
+/// the listener, so it holds at most as many inputs as the event queue of
+/// the node, apart from lossless and blocked ones.
+#[derive(Debug, Clone)]
+pub struct EventSender {
+    sender: mpsc::Sender<Timestamped<NodeEvent>>,
+    config: EventQueueConfig,
+    input_policies: Arc<InputPolicies>,
+    overflow: Arc<std::sync::Mutex<Overflow>>,
+    /// Number of inputs in the overflow queue.
+    overflow_inputs: Arc<AtomicUsize>,
+    /// Number of inputs in the channel.
+    in_flight_inputs: Arc<AtomicUsize>,
+}
+
+/// Events that wait for room in the event channel.
+#[derive(Debug, Default)]
+struct Overflow {
+    /// Waiting events, oldest first.
+    events: VecDeque<Box<Option<Timestamped<NodeEvent>>>>,
+    /// Inputs dropped from `events`, which the listener reports with its own.
+    dropped: DroppedInputs,
+}
+
+impl EventSender {
+    pub fn send(
+        &self,
+        event: Timestamped<NodeEvent>,
+    ) -> Result<(), TrySendError<Timestamped<NodeEvent>>> {
+        let mut overflow = self.overflow.lock().unwrap();
+        // events must not overtake the events that wait for room
+        let event = if overflow.events.is_empty() {
+            let inputs = event.inner.input_count();
+            // count before sending, so that the listener never sees a negative count
+            self.in_flight_inputs.fetch_add(inputs, Ordering::Relaxed);
+            match self.sender.try_send(event) {
+                Ok(()) => return Ok(()),
+                Err(TrySendError::Full(event)) => {
+                    self.in_flight_inputs.fetch_sub(inputs, Ordering::Relaxed);
+                    event
+                }
+                Err(err @ TrySendError::Closed(_)) => {
+                    self.in_flight_inputs.fetch_sub(inputs, Ordering::Relaxed);
+                    return Err(err);
+                }
+            }
+        } else if self.sender.is_closed() {
+            return Err(TrySendError::Closed(event));
+        } else {
+            event
+        };
+        if overflow.events.is_empty() {
+            tokio::spawn(self.clone().send_overflow());
+        }
+        let Overflow { events, dropped } = &mut *overflow;
+        enqueue(
+            events,
+            &self.overflow_inputs,
+            &self.config,
+            &self.input_policies,
+            dropped,
+            event,
+        );
+        Ok(())
+    }
+
+    /// Moves the overflowing events to the channel once the listener has room.
+    async fn send_overflow(self) {
+        loop {
+            let Ok(permit) = self.sender.reserve().await else {
+                self.overflow.lock().unwrap().events.clear();
+                self.overflow_inputs.store(0, Ordering::Relaxed);
+                break;
+            };
+            let mut overflow = self.overflow.lock().unwrap();
+            if let Some(event) = overflow.events.pop_front().and_then(|e| *e) {
+                let inputs = event.inner.input_count();
+                self.in_flight_inputs.fetch_add(inputs, Ordering::Relaxed);
+                self.overflow_inputs.fetch_sub(inputs, Ordering::Relaxed);
+                permit.send(event);
+            }
+            if overflow.events.is_empty() {
+                break;
+            }
+        }
+    }
+
+    /// Number of inputs that were sent, but not received by the listener yet.
+    pub fn in_flight_inputs(&self) -> usize {
+        self.in_flight_inputs.load(Ordering::Relaxed) + self.overflow_inputs.load(Ordering::Relaxed)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
+
+    pub async fn closed(&self) {
+        self.sender.closed().await
+    }
+}
+
+/// Receiving side of [`EventSender`].
+struct EventReceiver {
+    receiver: mpsc::Receiver<Timestamped<NodeEvent>>,
+    in_flight_inputs: Arc<AtomicUsize>,
+    overflow: Arc<std::sync::Mutex<Overflow>>,
+}
+
+impl EventReceiver {
+    async fn recv(&mut self) -> Option<Timestamped<NodeEvent>> {
+        let event = self.receiver.recv().await;
+        self.received(event.as_ref());
+        event
+    }
+
+    fn try_recv(&mut self) -> Result<Timestamped<NodeEvent>, TryRecvError> {
+        let event = self.receiver.try_recv();
+        self.received(event.as_ref().ok());
+        event
+    }
+
+    fn poll_recv(&mut self, cx: &mut task::Context<'_>) -> Poll<Option<Timestamped<NodeEvent>>> {
+        let event = self.receiver.poll_recv(cx);
+        if let Poll::Ready(event) = &event {
+            self.received(event.as_ref());
+        }
+        event
+    }
+
+    fn received(&self, event: Option<&Timestamped<NodeEvent>>) {
+        if let Some(event) = event {
+            self.in_flight_inputs
+                .fetch_sub(event.inner.input_count(), Ordering::Relaxed);
+        }
+    }
+
+    /// Takes the inputs that the sender dropped from its overflow queue.
+    fn take_dropped(&self) -> DroppedInputs {
+        mem::take(&mut self.overflow.lock().unwrap().dropped)
+    }
+}
+
+/// Checks whether the receiver of the given channel has at least `capacity`
+/// inputs queued or on their way to its queue.
+pub fn queue_is_full(queued_inputs: &AtomicUsize, channel: &EventSender, capacity: usize) -> bool {
+    queued_inputs.load(Ordering::Relaxed) + channel.in_flight_inputs() >= capacity
+}
+
+/// Checks whether the blocked node `from` waits for `target`, directly or
+/// through other blocked nodes.
+pub fn waits_for(
+    blocked_senders: &BTreeMap<NodeId, BTreeSet<NodeId>>,
+    from: &NodeId,
+    target: &NodeId,
+) -> bool {
+    let mut visited = BTreeSet::new();
+    let mut pending = vec![from];
+    while let Some(node_id) = pending.pop() {
+        if node_id == target {
+            return true;
+        }
+        if visited.insert(node_id) {
+            pending.extend(blocked_senders.get(node_id).into_iter().flatten());
+        }
+    }
+    false
 }
 
 #[async_trait::async_trait]
@@ -549,3 +1320,512 @@ trait Connection {
     async fn receive_message(&mut self) -> eyre::Result<Option<Timestamped<DaemonRequest>>>;
     async fn send_reply(&mut self, message: DaemonReply) -> eyre::Result<()>;
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use dora_core::{metadata::ArrowTypeInfoExt, uhlc::HLC};
+    use dora_message::{
+        daemon_to_node::BatchedInput,
+        metadata::{ArrowTypeInfo, Metadata},
+    };
+
+    use super::*;
+
+    struct Queue {
+        events: VecDeque<Box<Option<Timestamped<NodeEvent>>>>,
+        queued_inputs: AtomicUsize,
+        config: EventQueueConfig,
+        input_policies: InputPolicies,
+        dropped: DroppedInputs,
+        clock: HLC,
+    }
+
+    impl Queue {
+        fn new(capacity: usize, backpressure: BackpressureStrategy) -> Self {
+            Self {
+                events: VecDeque::new(),
+                queued_inputs: AtomicUsize::new(0),
+                config: EventQueueConfig {
+                    capacity: Some(capacity),
+                    backpressure,
+                },
+                input_policies: InputPolicies::default(),
+                dropped: DroppedInputs::default(),
+                clock: HLC::default(),
+            }
+        }
+
+        fn with_policy(mut self, id: &str, policy: QueuePolicy) -> Self {
+            self.input_policies
+                .queue_policies
+                .insert(id.to_owned().into(), policy);
+            self
+        }
+
+        fn with_unblockable(mut self, id: &str) -> Self {
+            self.input_policies.unblockable.insert(id.to_owned().into());
+            self
+        }
+
+        fn push(&mut self, event: NodeEvent) {
+            let event = Timestamped {
+                inner: event,
+                timestamp: self.clock.new_timestamp(),
+            };
+            enqueue(
+                &mut self.events,
+                &self.queued_inputs,
+                &self.config,
+                &self.input_policies,
+                &mut self.dropped,
+                event,
+            );
+        }
+
+        fn push_input(&mut self, id: &str) -> uhlc::Timestamp {
+            let input = self.input(id);
+            let timestamp = input.timestamp;
+            self.push(input.inner);
+            timestamp
+        }
+
+        fn input(&self, id: &str) -> Timestamped<NodeEvent> {
+            let timestamp = self.clock.new_timestamp();
+            Timestamped {
+                inner: NodeEvent::Input {
+                    id: id.to_owned().into(),
+                    metadata: Metadata::new(timestamp, ArrowTypeInfo::byte_array(0)),
+                    data: None,
+                },
+                timestamp,
+            }
+        }
+
+        fn push_batch(&mut self, ids: &[&str]) {
+            let inputs = ids
+                .iter()
+                .map(|id| {
+                    let timestamp = self.clock.new_timestamp();
+                    BatchedInput {
+                        id: id.to_string().into(),
+                        metadata: Metadata::new(timestamp, ArrowTypeInfo::byte_array(0)),
+                        data: None,
+                    }
+                })
+                .collect();
+            self.push(NodeEvent::InputBatch { inputs });
+        }
+
+        /// Ids of the queued inputs, in queue order.
+        ///
+        /// The inputs of a batch are joined by `+`.
+        fn inputs(&self) -> Vec<String> {
+            self.events
+                .iter()
+                .filter_map(|e| match &e.as_ref().as_ref()?.inner {
+                    NodeEvent::Input { id, .. } => Some(id.to_string()),
+                    NodeEvent::InputBatch { inputs } => Some(
+                        inputs
+                            .iter()
+                            .map(|input| input.id.to_string())
+                            .collect::<Vec<_>>()
+                            .join("+"),
+                    ),
+                    _ => None,
+                })
+                .collect()
+        }
+
+        fn dropped(&self, id: &str) -> u64 {
+            let id = DataId::from(id.to_owned());
+            self.dropped.counts.get(&id).copied().unwrap_or_default()
+        }
+    }
+
+    #[test]
+    fn keep_latest_replaces_queued_input() {
+        let mut queue = Queue::new(10, BackpressureStrategy::DropOldest)
+            .with_policy("pose", QueuePolicy::KeepLatest);
+        queue.push_input("pose");
+        queue.push_input("cmd");
+        let latest = queue.push_input("pose");
+
+        assert_eq!(queue.inputs(), ["cmd", "pose"]);
+        assert_eq!(queue.dropped("pose"), 1);
+        assert_eq!(queue.queued_inputs.load(Ordering::Relaxed), 2);
+        let Some(NodeEvent::Input { metadata, .. }) = queue
+            .events
+            .back()
+            .and_then(|e| e.as_ref().as_ref())
+            .map(|e| &e.inner)
+        else {
+            panic!("expected an input");
+        };
+        assert_eq!(metadata.timestamp(), latest);
+    }
+
+    #[test]
+    fn drop_oldest_keeps_lossless_inputs() {
+        let mut queue = Queue::new(2, BackpressureStrategy::DropOldest)
+            .with_policy("cmd", QueuePolicy::Lossless);
+        queue.push_input("cmd");
+        queue.push_input("a");
+        queue.push_input("b");
+
+        assert_eq!(queue.inputs(), ["cmd", "b"]);
+        assert_eq!(queue.dropped("a"), 1);
+        assert_eq!(queue.dropped("cmd"), 0);
+    }
+
+    #[test]
+    fn drop_oldest_drops_whole_batch() {
+        let mut queue = Queue::new(2, BackpressureStrategy::DropOldest);
+        queue.push_batch(&["a", "b"]);
+        queue.push_batch(&["a", "b"]);
+
+        assert_eq!(queue.inputs(), ["a+b"]);
+        assert_eq!(queue.queued_inputs.load(Ordering::Relaxed), 2);
+        assert_eq!(queue.dropped("a"), 1);
+        assert_eq!(queue.dropped("b"), 1);
+    }
+
+    #[test]
+    fn batches_with_lossless_inputs_are_kept() {
+        let mut queue = Queue::new(2, BackpressureStrategy::DropOldest)
+            .with_policy("cmd", QueuePolicy::Lossless);
+        queue.push_batch(&["cmd", "b"]);
+        queue.push_input("a");
+        queue.push_batch(&["a", "b"]);
+
+        assert_eq!(queue.inputs(), ["cmd+b", "a+b"]);
+        assert_eq!(queue.queued_inputs.load(Ordering::Relaxed), 4);
+        assert_eq!(queue.dropped("a"), 1);
+        assert_eq!(queue.dropped("b"), 0);
+    }
+
+    #[test]
+    fn lossless_inputs_exceed_capacity() {
+        let mut queue = Queue::new(1, BackpressureStrategy::DropIncoming)
+            .with_policy("cmd", QueuePolicy::Lossless);
+        queue.push_input("cmd");
+        queue.push_input("cmd");
+        queue.push_input("cmd");
+
+        assert_eq!(queue.inputs(), ["cmd", "cmd", "cmd"]);
+        assert_eq!(queue.queued_inputs.load(Ordering::Relaxed), 3);
+        assert!(queue.dropped.counts.is_empty());
+    }
+
+    #[test]
+    fn drop_incoming_drops_new_input() {
+        let mut queue = Queue::new(2, BackpressureStrategy::DropIncoming);
+        queue.push_input("a");
+        queue.push_input("a");
+        queue.push_input("b");
+        // events other than inputs are always queued
+        queue.push(NodeEvent::AllInputsClosed);
+
+        assert_eq!(queue.inputs(), ["a", "a"]);
+        assert_eq!(queue.events.len(), 3);
+        assert_eq!(queue.dropped("b"), 1);
+    }
+
+    #[test]
+    fn block_queues_beyond_capacity() {
+        let mut queue = Queue::new(1, BackpressureStrategy::Block);
+        queue.push_input("a");
+        queue.push_input("a");
+
+        assert_eq!(queue.inputs(), ["a", "a"]);
+        assert_eq!(queue.queued_inputs.load(Ordering::Relaxed), 2);
+        assert!(queue.dropped.counts.is_empty());
+    }
+
+    #[test]
+    fn block_replaces_unblockable_inputs() {
+        let mut queue = Queue::new(1, BackpressureStrategy::Block).with_unblockable("tick");
+        queue.push_input("a");
+        queue.push_input("tick");
+        queue.push_input("a");
+        queue.push_input("tick");
+
+        // the blocked inputs are never dropped
+        assert_eq!(queue.inputs(), ["a", "a", "tick"]);
+        assert_eq!(queue.queued_inputs.load(Ordering::Relaxed), 3);
+        assert_eq!(queue.dropped("tick"), 1);
+        assert_eq!(queue.dropped("a"), 0);
+    }
+
+    #[test]
+    fn block_counts_inputs_in_flight() {
+        let mut queue = Queue::new(2, BackpressureStrategy::Block);
+        // the channel has room for all events, see the tests below for full channels
+        let (sender, mut receiver) = event_channel(
+            &EventQueueConfig {
+                capacity: Some(4),
+                backpressure: BackpressureStrategy::Block,
+            },
+            &queue.input_policies,
+        );
+
+        // the producer sends faster than the listener moves the inputs to the queue
+        for _ in 0..3 {
+            sender.send(queue.input("a")).unwrap();
+        }
+        sender
+            .send(Timestamped {
+                inner: NodeEvent::AllInputsClosed,
+                timestamp: queue.clock.new_timestamp(),
+            })
+            .unwrap();
+        assert_eq!(sender.in_flight_inputs(), 3);
+        assert_eq!(queue.queued_inputs.load(Ordering::Relaxed), 0);
+        assert!(queue_is_full(&queue.queued_inputs, &sender, 2));
+
+        queue.push(receiver.try_recv().unwrap().inner);
+        assert_eq!(sender.in_flight_inputs(), 2);
+        assert!(queue_is_full(&queue.queued_inputs, &sender, 2));
+
+        while let Ok(event) = receiver.try_recv() {
+            queue.push(event.inner);
+        }
+        assert_eq!(sender.in_flight_inputs(), 0);
+        assert_eq!(queue.queued_inputs.load(Ordering::Relaxed), 3);
+        assert!(queue_is_full(&queue.queued_inputs, &sender, 2));
+
+        // inputs that can't be delivered are not in flight
+        drop(receiver);
+        assert!(sender.send(queue.input("a")).is_err());
+        assert_eq!(sender.in_flight_inputs(), 0);
+    }
+
+    #[tokio::test]
+    async fn slow_receiver_blocks_delivery() {
+        let queue = Queue::new(1, BackpressureStrategy::Block);
+        let (sender, mut receiver) = event_channel(&queue.config, &queue.input_policies);
+
+        sender.send(queue.input("a")).unwrap();
+        // the channel is full, so the input waits until the receiver has room
+        sender.send(queue.input("b")).unwrap();
+        sender
+            .send(Timestamped {
+                inner: NodeEvent::AllInputsClosed,
+                timestamp: queue.clock.new_timestamp(),
+            })
+            .unwrap();
+        assert_eq!(sender.in_flight_inputs(), 2);
+
+        let mut received = Vec::new();
+        for _ in 0..3 {
+            let event = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+                .await
+                .expect("blocked events were not delivered")
+                .unwrap();
+            received.push(match event.inner {
+                NodeEvent::Input { id, .. } => id.to_string(),
+                other => format!("{other:?}"),
+            });
+        }
+        assert_eq!(received, ["a", "b", "AllInputsClosed"]);
+        assert_eq!(sender.in_flight_inputs(), 0);
+    }
+
+    #[test]
+    fn blocked_nodes_wait_for_each_other() {
+        let id = |id: &str| NodeId::from(id.to_owned());
+        let blocked_senders = BTreeMap::from([
+            (id("a"), BTreeSet::from([id("b")])),
+            (id("b"), BTreeSet::from([id("c"), id("d")])),
+            (id("d"), BTreeSet::from([id("b")])),
+        ]);
+
+        assert!(waits_for(&blocked_senders, &id("a"), &id("c")));
+        assert!(waits_for(&blocked_senders, &id("d"), &id("c")));
+        assert!(!waits_for(&blocked_senders, &id("b"), &id("a")));
+        assert!(!waits_for(&blocked_senders, &id("c"), &id("a")));
+    }
+
+    /// Receives the given number of events and returns the input ids or the
+    /// debug output of other events.
+    async fn receive(receiver: &mut EventReceiver, count: usize) -> Vec<String> {
+        let mut received = Vec::new();
+        for _ in 0..count {
+            let event = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+                .await
+                .expect("overflowing events were not delivered")
+                .unwrap();
+            received.push(match event.inner {
+                NodeEvent::Input { id, .. } => id.to_string(),
+                other => format!("{other:?}"),
+            });
+        }
+        received
+    }
+
+    #[tokio::test]
+    async fn slow_receiver_drops_inputs() {
+        for (backpressure, kept, dropped) in [
+            (BackpressureStrategy::DropOldest, "c", "b"),
+            (BackpressureStrategy::DropIncoming, "b", "c"),
+        ] {
+            let queue = Queue::new(1, backpressure);
+            let (sender, mut receiver) = event_channel(&queue.config, &queue.input_policies);
+
+            sender.send(queue.input("a")).unwrap();
+            // the channel is full, the overflow holds one more input
+            sender.send(queue.input("b")).unwrap();
+            sender.send(queue.input("c")).unwrap();
+            // other events are never dropped
+            sender
+                .send(Timestamped {
+                    inner: NodeEvent::AllInputsClosed,
+                    timestamp: queue.clock.new_timestamp(),
+                })
+                .unwrap();
+            assert_eq!(sender.in_flight_inputs(), 2);
+
+            let received = receive(&mut receiver, 3).await;
+            assert_eq!(received, ["a", kept, "AllInputsClosed"]);
+            assert_eq!(sender.in_flight_inputs(), 0);
+            let dropped_counts = receiver.take_dropped().counts;
+            assert_eq!(dropped_counts, [(dropped.to_owned().into(), 1)].into());
+        }
+    }
+
+    #[tokio::test]
+    async fn full_channel_keeps_lossless_and_latest_inputs() {
+        let queue = Queue::new(1, BackpressureStrategy::DropIncoming)
+            .with_policy("cmd", QueuePolicy::Lossless)
+            .with_policy("pose", QueuePolicy::KeepLatest);
+        let (sender, mut receiver) = event_channel(&queue.config, &queue.input_policies);
+
+        sender.send(queue.input("a")).unwrap();
+        sender.send(queue.input("pose")).unwrap();
+        // the overflow is full, but replaces the queued input
+        let latest = queue.input("pose");
+        let latest_timestamp = latest.timestamp;
+        sender.send(latest).unwrap();
+        sender.send(queue.input("b")).unwrap();
+        // lossless inputs are queued regardless
+        sender.send(queue.input("cmd")).unwrap();
+        sender.send(queue.input("cmd")).unwrap();
+        assert_eq!(sender.in_flight_inputs(), 4);
+
+        let event = receiver.recv().await.unwrap();
+        assert!(matches!(event.inner, NodeEvent::Input { id, .. } if id.as_str() == "a"));
+        let event = receiver.recv().await.unwrap();
+        assert_eq!(event.timestamp, latest_timestamp);
+        let received = receive(&mut receiver, 2).await;
+        assert_eq!(received, ["cmd", "cmd"]);
+        assert_eq!(sender.in_flight_inputs(), 0);
+        let dropped = receiver.take_dropped().counts;
+        assert_eq!(
+            dropped,
+            [("b".to_owned().into(), 1), ("pose".to_owned().into(), 1)].into()
+        );
+    }
+
+    /// Connection that replays the given requests and records the replies.
+    struct FakeConnection {
+        requests: VecDeque<Timestamped<DaemonRequest>>,
+        replies: Vec<DaemonReply>,
+    }
+
+    #[async_trait::async_trait]
+    impl Connection for &mut FakeConnection {
+        async fn receive_message(&mut self) -> eyre::Result<Option<Timestamped<DaemonRequest>>> {
+            Ok(self.requests.pop_front())
+        }
+
+        async fn send_reply(&mut self, message: DaemonReply) -> eyre::Result<()> {
+            self.replies.push(message);
+            Ok(())
+        }
+    }
+
+    fn expected_node(token: NodeToken) -> ExpectedNode {
+        ExpectedNode {
+            dataflow_id: DataflowId::nil(),
+            node_id: "node".to_owned().into(),
+            token,
+            event_queue: EventQueueConfig::default(),
+            input_policies: InputPolicies::default(),
+        }
+    }
+
+    /// Registers at a listener for `expected` and returns the register reply
+    /// and whether the listener forwarded any event to the daemon.
+    async fn register(
+        expected: &ExpectedNode,
+        request: NodeRegisterRequest,
+    ) -> (Result<(), String>, bool) {
+        let clock = Arc::new(HLC::default());
+        let mut connection = FakeConnection {
+            requests: VecDeque::from([Timestamped {
+                inner: DaemonRequest::Register(request),
+                timestamp: clock.new_timestamp(),
+            }]),
+            replies: Vec::new(),
+        };
+        let (daemon_tx, mut daemon_rx) = mpsc::channel(10);
+        Listener::run(&mut connection, daemon_tx, expected, clock).await;
+        let forwarded = daemon_rx.try_recv().is_ok();
+        match connection.replies.first() {
+            Some(DaemonReply::Result(result)) => (result.clone(), forwarded),
+            other => panic!("expected register result, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn listener_accepts_expected_node() {
+        let token = NodeToken::generate();
+        let expected = expected_node(token.clone());
+        let request =
+            NodeRegisterRequest::new(expected.dataflow_id, expected.node_id.clone(), token);
+
+        let (result, _) = register(&expected, request).await;
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn listener_rejects_wrong_token() {
+        let expected = expected_node(NodeToken::generate());
+        let request = NodeRegisterRequest::new(
+            expected.dataflow_id,
+            expected.node_id.clone(),
+            NodeToken::generate(),
+        );
+
+        let (result, forwarded) = register(&expected, request).await;
+        assert_eq!(result, Err("invalid node token".to_owned()));
+        assert!(!forwarded);
+    }
+
+    #[tokio::test]
+    async fn listener_rejects_other_node() {
+        let token = NodeToken::generate();
+        let expected = expected_node(token.clone());
+        let request =
+            NodeRegisterRequest::new(expected.dataflow_id, "other".to_owned().into(), token);
+
+        let (result, forwarded) = register(&expected, request).await;
+        let err = result.unwrap_err();
+        assert!(err.starts_with("listener belongs to node"), "{err}");
+        assert!(!forwarded);
+    }
+
+    #[tokio::test]
+    async fn listener_rejects_other_dataflow() {
+        let token = NodeToken::generate();
+        let expected = expected_node(token.clone());
+        let request =
+            NodeRegisterRequest::new(DataflowId::from_u128(1), expected.node_id.clone(), token);
+
+        let (result, forwarded) = register(&expected, request).await;
+        let err = result.unwrap_err();
+        assert!(err.starts_with("listener belongs to node"), "{err}");
+        assert!(!forwarded);
+    }
+}
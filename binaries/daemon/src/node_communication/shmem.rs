@@ -1,6 +1,6 @@
 use std::{collections::BTreeMap, sync::Arc};
 
-use super::{Connection, Listener};
+use super::{Connection, ExpectedNode, Listener};
 use crate::Event;
 use dora_core::{config::DataId, uhlc::HLC};
 use dora_message::{
@@ -10,10 +10,11 @@ use eyre::eyre;
 use shared_memory_server::ShmemServer;
 use tokio::sync::{mpsc, oneshot};
 
-#[tracing::instrument(skip(server, daemon_tx, clock), level = "trace")]
+#[tracing::instrument(skip(server, daemon_tx, expected, clock), level = "trace")]
 pub async fn listener_loop(
     mut server: ShmemServer<Timestamped<DaemonRequest>, DaemonReply>,
     daemon_tx: mpsc::Sender<Timestamped<Event>>,
+    expected: Arc<ExpectedNode>,
     queue_sizes: BTreeMap<DataId, usize>,
     clock: Arc<HLC>,
 ) {
@@ -39,7 +40,7 @@ pub async fn listener_loop(
         }
     });
     let connection = ShmemConnection(tx);
-    Listener::run(connection, daemon_tx, clock).await
+    Listener::run(connection, daemon_tx, &expected, clock).await
 }
 
 enum Operation {
@@ -1,6 +1,6 @@
 use std::{collections::BTreeMap, io::ErrorKind, sync::Arc};
 
-use super::{Connection, Listener};
+use super::{Connection, ExpectedNode, Listener};
 use crate::{
     socket_stream_utils::{socket_stream_receive, socket_stream_send},
     Event,
@@ -15,10 +15,11 @@ use tokio::{
     sync::mpsc,
 };
 
-#[tracing::instrument(skip(listener, daemon_tx, clock), level = "trace")]
+#[tracing::instrument(skip(listener, daemon_tx, expected, clock), level = "trace")]
 pub async fn listener_loop(
     listener: TcpListener,
     daemon_tx: mpsc::Sender<Timestamped<Event>>,
+    expected: Arc<ExpectedNode>,
     queue_sizes: BTreeMap<DataId, usize>,
     clock: Arc<HLC>,
 ) {
@@ -35,6 +36,7 @@ pub async fn listener_loop(
                 tokio::spawn(handle_connection_loop(
                     connection,
                     daemon_tx.clone(),
+                    expected.clone(),
                     queue_sizes.clone(),
                     clock.clone(),
                 ));
@@ -43,10 +45,11 @@ pub async fn listener_loop(
     }
 }
 
-#[tracing::instrument(skip(connection, daemon_tx, clock), level = "trace")]
+#[tracing::instrument(skip(connection, daemon_tx, expected, clock), level = "trace")]
 async fn handle_connection_loop(
     connection: TcpStream,
     daemon_tx: mpsc::Sender<Timestamped<Event>>,
+    expected: Arc<ExpectedNode>,
     queue_sizes: BTreeMap<DataId, usize>,
     clock: Arc<HLC>,
 ) {
@@ -54,7 +57,7 @@ async fn handle_connection_loop(
         tracing::warn!("failed to set nodelay for connection: {err}");
     }
 
-    Listener::run(TcpConnection(connection), daemon_tx, clock).await
+    Listener::run(TcpConnection(connection), daemon_tx, &expected, clock).await
 }
 
 struct TcpConnection(TcpStream);
@@ -15,12 +15,13 @@ use crate::{
     Event,
 };
 
-use super::{Connection, Listener};
+use super::{Connection, ExpectedNode, Listener};
 
-#[tracing::instrument(skip(listener, daemon_tx, clock), level = "trace")]
+#[tracing::instrument(skip(listener, daemon_tx, expected, clock), level = "trace")]
 pub async fn listener_loop(
     listener: UnixListener,
     daemon_tx: mpsc::Sender<Timestamped<Event>>,
+    expected: Arc<ExpectedNode>,
     queue_sizes: BTreeMap<DataId, usize>,
     clock: Arc<HLC>,
 ) {
@@ -37,6 +38,7 @@ pub async fn listener_loop(
                 tokio::spawn(handle_connection_loop(
                     connection,
                     daemon_tx.clone(),
+                    expected.clone(),
                     queue_sizes.clone(),
                     clock.clone(),
                 ));
@@ -45,14 +47,15 @@ pub async fn listener_loop(
     }
 }
 
-#[tracing::instrument(skip(connection, daemon_tx, clock), level = "trace")]
+#[tracing::instrument(skip(connection, daemon_tx, expected, clock), level = "trace")]
 async fn handle_connection_loop(
     connection: UnixStream,
     daemon_tx: mpsc::Sender<Timestamped<Event>>,
+    expected: Arc<ExpectedNode>,
     queue_sizes: BTreeMap<DataId, usize>,
     clock: Arc<HLC>,
 ) {
-    Listener::run(UnixConnection(connection), daemon_tx, clock).await
+    Listener::run(UnixConnection(connection), daemon_tx, &expected, clock).await
 }
 
 struct UnixConnection(UnixStream);
@@ -0,0 +1,101 @@
+//! Pinning of node processes to NUMA nodes.
+
+/// Sets up the command so that the spawned process only runs on the CPUs of
+/// the NUMA node and prefers its memory.
+///
+/// The CPU affinity and memory policy are inherited by all threads and child
+/// processes of the node. Shared memory is allocated when it is first
+/// written, so the shared memory of the outputs of the node is placed on the
+/// NUMA node too.
+#[cfg(target_os = "linux")]
+pub fn apply(command: &mut tokio::process::Command, numa_node: u32) -> eyre::Result<()> {
+    let placement = linux::Placement::new(numa_node)?;
+    // SAFETY: the closure only performs system calls on memory that was
+    // prepared before forking, which is async-signal-safe
+    unsafe {
+        command.pre_exec(move || placement.enter());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn apply(_command: &mut tokio::process::Command, _numa_node: u32) -> eyre::Result<()> {
+    eyre::bail!("pinning nodes to NUMA nodes is only supported on Linux")
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::io;
+
+    use eyre::{bail, Context};
+
+    const MPOL_PREFERRED: libc::c_int = 1;
+
+    pub struct Placement {
+        cpus: libc::cpu_set_t,
+        node_mask: Vec<libc::c_ulong>,
+    }
+
+    impl Placement {
+        pub fn new(numa_node: u32) -> eyre::Result<Self> {
+            let cpu_list_path = format!("/sys/devices/system/node/node{numa_node}/cpulist");
+            let cpu_list = std::fs::read_to_string(&cpu_list_path)
+                .wrap_err_with(|| format!("NUMA node {numa_node} does not exist"))?;
+
+            // SAFETY: `cpu_set_t` is a plain bit set, for which zero is valid
+            let mut cpus: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+            for cpu in parse_cpu_list(&cpu_list)
+                .wrap_err_with(|| format!("failed to parse `{cpu_list_path}`"))?
+            {
+                if cpu >= libc::CPU_SETSIZE as usize {
+                    bail!("CPU {cpu} of NUMA node {numa_node} is not supported");
+                }
+                // SAFETY: `cpu` was checked to be within the set
+                unsafe { libc::CPU_SET(cpu, &mut cpus) };
+            }
+
+            let bits = libc::c_ulong::BITS as usize;
+            let mut node_mask = vec![0; numa_node as usize / bits + 1];
+            node_mask[numa_node as usize / bits] |= 1 << (numa_node as usize % bits);
+
+            Ok(Self { cpus, node_mask })
+        }
+
+        /// Applies the placement to the current process.
+        pub fn enter(&self) -> io::Result<()> {
+            // SAFETY: the CPU set is initialized and of the given size
+            let result = unsafe {
+                libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &self.cpus)
+            };
+            if result != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let max_node = self.node_mask.len() * libc::c_ulong::BITS as usize;
+            // SAFETY: the node mask contains `max_node` bits
+            let result = unsafe {
+                libc::syscall(
+                    libc::SYS_set_mempolicy,
+                    MPOL_PREFERRED,
+                    self.node_mask.as_ptr(),
+                    max_node as libc::c_ulong,
+                )
+            };
+            if result != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+    }
+
+    /// Parses a CPU list like `0-3,8-11`.
+    fn parse_cpu_list(list: &str) -> eyre::Result<Vec<usize>> {
+        let mut cpus = Vec::new();
+        for range in list.trim().split(',').filter(|r| !r.is_empty()) {
+            match range.split_once('-') {
+                Some((start, end)) => cpus.extend(start.parse::<usize>()?..=end.parse()?),
+                None => cpus.push(range.parse()?),
+            }
+        }
+        Ok(cpus)
+    }
+}
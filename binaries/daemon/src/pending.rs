@@ -54,6 +54,23 @@ impl PendingNodes {
         self.local_nodes.insert(node_id);
     }
 
+    /// Snapshot of the synchronization state, see [`crate::dump`].
+    #[cfg(feature = "state-dump")]
+    pub fn dump_state(&self) -> serde_json::Value {
+        serde_json::json!({
+            "local_nodes": self.local_nodes,
+            "external_nodes": self.external_nodes,
+            "waiting_subscribers": self.waiting_subscribers.keys().collect::<Vec<_>>(),
+            "exited_before_subscribe": self.exited_before_subscribe,
+            "reported_init_to_coordinator": self.reported_init_to_coordinator,
+        })
+    }
+
+    /// Whether the given local node did not subscribe to its events yet.
+    pub fn is_pending(&self, node_id: &NodeId) -> bool {
+        self.local_nodes.contains(node_id)
+    }
+
     pub fn set_external_nodes(&mut self, value: bool) {
         self.external_nodes = value;
     }
@@ -92,6 +109,7 @@ impl PendingNodes {
                 file: None,
                 line: None,
                 message: "node exited before initializing dora connection".into(),
+                fields: Default::default(),
             });
             self.exited_before_subscribe.push(node_id.clone());
             self.update_dataflow_status(coordinator_connection, clock, cascading_errors)
@@ -100,6 +118,20 @@ impl PendingNodes {
         Ok(log)
     }
 
+    /// Updates the dataflow status after all local nodes were spawned.
+    ///
+    /// This is required if no local node subscribes, e.g. because all local
+    /// nodes are replayed from a recording.
+    pub async fn handle_spawn_done(
+        &mut self,
+        coordinator_connection: &mut Option<TcpStream>,
+        clock: &HLC,
+        cascading_errors: &mut CascadingErrorCauses,
+    ) -> eyre::Result<DataflowStatus> {
+        self.update_dataflow_status(coordinator_connection, clock, cascading_errors)
+            .await
+    }
+
     pub async fn handle_dataflow_stop(
         &mut self,
         coordinator_connection: &mut Option<TcpStream>,
@@ -0,0 +1,73 @@
+//! Warnings for growing input queues and unreleased shared memory.
+//!
+//! A node that can't keep up with its inputs fills its event queue, so that
+//! inputs are dropped or senders are blocked, depending on the `event_queues`
+//! config of the dataflow. Receivers that keep their inputs alive make the
+//! in-flight shared memory grow. The queue metrics are therefore checked
+//! periodically and a warning is logged when they cross a threshold.
+
+use std::collections::BTreeSet;
+
+use dora_core::config::NodeId;
+use dora_message::common::QueueMetrics;
+
+/// Number of queued inputs of a node above which a warning is logged.
+const QUEUED_INPUTS_THRESHOLD: u64 = 1000;
+/// Size of the in-flight shared memory of a dataflow above which a warning is
+/// logged, in bytes.
+const IN_FLIGHT_SHARED_MEMORY_THRESHOLD: u64 = 1 << 30;
+
+/// Remembers the crossed thresholds to warn only once about them.
+///
+/// A threshold is considered crossed again after the value fell below half of
+/// the threshold.
+#[derive(Default)]
+pub struct QueueMonitor {
+    warned_nodes: BTreeSet<NodeId>,
+    warned_shared_memory: bool,
+}
+
+impl QueueMonitor {
+    /// Returns a warning for each threshold that was crossed since the last
+    /// check, together with the affected node.
+    pub fn check(&mut self, metrics: &QueueMetrics) -> Vec<(Option<NodeId>, String)> {
+        let mut warnings = Vec::new();
+
+        self.warned_nodes
+            .retain(|node_id| metrics.queued_inputs.contains_key(node_id));
+        for (node_id, &queued) in &metrics.queued_inputs {
+            if queued >= QUEUED_INPUTS_THRESHOLD {
+                if self.warned_nodes.insert(node_id.clone()) {
+                    warnings.push((
+                        Some(node_id.clone()),
+                        format!(
+                            "{queued} inputs are queued for node `{node_id}`, \
+                            it doesn't keep up with its inputs"
+                        ),
+                    ));
+                }
+            } else if queued < QUEUED_INPUTS_THRESHOLD / 2 {
+                self.warned_nodes.remove(node_id);
+            }
+        }
+
+        let bytes = metrics.in_flight_shared_memory_bytes;
+        if bytes >= IN_FLIGHT_SHARED_MEMORY_THRESHOLD {
+            if !self.warned_shared_memory {
+                self.warned_shared_memory = true;
+                warnings.push((
+                    None,
+                    format!(
+                        "{} MiB of shared memory are in flight, \
+                        receivers don't release their inputs",
+                        bytes >> 20
+                    ),
+                ));
+            }
+        } else if bytes < IN_FLIGHT_SHARED_MEMORY_THRESHOLD / 2 {
+            self.warned_shared_memory = false;
+        }
+
+        warnings
+    }
+}
@@ -0,0 +1,149 @@
+//! Recording of the outputs of local nodes, controlled by the coordinator.
+//!
+//! While a recording is active, the daemon copies the outputs of its local
+//! nodes, optionally filtered and downsampled according to the
+//! [`RecordingOptions`], to a writer thread, which stores them in the chunked
+//! format of [`dora_recording`]. The channel to the writer thread is bounded:
+//! if the disk can't keep up, messages are dropped from the recording instead
+//! of slowing down the dataflow.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use dora_message::{
+    common::{RecordingOptions, RecordingSummary},
+    metadata::Metadata,
+    DataflowId,
+};
+use dora_recording::{recording_dir, RecordedMessage, RecordingIndex, RecordingWriter};
+use eyre::Context;
+
+use crate::{Footprint, OutputId};
+
+/// Number of messages that can be queued for the writer thread.
+const WRITER_QUEUE_SIZE: usize = 1000;
+/// Writer queue size of a daemon with a [`Footprint::Minimal`].
+const MINIMAL_WRITER_QUEUE_SIZE: usize = 100;
+
+pub struct Recorder {
+    options: RecordingOptions,
+    /// Downsampling factor and number of messages of each output, or `None`
+    /// if the output is not recorded.
+    outputs: HashMap<OutputId, Option<(u64, u64)>>,
+    sender: flume::Sender<RecordedMessage>,
+    writer: tokio::task::JoinHandle<eyre::Result<RecordingIndex>>,
+    dir: PathBuf,
+    dropped: u64,
+}
+
+impl Recorder {
+    pub fn start(
+        working_dir: &Path,
+        dataflow_id: DataflowId,
+        recording_id: String,
+        machine_id: String,
+        options: RecordingOptions,
+        footprint: Footprint,
+    ) -> eyre::Result<Self> {
+        let dir = recording_dir(working_dir, &dataflow_id, &recording_id, &machine_id);
+        let mut writer = RecordingWriter::create(
+            dir.clone(),
+            dataflow_id,
+            recording_id,
+            machine_id,
+            options.clone(),
+        )?;
+
+        let queue_size = match footprint {
+            Footprint::Default => WRITER_QUEUE_SIZE,
+            Footprint::Minimal => MINIMAL_WRITER_QUEUE_SIZE,
+        };
+        let (sender, receiver) = flume::bounded::<RecordedMessage>(queue_size);
+        let writer = tokio::task::spawn_blocking(move || {
+            while let Ok(message) = receiver.recv() {
+                writer.write(&message)?;
+            }
+            writer.finish()
+        });
+
+        Ok(Self {
+            options,
+            outputs: HashMap::new(),
+            sender,
+            writer,
+            dir,
+            dropped: 0,
+        })
+    }
+
+    pub fn record(&mut self, output_id: &OutputId, metadata: &Metadata, data: Option<&[u8]>) {
+        if !self.should_record(output_id) {
+            return;
+        }
+        let message = RecordedMessage {
+            node_id: output_id.0.clone(),
+            output_id: output_id.1.clone(),
+            metadata: metadata.clone(),
+            data: data.map(|d| d.to_vec()),
+        };
+        if self.sender.try_send(message).is_err() {
+            if self.dropped == 0 {
+                tracing::warn!(
+                    "recorder can't keep up, dropping messages from recording `{}`",
+                    self.dir.display()
+                );
+            }
+            self.dropped += 1;
+        }
+    }
+
+    /// Stops the recording and waits until all queued messages are written.
+    pub async fn stop(self, machine_id: String) -> eyre::Result<RecordingSummary> {
+        let Self {
+            sender,
+            writer,
+            dir,
+            dropped,
+            ..
+        } = self;
+        drop(sender);
+        let index = writer
+            .await
+            .context("recording writer panicked")?
+            .wrap_err_with(|| format!("failed to write recording `{}`", dir.display()))?;
+        Ok(RecordingSummary {
+            machine_id,
+            messages: index.messages(),
+            bytes: index.bytes(),
+            path: dir,
+            dropped_messages: dropped,
+            deleted_messages: index.deleted_messages,
+        })
+    }
+
+    /// Checks whether the next message of the given output is recorded.
+    fn should_record(&mut self, output_id: &OutputId) -> bool {
+        let options = &self.options;
+        let output = self.outputs.entry(output_id.clone()).or_insert_with(|| {
+            let OutputId(node_id, output_id) = output_id;
+            options
+                .is_recorded(node_id.as_ref(), output_id.as_ref())
+                .then(|| {
+                    (
+                        options.downsampling(node_id.as_ref(), output_id.as_ref()),
+                        0,
+                    )
+                })
+        });
+        match output {
+            Some((downsampling, count)) => {
+                let record = *count % *downsampling == 0;
+                *count += 1;
+                record
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,364 @@
+//! Replay of recorded outputs in place of the recorded nodes.
+//!
+//! Nodes that are replaced through the `replay` section of the dataflow
+//! descriptor are not spawned. Instead, the daemon reads their outputs from a
+//! recording created through `dora record` and sends them to the receivers in
+//! timestamp order. The messages are either sent with the time intervals of
+//! the recording or as fast as the receivers accept them. Once the recording
+//! is exhausted, the replayed nodes are treated as finished, which closes the
+//! corresponding inputs of the receivers.
+//!
+//! A running replay can be paused, resumed, slowed down or sped up, and
+//! stepped through message by message through `dora replay`.
+
+use std::{collections::BTreeSet, path::PathBuf, sync::Arc, time::Duration};
+
+use dora_core::{config::NodeId, descriptor::ReplayTiming, uhlc::HLC};
+use dora_message::{common::ReplayControl, daemon_to_coordinator::Timestamped, DataflowId};
+use dora_recording::{RecordedMessage, RecordingReader};
+use eyre::{bail, Context};
+use tokio::sync::{mpsc, oneshot, watch};
+
+use crate::{sim_time::SimulatedClock, DoraEvent, Event, Footprint};
+
+/// Number of messages that the reader thread reads ahead.
+const READ_AHEAD: usize = 100;
+/// Read-ahead of a daemon with a [`Footprint::Minimal`].
+const MINIMAL_READ_AHEAD: usize = 10;
+
+pub struct Replay {
+    recording: PathBuf,
+    nodes: BTreeSet<NodeId>,
+    timing: ReplayTiming,
+    /// Number of messages that the reader thread reads ahead.
+    read_ahead: usize,
+    /// Clock of the dataflow if it runs on simulated time.
+    simulated_clock: Option<Arc<SimulatedClock>>,
+    playback: watch::Sender<Playback>,
+    stop: Option<oneshot::Sender<()>>,
+}
+
+/// Playback state of a replay, changed through [`ReplayControl`]s.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Playback {
+    paused: bool,
+    /// Speed multiplier for `original` timing.
+    speed: f64,
+    /// Number of messages that may be sent while paused.
+    ///
+    /// Only ever increases, so that the replay task can tell how many steps
+    /// were requested since it last looked.
+    steps: u64,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            speed: 1.0,
+            steps: 0,
+        }
+    }
+}
+
+impl Replay {
+    pub fn new(
+        recording: PathBuf,
+        nodes: BTreeSet<NodeId>,
+        timing: ReplayTiming,
+        simulated_clock: Option<Arc<SimulatedClock>>,
+        footprint: Footprint,
+    ) -> Self {
+        Self {
+            recording,
+            nodes,
+            timing,
+            read_ahead: match footprint {
+                Footprint::Default => READ_AHEAD,
+                Footprint::Minimal => MINIMAL_READ_AHEAD,
+            },
+            simulated_clock,
+            playback: watch::Sender::new(Playback::default()),
+            stop: None,
+        }
+    }
+
+    /// Applies the given control to the replay.
+    ///
+    /// Controls that are sent before the replay starts take effect once it
+    /// starts, e.g. to start a replay paused.
+    pub fn control(&self, control: ReplayControl) -> eyre::Result<()> {
+        if let ReplayControl::SetSpeed(speed) = control {
+            if !speed.is_finite() || speed <= 0.0 {
+                bail!("replay speed must be a positive number, got {speed}");
+            }
+        }
+        self.playback.send_modify(|playback| match control {
+            ReplayControl::Pause => playback.paused = true,
+            ReplayControl::Resume => playback.paused = false,
+            ReplayControl::Step => {
+                playback.paused = true;
+                playback.steps += 1;
+            }
+            ReplayControl::SetSpeed(speed) => playback.speed = speed,
+        });
+        Ok(())
+    }
+
+    /// Starts sending the recorded outputs of the replayed nodes as
+    /// [`DoraEvent::ReplayOutput`] events.
+    ///
+    /// A [`DoraEvent::ReplayFinished`] event is sent when the replay is done or
+    /// stopped.
+    pub fn start(
+        &mut self,
+        dataflow_id: DataflowId,
+        events_tx: &mpsc::Sender<Timestamped<Event>>,
+        clock: &Arc<HLC>,
+    ) {
+        if self.stop.is_some() {
+            return;
+        }
+        let (stop_tx, stop_rx) = oneshot::channel();
+        self.stop = Some(stop_tx);
+        tokio::spawn(run(
+            self.recording.clone(),
+            self.nodes.clone(),
+            self.timing,
+            self.read_ahead,
+            self.simulated_clock.clone(),
+            self.playback.subscribe(),
+            dataflow_id,
+            events_tx.clone(),
+            clock.clone(),
+            stop_rx,
+        ));
+    }
+
+    /// Stops a running replay.
+    ///
+    /// Returns `false` if the replay was not started.
+    pub fn stop(&mut self) -> bool {
+        match self.stop.take() {
+            Some(stop) => {
+                let _ = stop.send(());
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn run(
+    recording: PathBuf,
+    nodes: BTreeSet<NodeId>,
+    timing: ReplayTiming,
+    read_ahead: usize,
+    simulated_clock: Option<Arc<SimulatedClock>>,
+    playback: watch::Receiver<Playback>,
+    dataflow_id: DataflowId,
+    events_tx: mpsc::Sender<Timestamped<Event>>,
+    clock: Arc<HLC>,
+    stop: oneshot::Receiver<()>,
+) {
+    let replay = replay_messages(
+        recording,
+        nodes,
+        timing,
+        read_ahead,
+        simulated_clock,
+        playback,
+        dataflow_id,
+        &events_tx,
+        &clock,
+    );
+    let result = tokio::select! {
+        result = replay => result,
+        _ = stop => Ok(()),
+    };
+    let event = Timestamped {
+        inner: DoraEvent::ReplayFinished {
+            dataflow_id,
+            result: result.map_err(|err| format!("{err:?}")),
+        }
+        .into(),
+        timestamp: clock.new_timestamp(),
+    };
+    let _ = events_tx.send(event).await;
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn replay_messages(
+    recording: PathBuf,
+    nodes: BTreeSet<NodeId>,
+    timing: ReplayTiming,
+    read_ahead: usize,
+    simulated_clock: Option<Arc<SimulatedClock>>,
+    mut playback: watch::Receiver<Playback>,
+    dataflow_id: DataflowId,
+    events_tx: &mpsc::Sender<Timestamped<Event>>,
+    clock: &HLC,
+) -> eyre::Result<()> {
+    let (tx, rx) = flume::bounded::<RecordedMessage>(read_ahead);
+    let reader = tokio::task::spawn_blocking(move || {
+        let parts = RecordingReader::open_all(&recording)?;
+        for message in dora_recording::merge(parts.iter().map(|part| part.messages())) {
+            let message = message?;
+            if !nodes.contains(&message.node_id) {
+                continue;
+            }
+            if tx.send(message).is_err() {
+                // replay was stopped
+                break;
+            }
+        }
+        eyre::Ok(())
+    });
+
+    let timeline = match simulated_clock {
+        Some(simulated_clock) => Timeline::Simulated(simulated_clock),
+        None => Timeline::Real(tokio::time::Instant::now()),
+    };
+    let mut player: Option<Player> = None;
+    let mut steps_taken = 0;
+    while let Ok(message) = rx.recv_async().await {
+        let recorded = message.timestamp().get_time().to_duration();
+        let player = player.get_or_insert_with(|| Player::new(&timeline, recorded));
+        loop {
+            let state = *playback.borrow_and_update();
+            player.update(&timeline, state);
+            if state.paused {
+                if state.steps > steps_taken {
+                    steps_taken += 1;
+                    // continue from the stepped message once resumed
+                    player.seek(&timeline, recorded);
+                    break;
+                }
+            } else {
+                steps_taken = state.steps;
+                if timing == ReplayTiming::AsFastAsPossible {
+                    break;
+                }
+                let deadline = player.deadline(recorded);
+                tokio::select! {
+                    () = timeline.sleep_until(deadline) => break,
+                    changed = playback.changed() => {
+                        if changed.is_err() {
+                            // the replay was dropped
+                            return Ok(());
+                        }
+                        continue;
+                    }
+                }
+            }
+            if playback.changed().await.is_err() {
+                return Ok(());
+            }
+        }
+
+        let RecordedMessage {
+            node_id,
+            output_id,
+            metadata,
+            data,
+        } = message;
+        let event = Timestamped {
+            inner: DoraEvent::ReplayOutput {
+                dataflow_id,
+                node_id,
+                output_id,
+                metadata,
+                data,
+            }
+            .into(),
+            timestamp: clock.new_timestamp(),
+        };
+        if events_tx.send(event).await.is_err() {
+            break;
+        }
+    }
+    drop(rx);
+
+    reader.await.context("recording reader panicked")?
+}
+
+/// The time that the replayed messages are scheduled on.
+enum Timeline {
+    /// Time since the given instant.
+    Real(tokio::time::Instant),
+    Simulated(Arc<SimulatedClock>),
+}
+
+impl Timeline {
+    fn now(&self) -> Duration {
+        match self {
+            Timeline::Real(start) => start.elapsed(),
+            Timeline::Simulated(clock) => clock.now(),
+        }
+    }
+
+    async fn sleep_until(&self, deadline: Duration) {
+        match self {
+            Timeline::Real(start) => match start.checked_add(deadline) {
+                Some(deadline) => tokio::time::sleep_until(deadline).await,
+                None => std::future::pending().await,
+            },
+            Timeline::Simulated(clock) => clock.sleep_until(deadline).await,
+        }
+    }
+}
+
+/// Maps the timeline to positions in the recording, taking pauses and speed
+/// changes into account.
+struct Player {
+    /// A time on the timeline and the position in the recording at that time.
+    anchor: (Duration, Duration),
+    state: Playback,
+}
+
+impl Player {
+    fn new(timeline: &Timeline, recording_start: Duration) -> Self {
+        Self {
+            anchor: (timeline.now(), recording_start),
+            state: Playback::default(),
+        }
+    }
+
+    /// Position in the recording at the current time.
+    fn position(&self, timeline: &Timeline) -> Duration {
+        let (time, position) = self.anchor;
+        if self.state.paused {
+            position
+        } else {
+            position.saturating_add(scale(timeline.now().saturating_sub(time), self.state.speed))
+        }
+    }
+
+    /// Re-anchors the player when the playback state changes.
+    fn update(&mut self, timeline: &Timeline, state: Playback) {
+        if state.paused != self.state.paused || state.speed != self.state.speed {
+            self.anchor = (timeline.now(), self.position(timeline));
+        }
+        self.state = state;
+    }
+
+    fn seek(&mut self, timeline: &Timeline, position: Duration) {
+        self.anchor = (timeline.now(), position);
+    }
+
+    /// Time on the timeline at which the message with the given recorded
+    /// time is due.
+    fn deadline(&self, recorded: Duration) -> Duration {
+        let (time, position) = self.anchor;
+        time.saturating_add(scale(
+            recorded.saturating_sub(position),
+            self.state.speed.recip(),
+        ))
+    }
+}
+
+/// Multiplies the duration by the given factor, saturating on overflow.
+fn scale(duration: Duration, factor: f64) -> Duration {
+    Duration::try_from_secs_f64(duration.as_secs_f64() * factor).unwrap_or(Duration::MAX)
+}
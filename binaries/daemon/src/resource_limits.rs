@@ -0,0 +1,99 @@
+//! Memory and CPU limits of nodes, see the `resources` field of the node
+//! config.
+//!
+//! On Linux, the limits are set on the cgroup of the node before it's
+//! spawned. When the node exceeds its memory limit, the kernel kills all of
+//! its processes, which is detected through the `oom_kill` counter of the
+//! cgroup. On Windows, the limits are set on the job object of the node
+//! right after it was spawned. Allocations beyond the memory limit fail
+//! there, so the node is reported as exceeding its limit if its peak memory
+//! usage reached the limit.
+
+use dora_core::descriptor::ResourceLimits;
+
+use crate::{cgroup::NodeCgroup, ProcessId};
+
+/// Checks whether a node exceeded its memory limit, after it exited.
+pub struct LimitMonitor {
+    memory_mb: Option<u64>,
+    #[cfg(target_os = "linux")]
+    oom_kills: crate::cgroup::OomKills,
+    #[cfg(windows)]
+    job: std::sync::Arc<crate::job_object::JobObject>,
+}
+
+impl LimitMonitor {
+    /// Returns the memory limit in megabytes if the node exceeded it.
+    pub fn exceeded_memory_limit(&self) -> Option<u64> {
+        let limit_mb = self.memory_mb?;
+        #[cfg(target_os = "linux")]
+        let exceeded = self.oom_kills.read().is_some_and(|kills| kills > 0);
+        #[cfg(windows)]
+        let exceeded = self.job.memory_limit_reached();
+        #[cfg(not(any(target_os = "linux", windows)))]
+        let exceeded = false;
+        exceeded.then_some(limit_mb)
+    }
+}
+
+/// Sets up the limits that need to be in place before the node is spawned.
+pub fn apply_before_spawn(
+    limits: &ResourceLimits,
+    cgroup: Option<&NodeCgroup>,
+) -> eyre::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        cgroup
+            .ok_or_else(|| {
+                eyre::eyre!(
+                    "resource limits require cgroup v2 and write access of the daemon to its cgroup"
+                )
+            })?
+            .set_limits(limits)
+    }
+    #[cfg(windows)]
+    {
+        let _ = (limits, cgroup);
+        Ok(())
+    }
+    #[cfg(not(any(target_os = "linux", windows)))]
+    {
+        let _ = (limits, cgroup);
+        eyre::bail!("resource limits are only supported on Linux and Windows")
+    }
+}
+
+/// Sets up the limits that are applied to the spawned process.
+pub fn apply_after_spawn(
+    limits: &ResourceLimits,
+    cgroup: Option<&NodeCgroup>,
+    pid: &ProcessId,
+) -> eyre::Result<LimitMonitor> {
+    #[cfg(target_os = "linux")]
+    {
+        let _ = pid;
+        let cgroup = cgroup.ok_or_else(|| eyre::eyre!("node has no cgroup"))?;
+        Ok(LimitMonitor {
+            memory_mb: limits.memory_mb,
+            oom_kills: cgroup.oom_kills(),
+        })
+    }
+    #[cfg(windows)]
+    {
+        let _ = cgroup;
+        let job = pid
+            .job()
+            .ok_or_else(|| eyre::eyre!("node process has no job object"))?
+            .clone();
+        job.set_limits(limits)?;
+        Ok(LimitMonitor {
+            memory_mb: limits.memory_mb,
+            job,
+        })
+    }
+    #[cfg(not(any(target_os = "linux", windows)))]
+    {
+        let _ = (limits, cgroup, pid);
+        eyre::bail!("resource limits are only supported on Linux and Windows")
+    }
+}
@@ -0,0 +1,182 @@
+//! Restarts of nodes that exit while their dataflow is running, see the
+//! `restart` field of the node config.
+//!
+//! The inputs and outputs of a restarting node stay open, so that the other
+//! nodes don't notice the restart apart from the gap in the messages.
+
+use std::time::Duration;
+
+use dora_core::descriptor::{Descriptor, ResolvedNode, RestartConfig, RestartPolicy};
+use dora_message::common::NodeError;
+use tokio::sync::oneshot;
+
+/// A local node with a restart policy, together with everything that is
+/// needed to spawn it again.
+pub struct RestartableNode {
+    pub node: ResolvedNode,
+    pub descriptor: Descriptor,
+    pub uv: bool,
+    config: RestartConfig,
+    /// Number of restarts so far.
+    restarts: u32,
+    /// Result of the last exit of the node while a restart is scheduled.
+    ///
+    /// Reported as the result of the node if the restart is canceled because
+    /// the dataflow was stopped in the meantime.
+    pending_result: Option<Result<(), NodeError>>,
+    /// Ends the backoff delay of the scheduled restart early.
+    skip_backoff: Option<oneshot::Sender<()>>,
+}
+
+pub struct ScheduledRestart {
+    pub delay: Duration,
+    /// Completes when the backoff delay should end early, because the
+    /// dataflow is stopped.
+    pub skip_backoff: oneshot::Receiver<()>,
+}
+
+impl RestartableNode {
+    /// Returns `None` if the node has no restart policy.
+    pub fn new(node: &ResolvedNode, descriptor: &Descriptor, uv: bool) -> Option<Self> {
+        let config = node.restart.clone()?;
+        if config.policy == RestartPolicy::Never {
+            return None;
+        }
+        Some(Self {
+            node: node.clone(),
+            descriptor: descriptor.clone(),
+            uv,
+            config,
+            restarts: 0,
+            pending_result: None,
+            skip_backoff: None,
+        })
+    }
+
+    /// Decides whether the node is restarted after it exited with the given
+    /// result.
+    pub fn schedule_restart(&mut self, result: &Result<(), NodeError>) -> Option<ScheduledRestart> {
+        let delay = restart_delay(&self.config, self.restarts, result.is_err())?;
+        self.restarts = self.restarts.saturating_add(1);
+        self.pending_result = Some(result.clone());
+        let (skip_tx, skip_rx) = oneshot::channel();
+        self.skip_backoff = Some(skip_tx);
+        Some(ScheduledRestart {
+            delay,
+            skip_backoff: skip_rx,
+        })
+    }
+
+    /// Ends the backoff delay of a scheduled restart, so that the stop of the
+    /// dataflow isn't delayed by it.
+    pub fn skip_backoff(&mut self) {
+        if let Some(skip) = self.skip_backoff.take() {
+            let _ = skip.send(());
+        }
+    }
+
+    /// Takes the result of the last exit, ending the scheduled restart.
+    pub fn take_pending_result(&mut self) -> Option<Result<(), NodeError>> {
+        self.skip_backoff = None;
+        self.pending_result.take()
+    }
+
+    pub fn is_restarting(&self) -> bool {
+        self.pending_result.is_some()
+    }
+
+    pub fn restarts(&self) -> u32 {
+        self.restarts
+    }
+}
+
+/// Returns the backoff delay before the next restart, or `None` if the node
+/// isn't restarted.
+///
+/// The delay starts at `backoff_ms` and doubles with every restart, up to
+/// `max_backoff_ms`.
+fn restart_delay(config: &RestartConfig, restarts: u32, failed: bool) -> Option<Duration> {
+    let restart = match config.policy {
+        RestartPolicy::Never => false,
+        RestartPolicy::OnFailure => failed,
+        RestartPolicy::Always => true,
+    };
+    if !restart || config.max_retries.is_some_and(|max| restarts >= max) {
+        return None;
+    }
+    let factor = 1u64.checked_shl(restarts).unwrap_or(u64::MAX);
+    let backoff = config
+        .backoff_ms
+        .saturating_mul(factor)
+        .min(config.max_backoff_ms);
+    Some(Duration::from_millis(backoff))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(policy: RestartPolicy, max_retries: Option<u32>) -> RestartConfig {
+        RestartConfig {
+            policy,
+            max_retries,
+            backoff_ms: 100,
+            max_backoff_ms: 1000,
+        }
+    }
+
+    fn delays(config: &RestartConfig, restarts: impl Iterator<Item = u32>) -> Vec<Option<u64>> {
+        restarts
+            .map(|restarts| restart_delay(config, restarts, true).map(|d| d.as_millis() as u64))
+            .collect()
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let config = config(RestartPolicy::Always, None);
+        assert_eq!(
+            delays(&config, 0..6),
+            [100, 200, 400, 800, 1000, 1000].map(Some)
+        );
+    }
+
+    #[test]
+    fn backoff_does_not_overflow() {
+        let mut config = config(RestartPolicy::Always, None);
+        config.max_backoff_ms = u64::MAX;
+        assert_eq!(
+            delays(&config, [62, 63, 64, 100, u32::MAX].into_iter()),
+            [Some(u64::MAX); 5]
+        );
+    }
+
+    #[test]
+    fn stops_after_max_retries() {
+        let three = config(RestartPolicy::Always, Some(3));
+        assert_eq!(
+            delays(&three, 0..5),
+            [Some(100), Some(200), Some(400), None, None]
+        );
+        let none = config(RestartPolicy::Always, Some(0));
+        assert_eq!(delays(&none, 0..1), [None]);
+    }
+
+    #[test]
+    fn on_failure_ignores_clean_exit() {
+        let config = config(RestartPolicy::OnFailure, None);
+        assert!(restart_delay(&config, 0, false).is_none());
+        assert!(restart_delay(&config, 0, true).is_some());
+    }
+
+    #[test]
+    fn always_restarts_after_clean_exit() {
+        let config = config(RestartPolicy::Always, None);
+        assert!(restart_delay(&config, 0, false).is_some());
+    }
+
+    #[test]
+    fn never_restarts() {
+        let config = config(RestartPolicy::Never, None);
+        assert!(restart_delay(&config, 0, true).is_none());
+    }
+}
@@ -0,0 +1,472 @@
+//! Sandboxing of node processes through Linux namespaces and seccomp.
+
+use std::path::Path;
+
+use dora_core::descriptor::SandboxConfig;
+
+use crate::user::NodeUser;
+
+/// Sets up the command so that the spawned process runs in the sandbox.
+///
+/// The restrictions are applied in the child process right before the node
+/// executable is started, so a failure makes the spawn fail.
+#[cfg(target_os = "linux")]
+pub fn apply(
+    command: &mut tokio::process::Command,
+    config: &SandboxConfig,
+    working_dir: &Path,
+    user: Option<&NodeUser>,
+) -> eyre::Result<()> {
+    let sandbox = linux::Sandbox::new(config, working_dir, user)?;
+    // SAFETY: the closure only performs system calls on memory that was
+    // prepared before forking, which is async-signal-safe
+    unsafe {
+        command.pre_exec(move || sandbox.enter());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn apply(
+    _command: &mut tokio::process::Command,
+    _config: &SandboxConfig,
+    _working_dir: &Path,
+    _user: Option<&NodeUser>,
+) -> eyre::Result<()> {
+    eyre::bail!("sandboxing nodes is only supported on Linux")
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::{ffi::CString, io, os::unix::ffi::OsStrExt, path::Path};
+
+    use dora_core::descriptor::SandboxConfig;
+    use eyre::{bail, Context};
+
+    use crate::user::NodeUser;
+
+    /// Always writable, as it contains the shared memory regions of dora.
+    const SHARED_MEMORY_DIR: &str = "/dev/shm";
+
+    // not available in the `libc` crate for all targets
+    const SYS_MOUNT_SETATTR: libc::c_long = 442;
+    const SYS_CLONE3: libc::c_long = 435;
+    const MOUNT_ATTR_RDONLY: u64 = 0x1;
+    const AT_RECURSIVE: libc::c_uint = 0x8000;
+
+    #[repr(C)]
+    struct MountAttr {
+        attr_set: u64,
+        attr_clr: u64,
+        propagation: u64,
+        userns_fd: u64,
+    }
+
+    // classic BPF instructions used by seccomp filters
+    const BPF_LD_W_ABS: u16 = 0x20;
+    const BPF_JMP_JEQ_K: u16 = 0x15;
+    #[cfg(target_arch = "x86_64")]
+    const BPF_JMP_JGE_K: u16 = 0x35;
+    const BPF_JMP_JSET_K: u16 = 0x45;
+    const BPF_RET_K: u16 = 0x06;
+
+    const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+    const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+    const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+    const SECCOMP_MODE_FILTER: libc::c_ulong = 2;
+    /// Offsets of the fields of `struct seccomp_data`.
+    const SECCOMP_DATA_NR: u32 = 0;
+    const SECCOMP_DATA_ARCH: u32 = 4;
+    /// Lower half of the first system call argument, both targets are
+    /// little endian.
+    const SECCOMP_DATA_ARG0_LOW: u32 = 16;
+
+    #[cfg(target_arch = "x86_64")]
+    const AUDIT_ARCH: u32 = 0xc000_003e;
+    #[cfg(target_arch = "aarch64")]
+    const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+    /// System calls that nodes don't need, but that allow to escape the
+    /// sandbox, to inspect other processes, or to change the system.
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    const DENIED_SYSCALLS: &[libc::c_long] = &[
+        libc::SYS_ptrace,
+        libc::SYS_process_vm_readv,
+        libc::SYS_process_vm_writev,
+        libc::SYS_mount,
+        libc::SYS_umount2,
+        libc::SYS_pivot_root,
+        libc::SYS_chroot,
+        libc::SYS_unshare,
+        libc::SYS_setns,
+        // `open_tree`, `move_mount`, `fsopen`, `fsconfig`, `fsmount`, `fspick`
+        428,
+        429,
+        430,
+        431,
+        432,
+        433,
+        SYS_MOUNT_SETATTR,
+        libc::SYS_open_by_handle_at,
+        libc::SYS_name_to_handle_at,
+        libc::SYS_init_module,
+        libc::SYS_finit_module,
+        libc::SYS_delete_module,
+        libc::SYS_kexec_load,
+        libc::SYS_kexec_file_load,
+        libc::SYS_reboot,
+        libc::SYS_swapon,
+        libc::SYS_swapoff,
+        libc::SYS_acct,
+        libc::SYS_bpf,
+        libc::SYS_perf_event_open,
+        libc::SYS_userfaultfd,
+        libc::SYS_keyctl,
+        libc::SYS_add_key,
+        libc::SYS_request_key,
+        libc::SYS_settimeofday,
+        libc::SYS_clock_settime,
+        libc::SYS_clock_adjtime,
+        libc::SYS_adjtimex,
+        libc::SYS_sethostname,
+        libc::SYS_setdomainname,
+        libc::SYS_quotactl,
+    ];
+
+    /// Everything that the child process needs to enter the sandbox,
+    /// prepared before forking.
+    pub struct Sandbox {
+        network: bool,
+        uid_map: CString,
+        gid_map: CString,
+        writable: Vec<CString>,
+        filter: Vec<libc::sock_filter>,
+    }
+
+    impl Sandbox {
+        pub fn new(
+            config: &SandboxConfig,
+            working_dir: &Path,
+            user: Option<&NodeUser>,
+        ) -> eyre::Result<Self> {
+            if !cfg!(any(target_arch = "x86_64", target_arch = "aarch64")) {
+                bail!("sandboxing nodes is only supported on x86_64 and aarch64");
+            }
+
+            let mut writable = Vec::new();
+            for path in &config.writable {
+                let path = working_dir.join(path);
+                let path = path.canonicalize().wrap_err_with(|| {
+                    format!("writable sandbox path `{}` does not exist", path.display())
+                })?;
+                writable.push(to_cstring(&path)?);
+            }
+            if Path::new(SHARED_MEMORY_DIR).exists() {
+                writable.push(to_cstring(Path::new(SHARED_MEMORY_DIR))?);
+            }
+
+            // map the user of the node to itself inside the user namespace,
+            // the privileges are dropped before entering the sandbox
+            let (uid, gid) = match user {
+                Some(user) => (user.uid, user.gid),
+                // SAFETY: `getuid` and `getgid` have no preconditions
+                None => unsafe { (libc::getuid(), libc::getgid()) },
+            };
+            Ok(Self {
+                network: config.network,
+                uid_map: CString::new(format!("{uid} {uid} 1"))?,
+                gid_map: CString::new(format!("{gid} {gid} 1"))?,
+                writable,
+                filter: seccomp_filter(),
+            })
+        }
+
+        /// Enters the sandbox, called in the child process between `fork`
+        /// and `exec`.
+        pub fn enter(&self) -> io::Result<()> {
+            let mut flags = libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWIPC;
+            if !self.network {
+                flags |= libc::CLONE_NEWNET;
+            }
+            // SAFETY: the child process is single-threaded
+            check(unsafe { libc::unshare(flags) })?;
+
+            write_file(c"/proc/self/setgroups", c"deny")?;
+            write_file(c"/proc/self/uid_map", &self.uid_map)?;
+            write_file(c"/proc/self/gid_map", &self.gid_map)?;
+
+            // don't propagate the mount changes to the parent namespace
+            // SAFETY: all pointers are valid or null as allowed by `mount`
+            check(unsafe {
+                libc::mount(
+                    std::ptr::null(),
+                    c"/".as_ptr(),
+                    std::ptr::null(),
+                    libc::MS_REC | libc::MS_PRIVATE,
+                    std::ptr::null(),
+                )
+            })?;
+            set_read_only(c"/", true)?;
+            for path in &self.writable {
+                // a bind mount is a new mount, whose read-only flag can be
+                // cleared independently of the root
+                // SAFETY: all pointers are valid or null as allowed by `mount`
+                check(unsafe {
+                    libc::mount(
+                        path.as_ptr(),
+                        path.as_ptr(),
+                        std::ptr::null(),
+                        libc::MS_BIND | libc::MS_REC,
+                        std::ptr::null(),
+                    )
+                })?;
+                set_read_only(path, false)?;
+            }
+
+            let program = libc::sock_fprog {
+                len: self.filter.len() as u16,
+                filter: self.filter.as_ptr() as *mut libc::sock_filter,
+            };
+            // SAFETY: `prctl` is called with the documented arguments and
+            // `program` outlives the call
+            check(unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) })?;
+            check(unsafe {
+                libc::prctl(
+                    libc::PR_SET_SECCOMP,
+                    SECCOMP_MODE_FILTER,
+                    &program as *const libc::sock_fprog,
+                )
+            })?;
+            Ok(())
+        }
+    }
+
+    fn set_read_only(path: &std::ffi::CStr, read_only: bool) -> io::Result<()> {
+        let attr = MountAttr {
+            attr_set: if read_only { MOUNT_ATTR_RDONLY } else { 0 },
+            attr_clr: if read_only { 0 } else { MOUNT_ATTR_RDONLY },
+            propagation: 0,
+            userns_fd: 0,
+        };
+        // SAFETY: `attr` is a valid `struct mount_attr` of the given size
+        check(unsafe {
+            libc::syscall(
+                SYS_MOUNT_SETATTR,
+                libc::AT_FDCWD,
+                path.as_ptr(),
+                AT_RECURSIVE,
+                &attr as *const MountAttr,
+                std::mem::size_of::<MountAttr>(),
+            ) as libc::c_int
+        })
+    }
+
+    fn write_file(path: &std::ffi::CStr, content: &std::ffi::CStr) -> io::Result<()> {
+        // SAFETY: `path` is a valid C string
+        let fd = unsafe { libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC) };
+        check(fd)?;
+        let bytes = content.to_bytes();
+        // SAFETY: `bytes` is valid for `bytes.len()` bytes
+        let written = unsafe { libc::write(fd, bytes.as_ptr().cast(), bytes.len()) };
+        // SAFETY: `fd` was opened above
+        unsafe { libc::close(fd) };
+        if written < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn check(result: libc::c_int) -> io::Result<()> {
+        if result < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn to_cstring(path: &Path) -> eyre::Result<CString> {
+        CString::new(path.as_os_str().as_bytes())
+            .wrap_err_with(|| format!("invalid path `{}`", path.display()))
+    }
+
+    /// Builds a seccomp filter that kills processes of other architectures
+    /// and makes the denied system calls fail with `EPERM`.
+    ///
+    /// Creating user namespaces through `clone` is denied as well, since they
+    /// give the process the capabilities to create new mount namespaces. The
+    /// flags of `clone3` are passed in memory, which seccomp can't inspect, so
+    /// it fails with `ENOSYS` to make the C library fall back to `clone`.
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    fn seccomp_filter() -> Vec<libc::sock_filter> {
+        let statement = |code, k| libc::sock_filter {
+            code,
+            jt: 0,
+            jf: 0,
+            k,
+        };
+        let jump = |code, k, jt, jf| libc::sock_filter { code, jt, jf, k };
+        let deny = SECCOMP_RET_ERRNO | libc::EPERM as u32;
+
+        let mut filter = vec![
+            statement(BPF_LD_W_ABS, SECCOMP_DATA_ARCH),
+            jump(BPF_JMP_JEQ_K, AUDIT_ARCH, 1, 0),
+            statement(BPF_RET_K, SECCOMP_RET_KILL_PROCESS),
+            statement(BPF_LD_W_ABS, SECCOMP_DATA_NR),
+        ];
+        // deny the x32 ABI, which would bypass the system call numbers below
+        #[cfg(target_arch = "x86_64")]
+        filter.extend([
+            jump(BPF_JMP_JGE_K, 0x4000_0000, 0, 1),
+            statement(BPF_RET_K, deny),
+        ]);
+        for &syscall in DENIED_SYSCALLS {
+            filter.push(jump(BPF_JMP_JEQ_K, syscall as u32, 0, 1));
+            filter.push(statement(BPF_RET_K, deny));
+        }
+        filter.extend([
+            jump(BPF_JMP_JEQ_K, SYS_CLONE3 as u32, 0, 1),
+            statement(BPF_RET_K, SECCOMP_RET_ERRNO | libc::ENOSYS as u32),
+            // must be last, as it overwrites the loaded system call number
+            jump(BPF_JMP_JEQ_K, libc::SYS_clone as u32, 0, 3),
+            statement(BPF_LD_W_ABS, SECCOMP_DATA_ARG0_LOW),
+            jump(BPF_JMP_JSET_K, libc::CLONE_NEWUSER as u32, 0, 1),
+            statement(BPF_RET_K, deny),
+        ]);
+        filter.push(statement(BPF_RET_K, SECCOMP_RET_ALLOW));
+        filter
+    }
+
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    fn seccomp_filter() -> Vec<libc::sock_filter> {
+        Vec::new()
+    }
+
+    #[cfg(test)]
+    #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+    mod tests {
+        use super::*;
+
+        const ALLOW: u32 = SECCOMP_RET_ALLOW;
+        const EPERM: u32 = SECCOMP_RET_ERRNO | libc::EPERM as u32;
+        const ENOSYS: u32 = SECCOMP_RET_ERRNO | libc::ENOSYS as u32;
+
+        /// Runs the filter on a system call, like the kernel does.
+        fn run(filter: &[libc::sock_filter], arch: u32, nr: libc::c_long, arg0: u64) -> u32 {
+            let mut accumulator = 0;
+            let mut pc = 0;
+            loop {
+                let instruction = filter[pc];
+                pc += 1;
+                let condition = match instruction.code {
+                    BPF_LD_W_ABS => {
+                        accumulator = match instruction.k {
+                            SECCOMP_DATA_NR => nr as u32,
+                            SECCOMP_DATA_ARCH => arch,
+                            SECCOMP_DATA_ARG0_LOW => arg0 as u32,
+                            offset => panic!("unexpected load offset {offset}"),
+                        };
+                        continue;
+                    }
+                    BPF_RET_K => return instruction.k,
+                    BPF_JMP_JEQ_K => accumulator == instruction.k,
+                    #[cfg(target_arch = "x86_64")]
+                    BPF_JMP_JGE_K => accumulator >= instruction.k,
+                    BPF_JMP_JSET_K => accumulator & instruction.k != 0,
+                    code => panic!("unexpected instruction {code:#x}"),
+                };
+                pc += usize::from(if condition {
+                    instruction.jt
+                } else {
+                    instruction.jf
+                });
+            }
+        }
+
+        #[test]
+        fn filter_layout() {
+            let filter = seccomp_filter();
+
+            // the kernel rejects longer filters
+            assert!(filter.len() <= libc::BPF_MAXINSNS as usize);
+            assert_eq!(filter[0].code, BPF_LD_W_ABS);
+            assert_eq!(filter[0].k, SECCOMP_DATA_ARCH);
+            let last = filter.last().unwrap();
+            assert_eq!((last.code, last.k), (BPF_RET_K, SECCOMP_RET_ALLOW));
+            for (pc, instruction) in filter.iter().enumerate() {
+                if instruction.code != BPF_LD_W_ABS && instruction.code != BPF_RET_K {
+                    let target = pc + 1 + usize::from(instruction.jt.max(instruction.jf));
+                    assert!(target < filter.len(), "jump out of filter at {pc}");
+                }
+            }
+        }
+
+        #[test]
+        fn denied_syscalls_fail_with_eperm() {
+            let filter = seccomp_filter();
+
+            for &syscall in DENIED_SYSCALLS {
+                assert_eq!(run(&filter, AUDIT_ARCH, syscall, 0), EPERM, "{syscall}");
+            }
+            for syscall in [libc::SYS_read, libc::SYS_write, libc::SYS_openat] {
+                assert_eq!(run(&filter, AUDIT_ARCH, syscall, 0), ALLOW, "{syscall}");
+            }
+            assert_eq!(
+                run(&filter, 0x4000_0003, libc::SYS_read, 0),
+                SECCOMP_RET_KILL_PROCESS
+            );
+            #[cfg(target_arch = "x86_64")]
+            assert_eq!(run(&filter, AUDIT_ARCH, 0x4000_0000, 0), EPERM);
+        }
+
+        #[test]
+        fn user_namespaces_cannot_be_cloned() {
+            let filter = seccomp_filter();
+            let thread_flags = (libc::CLONE_VM | libc::CLONE_THREAD | libc::CLONE_SIGHAND) as u64;
+            let new_user = (libc::CLONE_NEWUSER | libc::SIGCHLD) as u64;
+
+            assert_eq!(
+                run(&filter, AUDIT_ARCH, libc::SYS_clone, thread_flags),
+                ALLOW
+            );
+            assert_eq!(
+                run(&filter, AUDIT_ARCH, libc::SYS_clone, libc::SIGCHLD as u64),
+                ALLOW
+            );
+            assert_eq!(run(&filter, AUDIT_ARCH, libc::SYS_clone, new_user), EPERM);
+            assert_eq!(run(&filter, AUDIT_ARCH, SYS_CLONE3, 0), ENOSYS);
+        }
+
+        /// Needs unprivileged user namespaces, which are often disabled in
+        /// containers.
+        #[tokio::test]
+        #[ignore]
+        async fn sandboxed_process_cannot_create_user_namespace() {
+            let config = SandboxConfig {
+                writable: Vec::new(),
+                network: true,
+            };
+            let sandbox = Sandbox::new(&config, &std::env::temp_dir(), None).unwrap();
+            let mut command = tokio::process::Command::new("true");
+            // SAFETY: only system calls are performed between `fork` and `exec`
+            unsafe {
+                command.pre_exec(move || {
+                    sandbox.enter()?;
+                    let flags = (libc::CLONE_NEWUSER | libc::SIGCHLD) as libc::c_ulong;
+                    match libc::syscall(libc::SYS_clone, flags, 0, 0, 0, 0) {
+                        0 => libc::_exit(0),
+                        -1 if io::Error::last_os_error().raw_os_error() == Some(libc::EPERM) => {}
+                        _ => return Err(io::Error::other("created user namespace")),
+                    }
+                    match libc::syscall(SYS_CLONE3, std::ptr::null::<u8>(), 0) {
+                        -1 if io::Error::last_os_error().raw_os_error() == Some(libc::ENOSYS) => {}
+                        _ => return Err(io::Error::other("`clone3` was not denied")),
+                    }
+                    Ok(())
+                });
+            }
+
+            let status = command.status().await.unwrap();
+            assert!(status.success());
+        }
+    }
+}
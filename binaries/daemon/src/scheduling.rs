@@ -0,0 +1,179 @@
+//! Real-time scheduling and CPU affinity of node processes.
+
+use dora_core::descriptor::SchedulingConfig;
+
+use crate::user::NodeUser;
+
+/// Sets up the command so that the spawned process runs with the given
+/// scheduling policy and CPU affinity.
+///
+/// Checks whether the daemon is permitted to use the policy first, so that
+/// missing privileges are reported with a clear error instead of a failed
+/// spawn.
+#[cfg(target_os = "linux")]
+pub fn apply(
+    command: &mut tokio::process::Command,
+    config: &SchedulingConfig,
+    user: Option<&NodeUser>,
+) -> eyre::Result<()> {
+    let scheduling = linux::Scheduling::new(config, user)?;
+    // SAFETY: the closure only performs system calls on memory that was
+    // prepared before forking, which is async-signal-safe
+    unsafe {
+        command.pre_exec(move || scheduling.enter());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn apply(
+    _command: &mut tokio::process::Command,
+    config: &SchedulingConfig,
+    _user: Option<&NodeUser>,
+) -> eyre::Result<()> {
+    eyre::bail!(
+        "setting the {} scheduling policy is only supported on Linux",
+        config.policy
+    )
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::io;
+
+    use dora_core::descriptor::{SchedulingConfig, SchedulingPolicy};
+    use eyre::{bail, Context};
+
+    use crate::user::NodeUser;
+
+    const CAP_SYS_NICE: u32 = 23;
+
+    pub struct Scheduling {
+        policy: libc::c_int,
+        priority: libc::c_int,
+        cpus: Option<libc::cpu_set_t>,
+    }
+
+    impl Scheduling {
+        pub fn new(config: &SchedulingConfig, user: Option<&NodeUser>) -> eyre::Result<Self> {
+            let policy = match config.policy {
+                SchedulingPolicy::Other => libc::SCHED_OTHER,
+                SchedulingPolicy::Fifo => libc::SCHED_FIFO,
+                SchedulingPolicy::RoundRobin => libc::SCHED_RR,
+            };
+            let priority = config.priority.unwrap_or(0).into();
+            if config.policy.is_real_time() {
+                check_permitted(config.policy, priority, user)?;
+            }
+
+            let cpus = if config.cpus.is_empty() {
+                None
+            } else {
+                // SAFETY: `cpu_set_t` is a plain bit set, for which zero is valid
+                let mut cpus: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+                let available = std::thread::available_parallelism().map_or(1, |n| n.get());
+                for &cpu in &config.cpus {
+                    if cpu >= libc::CPU_SETSIZE as usize {
+                        bail!("CPU {cpu} is not supported");
+                    }
+                    if cpu >= available {
+                        tracing::warn!(
+                            "CPU {cpu} might not be available, the daemon can use {available} CPUs"
+                        );
+                    }
+                    // SAFETY: `cpu` was checked to be within the set
+                    unsafe { libc::CPU_SET(cpu, &mut cpus) };
+                }
+                Some(cpus)
+            };
+
+            Ok(Self {
+                policy,
+                priority,
+                cpus,
+            })
+        }
+
+        /// Applies the scheduling to the current process.
+        pub fn enter(&self) -> io::Result<()> {
+            if let Some(cpus) = &self.cpus {
+                // SAFETY: the CPU set is initialized and of the given size
+                let result = unsafe {
+                    libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), cpus)
+                };
+                if result != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            let param = libc::sched_param {
+                sched_priority: self.priority,
+            };
+            // SAFETY: `param` is a valid `sched_param`
+            if unsafe { libc::sched_setscheduler(0, self.policy, &param) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+    }
+
+    /// Checks that the node process will be permitted to use the real-time
+    /// policy with the given priority.
+    ///
+    /// Nodes that run as another user lose the capabilities of the daemon,
+    /// so the daemon raises its `rtprio` limit for them, which is inherited.
+    fn check_permitted(
+        policy: SchedulingPolicy,
+        priority: libc::c_int,
+        user: Option<&NodeUser>,
+    ) -> eyre::Result<()> {
+        // SAFETY: `geteuid` has no preconditions
+        let is_root = unsafe { libc::geteuid() } == 0;
+        let mut limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        // SAFETY: `limit` is a valid `rlimit`
+        if unsafe { libc::getrlimit(libc::RLIMIT_RTPRIO, &mut limit) } != 0 {
+            return Err(io::Error::last_os_error()).wrap_err("failed to read `rtprio` limit");
+        }
+        if limit.rlim_cur >= priority as libc::rlim_t {
+            return Ok(());
+        }
+
+        if is_root && user.is_some() {
+            let raised = libc::rlimit {
+                rlim_cur: limit.rlim_cur.max(priority as libc::rlim_t),
+                rlim_max: limit.rlim_max.max(priority as libc::rlim_t),
+            };
+            // SAFETY: `raised` is a valid `rlimit`
+            if unsafe { libc::setrlimit(libc::RLIMIT_RTPRIO, &raised) } != 0 {
+                return Err(io::Error::last_os_error())
+                    .wrap_err("failed to raise `rtprio` limit for the node user");
+            }
+            return Ok(());
+        }
+        if is_root || has_sys_nice()? {
+            return Ok(());
+        }
+
+        bail!(
+            "the daemon is not permitted to use {policy} with priority {priority} \
+            (its `rtprio` limit is {}); run the daemon as root, grant it `CAP_SYS_NICE` \
+            (e.g. `AmbientCapabilities=CAP_SYS_NICE` in its systemd unit), or raise \
+            its `rtprio` limit (e.g. in `/etc/security/limits.conf`)",
+            limit.rlim_cur
+        )
+    }
+
+    /// Whether `CAP_SYS_NICE` is in the effective capabilities of the daemon.
+    fn has_sys_nice() -> eyre::Result<bool> {
+        let status = std::fs::read_to_string("/proc/self/status")
+            .wrap_err("failed to read `/proc/self/status`")?;
+        let effective = status
+            .lines()
+            .find_map(|line| line.strip_prefix("CapEff:"))
+            .and_then(|caps| u64::from_str_radix(caps.trim(), 16).ok())
+            .unwrap_or(0);
+        Ok(effective & (1 << CAP_SYS_NICE) != 0)
+    }
+}
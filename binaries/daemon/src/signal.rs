@@ -0,0 +1,99 @@
+//! Delivery of signals to node processes.
+//!
+//! Nodes are spawned as leaders of their own process group, so that they
+//! don't receive the ctrl-c signals of the terminal that the daemon runs in.
+//! Signals are thus sent to the whole process group of a node, which includes
+//! the child processes that the node spawned.
+
+use std::sync::Arc;
+
+use dora_core::{descriptor::StopSignal, uhlc::HLC};
+use dora_message::node_to_daemon::Timestamped;
+use tokio::sync::mpsc;
+
+use crate::Event;
+
+/// Sends the signal to the process group of the node with the given PID.
+#[cfg(unix)]
+pub fn send_to_node(pid: u32, signal: StopSignal) -> eyre::Result<()> {
+    let number = match signal {
+        StopSignal::Interrupt => libc::SIGINT,
+        StopSignal::Terminate => libc::SIGTERM,
+        StopSignal::Hangup => libc::SIGHUP,
+        StopSignal::Quit => libc::SIGQUIT,
+        StopSignal::User1 => libc::SIGUSR1,
+        StopSignal::User2 => libc::SIGUSR2,
+    };
+    // SAFETY: `killpg` has no memory safety preconditions
+    if unsafe { libc::killpg(pid as libc::pid_t, number) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+/// Sends a `CTRL_BREAK` event to the process group of the node with the
+/// given PID.
+///
+/// Windows has no signals and `CTRL_C` events can't be sent to other process
+/// groups, so all signals are delivered as `CTRL_BREAK`.
+#[cfg(windows)]
+pub fn send_to_node(pid: u32, _signal: StopSignal) -> eyre::Result<()> {
+    use windows_sys::Win32::System::Console::{GenerateConsoleCtrlEvent, CTRL_BREAK_EVENT};
+
+    // SAFETY: `GenerateConsoleCtrlEvent` has no memory safety preconditions
+    if unsafe { GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, pid) } == 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn send_to_node(_pid: u32, signal: StopSignal) -> eyre::Result<()> {
+    eyre::bail!("sending {signal} is not supported on this platform")
+}
+
+/// Reports the `SIGHUP`, `SIGUSR1`, and `SIGUSR2` signals that the daemon
+/// receives as [`Event::ForwardSignal`], so that they can be forwarded to the
+/// nodes that list them in their `forward_signals`.
+///
+/// `SIGINT` is handled by the ctrl-c handler instead, which stops the
+/// dataflows.
+#[cfg(unix)]
+pub fn forward_signals(
+    events_tx: mpsc::Sender<Timestamped<Event>>,
+    clock: Arc<HLC>,
+) -> eyre::Result<()> {
+    use eyre::Context;
+    use tokio::signal::unix::{signal, SignalKind};
+
+    for (kind, forwarded) in [
+        (SignalKind::hangup(), StopSignal::Hangup),
+        (SignalKind::user_defined1(), StopSignal::User1),
+        (SignalKind::user_defined2(), StopSignal::User2),
+    ] {
+        let mut signals =
+            signal(kind).wrap_err_with(|| format!("failed to set up {forwarded} handler"))?;
+        let events_tx = events_tx.clone();
+        let clock = clock.clone();
+        tokio::spawn(async move {
+            while signals.recv().await.is_some() {
+                let event = Timestamped {
+                    inner: Event::ForwardSignal(forwarded),
+                    timestamp: clock.new_timestamp(),
+                };
+                if events_tx.send(event).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn forward_signals(
+    _events_tx: mpsc::Sender<Timestamped<Event>>,
+    _clock: Arc<HLC>,
+) -> eyre::Result<()> {
+    Ok(())
+}
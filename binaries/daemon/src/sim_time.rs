@@ -0,0 +1,182 @@
+//! Simulated time for dataflows that set `time: { mode: simulated }`.
+//!
+//! The simulated clock replaces the wall clock for the timers of the dataflow,
+//! for replayed recordings, and for the timestamps of all messages that the
+//! daemon forwards. It is either advanced by the daemon at a configurable rate
+//! relative to real time, or by a simulator node that sends the current
+//! simulation time in the [`SIMULATION_TIME_PARAMETER`] of its messages.
+//!
+//! The simulated time starts at the wall clock time at which the dataflow is
+//! started, so that the timestamps remain comparable to log messages.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use dora_core::{
+    descriptor::{TimeConfig, SIMULATION_TIME_PARAMETER},
+    uhlc::{self, HLC, NTP64},
+};
+use dora_message::metadata::{Metadata, Parameter};
+use futures::FutureExt;
+use tokio::sync::{oneshot, Notify};
+
+use crate::OutputId;
+
+pub struct SimulatedClock {
+    epoch: Duration,
+    id: uhlc::ID,
+    state: Mutex<ClockState>,
+    deadline_added: Notify,
+    /// Speed of the simulated time relative to real time, if the clock is
+    /// advanced by the daemon.
+    rate: f64,
+    /// The output whose messages advance the clock, if the clock is not
+    /// advanced by the daemon.
+    advanced_by: Option<OutputId>,
+}
+
+#[derive(Default)]
+struct ClockState {
+    /// Simulated time since the start of the dataflow.
+    now: Duration,
+    /// Waiting tasks, ordered by their deadline.
+    waiters: BTreeMap<(Duration, u64), oneshot::Sender<()>>,
+    next_waiter_id: u64,
+}
+
+impl SimulatedClock {
+    pub fn new(config: &TimeConfig) -> eyre::Result<Arc<Self>> {
+        let advanced_by = match &config.advanced_by {
+            Some(output) => {
+                let Some((node, output)) = output.split_once('/') else {
+                    eyre::bail!("`advanced_by` must have the form `<node_id>/<output_id>`");
+                };
+                Some(OutputId(node.to_owned().into(), output.to_owned().into()))
+            }
+            None => None,
+        };
+        Ok(Arc::new(Self {
+            epoch: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default(),
+            id: *HLC::default().get_id(),
+            state: Default::default(),
+            deadline_added: Notify::new(),
+            rate: config.rate(),
+            advanced_by,
+        }))
+    }
+
+    /// Simulated time since the start of the dataflow.
+    pub fn now(&self) -> Duration {
+        self.state.lock().unwrap().now
+    }
+
+    /// Timestamp for the given simulated time.
+    pub fn timestamp_at(&self, time: Duration) -> uhlc::Timestamp {
+        uhlc::Timestamp::new(NTP64::from(self.epoch + time), self.id)
+    }
+
+    pub fn new_timestamp(&self) -> uhlc::Timestamp {
+        self.timestamp_at(self.now())
+    }
+
+    /// Replaces the timestamp of the given metadata by the current simulated
+    /// time.
+    pub fn restamp(&self, metadata: Metadata) -> Metadata {
+        Metadata::from_parameters(
+            self.new_timestamp(),
+            metadata.type_info,
+            metadata.parameters,
+        )
+    }
+
+    /// Waits until the simulated clock reaches the given time.
+    pub async fn sleep_until(&self, deadline: Duration) {
+        let receiver = {
+            let mut state = self.state.lock().unwrap();
+            if state.now >= deadline {
+                return;
+            }
+            let (sender, receiver) = oneshot::channel();
+            let id = state.next_waiter_id;
+            state.next_waiter_id += 1;
+            state.waiters.insert((deadline, id), sender);
+            receiver
+        };
+        self.deadline_added.notify_one();
+        let _ = receiver.await;
+    }
+
+    /// Advances the clock to the given time and wakes all tasks whose
+    /// deadline is reached.
+    ///
+    /// The clock never goes backwards.
+    pub fn advance_to(&self, time: Duration) {
+        let mut state = self.state.lock().unwrap();
+        if time <= state.now {
+            return;
+        }
+        state.now = time;
+        let later = state.waiters.split_off(&(time, u64::MAX));
+        for (_, waiter) in std::mem::replace(&mut state.waiters, later) {
+            let _ = waiter.send(());
+        }
+    }
+
+    /// Advances the clock if the given output is the configured clock source.
+    pub fn handle_output(&self, output_id: &OutputId, metadata: &Metadata) {
+        if self.advanced_by.as_ref() != Some(output_id) {
+            return;
+        }
+        match metadata.parameters.get(SIMULATION_TIME_PARAMETER) {
+            Some(Parameter::Integer(nanos)) if *nanos >= 0 => {
+                self.advance_to(Duration::from_nanos(*nanos as u64))
+            }
+            _ => tracing::warn!(
+                "message of `{}/{}` has no valid `{SIMULATION_TIME_PARAMETER}` parameter",
+                output_id.0,
+                output_id.1
+            ),
+        }
+    }
+
+    /// Advances the clock at the configured rate relative to real time until
+    /// the returned handle is dropped.
+    ///
+    /// Returns `None` if the clock is advanced by a simulator node instead.
+    pub fn drive(self: &Arc<Self>) -> Option<futures::future::RemoteHandle<()>> {
+        if self.advanced_by.is_some() {
+            return None;
+        }
+        let clock = self.clone();
+        let rate = self.rate;
+        let task = async move {
+            let real_start = tokio::time::Instant::now();
+            let simulated_start = clock.now();
+            loop {
+                let next_deadline = {
+                    let state = clock.state.lock().unwrap();
+                    state.waiters.keys().next().map(|(deadline, _)| *deadline)
+                };
+                let Some(deadline) = next_deadline else {
+                    clock.deadline_added.notified().await;
+                    continue;
+                };
+                let real_deadline =
+                    real_start + (deadline.saturating_sub(simulated_start)).div_f64(rate);
+                tokio::select! {
+                    _ = tokio::time::sleep_until(real_deadline) => clock.advance_to(deadline),
+                    // an earlier deadline might have been added
+                    _ = clock.deadline_added.notified() => {}
+                }
+            }
+        };
+        let (task, handle) = task.remote_handle();
+        tokio::spawn(task);
+        Some(handle)
+    }
+}
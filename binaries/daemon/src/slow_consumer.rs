@@ -0,0 +1,208 @@
+//! Detection of nodes that persistently lag behind their inputs.
+//!
+//! The thresholds of the [`QueueMonitor`](crate::queue_metrics::QueueMonitor)
+//! only trigger once the memory usage is already high. A slow consumer can be
+//! detected much earlier from the trend of its input queue: if the queue grows
+//! at every check, the node processes its inputs slower than they arrive. The
+//! same holds for nodes that keep shared memory inputs for a long time without
+//! releasing their drop tokens. For both cases, the warning names the input
+//! with the highest message rate and its producer, which is usually the
+//! culprit.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use dora_core::config::{DataId, NodeId};
+
+use crate::OutputId;
+
+/// Number of consecutive checks in which the input queue of a node must grow
+/// before the node is reported as slow consumer.
+const GROWING_CHECKS: usize = 3;
+/// Queue length below which a node is not reported, even if its queue grows.
+const MIN_QUEUED_INPUTS: u64 = 100;
+/// Time after which an unreleased drop token is reported.
+const DROP_TOKEN_AGE_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// Input state of a local node at the time of a check.
+pub struct ConsumerState {
+    pub queued_inputs: u64,
+    /// The edges to the node, with their current message rates.
+    pub inputs: Vec<IncomingEdge>,
+    /// Age and owner of the oldest drop token that the node didn't release
+    /// yet.
+    pub oldest_drop_token: Option<(Duration, NodeId)>,
+}
+
+pub struct IncomingEdge {
+    pub input_id: DataId,
+    pub producer: OutputId,
+    /// Messages per second.
+    pub rate: f64,
+}
+
+pub struct SlowConsumerWarning {
+    pub node_id: NodeId,
+    pub message: String,
+    pub fields: BTreeMap<String, String>,
+}
+
+#[derive(Default)]
+struct ConsumerHistory {
+    queue_samples: VecDeque<(Instant, u64)>,
+    warned_queue: bool,
+    warned_drop_token: bool,
+}
+
+/// Keeps the recent queue lengths of each node to detect a persistent lag.
+///
+/// Each lag is reported once; it is reported again after it was resolved in
+/// between.
+#[derive(Default)]
+pub struct SlowConsumerDetector {
+    nodes: BTreeMap<NodeId, ConsumerHistory>,
+}
+
+impl SlowConsumerDetector {
+    pub fn check(
+        &mut self,
+        consumers: &BTreeMap<NodeId, ConsumerState>,
+        now: Instant,
+    ) -> Vec<SlowConsumerWarning> {
+        let mut warnings = Vec::new();
+
+        self.nodes
+            .retain(|node_id, _| consumers.contains_key(node_id));
+        for (node_id, state) in consumers {
+            let history = self.nodes.entry(node_id.clone()).or_default();
+
+            history.queue_samples.push_back((now, state.queued_inputs));
+            if history.queue_samples.len() > GROWING_CHECKS + 1 {
+                history.queue_samples.pop_front();
+            }
+            if state.queued_inputs < MIN_QUEUED_INPUTS {
+                history.warned_queue = false;
+            } else if !history.warned_queue {
+                if let Some(warning) = queue_warning(node_id, state, &history.queue_samples) {
+                    history.warned_queue = true;
+                    warnings.push(warning);
+                }
+            }
+
+            match &state.oldest_drop_token {
+                Some((age, owner)) if *age >= DROP_TOKEN_AGE_THRESHOLD => {
+                    if !history.warned_drop_token {
+                        history.warned_drop_token = true;
+                        warnings.push(drop_token_warning(node_id, state, *age, owner));
+                    }
+                }
+                _ => history.warned_drop_token = false,
+            }
+        }
+
+        warnings
+    }
+}
+
+fn queue_warning(
+    node_id: &NodeId,
+    state: &ConsumerState,
+    samples: &VecDeque<(Instant, u64)>,
+) -> Option<SlowConsumerWarning> {
+    if samples.len() <= GROWING_CHECKS {
+        return None;
+    }
+    let growing = samples
+        .iter()
+        .zip(samples.iter().skip(1))
+        .all(|((_, before), (_, after))| after > before);
+    if !growing {
+        return None;
+    }
+
+    let (first_time, first_len) = samples.front()?;
+    let (last_time, last_len) = samples.back()?;
+    let elapsed = last_time.duration_since(*first_time);
+    let growth_rate = (last_len - first_len) as f64 / elapsed.as_secs_f64().max(1e-3);
+    let incoming_rate: f64 = state.inputs.iter().map(|edge| edge.rate).sum();
+    let processed_rate = (incoming_rate - growth_rate).max(0.0);
+
+    let mut message = format!(
+        "node `{node_id}` doesn't keep up with its inputs: its input queue grew from \
+        {first_len} to {last_len} within {}s, it receives {incoming_rate:.1} msg/s \
+        but processes only about {processed_rate:.1} msg/s",
+        elapsed.as_secs()
+    );
+    let mut fields = BTreeMap::from([
+        ("slow_consumer".to_owned(), node_id.to_string()),
+        ("queued_inputs".to_owned(), last_len.to_string()),
+        ("processed_rate".to_owned(), format!("{processed_rate:.1}")),
+    ]);
+    if let Some(edge) = busiest_input(state, None) {
+        message.push_str(&format!("; {}", describe_edge(edge)));
+        add_edge_fields(&mut fields, edge);
+    }
+
+    Some(SlowConsumerWarning {
+        node_id: node_id.clone(),
+        message,
+        fields,
+    })
+}
+
+fn drop_token_warning(
+    node_id: &NodeId,
+    state: &ConsumerState,
+    age: Duration,
+    owner: &NodeId,
+) -> SlowConsumerWarning {
+    let mut message = format!(
+        "node `{node_id}` didn't release a shared memory input from node `{owner}` \
+        for {}s, the sender can't reuse the memory and allocates new regions",
+        age.as_secs()
+    );
+    let mut fields = BTreeMap::from([
+        ("slow_consumer".to_owned(), node_id.to_string()),
+        ("drop_token_age_secs".to_owned(), age.as_secs().to_string()),
+    ]);
+    if let Some(edge) = busiest_input(state, Some(owner)) {
+        message.push_str(&format!("; {}", describe_edge(edge)));
+        add_edge_fields(&mut fields, edge);
+    }
+
+    SlowConsumerWarning {
+        node_id: node_id.clone(),
+        message,
+        fields,
+    }
+}
+
+/// Returns the input with the highest message rate, optionally only
+/// considering the inputs from the given producer node.
+fn busiest_input<'a>(
+    state: &'a ConsumerState,
+    producer: Option<&NodeId>,
+) -> Option<&'a IncomingEdge> {
+    state
+        .inputs
+        .iter()
+        .filter(|edge| producer.map_or(true, |p| &edge.producer.0 == p))
+        .max_by(|a, b| a.rate.total_cmp(&b.rate))
+}
+
+fn describe_edge(edge: &IncomingEdge) -> String {
+    let OutputId(producer, output) = &edge.producer;
+    format!(
+        "input `{}` is fed by `{producer}/{output}` at {:.1} msg/s",
+        edge.input_id, edge.rate
+    )
+}
+
+fn add_edge_fields(fields: &mut BTreeMap<String, String>, edge: &IncomingEdge) {
+    let OutputId(producer, output) = &edge.producer;
+    fields.insert("input".to_owned(), edge.input_id.to_string());
+    fields.insert("producer".to_owned(), format!("{producer}/{output}"));
+    fields.insert("producer_rate".to_owned(), format!("{:.1}", edge.rate));
+}
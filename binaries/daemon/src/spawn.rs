@@ -1,6 +1,11 @@
 use crate::{
-    log, node_communication::spawn_listener_loop, node_inputs, CoreNodeKindExt, DoraEvent, Event,
-    OutputId, RunningNode,
+    activate, artifacts,
+    cgroup::NodeCgroup,
+    container, crash_report, log,
+    node_communication::{spawn_listener_loop, InputPolicies},
+    node_inputs, numa, resource_limits, sandbox, scheduling,
+    user::NodeUser,
+    CoreNodeKindExt, DoraEvent, Event, Footprint, OutputId, RunningNode,
 };
 use aligned_vec::{AVec, ConstAlign};
 use crossbeam::queue::ArrayQueue;
@@ -8,16 +13,17 @@ use dora_arrow_convert::IntoArrow;
 use dora_core::{
     config::DataId,
     descriptor::{
-        resolve_path, source_is_url, Descriptor, OperatorDefinition, OperatorSource, PythonSource,
-        ResolvedNode, ResolvedNodeExt, DYNAMIC_SOURCE, SHELL_SOURCE,
+        resolve_path, source_is_url, CustomNodeExt, Descriptor, OperatorDefinition, OperatorSource,
+        PythonSource, ResolvedNode, ResolvedNodeExt, DYNAMIC_SOURCE, SHELL_SOURCE,
     },
     get_python_path,
     uhlc::HLC,
 };
 use dora_download::download_file;
 use dora_message::{
+    coordinator_to_daemon::NodeListener,
     daemon_to_coordinator::{DataMessage, NodeExitStatus, Timestamped},
-    daemon_to_node::{NodeConfig, RuntimeConfig},
+    daemon_to_node::{NodeConfig, NodeToken, RuntimeConfig},
     DataflowId,
 };
 use dora_node_api::{
@@ -26,19 +32,29 @@ use dora_node_api::{
     Metadata,
 };
 use eyre::{ContextCompat, WrapErr};
+use sha2::{Digest, Sha256};
 use std::{
+    collections::BTreeSet,
+    fmt::Write as _,
     path::{Path, PathBuf},
     process::Stdio,
     sync::Arc,
+    time::Duration,
 };
 use tokio::{
-    fs::File,
-    io::{AsyncBufReadExt, AsyncWriteExt},
+    io::AsyncBufReadExt,
     sync::{mpsc, oneshot},
 };
 use tracing::error;
 
 /// clock is required for generating timestamps when dropping messages early because queue is full
+///
+/// If `adopt` is set, the node process is still running from before a daemon
+/// restart. Instead of spawning it, only its listener is set up again.
+///
+/// The `unblockable_inputs` are the inputs whose senders the daemon can't
+/// block while the event queue of the node is full.
+#[allow(clippy::too_many_arguments)]
 pub async fn spawn_node(
     dataflow_id: DataflowId,
     working_dir: &Path,
@@ -47,22 +63,45 @@ pub async fn spawn_node(
     dataflow_descriptor: Descriptor,
     clock: Arc<HLC>,
     node_stderr_most_recent: Arc<ArrayQueue<String>>,
+    log_file: log::SharedNodeLogFile,
     uv: bool,
+    adopt: Option<NodeListener>,
+    footprint: Footprint,
+    unblockable_inputs: BTreeSet<DataId>,
 ) -> eyre::Result<RunningNode> {
     let node_id = node.id.clone();
     tracing::debug!("Spawning node `{dataflow_id}/{node_id}`");
 
-    let queue_sizes = node_inputs(&node)
+    let inputs = node_inputs(&node);
+    let input_policies = InputPolicies {
+        queue_policies: inputs
+            .iter()
+            .map(|(k, v)| (k.clone(), v.queue_policy))
+            .collect(),
+        unblockable: unblockable_inputs,
+    };
+    let queue_sizes = inputs
         .into_iter()
         .map(|(k, v)| (k, v.queue_size.unwrap_or(10)))
         .collect();
+    let (token, previous_communication) = match adopt {
+        Some(NodeListener {
+            communication,
+            token,
+        }) => (token, Some(communication)),
+        None => (NodeToken::generate(), None),
+    };
     let daemon_communication = spawn_listener_loop(
         &dataflow_id,
         &node_id,
+        token.clone(),
         &daemon_tx,
         dataflow_descriptor.communication.local,
+        dataflow_descriptor.event_queues.clone(),
+        input_policies,
         queue_sizes,
         clock.clone(),
+        previous_communication.as_ref(),
     )
     .await?;
     let send_stdout_to = node
@@ -74,20 +113,86 @@ pub async fn spawn_node(
         node_id: node_id.clone(),
         run_config: node.kind.run_config(),
         daemon_communication,
+        token,
         dataflow_descriptor,
         dynamic: node.kind.dynamic(),
     };
 
+    if previous_communication.is_some() {
+        // the node process is still running from before the daemon restart and
+        // reconnects to the listener on its own
+        return Ok(RunningNode {
+            pid: None,
+            node_config,
+            stop_signal: None,
+            forward_signals: Vec::new(),
+            grace_duration: None,
+            cgroup: None,
+            _container: None,
+        });
+    }
+
+    let sandbox_config = node.sandbox.clone();
+    let container_config = node.container.clone();
+    let activate = node.activate.clone();
+    let stop_signal = node.stop_signal;
+    let forward_signals = node.forward_signals.clone();
+    let grace_duration = node.grace_duration_ms.map(Duration::from_millis);
+    let resources = node.resources.clone();
+    let numa_node = node.deploy.numa_node;
+    let scheduling_config = node.deploy.scheduling.clone();
+    let user = node
+        .deploy
+        .user
+        .as_deref()
+        .map(NodeUser::lookup)
+        .transpose()?;
+    let cgroup;
+    let container;
     let mut child = match node.kind {
-        dora_core::descriptor::CoreNodeKind::Custom(n) => {
-            let mut command = match n.source.as_str() {
-                DYNAMIC_SOURCE => {
+        dora_core::descriptor::CoreNodeKind::Custom(mut n) => {
+            if let Some(target) = n.select_target(Some(&node.deploy.machine)) {
+                tracing::debug!("using source of target `{target}` for node `{node_id}`");
+            }
+            let mut container_guard = None;
+            let mut command = match (n.source.as_str(), &container_config) {
+                (DYNAMIC_SOURCE, _) => {
                     return Ok(RunningNode {
                         pid: None,
                         node_config,
+                        stop_signal: None,
+                        forward_signals: Vec::new(),
+                        grace_duration: None,
+                        cgroup: None,
+                        _container: None,
                     });
                 }
-                SHELL_SOURCE => {
+                (source, Some(config)) => {
+                    let env_names = ["DORA_NODE_CONFIG", "PYTHONUNBUFFERED"]
+                        .into_iter()
+                        .map(str::to_owned)
+                        .chain(node.log_level.as_ref().map(|_| "RUST_LOG".to_owned()))
+                        .chain(
+                            node.env
+                                .iter()
+                                .chain(&n.envs)
+                                .flat_map(|envs| envs.keys().cloned()),
+                        );
+                    let (command, guard) = container::command(
+                        config,
+                        &node_config,
+                        source,
+                        n.args.as_deref(),
+                        working_dir,
+                        user.as_ref(),
+                        env_names,
+                    )
+                    .wrap_err("failed to set up node container")?;
+                    tracing::info!("spawning node `{node_id}` in container");
+                    container_guard = Some(guard);
+                    command
+                }
+                (SHELL_SOURCE, None) => {
                     if cfg!(target_os = "windows") {
                         let mut cmd = tokio::process::Command::new("cmd");
                         cmd.args(["/C", &n.args.clone().unwrap_or_default()]);
@@ -98,7 +203,7 @@ pub async fn spawn_node(
                         cmd
                     }
                 }
-                source => {
+                (source, None) => {
                     let resolved_path = if source_is_url(source) {
                         // try to download the shared library
                         let target_dir = Path::new("build");
@@ -106,10 +211,23 @@ pub async fn spawn_node(
                             .await
                             .wrap_err("failed to download custom node")?
                     } else {
-                        resolve_path(source, working_dir).wrap_err_with(|| {
-                            format!("failed to resolve node source `{}`", source)
-                        })?
+                        // prefer the file that was distributed through
+                        // `dora start --distribute`, which matches the pinned
+                        // checksum
+                        let cached = n
+                            .sha256
+                            .as_deref()
+                            .and_then(|sha256| artifacts::cached(sha256, source));
+                        match cached {
+                            Some(path) => path,
+                            None => resolve_path(source, working_dir).wrap_err_with(|| {
+                                format!("failed to resolve node source `{}`", source)
+                            })?,
+                        }
                     };
+                    if let Some(sha256) = &n.sha256 {
+                        verify_checksum(&resolved_path, sha256).await?;
+                    }
 
                     // If extension is .py, use python to run the script
                     let mut cmd = match resolved_path.extension().map(|ext| ext.to_str()) {
@@ -140,6 +258,14 @@ pub async fn spawn_node(
                             cmd.arg(&resolved_path);
                             cmd
                         }
+                        // WebAssembly modules are run in the embedded WASM runtime of dora
+                        Some(Some("wasm")) => {
+                            tracing::info!("spawning WASM node: {}", resolved_path.display());
+                            let mut cmd = tokio::process::Command::new(dora_executable(footprint)?);
+                            cmd.arg("wasm-node");
+                            cmd.arg(&resolved_path);
+                            cmd
+                        }
                         _ => {
                             tracing::info!("spawning: {}", resolved_path.display());
                             if uv {
@@ -160,7 +286,42 @@ pub async fn spawn_node(
                 }
             };
 
+            if let Some(activate) = &activate {
+                tracing::info!("activating environment of node `{node_id}` with `{activate}`");
+                command = activate::wrap(&command, activate);
+            }
             command.current_dir(working_dir);
+            // the processes of container nodes are started by the container
+            // engine, which runs them as the node user already
+            container = container_guard;
+            cgroup = match &container {
+                Some(_) => None,
+                None => NodeCgroup::create(dataflow_id, &node_id),
+            };
+            if let Some(cgroup) = &cgroup {
+                cgroup
+                    .apply(&mut command)
+                    .wrap_err("failed to place node into its cgroup")?;
+            }
+            if let Some(limits) = &resources {
+                resource_limits::apply_before_spawn(limits, cgroup.as_ref())
+                    .wrap_err("failed to set up resource limits")?;
+            }
+            if let (Some(user), None) = (&user, &container) {
+                user.apply(&mut command);
+            }
+            if let Some(numa_node) = numa_node {
+                numa::apply(&mut command, numa_node)
+                    .wrap_err_with(|| format!("failed to pin node to NUMA node {numa_node}"))?;
+            }
+            if let Some(config) = &scheduling_config {
+                scheduling::apply(&mut command, config, user.as_ref())
+                    .wrap_err("failed to set up node scheduling")?;
+            }
+            if let Some(config) = &sandbox_config {
+                sandbox::apply(&mut command, config, working_dir, user.as_ref())
+                    .wrap_err("failed to set up node sandbox")?;
+            }
             command.stdin(Stdio::null());
 
             command.env(
@@ -168,6 +329,9 @@ pub async fn spawn_node(
                 serde_yaml::to_string(&node_config.clone())
                     .wrap_err("failed to serialize node config")?,
             );
+            if let Some(log_level) = &node.log_level {
+                command.env("RUST_LOG", log_level);
+            }
             // Injecting the env variable defined in the `yaml` into
             // the node runtime.
             if let Some(envs) = node.env {
@@ -182,9 +346,16 @@ pub async fn spawn_node(
                 }
             }
 
-            // Set the process group to 0 to ensure that the spawned process does not exit immediately on CTRL-C
+            // Use a separate process group to ensure that the spawned process does not exit immediately on CTRL-C
             #[cfg(unix)]
             command.process_group(0);
+            // The process is resumed once it was assigned to its job object,
+            // so that all of its child processes are part of the job too
+            #[cfg(windows)]
+            command.creation_flags(
+                windows_sys::Win32::System::Threading::CREATE_NEW_PROCESS_GROUP
+                    | windows_sys::Win32::System::Threading::CREATE_SUSPENDED,
+            );
 
             command.env("PYTHONUNBUFFERED", "1");
             command
@@ -259,15 +430,43 @@ pub async fn spawn_node(
                     command
                 }
             } else if python_operators.is_empty() && other_operators {
-                let mut cmd = tokio::process::Command::new(
-                    std::env::current_exe().wrap_err("failed to get current executable path")?,
-                );
+                let mut cmd = tokio::process::Command::new(dora_executable(footprint)?);
                 cmd.arg("runtime");
                 cmd
             } else {
                 eyre::bail!("Runtime can not mix Python Operator with other type of operator.");
             };
+            if let Some(activate) = &activate {
+                tracing::info!("activating environment of node `{node_id}` with `{activate}`");
+                command = activate::wrap(&command, activate);
+            }
             command.current_dir(working_dir);
+            cgroup = NodeCgroup::create(dataflow_id, &node_id);
+            container = None;
+            if let Some(cgroup) = &cgroup {
+                cgroup
+                    .apply(&mut command)
+                    .wrap_err("failed to place node into its cgroup")?;
+            }
+            if let Some(limits) = &resources {
+                resource_limits::apply_before_spawn(limits, cgroup.as_ref())
+                    .wrap_err("failed to set up resource limits")?;
+            }
+            if let Some(user) = &user {
+                user.apply(&mut command);
+            }
+            if let Some(numa_node) = numa_node {
+                numa::apply(&mut command, numa_node)
+                    .wrap_err_with(|| format!("failed to pin node to NUMA node {numa_node}"))?;
+            }
+            if let Some(config) = &scheduling_config {
+                scheduling::apply(&mut command, config, user.as_ref())
+                    .wrap_err("failed to set up node scheduling")?;
+            }
+            if let Some(config) = &sandbox_config {
+                sandbox::apply(&mut command, config, working_dir, user.as_ref())
+                    .wrap_err("failed to set up node sandbox")?;
+            }
 
             let runtime_config = RuntimeConfig {
                 node: node_config.clone(),
@@ -278,6 +477,9 @@ pub async fn spawn_node(
                 serde_yaml::to_string(&runtime_config)
                     .wrap_err("failed to serialize runtime config")?,
             );
+            if let Some(log_level) = &node.log_level {
+                command.env("RUST_LOG", log_level);
+            }
             // Injecting the env variable defined in the `yaml` into
             // the node runtime.
             if let Some(envs) = node.env {
@@ -285,9 +487,16 @@ pub async fn spawn_node(
                     command.env(key, value.to_string());
                 }
             }
-            // Set the process group to 0 to ensure that the spawned process does not exit immediately on CTRL-C
+            // Use a separate process group to ensure that the spawned process does not exit immediately on CTRL-C
             #[cfg(unix)]
             command.process_group(0);
+            // The process is resumed once it was assigned to its job object,
+            // so that all of its child processes are part of the job too
+            #[cfg(windows)]
+            command.creation_flags(
+                windows_sys::Win32::System::Threading::CREATE_NEW_PROCESS_GROUP
+                    | windows_sys::Win32::System::Threading::CREATE_SUSPENDED,
+            );
 
             command
                 .stdin(Stdio::null())
@@ -301,24 +510,31 @@ pub async fn spawn_node(
         }
     };
 
-    let pid = crate::ProcessId::new(child.id().context(
+    let process_id = child.id().context(
         "Could not get the pid for the just spawned node and indicate that there is an error",
-    )?);
+    )?;
+    let pid = crate::ProcessId::new(process_id);
     tracing::debug!("Spawned node `{dataflow_id}/{node_id}` with pid {pid:?}");
+    let limit_monitor = resources
+        .as_ref()
+        .map(|limits| resource_limits::apply_after_spawn(limits, cgroup.as_ref(), &pid))
+        .transpose();
+    // the job of the node kills the process if setting up the limits failed
+    #[cfg(windows)]
+    crate::job_object::resume_process(process_id).wrap_err("failed to resume node process")?;
+    let limit_monitor = limit_monitor.wrap_err("failed to set up resource limits")?;
 
-    let dataflow_dir: PathBuf = working_dir.join("out").join(dataflow_id.to_string());
-    if !dataflow_dir.exists() {
-        std::fs::create_dir_all(&dataflow_dir).context("could not create dataflow_dir")?;
-    }
     let (tx, mut rx) = mpsc::channel(10);
-    let mut file = File::create(log::log_path(working_dir, &dataflow_id, &node_id))
-        .await
-        .expect("Failed to create log file");
     let mut child_stdout =
         tokio::io::BufReader::new(child.stdout.take().expect("failed to take stdout"));
     let running_node = RunningNode {
         pid: Some(pid),
         node_config,
+        stop_signal,
+        forward_signals,
+        grace_duration,
+        cgroup,
+        _container: container,
     };
     let stdout_tx = tx.clone();
     let node_id = node.id.clone();
@@ -373,7 +589,9 @@ pub async fn spawn_node(
                     tracing::info!("log_{}: {}", node_id, &lines[..lines.len() - 1]);
                 }
             }
-            let sent = stdout_tx.send(lines.clone()).await;
+            let sent = stdout_tx
+                .send((log::NodeStdio::Stdout, lines.clone()))
+                .await;
             if sent.is_err() {
                 println!("Could not log: {lines}");
             }
@@ -424,7 +642,9 @@ pub async fn spawn_node(
 
             // send the buffered lines
             let lines = std::mem::take(&mut buffer);
-            let sent = stderr_tx.send(lines.clone()).await;
+            let sent = stderr_tx
+                .send((log::NodeStdio::Stderr, lines.clone()))
+                .await;
             if sent.is_err() {
                 println!("Could not log: {lines}");
             }
@@ -433,13 +653,22 @@ pub async fn spawn_node(
 
     let node_id = node.id.clone();
     let (log_finish_tx, log_finish_rx) = oneshot::channel();
+    let node_working_dir = working_dir.to_owned();
     tokio::spawn(async move {
+        // collect the crash report information before the process is reaped
+        let exit_info = match child.id() {
+            Some(pid) => crash_report::wait_for_exit(pid, &node_working_dir).await,
+            None => Default::default(),
+        };
         let exit_status = NodeExitStatus::from(child.wait().await);
+        let exceeded_memory_limit_mb = limit_monitor.and_then(|m| m.exceeded_memory_limit());
         let _ = log_finish_rx.await;
         let event = DoraEvent::SpawnedNodeResult {
             dataflow_id,
             node_id,
             exit_status,
+            exit_info,
+            exceeded_memory_limit_mb,
         }
         .into();
         let event = Timestamped {
@@ -452,7 +681,8 @@ pub async fn spawn_node(
     let node_id = node.id.clone();
     // Log to file stream.
     tokio::spawn(async move {
-        while let Some(message) = rx.recv().await {
+        while let Some((stream, message)) = rx.recv().await {
+            let captured = std::time::SystemTime::now();
             // If log is an output, we're sending the logs to the dataflow
             if let Some(stdout_output_name) = &send_stdout_to {
                 // Convert logs to DataMessage
@@ -483,10 +713,26 @@ pub async fn spawn_node(
                 let _ = daemon_tx_log.send(event).await;
             }
 
+            // forwarded to the coordinator, which streams it to subscribed CLIs
+            let event = DoraEvent::NodeOutput {
+                dataflow_id,
+                node_id: node_id.clone(),
+                stream,
+                text: message.clone(),
+            }
+            .into();
+            let event = Timestamped {
+                inner: event,
+                timestamp: uhlc.new_timestamp(),
+            };
+            let _ = daemon_tx_log.send(event).await;
+
+            // structured log records of the node are written to the same file by the daemon
+            let mut file = log_file.lock().await;
             let _ = file
-                .write_all(message.as_bytes())
+                .write(&log::format_node_output(stream, captured, &message))
                 .await
-                .map_err(|err| error!("Could not log {message} to file due to {err}"));
+                .map_err(|err| error!("Could not log {message} to file due to {err:?}"));
             let formatted = message.lines().fold(String::default(), |mut output, line| {
                 output.push_str("      ");
                 output.push_str(line);
@@ -496,7 +742,7 @@ pub async fn spawn_node(
             tracing::trace!("{dataflow_id}/{} logged:\n{formatted}", node.id.clone());
             // Make sure that all data has been synced to disk.
             let _ = file
-                .sync_all()
+                .sync()
                 .await
                 .map_err(|err| error!("Could not sync logs to file due to {err}"));
         }
@@ -506,3 +752,81 @@ pub async fn spawn_node(
     });
     Ok(running_node)
 }
+
+/// Path of the `dora` executable, which runs operators and WASM nodes.
+///
+/// This is the current executable, unless the daemon runs as the standalone
+/// `dora-daemon` executable, which has a [`Footprint::Minimal`].
+fn dora_executable(footprint: Footprint) -> eyre::Result<PathBuf> {
+    match footprint {
+        Footprint::Default => {
+            std::env::current_exe().wrap_err("failed to get current executable path")
+        }
+        Footprint::Minimal => which::which("dora").wrap_err(
+            "operators and WASM nodes require the `dora` executable, but it was not found in PATH",
+        ),
+    }
+}
+
+/// Fails if the SHA-256 checksum of the file doesn't match the expected one.
+///
+/// The file can still change between this check and the spawn, as the node
+/// is spawned by path.
+pub async fn verify_checksum(path: &Path, expected: &str) -> eyre::Result<()> {
+    let file_path = path.to_owned();
+    let actual = tokio::task::spawn_blocking(move || -> std::io::Result<_> {
+        let mut file = std::fs::File::open(file_path)?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut file, &mut hasher)?;
+        Ok(hasher.finalize())
+    })
+    .await
+    .wrap_err("checksum task panicked")?
+    .wrap_err_with(|| format!("failed to compute checksum of `{}`", path.display()))?;
+    let actual = actual.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    });
+    if !actual.eq_ignore_ascii_case(expected) {
+        eyre::bail!(
+            "checksum mismatch for `{}`: expected sha256 `{expected}`, got `{actual}`",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    /// SHA-256 checksum of `hello dora`.
+    const CHECKSUM: &str = "7648d57caa8c22ced22d92d750984bfba464b38ce511881f290b19c54aaac235";
+
+    fn executable() -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"hello dora").unwrap();
+        file
+    }
+
+    #[tokio::test]
+    async fn matching_checksum_is_accepted() {
+        let file = executable();
+        verify_checksum(file.path(), CHECKSUM).await.unwrap();
+        verify_checksum(file.path(), &CHECKSUM.to_uppercase())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn mismatching_checksum_is_rejected() {
+        let file = executable();
+        let expected = CHECKSUM.replace('7', "0");
+        let err = verify_checksum(file.path(), &expected).await.unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("checksum mismatch"), "{message}");
+        assert!(message.contains(CHECKSUM), "{message}");
+    }
+}
@@ -0,0 +1,101 @@
+//! Health reports for the `dora/status` input.
+//!
+//! The daemon publishes a report on every heartbeat to the local nodes that
+//! subscribed to `dora/status`, so that supervisory nodes can react to
+//! infrastructure problems from within the dataflow. The report is a JSON
+//! string with the state of the local nodes and the connections of the
+//! daemon.
+
+use std::{collections::BTreeMap, sync::atomic::Ordering};
+
+use aligned_vec::{AVec, ConstAlign};
+use dora_arrow_convert::IntoArrow;
+use dora_core::uhlc::HLC;
+use dora_message::daemon_to_coordinator::DataMessage;
+use dora_node_api::{
+    arrow::array::ArrayData,
+    arrow_utils::{copy_array_into_sample, required_data_size},
+    Metadata,
+};
+use serde_json::{json, Value};
+
+use crate::{Daemon, RunningDataflow};
+
+pub fn dataflow_status(daemon: &Daemon, dataflow: &RunningDataflow) -> Value {
+    let results = daemon.dataflow_node_results.get(&dataflow.id);
+    let mut nodes: BTreeMap<_, _> = dataflow
+        .running_nodes
+        .keys()
+        .map(|node_id| {
+            let restartable = dataflow.restartable_nodes.get(node_id);
+            let state = if restartable.is_some_and(|n| n.is_restarting()) {
+                "restarting"
+            } else if dataflow.pending_nodes.is_pending(node_id) {
+                "starting"
+            } else {
+                "running"
+            };
+            let health = dataflow.node_health.get(node_id);
+            let queued_inputs = dataflow
+                .queued_inputs
+                .get(node_id)
+                .map(|q| q.load(Ordering::Relaxed));
+            let input_latency_ms = dataflow
+                .input_latencies
+                .get(node_id)
+                .map(|l| l.load(Ordering::Relaxed) as f64 / 1000.0);
+            let status = json!({
+                "state": state,
+                "health": health.map(|h| h.status.to_string()),
+                "health_message": health.and_then(|h| h.message.as_deref()),
+                "queued_inputs": queued_inputs,
+                "input_latency_ms": input_latency_ms,
+                "restarts": restartable.map(|n| n.restarts()),
+                "dropped_inputs": dataflow.dropped_inputs.get(node_id),
+            });
+            (node_id.to_string(), status)
+        })
+        .collect();
+    for (node_id, result) in results.into_iter().flatten() {
+        let status = match result {
+            Ok(()) => json!({ "state": "finished" }),
+            Err(err) => json!({ "state": "failed", "error": err.to_string() }),
+        };
+        nodes.insert(node_id.to_string(), status);
+    }
+
+    let machines: BTreeMap<_, _> = dataflow
+        .remote_nodes
+        .values()
+        .map(|machine| {
+            let connected = daemon
+                .inter_daemon_connections
+                .get(machine)
+                .is_some_and(|c| c.is_connected());
+            (machine.clone(), json!({ "connected": connected }))
+        })
+        .collect();
+
+    json!({
+        "machine_id": daemon.machine_id,
+        "coordinator": {
+            "connected": daemon.coordinator_connection.is_some(),
+            "last_heartbeat_secs_ago": daemon.last_coordinator_heartbeat.elapsed().as_secs_f64(),
+        },
+        "machines": machines,
+        "nodes": nodes,
+        "in_flight_shared_memory_bytes": dataflow.queue_metrics().in_flight_shared_memory_bytes,
+    })
+}
+
+/// Encodes the report as an Arrow string array.
+pub fn to_message(status: &Value, clock: &HLC) -> (Metadata, DataMessage) {
+    let array: ArrayData = status.to_string().as_str().into_arrow().into();
+    let mut sample: AVec<u8, ConstAlign<128>> =
+        AVec::__from_elem(128, 0, required_data_size(&array));
+    let type_info = copy_array_into_sample(&mut sample, &array);
+    (
+        Metadata::new(clock.new_timestamp(), type_info),
+        DataMessage::Vec(sample),
+    )
+}
@@ -0,0 +1,92 @@
+//! Adaptive rate reduction of timer inputs.
+//!
+//! A timer input with a `throttle` config is only delivered on every n-th
+//! tick, where n is the current reduction factor. The factor is doubled when
+//! the receiving node exceeds one of the thresholds and halved again once the
+//! node stayed below half of all thresholds for [`RELAXED_TICKS`] delivered
+//! ticks. The gap between the two conditions avoids flapping between rates.
+
+use std::time::Duration;
+
+use dora_core::config::TimerThrottle;
+
+/// Default for [`TimerThrottle::max_factor`].
+const DEFAULT_MAX_FACTOR: u32 = 16;
+/// Number of consecutive delivered ticks below half of all thresholds after
+/// which the reduction factor is halved.
+const RELAXED_TICKS: u32 = 10;
+
+pub struct Throttle {
+    config: TimerThrottle,
+    factor: u32,
+    /// Ticks since the last delivered tick.
+    skipped: u32,
+    relaxed_ticks: u32,
+}
+
+pub struct Tick {
+    /// Whether the tick should be delivered to the node.
+    pub deliver: bool,
+    /// The previous reduction factor, if it changed on this tick.
+    pub changed_from: Option<u32>,
+}
+
+impl Throttle {
+    pub fn new(config: TimerThrottle) -> Self {
+        Self {
+            config,
+            factor: 1,
+            skipped: 0,
+            relaxed_ticks: 0,
+        }
+    }
+
+    /// The current reduction factor of the timer rate.
+    pub fn factor(&self) -> u32 {
+        self.factor
+    }
+
+    /// Handles a tick of the timer, given the current input queue of the
+    /// node and the time that its inputs waited in the queue.
+    ///
+    /// The load is only evaluated on ticks that are delivered, so that the
+    /// node gets the chance to catch up between them.
+    pub fn tick(&mut self, queued_inputs: u64, latency: Duration) -> Tick {
+        self.skipped += 1;
+        if self.skipped < self.factor {
+            return Tick {
+                deliver: false,
+                changed_from: None,
+            };
+        }
+        self.skipped = 0;
+
+        let previous = self.factor;
+        let max_factor = self.config.max_factor.unwrap_or(DEFAULT_MAX_FACTOR);
+        let latency_ms = latency.as_millis() as u64;
+        let exceeds = |value: u64, max: Option<u64>| max.is_some_and(|max| value > max);
+        let relaxed = |value: u64, max: Option<u64>| max.map_or(true, |max| value <= max / 2);
+
+        if exceeds(queued_inputs, self.config.max_queued_inputs)
+            || exceeds(latency_ms, self.config.max_latency_ms)
+        {
+            self.relaxed_ticks = 0;
+            self.factor = self.factor.saturating_mul(2).min(max_factor);
+        } else if relaxed(queued_inputs, self.config.max_queued_inputs)
+            && relaxed(latency_ms, self.config.max_latency_ms)
+        {
+            self.relaxed_ticks += 1;
+            if self.relaxed_ticks >= RELAXED_TICKS && self.factor > 1 {
+                self.relaxed_ticks = 0;
+                self.factor /= 2;
+            }
+        } else {
+            self.relaxed_ticks = 0;
+        }
+
+        Tick {
+            deliver: true,
+            changed_from: (self.factor != previous).then_some(previous),
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! Sampling and overhead limits of the message tracing.
+//!
+//! Tracing every message of a high-rate dataflow costs about as much CPU time
+//! as the dataflow itself. The `telemetry` section of the dataflow descriptor
+//! therefore allows to trace only a fraction of the timer ticks and of the
+//! messages of individual outputs, to reduce the number of recorded spans, and
+//! to limit the tracing data that a daemon produces per second.
+// spans are only recorded with the `telemetry` feature
+#![cfg_attr(not(feature = "telemetry"), allow(dead_code))]
+
+use std::{collections::BTreeMap, time::Instant};
+
+use dora_core::{
+    config::{DataId, NodeId},
+    descriptor::{SpanDetail, TelemetryConfig},
+};
+
+use crate::OutputId;
+
+/// Rough size of an exported span, in bytes, used for the byte budget.
+const ESTIMATED_SPAN_SIZE: u64 = 256;
+
+/// Selects a fixed fraction of the events, spread evenly over time.
+///
+/// Sampling is deterministic, so that e.g. a rate of `0.1` traces exactly
+/// every tenth event.
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    rate: f64,
+    credit: f64,
+}
+
+impl Sampler {
+    pub fn new(rate: f64) -> Self {
+        Self {
+            rate: rate.clamp(0.0, 1.0),
+            credit: 0.0,
+        }
+    }
+
+    pub fn sample(&mut self) -> bool {
+        self.credit += self.rate;
+        if self.credit >= 1.0 {
+            self.credit -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Token bucket that limits the tracing data produced per second.
+struct ByteBudget {
+    bytes_per_second: u64,
+    available: f64,
+    last_refill: Instant,
+}
+
+impl ByteBudget {
+    fn new(bytes_per_second: u64) -> Self {
+        Self {
+            bytes_per_second,
+            available: bytes_per_second as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn try_spend(&mut self, bytes: u64) -> bool {
+        let now = Instant::now();
+        let refill =
+            now.duration_since(self.last_refill).as_secs_f64() * self.bytes_per_second as f64;
+        // allow bursts of up to one second worth of data
+        self.available = (self.available + refill).min(self.bytes_per_second as f64);
+        self.last_refill = now;
+
+        if self.available >= bytes as f64 {
+            self.available -= bytes as f64;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// A point at which the daemon records a span for a traced message.
+#[derive(Clone, Copy)]
+pub enum Hop<'a> {
+    /// The message arrives at the daemon or is forwarded to another daemon.
+    Forward,
+    /// The message is delivered to a local node over an edge from the given
+    /// output.
+    Delivery(&'a OutputId),
+}
+
+/// What to do with the trace of a message at a hop.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Admission {
+    /// Record a span for the hop.
+    Record,
+    /// Don't record a span, but keep the trace context of the message.
+    Skip,
+    /// Remove the trace context, so that the message is not traced further.
+    Stop,
+}
+
+/// Sampling state of a running dataflow.
+pub struct TraceSampling {
+    sample_rate: f64,
+    span_detail: SpanDetail,
+    edges: BTreeMap<OutputId, Sampler>,
+    budget: Option<ByteBudget>,
+}
+
+impl TraceSampling {
+    pub fn new(config: &TelemetryConfig) -> Self {
+        let edges = config
+            .edges
+            .iter()
+            .filter_map(|(output, &rate)| {
+                let (node_id, output_id) = output.split_once('/')?;
+                let output_id = OutputId(
+                    NodeId::from(node_id.to_owned()),
+                    DataId::from(output_id.to_owned()),
+                );
+                Some((output_id, Sampler::new(rate)))
+            })
+            .collect();
+        Self {
+            sample_rate: config.sample_rate(),
+            span_detail: config.span_detail,
+            edges,
+            budget: config.max_bytes_per_second.map(ByteBudget::new),
+        }
+    }
+
+    /// Sampler for the traces that are started by timer ticks.
+    pub fn root_sampler(&self) -> Sampler {
+        Sampler::new(self.sample_rate)
+    }
+
+    /// Decides whether a span is recorded for a traced message at the given
+    /// hop. The trace context of the message has the given length.
+    pub fn admit(&mut self, hop: Hop, context_len: usize) -> Admission {
+        if let Hop::Delivery(output_id) = hop {
+            if let Some(sampler) = self.edges.get_mut(output_id) {
+                if !sampler.sample() {
+                    return Admission::Stop;
+                }
+            }
+        }
+        let required_detail = match hop {
+            Hop::Forward => SpanDetail::Hops,
+            Hop::Delivery(_) => SpanDetail::Deliveries,
+        };
+        if self.span_detail < required_detail {
+            return Admission::Skip;
+        }
+        let within_budget = match &mut self.budget {
+            Some(budget) => budget.try_spend(ESTIMATED_SPAN_SIZE + context_len as u64),
+            None => true,
+        };
+        if within_budget {
+            Admission::Record
+        } else {
+            Admission::Stop
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Running node processes as a different Unix user than the daemon.
+
+/// Unix user that a node process runs as.
+#[derive(Debug, Clone)]
+pub struct NodeUser {
+    pub name: String,
+    pub uid: u32,
+    pub gid: u32,
+    pub home: std::path::PathBuf,
+}
+
+impl NodeUser {
+    /// Looks up the user in the user database and checks that the daemon is
+    /// allowed to start processes as this user.
+    #[cfg(target_os = "linux")]
+    pub fn lookup(name: &str) -> eyre::Result<Self> {
+        let user = linux::lookup(name)?;
+        // SAFETY: `geteuid` has no preconditions
+        let euid = unsafe { libc::geteuid() };
+        if euid != 0 && euid != user.uid {
+            eyre::bail!("the daemon must run as root to start nodes as user `{name}`");
+        }
+        Ok(user)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn lookup(name: &str) -> eyre::Result<Self> {
+        eyre::bail!("running nodes as user `{name}` is only supported on Linux")
+    }
+
+    /// Makes the spawned process drop the privileges of the daemon.
+    ///
+    /// The supplementary groups of the daemon are dropped as well.
+    #[cfg(unix)]
+    pub fn apply(&self, command: &mut tokio::process::Command) {
+        command.uid(self.uid);
+        command.gid(self.gid);
+        command.env("USER", &self.name);
+        command.env("LOGNAME", &self.name);
+        command.env("HOME", &self.home);
+    }
+
+    #[cfg(not(unix))]
+    pub fn apply(&self, _command: &mut tokio::process::Command) {}
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::{
+        ffi::{CStr, CString, OsStr},
+        os::unix::ffi::OsStrExt,
+    };
+
+    use eyre::{bail, Context};
+
+    use super::NodeUser;
+
+    pub fn lookup(name: &str) -> eyre::Result<NodeUser> {
+        let c_name = CString::new(name).wrap_err("invalid user name")?;
+        let mut buffer = vec![0 as libc::c_char; 4096];
+        loop {
+            // SAFETY: `passwd` is only read if the lookup succeeded, in which
+            // case it was initialized by `getpwnam_r`
+            let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+            let mut result = std::ptr::null_mut();
+            // SAFETY: all pointers are valid for the given sizes
+            let error = unsafe {
+                libc::getpwnam_r(
+                    c_name.as_ptr(),
+                    &mut passwd,
+                    buffer.as_mut_ptr(),
+                    buffer.len(),
+                    &mut result,
+                )
+            };
+            if error == libc::ERANGE && buffer.len() < 1 << 20 {
+                buffer.resize(buffer.len() * 2, 0);
+                continue;
+            }
+            if error != 0 {
+                return Err(std::io::Error::from_raw_os_error(error))
+                    .wrap_err_with(|| format!("failed to look up user `{name}`"));
+            }
+            if result.is_null() {
+                bail!("user `{name}` does not exist");
+            }
+            // SAFETY: `pw_dir` points to a C string in `buffer`
+            let home = unsafe { CStr::from_ptr(passwd.pw_dir) };
+            return Ok(NodeUser {
+                name: name.to_owned(),
+                uid: passwd.pw_uid,
+                gid: passwd.pw_gid,
+                home: OsStr::from_bytes(home.to_bytes()).into(),
+            });
+        }
+    }
+}
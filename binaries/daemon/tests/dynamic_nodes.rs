@@ -0,0 +1,131 @@
+#![cfg(unix)]
+
+use std::{net::Ipv4Addr, time::Duration};
+
+use dora_core::descriptor::{Descriptor, DescriptorExt};
+use dora_daemon::Daemon;
+use dora_message::id::NodeId;
+use dora_node_api::{DoraNode, NodeToken};
+use tokio::sync::oneshot;
+
+/// Dynamic nodes only disconnect, they have no exit result, so a local
+/// dataflow must finish once its other nodes exited.
+#[tokio::test]
+async fn local_dataflow_does_not_wait_for_dynamic_nodes() -> eyre::Result<()> {
+    let descriptor = Descriptor::parse(
+        br#"
+nodes:
+  - id: static
+    path: /bin/sh
+    args: -c exit
+  - id: dynamic
+    path: dynamic
+"#
+        .to_vec(),
+    )?;
+    let (listen_port, _) = oneshot::channel();
+    let run = Daemon::run_dataflow_in_process(
+        descriptor,
+        std::env::temp_dir(),
+        listen_port,
+        None,
+        std::future::pending(),
+    );
+
+    let result = tokio::time::timeout(Duration::from_secs(30), run)
+        .await
+        .map_err(|_| eyre::eyre!("dataflow did not finish"))??;
+    let nodes: Vec<_> = result.node_results.keys().map(|id| id.as_ref()).collect();
+    assert_eq!(nodes, ["static"]);
+    assert!(result.is_ok(), "{:?}", result.node_results);
+    Ok(())
+}
+
+/// The local listener accepts connections from any local process, so it only
+/// hands out the node config to dynamic nodes that know the daemon's token.
+#[tokio::test(flavor = "multi_thread")]
+async fn dynamic_nodes_must_present_the_daemon_token() -> eyre::Result<()> {
+    let descriptor = Descriptor::parse(
+        br#"
+nodes:
+  - id: dynamic
+    path: dynamic
+"#
+        .to_vec(),
+    )?;
+    let (listen_port_tx, listen_port_rx) = oneshot::channel();
+    let (stop_tx, stop_rx) = oneshot::channel::<()>();
+    let token = NodeToken::generate();
+    let run = tokio::spawn(Daemon::run_dataflow_in_process(
+        descriptor,
+        std::env::temp_dir(),
+        listen_port_tx,
+        Some(token.clone()),
+        async {
+            let _ = stop_rx.await;
+        },
+    ));
+    let address = (Ipv4Addr::LOCALHOST, listen_port_rx.await?).into();
+
+    let init = move |token| {
+        std::thread::spawn(move || {
+            DoraNode::init_dynamic(NodeId::from("dynamic".to_owned()), address, token)
+                .map(|_| ())
+                .map_err(|err| format!("{err:?}"))
+        })
+        .join()
+        .unwrap()
+    };
+    let missing = init(None).unwrap_err();
+    assert!(missing.contains("must present a token"), "{missing}");
+    let invalid = init(Some(NodeToken::generate())).unwrap_err();
+    assert!(invalid.contains("invalid token"), "{invalid}");
+    init(Some(token)).map_err(|err| eyre::eyre!("valid token was rejected: {err}"))?;
+
+    let _ = stop_tx.send(());
+    run.abort();
+    Ok(())
+}
+
+/// Without a token, the daemon doesn't accept dynamic nodes at all.
+#[tokio::test(flavor = "multi_thread")]
+async fn dynamic_nodes_are_disabled_without_token() -> eyre::Result<()> {
+    let descriptor = Descriptor::parse(
+        br#"
+nodes:
+  - id: dynamic
+    path: dynamic
+"#
+        .to_vec(),
+    )?;
+    let (listen_port_tx, listen_port_rx) = oneshot::channel();
+    let (stop_tx, stop_rx) = oneshot::channel::<()>();
+    let run = tokio::spawn(Daemon::run_dataflow_in_process(
+        descriptor,
+        std::env::temp_dir(),
+        listen_port_tx,
+        None,
+        async {
+            let _ = stop_rx.await;
+        },
+    ));
+    let address = (Ipv4Addr::LOCALHOST, listen_port_rx.await?).into();
+
+    let err = std::thread::spawn(move || {
+        DoraNode::init_dynamic(
+            NodeId::from("dynamic".to_owned()),
+            address,
+            Some(NodeToken::generate()),
+        )
+        .map(|_| ())
+        .map_err(|err| format!("{err:?}"))
+    })
+    .join()
+    .unwrap()
+    .unwrap_err();
+    assert!(err.contains("dynamic nodes are disabled"), "{err}");
+
+    let _ = stop_tx.send(());
+    run.abort();
+    Ok(())
+}
@@ -0,0 +1,147 @@
+#![cfg(unix)]
+
+use std::{net::Ipv4Addr, time::Duration};
+
+use dora_core::descriptor::{Descriptor, DescriptorExt};
+use dora_daemon::Daemon;
+use dora_message::id::NodeId;
+use dora_node_api::{
+    arrow::array::{ArrayRef, UInt8Array},
+    DoraNode, Event, MetadataParameters, NodeToken,
+};
+use tokio::sync::oneshot;
+
+/// Receives the given number of inputs and returns their ids, timestamps,
+/// and lengths.
+fn receive_inputs(
+    events: &mut dora_node_api::EventStream,
+    count: usize,
+) -> eyre::Result<Vec<(String, dora_node_api::uhlc::Timestamp, usize)>> {
+    let mut inputs = Vec::new();
+    while inputs.len() < count {
+        match events.recv_timeout(Duration::from_secs(10)) {
+            Some(Event::Input { id, metadata, data }) => {
+                inputs.push((id.to_string(), metadata.timestamp(), data.len()))
+            }
+            Some(Event::Error(err)) => eyre::bail!("did not receive all inputs: {err}"),
+            Some(_) => {}
+            None => eyre::bail!("event stream closed before all inputs arrived"),
+        }
+    }
+    Ok(inputs)
+}
+
+/// Receivers get the outputs of a `send_outputs` batch back-to-back, in order,
+/// and with the same timestamp, even if other outputs are sent in between.
+#[tokio::test(flavor = "multi_thread")]
+async fn send_outputs_delivers_batch_as_unit() -> eyre::Result<()> {
+    let descriptor = Descriptor::parse(
+        br#"
+nodes:
+  - id: sender
+    path: dynamic
+    outputs:
+      - a
+      - b
+      - c
+  - id: receiver
+    path: dynamic
+    inputs:
+      a:
+        source: sender/a
+        queue_size: 10
+      b:
+        source: sender/b
+        queue_size: 10
+      c:
+        source: sender/c
+        queue_size: 10
+  - id: partial
+    path: dynamic
+    inputs:
+      b:
+        source: sender/b
+        queue_size: 10
+"#
+        .to_vec(),
+    )?;
+    let (listen_port_tx, listen_port_rx) = oneshot::channel();
+    let (stop_tx, stop_rx) = oneshot::channel::<()>();
+    let token = NodeToken::generate();
+    let run = tokio::spawn(Daemon::run_dataflow_in_process(
+        descriptor,
+        std::env::temp_dir(),
+        listen_port_tx,
+        Some(token.clone()),
+        async {
+            let _ = stop_rx.await;
+        },
+    ));
+    let address = (Ipv4Addr::LOCALHOST, listen_port_rx.await?).into();
+    let init = move |id: &str| {
+        DoraNode::init_dynamic(NodeId::from(id.to_owned()), address, Some(token.clone()))
+    };
+
+    let sender = std::thread::spawn({
+        let init = init.clone();
+        move || -> eyre::Result<()> {
+            let (mut node, _events) = init("sender")?;
+            for _ in 0..2 {
+                let c: ArrayRef = std::sync::Arc::new(UInt8Array::from(vec![0u8; 3]));
+                node.send_output("c".to_owned().into(), MetadataParameters::default(), c)?;
+                let a: ArrayRef = std::sync::Arc::new(UInt8Array::from(vec![1u8]));
+                let b: ArrayRef = std::sync::Arc::new(UInt8Array::from(vec![2u8, 3]));
+                node.send_outputs([
+                    ("a".to_owned().into(), MetadataParameters::default(), a),
+                    ("b".to_owned().into(), MetadataParameters::default(), b),
+                ])?;
+            }
+            // keep the node connected until the receivers got all inputs
+            std::thread::sleep(Duration::from_secs(1));
+            Ok(())
+        }
+    });
+    let receiver = std::thread::spawn({
+        let init = init.clone();
+        move || {
+            let (_node, mut events) = init("receiver")?;
+            receive_inputs(&mut events, 6)
+        }
+    });
+    let partial = std::thread::spawn(move || {
+        let (_node, mut events) = init("partial")?;
+        receive_inputs(&mut events, 2)
+    });
+
+    sender.join().unwrap()?;
+    let inputs = receiver.join().unwrap()?;
+    let partial = partial.join().unwrap()?;
+    let _ = stop_tx.send(());
+    run.abort();
+
+    let ids: Vec<_> = inputs.iter().map(|(id, _, _)| id.as_str()).collect();
+    let mut batches = 0;
+    for (index, (id, timestamp, len)) in inputs.iter().enumerate() {
+        match id.as_str() {
+            "a" => {
+                assert_eq!(*len, 1);
+                let (next_id, next_timestamp, next_len) = &inputs[index + 1];
+                assert_eq!(next_id, "b", "batch was split: {ids:?}");
+                assert_eq!(next_timestamp, timestamp);
+                assert_eq!(*next_len, 2);
+                batches += 1;
+            }
+            "b" => assert_eq!(ids[index - 1], "a", "batch was split: {ids:?}"),
+            _ => assert_eq!(*len, 3),
+        }
+    }
+    assert_eq!(batches, 2, "{ids:?}");
+
+    // receivers of a part of the batch only get their inputs
+    let partial: Vec<_> = partial
+        .iter()
+        .map(|(id, _, len)| (id.as_str(), *len))
+        .collect();
+    assert_eq!(partial, [("b", 2), ("b", 2)]);
+    Ok(())
+}
@@ -0,0 +1,52 @@
+#![cfg(unix)]
+
+use std::time::Duration;
+
+use dora_core::descriptor::{Descriptor, DescriptorExt};
+use dora_daemon::Daemon;
+use dora_node_api::DoraNode;
+use tokio::sync::oneshot;
+
+/// The reason that a node gives when it stops the dataflow is part of the
+/// dataflow result.
+#[tokio::test]
+async fn stop_reason_is_reported_in_result() -> eyre::Result<()> {
+    // runs `stopper_node` of this test binary as node
+    let yaml = format!(
+        r#"
+nodes:
+  - id: stopper
+    path: '{}'
+    args: stopper_node --exact --ignored
+"#,
+        std::env::current_exe()?.display()
+    );
+    let descriptor = Descriptor::parse(yaml.into_bytes())?;
+    let (listen_port, _) = oneshot::channel();
+    let run = Daemon::run_dataflow_in_process(
+        descriptor,
+        std::env::temp_dir(),
+        listen_port,
+        None,
+        std::future::pending(),
+    );
+
+    let result = tokio::time::timeout(Duration::from_secs(30), run)
+        .await
+        .map_err(|_| eyre::eyre!("dataflow did not stop"))??;
+    assert!(result.is_ok(), "{:?}", result.node_results);
+    let stop_requests: Vec<_> = result
+        .stop_requests
+        .iter()
+        .map(|(node_id, reason)| (node_id.as_ref(), reason.as_str()))
+        .collect();
+    assert_eq!(stop_requests, [("stopper", "all work is done")]);
+    Ok(())
+}
+
+#[test]
+#[ignore = "spawned as node by `stop_reason_is_reported_in_result`"]
+fn stopper_node() -> eyre::Result<()> {
+    let (mut node, _events) = DoraNode::init_from_env()?;
+    node.stop_dataflow("all work is done")
+}
@@ -0,0 +1,277 @@
+//! GPU contexts and device buffers that are shared by the operators of a
+//! runtime node.
+//!
+//! All operators of a runtime node live in the same process, so they can use
+//! the same device context and pass device memory to each other without
+//! copying it to the host. The runtime doesn't link any GPU library itself,
+//! it only keeps the opaque pointers that the operators register:
+//!
+//! - The first operator that needs a context for a device creates it and
+//!   registers it. All other operators get the registered context. Contexts
+//!   live until the runtime exits.
+//! - Device buffers are reference counted. An operator registers a buffer,
+//!   which gives it one reference, and hands this reference over by sending
+//!   the buffer as output. Every operator that receives the buffer holds a
+//!   reference until it drops the input. The `release` function of the
+//!   buffer is called once the last reference was dropped.
+//!
+//! Device buffers are only delivered to operators of the same runtime node.
+//! The registry is available to shared-library operators through their
+//! `SendOutput` context.
+
+use dora_core::metadata::ArrowTypeInfoExt;
+use dora_message::metadata::{ArrowTypeInfo, BufferOffset};
+use dora_node_api::{
+    arrow::{
+        array::{make_array, ArrayData},
+        buffer::Buffer,
+        datatypes::DataType,
+    },
+    ArrowData,
+};
+use dora_operator_api_types::{DeviceBufferView, DeviceKind, RawDeviceBuffer};
+use std::{
+    collections::HashMap,
+    ffi::c_void,
+    ptr::NonNull,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+#[derive(Default)]
+pub(crate) struct DeviceRegistry {
+    contexts: Mutex<HashMap<(DeviceKind, i32), DevicePtr>>,
+    buffers: Mutex<HashMap<u64, SharedBuffer>>,
+    next_handle: AtomicU64,
+}
+
+struct SharedBuffer {
+    buffer: RawDeviceBuffer,
+    references: usize,
+}
+
+/// Pointer into device memory, which is only passed around, but never
+/// dereferenced by the runtime.
+#[derive(Clone, Copy)]
+struct DevicePtr(*mut c_void);
+
+unsafe impl Send for DevicePtr {}
+
+// the pointers of the buffer are only passed back to the operators
+unsafe impl Send for SharedBuffer {}
+
+impl DeviceRegistry {
+    /// Returns the context registered for the given device.
+    ///
+    /// If there is none yet, the given `context` is registered, unless it is
+    /// null.
+    pub fn context(&self, device: DeviceKind, device_id: i32, context: *mut c_void) -> *mut c_void {
+        let mut contexts = self.contexts.lock().unwrap();
+        if let Some(existing) = contexts.get(&(device, device_id)) {
+            return existing.0;
+        }
+        if !context.is_null() {
+            contexts.insert((device, device_id), DevicePtr(context));
+        }
+        context
+    }
+
+    /// Registers the given buffer with a single reference and returns its
+    /// handle.
+    pub fn share(&self, buffer: RawDeviceBuffer) -> u64 {
+        // handle `0` is reserved for errors
+        let handle = self.next_handle.fetch_add(1, Ordering::Relaxed) + 1;
+        self.buffers.lock().unwrap().insert(
+            handle,
+            SharedBuffer {
+                buffer,
+                references: 1,
+            },
+        );
+        handle
+    }
+
+    pub fn view(&self, handle: u64) -> DeviceBufferView {
+        match self.buffers.lock().unwrap().get(&handle) {
+            Some(SharedBuffer { buffer, .. }) => DeviceBufferView {
+                device: buffer.device,
+                device_id: buffer.device_id,
+                ptr: buffer.ptr,
+                len: buffer.len,
+            },
+            None => DeviceBufferView {
+                device: DeviceKind::Cuda,
+                device_id: 0,
+                ptr: std::ptr::null_mut(),
+                len: 0,
+            },
+        }
+    }
+
+    /// Adds a reference to the buffer with the given handle.
+    ///
+    /// Returns `false` if the handle is unknown.
+    pub fn acquire(&self, handle: u64) -> bool {
+        match self.buffers.lock().unwrap().get_mut(&handle) {
+            Some(shared) => {
+                shared.references += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drops a reference to the buffer with the given handle and releases
+    /// the buffer if it was the last one.
+    pub fn release(&self, handle: u64) {
+        let released = {
+            let mut buffers = self.buffers.lock().unwrap();
+            let Some(shared) = buffers.get_mut(&handle) else {
+                tracing::warn!("tried to release unknown device buffer {handle}");
+                return;
+            };
+            shared.references -= 1;
+            if shared.references > 0 {
+                return;
+            }
+            buffers.remove(&handle)
+        };
+        if let Some(SharedBuffer { buffer, .. }) = released {
+            // call into the operator without holding the lock
+            unsafe { (buffer.release)(buffer.ptr, buffer.release_context) };
+        }
+    }
+
+    /// Creates the input data for an operator that receives the buffer with
+    /// the given handle.
+    ///
+    /// The data is a `UInt64` array containing the handle. It holds a
+    /// reference to the buffer, which is dropped together with the data.
+    pub fn input_data(self: &Arc<Self>, handle: u64) -> eyre::Result<(ArrowData, ArrowTypeInfo)> {
+        if !self.acquire(handle) {
+            eyre::bail!("unknown device buffer handle {handle}");
+        }
+        let reference = Arc::new(BufferReference {
+            handle,
+            registry: self.clone(),
+        });
+        let ptr = NonNull::from(&reference.handle).cast::<u8>();
+        let len = std::mem::size_of::<u64>();
+        let buffer = unsafe { Buffer::from_custom_allocation(ptr, len, reference) };
+        let data = ArrayData::builder(DataType::UInt64)
+            .len(1)
+            .add_buffer(buffer)
+            .build()?;
+
+        let mut type_info = ArrowTypeInfo::empty();
+        type_info.data_type = DataType::UInt64;
+        type_info.len = 1;
+        type_info.buffer_offsets = vec![BufferOffset { offset: 0, len }];
+
+        Ok((ArrowData(make_array(data)), type_info))
+    }
+}
+
+/// Reference to a shared device buffer, owned by the input data that carries
+/// its handle.
+struct BufferReference {
+    handle: u64,
+    registry: Arc<DeviceRegistry>,
+}
+
+impl Drop for BufferReference {
+    fn drop(&mut self) {
+        self.registry.release(self.handle);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    unsafe extern "C" fn count_release(_ptr: *mut c_void, release_context: *mut c_void) {
+        let releases = unsafe { &*(release_context as *const AtomicUsize) };
+        releases.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn buffer(releases: &AtomicUsize) -> RawDeviceBuffer {
+        RawDeviceBuffer {
+            device: DeviceKind::Cuda,
+            device_id: 0,
+            ptr: std::ptr::null_mut(),
+            len: 16,
+            release: count_release,
+            release_context: releases as *const AtomicUsize as *mut c_void,
+        }
+    }
+
+    #[test]
+    fn buffer_is_released_after_last_reference() {
+        let releases = AtomicUsize::new(0);
+        let registry = DeviceRegistry::default();
+        let handle = registry.share(buffer(&releases));
+        assert_ne!(handle, 0);
+        assert!(registry.acquire(handle));
+        assert!(registry.acquire(handle));
+
+        registry.release(handle);
+        registry.release(handle);
+        assert_eq!(releases.load(Ordering::SeqCst), 0);
+        assert_eq!(registry.view(handle).len, 16);
+
+        registry.release(handle);
+        assert_eq!(releases.load(Ordering::SeqCst), 1);
+        assert!(registry.view(handle).ptr.is_null());
+        assert_eq!(registry.view(handle).len, 0);
+    }
+
+    #[test]
+    fn double_release_calls_release_once() {
+        let releases = AtomicUsize::new(0);
+        let registry = DeviceRegistry::default();
+        let handle = registry.share(buffer(&releases));
+
+        registry.release(handle);
+        registry.release(handle);
+        assert_eq!(releases.load(Ordering::SeqCst), 1);
+        // released handles can't be acquired again
+        assert!(!registry.acquire(handle));
+    }
+
+    #[test]
+    fn input_data_holds_reference() {
+        let releases = AtomicUsize::new(0);
+        let registry = Arc::new(DeviceRegistry::default());
+        let handle = registry.share(buffer(&releases));
+
+        let (data, type_info) = registry.input_data(handle).unwrap();
+        let copy = data.clone();
+        registry.release(handle);
+        assert_eq!(type_info.len, 1);
+        assert_eq!(u64::try_from(&data).unwrap(), handle);
+
+        drop(data);
+        assert_eq!(releases.load(Ordering::SeqCst), 0);
+        drop(copy);
+        assert_eq!(releases.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn first_context_is_shared() {
+        let registry = DeviceRegistry::default();
+        let (mut a, mut b) = (0u8, 0u8);
+        let a = &mut a as *mut u8 as *mut c_void;
+        let b = &mut b as *mut u8 as *mut c_void;
+
+        assert!(registry
+            .context(DeviceKind::Cuda, 0, std::ptr::null_mut())
+            .is_null());
+        assert_eq!(registry.context(DeviceKind::Cuda, 0, a), a);
+        assert_eq!(registry.context(DeviceKind::Cuda, 0, b), a);
+        assert_eq!(registry.context(DeviceKind::Cuda, 1, b), b);
+    }
+}
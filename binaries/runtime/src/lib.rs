@@ -1,31 +1,56 @@
 #![warn(unsafe_op_in_unsafe_fn)]
 
+use device::DeviceRegistry;
 use dora_core::{
-    config::{DataId, OperatorId},
-    descriptor::OperatorConfig,
+    config::{DataId, InputMapping, NodeId, OperatorId},
+    descriptor::{Descriptor, OperatorConfig, OperatorDefinition},
 };
 use dora_message::daemon_to_node::{NodeConfig, RuntimeConfig};
 use dora_metrics::init_meter_provider;
-use dora_node_api::{DoraNode, Event};
+use dora_node_api::{arrow::array::make_array, ArrowData, DoraNode, Event, LogLevel, Metadata};
 use eyre::{bail, Context, Result};
 use futures::{Stream, StreamExt};
 use futures_concurrency::stream::Merge;
-use operator::{run_operator, OperatorEvent, StopReason};
+use middleware::{MiddlewareChain, OperatorEvents};
+use operator::{
+    channel::{OperatorReceiver, OperatorSender},
+    run_operator, OperatorEvent, StopReason,
+};
+use stats::OperatorStats;
 
 #[cfg(feature = "tracing")]
 use dora_tracing::set_up_tracing;
 use std::{
+    any::Any,
     collections::{BTreeMap, BTreeSet, HashMap},
     mem,
+    sync::Arc,
+    time::Duration,
 };
 use tokio::{
     runtime::Builder,
     sync::{mpsc, oneshot},
 };
-use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::wrappers::{IntervalStream, ReceiverStream};
+mod device;
+pub mod middleware;
 mod operator;
+mod stats;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+/// How often the processing-time statistics of the operators are reported to the daemon.
+const STATS_REPORT_INTERVAL: Duration = Duration::from_secs(5);
 
 pub fn main() -> eyre::Result<()> {
+    main_with_middleware(|_| Vec::new())
+}
+
+/// Runs the runtime with the [`Middleware`](middleware::Middleware) that the
+/// given function returns for the runtime node.
+pub fn main_with_middleware(
+    middleware: impl FnOnce(&NodeId) -> Vec<Arc<dyn middleware::Middleware>>,
+) -> eyre::Result<()> {
     let config: RuntimeConfig = {
         let raw = std::env::var("DORA_RUNTIME_CONFIG")
             .wrap_err("env variable DORA_RUNTIME_CONFIG must be set")?;
@@ -39,23 +64,17 @@ pub fn main() -> eyre::Result<()> {
     #[cfg(feature = "tracing")]
     set_up_tracing(node_id.as_ref()).context("failed to set up tracing subscriber")?;
 
-    let dataflow_descriptor = config.dataflow_descriptor.clone();
-
-    let operator_definition = if operators.is_empty() {
+    if operators.is_empty() {
         bail!("no operators");
-    } else if operators.len() > 1 {
-        bail!("multiple operators are not supported");
-    } else {
-        let mut ops = operators;
-        ops.remove(0)
-    };
+    }
+    let stats = Arc::new(OperatorStats::default());
+    let middleware = MiddlewareChain::new(
+        std::iter::once(stats.clone() as Arc<dyn middleware::Middleware>)
+            .chain(middleware(&node_id))
+            .collect(),
+    );
 
-    let (operator_events_tx, events) = mpsc::channel(1);
-    let operator_id = operator_definition.id.clone();
-    let operator_events = ReceiverStream::new(events).map(move |event| RuntimeEvent::Operator {
-        id: operator_id.clone(),
-        event,
-    });
+    let devices = Arc::new(DeviceRegistry::default());
 
     let tokio_runtime = Builder::new_current_thread()
         .enable_all()
@@ -63,46 +82,110 @@ pub fn main() -> eyre::Result<()> {
         .wrap_err("Could not build a tokio runtime.")?;
 
     let mut operator_channels = HashMap::new();
-    let queue_sizes = queue_sizes(&operator_definition.config);
-    let (operator_channel, incoming_events) =
-        operator::channel::channel(tokio_runtime.handle(), queue_sizes);
-    operator_channels.insert(operator_definition.id.clone(), operator_channel);
+    let mut operator_event_streams = Vec::new();
+    let mut spawners = HashMap::new();
+    let mut init_done = Vec::new();
+    for operator_definition in operators {
+        let operator_id = operator_definition.id.clone();
+        let queue_sizes = queue_sizes(&operator_definition.config);
+        let (operator_channel, incoming_events) = operator::channel::channel(queue_sizes);
+        operator_channels.insert(operator_id.clone(), operator_channel);
+
+        let (events_tx, events) = mpsc::channel(1);
+        let id = operator_id.clone();
+        operator_event_streams.push(
+            ReceiverStream::new(events)
+                .map(move |event| RuntimeEvent::Operator {
+                    id: id.clone(),
+                    event,
+                })
+                .boxed(),
+        );
+
+        let spawner = OperatorSpawner {
+            node_id: node_id.clone(),
+            definition: operator_definition,
+            incoming_events,
+            events_tx,
+            dataflow_descriptor: config.dataflow_descriptor.clone(),
+            middleware: middleware.clone(),
+            devices: devices.clone(),
+        };
+        let (init_done_tx, init_done_rx) = oneshot::channel();
+        spawner
+            .spawn(init_done_tx)
+            .wrap_err_with(|| format!("failed to run operator {operator_id}"))?;
+        init_done.push((operator_id.clone(), init_done_rx));
+        spawners.insert(operator_id, spawner);
+    }
+    // `select_all` polls the operators in round-robin order, so that an operator
+    // producing many outputs can't starve the others
+    let operator_events = futures::stream::select_all(operator_event_streams);
 
     tracing::info!("spawning main task");
-    let operator_config = [(
-        operator_definition.id.clone(),
-        operator_definition.config.clone(),
-    )]
-    .into_iter()
-    .collect();
-    let (init_done_tx, init_done) = oneshot::channel();
-    let main_task = std::thread::spawn(move || -> Result<()> {
-        tokio_runtime.block_on(run(
-            operator_config,
-            config,
-            operator_events,
-            operator_channels,
-            init_done,
-        ))
-    });
+    tokio_runtime.block_on(run(
+        spawners,
+        config,
+        middleware,
+        stats,
+        devices,
+        operator_events,
+        operator_channels,
+        init_done,
+    ))
+}
 
-    let operator_id = operator_definition.id.clone();
-    run_operator(
-        &node_id,
-        operator_definition,
-        incoming_events,
-        operator_events_tx,
-        init_done_tx,
-        &dataflow_descriptor,
-    )
-    .wrap_err_with(|| format!("failed to run operator {operator_id}"))?;
+/// Starts an operator on its own thread.
+///
+/// Each operator runs isolated from the other operators of the runtime, so a
+/// panicking operator doesn't take down its siblings. The spawner is kept
+/// around to restart operators after a panic.
+#[derive(Clone)]
+struct OperatorSpawner {
+    node_id: NodeId,
+    definition: OperatorDefinition,
+    incoming_events: OperatorReceiver,
+    events_tx: mpsc::Sender<OperatorEvent>,
+    dataflow_descriptor: Descriptor,
+    middleware: MiddlewareChain,
+    devices: Arc<DeviceRegistry>,
+}
 
-    match main_task.join() {
-        Ok(result) => result.wrap_err("main task failed")?,
-        Err(panic) => std::panic::resume_unwind(panic),
+impl OperatorSpawner {
+    fn spawn(&self, init_done: oneshot::Sender<Result<()>>) -> eyre::Result<()> {
+        let spawner = self.clone();
+        std::thread::Builder::new()
+            .name(format!("operator-{}", self.definition.id))
+            .spawn(move || {
+                let OperatorSpawner {
+                    node_id,
+                    definition,
+                    incoming_events,
+                    events_tx,
+                    dataflow_descriptor,
+                    middleware,
+                    devices,
+                } = spawner;
+                let operator_id = definition.id.clone();
+                let incoming_events =
+                    OperatorEvents::new(operator_id.clone(), incoming_events, middleware);
+                if let Err(err) = run_operator(
+                    &node_id,
+                    definition,
+                    incoming_events,
+                    events_tx.clone(),
+                    init_done,
+                    &dataflow_descriptor,
+                    devices,
+                )
+                .wrap_err_with(|| format!("failed to run operator {operator_id}"))
+                {
+                    let _ = events_tx.blocking_send(OperatorEvent::Error(err));
+                }
+            })
+            .wrap_err("failed to spawn operator thread")?;
+        Ok(())
     }
-
-    Ok(())
 }
 
 fn queue_sizes(config: &OperatorConfig) -> std::collections::BTreeMap<DataId, usize> {
@@ -114,20 +197,37 @@ fn queue_sizes(config: &OperatorConfig) -> std::collections::BTreeMap<DataId, us
     sizes
 }
 
-#[tracing::instrument(skip(operator_events, operator_channels), level = "trace")]
+#[tracing::instrument(
+    skip(
+        spawners,
+        middleware,
+        stats,
+        devices,
+        operator_events,
+        operator_channels,
+        init_done
+    ),
+    level = "trace"
+)]
+#[allow(clippy::too_many_arguments)]
 async fn run(
-    operators: HashMap<OperatorId, OperatorConfig>,
+    spawners: HashMap<OperatorId, OperatorSpawner>,
     config: NodeConfig,
+    middleware: MiddlewareChain,
+    stats: Arc<OperatorStats>,
+    devices: Arc<DeviceRegistry>,
     operator_events: impl Stream<Item = RuntimeEvent> + Unpin,
-    mut operator_channels: HashMap<OperatorId, flume::Sender<Event>>,
-    init_done: oneshot::Receiver<Result<()>>,
+    mut operator_channels: HashMap<OperatorId, OperatorSender>,
+    init_done: Vec<(OperatorId, oneshot::Receiver<Result<()>>)>,
 ) -> eyre::Result<()> {
     #[cfg(feature = "metrics")]
     let _meter_provider = init_meter_provider(config.node_id.to_string());
-    init_done
-        .await
-        .wrap_err("the `init_done` channel was closed unexpectedly")?
-        .wrap_err("failed to init an operator")?;
+    for (operator_id, init_done) in init_done {
+        init_done
+            .await
+            .wrap_err("the `init_done` channel was closed unexpectedly")?
+            .wrap_err_with(|| format!("failed to init operator {operator_id}"))?;
+    }
     tracing::info!("All operators are ready, starting runtime");
 
     let (mut node, mut daemon_events) = DoraNode::init(config)?;
@@ -139,12 +239,22 @@ async fn run(
             }
         }
     });
-    let mut events = (operator_events, daemon_event_stream.into_stream()).merge();
+    let stats_interval = IntervalStream::new(tokio::time::interval(STATS_REPORT_INTERVAL))
+        .map(|_| RuntimeEvent::ReportStats);
+    let mut events = (
+        operator_events,
+        daemon_event_stream.into_stream(),
+        stats_interval,
+    )
+        .merge();
 
-    let mut open_operator_inputs: HashMap<_, BTreeSet<_>> = operators
+    let mut open_operator_inputs: HashMap<_, BTreeSet<_>> = spawners
         .iter()
-        .map(|(id, config)| (id, config.inputs.keys().collect()))
+        .map(|(id, spawner)| (id, spawner.definition.config.inputs.keys().collect()))
         .collect();
+    let local_receivers = local_receivers(node.id(), &spawners);
+    let mut restarts = OperatorRestarts::default();
+    let mut failed_operators = Vec::new();
 
     while let Some(event) = events.next().await {
         match event {
@@ -152,62 +262,110 @@ async fn run(
                 id: operator_id,
                 event,
             } => {
-                match event {
+                let reason = match event {
                     OperatorEvent::Error(err) => {
-                        bail!(err.wrap_err(format!(
+                        middleware.on_error(&operator_id, &err);
+                        let err = err.wrap_err(format!(
                             "operator {}/{operator_id} raised an error",
                             node.id()
-                        )))
+                        ));
+                        tracing::error!("{err:?}");
+                        failed_operators.push(operator_id.clone());
+                        None
                     }
                     OperatorEvent::Panic(payload) => {
-                        bail!("operator {operator_id} panicked: {payload:?}");
-                    }
-                    OperatorEvent::Finished { reason } => {
-                        if let StopReason::ExplicitStopAll = reason {
-                            // let hlc = dora_core::message::uhlc::HLC::default();
-                            // let metadata = dora_core::message::Metadata::new(hlc.new_timestamp());
-                            // let data = metadata
-                            // .serialize()
-                            // .wrap_err("failed to serialize stop message")?;
-                            todo!("instruct dora-daemon/dora-coordinator to stop other nodes");
-                            // manual_stop_publisher
-                            //     .publish(&data)
-                            //     .map_err(|err| eyre::eyre!(err))
-                            //     .wrap_err("failed to send stop message")?;
-                            // break;
-                        }
-
-                        let Some(config) = operators.get(&operator_id) else {
+                        let message = panic_message(payload.as_ref());
+                        middleware.on_error(&operator_id, &eyre::eyre!("panicked: {message}"));
+                        let Some(spawner) = spawners.get(&operator_id) else {
                             tracing::warn!(
-                                "received Finished event for unknown operator `{operator_id}`"
+                                "received Panic event for unknown operator `{operator_id}`"
                             );
                             continue;
                         };
-                        let outputs = config
-                            .outputs
-                            .iter()
-                            .map(|output_id| operator_output_id(&operator_id, output_id))
-                            .collect();
-                        let result;
-                        (node, result) = tokio::task::spawn_blocking(move || {
-                            let result = node.close_outputs(outputs);
-                            (node, result)
-                        })
-                        .await
-                        .wrap_err("failed to wait for close_outputs task")?;
-                        result.wrap_err("failed to close outputs of finished operator")?;
-
-                        operator_channels.remove(&operator_id);
-
-                        if operator_channels.is_empty() {
-                            break;
+                        let max_restarts = spawner.definition.config.max_restarts;
+                        let restart_count = if operator_channels.contains_key(&operator_id) {
+                            restarts.record_panic(&operator_id, max_restarts)
+                        } else {
+                            None
+                        };
+                        if let Some(restart_count) = restart_count {
+                            tracing::warn!(
+                                "operator {}/{operator_id} panicked: {message} \
+                                (restart {restart_count}/{max_restarts})",
+                                node.id(),
+                            );
+                            let (init_done_tx, init_done) = oneshot::channel();
+                            spawner.spawn(init_done_tx).wrap_err_with(|| {
+                                format!("failed to restart operator {operator_id}")
+                            })?;
+                            // reported as lifecycle event by the coordinator
+                            let fields = BTreeMap::from([
+                                ("lifecycle_event".to_owned(), "node_restarted".to_owned()),
+                                ("operator_id".to_owned(), operator_id.to_string()),
+                                ("restart_count".to_owned(), restart_count.to_string()),
+                            ]);
+                            let log_result = node.log(
+                                LogLevel::Warn,
+                                format!(
+                                    "restarted operator {}/{operator_id} after panic",
+                                    node.id()
+                                ),
+                                fields,
+                            );
+                            if let Err(err) = log_result {
+                                tracing::warn!("{err:?}");
+                            }
+                            let operator_id = operator_id.clone();
+                            tokio::spawn(async move {
+                                if let Ok(Err(err)) = init_done.await {
+                                    tracing::error!(
+                                        "failed to init restarted operator {operator_id}: {err:?}"
+                                    );
+                                }
+                            });
+                            continue;
                         }
+                        tracing::error!("operator {}/{operator_id} panicked: {message}", node.id());
+                        failed_operators.push(operator_id.clone());
+                        None
                     }
+                    OperatorEvent::Finished { reason } => Some(reason),
                     OperatorEvent::AllocateOutputSample { len, sample: tx } => {
                         let sample = node.allocate_data_sample(len);
                         if tx.send(sample).is_err() {
                             tracing::warn!("output sample requested, but operator {operator_id} exited already");
                         }
+                        continue;
+                    }
+                    OperatorEvent::DeviceBufferOutput { output_id, handle } => {
+                        let output_id = operator_output_id(&operator_id, &output_id);
+                        let receivers = local_receivers.get(&output_id);
+                        if receivers.is_none() {
+                            tracing::warn!(
+                                "device buffer output `{output_id}` has no receivers in \
+                                this runtime node"
+                            );
+                        }
+                        for (receiver, input_id) in receivers.into_iter().flatten() {
+                            let Some(channel) = operator_channels.get(receiver) else {
+                                continue;
+                            };
+                            match devices.input_data(handle) {
+                                Ok((data, type_info)) => {
+                                    let _ = channel.send(Event::Input {
+                                        id: input_id.clone(),
+                                        metadata: Metadata::new(node.now(), type_info),
+                                        data,
+                                    });
+                                }
+                                Err(err) => tracing::warn!(
+                                    "failed to send device buffer output `{output_id}`: {err:?}"
+                                ),
+                            }
+                        }
+                        // the reference of the sender is handed over to the receivers
+                        devices.release(handle);
+                        continue;
                     }
                     OperatorEvent::Output {
                         output_id,
@@ -216,22 +374,86 @@ async fn run(
                         data,
                     } => {
                         let output_id = operator_output_id(&operator_id, &output_id);
+                        let Some(receivers) = local_receivers.get(&output_id) else {
+                            let result;
+                            (node, result) = tokio::task::spawn_blocking(move || {
+                                let result =
+                                    node.send_output_sample(output_id, type_info, parameters, data);
+                                (node, result)
+                            })
+                            .await
+                            .wrap_err("failed to wait for send_output task")?;
+                            result.wrap_err("failed to send node output")?;
+                            continue;
+                        };
                         let result;
                         (node, result) = tokio::task::spawn_blocking(move || {
-                            let result =
-                                node.send_output_sample(output_id, type_info, parameters, data);
+                            let result = node
+                                .send_output_sample_shared(output_id, type_info, parameters, data);
                             (node, result)
                         })
                         .await
                         .wrap_err("failed to wait for send_output task")?;
-                        result.wrap_err("failed to send node output")?;
+                        let (metadata, data) = result.wrap_err("failed to send node output")?;
+                        // pass the output to operators of this runtime directly, the
+                        // daemon doesn't forward it to them; the data is shared, not copied
+                        let data = make_array(data);
+                        for (receiver, input_id) in receivers {
+                            if let Some(channel) = operator_channels.get(receiver) {
+                                let _ = channel.send(Event::Input {
+                                    id: input_id.clone(),
+                                    metadata: metadata.clone(),
+                                    data: ArrowData(data.clone()),
+                                });
+                            }
+                        }
+                        continue;
                     }
+                };
+
+                // the operator finished or failed -> close its outputs
+                if let Some(StopReason::ExplicitStopAll) = reason {
+                    let reason = format!("operator `{operator_id}` returned `StopAll`");
+                    let result;
+                    (node, result) = tokio::task::spawn_blocking(move || {
+                        let result = node.stop_dataflow(reason);
+                        (node, result)
+                    })
+                    .await
+                    .wrap_err("failed to wait for stop_dataflow task")?;
+                    result.wrap_err("failed to request dataflow stop")?;
+                }
+
+                let Some(spawner) = spawners.get(&operator_id) else {
+                    tracing::warn!("received Finished event for unknown operator `{operator_id}`");
+                    continue;
+                };
+                let outputs = spawner
+                    .definition
+                    .config
+                    .outputs
+                    .iter()
+                    .map(|output_id| operator_output_id(&operator_id, output_id))
+                    .collect();
+                let result;
+                (node, result) = tokio::task::spawn_blocking(move || {
+                    let result = node.close_outputs(outputs);
+                    (node, result)
+                })
+                .await
+                .wrap_err("failed to wait for close_outputs task")?;
+                result.wrap_err("failed to close outputs of finished operator")?;
+
+                operator_channels.remove(&operator_id);
+
+                if operator_channels.is_empty() {
+                    break;
                 }
             }
             RuntimeEvent::Event(Event::Stop) => {
                 // forward stop event to all operators and close the event channels
                 for (_, channel) in operator_channels.drain() {
-                    let _ = channel.send_async(Event::Stop).await;
+                    let _ = channel.send(Event::Stop);
                 }
             }
             RuntimeEvent::Event(Event::Reload {
@@ -240,10 +462,9 @@ async fn run(
                 let _ = operator_channels
                     .get(&operator_id)
                     .unwrap()
-                    .send_async(Event::Reload {
+                    .send(Event::Reload {
                         operator_id: Some(operator_id),
-                    })
-                    .await;
+                    });
             }
             RuntimeEvent::Event(Event::Reload { operator_id: None }) => {
                 tracing::warn!("Reloading runtime nodes is not supported");
@@ -260,18 +481,16 @@ async fn run(
                     continue;
                 };
 
-                if let Err(err) = operator_channel
-                    .send_async(Event::Input {
-                        id: input_id.clone(),
-                        metadata,
-                        data,
-                    })
-                    .await
-                    .wrap_err_with(|| {
-                        format!("failed to send input `{input_id}` to operator `{operator_id}`")
-                    })
-                {
-                    tracing::warn!("{err}");
+                let event = Event::Input {
+                    id: input_id.clone(),
+                    metadata,
+                    data,
+                };
+                if operator_channel.send(event).is_err() {
+                    tracing::warn!(
+                        "failed to send input `{input_id}` to operator `{operator_id}`: \
+                        operator exited"
+                    );
                 }
             }
             RuntimeEvent::Event(Event::InputClosed { id }) => {
@@ -286,18 +505,14 @@ async fn run(
                     tracing::warn!("received input {id} for unknown operator");
                     continue;
                 };
-                if let Err(err) = operator_channel
-                    .send_async(Event::InputClosed {
-                        id: input_id.clone(),
-                    })
-                    .await
-                    .wrap_err_with(|| {
-                        format!(
-                            "failed to send InputClosed({input_id}) to operator `{operator_id}`"
-                        )
-                    })
-                {
-                    tracing::warn!("{err}");
+                let event = Event::InputClosed {
+                    id: input_id.clone(),
+                };
+                if operator_channel.send(event).is_err() {
+                    tracing::warn!(
+                        "failed to send InputClosed({input_id}) to operator `{operator_id}`: \
+                        operator exited"
+                    );
                 }
 
                 if let Some(open_inputs) = open_operator_inputs.get_mut(&operator_id) {
@@ -310,7 +525,27 @@ async fn run(
                     }
                 }
             }
+            RuntimeEvent::Event(Event::Backpressure { output_id, level }) => {
+                // operators have no way to react to backpressure yet
+                tracing::debug!("backpressure on output `{output_id}` (level {level})");
+            }
             RuntimeEvent::Event(Event::Error(err)) => eyre::bail!("received error event: {err}"),
+            RuntimeEvent::ReportStats => {
+                let health = stats.health();
+                if health.counters.is_empty() {
+                    continue;
+                }
+                let result;
+                (node, result) = tokio::task::spawn_blocking(move || {
+                    let result = node.report_health(health);
+                    (node, result)
+                })
+                .await
+                .wrap_err("failed to wait for report_health task")?;
+                if let Err(err) = result {
+                    tracing::warn!("failed to report operator stats: {err:?}");
+                }
+            }
             RuntimeEvent::Event(other) => {
                 tracing::warn!("received unknown event `{other:?}`");
             }
@@ -319,9 +554,66 @@ async fn run(
 
     mem::drop(events);
 
+    if !failed_operators.is_empty() {
+        let failed: Vec<_> = failed_operators.iter().map(|id| id.to_string()).collect();
+        bail!("operators failed: {}", failed.join(", "));
+    }
+
     Ok(())
 }
 
+/// Number of restarts of each operator after panics.
+#[derive(Default)]
+struct OperatorRestarts(HashMap<OperatorId, u32>);
+
+impl OperatorRestarts {
+    /// Records a panic of the operator.
+    ///
+    /// Returns the number of the restart if the operator should be
+    /// restarted, or `None` if it used up its `max_restarts` already.
+    fn record_panic(&mut self, operator_id: &OperatorId, max_restarts: u32) -> Option<u32> {
+        let restarts = self.0.entry(operator_id.clone()).or_default();
+        if *restarts < max_restarts {
+            *restarts += 1;
+            Some(*restarts)
+        } else {
+            None
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else {
+        payload
+            .downcast_ref::<String>()
+            .map_or("unknown panic payload", String::as_str)
+    }
+}
+
+/// Maps operator outputs to the inputs of operators of the same runtime node
+/// that they are connected to.
+fn local_receivers(
+    node_id: &NodeId,
+    spawners: &HashMap<OperatorId, OperatorSpawner>,
+) -> HashMap<DataId, Vec<(OperatorId, DataId)>> {
+    let mut receivers: HashMap<_, Vec<_>> = HashMap::new();
+    for (operator_id, spawner) in spawners {
+        for (input_id, input) in &spawner.definition.config.inputs {
+            if let InputMapping::User(mapping) = &input.mapping {
+                if &mapping.source == node_id {
+                    receivers
+                        .entry(mapping.output.clone())
+                        .or_default()
+                        .push((operator_id.clone(), input_id.clone()));
+                }
+            }
+        }
+    }
+    receivers
+}
+
 fn operator_output_id(operator_id: &OperatorId, output_id: &DataId) -> DataId {
     DataId::from(format!("{operator_id}/{output_id}"))
 }
@@ -333,4 +625,44 @@ enum RuntimeEvent {
         event: OperatorEvent,
     },
     Event(Event),
+    /// Time to report the processing-time statistics of the operators.
+    ReportStats,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::catch_unwind;
+
+    use super::*;
+
+    #[test]
+    fn operators_are_restarted_up_to_max_restarts() {
+        let mut restarts = OperatorRestarts::default();
+        let op = OperatorId::from("op".to_owned());
+        let other = OperatorId::from("other".to_owned());
+
+        assert_eq!(restarts.record_panic(&op, 2), Some(1));
+        assert_eq!(restarts.record_panic(&op, 2), Some(2));
+        assert_eq!(restarts.record_panic(&op, 2), None);
+        assert_eq!(restarts.record_panic(&op, 2), None);
+        // the limit applies to each operator separately
+        assert_eq!(restarts.record_panic(&other, 2), Some(1));
+    }
+
+    #[test]
+    fn operators_are_not_restarted_by_default() {
+        let mut restarts = OperatorRestarts::default();
+        let op = OperatorId::from("op".to_owned());
+        assert_eq!(restarts.record_panic(&op, 0), None);
+    }
+
+    #[test]
+    fn message_of_caught_panic() {
+        let payload = catch_unwind(|| panic!("static message")).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "static message");
+        let payload = catch_unwind(|| panic!("formatted {}", 42)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "formatted 42");
+        let payload = catch_unwind(|| std::panic::panic_any(42)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "unknown panic payload");
+    }
 }
@@ -0,0 +1,145 @@
+//! Hooks that run around the event handling of all operators of a runtime node.
+//!
+//! Middleware implements cross-cutting concerns such as input validation,
+//! timing, or logging once, instead of in every operator. It is registered
+//! through [`main_with_middleware`](crate::main_with_middleware), which starts
+//! the runtime with the middleware returned for the node:
+//!
+//! ```ignore
+//! fn main() -> eyre::Result<()> {
+//!     dora_runtime::main_with_middleware(|node_id| match node_id.as_ref() {
+//!         "detection" => vec![Arc::new(TimingMiddleware::default())],
+//!         _ => Vec::new(),
+//!     })
+//! }
+//! ```
+//!
+//! All hooks are called on the thread of the affected operator, except for
+//! [`Middleware::on_error`], which is called on the main thread of the runtime.
+
+use crate::operator::channel::{Disconnected, OperatorReceiver, TimerHandle};
+use dora_core::config::{DataId, OperatorId};
+use dora_node_api::Event;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+pub trait Middleware: Send + Sync + 'static {
+    /// Called before an event is passed to an operator.
+    ///
+    /// Returning [`EventAction::Drop`] skips the event, e.g. to drop invalid
+    /// or outdated inputs. The remaining middleware is not called for dropped
+    /// events. `Stop` events can't be dropped.
+    fn before_event(&self, operator_id: &OperatorId, event: &Event) -> EventAction {
+        let _ = (operator_id, event);
+        EventAction::Forward
+    }
+
+    /// Called after an operator handled an event.
+    ///
+    /// The `input_id` is set for input events. The `duration` is the time
+    /// between passing the event to the operator and the operator asking for
+    /// the next event.
+    fn after_event(&self, operator_id: &OperatorId, input_id: Option<&DataId>, duration: Duration) {
+        let _ = (operator_id, input_id, duration);
+    }
+
+    /// Called when an operator returned an error or panicked.
+    fn on_error(&self, operator_id: &OperatorId, error: &eyre::Report) {
+        let _ = (operator_id, error);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventAction {
+    /// Pass the event on to the operator.
+    Forward,
+    /// Skip the event.
+    Drop,
+}
+
+/// The middleware registered for a runtime node, in registration order.
+#[derive(Clone)]
+pub(crate) struct MiddlewareChain(Arc<[Arc<dyn Middleware>]>);
+
+impl MiddlewareChain {
+    pub fn new(middleware: Vec<Arc<dyn Middleware>>) -> Self {
+        Self(middleware.into())
+    }
+
+    pub fn on_error(&self, operator_id: &OperatorId, error: &eyre::Report) {
+        for middleware in self.0.iter() {
+            middleware.on_error(operator_id, error);
+        }
+    }
+}
+
+/// Receiver for the events of an operator that applies the middleware of
+/// the runtime node.
+pub(crate) struct OperatorEvents {
+    operator_id: OperatorId,
+    receiver: OperatorReceiver,
+    middleware: MiddlewareChain,
+    /// Input ID and start time of the event that the operator is handling.
+    current: Option<(Option<DataId>, Instant)>,
+}
+
+impl OperatorEvents {
+    pub(crate) fn new(
+        operator_id: OperatorId,
+        receiver: OperatorReceiver,
+        middleware: MiddlewareChain,
+    ) -> Self {
+        Self {
+            operator_id,
+            receiver,
+            middleware,
+            current: None,
+        }
+    }
+
+    /// Waits for the next event that is not dropped by a middleware.
+    ///
+    /// Calling this method marks the previous event as handled.
+    pub fn recv(&mut self) -> Result<Event, Disconnected> {
+        self.finish_current();
+        loop {
+            let event = self.receiver.recv()?;
+            let dropped = !matches!(event, Event::Stop)
+                && self.middleware.0.iter().any(|middleware| {
+                    middleware.before_event(&self.operator_id, &event) == EventAction::Drop
+                });
+            if dropped {
+                continue;
+            }
+            if !self.middleware.0.is_empty() {
+                let input_id = match &event {
+                    Event::Input { id, .. } => Some(id.clone()),
+                    _ => None,
+                };
+                self.current = Some((input_id, Instant::now()));
+            }
+            break Ok(event);
+        }
+    }
+
+    pub fn timer_handle(&self) -> TimerHandle {
+        self.receiver.timer_handle()
+    }
+
+    fn finish_current(&mut self) {
+        if let Some((input_id, start)) = self.current.take() {
+            let duration = start.elapsed();
+            for middleware in self.middleware.0.iter() {
+                middleware.after_event(&self.operator_id, input_id.as_ref(), duration);
+            }
+        }
+    }
+}
+
+impl Drop for OperatorEvents {
+    fn drop(&mut self) {
+        self.finish_current();
+    }
+}
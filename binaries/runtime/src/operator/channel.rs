@@ -1,98 +1,197 @@
+//! Event queues of the operators of a runtime node.
+//!
+//! Every operator has its own bounded queue. Pushing an event never blocks:
+//! if the queue of an input is full, its oldest queued input is dropped. This
+//! way, a slow operator can't delay the delivery of events to the other
+//! operators of the runtime.
+//!
+//! The queue also keeps the timers that the operator scheduled for itself.
+//! Expired timers are returned as [`Event::Timer`] before the queued events.
+
 use dora_core::config::DataId;
 use dora_node_api::Event;
-use futures::{
-    future::{self, FusedFuture},
-    FutureExt,
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, VecDeque},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Condvar, Mutex, Weak,
+    },
+    time::{Duration, Instant},
 };
-use std::collections::{BTreeMap, VecDeque};
-
-pub fn channel(
-    runtime: &tokio::runtime::Handle,
-    queue_sizes: BTreeMap<DataId, usize>,
-) -> (flume::Sender<Event>, flume::Receiver<Event>) {
-    let (incoming_tx, incoming_rx) = flume::bounded(10);
-    let (outgoing_tx, outgoing_rx) = flume::bounded(0);
 
-    runtime.spawn(async {
-        let mut buffer = InputBuffer::new(queue_sizes);
-        buffer.run(incoming_rx, outgoing_tx).await;
+pub fn channel(queue_sizes: BTreeMap<DataId, usize>) -> (OperatorSender, OperatorReceiver) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(InputBuffer {
+            queue: VecDeque::new(),
+            queue_sizes,
+            timers: BinaryHeap::new(),
+            sender_closed: false,
+        }),
+        available: Condvar::new(),
+        receivers: AtomicUsize::new(1),
+        closed: AtomicBool::new(false),
     });
+    (
+        OperatorSender {
+            shared: shared.clone(),
+        },
+        OperatorReceiver { shared },
+    )
+}
+
+/// The operator stopped receiving events.
+#[derive(Debug)]
+pub struct Disconnected;
 
-    (incoming_tx, outgoing_rx)
+/// Sending half of an operator queue.
+///
+/// Dropping the sender closes the queue. The receiver still returns the
+/// events that are already queued.
+pub struct OperatorSender {
+    shared: Arc<Shared>,
 }
 
-struct InputBuffer {
-    queue: VecDeque<Option<Event>>,
-    queue_sizes: BTreeMap<DataId, usize>,
+impl OperatorSender {
+    /// Pushes the given event to the queue without blocking.
+    ///
+    /// Returns the event if all receivers were dropped.
+    pub fn send(&self, event: Event) -> Result<(), Event> {
+        if self.shared.closed.load(Ordering::Acquire) {
+            return Err(event);
+        }
+        self.shared.state.lock().unwrap().add_event(event);
+        self.shared.available.notify_one();
+        Ok(())
+    }
 }
 
-impl InputBuffer {
-    pub fn new(queue_sizes: BTreeMap<DataId, usize>) -> Self {
+impl Drop for OperatorSender {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().sender_closed = true;
+        self.shared.available.notify_all();
+    }
+}
+
+/// Receiving half of an operator queue.
+///
+/// The receiver can be cloned to hand the queue over to a restarted operator.
+/// The queue is closed once all clones are dropped.
+pub struct OperatorReceiver {
+    shared: Arc<Shared>,
+}
+
+impl Clone for OperatorReceiver {
+    fn clone(&self) -> Self {
+        self.shared.receivers.fetch_add(1, Ordering::Relaxed);
         Self {
-            queue: VecDeque::new(),
-            queue_sizes,
+            shared: self.shared.clone(),
         }
     }
+}
 
-    pub async fn run(&mut self, incoming: flume::Receiver<Event>, outgoing: flume::Sender<Event>) {
-        let mut send_out_buf = future::Fuse::terminated();
-        let mut incoming_closed = false;
+impl Drop for OperatorReceiver {
+    fn drop(&mut self) {
+        if self.shared.receivers.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.shared.closed.store(true, Ordering::Release);
+        }
+    }
+}
+
+impl OperatorReceiver {
+    /// Blocks until the next event is available or a timer expires.
+    ///
+    /// Returns an error once the queue is empty and the sender was dropped.
+    /// Timers that didn't expire at this point are discarded.
+    pub fn recv(&self) -> Result<Event, Disconnected> {
+        let mut state = self.shared.state.lock().unwrap();
         loop {
-            let next_incoming = if incoming_closed {
-                future::Fuse::terminated()
-            } else {
-                incoming.recv_async().fuse()
-            };
-            match future::select(next_incoming, send_out_buf).await {
-                future::Either::Left((event, mut send_out)) => {
-                    match event {
-                        Ok(event) => {
-                            // received a new event -> push it to the queue
-                            self.add_event(event);
-
-                            // if outgoing queue is empty, fill it again
-                            if send_out.is_terminated() {
-                                send_out = self.send_next_queued(&outgoing);
-                            }
-                        }
-                        Err(flume::RecvError::Disconnected) => {
-                            incoming_closed = true;
-                        }
-                    }
-
-                    // reassign the send_out future, which might be still in progress
-                    send_out_buf = send_out;
+            let now = Instant::now();
+            if let Some(token) = state.pop_expired_timer(now) {
+                return Ok(Event::Timer { token });
+            }
+            if let Some(event) = state.queue.pop_front() {
+                return Ok(event);
+            }
+            if state.sender_closed {
+                return Err(Disconnected);
+            }
+            state = match state.timers.peek() {
+                Some(Reverse((deadline, _))) => {
+                    let timeout = deadline.saturating_duration_since(now);
+                    self.shared
+                        .available
+                        .wait_timeout(state, timeout)
+                        .unwrap()
+                        .0
                 }
-                future::Either::Right((send_result, _)) => match send_result {
-                    Ok(()) => {
-                        send_out_buf = self.send_next_queued(&outgoing);
-                    }
-                    Err(flume::SendError(_)) => break,
-                },
+                None => self.shared.available.wait(state).unwrap(),
             };
-            if incoming_closed && send_out_buf.is_terminated() && self.queue.is_empty() {
-                break;
-            }
         }
     }
 
-    fn send_next_queued<'a>(
-        &mut self,
-        outgoing: &'a flume::Sender<Event>,
-    ) -> future::Fuse<flume::r#async::SendFut<'a, Event>> {
-        loop {
-            match self.queue.pop_front() {
-                Some(Some(next)) => break outgoing.send_async(next).fuse(),
-                Some(None) => {
-                    // dropped event, try again with next one
-                }
-                None => break future::Fuse::terminated(),
+    pub fn timer_handle(&self) -> TimerHandle {
+        TimerHandle {
+            shared: Arc::downgrade(&self.shared),
+        }
+    }
+}
+
+/// Schedules timers on the queue of an operator.
+///
+/// The handle doesn't keep the queue alive.
+#[derive(Clone)]
+pub struct TimerHandle {
+    shared: Weak<Shared>,
+}
+
+impl TimerHandle {
+    /// Queues an [`Event::Timer`] with the given token after the given delay.
+    pub fn schedule(&self, delay: Duration, token: u64) -> Result<(), Disconnected> {
+        let shared = self.shared.upgrade().ok_or(Disconnected)?;
+        shared
+            .state
+            .lock()
+            .unwrap()
+            .timers
+            .push(Reverse((Instant::now() + delay, token)));
+        shared.available.notify_all();
+        Ok(())
+    }
+}
+
+struct Shared {
+    state: Mutex<InputBuffer>,
+    available: Condvar,
+    /// Number of live receivers.
+    ///
+    /// The [`TimerHandle`]s and the sender keep the shared state alive too, so
+    /// the reference count of the `Arc` can't be used for this.
+    receivers: AtomicUsize,
+    /// Set when the last receiver is dropped.
+    closed: AtomicBool,
+}
+
+struct InputBuffer {
+    queue: VecDeque<Event>,
+    queue_sizes: BTreeMap<DataId, usize>,
+    /// Deadline and token of the scheduled timers, earliest first.
+    timers: BinaryHeap<Reverse<(Instant, u64)>>,
+    sender_closed: bool,
+}
+
+impl InputBuffer {
+    fn pop_expired_timer(&mut self, now: Instant) -> Option<u64> {
+        match self.timers.peek() {
+            Some(Reverse((deadline, _))) if *deadline <= now => {
+                self.timers.pop().map(|Reverse((_, token))| token)
             }
+            _ => None,
         }
     }
 
     fn add_event(&mut self, event: Event) {
-        self.queue.push_back(Some(event));
+        self.queue.push_back(event);
 
         // drop oldest input events to maintain max queue length queue
         self.drop_oldest_inputs();
@@ -103,14 +202,16 @@ impl InputBuffer {
         let mut dropped = 0;
 
         // iterate over queued events, newest first
-        for event in self.queue.iter_mut().rev() {
-            let Some(Event::Input { id: input_id, .. }) = event.as_mut() else {
+        let mut index = self.queue.len();
+        while index > 0 {
+            index -= 1;
+            let Event::Input { id: input_id, .. } = &self.queue[index] else {
                 continue;
             };
             match queue_size_remaining.get_mut(input_id) {
                 Some(0) => {
                     dropped += 1;
-                    *event = None;
+                    self.queue.remove(index);
                 }
                 Some(size_remaining) => {
                     *size_remaining = size_remaining.saturating_sub(1);
@@ -126,3 +227,84 @@ impl InputBuffer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use dora_core::{metadata::ArrowTypeInfoExt, uhlc::HLC};
+    use dora_message::metadata::{ArrowTypeInfo, Metadata};
+    use dora_node_api::{arrow::array::NullArray, ArrowData};
+
+    use super::*;
+
+    fn input(id: &str) -> Event {
+        Event::Input {
+            id: id.to_owned().into(),
+            metadata: Metadata::new(HLC::default().new_timestamp(), ArrowTypeInfo::byte_array(0)),
+            data: ArrowData(Arc::new(NullArray::new(0))),
+        }
+    }
+
+    fn input_id(event: Event) -> String {
+        match event {
+            Event::Input { id, .. } => id.to_string(),
+            other => panic!("expected input, got {other:?}"),
+        }
+    }
+
+    fn queue_sizes(sizes: &[(&str, usize)]) -> BTreeMap<DataId, usize> {
+        sizes
+            .iter()
+            .map(|(id, size)| (id.to_string().into(), *size))
+            .collect()
+    }
+
+    #[test]
+    fn events_are_received_in_send_order() {
+        let (sender, receiver) = channel(queue_sizes(&[("a", 10), ("b", 10)]));
+        for id in ["a", "b", "a"] {
+            sender.send(input(id)).unwrap();
+        }
+        sender.send(Event::Stop).unwrap();
+        drop(sender);
+
+        assert_eq!(input_id(receiver.recv().unwrap()), "a");
+        assert_eq!(input_id(receiver.recv().unwrap()), "b");
+        assert_eq!(input_id(receiver.recv().unwrap()), "a");
+        assert!(matches!(receiver.recv(), Ok(Event::Stop)));
+        assert!(receiver.recv().is_err());
+    }
+
+    #[test]
+    fn send_fails_after_all_receivers_are_dropped() {
+        let (sender, receiver) = channel(queue_sizes(&[("a", 10)]));
+        let timer_handle = receiver.timer_handle();
+        let clone = receiver.clone();
+
+        drop(receiver);
+        assert!(sender.send(input("a")).is_ok());
+        assert_eq!(input_id(clone.recv().unwrap()), "a");
+
+        drop(clone);
+        let event = sender.send(input("a")).unwrap_err();
+        assert_eq!(input_id(event), "a");
+        drop(timer_handle);
+    }
+
+    #[test]
+    fn oldest_inputs_are_dropped_at_capacity() {
+        let (sender, receiver) = channel(queue_sizes(&[("a", 2), ("b", 1)]));
+        for id in ["a", "b", "a", "b", "a"] {
+            sender.send(input(id)).unwrap();
+        }
+        sender.send(Event::Stop).unwrap();
+        drop(sender);
+
+        // the first `a` and the first `b` were dropped
+        assert_eq!(input_id(receiver.recv().unwrap()), "a");
+        assert_eq!(input_id(receiver.recv().unwrap()), "b");
+        assert_eq!(input_id(receiver.recv().unwrap()), "a");
+        // non-input events are never dropped
+        assert!(matches!(receiver.recv(), Ok(Event::Stop)));
+        assert!(receiver.recv().is_err());
+    }
+}
@@ -1,36 +1,44 @@
+use crate::{device::DeviceRegistry, middleware::OperatorEvents};
 use dora_core::{
     config::{DataId, NodeId},
     descriptor::{Descriptor, OperatorDefinition, OperatorSource},
 };
 use dora_message::metadata::ArrowTypeInfo;
-use dora_node_api::{DataSample, Event, MetadataParameters};
+use dora_node_api::{DataSample, MetadataParameters};
 use eyre::{Context, Result};
-use std::any::Any;
+use std::{any::Any, sync::Arc};
 use tokio::sync::{mpsc::Sender, oneshot};
 
 pub mod channel;
 #[cfg(feature = "python")]
 mod python;
 mod shared_lib;
+#[cfg(feature = "wasm")]
+mod wasm;
 
 #[allow(unused_variables)]
 pub fn run_operator(
     node_id: &NodeId,
     operator_definition: OperatorDefinition,
-    incoming_events: flume::Receiver<Event>,
+    incoming_events: OperatorEvents,
     events_tx: Sender<OperatorEvent>,
     init_done: oneshot::Sender<Result<()>>,
     dataflow_descriptor: &Descriptor,
+    devices: Arc<DeviceRegistry>,
 ) -> eyre::Result<()> {
     match &operator_definition.config.source {
         OperatorSource::SharedLibrary(source) => {
             shared_lib::run(
                 node_id,
                 &operator_definition.id,
+                &operator_definition.config,
                 source,
-                events_tx,
-                incoming_events,
-                init_done,
+                shared_lib::OperatorChannels {
+                    events_tx,
+                    incoming_events,
+                    init_done,
+                    devices,
+                },
             )
             .wrap_err_with(|| {
                 format!(
@@ -45,6 +53,7 @@ pub fn run_operator(
             python::run(
                 node_id,
                 &operator_definition.id,
+                &operator_definition.config,
                 source,
                 events_tx,
                 incoming_events,
@@ -62,8 +71,25 @@ pub fn run_operator(
                 "Dora runtime tried spawning Python Operator outside of python environment."
             );
         }
-        OperatorSource::Wasm(_) => {
-            tracing::error!("WASM operators are not supported yet");
+        #[allow(unused_variables)]
+        OperatorSource::Wasm(source) => {
+            #[cfg(feature = "wasm")]
+            wasm::run(
+                node_id,
+                &operator_definition.id,
+                source,
+                events_tx,
+                incoming_events,
+                init_done,
+            )
+            .wrap_err_with(|| {
+                format!(
+                    "failed to spawn WASM operator for {}",
+                    operator_definition.id
+                )
+            })?;
+            #[cfg(not(feature = "wasm"))]
+            tracing::error!("dora-runtime was built without support for WASM operators.");
         }
     }
     Ok(())
@@ -82,6 +108,12 @@ pub enum OperatorEvent {
         parameters: MetadataParameters,
         data: Option<DataSample>,
     },
+    /// Sends a shared device buffer to the operators of the runtime node that
+    /// are connected to the given output.
+    DeviceBufferOutput {
+        output_id: DataId,
+        handle: u64,
+    },
     Error(eyre::Error),
     Panic(Box<dyn Any + Send>),
     Finished {
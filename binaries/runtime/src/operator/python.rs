@@ -1,9 +1,10 @@
 #![allow(clippy::borrow_deref_ref)] // clippy warns about code generated by #[pymethods]
 
 use super::{OperatorEvent, StopReason};
+use crate::{middleware::OperatorEvents, operator::channel::TimerHandle};
 use dora_core::{
     config::{NodeId, OperatorId},
-    descriptor::{source_is_url, Descriptor, PythonSource},
+    descriptor::{source_is_url, Descriptor, OperatorConfig, PythonSource},
 };
 use dora_download::download_file;
 use dora_node_api::{merged::MergedEvent, Event, Parameter};
@@ -12,12 +13,14 @@ use dora_operator_api_types::DoraStatus;
 use eyre::{bail, eyre, Context, Result};
 use pyo3::{
     pyclass,
-    types::{IntoPyDict, PyAnyMethods, PyDict, PyDictMethods, PyTracebackMethods},
-    Py, PyAny, Python,
+    types::{IntoPyDict, PyAnyMethods, PyDict, PyDictMethods, PyTracebackMethods, PyTuple},
+    IntoPy, Py, PyAny, Python, ToPyObject,
 };
 use std::{
+    collections::HashSet,
     panic::{catch_unwind, AssertUnwindSafe},
-    path::Path,
+    path::{Path, PathBuf},
+    time::SystemTime,
 };
 use tokio::sync::{mpsc::Sender, oneshot};
 use tracing::{error, field, span, warn};
@@ -31,13 +34,156 @@ fn traceback(err: pyo3::PyErr) -> eyre::Report {
     }
 }
 
-#[tracing::instrument(skip(events_tx, incoming_events), level = "trace")]
+/// Calls the lifecycle hook with the given name if the operator defines it.
+fn call_hook(
+    py: Python,
+    operator: &Py<PyAny>,
+    name: &str,
+    args: impl IntoPy<Py<PyTuple>>,
+) -> eyre::Result<()> {
+    if !operator.bind(py).hasattr(name).unwrap_or(false) {
+        return Ok(());
+    }
+    operator
+        .call_method1(py, name, args)
+        .map_err(traceback)
+        .wrap_err_with(|| format!("`{name}` failed"))?;
+    Ok(())
+}
+
+/// Tracks the modules that were imported by a Python operator, together with
+/// the modification times of their source files.
+///
+/// `importlib.reload` only reloads the given module, not the modules that it
+/// imports. To make changes to helper modules effective on hot reload, the
+/// changed modules of the import graph are reloaded before the operator module.
+struct ImportGraph {
+    /// Modules that were loaded before the operator was imported.
+    preloaded: HashSet<String>,
+    /// Modules imported by the operator, in import order.
+    modules: Vec<TrackedModule>,
+}
+
+struct TrackedModule {
+    name: String,
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl ImportGraph {
+    fn before_import(py: Python) -> Result<Self> {
+        let preloaded = loaded_modules(py)?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        Ok(Self {
+            preloaded,
+            modules: Vec::new(),
+        })
+    }
+
+    /// Rescans the modules that were imported since [`Self::before_import`].
+    ///
+    /// Modules of the standard library and of installed packages are ignored.
+    fn update(&mut self, py: Python, operator_module: &str) -> Result<()> {
+        let sys = py
+            .import_bound("sys")
+            .wrap_err("failed to import `sys` module")?;
+        let mut prefixes = Vec::new();
+        for name in ["prefix", "base_prefix", "exec_prefix"] {
+            let prefix: String = sys
+                .getattr(name)
+                .and_then(|prefix| prefix.extract())
+                .wrap_err_with(|| format!("failed to read `sys.{name}`"))?;
+            prefixes.push(PathBuf::from(prefix));
+        }
+
+        self.modules = loaded_modules(py)?
+            .into_iter()
+            .filter(|(name, _)| name != operator_module && !self.preloaded.contains(name))
+            .filter_map(|(name, path)| Some((name, path?)))
+            .filter(|(_, path)| !prefixes.iter().any(|prefix| path.starts_with(prefix)))
+            .map(|(name, path)| TrackedModule {
+                modified: modification_time(&path),
+                name,
+                path,
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// Reloads all tracked modules whose source file changed.
+    ///
+    /// Modules are reloaded in reverse import order, so that dependencies are
+    /// reloaded before the modules that import them.
+    fn reload_changed(&self, py: Python) -> Result<()> {
+        let changed = self
+            .modules
+            .iter()
+            .rev()
+            .filter(|module| modification_time(&module.path) != module.modified);
+        let sys_modules = py
+            .import_bound("sys")
+            .and_then(|sys| sys.getattr("modules"))
+            .wrap_err("failed to get `sys.modules`")?;
+        let importlib = py
+            .import_bound("importlib")
+            .wrap_err("failed to import `importlib` module")?;
+        for module in changed {
+            let Ok(loaded) = sys_modules.get_item(&module.name) else {
+                // module was removed in the meantime
+                continue;
+            };
+            importlib
+                .call_method1("reload", (loaded,))
+                .map_err(traceback)
+                .wrap_err_with(|| format!("failed to reload module `{}`", module.name))?;
+            tracing::debug!("reloaded changed module `{}`", module.name);
+        }
+        Ok(())
+    }
+}
+
+/// Returns the names of all loaded modules, in import order, together with
+/// their source file (if any).
+fn loaded_modules(py: Python) -> Result<Vec<(String, Option<PathBuf>)>> {
+    let sys_modules = py
+        .import_bound("sys")
+        .and_then(|sys| sys.getattr("modules"))
+        .wrap_err("failed to get `sys.modules`")?;
+    let sys_modules = sys_modules
+        .downcast::<PyDict>()
+        .map_err(|err| eyre!("`sys.modules` is not a dict: {err}"))?;
+    let modules = sys_modules
+        .iter()
+        .filter_map(|(name, module)| {
+            let name: String = name.extract().ok()?;
+            let path = module
+                .getattr("__file__")
+                .and_then(|file| file.extract::<String>())
+                .ok()
+                .map(PathBuf::from);
+            Some((name, path))
+        })
+        .collect();
+    Ok(modules)
+}
+
+fn modification_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[tracing::instrument(skip(config, events_tx, incoming_events), level = "trace")]
+#[allow(clippy::too_many_arguments)]
 pub fn run(
     node_id: &NodeId,
     operator_id: &OperatorId,
+    config: &OperatorConfig,
     python_source: &PythonSource,
     events_tx: Sender<OperatorEvent>,
-    incoming_events: flume::Receiver<Event>,
+    mut incoming_events: OperatorEvents,
     init_done: oneshot::Sender<Result<()>>,
     dataflow_descriptor: &Descriptor,
 ) -> eyre::Result<()> {
@@ -68,7 +214,10 @@ pub fn run(
 
     let send_output = SendOutputCallback {
         events_tx: events_tx.clone(),
+        timers: incoming_events.timer_handle(),
     };
+    let input_ids: Vec<String> = config.inputs.keys().map(|id| id.to_string()).collect();
+    let output_ids: Vec<String> = config.outputs.iter().map(|id| id.to_string()).collect();
 
     let init_operator = move |py: Python| {
         if let Some(parent_path) = path_parent {
@@ -89,7 +238,9 @@ pub fn run(
                 .wrap_err("failed to append module path to python search path")?;
         }
 
+        let mut import_graph = ImportGraph::before_import(py)?;
         let module = py.import_bound(module_name).map_err(traceback)?;
+        import_graph.update(py, module_name)?;
         let operator_class = module
             .getattr("Operator")
             .wrap_err("no `Operator` class found in module")?;
@@ -103,11 +254,11 @@ pub fn run(
             pythonize::pythonize(py, dataflow_descriptor)?,
         )?;
 
-        Result::<_, eyre::Report>::Ok(Py::from(operator))
+        Result::<_, eyre::Report>::Ok((Py::from(operator), import_graph))
     };
 
     let python_runner = move || {
-        let mut operator =
+        let (mut operator, mut import_graph) =
             match Python::with_gil(init_operator).wrap_err("failed to init python operator") {
                 Ok(op) => {
                     let _ = init_done.send(Ok(()));
@@ -119,11 +270,21 @@ pub fn run(
                 }
             };
 
+        Python::with_gil(|py| {
+            let config = [
+                ("id", operator_id.to_string().to_object(py)),
+                ("inputs", input_ids.to_object(py)),
+                ("outputs", output_ids.to_object(py)),
+            ]
+            .into_py_dict_bound(py);
+            call_hook(py, &operator, "on_start", (config, send_output.clone()))
+        })?;
+
         let mut reload = false;
-        let reason = loop {
+        let mut result = loop {
             #[allow(unused_mut)]
             let Ok(mut event) = incoming_events.recv() else {
-                break StopReason::InputsClosed;
+                break Ok(StopReason::InputsClosed);
             };
 
             if let Event::Reload { .. } = event {
@@ -132,6 +293,16 @@ pub fn run(
                 #[allow(clippy::blocks_in_conditions)]
                 match Python::with_gil(|py| -> Result<Py<PyAny>> {
                     // Saving current state
+                    let saved_state = if operator.bind(py).hasattr("on_save_state").unwrap_or(false)
+                    {
+                        let state = operator
+                            .call_method0(py, "on_save_state")
+                            .map_err(traceback)
+                            .wrap_err("`on_save_state` failed")?;
+                        Some(state)
+                    } else {
+                        None
+                    };
                     let current_state = operator
                         .getattr(py, "__dict__")
                         .wrap_err("Could not retrieve current operator state")?;
@@ -139,6 +310,10 @@ pub fn run(
                         current_state.downcast_bound::<PyDict>(py).map_err(|err| {
                             eyre!("could not extract operator state as a PyDict. Err: {}", err)
                         })?;
+                    // Reload changed helper modules first, so that the reloaded
+                    // operator module picks up their new versions
+                    import_graph.reload_changed(py)?;
+
                     // Reload module
                     let module = py
                         .import_bound(module_name)
@@ -150,6 +325,7 @@ pub fn run(
                     let module = importlib
                         .call_method("reload", (module,), None)
                         .wrap_err(format!("Could not reload {module_name} while reloading"))?;
+                    import_graph.update(py, module_name)?;
                     let reloaded_operator_class = module
                         .getattr("Operator")
                         .wrap_err("no `Operator` class found in module")?;
@@ -162,7 +338,26 @@ pub fn run(
                         .wrap_err("Could not initialize reloaded operator")?
                         .into();
 
-                    // Replace initialized state with current state
+                    // Hand the saved state to the new operator if it knows how to
+                    // restore it, otherwise replace its initialized state with the
+                    // current state
+                    if let Some(state) = saved_state {
+                        if operator
+                            .bind(py)
+                            .hasattr("on_restore_state")
+                            .unwrap_or(false)
+                        {
+                            operator
+                                .call_method1(py, "on_restore_state", (state,))
+                                .map_err(traceback)
+                                .wrap_err("`on_restore_state` failed")?;
+                            return Ok(operator);
+                        }
+                        warn!(
+                            "reloaded operator has no `on_restore_state` method, \
+                            copying its attributes instead"
+                        );
+                    }
                     operator
                         .getattr(py, "__dict__")
                         .wrap_err("Could not retrieve new operator state")?
@@ -239,22 +434,48 @@ pub fn run(
                         }
                     }
                 }
-            })?;
+            });
+            let status = match status {
+                Ok(status) => status,
+                Err(err) => {
+                    let hook_result = Python::with_gil(|py| {
+                        call_hook(
+                            py,
+                            &operator,
+                            "on_error",
+                            (format!("{err:?}"), send_output.clone()),
+                        )
+                    });
+                    if let Err(hook_err) = hook_result {
+                        warn!("{hook_err:?}");
+                    }
+                    break Err(err);
+                }
+            };
             match status {
                 s if s == DoraStatus::Continue as i32 => {} // ok
-                s if s == DoraStatus::Stop as i32 => break StopReason::ExplicitStop,
-                s if s == DoraStatus::StopAll as i32 => break StopReason::ExplicitStopAll,
-                other => bail!("on_event returned invalid status {other}"),
+                s if s == DoraStatus::Stop as i32 => break Ok(StopReason::ExplicitStop),
+                s if s == DoraStatus::StopAll as i32 => break Ok(StopReason::ExplicitStopAll),
+                other => break Err(eyre!("on_event returned invalid status {other}")),
             }
         };
 
+        let stop_result =
+            Python::with_gil(|py| call_hook(py, &operator, "on_stop", (send_output.clone(),)));
+        if let Err(err) = stop_result {
+            match &result {
+                Ok(_) => result = Err(err),
+                Err(_) => warn!("{err:?}"),
+            }
+        }
+
         // Dropping the operator using Python garbage collector.
         // Locking the GIL for immediate release.
         Python::with_gil(|_py| {
             drop(operator);
         });
 
-        Result::<_, eyre::Report>::Ok(reason)
+        result
     };
 
     let closure = AssertUnwindSafe(|| {
@@ -280,6 +501,7 @@ pub fn run(
 #[derive(Clone)]
 struct SendOutputCallback {
     events_tx: Sender<OperatorEvent>,
+    timers: TimerHandle,
 }
 
 #[allow(unsafe_op_in_unsafe_fn)]
@@ -304,6 +526,7 @@ mod callback_impl {
         types::{PyBytes, PyBytesMethods, PyDict},
         Bound, PyObject, Python,
     };
+    use std::time::Duration;
     use tokio::sync::oneshot;
     use tracing::{field, span};
     use tracing_opentelemetry::OpenTelemetrySpanExt;
@@ -391,5 +614,17 @@ mod callback_impl {
 
             Ok(())
         }
+
+        /// Request a `TIMER` event after the given delay:
+        /// - the first argument is the delay in seconds.
+        /// - the second argument is an integer token that is passed back as `dora_event["token"]`.
+        /// `e.g.:  send_output.schedule(0.5, 1)`
+        fn schedule(&self, delay: f64, token: u64) -> Result<()> {
+            let delay = Duration::try_from_secs_f64(delay)
+                .map_err(|err| eyre!("invalid timer delay `{delay}`: {err}"))?;
+            self.timers
+                .schedule(delay, token)
+                .map_err(|_| eyre!("operator event queue was closed"))
+        }
     }
 }
@@ -1,9 +1,10 @@
 use super::{OperatorEvent, StopReason};
+use crate::{device::DeviceRegistry, middleware::OperatorEvents};
 use aligned_vec::{AVec, ConstAlign};
 use dora_core::{
     adjust_shared_library_path,
     config::{DataId, NodeId, OperatorId},
-    descriptor::source_is_url,
+    descriptor::{source_is_url, OperatorConfig},
 };
 use dora_download::download_file;
 use dora_node_api::{
@@ -11,8 +12,11 @@ use dora_node_api::{
     Event, Parameter,
 };
 use dora_operator_api_types::{
-    safer_ffi::closure::ArcDynFn1, DoraDropOperator, DoraInitOperator, DoraInitResult, DoraOnEvent,
-    DoraResult, DoraStatus, Metadata, OnEventResult, Output, SendOutput,
+    safer_ffi::{self, closure::ArcDynFn1},
+    DeviceBufferOutput, DeviceContextRequest, DoraDropOperator, DoraInitOperator, DoraInitResult,
+    DoraOnError, DoraOnEvent, DoraOnStart, DoraOnStop, DoraResult, DoraStatus, Metadata,
+    OnEventResult, Output, RawOperatorConfig, RustcVersion, ScheduleTimer, SendOutput, Timer,
+    DORA_OPERATOR_API_VERSION,
 };
 use eyre::{bail, eyre, Context, Result};
 use libloading::Symbol;
@@ -22,18 +26,33 @@ use std::{
     panic::{catch_unwind, AssertUnwindSafe},
     path::Path,
     sync::Arc,
+    time::Duration,
 };
 use tokio::sync::{mpsc::Sender, oneshot};
 use tracing::{field, span};
 
+/// Channels and shared state through which an operator interacts with the
+/// runtime.
+pub struct OperatorChannels {
+    pub events_tx: Sender<OperatorEvent>,
+    pub incoming_events: OperatorEvents,
+    pub init_done: oneshot::Sender<Result<()>>,
+    pub devices: Arc<DeviceRegistry>,
+}
+
 pub fn run(
     _node_id: &NodeId,
-    _operator_id: &OperatorId,
+    operator_id: &OperatorId,
+    config: &OperatorConfig,
     source: &str,
-    events_tx: Sender<OperatorEvent>,
-    incoming_events: flume::Receiver<Event>,
-    init_done: oneshot::Sender<Result<()>>,
+    channels: OperatorChannels,
 ) -> eyre::Result<()> {
+    let OperatorChannels {
+        events_tx,
+        incoming_events,
+        init_done,
+        devices,
+    } = channels;
     let path = if source_is_url(source) {
         let target_path = &Path::new("build");
         // try to download the shared library
@@ -58,6 +77,8 @@ pub fn run(
             incoming_events,
             bindings,
             events_tx: events_tx.clone(),
+            config: raw_config(operator_id, config),
+            devices,
         };
 
         operator.run(init_done)
@@ -78,14 +99,16 @@ pub fn run(
 }
 
 struct SharedLibraryOperator<'lib> {
-    incoming_events: flume::Receiver<Event>,
+    incoming_events: OperatorEvents,
     events_tx: Sender<OperatorEvent>,
 
     bindings: Bindings<'lib>,
+    config: RawOperatorConfig,
+    devices: Arc<DeviceRegistry>,
 }
 
 impl<'lib> SharedLibraryOperator<'lib> {
-    fn run(self, init_done: oneshot::Sender<Result<()>>) -> eyre::Result<StopReason> {
+    fn run(mut self, init_done: oneshot::Sender<Result<()>>) -> eyre::Result<StopReason> {
         let operator_context = {
             let DoraInitResult {
                 result,
@@ -106,6 +129,7 @@ impl<'lib> SharedLibraryOperator<'lib> {
 
         let _ = init_done.send(Ok(()));
 
+        let device_events_tx = self.events_tx.clone();
         let send_output_closure = Arc::new(move |output: Output| {
             let Output {
                 id: output_id,
@@ -149,10 +173,55 @@ impl<'lib> SharedLibraryOperator<'lib> {
             }
         });
 
-        let reason = loop {
+        let timers = self.incoming_events.timer_handle();
+        let schedule_timer_closure = Arc::new(move |timer: ScheduleTimer| {
+            let delay = Duration::from_nanos(timer.delay_ns);
+            match timers.schedule(delay, timer.token) {
+                Ok(()) => DoraResult::SUCCESS,
+                Err(_) => DoraResult::from_error("operator event queue was closed".into()),
+            }
+        });
+
+        let devices = self.devices.clone();
+        let share_device_buffer = Arc::new(move |buffer| devices.share(buffer));
+        let send_device_buffer = Arc::new(move |output: DeviceBufferOutput| {
+            let event = OperatorEvent::DeviceBufferOutput {
+                output_id: DataId::from(String::from(output.id)),
+                handle: output.handle,
+            };
+            match device_events_tx.blocking_send(event) {
+                Ok(()) => DoraResult::SUCCESS,
+                Err(_) => DoraResult::from_error("runtime process closed unexpectedly".into()),
+            }
+        });
+        let devices = self.devices.clone();
+        let device_buffer = Arc::new(move |handle| devices.view(handle));
+        let devices = self.devices.clone();
+        let device_context = Arc::new(move |request: DeviceContextRequest| {
+            devices.context(request.device, request.device_id, request.context)
+        });
+
+        let send_output = SendOutput {
+            send_output: ArcDynFn1::new(send_output_closure),
+            schedule_timer: ArcDynFn1::new(schedule_timer_closure),
+            share_device_buffer: ArcDynFn1::new(share_device_buffer),
+            send_device_buffer: ArcDynFn1::new(send_device_buffer),
+            device_buffer: ArcDynFn1::new(device_buffer),
+            device_context: ArcDynFn1::new(device_context),
+        };
+
+        if let Some(on_start) = &self.bindings.on_start {
+            let DoraResult { error } =
+                unsafe { (on_start.on_start.0)(&self.config, &send_output, operator_context.raw) };
+            if let Some(error) = error {
+                bail!("on_start failed: {}", *error);
+            }
+        }
+
+        let mut result = loop {
             #[allow(unused_mut)]
             let Ok(mut event) = self.incoming_events.recv() else {
-                break StopReason::InputsClosed;
+                break Ok(StopReason::InputsClosed);
             };
 
             let span = span!(tracing::Level::TRACE, "on_event", input_id = field::Empty);
@@ -187,6 +256,7 @@ impl<'lib> SharedLibraryOperator<'lib> {
                     input_closed: None,
                     stop: true,
                     error: None,
+                    timer: None,
                 },
                 Event::Input {
                     id: input_id,
@@ -208,6 +278,7 @@ impl<'lib> SharedLibraryOperator<'lib> {
                         input_closed: None,
                         stop: false,
                         error: None,
+                        timer: None,
                     }
                 }
                 Event::InputClosed { id: input_id } => dora_operator_api_types::RawEvent {
@@ -215,6 +286,7 @@ impl<'lib> SharedLibraryOperator<'lib> {
                     input: None,
                     stop: false,
                     error: None,
+                    timer: None,
                 },
                 Event::Reload { .. } => {
                     // Reloading shared lib operator is not supported. See: https://github.com/dora-rs/dora/pull/239#discussion_r1154313139
@@ -225,6 +297,14 @@ impl<'lib> SharedLibraryOperator<'lib> {
                     input_closed: None,
                     input: None,
                     stop: false,
+                    timer: None,
+                },
+                Event::Timer { token } => dora_operator_api_types::RawEvent {
+                    timer: Some(Box::new(Timer { token }).into()),
+                    input: None,
+                    input_closed: None,
+                    stop: false,
+                    error: None,
                 },
                 other => {
                     tracing::warn!("unexpected event: {other:?}");
@@ -232,9 +312,6 @@ impl<'lib> SharedLibraryOperator<'lib> {
                 }
             };
 
-            let send_output = SendOutput {
-                send_output: ArcDynFn1::new(send_output_closure.clone()),
-            };
             let OnEventResult {
                 result: DoraResult { error },
                 status,
@@ -246,15 +323,37 @@ impl<'lib> SharedLibraryOperator<'lib> {
                 )
             };
             match error {
-                Some(error) => bail!("on_input failed: {}", *error),
+                Some(error) => {
+                    if let Some(on_error) = &self.bindings.on_error {
+                        let DoraResult { error: hook_error } = unsafe {
+                            (on_error.on_error.0)(&error, &send_output, operator_context.raw)
+                        };
+                        if let Some(hook_error) = hook_error {
+                            tracing::warn!("on_error failed: {}", *hook_error);
+                        }
+                    }
+                    break Err(eyre!("on_input failed: {}", *error));
+                }
                 None => match status {
                     DoraStatus::Continue => {}
-                    DoraStatus::Stop => break StopReason::ExplicitStop,
-                    DoraStatus::StopAll => break StopReason::ExplicitStopAll,
+                    DoraStatus::Stop => break Ok(StopReason::ExplicitStop),
+                    DoraStatus::StopAll => break Ok(StopReason::ExplicitStopAll),
                 },
             }
         };
-        Ok(reason)
+
+        if let Some(on_stop) = &self.bindings.on_stop {
+            let DoraResult { error } =
+                unsafe { (on_stop.on_stop.0)(&send_output, operator_context.raw) };
+            if let Some(error) = error {
+                let error = eyre!("on_stop failed: {}", *error);
+                match &result {
+                    Ok(_) => result = Err(error),
+                    Err(_) => tracing::warn!("{error:?}"),
+                }
+            }
+        }
+        result
     }
 }
 
@@ -273,10 +372,14 @@ struct Bindings<'lib> {
     init_operator: Symbol<'lib, DoraInitOperator>,
     drop_operator: Symbol<'lib, DoraDropOperator>,
     on_event: Symbol<'lib, DoraOnEvent>,
+    on_start: Option<Symbol<'lib, DoraOnStart>>,
+    on_stop: Option<Symbol<'lib, DoraOnStop>>,
+    on_error: Option<Symbol<'lib, DoraOnError>>,
 }
 
 impl<'lib> Bindings<'lib> {
     fn init(library: &'lib libloading::Library) -> Result<Self, eyre::Error> {
+        check_abi_version(library)?;
         let bindings = unsafe {
             Bindings {
                 init_operator: library
@@ -288,8 +391,67 @@ impl<'lib> Bindings<'lib> {
                 on_event: library
                     .get(b"dora_on_event")
                     .wrap_err("failed to get `dora_on_event`")?,
+                // the lifecycle hooks are optional
+                on_start: library.get(b"dora_on_start").ok(),
+                on_stop: library.get(b"dora_on_stop").ok(),
+                on_error: library.get(b"dora_on_error").ok(),
             }
         };
         Ok(bindings)
     }
 }
+
+fn raw_config(operator_id: &OperatorId, config: &OperatorConfig) -> RawOperatorConfig {
+    let ids = |ids: Vec<String>| -> safer_ffi::Vec<safer_ffi::String> {
+        ids.into_iter()
+            .map(safer_ffi::String::from)
+            .collect::<Vec<_>>()
+            .into()
+    };
+    RawOperatorConfig {
+        id: operator_id.to_string().into(),
+        inputs: ids(config.inputs.keys().map(|id| id.to_string()).collect()),
+        outputs: ids(config.outputs.iter().map(|id| id.to_string()).collect()),
+    }
+}
+
+/// Verifies that the operator was built against a compatible operator API
+/// before calling into it.
+///
+/// Rust operators additionally export the version of the compiler that they
+/// were built with, which needs to match the compiler of the runtime.
+fn check_abi_version(library: &libloading::Library) -> eyre::Result<()> {
+    let api_version = unsafe { library.get::<*const u32>(b"dora_operator_api_version") }
+        .map(|symbol| unsafe { **symbol })
+        .map_err(|_| {
+            eyre!(
+                "operator does not export a `dora_operator_api_version` symbol, so it was \
+                built against a dora version older than {} -> please rebuild the operator",
+                env!("CARGO_PKG_VERSION")
+            )
+        })?;
+    if api_version != DORA_OPERATOR_API_VERSION {
+        bail!(
+            "operator was built against operator API version {api_version}, but this runtime \
+            (dora {}) requires version {DORA_OPERATOR_API_VERSION} -> please rebuild the operator",
+            env!("CARGO_PKG_VERSION")
+        );
+    }
+
+    if let Ok(symbol) =
+        unsafe { library.get::<*const RustcVersion>(b"dora_operator_rustc_version") }
+    {
+        let operator_rustc = unsafe { &**symbol }.as_str();
+        let runtime_rustc = RustcVersion::CURRENT;
+        if operator_rustc != runtime_rustc.as_str() {
+            bail!(
+                "Rust operator was compiled with `{operator_rustc}`, but the dora runtime was \
+                compiled with `{}` -> please rebuild the operator with the same compiler \
+                version as the runtime",
+                runtime_rustc.as_str()
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,441 @@
+//! Runs operators that are compiled to WebAssembly.
+//!
+//! WASM operators are sandboxed: they only get access to stdio, but not to the
+//! file system or the environment of the runtime. The module must export its
+//! `memory` and the following functions:
+//!
+//! | Function | Signature | Description |
+//! |----------|-----------|-------------|
+//! | `dora_init_operator` | `() -> i32` | Optional. Called once after the module was loaded. A non-zero result is reported as error. |
+//! | `dora_on_event` | `(event_type: i32) -> i32` | Called for every event with its type: `0` stop, `1` input, `2` input closed, `3` error. Returns `0` to continue, `1` to stop the operator, `2` to stop the whole dataflow, and a negative value on error. |
+//! | `dora_save_state` | `() -> i32` | Optional. Called before the module is reloaded, see below. A non-zero result is reported as error. |
+//! | `dora_restore_state` | `() -> i32` | Optional. Called on the reloaded module after `dora_init_operator`, see below. A non-zero result is reported as error. |
+//!
+//! While handling an event, the module can use the following host functions
+//! of the `dora` import module:
+//!
+//! | Function | Signature | Description |
+//! |----------|-----------|-------------|
+//! | `event_id_len` | `() -> i32` | Length of the input ID of the current event. |
+//! | `event_id` | `(ptr: i32, len: i32) -> i32` | Copies the input ID of the current event into guest memory. |
+//! | `event_data_len` | `() -> i32` | Length of the input data of the current event in bytes. |
+//! | `event_data` | `(ptr: i32, len: i32) -> i32` | Copies the input data of the current event into guest memory. |
+//! | `event_timestamp` | `() -> i64` | Timestamp of the current input event. |
+//! | `send_output` | `(id_ptr: i32, id_len: i32, data_ptr: i32, data_len: i32) -> i32` | Sends the given bytes as output. |
+//! | `save_state` | `(ptr: i32, len: i32) -> i32` | Stores the given bytes as state for the reloaded module. Only valid in `dora_save_state`. |
+//! | `state_len` | `() -> i32` | Length of the saved state in bytes. |
+//! | `restore_state` | `(ptr: i32, len: i32) -> i32` | Copies the saved state into guest memory. |
+//!
+//! The status codes are the same as for WASM nodes, see [`crate::wasm`].
+//!
+//! WASM operators can be swapped while the dataflow is running: on a reload
+//! event, the module is loaded again from its source path. If the new module
+//! fails to load, the old one keeps running.
+//!
+//! Modules can carry their state over to the new version by exporting
+//! `dora_save_state` and `dora_restore_state`. Before the swap, the host calls
+//! `dora_save_state` on the old module, which passes the serialized state to
+//! `save_state`. After the new module was initialized, its `dora_restore_state`
+//! function is called, which reads the state back through `state_len` and
+//! `restore_state`. If the new module has no `dora_restore_state` function, the
+//! saved state is dropped. The state format is up to the module.
+
+use super::{OperatorEvent, StopReason};
+use crate::middleware::OperatorEvents;
+use crate::wasm::{
+    copy_to_guest, event_data, event_id, guest_memory, guest_slice, to_eyre, STATUS_ERROR,
+    STATUS_INVALID_ARGUMENT,
+};
+use aligned_vec::{AVec, ConstAlign};
+use dora_core::{
+    config::{DataId, NodeId, OperatorId},
+    descriptor::source_is_url,
+};
+use dora_download::download_file;
+use dora_node_api::{
+    arrow::array::{ArrayData, UInt8Array},
+    arrow_utils::{copy_array_into_sample, required_data_size},
+    Event,
+};
+use eyre::{bail, eyre, Context, Result};
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::{Path, PathBuf},
+};
+use tokio::sync::{mpsc::Sender, oneshot};
+use wasmtime::{Caller, Engine, Instance, Linker, Module, Store, TypedFunc};
+use wasmtime_wasi::{preview1::WasiP1Ctx, WasiCtxBuilder};
+
+const STATUS_CONTINUE: i32 = 0;
+const STATUS_STOP: i32 = 1;
+const STATUS_STOP_ALL: i32 = 2;
+
+pub fn run(
+    _node_id: &NodeId,
+    operator_id: &OperatorId,
+    source: &str,
+    events_tx: Sender<OperatorEvent>,
+    mut incoming_events: OperatorEvents,
+    init_done: oneshot::Sender<Result<()>>,
+) -> eyre::Result<()> {
+    let path = if source_is_url(source) {
+        let target_path = Path::new("build");
+        // try to download the WASM module
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        rt.block_on(download_file(source, target_path))
+            .wrap_err("failed to download WASM operator")?
+    } else {
+        PathBuf::from(source)
+    };
+
+    let closure = AssertUnwindSafe(|| {
+        let engine = Engine::default();
+        let mut instance = match WasmOperator::load(&engine, &path, events_tx.clone()) {
+            Ok(instance) => instance,
+            Err(err) => {
+                let _ = init_done.send(Err(eyre!("{err:?}")));
+                return Err(err);
+            }
+        };
+        let _ = init_done.send(Ok(()));
+
+        let reason = loop {
+            let Ok(event) = incoming_events.recv() else {
+                break StopReason::InputsClosed;
+            };
+            let event_type = match &event {
+                Event::Stop => 0,
+                Event::Input { .. } => 1,
+                Event::InputClosed { .. } => 2,
+                Event::Error(_) => 3,
+                Event::Reload { .. } => {
+                    let reloaded = instance.save_state().and_then(|state| {
+                        WasmOperator::load_with_state(&engine, &path, events_tx.clone(), state)
+                    });
+                    match reloaded {
+                        Ok(new_instance) => {
+                            instance = new_instance;
+                            tracing::info!("reloaded WASM operator `{operator_id}`");
+                        }
+                        Err(err) => tracing::error!(
+                            "failed to reload WASM operator `{operator_id}`, \
+                            keeping the previous version: {err:?}"
+                        ),
+                    }
+                    continue;
+                }
+                other => {
+                    tracing::warn!("unexpected event: {other:?}");
+                    continue;
+                }
+            };
+            match instance.on_event(event, event_type)? {
+                STATUS_CONTINUE => {}
+                STATUS_STOP => break StopReason::ExplicitStop,
+                STATUS_STOP_ALL => break StopReason::ExplicitStopAll,
+                other => bail!("dora_on_event failed with status {other}"),
+            }
+        };
+        Ok(reason)
+    });
+    match catch_unwind(closure) {
+        Ok(Ok(reason)) => {
+            let _ = events_tx.blocking_send(OperatorEvent::Finished { reason });
+        }
+        Ok(Err(err)) => {
+            let _ = events_tx.blocking_send(OperatorEvent::Error(err));
+        }
+        Err(panic) => {
+            let _ = events_tx.blocking_send(OperatorEvent::Panic(panic));
+        }
+    }
+
+    Ok(())
+}
+
+struct OperatorState {
+    wasi: WasiP1Ctx,
+    events_tx: Sender<OperatorEvent>,
+    current_event: Option<Event>,
+    /// State passed from `dora_save_state` to `dora_restore_state` on reload.
+    saved_state: Option<Vec<u8>>,
+}
+
+struct WasmOperator {
+    store: Store<OperatorState>,
+    on_event: TypedFunc<i32, i32>,
+    save_state: Option<TypedFunc<(), i32>>,
+}
+
+impl WasmOperator {
+    /// Loads and instantiates the module at the given path, then calls its
+    /// `dora_init_operator` function (if any).
+    fn load(engine: &Engine, path: &Path, events_tx: Sender<OperatorEvent>) -> eyre::Result<Self> {
+        Self::load_with_state(engine, path, events_tx, None)
+    }
+
+    /// Like [`Self::load`], but passes the given state to the
+    /// `dora_restore_state` function of the module after initialization.
+    fn load_with_state(
+        engine: &Engine,
+        path: &Path,
+        events_tx: Sender<OperatorEvent>,
+        state: Option<Vec<u8>>,
+    ) -> eyre::Result<Self> {
+        let module = Module::from_file(engine, path)
+            .map_err(to_eyre)
+            .wrap_err_with(|| format!("failed to load WASM module `{}`", path.display()))?;
+
+        let mut linker: Linker<OperatorState> = Linker::new(engine);
+        wasmtime_wasi::preview1::add_to_linker_sync(&mut linker, |state| &mut state.wasi)
+            .map_err(to_eyre)?;
+        add_operator_api_to_linker(&mut linker).map_err(to_eyre)?;
+
+        let wasi = WasiCtxBuilder::new().inherit_stdio().build_p1();
+        let mut store = Store::new(
+            engine,
+            OperatorState {
+                wasi,
+                events_tx,
+                current_event: None,
+                saved_state: None,
+            },
+        );
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .map_err(to_eyre)
+            .wrap_err("failed to instantiate WASM module")?;
+
+        if let Some(init) = optional_func(&instance, &mut store, "dora_init_operator")? {
+            call_status_func(&init, &mut store, "dora_init_operator")?;
+        }
+        if let Some(state) = state {
+            match optional_func(&instance, &mut store, "dora_restore_state")? {
+                Some(restore) => {
+                    store.data_mut().saved_state = Some(state);
+                    let result = call_status_func(&restore, &mut store, "dora_restore_state");
+                    store.data_mut().saved_state = None;
+                    result?;
+                }
+                None => tracing::warn!(
+                    "reloaded WASM module has no `dora_restore_state` function, \
+                    dropping the saved state"
+                ),
+            }
+        }
+        let on_event = instance
+            .get_typed_func::<i32, i32>(&mut store, "dora_on_event")
+            .map_err(to_eyre)
+            .wrap_err("WASM module has no valid `dora_on_event` function")?;
+        let save_state = optional_func(&instance, &mut store, "dora_save_state")?;
+
+        Ok(Self {
+            store,
+            on_event,
+            save_state,
+        })
+    }
+
+    /// Calls the `dora_save_state` function of the module (if any) and returns
+    /// the state that it passed to `save_state`.
+    fn save_state(&mut self) -> eyre::Result<Option<Vec<u8>>> {
+        let Some(save_state) = &self.save_state else {
+            return Ok(None);
+        };
+        self.store.data_mut().saved_state = None;
+        call_status_func(save_state, &mut self.store, "dora_save_state")?;
+        Ok(self.store.data_mut().saved_state.take())
+    }
+
+    fn on_event(&mut self, event: Event, event_type: i32) -> eyre::Result<i32> {
+        self.store.data_mut().current_event = Some(event);
+        let result = self
+            .on_event
+            .call(&mut self.store, event_type)
+            .map_err(to_eyre)
+            .wrap_err("`dora_on_event` failed");
+        // release the input data
+        self.store.data_mut().current_event = None;
+        result
+    }
+}
+
+/// Looks up an optional `() -> i32` function exported by the module.
+fn optional_func(
+    instance: &Instance,
+    store: &mut Store<OperatorState>,
+    name: &str,
+) -> eyre::Result<Option<TypedFunc<(), i32>>> {
+    let Some(func) = instance.get_func(&mut *store, name) else {
+        return Ok(None);
+    };
+    func.typed::<(), i32>(&*store)
+        .map(Some)
+        .map_err(to_eyre)
+        .wrap_err_with(|| format!("`{name}` has an invalid signature"))
+}
+
+/// Calls a `() -> i32` guest function and reports a non-zero result as error.
+fn call_status_func(
+    func: &TypedFunc<(), i32>,
+    store: &mut Store<OperatorState>,
+    name: &str,
+) -> eyre::Result<()> {
+    let status = func
+        .call(store, ())
+        .map_err(to_eyre)
+        .wrap_err_with(|| format!("`{name}` failed"))?;
+    if status != 0 {
+        bail!("`{name}` failed with status {status}");
+    }
+    Ok(())
+}
+
+fn add_operator_api_to_linker(linker: &mut Linker<OperatorState>) -> wasmtime::Result<()> {
+    linker.func_wrap(
+        "dora",
+        "event_id_len",
+        |caller: Caller<'_, OperatorState>| -> i32 {
+            event_id(&caller.data().current_event)
+                .map(|id| id.len() as i32)
+                .unwrap_or(0)
+        },
+    )?;
+    linker.func_wrap(
+        "dora",
+        "event_id",
+        |mut caller: Caller<'_, OperatorState>, ptr: i32, len: i32| -> i32 {
+            let Some(memory) = guest_memory(&mut caller) else {
+                return STATUS_INVALID_ARGUMENT;
+            };
+            let (memory, state) = memory.data_and_store_mut(&mut caller);
+            let id = event_id(&state.current_event).unwrap_or_default();
+            copy_to_guest(memory, ptr, len, id.as_bytes())
+        },
+    )?;
+
+    linker.func_wrap(
+        "dora",
+        "event_data_len",
+        |caller: Caller<'_, OperatorState>| -> i32 {
+            match event_data(&caller.data().current_event) {
+                Ok(data) => data.len() as i32,
+                Err(status) => status,
+            }
+        },
+    )?;
+    linker.func_wrap(
+        "dora",
+        "event_data",
+        |mut caller: Caller<'_, OperatorState>, ptr: i32, len: i32| -> i32 {
+            let Some(memory) = guest_memory(&mut caller) else {
+                return STATUS_INVALID_ARGUMENT;
+            };
+            let (memory, state) = memory.data_and_store_mut(&mut caller);
+            match event_data(&state.current_event) {
+                Ok(data) => copy_to_guest(memory, ptr, len, data),
+                Err(status) => status,
+            }
+        },
+    )?;
+
+    linker.func_wrap(
+        "dora",
+        "event_timestamp",
+        |caller: Caller<'_, OperatorState>| -> i64 {
+            match &caller.data().current_event {
+                Some(Event::Input { metadata, .. }) => {
+                    metadata.timestamp().get_time().as_u64() as i64
+                }
+                _ => 0,
+            }
+        },
+    )?;
+
+    linker.func_wrap(
+        "dora",
+        "send_output",
+        |mut caller: Caller<'_, OperatorState>,
+         id_ptr: i32,
+         id_len: i32,
+         data_ptr: i32,
+         data_len: i32|
+         -> i32 {
+            let Some(memory) = guest_memory(&mut caller) else {
+                return STATUS_INVALID_ARGUMENT;
+            };
+            let (memory, state) = memory.data_and_store_mut(&mut caller);
+            let (Some(id), Some(data)) = (
+                guest_slice(memory, id_ptr, id_len),
+                guest_slice(memory, data_ptr, data_len),
+            ) else {
+                return STATUS_INVALID_ARGUMENT;
+            };
+            let Ok(id) = std::str::from_utf8(id) else {
+                return STATUS_INVALID_ARGUMENT;
+            };
+
+            let array: ArrayData = UInt8Array::from(data.to_vec()).into();
+            let total_len = required_data_size(&array);
+            let mut sample: AVec<u8, ConstAlign<128>> = AVec::__from_elem(128, 0, total_len);
+            let type_info = copy_array_into_sample(&mut sample, &array);
+            let event = OperatorEvent::Output {
+                output_id: DataId::from(id.to_owned()),
+                type_info,
+                parameters: Default::default(),
+                data: Some(sample.into()),
+            };
+            match state.events_tx.blocking_send(event) {
+                Ok(()) => 0,
+                Err(_) => {
+                    tracing::warn!("failed to send output `{id}`: runtime closed unexpectedly");
+                    STATUS_ERROR
+                }
+            }
+        },
+    )?;
+
+    linker.func_wrap(
+        "dora",
+        "save_state",
+        |mut caller: Caller<'_, OperatorState>, ptr: i32, len: i32| -> i32 {
+            let Some(memory) = guest_memory(&mut caller) else {
+                return STATUS_INVALID_ARGUMENT;
+            };
+            let (memory, state) = memory.data_and_store_mut(&mut caller);
+            let Some(data) = guest_slice(memory, ptr, len) else {
+                return STATUS_INVALID_ARGUMENT;
+            };
+            state.saved_state = Some(data.to_vec());
+            0
+        },
+    )?;
+    linker.func_wrap(
+        "dora",
+        "state_len",
+        |caller: Caller<'_, OperatorState>| -> i32 {
+            caller
+                .data()
+                .saved_state
+                .as_ref()
+                .map(|state| state.len() as i32)
+                .unwrap_or(0)
+        },
+    )?;
+    linker.func_wrap(
+        "dora",
+        "restore_state",
+        |mut caller: Caller<'_, OperatorState>, ptr: i32, len: i32| -> i32 {
+            let Some(memory) = guest_memory(&mut caller) else {
+                return STATUS_INVALID_ARGUMENT;
+            };
+            let (memory, state) = memory.data_and_store_mut(&mut caller);
+            let saved = state.saved_state.as_deref().unwrap_or_default();
+            copy_to_guest(memory, ptr, len, saved)
+        },
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,88 @@
+//! Processing-time statistics of the operators of a runtime node.
+//!
+//! The statistics are reported to the daemon as node health metrics, so they
+//! show up in `dora health`, e.g. as `detection/p99_ms=12.5`.
+
+use crate::middleware::Middleware;
+use dora_core::config::{DataId, OperatorId};
+use dora_message::common::NodeHealth;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::Mutex,
+    time::Duration,
+};
+
+/// Number of recent events that the percentiles are computed from.
+const WINDOW_SIZE: usize = 1000;
+
+/// Records how long each operator takes to handle its events.
+#[derive(Default)]
+pub(crate) struct OperatorStats {
+    operators: Mutex<BTreeMap<OperatorId, ProcessingTimes>>,
+}
+
+#[derive(Default)]
+struct ProcessingTimes {
+    events: u64,
+    total: Duration,
+    recent: VecDeque<Duration>,
+}
+
+impl OperatorStats {
+    /// Summarizes the recorded statistics as node health metrics.
+    ///
+    /// For every operator, the number of handled events and the total
+    /// processing time are reported as counters. The percentiles and the
+    /// maximum of the processing times of the recent events are reported
+    /// as gauges, in milliseconds.
+    pub fn health(&self) -> NodeHealth {
+        let mut health = NodeHealth::default();
+        let operators = self.operators.lock().unwrap();
+        for (operator_id, times) in operators.iter() {
+            health
+                .counters
+                .insert(format!("{operator_id}/events"), times.events);
+            health.counters.insert(
+                format!("{operator_id}/total_ms"),
+                times.total.as_millis() as u64,
+            );
+
+            let mut recent: Vec<_> = times.recent.iter().copied().collect();
+            recent.sort_unstable();
+            for (name, quantile) in [("p50", 0.5), ("p90", 0.9), ("p99", 0.99), ("max", 1.0)] {
+                if let Some(value) = percentile(&recent, quantile) {
+                    health.gauges.insert(
+                        format!("{operator_id}/{name}_ms"),
+                        value.as_secs_f64() * 1000.,
+                    );
+                }
+            }
+        }
+        health
+    }
+}
+
+impl Middleware for OperatorStats {
+    fn after_event(
+        &self,
+        operator_id: &OperatorId,
+        _input_id: Option<&DataId>,
+        duration: Duration,
+    ) {
+        let mut operators = self.operators.lock().unwrap();
+        let times = operators.entry(operator_id.clone()).or_default();
+        times.events += 1;
+        times.total += duration;
+        if times.recent.len() == WINDOW_SIZE {
+            times.recent.pop_front();
+        }
+        times.recent.push_back(duration);
+    }
+}
+
+/// Returns the value at the given quantile of the sorted durations.
+fn percentile(sorted: &[Duration], quantile: f64) -> Option<Duration> {
+    let last = sorted.len().checked_sub(1)?;
+    let index = (last as f64 * quantile).round() as usize;
+    sorted.get(index).copied()
+}
@@ -0,0 +1,320 @@
+//! Runs custom nodes that are compiled to WebAssembly.
+//!
+//! The daemon spawns nodes with a `.wasm` source through `dora wasm-node`, which
+//! executes the module in an embedded [`wasmtime`] runtime. The module is
+//! started through its WASI `_start` function, with access to the node's
+//! arguments, the variables of its `env`, and stdio. The module can only access
+//! the directories that are listed in the `wasm` field of the node. The dora
+//! node API is provided as host functions in the `dora` import module:
+//!
+//! | Function | Signature | Description |
+//! |----------|-----------|-------------|
+//! | `next_event` | `(timeout_ms: i64) -> i32` | Waits for the next event (no timeout if negative) and returns its type: `0` stop, `1` input, `2` input closed, `3` error, `4` unknown, `-1` if the event stream is closed. |
+//! | `event_id_len` | `() -> i32` | Length of the input ID of the current event. |
+//! | `event_id` | `(ptr: i32, len: i32) -> i32` | Copies the input ID of the current event into guest memory. |
+//! | `event_data_len` | `() -> i32` | Length of the input data of the current event in bytes. |
+//! | `event_data` | `(ptr: i32, len: i32) -> i32` | Copies the input data of the current event into guest memory. |
+//! | `event_timestamp` | `() -> i64` | Timestamp of the current input event. |
+//! | `send_output` | `(id_ptr: i32, id_len: i32, data_ptr: i32, data_len: i32) -> i32` | Sends the given bytes as output. |
+//!
+//! The copy functions return the number of copied bytes. All functions return
+//! `-2` on invalid arguments and `-3` if the input data is not a byte array.
+//! `send_output` returns `0` on success and `-1` on error. Details about errors
+//! are logged by the host.
+
+use std::path::Path;
+
+use dora_node_api::{
+    arrow::array::Array,
+    dora_core::descriptor::{CoreNodeKind, ResolvedNode},
+    DoraNode, Event, EventStream,
+};
+use eyre::{eyre, Context, ContextCompat};
+use wasmtime::{Caller, Engine, Linker, Memory, Module, Store};
+use wasmtime_wasi::{
+    preview1::{self, WasiP1Ctx},
+    DirPerms, FilePerms, I32Exit, WasiCtxBuilder,
+};
+
+pub(crate) const STATUS_ERROR: i32 = -1;
+pub(crate) const STATUS_INVALID_ARGUMENT: i32 = -2;
+pub(crate) const STATUS_UNSUPPORTED_DATA_TYPE: i32 = -3;
+
+struct HostState {
+    wasi: WasiP1Ctx,
+    node: DoraNode,
+    events: EventStream,
+    current_event: Option<Event>,
+}
+
+/// Runs the given WebAssembly module as a dora node.
+///
+/// The node is initialized from the `DORA_NODE_CONFIG` environment variable set
+/// by the daemon. The `args` are passed to the module as WASI arguments.
+pub fn main(module_path: &Path, args: Vec<String>) -> eyre::Result<()> {
+    let (node, events) = DoraNode::init_from_env()?;
+    #[cfg(feature = "tracing")]
+    dora_tracing::set_up_tracing(node.id().as_ref())
+        .context("failed to set up tracing subscriber")?;
+
+    let engine = Engine::default();
+    let module = Module::from_file(&engine, module_path)
+        .map_err(to_eyre)
+        .wrap_err_with(|| format!("failed to load WASM module `{}`", module_path.display()))?;
+
+    let mut linker: Linker<HostState> = Linker::new(&engine);
+    preview1::add_to_linker_sync(&mut linker, |state| &mut state.wasi).map_err(to_eyre)?;
+    add_node_api_to_linker(&mut linker).map_err(to_eyre)?;
+
+    let program_name = module_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let resolved_node = node
+        .topology()
+        .resolved_node()
+        .context("failed to resolve the descriptor entry of the node")?;
+    let wasi = wasi_context(resolved_node, program_name, &args)?;
+
+    let mut store = Store::new(
+        &engine,
+        HostState {
+            wasi,
+            node,
+            events,
+            current_event: None,
+        },
+    );
+    linker
+        .module(&mut store, "", &module)
+        .map_err(to_eyre)
+        .wrap_err("failed to instantiate WASM module")?;
+    let start = linker
+        .get_default(&mut store, "")
+        .and_then(|func| func.typed::<(), ()>(&store))
+        .map_err(to_eyre)
+        .wrap_err("WASM module has no valid `_start` function")?;
+
+    match start.call(&mut store, ()) {
+        Ok(()) => Ok(()),
+        Err(err) => match err.downcast_ref::<I32Exit>() {
+            Some(I32Exit(0)) => Ok(()),
+            Some(I32Exit(code)) => Err(eyre!("WASM node exited with code {code}")),
+            None => Err(to_eyre(err)).wrap_err("WASM node failed"),
+        },
+    }
+}
+
+/// Gives the module access to stdio, its arguments, the `env` of the node,
+/// and the directories of its `wasm` config.
+///
+/// The environment of the process is not passed on, as it contains the
+/// `DORA_NODE_CONFIG` with the token of the node.
+fn wasi_context(
+    node: &ResolvedNode,
+    program_name: String,
+    args: &[String],
+) -> eyre::Result<WasiP1Ctx> {
+    let mut wasi = WasiCtxBuilder::new();
+    wasi.inherit_stdio().arg(program_name).args(args);
+
+    let custom_envs = match &node.kind {
+        CoreNodeKind::Custom(custom) => custom.envs.as_ref(),
+        CoreNodeKind::Runtime(_) => None,
+    };
+    for (key, value) in node.env.iter().chain(custom_envs).flatten() {
+        wasi.env(key, value.to_string());
+    }
+
+    let config = node.wasm.clone().unwrap_or_default();
+    let dirs = config
+        .readable
+        .iter()
+        .map(|dir| (dir, DirPerms::READ, FilePerms::READ))
+        .chain(
+            config
+                .writable
+                .iter()
+                .map(|dir| (dir, DirPerms::all(), FilePerms::all())),
+        );
+    for (dir, dir_perms, file_perms) in dirs {
+        wasi.preopened_dir(dir, dir.to_string_lossy(), dir_perms, file_perms)
+            .map_err(to_eyre)
+            .wrap_err_with(|| {
+                format!("failed to give WASM module access to `{}`", dir.display())
+            })?;
+    }
+    Ok(wasi.build_p1())
+}
+
+fn add_node_api_to_linker(linker: &mut Linker<HostState>) -> wasmtime::Result<()> {
+    linker.func_wrap(
+        "dora",
+        "next_event",
+        |mut caller: Caller<'_, HostState>, timeout_ms: i64| -> i32 {
+            let state = caller.data_mut();
+            let event = match u64::try_from(timeout_ms) {
+                Ok(timeout) => state
+                    .events
+                    .recv_timeout(std::time::Duration::from_millis(timeout)),
+                Err(_) => state.events.recv(),
+            };
+            let ty = match &event {
+                None => -1,
+                Some(Event::Stop) => 0,
+                Some(Event::Input { .. }) => 1,
+                Some(Event::InputClosed { .. }) => 2,
+                Some(Event::Error(_)) => 3,
+                Some(_) => 4,
+            };
+            state.current_event = event;
+            ty
+        },
+    )?;
+
+    linker.func_wrap(
+        "dora",
+        "event_id_len",
+        |caller: Caller<'_, HostState>| -> i32 {
+            event_id(&caller.data().current_event)
+                .map(|id| id.len() as i32)
+                .unwrap_or(0)
+        },
+    )?;
+    linker.func_wrap(
+        "dora",
+        "event_id",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> i32 {
+            let Some(memory) = guest_memory(&mut caller) else {
+                return STATUS_INVALID_ARGUMENT;
+            };
+            let (memory, state) = memory.data_and_store_mut(&mut caller);
+            let id = event_id(&state.current_event).unwrap_or_default();
+            copy_to_guest(memory, ptr, len, id.as_bytes())
+        },
+    )?;
+
+    linker.func_wrap(
+        "dora",
+        "event_data_len",
+        |caller: Caller<'_, HostState>| -> i32 {
+            match event_data(&caller.data().current_event) {
+                Ok(data) => data.len() as i32,
+                Err(status) => status,
+            }
+        },
+    )?;
+    linker.func_wrap(
+        "dora",
+        "event_data",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> i32 {
+            let Some(memory) = guest_memory(&mut caller) else {
+                return STATUS_INVALID_ARGUMENT;
+            };
+            let (memory, state) = memory.data_and_store_mut(&mut caller);
+            match event_data(&state.current_event) {
+                Ok(data) => copy_to_guest(memory, ptr, len, data),
+                Err(status) => status,
+            }
+        },
+    )?;
+
+    linker.func_wrap(
+        "dora",
+        "event_timestamp",
+        |caller: Caller<'_, HostState>| -> i64 {
+            match &caller.data().current_event {
+                Some(Event::Input { metadata, .. }) => {
+                    metadata.timestamp().get_time().as_u64() as i64
+                }
+                _ => 0,
+            }
+        },
+    )?;
+
+    linker.func_wrap(
+        "dora",
+        "send_output",
+        |mut caller: Caller<'_, HostState>,
+         id_ptr: i32,
+         id_len: i32,
+         data_ptr: i32,
+         data_len: i32|
+         -> i32 {
+            let Some(memory) = guest_memory(&mut caller) else {
+                return STATUS_INVALID_ARGUMENT;
+            };
+            let (memory, state) = memory.data_and_store_mut(&mut caller);
+            let (Some(id), Some(data)) = (
+                guest_slice(memory, id_ptr, id_len),
+                guest_slice(memory, data_ptr, data_len),
+            ) else {
+                return STATUS_INVALID_ARGUMENT;
+            };
+            let Ok(id) = std::str::from_utf8(id) else {
+                return STATUS_INVALID_ARGUMENT;
+            };
+            let result = state.node.send_output_bytes(
+                id.to_owned().into(),
+                Default::default(),
+                data.len(),
+                data,
+            );
+            match result {
+                Ok(()) => 0,
+                Err(err) => {
+                    tracing::warn!("failed to send output `{id}`: {err:?}");
+                    STATUS_ERROR
+                }
+            }
+        },
+    )?;
+
+    Ok(())
+}
+
+pub(crate) fn event_id(event: &Option<Event>) -> Option<&str> {
+    match event {
+        Some(Event::Input { id, .. }) | Some(Event::InputClosed { id }) => Some(id.as_str()),
+        _ => None,
+    }
+}
+
+pub(crate) fn event_data(event: &Option<Event>) -> Result<&[u8], i32> {
+    match event {
+        Some(Event::Input { data, .. }) if data.is_empty() => Ok(&[]),
+        Some(Event::Input { data, .. }) => {
+            <&[u8]>::try_from(data).map_err(|_| STATUS_UNSUPPORTED_DATA_TYPE)
+        }
+        _ => Ok(&[]),
+    }
+}
+
+pub(crate) fn guest_memory<T>(caller: &mut Caller<'_, T>) -> Option<Memory> {
+    caller.get_export("memory")?.into_memory()
+}
+
+pub(crate) fn guest_slice(memory: &[u8], ptr: i32, len: i32) -> Option<&[u8]> {
+    let start = usize::try_from(ptr).ok()?;
+    let len = usize::try_from(len).ok()?;
+    memory.get(start..start.checked_add(len)?)
+}
+
+pub(crate) fn copy_to_guest(memory: &mut [u8], ptr: i32, len: i32, value: &[u8]) -> i32 {
+    let (Ok(start), Ok(len)) = (usize::try_from(ptr), usize::try_from(len)) else {
+        return STATUS_INVALID_ARGUMENT;
+    };
+    let len = len.min(value.len());
+    let Some(target) = start
+        .checked_add(len)
+        .and_then(|end| memory.get_mut(start..end))
+    else {
+        return STATUS_INVALID_ARGUMENT;
+    };
+    target.copy_from_slice(&value[..len]);
+    len as i32
+}
+
+pub(crate) fn to_eyre(err: wasmtime::Error) -> eyre::Report {
+    eyre!("{err:?}")
+}
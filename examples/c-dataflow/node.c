@@ -46,7 +46,14 @@ int main()
             char out_data[50];
             int out_data_len = sprintf(out_data, "loop iteration %d", i);
 
-            dora_send_output(dora_context, out_id, strlen(out_id), out_data, out_data_len);
+            if (dora_send_output(dora_context, out_id, strlen(out_id), out_data, out_data_len) != DoraStatus_Ok)
+            {
+                char *err;
+                size_t err_len;
+                dora_last_error(&err, &err_len);
+                fprintf(stderr, "[c node] failed to send output: %.*s\n", (int)err_len, err);
+                return -1;
+            }
         }
         else if (ty == DoraEventType_Stop)
         {
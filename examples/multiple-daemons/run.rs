@@ -50,6 +50,10 @@ async fn main() -> eyre::Result<()> {
         coordinator_bind,
         coordinator_control_bind,
         ReceiverStream::new(coordinator_events_rx),
+        None,
+        None,
+        None,
+        None,
     )
     .await?;
     let coordinator_addr = Ipv4Addr::LOCALHOST;
@@ -135,6 +139,7 @@ async fn start_dataflow(
                 dataflow: dataflow_descriptor,
                 local_working_dir: working_dir,
                 name: None,
+                signed: None,
             },
             reply_sender,
         }))
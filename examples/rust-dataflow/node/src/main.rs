@@ -7,7 +7,9 @@ fn main() -> eyre::Result<()> {
 
     let (mut node, mut events) = DoraNode::init_from_env()?;
 
-    for i in 0..100 {
+    // send 100 values, other events like backpressure notifications don't count
+    let mut i = 0;
+    while i < 100 {
         let event = match events.recv() {
             Some(input) => input,
             None => break,
@@ -23,6 +25,7 @@ fn main() -> eyre::Result<()> {
                     let random: u64 = rand::random();
                     println!("tick {i}, sending {random:#x}");
                     node.send_output(output.clone(), metadata.parameters, random.into_arrow())?;
+                    i += 1;
                 }
                 other => eprintln!("Ignoring unexpected input `{other}`"),
             },
@@ -0,0 +1,9 @@
+fn main() {
+    // the target triple is only available to build scripts, but the daemons
+    // need it to select the node sources for their machine
+    println!(
+        "cargo:rustc-env=DORA_TARGET_TRIPLE={}",
+        std::env::var("TARGET").unwrap()
+    );
+    println!("cargo:rerun-if-changed=build.rs");
+}
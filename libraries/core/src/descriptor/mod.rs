@@ -1,6 +1,6 @@
 use dora_message::{
     config::{Input, InputMapping, NodeRunConfig},
-    id::{DataId, OperatorId},
+    id::{DataId, NodeId, OperatorId},
 };
 use eyre::{bail, Context, OptionExt, Result};
 use std::{
@@ -13,13 +13,19 @@ use tokio::process::Command;
 
 // reexport for compatibility
 pub use dora_message::descriptor::{
-    CoreNodeKind, CustomNode, Descriptor, Node, OperatorConfig, OperatorDefinition, OperatorSource,
-    PythonSource, ResolvedDeploy, ResolvedNode, RuntimeNode, SingleOperatorDefinition,
-    DYNAMIC_SOURCE, SHELL_SOURCE,
+    BackpressureStrategy, ContainerConfig, ContainerEngine, CoreNodeKind, CustomNode, Descriptor,
+    EdgeFaults, EventQueueConfig, FaultConfig, KubernetesConfig, LogsConfig, Node, NodeKill,
+    OperatorConfig, OperatorDefinition, OperatorSource, PythonSource, ReplayConfig, ReplayTiming,
+    ResolvedDeploy, ResolvedNode, ResourceLimits, RestartConfig, RestartPolicy, RuntimeNode,
+    SandboxConfig, SchedulingConfig, SchedulingPolicy, SecurityConfig, SignedDescriptor,
+    SingleOperatorDefinition, SpanDetail, StopSignal, TargetSource, TelemetryConfig, TimeConfig,
+    TimeMode, WasmConfig, CONTAINER_SOURCE, DYNAMIC_SOURCE, SHELL_SOURCE,
+    SIMULATION_TIME_PARAMETER,
 };
 pub use validate::ResolvedNodeExt;
 pub use visualize::collect_dora_timers;
 
+pub mod signing;
 mod validate;
 mod visualize;
 
@@ -70,7 +76,7 @@ impl DescriptorExt for Descriptor {
             for mapping in input_mappings
                 .into_iter()
                 .filter_map(|i| match &mut i.mapping {
-                    InputMapping::Timer { .. } => None,
+                    InputMapping::Timer { .. } | InputMapping::Status => None,
                     InputMapping::User(m) => Some(m),
                 })
             {
@@ -82,10 +88,12 @@ impl DescriptorExt for Descriptor {
             // resolve nodes
             let kind = match node_kind {
                 NodeKindMut::Standard { path, inputs: _ } => CoreNodeKind::Custom(CustomNode {
-                    source: path.clone(),
+                    source: path,
                     args: node.args,
                     build: node.build,
                     send_stdout_as: node.send_stdout_as,
+                    sha256: node.sha256,
+                    targets: node.targets,
                     run_config: NodeRunConfig {
                         inputs: node.inputs,
                         outputs: node.outputs,
@@ -103,22 +111,55 @@ impl DescriptorExt for Descriptor {
             };
 
             resolved.push(ResolvedNode {
-                id: node.id,
+                id: node.id.clone(),
                 name: node.name,
                 description: node.description,
                 env: node.env,
+                log_level: node.log_level,
+                sandbox: node.sandbox,
+                wasm: node.wasm,
+                container: node.container,
+                activate: node.activate,
+                stop_signal: node.stop_signal,
+                forward_signals: node.forward_signals,
+                grace_duration_ms: node.grace_duration_ms,
+                restart: node.restart,
+                resources: node.resources,
                 deploy: {
                     let default_machine = self.deploy.machine.as_deref().unwrap_or_default();
-                    let machine = match node.deploy.machine {
-                        Some(m) => m,
-                        None => default_machine.to_owned(),
+                    let kubernetes = match &node.deploy.machine {
+                        Some(_) => node.deploy.kubernetes,
+                        None => node
+                            .deploy
+                            .kubernetes
+                            .or_else(|| self.deploy.kubernetes.clone()),
                     };
-                    ResolvedDeploy { machine }
+                    let machine = match (node.deploy.machine, &kubernetes) {
+                        (Some(m), _) => m,
+                        // the daemon in the pod of the node registers with this ID
+                        (None, Some(_)) => format!("k8s-{}", node.id),
+                        (None, None) => default_machine.to_owned(),
+                    };
+                    let user = node.deploy.user.or_else(|| self.deploy.user.clone());
+                    let numa_node = node.deploy.numa_node.or(self.deploy.numa_node);
+                    let scheduling = node
+                        .deploy
+                        .scheduling
+                        .or_else(|| self.deploy.scheduling.clone());
+                    ResolvedDeploy {
+                        machine,
+                        user,
+                        numa_node,
+                        scheduling,
+                        kubernetes,
+                    }
                 },
                 kind,
             });
         }
 
+        inherit_numa_nodes(&mut resolved)?;
+
         Ok(resolved)
     }
 
@@ -166,16 +207,93 @@ pub async fn read_as_descriptor(path: &Path) -> eyre::Result<Descriptor> {
     Descriptor::parse(buf)
 }
 
+/// Sets the NUMA node of the nodes with `pin_near_source` inputs to the NUMA
+/// node of the source, following chains of pinned nodes.
+fn inherit_numa_nodes(nodes: &mut [ResolvedNode]) -> eyre::Result<()> {
+    fn pinned_sources(node: &ResolvedNode) -> Vec<NodeId> {
+        let inputs: Vec<&Input> = match &node.kind {
+            CoreNodeKind::Custom(n) => n.run_config.inputs.values().collect(),
+            CoreNodeKind::Runtime(n) => n
+                .operators
+                .iter()
+                .flat_map(|op| op.config.inputs.values())
+                .collect(),
+        };
+        inputs
+            .into_iter()
+            .filter(|input| input.pin_near_source)
+            .filter_map(|input| match &input.mapping {
+                InputMapping::User(mapping) => Some(mapping.source.clone()),
+                InputMapping::Timer { .. } | InputMapping::Status => None,
+            })
+            .collect()
+    }
+
+    loop {
+        let placement: HashMap<_, _> = nodes
+            .iter()
+            .map(|n| (n.id.clone(), (n.deploy.machine.clone(), n.deploy.numa_node)))
+            .collect();
+        let mut changed = false;
+        for node in nodes.iter_mut() {
+            for source in pinned_sources(node) {
+                let Some((machine, numa_node)) = placement.get(&source) else {
+                    continue;
+                };
+                if machine != &node.deploy.machine {
+                    bail!(
+                        "node `{}` can't be pinned near source `{source}`, which runs on \
+                        another machine",
+                        node.id
+                    );
+                }
+                match (node.deploy.numa_node, *numa_node) {
+                    (None, Some(numa_node)) => {
+                        node.deploy.numa_node = Some(numa_node);
+                        changed = true;
+                    }
+                    (Some(own), Some(numa_node)) if own != numa_node => bail!(
+                        "node `{}` is pinned to NUMA node {own}, but also near source \
+                        `{source}` on NUMA node {numa_node}",
+                        node.id
+                    ),
+                    _ => {}
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    for node in nodes.iter() {
+        if node.deploy.numa_node.is_none() {
+            if let Some(source) = pinned_sources(node).first() {
+                bail!(
+                    "node `{}` is pinned near source `{source}`, whose NUMA node is not \
+                    known (set `_unstable_deploy.numa_node` of the source)",
+                    node.id
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
 fn node_kind_mut(node: &mut Node) -> eyre::Result<NodeKindMut> {
     match node.kind()? {
         NodeKind::Standard(_) => node
             .path
-            .as_ref()
+            .clone()
             .map(|path| NodeKindMut::Standard {
                 path,
                 inputs: &mut node.inputs,
             })
             .ok_or_eyre("no path"),
+        NodeKind::Container => Ok(NodeKindMut::Standard {
+            path: CONTAINER_SOURCE.to_owned(),
+            inputs: &mut node.inputs,
+        }),
         NodeKind::Runtime(_) => node
             .operators
             .as_mut()
@@ -227,6 +345,56 @@ pub fn resolve_path(source: &str, working_dir: &Path) -> Result<PathBuf> {
     }
 }
 
+/// Target triple that this executable was compiled for, e.g.
+/// `aarch64-unknown-linux-gnu`.
+pub const TARGET_TRIPLE: &str = env!("DORA_TARGET_TRIPLE");
+
+/// Returns the most specific entry of the node `targets` that applies to the
+/// given machine and the target of this executable.
+///
+/// Machine IDs take precedence over target triples, which take precedence
+/// over architecture names such as `aarch64`.
+pub fn matching_target<'a>(
+    targets: &'a BTreeMap<String, TargetSource>,
+    machine: Option<&str>,
+) -> Option<(&'a str, &'a TargetSource)> {
+    machine
+        .filter(|machine| !machine.is_empty())
+        .into_iter()
+        .chain([TARGET_TRIPLE, std::env::consts::ARCH])
+        .find_map(|key| targets.get_key_value(key))
+        .map(|(key, target)| (key.as_str(), target))
+}
+
+pub trait CustomNodeExt {
+    /// Applies the entry of the node `targets` that matches the given machine,
+    /// see [`matching_target`].
+    ///
+    /// Returns the key of the applied entry.
+    fn select_target(&mut self, machine: Option<&str>) -> Option<String>;
+}
+
+impl CustomNodeExt for CustomNode {
+    fn select_target(&mut self, machine: Option<&str>) -> Option<String> {
+        let (key, target) = matching_target(&self.targets, machine)?;
+        let (key, target) = (key.to_owned(), target.clone());
+        if let Some(path) = target.path {
+            self.source = path;
+            // the checksum of the default source doesn't apply to other files
+            self.sha256 = target.sha256;
+        } else if target.sha256.is_some() {
+            self.sha256 = target.sha256;
+        }
+        if target.args.is_some() {
+            self.args = target.args;
+        }
+        if target.build.is_some() {
+            self.build = target.build;
+        }
+        Some(key)
+    }
+}
+
 pub trait NodeExt {
     fn kind(&self) -> eyre::Result<NodeKind>;
 }
@@ -234,6 +402,7 @@ pub trait NodeExt {
 impl NodeExt for Node {
     fn kind(&self) -> eyre::Result<NodeKind> {
         match (&self.path, &self.operators, &self.custom, &self.operator) {
+            (None, None, None, None) if self.container.is_some() => Ok(NodeKind::Container),
             (None, None, None, None) => {
                 eyre::bail!(
                     "node `{}` requires a `path`, `custom`, or `operators` field",
@@ -257,6 +426,8 @@ impl NodeExt for Node {
 #[derive(Debug)]
 pub enum NodeKind<'a> {
     Standard(&'a String),
+    /// Runs the entrypoint of its `container` image
+    Container,
     /// Dora runtime node
     Runtime(&'a RuntimeNode),
     Custom(&'a CustomNode),
@@ -266,7 +437,7 @@ pub enum NodeKind<'a> {
 #[derive(Debug)]
 enum NodeKindMut<'a> {
     Standard {
-        path: &'a String,
+        path: String,
         inputs: &'a mut BTreeMap<DataId, Input>,
     },
     /// Dora runtime node
@@ -0,0 +1,350 @@
+//! Signing of dataflow descriptors with Ed25519 keys.
+//!
+//! A descriptor is signed either through a detached `<dataflow>.yml.sig` file
+//! or through a signature line at the end of the descriptor file. In both
+//! cases, the signature covers the file content without the signature line.
+
+use std::path::{Component, Path, PathBuf};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use dora_message::descriptor::{Descriptor, SignedDescriptor};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use eyre::{bail, Context, ContextCompat};
+
+use super::DescriptorExt;
+
+/// Prefix of the line that embeds the signature into a descriptor file.
+///
+/// The line is a YAML comment, so signed descriptors stay valid.
+pub const SIGNATURE_LINE_PREFIX: &str = "# dora-signature: ";
+
+/// Reads the descriptor file and its embedded or detached signature.
+///
+/// Returns `None` if the descriptor is not signed.
+pub fn read_signed(path: &Path) -> eyre::Result<Option<SignedDescriptor>> {
+    let file = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("failed to read `{}`", path.display()))?;
+    let (content, embedded) = split_signature(&file);
+    let signature = match embedded {
+        Some(signature) => signature.to_owned(),
+        None => match std::fs::read_to_string(detached_signature_path(path)) {
+            Ok(signature) => signature,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err).wrap_err("failed to read detached signature"),
+        },
+    };
+    let signature = STANDARD
+        .decode(signature.trim())
+        .wrap_err("signature is not valid base64")?;
+    Ok(Some(SignedDescriptor {
+        content: content.to_owned(),
+        signature,
+    }))
+}
+
+/// Signs the descriptor file, replacing an existing signature.
+///
+/// Returns the path that the signature was written to.
+pub fn sign_file(path: &Path, key: &DescriptorSigner, embed: bool) -> eyre::Result<PathBuf> {
+    let file = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("failed to read `{}`", path.display()))?;
+    let (content, _) = split_signature(&file);
+    Descriptor::parse(content.as_bytes().to_vec())
+        .wrap_err("refusing to sign invalid descriptor")?;
+    let signature = STANDARD.encode(key.0.sign(content.as_bytes()).to_bytes());
+
+    if embed {
+        let mut signed = content.to_owned();
+        if !signed.is_empty() && !signed.ends_with('\n') {
+            signed.push('\n');
+        }
+        signed.push_str(SIGNATURE_LINE_PREFIX);
+        signed.push_str(&signature);
+        signed.push('\n');
+        std::fs::write(path, signed)
+            .wrap_err_with(|| format!("failed to write `{}`", path.display()))?;
+        Ok(path.to_owned())
+    } else {
+        if content.len() != file.len() {
+            // an outdated embedded signature would take precedence
+            std::fs::write(path, content)
+                .wrap_err_with(|| format!("failed to write `{}`", path.display()))?;
+        }
+        let signature_path = detached_signature_path(path);
+        std::fs::write(&signature_path, signature + "\n")
+            .wrap_err_with(|| format!("failed to write `{}`", signature_path.display()))?;
+        Ok(signature_path)
+    }
+}
+
+fn detached_signature_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_owned();
+    file_name.push(".sig");
+    path.with_file_name(file_name)
+}
+
+/// Splits off the embedded signature line, if any.
+fn split_signature(file: &str) -> (&str, Option<&str>) {
+    let trimmed = file.trim_end_matches(['\n', '\r']);
+    let line_start = trimmed.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    match trimmed[line_start..].strip_prefix(SIGNATURE_LINE_PREFIX) {
+        Some(signature) => (&file[..line_start], Some(signature)),
+        None => (file, None),
+    }
+}
+
+/// Secret key for signing descriptors.
+pub struct DescriptorSigner(SigningKey);
+
+impl DescriptorSigner {
+    /// Generates a new key and writes it to the given path as base64.
+    ///
+    /// The public key is written to the same path with a `.pub` extension.
+    pub fn generate(path: &Path) -> eyre::Result<Self> {
+        let mut secret = [0; 32];
+        getrandom::getrandom(&mut secret).wrap_err("failed to generate signing key")?;
+        let signer = Self(SigningKey::from_bytes(&secret));
+
+        write_secret(path, &STANDARD.encode(secret))?;
+        let public_path = path.with_extension("pub");
+        std::fs::write(&public_path, signer.public_key() + "\n")
+            .wrap_err_with(|| format!("failed to write `{}`", public_path.display()))?;
+        Ok(signer)
+    }
+
+    pub fn read(path: &Path) -> eyre::Result<Self> {
+        let encoded = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to read signing key `{}`", path.display()))?;
+        let secret: [u8; 32] = STANDARD
+            .decode(encoded.trim())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .with_context(|| format!("`{}` is not a valid signing key", path.display()))?;
+        Ok(Self(SigningKey::from_bytes(&secret)))
+    }
+
+    /// Base64 encoded public key, as listed in the trusted keys file.
+    pub fn public_key(&self) -> String {
+        STANDARD.encode(self.0.verifying_key().as_bytes())
+    }
+}
+
+#[cfg(unix)]
+fn write_secret(path: &Path, secret: &str) -> eyre::Result<()> {
+    use std::{io::Write, os::unix::fs::OpenOptionsExt};
+
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+        .wrap_err_with(|| format!("failed to create `{}`", path.display()))?;
+    writeln!(file, "{secret}").wrap_err_with(|| format!("failed to write `{}`", path.display()))
+}
+
+#[cfg(not(unix))]
+fn write_secret(path: &Path, secret: &str) -> eyre::Result<()> {
+    if path.exists() {
+        bail!("`{}` already exists", path.display());
+    }
+    std::fs::write(path, format!("{secret}\n"))
+        .wrap_err_with(|| format!("failed to write `{}`", path.display()))
+}
+
+/// Public keys whose signatures are accepted.
+#[derive(Debug, Clone)]
+pub struct TrustedKeys(Vec<VerifyingKey>);
+
+impl TrustedKeys {
+    /// Reads a file with one base64 encoded public key per line.
+    ///
+    /// Empty lines and lines starting with `#` are ignored.
+    pub fn read(path: &Path) -> eyre::Result<Self> {
+        let file = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to read trusted keys `{}`", path.display()))?;
+        let keys = file
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let bytes: [u8; 32] = STANDARD
+                    .decode(line)
+                    .ok()
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .with_context(|| format!("invalid public key `{line}`"))?;
+                VerifyingKey::from_bytes(&bytes)
+                    .wrap_err_with(|| format!("invalid public key `{line}`"))
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+        if keys.is_empty() {
+            bail!("no trusted keys found in `{}`", path.display());
+        }
+        Ok(Self(keys))
+    }
+
+    /// Checks the signature and parses the signed descriptor.
+    pub fn verify(&self, signed: &SignedDescriptor) -> eyre::Result<Descriptor> {
+        let signature = Signature::from_slice(&signed.signature).wrap_err("invalid signature")?;
+        if !self
+            .0
+            .iter()
+            .any(|key| key.verify(signed.content.as_bytes(), &signature).is_ok())
+        {
+            bail!("descriptor was modified or not signed by a trusted key");
+        }
+        Descriptor::parse(signed.content.as_bytes().to_vec())
+    }
+}
+
+/// Signature checks of the coordinator and the daemons.
+#[derive(Debug, Clone)]
+pub struct SignaturePolicy {
+    pub trusted_keys: TrustedKeys,
+    /// Refuse descriptors without signature (production mode).
+    pub require_signed: bool,
+}
+
+impl SignaturePolicy {
+    /// Returns the descriptor that should be run.
+    ///
+    /// For signed dataflows, this is the descriptor parsed from the verified
+    /// content. Only the `restore` checkpoint is taken from the given
+    /// descriptor, as it is set on start through `dora start --restore`.
+    ///
+    /// The checkpoint is not covered by the signature. It only provides the
+    /// state and the queued inputs of the nodes, not code or configuration.
+    /// To prevent reading checkpoints from arbitrary locations, it must be a
+    /// relative path that stays within the working directory.
+    pub fn check(
+        &self,
+        dataflow: Descriptor,
+        signed: Option<&SignedDescriptor>,
+    ) -> eyre::Result<Descriptor> {
+        match signed {
+            Some(signed) => {
+                let mut verified = self.trusted_keys.verify(signed)?;
+                if let Some(restore) = &dataflow.restore {
+                    check_restore_path(restore)?;
+                }
+                verified.restore = dataflow.restore;
+                Ok(verified)
+            }
+            None if self.require_signed => {
+                bail!("refusing to run unsigned dataflow, sign it through `dora sign`")
+            }
+            None => Ok(dataflow),
+        }
+    }
+}
+
+/// Checks that the unsigned `restore` path of a signed dataflow stays within
+/// the working directory.
+fn check_restore_path(restore: &Path) -> eyre::Result<()> {
+    if !restore
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        bail!(
+            "checkpoint `{}` of signed dataflow must be a relative path \
+            within the working directory",
+            restore.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATAFLOW: &str = "nodes: []\n";
+
+    fn signer(seed: u8) -> DescriptorSigner {
+        DescriptorSigner(SigningKey::from_bytes(&[seed; 32]))
+    }
+
+    fn sign(signer: &DescriptorSigner, content: &str) -> SignedDescriptor {
+        SignedDescriptor {
+            content: content.to_owned(),
+            signature: signer.0.sign(content.as_bytes()).to_bytes().to_vec(),
+        }
+    }
+
+    fn policy(signer: &DescriptorSigner, require_signed: bool) -> SignaturePolicy {
+        SignaturePolicy {
+            trusted_keys: TrustedKeys(vec![signer.0.verifying_key()]),
+            require_signed,
+        }
+    }
+
+    fn unsigned(restore: Option<&str>) -> Descriptor {
+        let mut dataflow = Descriptor::parse(DATAFLOW.as_bytes().to_vec()).unwrap();
+        dataflow.restore = restore.map(PathBuf::from);
+        dataflow
+    }
+
+    #[test]
+    fn verifies_trusted_signature() {
+        let signer = signer(1);
+        let policy = policy(&signer, true);
+        assert!(policy.trusted_keys.verify(&sign(&signer, DATAFLOW)).is_ok());
+    }
+
+    #[test]
+    fn rejects_modified_content() {
+        let signer = signer(1);
+        let policy = policy(&signer, true);
+        let mut signed = sign(&signer, DATAFLOW);
+        signed.content.push_str("restore: checkpoint\n");
+        assert!(policy.trusted_keys.verify(&signed).is_err());
+    }
+
+    #[test]
+    fn rejects_untrusted_key() {
+        let policy = policy(&signer(1), true);
+        let signed = sign(&signer(2), DATAFLOW);
+        assert!(policy.trusted_keys.verify(&signed).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_signature() {
+        let policy = policy(&signer(1), true);
+        let signed = SignedDescriptor {
+            content: DATAFLOW.to_owned(),
+            signature: vec![0; 10],
+        };
+        assert!(policy.trusted_keys.verify(&signed).is_err());
+    }
+
+    #[test]
+    fn refuses_unsigned_dataflow_if_required() {
+        let signer = signer(1);
+        assert!(policy(&signer, true).check(unsigned(None), None).is_err());
+        assert!(policy(&signer, false).check(unsigned(None), None).is_ok());
+    }
+
+    #[test]
+    fn takes_restore_path_from_given_descriptor() {
+        let signer = signer(1);
+        let policy = policy(&signer, true);
+        let signed = sign(&signer, DATAFLOW);
+
+        let dataflow = policy
+            .check(unsigned(Some("checkpoints/1")), Some(&signed))
+            .unwrap();
+        assert_eq!(dataflow.restore, Some(PathBuf::from("checkpoints/1")));
+
+        for restore in ["../checkpoint", "/tmp/checkpoint", "a/../../b"] {
+            assert!(policy
+                .check(unsigned(Some(restore)), Some(&signed))
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn splits_off_embedded_signature() {
+        let file = format!("{DATAFLOW}{SIGNATURE_LINE_PREFIX}c2ln\n");
+        assert_eq!(split_signature(&file), (DATAFLOW, Some("c2ln")));
+        assert_eq!(split_signature(DATAFLOW), (DATAFLOW, None));
+    }
+}
@@ -5,15 +5,22 @@ use crate::{
 };
 
 use dora_message::{
-    config::{Input, InputMapping, UserInputMapping},
-    descriptor::{CoreNodeKind, OperatorSource, ResolvedNode, DYNAMIC_SOURCE, SHELL_SOURCE},
+    config::{
+        Input, InputMapping, LocalCommunicationConfig, QueuePolicy, TimerThrottle, UserInputMapping,
+    },
+    descriptor::{
+        ContainerConfig, CoreNodeKind, CustomNode, EventQueueConfig, FaultConfig, KubernetesConfig,
+        OperatorSource, ReplayConfig, ResolvedNode, ResourceLimits, RestartConfig, SandboxConfig,
+        SchedulingConfig, StopSignal, TelemetryConfig, TimeConfig, TimeMode, WasmConfig,
+        CONTAINER_SOURCE, DYNAMIC_SOURCE, SHELL_SOURCE,
+    },
     id::{DataId, OperatorId},
 };
 use eyre::{bail, eyre, Context};
 use std::{path::Path, process::Command};
 use tracing::info;
 
-use super::{resolve_path, Descriptor, DescriptorExt};
+use super::{resolve_path, CustomNodeExt, Descriptor, DescriptorExt};
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub fn check_dataflow(
@@ -22,6 +29,22 @@ pub fn check_dataflow(
     remote_daemon_id: Option<&[&str]>,
     coordinator_is_remote: bool,
 ) -> eyre::Result<()> {
+    for node in &dataflow.nodes {
+        if !node.targets.is_empty() && node.path.is_none() {
+            bail!(
+                "node `{}` has `targets`, which are only supported for nodes with a `path`",
+                node.id
+            );
+        }
+        if node.deploy.machine.is_some() && node.deploy.kubernetes.is_some() {
+            bail!(
+                "node `{}` can't run both on machine `{}` and in Kubernetes",
+                node.id,
+                node.deploy.machine.as_deref().unwrap_or_default()
+            );
+        }
+    }
+
     let nodes = dataflow.resolve_aliases_and_set_defaults()?;
     let mut has_python_operator = false;
 
@@ -31,6 +54,8 @@ pub fn check_dataflow(
             descriptor::CoreNodeKind::Custom(custom) => match custom.source.as_str() {
                 SHELL_SOURCE => (),
                 DYNAMIC_SOURCE => (),
+                // the source of container nodes is part of their image
+                _ if node.container.is_some() => (),
                 source => {
                     if source_is_url(source) {
                         info!("{source} is a URL."); // TODO: Implement url check.
@@ -41,6 +66,9 @@ pub fn check_dataflow(
                             info!("skipping path check for remote node `{}`", node.id);
                         }
                     } else {
+                        let mut custom = custom.clone();
+                        custom.select_target(Some(&node.deploy.machine));
+                        let source = &custom.source;
                         resolve_path(source, working_dir)
                             .wrap_err_with(|| format!("Could not find source path `{}`", source))?;
                     };
@@ -109,6 +137,114 @@ pub fn check_dataflow(
             .context("Could not resolve `send_stdout_as` configuration")?;
     }
 
+    for node in &nodes {
+        if let Some(sandbox) = &node.sandbox {
+            check_sandbox(sandbox, node, dataflow).with_context(|| {
+                format!("invalid `sandbox` configuration of node `{}`", node.id)
+            })?;
+        }
+        if let Some(wasm) = &node.wasm {
+            check_wasm(wasm, node)
+                .with_context(|| format!("invalid `wasm` configuration of node `{}`", node.id))?;
+        }
+        if let CoreNodeKind::Custom(custom) = &node.kind {
+            if let Some(sha256) = &custom.sha256 {
+                check_sha256(sha256, &custom.source)
+                    .with_context(|| format!("invalid `sha256` of node `{}`", node.id))?;
+            }
+            check_targets(custom)
+                .with_context(|| format!("invalid `targets` of node `{}`", node.id))?;
+        }
+        if let (Some(user), CoreNodeKind::Custom(custom)) = (&node.deploy.user, &node.kind) {
+            if custom.source == DYNAMIC_SOURCE {
+                bail!(
+                    "node `{}` can't run as user `{user}`, dynamic nodes are not spawned by dora",
+                    node.id
+                );
+            }
+        }
+        if let (Some(signal), CoreNodeKind::Custom(custom)) = (&node.stop_signal, &node.kind) {
+            if custom.source == DYNAMIC_SOURCE {
+                bail!(
+                    "node `{}` can't be stopped through {signal}, dynamic nodes are not spawned by dora",
+                    node.id
+                );
+            }
+        }
+        check_forward_signals(node)
+            .with_context(|| format!("invalid `forward_signals` of node `{}`", node.id))?;
+        if let (Some(numa_node), CoreNodeKind::Custom(custom)) = (node.deploy.numa_node, &node.kind)
+        {
+            if custom.source == DYNAMIC_SOURCE {
+                bail!(
+                    "node `{}` can't be pinned to NUMA node {numa_node}, dynamic nodes are not spawned by dora",
+                    node.id
+                );
+            }
+        }
+        if let Some(scheduling) = &node.deploy.scheduling {
+            if let CoreNodeKind::Custom(custom) = &node.kind {
+                if custom.source == DYNAMIC_SOURCE {
+                    bail!(
+                        "node `{}` can't be scheduled through {}, dynamic nodes are not spawned by dora",
+                        node.id,
+                        scheduling.policy
+                    );
+                }
+            }
+            check_scheduling(scheduling)
+                .with_context(|| format!("invalid `scheduling` of node `{}`", node.id))?;
+        }
+        if let Some(kubernetes) = &node.deploy.kubernetes {
+            check_kubernetes(kubernetes, node)
+                .with_context(|| format!("invalid `kubernetes` config of node `{}`", node.id))?;
+        }
+        if let Some(container) = &node.container {
+            check_container(container, node).with_context(|| {
+                format!("invalid `container` configuration of node `{}`", node.id)
+            })?;
+        } else if let CoreNodeKind::Custom(custom) = &node.kind {
+            if custom.source == CONTAINER_SOURCE {
+                bail!(
+                    "node `{}` has `path: {CONTAINER_SOURCE}`, which requires a `container` field",
+                    node.id
+                );
+            }
+        }
+        if let Some(activate) = &node.activate {
+            check_activate(activate, node)
+                .with_context(|| format!("invalid `activate` command of node `{}`", node.id))?;
+        }
+        if let Some(restart) = &node.restart {
+            check_restart(restart, node)
+                .with_context(|| format!("invalid `restart` policy of node `{}`", node.id))?;
+        }
+        if let Some(resources) = &node.resources {
+            check_resources(resources, node)
+                .with_context(|| format!("invalid `resources` of node `{}`", node.id))?;
+        }
+    }
+
+    check_telemetry(&dataflow.telemetry, &nodes).context("invalid `telemetry` configuration")?;
+    check_time(&dataflow.time, &nodes).context("invalid `time` configuration")?;
+    check_event_queues(&dataflow.event_queues).context("invalid `event_queues` configuration")?;
+    if let Some(faults) = &dataflow.faults {
+        check_faults(faults, &nodes).context("invalid `faults` configuration")?;
+    }
+    if let Some(replay) = &dataflow.replay {
+        check_replay(replay, &nodes, working_dir, remote_daemon_id.is_none())
+            .context("invalid `replay` configuration")?;
+    }
+    // the checkpoint is read by the daemons, so it can only be checked locally
+    if let (Some(restore), None) = (&dataflow.restore, remote_daemon_id) {
+        if !working_dir.join(restore).is_dir() {
+            bail!(
+                "invalid `restore` configuration: no checkpoint found at `{}`",
+                working_dir.join(restore).display()
+            );
+        }
+    }
+
     if has_python_operator {
         check_python_runtime()?;
     }
@@ -152,9 +288,32 @@ fn check_input(
     nodes: &[super::ResolvedNode],
     input_id_str: &str,
 ) -> Result<(), eyre::ErrReport> {
+    if input.queue_size.is_some() && input.queue_policy != QueuePolicy::DropOldest {
+        bail!(
+            "input `{input_id_str}` has a `queue_size`, which is only supported \
+            with `queue_policy: drop_oldest`"
+        );
+    }
     match &input.mapping {
-        InputMapping::Timer { interval: _ } => {}
+        InputMapping::Timer { interval: _ } => {
+            if let Some(throttle) = &input.throttle {
+                check_throttle(throttle)
+                    .with_context(|| format!("invalid `throttle` of input `{input_id_str}`"))?;
+            }
+        }
+        InputMapping::Status => {
+            if input.throttle.is_some() {
+                bail!(
+                    "input `{input_id_str}` has a `throttle`, which is only supported for timers"
+                );
+            }
+        }
         InputMapping::User(UserInputMapping { source, output }) => {
+            if input.throttle.is_some() {
+                bail!(
+                    "input `{input_id_str}` has a `throttle`, which is only supported for timers"
+                );
+            }
             let source_node = nodes.iter().find(|n| &n.id == source).ok_or_else(|| {
                 eyre!("source node `{source}` mapped to input `{input_id_str}` does not exist",)
             })?;
@@ -196,6 +355,334 @@ fn check_input(
     Ok(())
 }
 
+fn check_throttle(throttle: &TimerThrottle) -> eyre::Result<()> {
+    if throttle.max_queued_inputs.is_none() && throttle.max_latency_ms.is_none() {
+        bail!("at least one of `max_queued_inputs` and `max_latency_ms` must be set");
+    }
+    if throttle.max_factor == Some(0) {
+        bail!("`max_factor` must be at least 1");
+    }
+    Ok(())
+}
+
+fn check_sandbox(
+    sandbox: &SandboxConfig,
+    node: &ResolvedNode,
+    dataflow: &Descriptor,
+) -> eyre::Result<()> {
+    if let CoreNodeKind::Custom(custom) = &node.kind {
+        if custom.source == DYNAMIC_SOURCE {
+            bail!("dynamic nodes are not spawned by dora, so they can't be sandboxed");
+        }
+    }
+    if !sandbox.network && dataflow.communication.local == LocalCommunicationConfig::Tcp {
+        bail!(
+            "nodes without network access can't connect to the daemon through TCP, \
+            use `_unstable_local: Shmem` or `UnixDomain` communication"
+        );
+    }
+    Ok(())
+}
+
+fn check_wasm(wasm: &WasmConfig, node: &ResolvedNode) -> eyre::Result<()> {
+    match &node.kind {
+        CoreNodeKind::Custom(custom) if custom.source.ends_with(".wasm") => {}
+        _ => bail!("only nodes with a `.wasm` source run in the WASM runtime"),
+    }
+    for dir in wasm.readable.iter().chain(&wasm.writable) {
+        if !dir.is_relative() {
+            bail!(
+                "directory `{}` must be relative to the working directory",
+                dir.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn check_kubernetes(kubernetes: &KubernetesConfig, node: &ResolvedNode) -> eyre::Result<()> {
+    if let CoreNodeKind::Custom(custom) = &node.kind {
+        if custom.source == DYNAMIC_SOURCE {
+            bail!("dynamic nodes are not spawned by dora, so they can't run in Kubernetes");
+        }
+    }
+    if kubernetes.image.trim().is_empty() {
+        bail!("`image` must not be empty");
+    }
+    if node.container.is_some() {
+        bail!("nodes in Kubernetes already run in a container, use the `image` of the pod instead");
+    }
+    Ok(())
+}
+
+fn check_activate(activate: &str, node: &ResolvedNode) -> eyre::Result<()> {
+    if activate.trim().is_empty() {
+        bail!("`activate` must not be empty");
+    }
+    if node.container.is_some() {
+        bail!("container nodes run in the environment of their image");
+    }
+    if let CoreNodeKind::Custom(custom) = &node.kind {
+        if custom.source == DYNAMIC_SOURCE {
+            bail!("dynamic nodes are not spawned by dora, so their environment can't be activated");
+        }
+    }
+    Ok(())
+}
+
+fn check_restart(restart: &RestartConfig, node: &ResolvedNode) -> eyre::Result<()> {
+    if let CoreNodeKind::Custom(custom) = &node.kind {
+        if custom.source == DYNAMIC_SOURCE {
+            bail!("dynamic nodes are not spawned by dora, so they can't be restarted");
+        }
+    }
+    if restart.backoff_ms > restart.max_backoff_ms {
+        bail!(
+            "`backoff_ms` ({}) must not be larger than `max_backoff_ms` ({})",
+            restart.backoff_ms,
+            restart.max_backoff_ms
+        );
+    }
+    Ok(())
+}
+
+fn check_resources(resources: &ResourceLimits, node: &ResolvedNode) -> eyre::Result<()> {
+    if let CoreNodeKind::Custom(custom) = &node.kind {
+        if custom.source == DYNAMIC_SOURCE {
+            bail!("dynamic nodes are not spawned by dora, so their resources can't be limited");
+        }
+    }
+    if node.container.is_some() {
+        bail!("the resources of container nodes are limited through the `args` of the container engine, e.g. `--memory 512m`");
+    }
+    if resources.memory_mb == Some(0) {
+        bail!("`memory_mb` must be larger than zero");
+    }
+    if let Some(cpus) = resources.cpus {
+        if !(cpus.is_finite() && cpus > 0.0) {
+            bail!("`cpus` must be a positive number, got {cpus}");
+        }
+    }
+    Ok(())
+}
+
+fn check_container(container: &ContainerConfig, node: &ResolvedNode) -> eyre::Result<()> {
+    let CoreNodeKind::Custom(custom) = &node.kind else {
+        bail!("only nodes with a `path` can run in a container");
+    };
+    if custom.source == DYNAMIC_SOURCE {
+        bail!("dynamic nodes are not spawned by dora, so they can't run in a container");
+    }
+    if custom.sha256.is_some() {
+        bail!("the checksum of container nodes can't be verified, pin the image digest instead");
+    }
+    if container.image.trim().is_empty() {
+        bail!("`image` must not be empty");
+    }
+    // these would only apply to the client of the container engine
+    if node.sandbox.is_some() {
+        bail!("container nodes can't be sandboxed, they are isolated by the container engine");
+    }
+    if node.deploy.numa_node.is_some() || node.deploy.scheduling.is_some() {
+        bail!(
+            "NUMA pinning and scheduling are not supported for container nodes, \
+            pass the corresponding options of the container engine in `args` instead"
+        );
+    }
+    for mount in &container.mounts {
+        if !mount.contains(':') {
+            bail!("mount `{mount}` must have the form `host:container[:options]`");
+        }
+    }
+    Ok(())
+}
+
+fn check_sha256(sha256: &str, source: &str) -> eyre::Result<()> {
+    if source == DYNAMIC_SOURCE || source == SHELL_SOURCE {
+        bail!("the checksum of `{source}` nodes can't be verified");
+    }
+    if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("expected 64 hexadecimal digits, got `{sha256}`");
+    }
+    Ok(())
+}
+
+fn check_targets(custom: &CustomNode) -> eyre::Result<()> {
+    if custom.targets.is_empty() {
+        return Ok(());
+    }
+    if custom.source == DYNAMIC_SOURCE || custom.source == SHELL_SOURCE {
+        bail!("`{}` nodes can't have alternative sources", custom.source);
+    }
+    for (key, target) in &custom.targets {
+        if key.is_empty() {
+            bail!("target keys must not be empty");
+        }
+        if let Some(sha256) = &target.sha256 {
+            let source = target.path.as_ref().unwrap_or(&custom.source);
+            check_sha256(sha256, source)
+                .with_context(|| format!("invalid `sha256` of target `{key}`"))?;
+        }
+    }
+    Ok(())
+}
+
+fn check_scheduling(scheduling: &SchedulingConfig) -> eyre::Result<()> {
+    match (scheduling.policy.is_real_time(), scheduling.priority) {
+        (true, None) => bail!("{} requires a `priority`", scheduling.policy),
+        (true, Some(priority)) if !(1..=99).contains(&priority) => {
+            bail!("priority must be between 1 and 99, got {priority}")
+        }
+        (false, Some(_)) => bail!(
+            "`priority` is only supported for the `fifo` and `rr` policies, not for {}",
+            scheduling.policy
+        ),
+        _ => Ok(()),
+    }
+}
+
+fn check_telemetry(telemetry: &TelemetryConfig, nodes: &[ResolvedNode]) -> eyre::Result<()> {
+    let check_rate = |rate: f64, name: &str| {
+        if !(0.0..=1.0).contains(&rate) {
+            bail!("sample rate of {name} must be between 0 and 1, got {rate}");
+        }
+        Ok(())
+    };
+    if let Some(rate) = telemetry.sample_rate {
+        check_rate(rate, "the dataflow")?;
+    }
+    for (output, &rate) in &telemetry.edges {
+        check_rate(rate, &format!("`{output}`"))?;
+        let Some((node_id, _)) = output.split_once('/') else {
+            bail!("edge `{output}` must have the form `<node_id>/<output_id>`");
+        };
+        if !nodes.iter().any(|node| node.id.as_ref() == node_id) {
+            bail!("node `{node_id}` of edge `{output}` does not exist");
+        }
+    }
+    Ok(())
+}
+
+fn check_forward_signals(node: &ResolvedNode) -> eyre::Result<()> {
+    for signal in &node.forward_signals {
+        match signal {
+            StopSignal::Hangup | StopSignal::User1 | StopSignal::User2 => {}
+            StopSignal::Interrupt | StopSignal::Terminate | StopSignal::Quit => {
+                bail!("{signal} can't be forwarded, only SIGHUP, SIGUSR1, and SIGUSR2")
+            }
+        }
+    }
+    if let (Some(signal), CoreNodeKind::Custom(custom)) = (node.forward_signals.first(), &node.kind)
+    {
+        if custom.source == DYNAMIC_SOURCE {
+            bail!("{signal} can't be forwarded to dynamic nodes, they are not spawned by dora");
+        }
+    }
+    Ok(())
+}
+
+fn check_event_queues(config: &EventQueueConfig) -> eyre::Result<()> {
+    if config.capacity() == 0 {
+        bail!("`capacity` must be at least 1");
+    }
+    Ok(())
+}
+
+fn check_time(time: &TimeConfig, nodes: &[ResolvedNode]) -> eyre::Result<()> {
+    if time.mode == TimeMode::Real {
+        if time.rate.is_some() || time.advanced_by.is_some() {
+            bail!("`rate` and `advanced_by` require `mode: simulated`");
+        }
+        return Ok(());
+    }
+    if !(time.rate() > 0.0 && time.rate().is_finite()) {
+        bail!("rate must be a positive number, got {}", time.rate());
+    }
+    if let Some(output) = &time.advanced_by {
+        let Some((node_id, output_id)) = output.split_once('/') else {
+            bail!("`advanced_by` must have the form `<node_id>/<output_id>`, got `{output}`");
+        };
+        let Some(node) = nodes.iter().find(|node| node.id.as_ref() == node_id) else {
+            bail!("node `{node_id}` of `advanced_by` does not exist");
+        };
+        let has_output = match &node.kind {
+            CoreNodeKind::Custom(custom) => custom
+                .run_config
+                .outputs
+                .iter()
+                .any(|o| o.as_str() == output_id),
+            // operator outputs are prefixed with the operator ID
+            CoreNodeKind::Runtime(_) => true,
+        };
+        if !has_output {
+            bail!("node `{node_id}` has no output `{output_id}`");
+        }
+    }
+    Ok(())
+}
+
+fn check_faults(faults: &FaultConfig, nodes: &[ResolvedNode]) -> eyre::Result<()> {
+    let check_node = |node_id: &str, context: &str| {
+        if !nodes.iter().any(|node| node.id.as_ref() == node_id) {
+            bail!("node `{node_id}` of {context} does not exist");
+        }
+        Ok(())
+    };
+    for (edge, edge_faults) in &faults.edges {
+        let Some(((node_id, _), input)) = FaultConfig::parse_edge(edge) else {
+            bail!(
+                "edge `{edge}` must have the form `<node_id>/<output_id>` or \
+                `<node_id>/<output_id> -> <receiver_id>/<input_id>`"
+            );
+        };
+        check_node(node_id, &format!("edge `{edge}`"))?;
+        if let Some((receiver_id, _)) = input {
+            check_node(receiver_id, &format!("edge `{edge}`"))?;
+        }
+
+        let probabilities = [
+            ("drop", edge_faults.drop),
+            ("duplicate", edge_faults.duplicate),
+            ("reorder", edge_faults.reorder),
+            ("delay", edge_faults.delay),
+        ];
+        for (name, probability) in probabilities {
+            if !(0.0..=1.0).contains(&probability) {
+                bail!("`{name}` probability of edge `{edge}` must be between 0 and 1");
+            }
+        }
+        let sum: f64 = probabilities.iter().map(|(_, p)| p).sum();
+        if sum > 1.0 {
+            bail!("fault probabilities of edge `{edge}` add up to more than 1");
+        }
+    }
+    for kill in &faults.kill {
+        check_node(kill.node.as_ref(), "`kill`")?;
+    }
+    Ok(())
+}
+
+fn check_replay(
+    replay: &ReplayConfig,
+    nodes: &[ResolvedNode],
+    working_dir: &Path,
+    check_recording: bool,
+) -> eyre::Result<()> {
+    for node_id in &replay.nodes {
+        if !nodes.iter().any(|node| &node.id == node_id) {
+            bail!("replayed node `{node_id}` does not exist");
+        }
+    }
+    // the recording is read by the daemons, so it can only be checked locally
+    if check_recording && !working_dir.join(&replay.recording).is_dir() {
+        bail!(
+            "no recording found at `{}`",
+            working_dir.join(&replay.recording).display()
+        );
+    }
+    Ok(())
+}
+
 fn check_python_runtime() -> eyre::Result<()> {
     // Check if python dora-rs is installed and match cli version
     let reinstall_command =
@@ -62,7 +62,7 @@ fn collect_dora_nodes(
 ) {
     for input in values {
         match &input.mapping {
-            InputMapping::User(_) => {}
+            InputMapping::User(_) | InputMapping::Status => {}
             InputMapping::Timer { interval } => {
                 dora_timers.insert(*interval);
             }
@@ -173,7 +173,7 @@ fn visualize_inputs(
 ) {
     for (input_id, input) in inputs {
         match &input.mapping {
-            mapping @ InputMapping::Timer { .. } => {
+            mapping @ (InputMapping::Timer { .. } | InputMapping::Status) => {
                 writeln!(flowchart, "  {} -- {input_id} --> {target}", mapping).unwrap();
             }
             InputMapping::User(mapping) => {
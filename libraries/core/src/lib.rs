@@ -9,6 +9,7 @@ pub use dora_message::{config, uhlc};
 
 pub mod descriptor;
 pub mod metadata;
+pub mod socket_activation;
 pub mod topics;
 
 pub fn adjust_shared_library_path(path: &Path) -> Result<std::path::PathBuf, eyre::ErrReport> {
@@ -0,0 +1,85 @@
+//! Listener sockets passed by systemd socket units.
+//!
+//! When started through a `.socket` unit, systemd binds the listening ports
+//! itself and passes them to the service as file descriptors, starting at
+//! `3`. This allows starting the coordinator or daemon on the first
+//! connection and restarting them without a gap in which the port is not
+//! listening. The sockets are matched by their `FileDescriptorName`, e.g.:
+//!
+//! ```ini
+//! [Socket]
+//! ListenStream=6012
+//! FileDescriptorName=dora-control
+//! ```
+//!
+//! A single socket without a name is used for any of the listeners.
+
+use std::net::TcpListener;
+
+/// Socket name of the coordinator control listener.
+pub const CONTROL_SOCKET_NAME: &str = "dora-control";
+/// Socket name of the daemon listener for dynamic nodes.
+pub const DAEMON_LOCAL_SOCKET_NAME: &str = "dora-daemon-local";
+
+/// Takes the listener with the given name that was passed by systemd, if any.
+///
+/// Each passed socket can only be taken once.
+#[cfg(unix)]
+pub fn take_listener(name: &str) -> eyre::Result<Option<TcpListener>> {
+    use eyre::Context;
+    use std::{
+        collections::BTreeSet,
+        os::fd::{FromRawFd, RawFd},
+        sync::Mutex,
+    };
+
+    const LISTEN_FDS_START: RawFd = 3;
+    static TAKEN: Mutex<BTreeSet<RawFd>> = Mutex::new(BTreeSet::new());
+
+    let Ok(pid) = std::env::var("LISTEN_PID") else {
+        return Ok(None);
+    };
+    if pid.parse::<u32>().ok() != Some(std::process::id()) {
+        // the sockets are meant for another process, e.g. our parent
+        return Ok(None);
+    }
+    let count: RawFd = std::env::var("LISTEN_FDS")
+        .context("`LISTEN_PID` is set without `LISTEN_FDS`")?
+        .parse()
+        .context("invalid `LISTEN_FDS`")?;
+    let names = std::env::var("LISTEN_FDNAMES").unwrap_or_default();
+    let names: Vec<_> = names.split(':').collect();
+
+    let fd = (0..count).map(|i| LISTEN_FDS_START + i).find(|&fd| {
+        let index = (fd - LISTEN_FDS_START) as usize;
+        match names.get(index) {
+            Some(&n) if !n.is_empty() && n != "unknown" => n == name,
+            _ => count == 1,
+        }
+    });
+    let Some(fd) = fd else {
+        return Ok(None);
+    };
+    if !TAKEN.lock().unwrap().insert(fd) {
+        return Ok(None);
+    }
+
+    // SAFETY: systemd passes ownership of the file descriptor to this
+    // process and `TAKEN` ensures that it is only taken once
+    let passed = unsafe { TcpListener::from_raw_fd(fd) };
+    // the passed file descriptor is not close-on-exec, which would leak it
+    // into the spawned nodes, so replace it with a close-on-exec duplicate
+    let listener = passed
+        .try_clone()
+        .wrap_err_with(|| format!("failed to duplicate socket `{name}` passed by systemd"))?;
+    drop(passed);
+    listener
+        .set_nonblocking(true)
+        .wrap_err_with(|| format!("failed to configure socket `{name}` passed by systemd"))?;
+    Ok(Some(listener))
+}
+
+#[cfg(not(unix))]
+pub fn take_listener(_name: &str) -> eyre::Result<Option<TcpListener>> {
+    Ok(None)
+}
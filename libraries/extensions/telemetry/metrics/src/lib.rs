@@ -17,6 +17,9 @@ use opentelemetry::metrics::{self, MeterProvider as _};
 use opentelemetry_otlp::{ExportConfig, WithExportConfig};
 use opentelemetry_sdk::{metrics::SdkMeterProvider, runtime};
 use opentelemetry_system_metrics::init_process_observer;
+
+pub mod prometheus;
+
 /// Init opentelemetry meter
 ///
 /// Use the default Opentelemetry exporter with default config
@@ -0,0 +1,280 @@
+//! Formatting of dataflow health reports in the [Prometheus text exposition
+//! format], e.g. for scraping them through a textfile collector.
+//!
+//! [Prometheus text exposition format]: https://prometheus.io/docs/instrumenting/exposition_formats/
+
+use std::fmt::Write as _;
+
+use dora_message::{common::HealthStatus, coordinator_to_cli::DataflowHealth};
+
+/// Formats the health summary in the Prometheus text exposition format.
+pub fn format_health(health: &DataflowHealth) -> String {
+    let dataflow = health.id.uuid;
+    let mut out = String::new();
+
+    let _ = writeln!(
+        out,
+        "# HELP dora_node_health_status Health status reported by the node (0 = ok, 1 = degraded, 2 = error)."
+    );
+    let _ = writeln!(out, "# TYPE dora_node_health_status gauge");
+    for (node_id, node_health) in &health.nodes {
+        let Some(node_health) = node_health else {
+            continue;
+        };
+        let value = match node_health.status {
+            HealthStatus::Ok => 0,
+            HealthStatus::Degraded => 1,
+            HealthStatus::Error => 2,
+        };
+        let _ = writeln!(
+            out,
+            "dora_node_health_status{{dataflow=\"{dataflow}\",node=\"{node_id}\"}} {value}"
+        );
+    }
+
+    let _ = writeln!(out, "# TYPE dora_node_counter counter");
+    for (node_id, node_health) in health
+        .nodes
+        .iter()
+        .filter_map(|(id, h)| Some((id, h.as_ref()?)))
+    {
+        for (name, value) in &node_health.counters {
+            let name = escape_label(name);
+            let _ = writeln!(
+                out,
+                "dora_node_counter{{dataflow=\"{dataflow}\",node=\"{node_id}\",name=\"{name}\"}} {value}"
+            );
+        }
+    }
+
+    let _ = writeln!(out, "# TYPE dora_node_gauge gauge");
+    for (node_id, node_health) in health
+        .nodes
+        .iter()
+        .filter_map(|(id, h)| Some((id, h.as_ref()?)))
+    {
+        for (name, value) in &node_health.gauges {
+            let name = escape_label(name);
+            let _ = writeln!(
+                out,
+                "dora_node_gauge{{dataflow=\"{dataflow}\",node=\"{node_id}\",name=\"{name}\"}} {value}"
+            );
+        }
+    }
+
+    let _ = writeln!(
+        out,
+        "# HELP dora_node_queued_inputs Number of inputs that the daemon queued for the node."
+    );
+    let _ = writeln!(out, "# TYPE dora_node_queued_inputs gauge");
+    for metrics in health.queues.values() {
+        for (node_id, queued) in &metrics.queued_inputs {
+            let _ = writeln!(
+                out,
+                "dora_node_queued_inputs{{dataflow=\"{dataflow}\",node=\"{node_id}\"}} {queued}"
+            );
+        }
+    }
+
+    let _ = writeln!(
+        out,
+        "# HELP dora_input_dropped_messages_total Number of messages of the input that were dropped because of its queue policy or a full event queue."
+    );
+    let _ = writeln!(out, "# TYPE dora_input_dropped_messages_total counter");
+    for metrics in health.queues.values() {
+        for (node_id, inputs) in &metrics.dropped_inputs {
+            for (input_id, dropped) in inputs {
+                let _ = writeln!(
+                    out,
+                    "dora_input_dropped_messages_total{{dataflow=\"{dataflow}\",node=\"{node_id}\",input=\"{input_id}\"}} {dropped}"
+                );
+            }
+        }
+    }
+
+    let _ = writeln!(
+        out,
+        "# HELP dora_in_flight_shared_memory_bytes Size of the shared memory that was sent, but not released by all receivers yet."
+    );
+    let _ = writeln!(out, "# TYPE dora_in_flight_shared_memory_bytes gauge");
+    for (machine_id, metrics) in &health.queues {
+        let machine = escape_label(machine_id);
+        let _ = writeln!(
+            out,
+            "dora_in_flight_shared_memory_bytes{{dataflow=\"{dataflow}\",machine=\"{machine}\"}} {}",
+            metrics.in_flight_shared_memory_bytes
+        );
+    }
+
+    let _ = writeln!(
+        out,
+        "# HELP dora_node_cpu_seconds_total CPU time of all processes of the node."
+    );
+    let _ = writeln!(out, "# TYPE dora_node_cpu_seconds_total counter");
+    for (node_id, usage) in &health.resources {
+        let _ = writeln!(
+            out,
+            "dora_node_cpu_seconds_total{{dataflow=\"{dataflow}\",node=\"{node_id}\"}} {}",
+            usage.cpu_time.as_secs_f64()
+        );
+    }
+
+    let _ = writeln!(
+        out,
+        "# HELP dora_node_memory_bytes Memory usage of all processes of the node."
+    );
+    let _ = writeln!(out, "# TYPE dora_node_memory_bytes gauge");
+    for (node_id, usage) in &health.resources {
+        if let Some(memory) = usage.memory_bytes {
+            let _ = writeln!(
+                out,
+                "dora_node_memory_bytes{{dataflow=\"{dataflow}\",node=\"{node_id}\"}} {memory}"
+            );
+        }
+    }
+
+    let _ = writeln!(
+        out,
+        "# HELP dora_node_io_bytes_total Bytes that all processes of the node read from or wrote to block devices."
+    );
+    let _ = writeln!(out, "# TYPE dora_node_io_bytes_total counter");
+    for (node_id, usage) in &health.resources {
+        for (direction, value) in [
+            ("read", usage.io_read_bytes),
+            ("write", usage.io_write_bytes),
+        ] {
+            if let Some(value) = value {
+                let _ = writeln!(
+                    out,
+                    "dora_node_io_bytes_total{{dataflow=\"{dataflow}\",node=\"{node_id}\",direction=\"{direction}\"}} {value}"
+                );
+            }
+        }
+    }
+
+    out
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, time::Duration};
+
+    use dora_message::{
+        common::{NodeHealth, NodeResourceUsage, QueueMetrics},
+        coordinator_to_cli::DataflowIdAndName,
+        id::{DataId, NodeId},
+    };
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn node(id: &str) -> NodeId {
+        NodeId::from(id.to_owned())
+    }
+
+    #[test]
+    fn format_health_report() {
+        let uuid = Uuid::nil();
+        let health = DataflowHealth {
+            id: DataflowIdAndName { uuid, name: None },
+            nodes: BTreeMap::from([
+                (
+                    node("camera"),
+                    Some(NodeHealth {
+                        status: HealthStatus::Degraded,
+                        message: Some("low frame rate".to_owned()),
+                        counters: BTreeMap::from([("frames".to_owned(), 42)]),
+                        gauges: BTreeMap::from([("temp \"C\"".to_owned(), 36.5)]),
+                    }),
+                ),
+                (node("sink"), None),
+            ]),
+            queues: BTreeMap::from([(
+                "a".to_owned(),
+                QueueMetrics {
+                    queued_inputs: BTreeMap::from([(node("sink"), 3)]),
+                    in_flight_shared_memory_bytes: 4096,
+                    dropped_inputs: BTreeMap::from([(
+                        node("sink"),
+                        BTreeMap::from([(DataId::from("image".to_owned()), 7)]),
+                    )]),
+                },
+            )]),
+            resources: BTreeMap::from([(
+                node("camera"),
+                NodeResourceUsage {
+                    cpu_time: Duration::from_millis(1500),
+                    cpu_percent: 10.0,
+                    memory_bytes: Some(1024),
+                    io_read_bytes: Some(1),
+                    io_write_bytes: None,
+                },
+            )]),
+        };
+
+        let labels = format!("dataflow=\"{uuid}\"");
+        let samples: Vec<_> = format_health(&health)
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .map(|line| line.replace(&labels, "…"))
+            .collect();
+        assert_eq!(
+            samples,
+            [
+                r#"dora_node_health_status{…,node="camera"} 1"#,
+                r#"dora_node_counter{…,node="camera",name="frames"} 42"#,
+                r#"dora_node_gauge{…,node="camera",name="temp \"C\""} 36.5"#,
+                r#"dora_node_queued_inputs{…,node="sink"} 3"#,
+                r#"dora_input_dropped_messages_total{…,node="sink",input="image"} 7"#,
+                r#"dora_in_flight_shared_memory_bytes{…,machine="a"} 4096"#,
+                r#"dora_node_cpu_seconds_total{…,node="camera"} 1.5"#,
+                r#"dora_node_memory_bytes{…,node="camera"} 1024"#,
+                r#"dora_node_io_bytes_total{…,node="camera",direction="read"} 1"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn every_metric_has_a_type() {
+        let health = DataflowHealth {
+            id: DataflowIdAndName {
+                uuid: Uuid::nil(),
+                name: None,
+            },
+            nodes: BTreeMap::new(),
+            queues: BTreeMap::new(),
+            resources: BTreeMap::new(),
+        };
+        let types: Vec<_> = format_health(&health)
+            .lines()
+            .filter_map(|line| line.strip_prefix("# TYPE "))
+            .map(|line| line.to_owned())
+            .collect();
+        assert_eq!(
+            types,
+            [
+                "dora_node_health_status gauge",
+                "dora_node_counter counter",
+                "dora_node_gauge gauge",
+                "dora_node_queued_inputs gauge",
+                "dora_input_dropped_messages_total counter",
+                "dora_in_flight_shared_memory_bytes gauge",
+                "dora_node_cpu_seconds_total counter",
+                "dora_node_memory_bytes gauge",
+                "dora_node_io_bytes_total counter",
+            ]
+        );
+    }
+
+    #[test]
+    fn escape_label_values() {
+        assert_eq!(escape_label("a\\b\"c\nd"), r#"a\\b\"c\nd"#);
+    }
+}
@@ -3,18 +3,21 @@
 //! This module init a tracing propagator for Rust code that requires tracing, and is
 //! able to serialize and deserialize context that has been sent via the middleware.
 
-use std::path::Path;
+use std::{collections::BTreeMap, path::Path, sync::Mutex};
 
 use eyre::Context as EyreContext;
 use tracing::metadata::LevelFilter;
 use tracing_subscriber::{
-    filter::FilterExt, prelude::__tracing_subscriber_SubscriberExt, EnvFilter, Layer,
+    filter::FilterExt, prelude::__tracing_subscriber_SubscriberExt, reload, EnvFilter, Layer,
 };
 
 use eyre::ContextCompat;
 use tracing_subscriber::Registry;
 pub mod telemetry;
 
+/// Handles to the per-node filters of all layers, see [`set_node_log_levels`].
+static NODE_FILTERS: Mutex<Vec<reload::Handle<EnvFilter, Registry>>> = Mutex::new(Vec::new());
+
 pub fn set_up_tracing(name: &str) -> eyre::Result<()> {
     set_up_tracing_opts(name, Some(LevelFilter::WARN), None)
 }
@@ -33,7 +36,7 @@ pub fn set_up_tracing_opts(
 
     if let Some(level) = stdout {
         // Filter log using `RUST_LOG`. More useful for CLI.
-        let env_filter = EnvFilter::from_default_env().or(level);
+        let env_filter = EnvFilter::from_default_env().or(level).or(node_filter());
         let layer = tracing_subscriber::fmt::layer()
             .compact()
             .with_filter(env_filter);
@@ -54,7 +57,7 @@ pub fn set_up_tracing_opts(
         let layer = tracing_subscriber::fmt::layer()
             .with_ansi(false)
             .with_writer(file)
-            .with_filter(filter);
+            .with_filter(filter.or(node_filter()));
         layers.push(layer.boxed());
     }
 
@@ -73,3 +76,38 @@ pub fn set_up_tracing_opts(
         "failed to set tracing global subscriber for {name}"
     ))
 }
+
+/// Enables the events in the spans of individual nodes up to the given levels,
+/// in addition to the global filter.
+///
+/// The keys are `(dataflow_id, node_id)` pairs, which are matched against the
+/// `dataflow_id` and `node_id` fields of spans. Replaces the previously set
+/// levels.
+pub fn set_node_log_levels(levels: &BTreeMap<(String, String), LevelFilter>) -> eyre::Result<()> {
+    let directives = levels
+        .iter()
+        .map(|((dataflow_id, node_id), level)| {
+            format!("[{{dataflow_id={dataflow_id},node_id={node_id}}}]={level}")
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    let directives = if directives.is_empty() {
+        "off".to_owned()
+    } else {
+        directives
+    };
+    for handle in NODE_FILTERS.lock().unwrap().iter() {
+        let filter = EnvFilter::try_new(&directives)
+            .wrap_err_with(|| format!("invalid node log levels `{directives}`"))?;
+        handle
+            .reload(filter)
+            .context("failed to update node log levels")?;
+    }
+    Ok(())
+}
+
+fn node_filter() -> reload::Layer<EnvFilter, Registry> {
+    let (filter, handle) = reload::Layer::new(EnvFilter::new("off"));
+    NODE_FILTERS.lock().unwrap().push(handle);
+    filter
+}
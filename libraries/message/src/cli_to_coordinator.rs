@@ -3,10 +3,12 @@ use std::{path::PathBuf, time::Duration};
 use uuid::Uuid;
 
 use crate::{
-    descriptor::Descriptor,
+    descriptor::{Descriptor, SignedDescriptor},
     id::{NodeId, OperatorId},
 };
 
+pub use crate::common::{ArtifactId, ArtifactStatus, RecordingOptions, ReplayControl};
+
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub enum ControlRequest {
     Start {
@@ -15,6 +17,9 @@ pub enum ControlRequest {
         // TODO: remove this once we figure out deploying of node/operator
         // binaries from CLI to coordinator/daemon
         local_working_dir: PathBuf,
+        /// Signed content of the descriptor file, if it is signed
+        #[serde(default)]
+        signed: Option<SignedDescriptor>,
     },
     Reload {
         dataflow_id: Uuid,
@@ -39,10 +44,121 @@ pub enum ControlRequest {
     },
     Destroy,
     List,
+    Health {
+        dataflow_uuid: Uuid,
+    },
+    EdgeLatencies {
+        dataflow_uuid: Uuid,
+    },
+    LiveGraph {
+        dataflow_uuid: Uuid,
+    },
+    DumpDaemonStates,
+    CrashReports {
+        uuid: Option<Uuid>,
+        name: Option<String>,
+        node: Option<NodeId>,
+    },
+    StartRecording {
+        dataflow_uuid: Uuid,
+        options: RecordingOptions,
+    },
+    StopRecording {
+        dataflow_uuid: Uuid,
+    },
+    Checkpoint {
+        dataflow_uuid: Uuid,
+    },
+    ControlReplay {
+        dataflow_uuid: Uuid,
+        control: ReplayControl,
+    },
     DaemonConnected,
     ConnectedMachines,
     LogSubscribe {
         dataflow_id: Uuid,
         level: log::LevelFilter,
     },
+    /// Query how much of the artifact the daemon of the machine already has.
+    ArtifactStatus {
+        machine_id: String,
+        artifact: ArtifactId,
+    },
+    /// Upload a part of an artifact to the daemon of the machine, starting at
+    /// `offset`.
+    ///
+    /// Replies with the new [`ArtifactStatus`]. If the offset doesn't match
+    /// the bytes that the daemon has received, the data is ignored and the
+    /// upload should resume at the reported offset.
+    UploadArtifact {
+        machine_id: String,
+        artifact: ArtifactId,
+        offset: u64,
+        #[serde(with = "crate::common::artifact_data")]
+        data: Vec<u8>,
+        /// Whether this is the last part of the file.
+        last: bool,
+    },
+    /// Authenticates the connection with an access token.
+    ///
+    /// Required before any other request if the coordinator has access
+    /// control enabled.
+    Authenticate {
+        token: String,
+    },
+}
+
+impl ControlRequest {
+    /// Minimum role that is allowed to send this request.
+    pub fn required_role(&self) -> Role {
+        match self {
+            ControlRequest::Check { .. }
+            | ControlRequest::Logs { .. }
+            | ControlRequest::List
+            | ControlRequest::Health { .. }
+            | ControlRequest::EdgeLatencies { .. }
+            | ControlRequest::LiveGraph { .. }
+            | ControlRequest::CrashReports { .. }
+            | ControlRequest::DaemonConnected
+            | ControlRequest::ConnectedMachines
+            | ControlRequest::LogSubscribe { .. }
+            | ControlRequest::Authenticate { .. } => Role::Viewer,
+            ControlRequest::Start { .. }
+            | ControlRequest::Reload { .. }
+            | ControlRequest::Stop { .. }
+            | ControlRequest::StopByName { .. }
+            | ControlRequest::DumpDaemonStates
+            | ControlRequest::StartRecording { .. }
+            | ControlRequest::StopRecording { .. }
+            | ControlRequest::Checkpoint { .. }
+            | ControlRequest::ControlReplay { .. }
+            | ControlRequest::ArtifactStatus { .. }
+            | ControlRequest::UploadArtifact { .. } => Role::Operator,
+            ControlRequest::Destroy => Role::Admin,
+        }
+    }
+}
+
+/// Access level of a control client, each role includes the previous ones.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize, serde::Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Can list dataflows and read their logs and metrics.
+    Viewer,
+    /// Can start, stop, and control dataflows.
+    Operator,
+    /// Can destroy the coordinator and the connected daemons.
+    Admin,
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Role::Viewer => f.write_str("viewer"),
+            Role::Operator => f.write_str("operator"),
+            Role::Admin => f.write_str("admin"),
+        }
+    }
 }
@@ -1,10 +1,13 @@
 use core::fmt;
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::BTreeMap, path::PathBuf, time::Duration};
 
 use aligned_vec::{AVec, ConstAlign};
 use uuid::Uuid;
 
-use crate::{id::NodeId, DataflowId};
+use crate::{
+    id::{DataId, NodeId},
+    DataflowId,
+};
 
 pub use log::Level as LogLevel;
 
@@ -19,6 +22,9 @@ pub struct LogMessage {
     pub file: Option<String>,
     pub line: Option<u32>,
     pub message: String,
+    /// Structured key-value fields attached to the log record.
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
@@ -26,6 +32,14 @@ pub struct NodeError {
     pub timestamp: uhlc::Timestamp,
     pub cause: NodeErrorCause,
     pub exit_status: NodeExitStatus,
+    /// File with the recent daemon events of the dataflow, written when the
+    /// node failed.
+    #[serde(default)]
+    pub flight_recording: Option<PathBuf>,
+    /// Crash report of the node, written when the node was terminated by a
+    /// signal.
+    #[serde(default)]
+    pub crash_report: Option<PathBuf>,
 }
 
 impl std::fmt::Display for NodeError {
@@ -35,24 +49,9 @@ impl std::fmt::Display for NodeError {
             NodeExitStatus::IoError(err) => write!(f, "I/O error while reading exit status: {err}"),
             NodeExitStatus::ExitCode(code) => write!(f, "exited with code {code}"),
             NodeExitStatus::Signal(signal) => {
-                let signal_str: Cow<_> = match signal {
-                    1 => "SIGHUP".into(),
-                    2 => "SIGINT".into(),
-                    3 => "SIGQUIT".into(),
-                    4 => "SIGILL".into(),
-                    6 => "SIGABRT".into(),
-                    8 => "SIGFPE".into(),
-                    9 => "SIGKILL".into(),
-                    11 => "SIGSEGV".into(),
-                    13 => "SIGPIPE".into(),
-                    14 => "SIGALRM".into(),
-                    15 => "SIGTERM".into(),
-                    22 => "SIGABRT".into(),
-                    23 => "NSIG".into(),
-                    other => other.to_string().into(),
-                };
+                let signal_str = signal_name(*signal);
                 if matches!(self.cause, NodeErrorCause::GraceDuration) {
-                    write!(f, "node was killed by dora because it didn't react to a stop message in time ({signal_str})")
+                    write!(f, "node was terminated by dora because it didn't exit within its grace duration after the stop ({signal_str})")
                 } else {
                     write!(f, "exited because of signal {signal_str}")
                 }
@@ -61,11 +60,19 @@ impl std::fmt::Display for NodeError {
         }?;
 
         match &self.cause {
-            NodeErrorCause::GraceDuration => {}, // handled above
+            NodeErrorCause::GraceDuration => {
+                if !matches!(self.exit_status, NodeExitStatus::Signal(_)) {
+                    write!(f, ". The node was sent SIGTERM because it didn't exit within its grace duration after the stop")?
+                }
+            }
             NodeErrorCause::Cascading { caused_by_node } => write!(
                 f,
                 ". This error occurred because node `{caused_by_node}` exited before connecting to dora."
             )?,
+            NodeErrorCause::MemoryLimitExceeded { limit_mb } => write!(
+                f,
+                ". Resource limit exceeded: the node used more than its memory limit of {limit_mb} MB"
+            )?,
             NodeErrorCause::Other { stderr } if stderr.is_empty() => {}
             NodeErrorCause::Other { stderr } => {
                 let line: &str = "---------------------------------------------------------------------------------\n";
@@ -73,18 +80,35 @@ impl std::fmt::Display for NodeError {
             },
         }
 
+        if let Some(path) = &self.flight_recording {
+            write!(
+                f,
+                "\nRecent daemon events were written to `{}`",
+                path.display()
+            )?;
+        }
+        if let Some(path) = &self.crash_report {
+            write!(f, "\nA crash report was written to `{}`", path.display())?;
+        }
+
         Ok(())
     }
 }
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub enum NodeErrorCause {
-    /// Node was killed because it didn't react to a stop message in time.
+    /// Node was sent `SIGTERM` and then killed because it didn't exit within
+    /// its grace duration after the dataflow was stopped.
     GraceDuration,
     /// Node failed because another node failed before,
     Cascading {
         caused_by_node: NodeId,
     },
+    /// Node was killed because it exceeded the memory limit of its
+    /// `resources` config.
+    MemoryLimitExceeded {
+        limit_mb: u64,
+    },
     Other {
         stderr: String,
     },
@@ -106,6 +130,10 @@ impl From<Result<std::process::ExitStatus, std::io::Error>> for NodeExitStatus {
                 if status.success() {
                     NodeExitStatus::Success
                 } else if let Some(code) = status.code() {
+                    #[cfg(windows)]
+                    if let Some(signal) = windows_exception_signal(code as u32) {
+                        return Self::Signal(signal);
+                    }
                     Self::ExitCode(code)
                 } else {
                     #[cfg(unix)]
@@ -123,6 +151,463 @@ impl From<Result<std::process::ExitStatus, std::io::Error>> for NodeExitStatus {
     }
 }
 
+/// Maps the `NTSTATUS` exit code of a crashed Windows process to the
+/// equivalent Unix signal, so that crashes are reported the same way on all
+/// platforms.
+#[cfg(windows)]
+fn windows_exception_signal(code: u32) -> Option<i32> {
+    match code {
+        // STATUS_CONTROL_C_EXIT
+        0xC000_013A => Some(2),
+        // STATUS_ILLEGAL_INSTRUCTION, STATUS_PRIVILEGED_INSTRUCTION
+        0xC000_001D | 0xC000_0096 => Some(4),
+        // STATUS_STACK_BUFFER_OVERRUN, raised by `abort` and Rust panics with
+        // `panic = "abort"` through `__fastfail`
+        0xC000_0409 => Some(6),
+        // STATUS_FLOAT_*, STATUS_INTEGER_DIVIDE_BY_ZERO, STATUS_INTEGER_OVERFLOW
+        0xC000_008C..=0xC000_0095 => Some(8),
+        // STATUS_ACCESS_VIOLATION, STATUS_IN_PAGE_ERROR, STATUS_STACK_OVERFLOW
+        0xC000_0005 | 0xC000_0006 | 0xC000_00FD => Some(11),
+        _ => None,
+    }
+}
+
+/// Returns the name of the given signal number, e.g. `SIGSEGV`.
+pub fn signal_name(signal: i32) -> Cow<'static, str> {
+    match signal {
+        1 => "SIGHUP".into(),
+        2 => "SIGINT".into(),
+        3 => "SIGQUIT".into(),
+        4 => "SIGILL".into(),
+        6 => "SIGABRT".into(),
+        8 => "SIGFPE".into(),
+        9 => "SIGKILL".into(),
+        11 => "SIGSEGV".into(),
+        13 => "SIGPIPE".into(),
+        14 => "SIGALRM".into(),
+        15 => "SIGTERM".into(),
+        22 => "SIGABRT".into(),
+        23 => "NSIG".into(),
+        other => other.to_string().into(),
+    }
+}
+
+/// Application-level health report of a node.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct NodeHealth {
+    pub status: HealthStatus,
+    pub message: Option<String>,
+    /// Monotonically increasing values, e.g. the number of processed frames.
+    pub counters: BTreeMap<String, u64>,
+    /// Values that can go up and down, e.g. a queue length or a temperature.
+    pub gauges: BTreeMap<String, f64>,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub enum HealthStatus {
+    #[default]
+    Ok,
+    Degraded,
+    Error,
+}
+
+impl fmt::Display for HealthStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HealthStatus::Ok => write!(f, "ok"),
+            HealthStatus::Degraded => write!(f, "degraded"),
+            HealthStatus::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Upper bounds of the buckets of a [`LatencyHistogram`], in microseconds.
+///
+/// Latencies above the last bound are counted in an additional overflow
+/// bucket.
+pub const LATENCY_BUCKET_BOUNDS_US: [u64; 16] = [
+    50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000,
+    1_000_000, 2_500_000, 5_000_000,
+];
+
+/// Distribution of the latencies of the messages sent over an edge.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct LatencyHistogram {
+    /// Number of latencies per bucket, see [`LATENCY_BUCKET_BOUNDS_US`].
+    pub buckets: Vec<u64>,
+    pub count: u64,
+    pub sum_us: u64,
+    pub max_us: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: vec![0; LATENCY_BUCKET_BOUNDS_US.len() + 1],
+            count: 0,
+            sum_us: 0,
+            max_us: 0,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: Duration) {
+        let us = latency.as_micros().try_into().unwrap_or(u64::MAX);
+        let bucket = LATENCY_BUCKET_BOUNDS_US.partition_point(|&bound| bound < us);
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.sum_us = self.sum_us.saturating_add(us);
+        self.max_us = self.max_us.max(us);
+    }
+
+    /// Adds the latencies of the given histogram to this one.
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        for (bucket, count) in self.buckets.iter_mut().zip(&other.buckets) {
+            *bucket += count;
+        }
+        self.count += other.count;
+        self.sum_us = self.sum_us.saturating_add(other.sum_us);
+        self.max_us = self.max_us.max(other.max_us);
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        let mean = self.sum_us.checked_div(self.count)?;
+        Some(Duration::from_micros(mean))
+    }
+
+    /// Upper estimate of the given quantile, i.e. the upper bound of the
+    /// bucket that contains it.
+    ///
+    /// For the overflow bucket, the maximum latency is returned.
+    pub fn quantile(&self, quantile: f64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((self.count as f64 * quantile).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let bound = LATENCY_BUCKET_BOUNDS_US
+                    .get(index)
+                    .map(|&bound| bound.min(self.max_us))
+                    .unwrap_or(self.max_us);
+                return Some(Duration::from_micros(bound));
+            }
+        }
+        Some(Duration::from_micros(self.max_us))
+    }
+}
+
+/// Latencies of the messages sent from an output to an input, measured from
+/// the send timestamp of the message until the daemon delivers it to the
+/// receiving node.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EdgeLatency {
+    pub source: NodeId,
+    pub output: DataId,
+    pub target: NodeId,
+    pub input: DataId,
+    pub histogram: LatencyHistogram,
+}
+
+/// State of a node, as seen by its daemon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeState {
+    /// The node was spawned, but did not subscribe to its events yet.
+    Starting,
+    Running,
+    Finished,
+    Failed,
+}
+
+/// Live statistics of the connection from an output to an input of a node.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EdgeStats {
+    pub source: NodeId,
+    pub output: DataId,
+    pub target: NodeId,
+    pub input: DataId,
+    /// Number of messages delivered over this edge.
+    pub messages: u64,
+    /// Recent message rate, in messages per second.
+    pub rate_hz: f64,
+    /// Time since the last message was delivered, or `None` if no message
+    /// was delivered yet.
+    pub last_message_age_ms: Option<u64>,
+}
+
+/// Live state of the nodes of a dataflow that run on a single daemon and of
+/// the edges to their inputs.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct DaemonLiveGraph {
+    pub nodes: BTreeMap<NodeId, NodeState>,
+    pub edges: Vec<EdgeStats>,
+}
+
+/// Queue metrics of a dataflow on a single daemon.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct QueueMetrics {
+    /// Number of inputs that were delivered to a local node, but not yet
+    /// received by it.
+    pub queued_inputs: BTreeMap<NodeId, u64>,
+    /// Size of the shared memory regions whose drop tokens were not released
+    /// yet, in bytes.
+    pub in_flight_shared_memory_bytes: u64,
+    /// Number of messages of each input of the local nodes that were dropped
+    /// because of its `queue_policy` or a full event queue, since the start
+    /// of the dataflow.
+    #[serde(default)]
+    pub dropped_inputs: BTreeMap<NodeId, BTreeMap<DataId, u64>>,
+}
+
+/// Resource usage of a running node, sampled from the cgroup that its daemon
+/// placed it in.
+///
+/// The numbers include all processes and threads of the node.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct NodeResourceUsage {
+    /// Total CPU time since the node was started.
+    pub cpu_time: Duration,
+    /// CPU usage since the previous sample, in percent of a single core.
+    pub cpu_percent: f64,
+    /// Current memory usage, if the memory controller is available.
+    pub memory_bytes: Option<u64>,
+    /// Total bytes read from block devices, if the IO controller is available.
+    pub io_read_bytes: Option<u64>,
+    /// Total bytes written to block devices, if the IO controller is
+    /// available.
+    pub io_write_bytes: Option<u64>,
+}
+
+/// Information about a node that was terminated by a signal, collected by
+/// its daemon.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CrashReport {
+    pub dataflow_id: DataflowId,
+    pub node_id: NodeId,
+    pub machine_id: String,
+    pub timestamp: uhlc::Timestamp,
+    pub exit_status: NodeExitStatus,
+    /// Whether the operating system reported that a core dump was written.
+    pub core_dumped: bool,
+    /// Location of the core dump, derived from the core pattern of the
+    /// system.
+    pub core_dump_location: Option<String>,
+    /// Resource usage of the node process, if available on this platform.
+    pub resource_usage: Option<ResourceUsage>,
+    /// Most recent lines that the node printed to stdout or stderr.
+    pub log_lines: Vec<String>,
+    /// Metadata of the most recent inputs that were delivered to the node.
+    pub recent_inputs: Vec<InputSummary>,
+    /// File with the recent daemon events of the dataflow.
+    pub flight_recording: Option<PathBuf>,
+}
+
+/// Node file that is distributed to the daemons, e.g. a node executable.
+///
+/// Daemons store artifacts in a cache that is addressed by their SHA-256
+/// checksum, so nodes that pin the checksum through their `sha256` field can
+/// be spawned on machines that don't have the file at the node `path`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ArtifactId {
+    /// SHA-256 checksum of the file content, as hex string.
+    pub sha256: String,
+    /// File name of the artifact, without directories.
+    ///
+    /// Kept because the extension decides how the node is run, e.g. `.py`.
+    pub file_name: String,
+}
+
+/// Serializes artifact data as base64 string.
+///
+/// The control messages are JSON encoded, which would otherwise store each
+/// byte as a separate number.
+pub(crate) mod artifact_data {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        STANDARD.decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+/// How much of an artifact a daemon has received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ArtifactStatus {
+    /// The artifact is cached and its checksum was verified.
+    Complete,
+    /// The first `received` bytes were received, the upload should resume
+    /// at this offset.
+    Partial { received: u64 },
+}
+
+/// Selects the recorded outputs and limits the size of a message recording.
+///
+/// Outputs are selected through glob patterns that are matched against
+/// `node/output`, where `*` matches any sequence of characters and `?`
+/// matches a single character. A pattern without `/` selects all outputs of
+/// the matching nodes.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct RecordingOptions {
+    /// Patterns of the recorded outputs. All outputs are recorded if empty.
+    pub include: Vec<String>,
+    /// Patterns of outputs that are not recorded, even if they are included.
+    pub exclude: Vec<String>,
+    /// Only every n-th message of the outputs that match the pattern is
+    /// recorded. The first matching pattern applies.
+    pub downsample: Vec<(String, u64)>,
+    /// Maximum size of the recording on each machine, in bytes.
+    ///
+    /// The oldest messages are deleted once the recording exceeds the size.
+    pub max_bytes: Option<u64>,
+    /// Only the messages of the given time span before the most recent
+    /// message are kept, older messages are deleted.
+    pub keep_last: Option<Duration>,
+}
+
+impl RecordingOptions {
+    pub fn is_recorded(&self, node_id: &str, output_id: &str) -> bool {
+        let matches = |pattern: &String| output_pattern_matches(pattern, node_id, output_id);
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+
+    /// Returns `n` if only every n-th message of the given output should be
+    /// recorded.
+    pub fn downsampling(&self, node_id: &str, output_id: &str) -> u64 {
+        self.downsample
+            .iter()
+            .find(|(pattern, _)| output_pattern_matches(pattern, node_id, output_id))
+            .map_or(1, |(_, n)| (*n).max(1))
+    }
+
+    /// All patterns of the options.
+    pub fn patterns(&self) -> impl Iterator<Item = &str> {
+        self.include
+            .iter()
+            .chain(&self.exclude)
+            .chain(self.downsample.iter().map(|(pattern, _)| pattern))
+            .map(|pattern| pattern.as_str())
+    }
+}
+
+/// Matches the given output against a pattern like `camera/*`, `*/image` or
+/// `camera`, which selects all outputs of the node.
+pub fn output_pattern_matches(pattern: &str, node_id: &str, output_id: &str) -> bool {
+    match pattern.split_once('/') {
+        Some((node, output)) => glob_match(node, node_id) && glob_match(output, output_id),
+        None => glob_match(pattern, node_id),
+    }
+}
+
+/// Matches the given text against a glob pattern with `*` and `?` wildcards.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // position of the last `*` and the text position it was tried at
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // let the last `*` match one more character
+                Some((star, star_t)) => {
+                    backtrack = Some((star, star_t + 1));
+                    p = star + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Result of a message recording on a single daemon.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RecordingSummary {
+    pub machine_id: String,
+    /// Directory of the recording, on the machine of the daemon.
+    pub path: PathBuf,
+    pub messages: u64,
+    pub bytes: u64,
+    /// Messages that were not recorded because the recorder couldn't keep up.
+    pub dropped_messages: u64,
+    /// Messages that were deleted because of the size or age limit.
+    pub deleted_messages: u64,
+}
+
+/// Part of a dataflow checkpoint that was written by a single daemon.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CheckpointSummary {
+    pub machine_id: String,
+    /// Directory of the checkpoint, on the machine of the daemon.
+    pub path: PathBuf,
+    /// Local nodes that stored a state.
+    pub node_states: usize,
+    /// Inputs that were queued for the local nodes.
+    pub queued_inputs: u64,
+}
+
+/// Runtime control of a running replay.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ReplayControl {
+    /// Stop sending recorded messages until the replay is resumed.
+    Pause,
+    /// Continue a paused replay.
+    Resume,
+    /// Pause the replay, if it isn't paused already, and send the next
+    /// recorded message.
+    Step,
+    /// Change the playback speed for `original` timing, e.g. `2.0` replays
+    /// the recording twice as fast.
+    SetSpeed(f64),
+}
+
+/// Resource usage of a node process at the time it exited.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ResourceUsage {
+    pub user_time: Duration,
+    pub system_time: Duration,
+}
+
+/// Metadata of an input that was delivered to a node, without the data.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct InputSummary {
+    pub input_id: DataId,
+    pub timestamp: uhlc::Timestamp,
+    pub data_type: String,
+    /// Number of elements of the Arrow array.
+    pub len: usize,
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Timestamped<T> {
     pub inner: T,
@@ -181,3 +666,72 @@ impl DropToken {
         Self(Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)))
     }
 }
+
+/// Secret that a node presents when it registers with the daemon.
+///
+/// The daemon generates a random token for each node at spawn time and passes
+/// it to the node as part of its config. The daemon rejects connections that
+/// don't present the token, so that other local processes can't register as
+/// the node.
+///
+/// Dynamic nodes are started outside of the daemon, so they request their
+/// config, including the token, through the local listen port of the daemon.
+/// They authenticate these requests with a token that the user chooses and
+/// passes to both the daemon and the node, see
+/// [`DYNAMIC_NODE_TOKEN_ENV`](crate::node_to_daemon::DYNAMIC_NODE_TOKEN_ENV).
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct NodeToken(String);
+
+impl NodeToken {
+    pub fn generate() -> Self {
+        Self(Uuid::new_v4().simple().to_string())
+    }
+
+    /// Uses the given secret as token, e.g. a token chosen by the user.
+    pub fn new(secret: String) -> Self {
+        Self(secret)
+    }
+
+    /// Reads a token chosen by the user from the given environment variable.
+    ///
+    /// Returns `None` if the variable is not set or empty.
+    pub fn from_env(var: &str) -> Option<Self> {
+        std::env::var(var)
+            .ok()
+            .filter(|secret| !secret.is_empty())
+            .map(Self)
+    }
+
+    /// Compares the tokens in constant time.
+    pub fn matches(&self, other: &NodeToken) -> bool {
+        let (a, b) = (self.0.as_bytes(), other.0.as_bytes());
+        a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+    }
+}
+
+impl fmt::Debug for NodeToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // don't leak the secret into logs
+        f.write_str("NodeToken(..)")
+    }
+}
+
+/// Symmetric key of a dataflow, for encrypting the messages that are
+/// forwarded between daemons.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+pub struct EncryptionKey(pub [u8; 32]);
+
+impl EncryptionKey {
+    pub fn generate() -> eyre::Result<Self> {
+        let mut key = [0; 32];
+        getrandom::getrandom(&mut key)
+            .map_err(|err| eyre::eyre!("failed to generate encryption key: {err}"))?;
+        Ok(Self(key))
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
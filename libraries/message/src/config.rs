@@ -40,6 +40,76 @@ pub struct NodeRunConfig {
 pub struct Input {
     pub mapping: InputMapping,
     pub queue_size: Option<usize>,
+    /// Which messages are dropped when the node doesn't keep up with the
+    /// input.
+    pub queue_policy: QueuePolicy,
+    /// Run the receiving node on the NUMA node of the source node.
+    ///
+    /// Requires that both nodes run on the same machine and that the NUMA
+    /// node of the source node is known, e.g. through its `numa_node`.
+    pub pin_near_source: bool,
+    /// Reduce the rate of this timer input when the node can't keep up.
+    pub throttle: Option<TimerThrottle>,
+}
+
+/// Buffering of the messages of an input that the node didn't take yet.
+///
+/// e.g.
+///
+/// image:
+///
+///   source: camera/image
+///
+///   queue_policy: keep_latest
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueuePolicy {
+    /// Up to `queue_size` messages are queued. The oldest message is dropped
+    /// when the queue is full.
+    #[default]
+    DropOldest,
+    /// Only the latest message is queued, e.g. for high-rate sensor inputs.
+    KeepLatest,
+    /// No message is dropped, e.g. for command inputs.
+    ///
+    /// The messages are queued even if the event queue of the node is full.
+    Lossless,
+}
+
+impl QueuePolicy {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Automatic rate reduction of a timer input, so that a node that can't keep
+/// up doesn't build an unbounded backlog of ticks.
+///
+/// The rate is halved whenever a threshold is exceeded, down to `1 /
+/// max_factor` of the configured rate. It is doubled again once the node
+/// stays below half of all thresholds for some ticks. Throttled ticks carry
+/// the current reduction factor in the `dora_throttle_factor` metadata
+/// parameter, and every change is logged.
+///
+/// e.g.
+///
+/// ```yaml
+/// tick:
+///   source: dora/timer/millis/10
+///   throttle:
+///     max_queued_inputs: 5
+///     max_latency_ms: 50
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TimerThrottle {
+    /// Number of inputs queued for the node above which the rate is reduced.
+    pub max_queued_inputs: Option<u64>,
+    /// Time in milliseconds that inputs wait in the queue of the node, above
+    /// which the rate is reduced.
+    pub max_latency_ms: Option<u64>,
+    /// Maximum factor by which the rate is reduced. Defaults to 16.
+    pub max_factor: Option<u32>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -49,6 +119,12 @@ pub enum InputDef {
     WithOptions {
         source: InputMapping,
         queue_size: Option<usize>,
+        #[serde(default, skip_serializing_if = "QueuePolicy::is_default")]
+        queue_policy: QueuePolicy,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        pin_near_source: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        throttle: Option<TimerThrottle>,
     },
 }
 
@@ -58,13 +134,22 @@ impl From<Input> for InputDef {
             Input {
                 mapping,
                 queue_size: None,
+                queue_policy: QueuePolicy::DropOldest,
+                pin_near_source: false,
+                throttle: None,
             } => Self::MappingOnly(mapping),
             Input {
                 mapping,
                 queue_size,
+                queue_policy,
+                pin_near_source,
+                throttle,
             } => Self::WithOptions {
                 source: mapping,
                 queue_size,
+                queue_policy,
+                pin_near_source,
+                throttle,
             },
         }
     }
@@ -76,10 +161,22 @@ impl From<InputDef> for Input {
             InputDef::MappingOnly(mapping) => Self {
                 mapping,
                 queue_size: None,
+                queue_policy: QueuePolicy::DropOldest,
+                pin_near_source: false,
+                throttle: None,
             },
-            InputDef::WithOptions { source, queue_size } => Self {
+            InputDef::WithOptions {
+                source,
+                queue_size,
+                queue_policy,
+                pin_near_source,
+                throttle,
+            } => Self {
                 mapping: source,
                 queue_size,
+                queue_policy,
+                pin_near_source,
+                throttle,
             },
         }
     }
@@ -87,7 +184,13 @@ impl From<InputDef> for Input {
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, JsonSchema)]
 pub enum InputMapping {
-    Timer { interval: Duration },
+    Timer {
+        interval: Duration,
+    },
+    /// Periodic health report of the local daemon, as JSON string, e.g. the
+    /// state and queue pressure of the nodes and the connections to the
+    /// coordinator and the other machines.
+    Status,
     User(UserInputMapping),
 }
 
@@ -97,7 +200,9 @@ impl InputMapping {
 
         match self {
             InputMapping::User(mapping) => &mapping.source,
-            InputMapping::Timer { .. } => DORA_NODE_ID.get_or_init(|| NodeId("dora".to_string())),
+            InputMapping::Timer { .. } | InputMapping::Status => {
+                DORA_NODE_ID.get_or_init(|| NodeId("dora".to_string()))
+            }
         }
     }
 }
@@ -109,6 +214,7 @@ impl fmt::Display for InputMapping {
                 let duration = format_duration(*interval);
                 write!(f, "dora/timer/{duration}")
             }
+            InputMapping::Status => write!(f, "dora/status"),
             InputMapping::User(mapping) => {
                 write!(f, "{}/{}", mapping.source, mapping.output)
             }
@@ -152,6 +258,7 @@ impl<'de> Deserialize<'de> for InputMapping {
             .ok_or_else(|| serde::de::Error::custom("input must start with `<source>/`"))?;
 
         let deserialized = match source {
+            "dora" if output == "status" => Self::Status,
             "dora" => match output.split_once('/') {
                 Some(("timer", output)) => {
                     let (unit, value) = output.split_once('/').ok_or_else(|| {
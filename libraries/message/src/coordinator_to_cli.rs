@@ -2,21 +2,66 @@ use std::collections::{BTreeMap, BTreeSet};
 
 use uuid::Uuid;
 
-pub use crate::common::{LogMessage, NodeError, NodeErrorCause, NodeExitStatus};
-use crate::id::NodeId;
+pub use crate::common::{
+    ArtifactStatus, CheckpointSummary, CrashReport, EdgeLatency, EdgeStats, HealthStatus,
+    InputSummary, LatencyHistogram, LogMessage, NodeError, NodeErrorCause, NodeExitStatus,
+    NodeHealth, NodeResourceUsage, NodeState, QueueMetrics, RecordingSummary, ResourceUsage,
+    LATENCY_BUCKET_BOUNDS_US,
+};
+use crate::{cli_to_coordinator::Role, id::NodeId};
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub enum ControlRequestReply {
     Error(String),
     CoordinatorStopped,
-    DataflowStarted { uuid: Uuid },
-    DataflowReloaded { uuid: Uuid },
-    DataflowStopped { uuid: Uuid, result: DataflowResult },
+    DataflowStarted {
+        uuid: Uuid,
+    },
+    DataflowReloaded {
+        uuid: Uuid,
+    },
+    DataflowStopped {
+        uuid: Uuid,
+        result: DataflowResult,
+    },
     DataflowList(DataflowList),
     DestroyOk,
     DaemonConnected(bool),
     ConnectedMachines(BTreeSet<String>),
     Logs(Vec<u8>),
+    DataflowHealth(DataflowHealth),
+    EdgeLatencies(DataflowEdgeLatencies),
+    LiveGraph(DataflowGraph),
+    /// JSON snapshot of the internal state of each connected daemon, by
+    /// machine ID.
+    DaemonStates(BTreeMap<String, Vec<u8>>),
+    /// Crash reports of a dataflow, ordered by time.
+    CrashReports(Vec<CrashReport>),
+    RecordingStarted {
+        uuid: Uuid,
+        recording_id: String,
+    },
+    /// Summaries of the recording on each machine.
+    RecordingStopped {
+        uuid: Uuid,
+        recordings: Vec<RecordingSummary>,
+    },
+    /// Parts of the checkpoint on each machine.
+    CheckpointCreated {
+        uuid: Uuid,
+        checkpoint_id: String,
+        checkpoints: Vec<CheckpointSummary>,
+    },
+    ReplayControlled {
+        uuid: Uuid,
+    },
+    /// How much of an artifact the daemon of the machine has received.
+    ArtifactStatus(ArtifactStatus),
+    /// The identity and role that the connection is authenticated as.
+    Authenticated {
+        name: String,
+        role: Role,
+    },
 }
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
@@ -24,6 +69,9 @@ pub struct DataflowResult {
     pub uuid: Uuid,
     pub timestamp: uhlc::Timestamp,
     pub node_results: BTreeMap<NodeId, Result<(), NodeError>>,
+    /// Nodes that requested the dataflow to stop, with the reason they gave.
+    #[serde(default)]
+    pub stop_requests: BTreeMap<NodeId, String>,
 }
 
 impl DataflowResult {
@@ -32,6 +80,7 @@ impl DataflowResult {
             uuid,
             timestamp,
             node_results: Default::default(),
+            stop_requests: Default::default(),
         }
     }
 
@@ -40,6 +89,71 @@ impl DataflowResult {
     }
 }
 
+/// Latencies of the edges of a dataflow, summed over all machines.
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct DataflowEdgeLatencies {
+    pub id: DataflowIdAndName,
+    pub edges: Vec<EdgeLatency>,
+}
+
+/// Version of the JSON format of [`DataflowGraph`].
+///
+/// Fields are only ever added to the format. The version is increased when
+/// existing fields are changed or removed.
+pub const DATAFLOW_GRAPH_FORMAT_VERSION: u32 = 1;
+
+/// Graph of a running dataflow with live statistics, intended for external
+/// visualization tools.
+///
+/// The JSON serialization of this type is stable, see
+/// [`DATAFLOW_GRAPH_FORMAT_VERSION`].
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct DataflowGraph {
+    pub format_version: u32,
+    pub uuid: Uuid,
+    pub name: Option<String>,
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<EdgeStats>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct GraphNode {
+    pub id: NodeId,
+    pub name: Option<String>,
+    /// Machine that the node is deployed on.
+    pub machine: String,
+    /// State reported by the daemon, or `None` if the daemon doesn't know the
+    /// node (yet).
+    pub state: Option<NodeState>,
+    /// Latest health status reported by the node.
+    pub health: Option<HealthStatus>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct DataflowHealth {
+    pub id: DataflowIdAndName,
+    /// Latest health report of each node, or `None` if the node did not report yet.
+    pub nodes: BTreeMap<NodeId, Option<NodeHealth>>,
+    /// Latest queue metrics reported by each machine of the dataflow.
+    #[serde(default)]
+    pub queues: BTreeMap<String, QueueMetrics>,
+    /// Latest resource usage of each node, for nodes that run in a cgroup.
+    #[serde(default)]
+    pub resources: BTreeMap<NodeId, NodeResourceUsage>,
+}
+
+impl DataflowHealth {
+    /// The worst status reported by any node of the dataflow.
+    pub fn status(&self) -> HealthStatus {
+        self.nodes
+            .values()
+            .flatten()
+            .map(|health| health.status)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct DataflowList(pub Vec<DataflowListEntry>);
 
@@ -57,6 +171,9 @@ impl DataflowList {
 pub struct DataflowListEntry {
     pub id: DataflowIdAndName,
     pub status: DataflowStatus,
+    /// Nodes that requested the dataflow to stop, with the reason they gave.
+    #[serde(default)]
+    pub stop_requests: BTreeMap<NodeId, String>,
 }
 
 #[derive(Debug, Clone, Copy, serde::Deserialize, serde::Serialize, PartialEq, Eq)]
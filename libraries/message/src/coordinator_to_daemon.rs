@@ -1,12 +1,15 @@
 use std::{collections::BTreeMap, net::SocketAddr, path::PathBuf, time::Duration};
 
 use crate::{
-    descriptor::{Descriptor, ResolvedNode},
+    daemon_to_node::DaemonCommunication,
+    descriptor::{Descriptor, ResolvedNode, SignedDescriptor},
     id::{NodeId, OperatorId},
     DataflowId,
 };
 
-pub use crate::common::Timestamped;
+pub use crate::common::{
+    ArtifactId, EncryptionKey, NodeToken, RecordingOptions, ReplayControl, Timestamped,
+};
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub enum RegisterResult {
@@ -43,11 +46,74 @@ pub enum DaemonCoordinatorEvent {
         dataflow_id: DataflowId,
         node_id: NodeId,
     },
+    EdgeLatencies {
+        dataflow_id: DataflowId,
+    },
+    LiveGraph {
+        dataflow_id: DataflowId,
+    },
+    /// Request a JSON snapshot of the internal state of the daemon.
+    DumpState,
+    /// Request the crash reports of the dataflow, optionally only of a
+    /// single node.
+    CrashReports {
+        dataflow_id: DataflowId,
+        node_id: Option<NodeId>,
+    },
+    /// Start recording the outputs of the local nodes of the dataflow.
+    StartRecording {
+        dataflow_id: DataflowId,
+        recording_id: String,
+        options: RecordingOptions,
+    },
+    StopRecording {
+        dataflow_id: DataflowId,
+    },
+    /// Store a checkpoint of the local nodes of the dataflow.
+    Checkpoint {
+        dataflow_id: DataflowId,
+        checkpoint_id: String,
+    },
+    /// Pause, resume, step or change the speed of the replay of the dataflow.
+    ControlReplay {
+        dataflow_id: DataflowId,
+        control: ReplayControl,
+    },
+    /// Query how much of the artifact the daemon has received.
+    ArtifactStatus {
+        artifact: ArtifactId,
+    },
+    /// Write a part of an artifact to the artifact cache of the daemon, see
+    /// `ControlRequest::UploadArtifact`.
+    UploadArtifact {
+        artifact: ArtifactId,
+        offset: u64,
+        #[serde(with = "crate::common::artifact_data")]
+        data: Vec<u8>,
+        last: bool,
+    },
+    /// A key of the dataflow blackboard was written on some machine.
+    ///
+    /// Sent to all daemons of the dataflow, including the one that the write
+    /// originated from, in the order in which the coordinator received the
+    /// writes.
+    BlackboardUpdate {
+        dataflow_id: DataflowId,
+        key: String,
+        value: Option<Vec<u8>>,
+    },
+    /// Current leader of an election group, sent to all daemons of the
+    /// dataflow whenever the leader changes or a node starts campaigning.
+    LeaderElected {
+        dataflow_id: DataflowId,
+        group: String,
+        leader: Option<NodeId>,
+    },
     Destroy,
     Heartbeat,
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct SpawnDataflowNodes {
     pub dataflow_id: DataflowId,
     pub working_dir: PathBuf,
@@ -55,4 +121,35 @@ pub struct SpawnDataflowNodes {
     pub machine_listen_ports: BTreeMap<String, SocketAddr>,
     pub dataflow_descriptor: Descriptor,
     pub uv: bool,
+    /// Set when the dataflow is already running and the daemon was restarted.
+    ///
+    /// Instead of spawning the nodes, the daemon waits for the still running
+    /// node processes to reconnect.
+    #[serde(default)]
+    pub adopt: bool,
+    /// Key for encrypting the outputs that are forwarded between daemons, set
+    /// if the dataflow enables `security.encrypt_remote`.
+    #[serde(default)]
+    pub encryption_key: Option<EncryptionKey>,
+    /// Signed content of the descriptor, for daemons that verify signatures.
+    #[serde(default)]
+    pub signed_descriptor: Option<SignedDescriptor>,
+    /// Current content of the dataflow blackboard, set when a restarted
+    /// daemon re-adopts the dataflow.
+    #[serde(default)]
+    pub blackboard: BTreeMap<String, Vec<u8>>,
+    /// Listeners of the nodes, set when a restarted daemon re-adopts the
+    /// dataflow.
+    ///
+    /// The daemon sets up these listeners again, so that the still running
+    /// node processes can reconnect with their original config.
+    #[serde(default)]
+    pub node_listeners: BTreeMap<NodeId, NodeListener>,
+}
+
+/// Address and token that a spawned node uses to connect to its daemon.
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct NodeListener {
+    pub communication: DaemonCommunication,
+    pub token: NodeToken,
 }
@@ -1,9 +1,14 @@
 use std::collections::BTreeMap;
 
 pub use crate::common::{
-    DataMessage, LogLevel, LogMessage, NodeError, NodeErrorCause, NodeExitStatus, Timestamped,
+    ArtifactStatus, CheckpointSummary, CrashReport, DaemonLiveGraph, DataMessage, EdgeLatency,
+    LogLevel, LogMessage, NodeError, NodeErrorCause, NodeExitStatus, NodeHealth, NodeResourceUsage,
+    QueueMetrics, RecordingSummary, Timestamped,
+};
+use crate::{
+    coordinator_to_daemon::NodeListener, current_crate_version, id::NodeId, versions_compatible,
+    DataflowId,
 };
-use crate::{current_crate_version, id::NodeId, versions_compatible, DataflowId};
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub enum CoordinatorRequest {
@@ -58,12 +63,65 @@ pub enum DaemonEvent {
     },
     Heartbeat,
     Log(LogMessage),
+    /// A node requested that the whole dataflow is stopped.
+    StopDataflowRequested {
+        dataflow_id: DataflowId,
+        node_id: NodeId,
+        reason: String,
+    },
+    /// A node reported a new health status.
+    NodeHealthReported {
+        dataflow_id: DataflowId,
+        node_id: NodeId,
+        health: NodeHealth,
+    },
+    /// Periodic report of the queue metrics of a dataflow on this machine.
+    QueueMetricsReported {
+        dataflow_id: DataflowId,
+        metrics: QueueMetrics,
+    },
+    /// Periodic report of the resource usage of the nodes of a dataflow on
+    /// this machine.
+    ResourceUsageReported {
+        dataflow_id: DataflowId,
+        usage: BTreeMap<NodeId, NodeResourceUsage>,
+    },
+    /// A local node wrote to the blackboard of the dataflow, which the
+    /// coordinator replicates to all daemons of the dataflow.
+    BlackboardSet {
+        dataflow_id: DataflowId,
+        key: String,
+        value: Option<Vec<u8>>,
+    },
+    /// A local node became a candidate in the leader election of a group.
+    Campaign {
+        dataflow_id: DataflowId,
+        node_id: NodeId,
+        group: String,
+    },
+    /// A local node stopped being a candidate in the leader election of a
+    /// group, or of all groups if `group` is `None` because the node exited.
+    Resign {
+        dataflow_id: DataflowId,
+        node_id: NodeId,
+        group: Option<String>,
+    },
+    /// A local node was restarted with a new listener, which is needed for
+    /// re-adopting the node after a daemon restart.
+    NodeListenerChanged {
+        dataflow_id: DataflowId,
+        node_id: NodeId,
+        listener: NodeListener,
+    },
 }
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct DataflowDaemonResult {
     pub timestamp: uhlc::Timestamp,
     pub node_results: BTreeMap<NodeId, Result<(), NodeError>>,
+    /// Nodes that requested the dataflow to stop, with the reason they gave.
+    #[serde(default)]
+    pub stop_requests: BTreeMap<NodeId, String>,
 }
 
 impl DataflowDaemonResult {
@@ -74,7 +132,8 @@ impl DataflowDaemonResult {
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub enum DaemonCoordinatorReply {
-    SpawnResult(Result<(), String>),
+    /// Contains the listeners of the spawned nodes on success.
+    SpawnResult(Result<BTreeMap<NodeId, NodeListener>, String>),
     ReloadResult(Result<(), String>),
     StopResult(Result<(), String>),
     DestroyResult {
@@ -83,4 +142,16 @@ pub enum DaemonCoordinatorReply {
         notify: Option<tokio::sync::oneshot::Sender<()>>,
     },
     Logs(Result<Vec<u8>, String>),
+    EdgeLatencies(Result<Vec<EdgeLatency>, String>),
+    LiveGraph(Result<DaemonLiveGraph, String>),
+    /// JSON snapshot of the internal state of the daemon.
+    StateDump(Result<Vec<u8>, String>),
+    CrashReports(Result<Vec<CrashReport>, String>),
+    StartRecordingResult(Result<(), String>),
+    /// `None` if no recording was active on the daemon.
+    StopRecordingResult(Result<Option<RecordingSummary>, String>),
+    CheckpointResult(Result<CheckpointSummary, String>),
+    /// `false` if no replay was running on the daemon.
+    ControlReplayResult(Result<bool, String>),
+    ArtifactStatus(Result<ArtifactStatus, String>),
 }
@@ -8,7 +8,7 @@ use crate::{
     DataflowId,
 };
 
-#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub enum InterDaemonEvent {
     Output {
         dataflow_id: DataflowId,
@@ -17,8 +17,59 @@ pub enum InterDaemonEvent {
         metadata: Metadata,
         data: Option<AVec<u8, ConstAlign<128>>>,
     },
+    /// Output of a dataflow with `security.encrypt_remote` enabled.
+    ///
+    /// The `ciphertext` contains the encrypted metadata and data, which are
+    /// authenticated together with the dataflow, node, and output IDs and the
+    /// `sequence` number.
+    EncryptedOutput {
+        dataflow_id: DataflowId,
+        node_id: NodeId,
+        output_id: DataId,
+        /// Increases with every message of the output, so that receivers can
+        /// reject replayed messages.
+        sequence: u64,
+        nonce: [u8; 24],
+        ciphertext: Vec<u8>,
+    },
+    /// Outputs that a node sent together through `send_outputs`.
+    ///
+    /// Contains the [`Output`](Self::Output) or
+    /// [`EncryptedOutput`](Self::EncryptedOutput) events of the batch that
+    /// have receivers on the target machine, which delivers them as a unit.
+    OutputBatch {
+        dataflow_id: DataflowId,
+        outputs: Vec<InterDaemonEvent>,
+    },
     InputsClosed {
         dataflow_id: DataflowId,
         inputs: BTreeSet<(NodeId, DataId)>,
+        /// Set if the dataflow has `security.encrypt_remote` enabled.
+        tag: Option<EventTag>,
     },
+    /// Forwards a backpressure report of a local receiver to the daemon of
+    /// the producing node.
+    Backpressure {
+        dataflow_id: DataflowId,
+        node_id: NodeId,
+        output_id: DataId,
+        level: f32,
+        /// Set if the dataflow has `security.encrypt_remote` enabled.
+        tag: Option<EventTag>,
+    },
+}
+
+/// Authenticates the unencrypted fields of an inter-daemon event.
+///
+/// The `tag` is the result of encrypting an empty message with the event
+/// fields, the `sender`, and the `sequence` number as associated data.
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct EventTag {
+    /// Machine ID of the sending daemon.
+    pub sender: String,
+    /// Increases with every event of the sender, so that receivers can reject
+    /// replayed events.
+    pub sequence: u64,
+    pub nonce: [u8; 24],
+    pub tag: Vec<u8>,
 }
@@ -8,7 +8,7 @@ use crate::{
     DataflowId,
 };
 
-pub use crate::common::{DataMessage, DropToken, SharedMemoryId, Timestamped};
+pub use crate::common::{DataMessage, DropToken, NodeToken, SharedMemoryId, Timestamped};
 
 // Passed via env variable
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -23,6 +23,8 @@ pub struct NodeConfig {
     pub node_id: NodeId,
     pub run_config: NodeRunConfig,
     pub daemon_communication: DaemonCommunication,
+    /// Token that the node presents when registering with the daemon.
+    pub token: NodeToken,
     pub dataflow_descriptor: Descriptor,
     pub dynamic: bool,
 }
@@ -48,10 +50,18 @@ pub enum DaemonCommunication {
 #[must_use]
 pub enum DaemonReply {
     Result(Result<(), String>),
-    PreparedMessage { shared_memory_id: SharedMemoryId },
+    PreparedMessage {
+        shared_memory_id: SharedMemoryId,
+    },
     NextEvents(Vec<Timestamped<NodeEvent>>),
     NextDropEvents(Vec<Timestamped<NodeDropEvent>>),
-    NodeConfig { result: Result<NodeConfig, String> },
+    NodeConfig {
+        result: Result<NodeConfig, String>,
+    },
+    /// State of the node that was restored from a checkpoint, if any.
+    RestoredState(Option<Vec<u8>>),
+    /// Current value of a blackboard key, if it is set.
+    BlackboardValue(Option<Vec<u8>>),
     Empty,
 }
 
@@ -66,10 +76,75 @@ pub enum NodeEvent {
         metadata: Metadata,
         data: Option<DataMessage>,
     },
+    /// Inputs of outputs that a node sent together through `send_outputs`.
+    ///
+    /// The inputs are delivered as a unit: they are queued and dropped
+    /// together and the node receives them back-to-back.
+    InputBatch {
+        inputs: Vec<BatchedInput>,
+    },
     InputClosed {
         id: DataId,
     },
     AllInputsClosed,
+    /// Messages of the given output are not consumed fast enough.
+    ///
+    /// Sent when the input queue of a receiver overflows or when the link to
+    /// a remote receiver is saturated. The `level` is between `0.0` and `1.0`,
+    /// higher values mean that more messages are dropped or delayed.
+    Backpressure {
+        output_id: DataId,
+        level: f32,
+    },
+    /// A watched key of the dataflow blackboard was set to a new value, or
+    /// removed if `value` is `None`.
+    BlackboardChanged {
+        key: String,
+        value: Option<Vec<u8>>,
+    },
+    /// The leader of an election group that the node campaigns in changed.
+    ///
+    /// Also sent once after the node started campaigning. `leader` is `None`
+    /// if the group has no candidates that are still running.
+    LeadershipChanged {
+        group: String,
+        leader: Option<NodeId>,
+        /// Whether the receiving node is the new leader.
+        is_leader: bool,
+    },
+    /// Lines that a subscribed node printed to stdout or stderr.
+    NodeOutput {
+        node_id: NodeId,
+        /// Either `stdout` or `stderr`.
+        stream: String,
+        text: String,
+    },
+}
+
+impl NodeEvent {
+    /// Number of inputs that this event delivers.
+    pub fn input_count(&self) -> usize {
+        match self {
+            NodeEvent::Input { .. } => 1,
+            NodeEvent::InputBatch { inputs } => inputs.len(),
+            _ => 0,
+        }
+    }
+}
+
+/// An input of a [`NodeEvent::InputBatch`].
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BatchedInput {
+    pub id: DataId,
+    pub metadata: Metadata,
+    pub data: Option<DataMessage>,
+}
+
+impl From<BatchedInput> for NodeEvent {
+    fn from(input: BatchedInput) -> Self {
+        let BatchedInput { id, metadata, data } = input;
+        NodeEvent::Input { id, metadata, data }
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
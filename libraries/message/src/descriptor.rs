@@ -13,6 +13,9 @@ use std::{
 
 pub const SHELL_SOURCE: &str = "shell";
 pub const DYNAMIC_SOURCE: &str = "dynamic";
+/// Source of container nodes without a `path`, which run the entrypoint of
+/// their image.
+pub const CONTAINER_SOURCE: &str = "container";
 
 /// Dataflow description
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
@@ -25,6 +28,32 @@ pub struct Descriptor {
     #[schemars(skip)]
     #[serde(default, rename = "_unstable_deploy")]
     pub deploy: Deploy,
+    /// Rotation and retention of the node log files
+    #[serde(default)]
+    pub logs: LogsConfig,
+    /// Sampling and overhead limits of the message tracing
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    /// Replaces nodes by the outputs of a recording
+    pub replay: Option<ReplayConfig>,
+    /// Clock of the timers and message timestamps
+    #[serde(default)]
+    pub time: TimeConfig,
+    /// Capacity of the input queues of the nodes and the handling of full
+    /// queues
+    #[serde(default)]
+    pub event_queues: EventQueueConfig,
+    /// Faults that are injected for testing
+    pub faults: Option<FaultConfig>,
+    /// Checkpoint to resume the dataflow from
+    ///
+    /// Directory of a checkpoint created through `dora checkpoint`, relative to
+    /// the working directory. Either the directory of the whole checkpoint or
+    /// of the part that was written by a single daemon.
+    pub restore: Option<PathBuf>,
+    /// Protection of the data that is exchanged between machines
+    #[serde(default)]
+    pub security: SecurityConfig,
     pub nodes: Vec<Node>,
 }
 
@@ -32,6 +61,470 @@ pub struct Descriptor {
 #[serde(deny_unknown_fields)]
 pub struct Deploy {
     pub machine: Option<String>,
+    /// Name of the Unix user that the node processes run as.
+    ///
+    /// Requires the daemon to run as root. Defaults to the user of the daemon.
+    pub user: Option<String>,
+    /// NUMA node that the node processes are pinned to.
+    ///
+    /// The processes only run on the CPUs of the NUMA node and prefer its
+    /// memory, which includes the shared memory of the outputs that they
+    /// send. Only supported on Linux.
+    pub numa_node: Option<u32>,
+    /// Real-time scheduling policy and CPU affinity of the node processes.
+    pub scheduling: Option<SchedulingConfig>,
+    /// Runs the node in a pod of a Kubernetes cluster instead of on a
+    /// machine with a daemon.
+    ///
+    /// Can't be combined with `machine`. Nodes with a `machine` don't inherit
+    /// the `kubernetes` config of the dataflow.
+    pub kubernetes: Option<KubernetesConfig>,
+}
+
+/// Pod that the coordinator creates for a node.
+///
+/// The pod runs a `dora-daemon` that connects back to the coordinator and
+/// spawns the node inside of the pod, so the image must contain both the
+/// `dora-daemon` executable and the node. The coordinator must be started
+/// with `--kubernetes-advertise-addr` so that the pods can reach it.
+///
+/// e.g.
+///
+/// ```yaml
+/// kubernetes:
+///   image: registry.example.com/planner:1.0
+///   namespace: robots
+///   node_selector:
+///     gpu: "true"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct KubernetesConfig {
+    /// Image of the pod.
+    pub image: String,
+    /// Namespace of the pod. Defaults to `default`.
+    pub namespace: Option<String>,
+    /// Labels that the Kubernetes node of the pod must have.
+    #[serde(default)]
+    pub node_selector: BTreeMap<String, String>,
+}
+
+/// Scheduling of the node processes, for latency-critical nodes.
+///
+/// The `fifo` and `rr` policies require the daemon to run as root, to have
+/// the `CAP_SYS_NICE` capability, or to have an `rtprio` limit of at least
+/// the given priority. Only supported on Linux.
+///
+/// e.g.
+///
+/// scheduling:
+///
+///   policy: fifo
+///
+///   priority: 30
+///
+///   cpus: [2, 3]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SchedulingConfig {
+    /// Scheduling policy. Defaults to `other`, the default time-sharing
+    /// policy.
+    #[serde(default)]
+    pub policy: SchedulingPolicy,
+    /// Static priority for the `fifo` and `rr` policies, from 1 (lowest) to
+    /// 99 (highest).
+    pub priority: Option<u8>,
+    /// CPUs that the node processes are allowed to run on.
+    ///
+    /// Takes precedence over the CPUs of the `numa_node`.
+    #[serde(default)]
+    pub cpus: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SchedulingPolicy {
+    #[default]
+    Other,
+    Fifo,
+    #[serde(rename = "rr")]
+    RoundRobin,
+}
+
+impl SchedulingPolicy {
+    /// Whether this is a real-time policy, which requires a priority.
+    pub fn is_real_time(&self) -> bool {
+        matches!(self, Self::Fifo | Self::RoundRobin)
+    }
+}
+
+impl fmt::Display for SchedulingPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SchedulingPolicy::Other => "SCHED_OTHER",
+            SchedulingPolicy::Fifo => "SCHED_FIFO",
+            SchedulingPolicy::RoundRobin => "SCHED_RR",
+        })
+    }
+}
+
+/// Rotation and retention of the log files that the daemons write for each
+/// node to `out/<dataflow_id>/log_<node_id>.txt`.
+///
+/// e.g.
+///
+/// logs:
+///
+///   max_file_size: 1000000
+///
+///   max_rotated_files: 3
+///
+///   keep_runs: 10
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LogsConfig {
+    /// Size in bytes after which a log file is rotated. Defaults to 10 MiB.
+    pub max_file_size: Option<u64>,
+    /// Number of rotated log files that are kept per node. Defaults to 5.
+    pub max_rotated_files: Option<usize>,
+    /// Number of previous dataflow runs whose log files are kept.
+    ///
+    /// By default, the log files of all runs are kept.
+    pub keep_runs: Option<usize>,
+}
+
+/// Protection of the messages that the daemons forward between machines.
+///
+/// e.g.
+///
+/// security:
+///
+///   encrypt_remote: true
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SecurityConfig {
+    /// Encrypt and authenticate the outputs that are forwarded between
+    /// daemons, using XChaCha20-Poly1305 with a key per dataflow. Replayed
+    /// outputs are rejected. Input-closed and backpressure events are not
+    /// encrypted, but authenticated with the same key.
+    ///
+    /// The key is generated by the coordinator and distributed to the daemons
+    /// on spawn. This protects the data independently of the transport, e.g.
+    /// when the daemons are connected through a relay. The key is sent over
+    /// the unencrypted coordinator connections, though, so these must run
+    /// over a trusted network.
+    #[serde(default)]
+    pub encrypt_remote: bool,
+}
+
+/// Sampling and overhead limits of the message tracing, which is enabled
+/// through the `telemetry` feature of the daemon.
+///
+/// e.g.
+///
+/// ```yaml
+/// telemetry:
+///   sample_rate: 0.01
+///   edges:
+///     camera/image: 0.001
+///   span_detail: deliveries
+///   max_bytes_per_second: 100000
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TelemetryConfig {
+    /// Fraction of the timer ticks that start a new trace, between 0 and 1.
+    /// Defaults to 1, i.e. all ticks are traced.
+    pub sample_rate: Option<f64>,
+    /// Fraction of the traced messages of the given outputs, e.g.
+    /// `camera/image`, whose trace is continued when they are delivered to a
+    /// receiver, between 0 and 1.
+    #[serde(default)]
+    pub edges: BTreeMap<String, f64>,
+    /// Which spans the daemons record for traced messages.
+    #[serde(default)]
+    pub span_detail: SpanDetail,
+    /// Approximate limit of the tracing data that each daemon produces for
+    /// the dataflow, in bytes per second. Traces are cut off when the limit
+    /// is reached.
+    pub max_bytes_per_second: Option<u64>,
+}
+
+impl TelemetryConfig {
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate.unwrap_or(1.0)
+    }
+}
+
+/// Spans that the daemons record for a traced message.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SpanDetail {
+    /// No spans, the trace context is only passed on to the receivers.
+    None,
+    /// A span for each delivery of the message to a node.
+    Deliveries,
+    /// Spans for all hops of the message, including the forwarding between
+    /// daemons.
+    #[default]
+    Hops,
+}
+
+/// Feeds the outputs of a recording, created through `dora record`, back
+/// into the dataflow instead of running the recorded nodes.
+///
+/// The replayed nodes are not spawned. Their recorded outputs are sent to the
+/// receivers in timestamp order, with fresh timestamps.
+///
+/// e.g.
+///
+/// ```yaml
+/// replay:
+///   recording: out/<dataflow_id>/recordings/<recording_id>
+///   nodes:
+///     - camera
+///   timing: as_fast_as_possible
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ReplayConfig {
+    /// Directory of the recording, relative to the working directory.
+    ///
+    /// Either the directory of the whole recording or of the part that was
+    /// written by a single daemon.
+    pub recording: PathBuf,
+    /// The nodes that are replaced by the recording.
+    ///
+    /// By default, all source nodes are replaced, i.e. the nodes that have no
+    /// inputs except for timers.
+    #[serde(default)]
+    pub nodes: Vec<NodeId>,
+    /// Timing of the replayed messages.
+    #[serde(default)]
+    pub timing: ReplayTiming,
+}
+
+impl ReplayConfig {
+    /// Returns whether the node with the given ID and inputs is replaced by
+    /// the recording.
+    pub fn replaces(&self, node_id: &NodeId, inputs: &BTreeMap<DataId, Input>) -> bool {
+        if self.nodes.is_empty() {
+            inputs.values().all(|input| {
+                matches!(
+                    input.mapping,
+                    InputMapping::Timer { .. } | InputMapping::Status
+                )
+            })
+        } else {
+            self.nodes.contains(node_id)
+        }
+    }
+}
+
+/// Timing of replayed messages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayTiming {
+    /// Reproduce the time intervals between the recorded messages.
+    #[default]
+    Original,
+    /// Send the recorded messages without waiting in between.
+    AsFastAsPossible,
+}
+
+/// Faults that the daemons inject into the dataflow to test its robustness.
+///
+/// The faults are decided by a random number generator for each edge, which
+/// is seeded from `seed`. So a dataflow that sends the same messages in the
+/// same order experiences the same faults in each run.
+///
+/// Edges are given as `node/output`, which applies to all receivers of the
+/// output, or as `node/output -> receiver/input`, which takes precedence.
+///
+/// e.g.
+///
+/// ```yaml
+/// faults:
+///   seed: 42
+///   edges:
+///     camera/image:
+///       drop: 0.1
+///     camera/image -> detector/image:
+///       delay: 0.5
+///       delay_ms: 200
+///   kill:
+///     - node: planner
+///       after_ms: 10000
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FaultConfig {
+    /// Seed of the random fault decisions.
+    #[serde(default)]
+    pub seed: u64,
+    /// Faults of the messages sent over the given edges.
+    #[serde(default)]
+    pub edges: BTreeMap<String, EdgeFaults>,
+    /// Nodes that are killed after the given time.
+    #[serde(default)]
+    pub kill: Vec<NodeKill>,
+}
+
+/// A `(node, id)` pair of an edge key.
+pub type EdgeEnd<'a> = (&'a str, &'a str);
+
+impl FaultConfig {
+    /// Splits an edge key into the output and, if given, the input.
+    pub fn parse_edge(key: &str) -> Option<(EdgeEnd<'_>, Option<EdgeEnd<'_>>)> {
+        let (output, input) = match key.split_once("->") {
+            Some((output, input)) => (output.trim(), Some(input.trim())),
+            None => (key.trim(), None),
+        };
+        let output = output.split_once('/')?;
+        let input = match input {
+            Some(input) => Some(input.split_once('/')?),
+            None => None,
+        };
+        Some((output, input))
+    }
+}
+
+/// Probabilities of the faults of an edge, between 0 and 1.
+///
+/// At most one fault is injected per message, so the probabilities must not
+/// add up to more than 1. Messages that are sent together through
+/// `send_outputs` are delivered as a unit, so they are only dropped.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct EdgeFaults {
+    /// Probability that a message is not delivered.
+    #[serde(default)]
+    pub drop: f64,
+    /// Probability that a message is delivered twice.
+    ///
+    /// Messages in shared memory are never duplicated.
+    #[serde(default)]
+    pub duplicate: f64,
+    /// Probability that a message is delivered after the next message of the
+    /// edge.
+    #[serde(default)]
+    pub reorder: f64,
+    /// Probability that a message is delayed by `delay_ms`.
+    #[serde(default)]
+    pub delay: f64,
+    /// Delay of the delayed messages, in milliseconds.
+    #[serde(default)]
+    pub delay_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct NodeKill {
+    pub node: NodeId,
+    /// Time after the start of the dataflow at which the node is killed, in
+    /// milliseconds.
+    pub after_ms: u64,
+}
+
+/// Name of the metadata parameter through which a simulator node advances
+/// the simulated time, in nanoseconds since the start of the dataflow.
+pub const SIMULATION_TIME_PARAMETER: &str = "simulation_time";
+
+/// Clock that drives the timers of the dataflow and the timestamps of its
+/// messages.
+///
+/// With simulated time, timers tick on a virtual clock, which allows to run
+/// dataflows faster than real time or in lockstep with a simulator.
+///
+/// e.g.
+///
+/// time:
+///
+///   mode: simulated
+///
+///   rate: 10
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TimeConfig {
+    /// Whether the dataflow runs on real or simulated time.
+    #[serde(default)]
+    pub mode: TimeMode,
+    /// Speed of the simulated time relative to real time, e.g. `10` runs the
+    /// timers ten times faster. Defaults to 1.
+    pub rate: Option<f64>,
+    /// Output of a simulator node, e.g. `sim/clock`, that advances the
+    /// simulated time instead of the daemon.
+    ///
+    /// The simulated time is set to the `simulation_time` parameter of each
+    /// message of the output, in nanoseconds since the start of the dataflow.
+    /// The output only advances the clocks of the daemons that run the
+    /// simulator node or a receiver of the output.
+    pub advanced_by: Option<String>,
+}
+
+impl TimeConfig {
+    pub fn rate(&self) -> f64 {
+        self.rate.unwrap_or(1.0)
+    }
+}
+
+/// Limit of the inputs that the daemon queues for each local node until the
+/// node takes them, so that a slow node can't make the memory of the daemon
+/// grow without bounds.
+///
+/// e.g.
+///
+/// event_queues:
+///
+///   capacity: 100
+///
+///   backpressure: block
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct EventQueueConfig {
+    /// Maximum number of queued inputs per node. Defaults to 1000.
+    pub capacity: Option<usize>,
+    /// Handling of new inputs for a node whose queue is full.
+    ///
+    /// Inputs with `queue_policy: lossless` are queued regardless.
+    #[serde(default)]
+    pub backpressure: BackpressureStrategy,
+}
+
+impl EventQueueConfig {
+    pub fn capacity(&self) -> usize {
+        self.capacity.unwrap_or(1000)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BackpressureStrategy {
+    /// The oldest queued input is dropped to make room for the new input.
+    #[default]
+    DropOldest,
+    /// The new input is dropped.
+    DropIncoming,
+    /// Local nodes that send to a full queue are blocked until the receiver
+    /// takes some of its inputs, so that no input is lost.
+    ///
+    /// Inputs from timers and from other machines can't be blocked, so each
+    /// of them replaces the oldest such input of a full queue instead. A node
+    /// is not blocked by a receiver that waits for the node itself, so that
+    /// cycles of nodes with full queues don't block each other forever.
+    Block,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeMode {
+    /// Timers tick on the wall clock.
+    #[default]
+    Real,
+    /// Timers tick on a simulated clock.
+    Simulated,
 }
 
 /// Dora Node
@@ -46,6 +539,18 @@ pub struct Node {
     pub description: Option<String>,
     /// Environment variables
     pub env: Option<BTreeMap<String, EnvValue>>,
+    /// Log level of the node, e.g. `debug`.
+    ///
+    /// Passed to the node process as `RUST_LOG` and applied to the spans that
+    /// the daemon records for the node. The value can also be a full
+    /// `RUST_LOG` filter such as `my_node=trace,info`, but only plain levels
+    /// are applied to the spans of the daemon.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        alias = "tracing_filter"
+    )]
+    pub log_level: Option<String>,
 
     /// Unstable machine deployment configuration
     #[schemars(skip)]
@@ -67,18 +572,337 @@ pub struct Node {
     pub build: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub send_stdout_as: Option<String>,
+    /// Expected SHA-256 checksum of the node executable, as hex string.
+    ///
+    /// The daemon refuses to spawn the node if the file at `path` doesn't
+    /// match, e.g. because it was replaced or not rebuilt. The file is
+    /// executed by path after it was checked, so a process that can write to
+    /// it can still replace it in between.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Runs the node process in a restricted sandbox (Linux only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SandboxConfig>,
+    /// Directories that a WebAssembly node can access.
+    ///
+    /// Nodes with a `.wasm` source can't access the filesystem if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wasm: Option<WasmConfig>,
+    /// Runs the node in a container instead of directly on the machine.
+    ///
+    /// The `path` of container nodes is optional: if set, it's the command
+    /// that is run inside the container, otherwise the entrypoint of the
+    /// image is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<ContainerConfig>,
+    /// Shell command prefix that activates the environment of the node, e.g.
+    /// `nix develop --command`, `conda run -n my-env`, or
+    /// `. ./setup.bash &&`.
+    ///
+    /// The daemon runs the node command through `sh -c` (`cmd /C` on
+    /// Windows) with this prefix in front of it, so that the environment
+    /// doesn't need to be activated by a wrapper script on every machine.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub activate: Option<String>,
+    /// Signal that is sent to the node process group when the dataflow is
+    /// stopped, in addition to the `Stop` event.
+    ///
+    /// Useful for third-party binaries that don't use the dora API. On
+    /// Windows, all stop signals are delivered as a `CTRL_BREAK` event.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_signal: Option<StopSignal>,
+    /// Signals that the daemon forwards to the node process group when it
+    /// receives them itself, e.g. `[SIGHUP]` for a node that reopens its log
+    /// files on log rotation.
+    ///
+    /// Only `SIGHUP`, `SIGUSR1`, and `SIGUSR2` can be forwarded. Nodes don't
+    /// receive signals that they didn't opt in to, as the default action of
+    /// these signals terminates the process. Not supported on Windows.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub forward_signals: Vec<StopSignal>,
+    /// Time in milliseconds that the node gets to exit after the dataflow
+    /// was stopped, e.g. `30000` for a node that needs to park a robot arm.
+    ///
+    /// The daemon sends `SIGTERM` to nodes that are still running after this
+    /// duration and kills them two seconds later. Defaults to 15 seconds.
+    /// The `--grace-duration` argument of `dora stop` overrides this value
+    /// for all nodes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grace_duration_ms: Option<u64>,
+    /// Restarts the node when it exits while the dataflow is running, so
+    /// that transient crashes don't require a restart of the whole dataflow.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restart: Option<RestartConfig>,
+    /// Memory and CPU limits of the node processes (Linux and Windows only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourceLimits>,
+    /// Alternative executables for some machines of a heterogeneous cluster.
+    ///
+    /// Keyed by machine ID (see `_unstable_deploy`), target triple (e.g.
+    /// `aarch64-unknown-linux-gnu`), or architecture (e.g. `aarch64`). Each
+    /// daemon applies the most specific matching entry when it spawns the
+    /// node, in this order.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub targets: BTreeMap<String, TargetSource>,
     #[serde(default)]
     pub inputs: BTreeMap<DataId, Input>,
     #[serde(default)]
     pub outputs: BTreeSet<DataId>,
 }
 
+/// Limits for the resources that the processes of a node use together.
+///
+/// The limits are enforced through cgroups on Linux, which requires write
+/// access of the daemon to its cgroup, and through job objects on Windows.
+/// The CPU usage of the node is throttled to the limit. On Linux, the node is
+/// killed when it exceeds its memory limit, on Windows its allocations beyond
+/// the limit fail. In both cases, the node fails with a "resource limit
+/// exceeded" error.
+///
+/// e.g.
+///
+/// resources:
+///
+///   memory_mb: 512
+///
+///   cpus: 1.5
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceLimits {
+    /// Maximum memory usage in megabytes.
+    pub memory_mb: Option<u64>,
+    /// Maximum CPU usage in number of CPUs, e.g. `0.5` for half of a CPU.
+    pub cpus: Option<f64>,
+}
+
+/// Restrictions of a node process, to limit what third-party node binaries
+/// can do.
+///
+/// The node runs in its own user, mount, and IPC namespaces with a read-only
+/// view of the filesystem, except for the `writable` paths and `/dev/shm`.
+/// A seccomp filter denies system calls that are not needed by nodes, e.g.
+/// `ptrace`, `mount`, or loading kernel modules.
+///
+/// e.g.
+///
+/// ```yaml
+/// sandbox:
+///   writable:
+///     - out/images
+///   network: false
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SandboxConfig {
+    /// Paths that the node can write to, relative to the working directory.
+    ///
+    /// The paths must exist when the node is spawned.
+    #[serde(default)]
+    pub writable: Vec<PathBuf>,
+    /// Whether the node can access the network. Defaults to `true`.
+    ///
+    /// Without network access, the node can only communicate with the daemon
+    /// through shared memory or Unix domain sockets, so the dataflow must not
+    /// use TCP for local communication.
+    #[serde(default = "default_true")]
+    pub network: bool,
+}
+
+/// Filesystem access of a node with a `.wasm` source.
+///
+/// WebAssembly nodes run in a WASI sandbox that only gives access to stdio,
+/// the `args` of the node, and the variables of its `env`. The listed
+/// directories are made available to the module under the same relative path.
+///
+/// e.g.
+///
+/// ```yaml
+/// wasm:
+///   readable:
+///     - models
+///   writable:
+///     - out
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct WasmConfig {
+    /// Directories that the node can read, relative to the working directory.
+    #[serde(default)]
+    pub readable: Vec<PathBuf>,
+    /// Directories that the node can read and write, relative to the working
+    /// directory.
+    #[serde(default)]
+    pub writable: Vec<PathBuf>,
+}
+
+/// Container image that a node runs in, e.g. to ship nodes with many
+/// dependencies as images.
+///
+/// The daemon runs the container with Docker or Podman, sharing the network
+/// and IPC namespaces of the machine and mounting `/dev/shm` and the node API
+/// socket, so that the node communicates with the daemon like any other
+/// node. The container runs as the user of the node, so that the daemon can
+/// access the shared memory of its outputs.
+///
+/// e.g.
+///
+/// ```yaml
+/// container:
+///   image: ghcr.io/example/detector:1.2
+///   args: ["--gpus=all"]
+///   mounts:
+///     - models:/models:ro
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ContainerConfig {
+    /// Image to run.
+    pub image: String,
+    /// Additional arguments for the `run` command of the container engine.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Volumes to mount into the container, as `host:container[:options]`.
+    ///
+    /// Relative host paths are relative to the working directory of the
+    /// dataflow.
+    #[serde(default)]
+    pub mounts: Vec<String>,
+    /// Container engine to use. Defaults to Podman if it is installed and
+    /// Docker otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub engine: Option<ContainerEngine>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ContainerEngine {
+    Docker,
+    Podman,
+}
+
+impl ContainerEngine {
+    /// Name of the executable of the engine.
+    pub fn executable(&self) -> &'static str {
+        match self {
+            ContainerEngine::Docker => "docker",
+            ContainerEngine::Podman => "podman",
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Restart policy of a node.
+///
+/// The node is restarted with the same config after a delay that starts at
+/// `backoff_ms` and is doubled after every restart, up to `max_backoff_ms`.
+/// Its inputs stay open in the meantime, but the messages that arrive while
+/// the node is down are dropped.
+///
+/// e.g.
+///
+/// restart:
+///
+///   policy: on-failure
+///
+///   max_retries: 5
+///
+///   backoff_ms: 500
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RestartConfig {
+    pub policy: RestartPolicy,
+    /// Maximum number of restarts, unlimited if not set.
+    ///
+    /// Once all restarts are used up, the exit of the node is handled as if
+    /// it had no restart policy.
+    #[serde(default)]
+    pub max_retries: Option<u32>,
+    /// Delay before the first restart, in milliseconds.
+    #[serde(default = "default_restart_backoff_ms")]
+    pub backoff_ms: u64,
+    /// Maximum delay between two restarts, in milliseconds.
+    #[serde(default = "default_restart_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+}
+
+fn default_restart_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_restart_max_backoff_ms() -> u64 {
+    30000
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    /// Never restart the node.
+    Never,
+    /// Restart the node when it exits with an error.
+    OnFailure,
+    /// Restart the node whenever it exits, also after a successful exit.
+    Always,
+}
+
+/// Unix signal that asks a node process to stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum StopSignal {
+    #[serde(rename = "SIGINT")]
+    Interrupt,
+    #[serde(rename = "SIGTERM")]
+    Terminate,
+    #[serde(rename = "SIGHUP")]
+    Hangup,
+    #[serde(rename = "SIGQUIT")]
+    Quit,
+    #[serde(rename = "SIGUSR1")]
+    User1,
+    #[serde(rename = "SIGUSR2")]
+    User2,
+}
+
+impl fmt::Display for StopSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            StopSignal::Interrupt => "SIGINT",
+            StopSignal::Terminate => "SIGTERM",
+            StopSignal::Hangup => "SIGHUP",
+            StopSignal::Quit => "SIGQUIT",
+            StopSignal::User1 => "SIGUSR1",
+            StopSignal::User2 => "SIGUSR2",
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResolvedNode {
     pub id: NodeId,
     pub name: Option<String>,
     pub description: Option<String>,
     pub env: Option<BTreeMap<String, EnvValue>>,
+    #[serde(default)]
+    pub log_level: Option<String>,
+    #[serde(default)]
+    pub sandbox: Option<SandboxConfig>,
+    #[serde(default)]
+    pub wasm: Option<WasmConfig>,
+    #[serde(default)]
+    pub container: Option<ContainerConfig>,
+    #[serde(default)]
+    pub activate: Option<String>,
+    #[serde(default)]
+    pub stop_signal: Option<StopSignal>,
+    #[serde(default)]
+    pub forward_signals: Vec<StopSignal>,
+    #[serde(default)]
+    pub grace_duration_ms: Option<u64>,
+    #[serde(default)]
+    pub restart: Option<RestartConfig>,
+    #[serde(default)]
+    pub resources: Option<ResourceLimits>,
 
     #[serde(default)]
     pub deploy: ResolvedDeploy,
@@ -90,6 +914,28 @@ pub struct ResolvedNode {
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ResolvedDeploy {
     pub machine: String,
+    #[serde(default)]
+    pub user: Option<String>,
+    /// NUMA node of the node, either set explicitly or derived from the
+    /// source of an input with `pin_near_source`.
+    #[serde(default)]
+    pub numa_node: Option<u32>,
+    #[serde(default)]
+    pub scheduling: Option<SchedulingConfig>,
+    #[serde(default)]
+    pub kubernetes: Option<KubernetesConfig>,
+}
+
+/// Content of a dataflow descriptor file together with its signature.
+///
+/// Sent along with the parsed descriptor so that the coordinator and the
+/// daemons can verify that the dataflow was signed by a trusted key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedDescriptor {
+    /// The signed YAML content, without an embedded signature line.
+    pub content: String,
+    /// Ed25519 signature of `content`.
+    pub signature: Vec<u8>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -139,6 +985,12 @@ pub struct OperatorConfig {
     pub build: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub send_stdout_as: Option<String>,
+    /// How often the operator is restarted after a panic
+    ///
+    /// Once all restarts are used up, a panicking operator is reported as
+    /// failed. The other operators of the runtime keep running in both cases.
+    #[serde(default)]
+    pub max_restarts: u32,
 }
 
 #[derive(Debug, Serialize, Deserialize, JsonSchema, Clone)]
@@ -146,7 +998,6 @@ pub struct OperatorConfig {
 pub enum OperatorSource {
     SharedLibrary(String),
     Python(PythonSource),
-    #[schemars(skip)]
     Wasm(String),
 }
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
@@ -212,6 +1063,9 @@ pub struct CustomNode {
     /// args: some_node.py
     ///
     /// Source can match any executable in PATH.
+    ///
+    /// Sources ending in `.wasm` are run as WebAssembly modules in an embedded,
+    /// sandboxed WASM runtime.
     pub source: String,
     /// Args for the executable.
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -225,11 +1079,46 @@ pub struct CustomNode {
     /// Send stdout and stderr to another node
     #[serde(skip_serializing_if = "Option::is_none")]
     pub send_stdout_as: Option<String>,
+    /// Expected SHA-256 checksum of the source file, as hex string.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Alternative sources for some machines, see [`Node::targets`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub targets: BTreeMap<String, TargetSource>,
 
     #[serde(flatten)]
     pub run_config: NodeRunConfig,
 }
 
+/// Node executable for a specific machine or target.
+///
+/// Fields that are not set are taken from the node.
+///
+/// e.g.
+///
+/// ```yaml
+/// targets:
+///   aarch64-unknown-linux-gnu:
+///     path: target/aarch64-unknown-linux-gnu/release/camera
+///     build: cargo build --release --target aarch64-unknown-linux-gnu
+///   robot-2:
+///     path: /opt/camera/bin/camera
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TargetSource {
+    /// Replaces the `path` of the node, or the `source` of custom nodes.
+    #[serde(default, alias = "source", skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub args: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<String>,
+    /// Expected SHA-256 checksum of the executable of this target.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum EnvValue {
@@ -1,4 +1,7 @@
-use std::collections::BTreeMap;
+use std::{
+    collections::BTreeMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use arrow_schema::DataType;
 use serde::{Deserialize, Serialize};
@@ -33,6 +36,26 @@ impl Metadata {
         self.timestamp
     }
 
+    /// Returns the time that passed between the creation of this message and
+    /// the given HLC timestamp, e.g. the current time of a node's clock.
+    ///
+    /// Returns zero if the message timestamp lies after `now`.
+    pub fn age_at(&self, now: uhlc::Timestamp) -> Duration {
+        now.get_time()
+            .to_duration()
+            .saturating_sub(self.timestamp.get_time().to_duration())
+    }
+
+    /// Returns the time at which the data of this message was acquired, if
+    /// the sender supplied it.
+    ///
+    /// In contrast to the [`timestamp`](Self::timestamp), which is set when
+    /// the message is sent, this is the original sensor time, e.g. the hardware
+    /// timestamp of a camera frame. See [`MetadataParametersExt`].
+    pub fn acquisition_time(&self) -> Option<SystemTime> {
+        self.parameters.acquisition_time()
+    }
+
     pub fn open_telemetry_context(&self) -> String {
         if let Some(Parameter::String(otel)) = self.parameters.get("open_telemetry_context") {
             otel.to_string()
@@ -44,6 +67,46 @@ impl Metadata {
 
 pub type MetadataParameters = BTreeMap<String, Parameter>;
 
+/// Name of the metadata parameter that stores the acquisition time of a
+/// message, in nanoseconds since the Unix epoch.
+pub const ACQUISITION_TIME_PARAMETER: &str = "acquisition_time";
+
+pub trait MetadataParametersExt {
+    /// Stores the time at which the data of a message was acquired, e.g. the
+    /// hardware timestamp of a camera frame.
+    ///
+    /// The acquisition time is sent alongside the HLC send timestamp of the
+    /// message, so that receivers can use the true sensor time for fusion or
+    /// replay.
+    fn set_acquisition_time(&mut self, time: SystemTime);
+
+    /// Returns the acquisition time stored through [`Self::set_acquisition_time`].
+    fn acquisition_time(&self) -> Option<SystemTime>;
+}
+
+impl MetadataParametersExt for MetadataParameters {
+    fn set_acquisition_time(&mut self, time: SystemTime) {
+        let nanos = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        self.insert(
+            ACQUISITION_TIME_PARAMETER.to_owned(),
+            Parameter::Integer(i64::try_from(nanos).unwrap_or(i64::MAX)),
+        );
+    }
+
+    fn acquisition_time(&self) -> Option<SystemTime> {
+        match self.get(ACQUISITION_TIME_PARAMETER)? {
+            Parameter::Integer(nanos) => {
+                let nanos = u64::try_from(*nanos).ok()?;
+                Some(UNIX_EPOCH + Duration::from_nanos(nanos))
+            }
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ArrowTypeInfo {
     pub data_type: DataType,
@@ -1,5 +1,8 @@
+use std::collections::{BTreeMap, BTreeSet};
+
 pub use crate::common::{
-    DataMessage, DropToken, LogLevel, LogMessage, SharedMemoryId, Timestamped,
+    DataMessage, DropToken, HealthStatus, LogLevel, LogMessage, NodeHealth, NodeToken,
+    SharedMemoryId, Timestamped,
 };
 use crate::{
     current_crate_version,
@@ -17,6 +20,13 @@ pub enum DaemonRequest {
         metadata: Metadata,
         data: Option<DataMessage>,
     },
+    /// Sends multiple outputs as one unit.
+    ///
+    /// Receivers get the messages as a single
+    /// [`NodeEvent::InputBatch`](crate::daemon_to_node::NodeEvent::InputBatch).
+    SendMessages {
+        messages: Vec<OutputMessage>,
+    },
     CloseOutputs(Vec<DataId>),
     /// Signals that the node is finished sending outputs and that it received all
     /// required drop tokens.
@@ -27,11 +37,92 @@ pub enum DaemonRequest {
     ReportDropTokens {
         drop_tokens: Vec<DropToken>,
     },
+    /// Reports that the queue of the given input overflowed, so that the
+    /// daemon can notify the producing node.
+    ///
+    /// The `level` is the fraction of messages that were dropped since the
+    /// last report, between `0.0` and `1.0`, and `dropped` their number.
+    ReportBackpressure {
+        input_id: DataId,
+        level: f32,
+        dropped: u32,
+    },
     SubscribeDrop,
     NextFinishedDropTokens,
     EventStreamDropped,
+    /// Requests the config of a dynamic node through the local listen port.
     NodeConfig {
         node_id: NodeId,
+        /// The token that the daemon was started with, see
+        /// [`DYNAMIC_NODE_TOKEN_ENV`].
+        token: Option<NodeToken>,
+    },
+    /// Requests that the whole dataflow is stopped, e.g. because the node
+    /// detected that the mission is complete or that a fatal fault occurred.
+    StopDataflow {
+        reason: String,
+    },
+    /// Reports the application-level health of the node, replacing any
+    /// previous report.
+    ReportHealth {
+        health: NodeHealth,
+    },
+    /// Stores the given state of the node for dataflow checkpoints, replacing
+    /// any previously stored state.
+    SaveState {
+        state: Vec<u8>,
+    },
+    /// Requests the state of the node that was restored from a checkpoint.
+    ///
+    /// Answered with a
+    /// [`DaemonReply::RestoredState`](crate::daemon_to_node::DaemonReply::RestoredState).
+    RestoredState,
+    /// Reads a value of the blackboard of the dataflow.
+    ///
+    /// Answered with a
+    /// [`DaemonReply::BlackboardValue`](crate::daemon_to_node::DaemonReply::BlackboardValue).
+    BlackboardGet {
+        key: String,
+    },
+    /// Writes a value to the blackboard of the dataflow, or removes it if
+    /// `value` is `None`.
+    BlackboardSet {
+        key: String,
+        value: Option<Vec<u8>>,
+    },
+    /// Subscribes to changes of the blackboard keys that start with the given
+    /// prefix, which are sent as
+    /// [`NodeEvent::BlackboardChanged`](crate::daemon_to_node::NodeEvent::BlackboardChanged).
+    BlackboardWatch {
+        prefix: String,
+    },
+    /// Becomes a candidate in the leader election of the given group.
+    ///
+    /// The elected leader is announced through
+    /// [`NodeEvent::LeadershipChanged`](crate::daemon_to_node::NodeEvent::LeadershipChanged).
+    Campaign {
+        group: String,
+    },
+    /// Stops being a candidate in the leader election of the given group,
+    /// handing over the leadership if the node is the current leader.
+    Resign {
+        group: String,
+    },
+    /// Subscribes to the stdout and stderr output of the given nodes, or of
+    /// all other nodes if `nodes` is empty.
+    ///
+    /// The output is sent as
+    /// [`NodeEvent::NodeOutput`](crate::daemon_to_node::NodeEvent::NodeOutput).
+    /// Only the output of nodes that run on the same machine is delivered.
+    SubscribeNodeOutput {
+        nodes: BTreeSet<NodeId>,
+    },
+    /// Structured log record that should be stored and forwarded by the daemon.
+    Log {
+        level: LogLevel,
+        target: Option<String>,
+        message: String,
+        fields: BTreeMap<String, String>,
     },
 }
 
@@ -40,8 +131,11 @@ impl DaemonRequest {
         #[allow(clippy::match_like_matches_macro)]
         match self {
             DaemonRequest::SendMessage { .. }
+            | DaemonRequest::SendMessages { .. }
             | DaemonRequest::NodeConfig { .. }
-            | DaemonRequest::ReportDropTokens { .. } => false,
+            | DaemonRequest::ReportDropTokens { .. }
+            | DaemonRequest::ReportBackpressure { .. }
+            | DaemonRequest::Log { .. } => false,
             DaemonRequest::Register(NodeRegisterRequest { .. })
             | DaemonRequest::Subscribe
             | DaemonRequest::CloseOutputs(_)
@@ -49,7 +143,17 @@ impl DaemonRequest {
             | DaemonRequest::NextEvent { .. }
             | DaemonRequest::SubscribeDrop
             | DaemonRequest::NextFinishedDropTokens
-            | DaemonRequest::EventStreamDropped => true,
+            | DaemonRequest::EventStreamDropped
+            | DaemonRequest::StopDataflow { .. }
+            | DaemonRequest::ReportHealth { .. }
+            | DaemonRequest::SaveState { .. }
+            | DaemonRequest::RestoredState
+            | DaemonRequest::BlackboardGet { .. }
+            | DaemonRequest::BlackboardSet { .. }
+            | DaemonRequest::BlackboardWatch { .. }
+            | DaemonRequest::Campaign { .. }
+            | DaemonRequest::Resign { .. }
+            | DaemonRequest::SubscribeNodeOutput { .. } => true,
         }
     }
 
@@ -65,24 +169,46 @@ impl DaemonRequest {
             | DaemonRequest::SubscribeDrop
             | DaemonRequest::NextFinishedDropTokens
             | DaemonRequest::ReportDropTokens { .. }
+            | DaemonRequest::ReportBackpressure { .. }
             | DaemonRequest::SendMessage { .. }
-            | DaemonRequest::EventStreamDropped => false,
+            | DaemonRequest::SendMessages { .. }
+            | DaemonRequest::EventStreamDropped
+            | DaemonRequest::StopDataflow { .. }
+            | DaemonRequest::ReportHealth { .. }
+            | DaemonRequest::SaveState { .. }
+            | DaemonRequest::RestoredState
+            | DaemonRequest::BlackboardGet { .. }
+            | DaemonRequest::BlackboardSet { .. }
+            | DaemonRequest::BlackboardWatch { .. }
+            | DaemonRequest::Campaign { .. }
+            | DaemonRequest::Resign { .. }
+            | DaemonRequest::SubscribeNodeOutput { .. }
+            | DaemonRequest::Log { .. } => false,
         }
     }
 }
 
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct OutputMessage {
+    pub output_id: DataId,
+    pub metadata: Metadata,
+    pub data: Option<DataMessage>,
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct NodeRegisterRequest {
     pub dataflow_id: DataflowId,
     pub node_id: NodeId,
+    pub token: NodeToken,
     dora_version: semver::Version,
 }
 
 impl NodeRegisterRequest {
-    pub fn new(dataflow_id: DataflowId, node_id: NodeId) -> Self {
+    pub fn new(dataflow_id: DataflowId, node_id: NodeId, token: NodeToken) -> Self {
         Self {
             dataflow_id,
             node_id,
+            token,
             dora_version: semver::Version::parse(env!("CARGO_PKG_VERSION")).unwrap(),
         }
     }
@@ -132,5 +258,18 @@ pub struct SharedMemoryInput {
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub enum DynamicNodeEvent {
-    NodeConfig { node_id: NodeId },
+    NodeConfig {
+        node_id: NodeId,
+        token: Option<NodeToken>,
+    },
 }
+
+/// Environment variable with the token that authenticates dynamic nodes.
+///
+/// Dynamic nodes request their config from the local listen port of the
+/// daemon, which is reachable by any local process. So the daemon only hands
+/// out the config to dynamic nodes that present the token that the daemon
+/// was started with. The token is chosen by the user and must be set for both
+/// the daemon and the dynamic nodes. Without it, the daemon rejects all
+/// dynamic nodes.
+pub const DYNAMIC_NODE_TOKEN_ENV: &str = "DORA_DYNAMIC_NODE_TOKEN";
@@ -0,0 +1,52 @@
+//! Conversion of recorded messages to Arrow arrays.
+
+use arrow::{
+    array::ArrayData,
+    buffer::{Buffer, MutableBuffer},
+};
+use dora_message::metadata::{ArrowTypeInfo, BufferOffset};
+use eyre::Context;
+
+use crate::RecordedMessage;
+
+impl RecordedMessage {
+    /// Converts the raw data of the message to an Arrow array.
+    ///
+    /// Messages without data result in an empty array.
+    pub fn to_arrow(&self) -> eyre::Result<ArrayData> {
+        let Some(raw) = &self.data else {
+            return Ok(ArrayData::new_empty(&self.metadata.type_info.data_type));
+        };
+        // copy the data into an aligned buffer, as required by Arrow
+        let mut aligned = MutableBuffer::from_len_zeroed(raw.len());
+        aligned.as_slice_mut().copy_from_slice(raw);
+        buffer_into_arrow_array(&aligned.into(), &self.metadata.type_info)
+    }
+}
+
+fn buffer_into_arrow_array(raw: &Buffer, type_info: &ArrowTypeInfo) -> eyre::Result<ArrayData> {
+    if raw.is_empty() {
+        return Ok(ArrayData::new_empty(&type_info.data_type));
+    }
+
+    let buffers = type_info
+        .buffer_offsets
+        .iter()
+        .map(|BufferOffset { offset, len }| raw.slice_with_length(*offset, *len))
+        .collect();
+    let child_data = type_info
+        .child_data
+        .iter()
+        .map(|child| buffer_into_arrow_array(raw, child))
+        .collect::<eyre::Result<_>>()?;
+
+    ArrayData::try_new(
+        type_info.data_type.clone(),
+        type_info.len,
+        type_info.validity.clone().map(Buffer::from_vec),
+        type_info.offset,
+        buffers,
+        child_data,
+    )
+    .context("failed to create Arrow array")
+}
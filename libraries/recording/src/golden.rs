@@ -0,0 +1,724 @@
+//! Golden traces for regression tests of dataflows.
+//!
+//! A golden trace stores the normalized outputs of a reference run of a
+//! dataflow, grouped by output. Later runs, e.g. after refactoring the nodes,
+//! are recorded through `dora record` as well and compared against the golden
+//! trace through [`GoldenTrace::compare`]. Together with the `replay` key of
+//! the dataflow descriptor, which feeds recorded inputs into the dataflow,
+//! this allows validating a pipeline in CI without access to its sensors.
+//!
+//! The [`Normalization`] of a trace removes differences that are expected
+//! between runs:
+//!
+//! - send times are only compared if a time tolerance is set, relative to the
+//!   first message of the trace
+//! - floating point values are rounded to a given number of decimal places
+//! - ignored metadata parameters and outputs are left out
+//!
+//! Messages are compared in order for each output. The interleaving of
+//! different outputs is not compared, as it depends on scheduling.
+
+use std::{collections::BTreeMap, fmt, fs::File, io::BufReader, io::BufWriter, path::Path};
+
+use dora_message::common::output_pattern_matches;
+use eyre::{bail, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    json::{arrow_values, parameters_to_json},
+    merge, RecordingReader,
+};
+
+/// Version of the golden trace format.
+pub const GOLDEN_FORMAT_VERSION: u32 = 1;
+/// Values in mismatch reports are truncated to this many characters.
+const MAX_REPORTED_VALUE_LEN: usize = 200;
+
+/// Rules that are applied to both the golden and the compared trace.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Normalization {
+    /// Number of decimal places that floating point values are rounded to.
+    ///
+    /// Values are compared with a tolerance of one unit in the last place, so
+    /// that values close to a rounding boundary don't cause spurious
+    /// differences.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub float_precision: Option<u32>,
+    /// Metadata parameters that are left out, e.g. parameters that contain
+    /// timestamps.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore_parameters: Vec<String>,
+    /// Outputs that are left out, as patterns like `camera/*` or `*/debug`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore_outputs: Vec<String>,
+    /// Maximum difference of the send times of corresponding messages, in
+    /// milliseconds since the first message of the trace.
+    ///
+    /// Send times are not compared if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_tolerance_ms: Option<u64>,
+}
+
+/// A normalized output message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceMessage {
+    /// Send time in microseconds since the first message of the trace, only
+    /// stored if send times are compared.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_us: Option<u64>,
+    pub data_type: String,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub parameters: Map<String, Value>,
+    /// The values of the Arrow array, or the raw bytes if the data couldn't
+    /// be converted.
+    pub data: Vec<Value>,
+}
+
+/// Normalized outputs of a dataflow run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoldenTrace {
+    pub version: u32,
+    pub normalization: Normalization,
+    /// Messages of each output, by `node/output`.
+    pub outputs: BTreeMap<String, Vec<TraceMessage>>,
+}
+
+impl GoldenTrace {
+    /// Reads and normalizes the messages of the recording in the given
+    /// directory.
+    ///
+    /// The directory is either the directory of the whole recording or of the
+    /// part that was written by a single daemon, see
+    /// [`RecordingReader::open_all`].
+    pub fn from_recording(recording: &Path, normalization: Normalization) -> eyre::Result<Self> {
+        let parts = RecordingReader::open_all(recording)?;
+        let mut outputs: BTreeMap<String, Vec<TraceMessage>> = BTreeMap::new();
+        let mut start = None;
+        for message in merge(parts.iter().map(|part| part.messages())) {
+            let message = message?;
+            let ignored = normalization.ignore_outputs.iter().any(|pattern| {
+                output_pattern_matches(pattern, message.node_id.as_ref(), &message.output_id)
+            });
+            if ignored {
+                continue;
+            }
+
+            let time = message.timestamp().get_time().to_duration();
+            let start = *start.get_or_insert(time);
+            let time_us = normalization
+                .time_tolerance_ms
+                .map(|_| time.saturating_sub(start).as_micros() as u64);
+
+            let mut parameters = match parameters_to_json(&message) {
+                Value::Object(parameters) => parameters,
+                _ => Map::new(),
+            };
+            parameters.retain(|key, _| !normalization.ignore_parameters.contains(key));
+
+            let mut data = arrow_values(&message).unwrap_or_else(|_| {
+                message
+                    .data
+                    .iter()
+                    .flatten()
+                    .map(|byte| json!(byte))
+                    .collect()
+            });
+            if let Some(precision) = normalization.float_precision {
+                data.iter_mut()
+                    .for_each(|value| round_floats(value, precision));
+            }
+
+            outputs
+                .entry(format!("{}/{}", message.node_id, message.output_id))
+                .or_default()
+                .push(TraceMessage {
+                    time_us,
+                    data_type: format!("{}", message.metadata.type_info.data_type),
+                    parameters,
+                    data,
+                });
+        }
+
+        Ok(Self {
+            version: GOLDEN_FORMAT_VERSION,
+            normalization,
+            outputs,
+        })
+    }
+
+    pub fn read(path: &Path) -> eyre::Result<Self> {
+        let file =
+            File::open(path).wrap_err_with(|| format!("failed to open `{}`", path.display()))?;
+        let trace: Self = serde_json::from_reader(BufReader::new(file))
+            .wrap_err_with(|| format!("failed to parse `{}`", path.display()))?;
+        if trace.version != GOLDEN_FORMAT_VERSION {
+            bail!(
+                "unsupported golden trace version {} (expected {GOLDEN_FORMAT_VERSION})",
+                trace.version
+            );
+        }
+        Ok(trace)
+    }
+
+    pub fn write(&self, path: &Path) -> eyre::Result<()> {
+        let file = File::create(path)
+            .wrap_err_with(|| format!("failed to create `{}`", path.display()))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)
+            .wrap_err_with(|| format!("failed to write `{}`", path.display()))
+    }
+
+    /// Compares the given trace against this golden trace.
+    ///
+    /// Both traces must be created with the same normalization, e.g. by
+    /// passing the normalization of the golden trace to
+    /// [`Self::from_recording`].
+    pub fn compare(&self, actual: &GoldenTrace) -> eyre::Result<TraceDiff> {
+        if self.normalization != actual.normalization {
+            bail!("traces were created with different normalization rules");
+        }
+        let float_tolerance = self
+            .normalization
+            .float_precision
+            .map(|precision| 10f64.powi(-(precision as i32)));
+        let time_tolerance_us = self.normalization.time_tolerance_ms.map(|ms| ms * 1000);
+
+        let mut diff = TraceDiff::default();
+        for (output, expected) in &self.outputs {
+            let Some(actual) = actual.outputs.get(output) else {
+                diff.outputs.insert(
+                    output.clone(),
+                    OutputDiff::Missing {
+                        expected: expected.len(),
+                    },
+                );
+                continue;
+            };
+            let mut mismatches = 0;
+            let mut first_mismatch = None;
+            for (index, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+                if let Some(mismatch) =
+                    compare_messages(index, expected, actual, float_tolerance, time_tolerance_us)
+                {
+                    mismatches += 1;
+                    first_mismatch.get_or_insert(mismatch);
+                }
+            }
+            if mismatches > 0 || expected.len() != actual.len() {
+                diff.outputs.insert(
+                    output.clone(),
+                    OutputDiff::Changed {
+                        expected: expected.len(),
+                        actual: actual.len(),
+                        mismatches,
+                        first_mismatch,
+                    },
+                );
+            }
+        }
+        for (output, actual) in &actual.outputs {
+            if !self.outputs.contains_key(output) {
+                diff.outputs.insert(
+                    output.clone(),
+                    OutputDiff::Unexpected {
+                        actual: actual.len(),
+                    },
+                );
+            }
+        }
+        Ok(diff)
+    }
+}
+
+/// Differences between a trace and its golden trace, by `node/output`.
+#[derive(Debug, Default)]
+pub struct TraceDiff {
+    pub outputs: BTreeMap<String, OutputDiff>,
+}
+
+impl TraceDiff {
+    pub fn is_empty(&self) -> bool {
+        self.outputs.is_empty()
+    }
+}
+
+impl fmt::Display for TraceDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (output, diff) in &self.outputs {
+            write!(f, "{output}: ")?;
+            match diff {
+                OutputDiff::Missing { expected } => {
+                    writeln!(f, "expected {expected} messages, but none were sent")?
+                }
+                OutputDiff::Unexpected { actual } => {
+                    writeln!(f, "{actual} messages on output that is not in golden trace")?
+                }
+                OutputDiff::Changed {
+                    expected,
+                    actual,
+                    mismatches,
+                    first_mismatch,
+                } => {
+                    write!(f, "expected {expected} messages, got {actual}")?;
+                    if *mismatches > 0 {
+                        write!(f, ", {mismatches} differ")?;
+                    }
+                    writeln!(f)?;
+                    if let Some(Mismatch {
+                        index,
+                        field,
+                        expected,
+                        actual,
+                    }) = first_mismatch
+                    {
+                        writeln!(f, "  first difference in {field} of message {index}:")?;
+                        writeln!(f, "    expected: {expected}")?;
+                        writeln!(f, "    actual:   {actual}")?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum OutputDiff {
+    /// The output didn't send any messages.
+    Missing { expected: usize },
+    /// The output is not part of the golden trace.
+    Unexpected { actual: usize },
+    /// The number of messages or some of the messages differ.
+    Changed {
+        expected: usize,
+        actual: usize,
+        /// Number of differing messages, not counting missing or additional
+        /// messages at the end.
+        mismatches: usize,
+        first_mismatch: Option<Mismatch>,
+    },
+}
+
+/// A message that differs from the corresponding message of the golden
+/// trace.
+#[derive(Debug)]
+pub struct Mismatch {
+    /// Position of the message in the messages of its output.
+    pub index: usize,
+    /// The part of the message that differs, e.g. `data`.
+    pub field: &'static str,
+    pub expected: String,
+    pub actual: String,
+}
+
+fn compare_messages(
+    index: usize,
+    expected: &TraceMessage,
+    actual: &TraceMessage,
+    float_tolerance: Option<f64>,
+    time_tolerance_us: Option<u64>,
+) -> Option<Mismatch> {
+    let mismatch = |field, expected: String, actual: String| Mismatch {
+        index,
+        field,
+        expected: truncate(expected),
+        actual: truncate(actual),
+    };
+    if expected.data_type != actual.data_type {
+        return Some(mismatch(
+            "data type",
+            expected.data_type.clone(),
+            actual.data_type.clone(),
+        ));
+    }
+    let data_equal = expected.data.len() == actual.data.len()
+        && expected
+            .data
+            .iter()
+            .zip(&actual.data)
+            .all(|(e, a)| values_equal(e, a, float_tolerance));
+    if !data_equal {
+        return Some(mismatch(
+            "data",
+            Value::from(expected.data.clone()).to_string(),
+            Value::from(actual.data.clone()).to_string(),
+        ));
+    }
+    if expected.parameters != actual.parameters {
+        return Some(mismatch(
+            "parameters",
+            Value::from(expected.parameters.clone()).to_string(),
+            Value::from(actual.parameters.clone()).to_string(),
+        ));
+    }
+    if let (Some(tolerance), Some(e), Some(a)) =
+        (time_tolerance_us, expected.time_us, actual.time_us)
+    {
+        if e.abs_diff(a) > tolerance {
+            return Some(mismatch(
+                "send time",
+                format!("{}ms", e / 1000),
+                format!("{}ms", a / 1000),
+            ));
+        }
+    }
+    None
+}
+
+fn values_equal(expected: &Value, actual: &Value, float_tolerance: Option<f64>) -> bool {
+    match (expected, actual) {
+        (Value::Number(e), Value::Number(a)) if e.is_f64() || a.is_f64() => {
+            match (e.as_f64(), a.as_f64(), float_tolerance) {
+                // allow for rounding in different directions
+                (Some(e), Some(a), Some(tolerance)) => (e - a).abs() <= tolerance * 1.000_001,
+                (e, a, _) => e == a,
+            }
+        }
+        (Value::Array(e), Value::Array(a)) => {
+            e.len() == a.len()
+                && e.iter()
+                    .zip(a)
+                    .all(|(e, a)| values_equal(e, a, float_tolerance))
+        }
+        (Value::Object(e), Value::Object(a)) => {
+            e.len() == a.len()
+                && e.iter().all(|(key, e)| {
+                    a.get(key)
+                        .is_some_and(|a| values_equal(e, a, float_tolerance))
+                })
+        }
+        (e, a) => e == a,
+    }
+}
+
+fn round_floats(value: &mut Value, precision: u32) {
+    match value {
+        Value::Number(number) if number.is_f64() => {
+            if let Some(float) = number.as_f64() {
+                let factor = 10f64.powi(precision as i32);
+                *value = json!((float * factor).round() / factor);
+            }
+        }
+        Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| round_floats(value, precision)),
+        Value::Object(values) => values
+            .values_mut()
+            .for_each(|value| round_floats(value, precision)),
+        _ => {}
+    }
+}
+
+fn truncate(mut value: String) -> String {
+    if value.len() > MAX_REPORTED_VALUE_LEN {
+        let mut end = MAX_REPORTED_VALUE_LEN;
+        while !value.is_char_boundary(end) {
+            end -= 1;
+        }
+        value.truncate(end);
+        value.push_str("...");
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use dora_message::{
+        arrow_schema::DataType,
+        config::{DataId, NodeId},
+        metadata::{ArrowTypeInfo, Metadata, MetadataParameters, Parameter},
+        uhlc::{Timestamp, HLC, NTP64},
+        DataflowId,
+    };
+
+    use super::*;
+    use crate::{RecordedMessage, RecordingWriter};
+
+    fn message(data: Vec<Value>) -> TraceMessage {
+        TraceMessage {
+            time_us: None,
+            data_type: "Float64".into(),
+            parameters: Map::new(),
+            data,
+        }
+    }
+
+    fn trace(normalization: Normalization, outputs: &[(&str, Vec<TraceMessage>)]) -> GoldenTrace {
+        GoldenTrace {
+            version: GOLDEN_FORMAT_VERSION,
+            normalization,
+            outputs: outputs
+                .iter()
+                .map(|(output, messages)| (output.to_string(), messages.clone()))
+                .collect(),
+        }
+    }
+
+    fn precision(float_precision: u32) -> Normalization {
+        Normalization {
+            float_precision: Some(float_precision),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn floats_are_rounded() {
+        let mut value = json!([1.23456, {"x": -0.5555}, "1.23456", 7]);
+        round_floats(&mut value, 2);
+        assert_eq!(value, json!([1.23, {"x": -0.56}, "1.23456", 7]));
+    }
+
+    #[test]
+    fn floats_are_compared_with_tolerance() {
+        // values close to a rounding boundary are rounded in different directions
+        let golden = trace(precision(2), &[("a/x", vec![message(vec![json!(0.12)])])]);
+        let actual = trace(precision(2), &[("a/x", vec![message(vec![json!(0.13)])])]);
+        assert!(golden.compare(&actual).unwrap().is_empty());
+
+        let actual = trace(precision(2), &[("a/x", vec![message(vec![json!(0.14)])])]);
+        assert!(!golden.compare(&actual).unwrap().is_empty());
+    }
+
+    #[test]
+    fn floats_are_compared_exactly_without_precision() {
+        let golden = trace(
+            Default::default(),
+            &[("a/x", vec![message(vec![json!(0.1)])])],
+        );
+        let actual = trace(
+            Default::default(),
+            &[("a/x", vec![message(vec![json!(0.1000001)])])],
+        );
+        assert!(!golden.compare(&actual).unwrap().is_empty());
+    }
+
+    #[test]
+    fn send_times_are_compared_with_tolerance() {
+        let normalization = Normalization {
+            time_tolerance_ms: Some(5),
+            ..Default::default()
+        };
+        let at = |time_us| TraceMessage {
+            time_us: Some(time_us),
+            ..message(Vec::new())
+        };
+        let golden = trace(normalization.clone(), &[("a/x", vec![at(0), at(10_000)])]);
+
+        let actual = trace(normalization.clone(), &[("a/x", vec![at(0), at(15_000)])]);
+        assert!(golden.compare(&actual).unwrap().is_empty());
+
+        let actual = trace(normalization, &[("a/x", vec![at(0), at(15_001)])]);
+        let diff = golden.compare(&actual).unwrap();
+        match &diff.outputs["a/x"] {
+            OutputDiff::Changed {
+                mismatches: 1,
+                first_mismatch: Some(mismatch),
+                ..
+            } => {
+                assert_eq!(mismatch.index, 1);
+                assert_eq!(mismatch.field, "send time");
+                assert_eq!(mismatch.expected, "10ms");
+                assert_eq!(mismatch.actual, "15ms");
+            }
+            other => panic!("unexpected diff {other:?}"),
+        }
+    }
+
+    #[test]
+    fn different_normalization_is_rejected() {
+        let golden = trace(precision(2), &[]);
+        let actual = trace(precision(3), &[]);
+        assert!(golden.compare(&actual).is_err());
+    }
+
+    #[test]
+    fn missing_and_unexpected_outputs_are_reported() {
+        let golden = trace(Default::default(), &[("a/x", vec![message(Vec::new()); 2])]);
+        let actual = trace(Default::default(), &[("b/y", vec![message(Vec::new()); 3])]);
+        let diff = golden.compare(&actual).unwrap();
+        assert!(matches!(
+            diff.outputs["a/x"],
+            OutputDiff::Missing { expected: 2 }
+        ));
+        assert!(matches!(
+            diff.outputs["b/y"],
+            OutputDiff::Unexpected { actual: 3 }
+        ));
+        assert_eq!(
+            diff.to_string(),
+            "a/x: expected 2 messages, but none were sent\n\
+             b/y: 3 messages on output that is not in golden trace\n"
+        );
+    }
+
+    #[test]
+    fn first_mismatch_is_reported() {
+        let golden = trace(
+            Default::default(),
+            &[(
+                "a/x",
+                vec![
+                    message(vec![json!(1)]),
+                    message(vec![json!(2)]),
+                    message(vec![json!(3)]),
+                ],
+            )],
+        );
+        let actual = trace(
+            Default::default(),
+            &[(
+                "a/x",
+                vec![
+                    message(vec![json!(1)]),
+                    TraceMessage {
+                        data_type: "Int64".into(),
+                        ..message(vec![json!(2)])
+                    },
+                    message(vec![json!(4)]),
+                    message(vec![json!(5)]),
+                ],
+            )],
+        );
+        let diff = golden.compare(&actual).unwrap();
+        assert_eq!(
+            diff.to_string(),
+            "a/x: expected 3 messages, got 4, 2 differ\n  \
+             first difference in data type of message 1:\n    \
+             expected: Float64\n    \
+             actual:   Int64\n"
+        );
+    }
+
+    #[test]
+    fn reported_values_are_truncated() {
+        let long = vec![json!(1); MAX_REPORTED_VALUE_LEN];
+        let golden = trace(Default::default(), &[("a/x", vec![message(long.clone())])]);
+        let actual = trace(
+            Default::default(),
+            &[("a/x", vec![message(long[1..].to_vec())])],
+        );
+        let diff = golden.compare(&actual).unwrap();
+        let OutputDiff::Changed {
+            first_mismatch: Some(mismatch),
+            ..
+        } = &diff.outputs["a/x"]
+        else {
+            panic!("unexpected diff {diff:?}");
+        };
+        assert_eq!(mismatch.field, "data");
+        assert_eq!(
+            mismatch.expected.len(),
+            MAX_REPORTED_VALUE_LEN + "...".len()
+        );
+        assert!(mismatch.expected.ends_with("..."));
+    }
+
+    /// Test recording in the temp dir, deleted on drop.
+    struct Recording(PathBuf);
+
+    impl Recording {
+        fn write(name: &str, messages: &[(&str, &str, MetadataParameters)]) -> Self {
+            let dir =
+                std::env::temp_dir().join(format!("dora-golden-{name}-{}", std::process::id()));
+            let mut writer = RecordingWriter::create(
+                dir.clone(),
+                DataflowId::nil(),
+                "test".into(),
+                String::new(),
+                Default::default(),
+            )
+            .unwrap();
+            let clock = HLC::default();
+            for (index, (node_id, output_id, parameters)) in messages.iter().enumerate() {
+                let time = NTP64::from(Duration::from_secs(index as u64));
+                let type_info = ArrowTypeInfo {
+                    data_type: DataType::Null,
+                    len: 0,
+                    null_count: 0,
+                    validity: None,
+                    offset: 0,
+                    buffer_offsets: Vec::new(),
+                    child_data: Vec::new(),
+                };
+                let message = RecordedMessage {
+                    node_id: NodeId::from(node_id.to_string()),
+                    output_id: DataId::from(output_id.to_string()),
+                    metadata: Metadata::from_parameters(
+                        Timestamp::new(time, *clock.get_id()),
+                        type_info,
+                        parameters.clone(),
+                    ),
+                    data: None,
+                };
+                writer.write(&message).unwrap();
+            }
+            writer.finish().unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for Recording {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn ignored_outputs_and_parameters_are_masked() {
+        let parameters = MetadataParameters::from([
+            ("frame".to_owned(), Parameter::Integer(1)),
+            ("acquired_at".to_owned(), Parameter::Integer(123)),
+        ]);
+        let recording = Recording::write(
+            "masked",
+            &[
+                ("camera", "image", parameters.clone()),
+                ("camera", "debug", parameters.clone()),
+                ("detector", "debug", parameters),
+            ],
+        );
+        let normalization = Normalization {
+            ignore_parameters: vec!["acquired_at".into()],
+            ignore_outputs: vec!["*/debug".into()],
+            ..Default::default()
+        };
+        let trace = GoldenTrace::from_recording(&recording.0, normalization).unwrap();
+
+        assert_eq!(trace.outputs.keys().collect::<Vec<_>>(), ["camera/image"]);
+        let [message] = trace.outputs["camera/image"].as_slice() else {
+            panic!("expected a single message");
+        };
+        assert_eq!(Value::from(message.parameters.clone()), json!({"frame": 1}));
+        // send times are only stored if they are compared
+        assert_eq!(message.time_us, None);
+    }
+
+    #[test]
+    fn send_times_are_relative_to_first_message() {
+        let recording = Recording::write(
+            "times",
+            &[
+                ("a", "x", Default::default()),
+                ("a", "x", Default::default()),
+                ("b", "y", Default::default()),
+            ],
+        );
+        let normalization = Normalization {
+            time_tolerance_ms: Some(1),
+            ..Default::default()
+        };
+        // messages are written one second apart
+        let trace = GoldenTrace::from_recording(&recording.0, normalization).unwrap();
+
+        let times = |output: &str| -> Vec<_> {
+            trace.outputs[output]
+                .iter()
+                .map(|message| message.time_us)
+                .collect()
+        };
+        assert_eq!(times("a/x"), [Some(0), Some(1_000_000)]);
+        assert_eq!(times("b/y"), [Some(2_000_000)]);
+    }
+}
@@ -0,0 +1,50 @@
+//! Conversion of recorded messages to JSON.
+
+use std::sync::Arc;
+
+use arrow::{array::make_array, record_batch::RecordBatch};
+use dora_message::metadata::Parameter;
+use eyre::Context;
+use serde_json::{json, Map, Value};
+
+use crate::RecordedMessage;
+
+pub(crate) fn parameters_to_json(message: &RecordedMessage) -> Value {
+    let parameters = message
+        .metadata
+        .parameters
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Parameter::Bool(value) => json!(value),
+                Parameter::Integer(value) => json!(value),
+                Parameter::String(value) => json!(value),
+                Parameter::ListInt(value) => json!(value),
+            };
+            (key.clone(), value)
+        })
+        .collect::<Map<_, _>>();
+    Value::Object(parameters)
+}
+
+/// Converts the raw data of a message to a list of JSON values.
+pub(crate) fn arrow_values(message: &RecordedMessage) -> eyre::Result<Vec<Value>> {
+    if message.data.is_none() {
+        return Ok(Vec::new());
+    }
+    let array = make_array(message.to_arrow()?);
+
+    let batch = RecordBatch::try_from_iter([("data", Arc::clone(&array))])
+        .context("failed to create record batch")?;
+    let mut json_writer = arrow::json::ArrayWriter::new(Vec::new());
+    json_writer
+        .write(&batch)
+        .and_then(|()| json_writer.finish())
+        .context("failed to convert data to JSON")?;
+    let rows: Vec<Map<String, Value>> = serde_json::from_slice(&json_writer.into_inner())
+        .context("failed to parse converted JSON")?;
+    Ok(rows
+        .into_iter()
+        .map(|mut row| row.remove("data").unwrap_or(Value::Null))
+        .collect())
+}
@@ -0,0 +1,761 @@
+//! On-disk format of dora message recordings.
+//!
+//! A recording consists of one directory per daemon, which contains the
+//! messages sent by the local nodes of that daemon. The messages are written
+//! to a sequence of chunk files of bounded size, each starting with
+//! [`CHUNK_MAGIC`], followed by length-prefixed, `bincode`-encoded
+//! [`RecordedMessage`]s. The `index.json` file of the directory describes the
+//! chunks, including the time range and the recorded outputs of each chunk and
+//! a list of seek points, so that readers can start at a given time without
+//! scanning all chunks.
+//!
+//! The index is updated whenever a chunk is completed, so a recording that was
+//! interrupted, e.g. because the daemon was killed, only lacks the index of its
+//! last chunk. The [`RecordingReader`] still reads such chunks.
+//!
+//! Recordings with a size or age limit behave like a ring buffer: the oldest
+//! chunks are deleted once the limit is exceeded. Such recordings use smaller
+//! chunks, so that the limit is kept closely.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use dora_message::{
+    common::RecordingOptions,
+    config::{DataId, NodeId},
+    metadata::Metadata,
+    uhlc, DataflowId,
+};
+use eyre::{bail, Context};
+
+#[cfg(feature = "golden")]
+pub use golden::{GoldenTrace, Mismatch, Normalization, OutputDiff, TraceDiff, TraceMessage};
+#[cfg(feature = "mcap")]
+pub use mcap_export::{export_mcap, McapExport};
+
+#[cfg(feature = "arrow")]
+mod arrow_data;
+#[cfg(feature = "golden")]
+mod golden;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "mcap")]
+mod mcap_export;
+
+/// Version of the recording format, stored in the index.
+pub const FORMAT_VERSION: u32 = 1;
+/// Every chunk file starts with these bytes.
+pub const CHUNK_MAGIC: &[u8; 8] = b"DORAREC1";
+/// Chunks are completed once they reach this size.
+pub const DEFAULT_MAX_CHUNK_SIZE: u64 = 64 << 20;
+/// Recordings with a size or age limit are split into about this many chunks,
+/// so that only a small part of the recording is deleted at once.
+const RING_BUFFER_CHUNKS: u64 = 16;
+/// Lower bound for the chunk size of recordings with a size limit.
+const MIN_RING_BUFFER_CHUNK_SIZE: u64 = 64 << 10;
+/// A seek point is stored for every n-th message of a chunk.
+const SEEK_POINT_INTERVAL: u64 = 100;
+const INDEX_FILE: &str = "index.json";
+
+/// Directory of a recording on the given machine, relative to the working
+/// directory of the dataflow.
+pub fn recording_dir(
+    working_dir: &Path,
+    dataflow_id: &DataflowId,
+    recording_id: &str,
+    machine_id: &str,
+) -> PathBuf {
+    let machine = if machine_id.is_empty() {
+        "default"
+    } else {
+        machine_id
+    };
+    working_dir
+        .join("out")
+        .join(dataflow_id.to_string())
+        .join("recordings")
+        .join(recording_id)
+        .join(machine)
+}
+
+/// An output message, as sent by a node.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RecordedMessage {
+    pub node_id: NodeId,
+    pub output_id: DataId,
+    pub metadata: Metadata,
+    /// The raw data of the message, as described by the type info of the
+    /// metadata.
+    pub data: Option<Vec<u8>>,
+}
+
+impl RecordedMessage {
+    pub fn timestamp(&self) -> uhlc::Timestamp {
+        self.metadata.timestamp()
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RecordingIndex {
+    pub version: u32,
+    pub dataflow_id: DataflowId,
+    pub recording_id: String,
+    pub machine_id: String,
+    #[serde(default)]
+    pub options: RecordingOptions,
+    /// Whether the recording was stopped regularly. Incomplete recordings might
+    /// contain chunks that are not listed in the index.
+    pub complete: bool,
+    pub chunks: Vec<ChunkIndex>,
+    /// Messages of the chunks that were deleted because of the size or age
+    /// limit of the recording.
+    #[serde(default)]
+    pub deleted_messages: u64,
+    /// Number of the next chunk file.
+    #[serde(default)]
+    pub next_chunk: u64,
+}
+
+impl RecordingIndex {
+    pub fn messages(&self) -> u64 {
+        self.chunks.iter().map(|c| c.messages).sum()
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.chunks.iter().map(|c| c.bytes).sum()
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ChunkIndex {
+    /// File name of the chunk, relative to the recording directory.
+    pub file: String,
+    pub messages: u64,
+    /// Size of the chunk file, in bytes.
+    pub bytes: u64,
+    /// Timestamp of the first message.
+    pub start: Option<uhlc::Timestamp>,
+    /// Timestamp of the last message.
+    pub end: Option<uhlc::Timestamp>,
+    /// Number of messages per output, keyed by `node/output`.
+    pub outputs: BTreeMap<String, u64>,
+    pub seek_points: Vec<SeekPoint>,
+}
+
+/// Position of a message in a chunk file.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SeekPoint {
+    pub timestamp: uhlc::Timestamp,
+    pub offset: u64,
+}
+
+/// Writes a recording to a directory.
+pub struct RecordingWriter {
+    dir: PathBuf,
+    index: RecordingIndex,
+    chunk: Option<(BufWriter<File>, ChunkIndex)>,
+    max_chunk_size: u64,
+    /// Maximum time span of a chunk, for recordings with an age limit.
+    max_chunk_duration: Option<Duration>,
+}
+
+impl RecordingWriter {
+    /// Creates the given directory and starts a new recording in it.
+    pub fn create(
+        dir: PathBuf,
+        dataflow_id: DataflowId,
+        recording_id: String,
+        machine_id: String,
+        options: RecordingOptions,
+    ) -> eyre::Result<Self> {
+        std::fs::create_dir_all(&dir)
+            .wrap_err_with(|| format!("failed to create recording dir `{}`", dir.display()))?;
+        let max_chunk_size = match options.max_bytes {
+            Some(max_bytes) => (max_bytes / RING_BUFFER_CHUNKS)
+                .clamp(MIN_RING_BUFFER_CHUNK_SIZE, DEFAULT_MAX_CHUNK_SIZE),
+            None => DEFAULT_MAX_CHUNK_SIZE,
+        };
+        let max_chunk_duration = options
+            .keep_last
+            .map(|keep_last| keep_last / RING_BUFFER_CHUNKS as u32);
+        let writer = Self {
+            dir,
+            index: RecordingIndex {
+                version: FORMAT_VERSION,
+                dataflow_id,
+                recording_id,
+                machine_id,
+                options,
+                complete: false,
+                chunks: Vec::new(),
+                deleted_messages: 0,
+                next_chunk: 0,
+            },
+            chunk: None,
+            max_chunk_size,
+            max_chunk_duration,
+        };
+        writer.write_index()?;
+        Ok(writer)
+    }
+
+    pub fn with_max_chunk_size(mut self, max_chunk_size: u64) -> Self {
+        self.max_chunk_size = max_chunk_size;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn write(&mut self, message: &RecordedMessage) -> eyre::Result<()> {
+        let encoded = bincode::serialize(message).context("failed to serialize message")?;
+        let record_len = 4 + encoded.len() as u64;
+        let timestamp = message.timestamp();
+
+        let chunk_full = match &self.chunk {
+            Some((_, info)) => {
+                let too_long = match (self.max_chunk_duration, info.start) {
+                    (Some(max), Some(start)) => time_between(start, timestamp) > max,
+                    _ => false,
+                };
+                info.messages > 0 && (info.bytes + record_len > self.max_chunk_size || too_long)
+            }
+            None => true,
+        };
+        if chunk_full {
+            self.finish_chunk()?;
+            // the new chunk grows up to the maximum chunk size, but holds at
+            // least this message
+            let new_chunk_bytes = self
+                .max_chunk_size
+                .max(CHUNK_MAGIC.len() as u64 + record_len);
+            self.delete_old_chunks(timestamp, new_chunk_bytes)?;
+            self.start_chunk()?;
+        }
+        let Some((file, info)) = &mut self.chunk else {
+            bail!("no open chunk")
+        };
+
+        if info.messages % SEEK_POINT_INTERVAL == 0 {
+            info.seek_points.push(SeekPoint {
+                timestamp,
+                offset: info.bytes,
+            });
+        }
+        file.write_all(&(encoded.len() as u32).to_le_bytes())
+            .and_then(|()| file.write_all(&encoded))
+            .wrap_err_with(|| format!("failed to write to chunk `{}`", info.file))?;
+
+        info.messages += 1;
+        info.bytes += record_len;
+        info.start.get_or_insert(timestamp);
+        info.end = Some(timestamp);
+        *info
+            .outputs
+            .entry(format!("{}/{}", message.node_id, message.output_id))
+            .or_default() += 1;
+        Ok(())
+    }
+
+    /// Completes the last chunk and marks the recording as complete.
+    pub fn finish(mut self) -> eyre::Result<RecordingIndex> {
+        self.finish_chunk()?;
+        self.index.complete = true;
+        self.write_index()?;
+        Ok(self.index)
+    }
+
+    /// Deletes the oldest chunks until the recording stays within its limits
+    /// after writing a new chunk of up to the given size.
+    fn delete_old_chunks(
+        &mut self,
+        newest: uhlc::Timestamp,
+        new_chunk_bytes: u64,
+    ) -> eyre::Result<()> {
+        let RecordingOptions {
+            max_bytes,
+            keep_last,
+            ..
+        } = self.index.options;
+        let mut deleted = false;
+        while let Some(oldest) = self.index.chunks.first() {
+            let too_large = max_bytes.is_some_and(|max| self.index.bytes() + new_chunk_bytes > max);
+            let too_old = match (keep_last, oldest.end) {
+                (Some(keep_last), Some(end)) => time_between(end, newest) > keep_last,
+                _ => false,
+            };
+            if !too_large && !too_old {
+                break;
+            }
+            let oldest = self.index.chunks.remove(0);
+            let path = self.dir.join(&oldest.file);
+            std::fs::remove_file(&path)
+                .wrap_err_with(|| format!("failed to delete chunk `{}`", path.display()))?;
+            self.index.deleted_messages += oldest.messages;
+            deleted = true;
+        }
+        if deleted {
+            self.write_index()?;
+        }
+        Ok(())
+    }
+
+    fn start_chunk(&mut self) -> eyre::Result<()> {
+        let file_name = format!("chunk_{:05}.dorarec", self.index.next_chunk);
+        self.index.next_chunk += 1;
+        let path = self.dir.join(&file_name);
+        let mut file = BufWriter::new(
+            File::create(&path)
+                .wrap_err_with(|| format!("failed to create chunk `{}`", path.display()))?,
+        );
+        file.write_all(CHUNK_MAGIC)
+            .wrap_err_with(|| format!("failed to write to chunk `{}`", path.display()))?;
+        self.chunk = Some((
+            file,
+            ChunkIndex {
+                file: file_name,
+                messages: 0,
+                bytes: CHUNK_MAGIC.len() as u64,
+                start: None,
+                end: None,
+                outputs: BTreeMap::new(),
+                seek_points: Vec::new(),
+            },
+        ));
+        Ok(())
+    }
+
+    fn finish_chunk(&mut self) -> eyre::Result<()> {
+        if let Some((mut file, info)) = self.chunk.take() {
+            file.flush()
+                .wrap_err_with(|| format!("failed to flush chunk `{}`", info.file))?;
+            self.index.chunks.push(info);
+            self.write_index()?;
+        }
+        Ok(())
+    }
+
+    fn write_index(&self) -> eyre::Result<()> {
+        let path = self.dir.join(INDEX_FILE);
+        let tmp = path.with_extension("json.tmp");
+        let json = serde_json::to_vec_pretty(&self.index)
+            .context("failed to serialize recording index")?;
+        std::fs::write(&tmp, json)
+            .and_then(|()| std::fs::rename(&tmp, &path))
+            .wrap_err_with(|| format!("failed to write recording index `{}`", path.display()))
+    }
+}
+
+fn time_between(start: uhlc::Timestamp, end: uhlc::Timestamp) -> Duration {
+    end.get_time()
+        .to_duration()
+        .saturating_sub(start.get_time().to_duration())
+}
+
+/// Reads a recording from a directory.
+pub struct RecordingReader {
+    dir: PathBuf,
+    index: RecordingIndex,
+    /// File names of all chunks, including those that are not indexed.
+    chunk_files: Vec<String>,
+}
+
+impl RecordingReader {
+    pub fn open(dir: impl Into<PathBuf>) -> eyre::Result<Self> {
+        let dir = dir.into();
+        let index_path = dir.join(INDEX_FILE);
+        let raw = std::fs::read(&index_path).wrap_err_with(|| {
+            format!("failed to read recording index `{}`", index_path.display())
+        })?;
+        let index: RecordingIndex = serde_json::from_slice(&raw).wrap_err_with(|| {
+            format!("failed to parse recording index `{}`", index_path.display())
+        })?;
+        if index.version != FORMAT_VERSION {
+            bail!(
+                "unsupported recording format version {} (expected {FORMAT_VERSION})",
+                index.version
+            );
+        }
+
+        let mut chunk_files: Vec<_> = index.chunks.iter().map(|c| c.file.clone()).collect();
+        if !index.complete {
+            let mut unindexed = Vec::new();
+            for entry in std::fs::read_dir(&dir)
+                .wrap_err_with(|| format!("failed to read recording dir `{}`", dir.display()))?
+            {
+                let name = entry?.file_name().to_string_lossy().into_owned();
+                if name.ends_with(".dorarec") && !chunk_files.contains(&name) {
+                    unindexed.push(name);
+                }
+            }
+            unindexed.sort();
+            chunk_files.extend(unindexed);
+        }
+
+        Ok(Self {
+            dir,
+            index,
+            chunk_files,
+        })
+    }
+
+    /// Opens all parts of a recording.
+    ///
+    /// The given directory is either the directory of a single daemon or the
+    /// directory of the whole recording, which contains one subdirectory per
+    /// daemon.
+    pub fn open_all(dir: impl Into<PathBuf>) -> eyre::Result<Vec<Self>> {
+        let dir = dir.into();
+        if dir.join(INDEX_FILE).exists() {
+            return Ok(vec![Self::open(dir)?]);
+        }
+        let mut parts = Vec::new();
+        for entry in std::fs::read_dir(&dir)
+            .wrap_err_with(|| format!("failed to read recording dir `{}`", dir.display()))?
+        {
+            let path = entry?.path();
+            if path.join(INDEX_FILE).exists() {
+                parts.push(path);
+            }
+        }
+        if parts.is_empty() {
+            bail!("no recording found in `{}`", dir.display());
+        }
+        parts.sort();
+        parts.into_iter().map(Self::open).collect()
+    }
+
+    pub fn index(&self) -> &RecordingIndex {
+        &self.index
+    }
+
+    /// Iterates over all messages, in the order in which they were recorded.
+    pub fn messages(&self) -> Messages {
+        Messages {
+            chunks: self
+                .chunk_files
+                .iter()
+                .map(|file| (self.dir.join(file), 0))
+                .collect(),
+            current: None,
+            start: None,
+        }
+    }
+
+    /// Iterates over the messages that were sent at or after the given time.
+    ///
+    /// Uses the seek points of the index to skip the earlier messages.
+    pub fn messages_since(&self, start: uhlc::Timestamp) -> Messages {
+        let mut chunks = Vec::new();
+        for file in &self.chunk_files {
+            let info = self.index.chunks.iter().find(|c| &c.file == file);
+            let offset = match info {
+                Some(info) if info.end.map_or(true, |end| end < start) => continue,
+                Some(info) => info
+                    .seek_points
+                    .iter()
+                    .take_while(|point| point.timestamp <= start)
+                    .last()
+                    .map_or(0, |point| point.offset),
+                None => 0,
+            };
+            chunks.push((self.dir.join(file), offset));
+        }
+        Messages {
+            chunks,
+            current: None,
+            start: Some(start),
+        }
+    }
+}
+
+/// Iterator over the messages of a recording.
+pub struct Messages {
+    /// Remaining chunk files, with the offset to start reading at.
+    chunks: Vec<(PathBuf, u64)>,
+    current: Option<(PathBuf, BufReader<File>)>,
+    start: Option<uhlc::Timestamp>,
+}
+
+impl Messages {
+    fn open_next_chunk(&mut self) -> eyre::Result<bool> {
+        if self.chunks.is_empty() {
+            return Ok(false);
+        }
+        let (path, offset) = self.chunks.remove(0);
+        let mut file = BufReader::new(
+            File::open(&path)
+                .wrap_err_with(|| format!("failed to open chunk `{}`", path.display()))?,
+        );
+        let mut magic = [0; CHUNK_MAGIC.len()];
+        file.read_exact(&mut magic)
+            .wrap_err_with(|| format!("failed to read chunk `{}`", path.display()))?;
+        if &magic != CHUNK_MAGIC {
+            bail!("`{}` is not a dora recording chunk", path.display());
+        }
+        if offset > 0 {
+            file.seek(SeekFrom::Start(offset))
+                .wrap_err_with(|| format!("failed to seek in chunk `{}`", path.display()))?;
+        }
+        self.current = Some((path, file));
+        Ok(true)
+    }
+
+    fn read_next(&mut self) -> eyre::Result<Option<RecordedMessage>> {
+        loop {
+            let Some((path, file)) = &mut self.current else {
+                if self.open_next_chunk()? {
+                    continue;
+                }
+                return Ok(None);
+            };
+            let mut len = [0; 4];
+            match file.read_exact(&mut len) {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                    self.current = None;
+                    continue;
+                }
+                Err(err) => {
+                    return Err(err)
+                        .wrap_err_with(|| format!("failed to read chunk `{}`", path.display()))
+                }
+            }
+            let mut encoded = vec![0; u32::from_le_bytes(len) as usize];
+            match file.read_exact(&mut encoded) {
+                Ok(()) => {}
+                // the last message of an interrupted recording might be
+                // truncated
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                    self.current = None;
+                    continue;
+                }
+                Err(err) => {
+                    return Err(err)
+                        .wrap_err_with(|| format!("failed to read chunk `{}`", path.display()))
+                }
+            }
+            let message: RecordedMessage = bincode::deserialize(&encoded).wrap_err_with(|| {
+                format!(
+                    "failed to deserialize message in chunk `{}`",
+                    path.display()
+                )
+            })?;
+            if self
+                .start
+                .map_or(true, |start| message.timestamp() >= start)
+            {
+                return Ok(Some(message));
+            }
+        }
+    }
+}
+
+impl Iterator for Messages {
+    type Item = eyre::Result<RecordedMessage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_next() {
+            Ok(message) => message.map(Ok),
+            Err(err) => {
+                // don't continue after an error
+                self.chunks.clear();
+                self.current = None;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Merges the messages of several recordings, e.g. of all daemons of a
+/// dataflow, into a single iterator in timestamp order.
+pub fn merge(parts: impl IntoIterator<Item = Messages>) -> Merged {
+    Merged {
+        parts: parts.into_iter().map(|messages| (messages, None)).collect(),
+    }
+}
+
+/// Iterator over the messages of several recordings in timestamp order.
+pub struct Merged {
+    /// The remaining messages of each part, with the next message if it was
+    /// already read.
+    parts: Vec<(Messages, Option<RecordedMessage>)>,
+}
+
+impl Iterator for Merged {
+    type Item = eyre::Result<RecordedMessage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (messages, next) in &mut self.parts {
+            if next.is_none() {
+                match messages.next() {
+                    Some(Ok(message)) => *next = Some(message),
+                    Some(Err(err)) => return Some(Err(err)),
+                    None => {}
+                }
+            }
+        }
+        self.parts.retain(|(_, next)| next.is_some());
+
+        let (_, next) = self
+            .parts
+            .iter_mut()
+            .min_by_key(|(_, next)| next.as_ref().map(|m| m.timestamp()))?;
+        next.take().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dora_message::{
+        arrow_schema::DataType,
+        metadata::ArrowTypeInfo,
+        uhlc::{Timestamp, HLC, NTP64},
+    };
+
+    use super::*;
+
+    /// Test recording in the temp dir, deleted on drop.
+    struct Recording(PathBuf);
+
+    impl Recording {
+        /// Writes the given number of messages, one every `interval`.
+        fn write(
+            name: &str,
+            options: RecordingOptions,
+            messages: u64,
+            interval: Duration,
+            data_len: usize,
+        ) -> (Self, RecordingIndex) {
+            let dir = std::env::temp_dir().join(format!("dora-ring-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            let recording = Self(dir.clone());
+            let mut writer = RecordingWriter::create(
+                dir,
+                DataflowId::nil(),
+                "test".into(),
+                String::new(),
+                options,
+            )
+            .unwrap();
+            for index in 0..messages {
+                writer.write(&message(index, interval, data_len)).unwrap();
+            }
+            (recording, writer.finish().unwrap())
+        }
+
+        fn chunk_files(&self) -> Vec<String> {
+            let mut files: Vec<_> = std::fs::read_dir(&self.0)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+                .filter(|name| name.ends_with(".dorarec"))
+                .collect();
+            files.sort();
+            files
+        }
+
+        /// Indexes of the messages that can still be read, see [`message`].
+        fn messages(&self) -> Vec<u64> {
+            RecordingReader::open(&self.0)
+                .unwrap()
+                .messages()
+                .map(|message| message.unwrap().output_id.to_string().parse().unwrap())
+                .collect()
+        }
+    }
+
+    impl Drop for Recording {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Message with the given index as output ID.
+    fn message(index: u64, interval: Duration, data_len: usize) -> RecordedMessage {
+        let time = NTP64::from(interval * index as u32);
+        let type_info = ArrowTypeInfo {
+            data_type: DataType::UInt8,
+            len: data_len,
+            null_count: 0,
+            validity: None,
+            offset: 0,
+            buffer_offsets: Vec::new(),
+            child_data: Vec::new(),
+        };
+        RecordedMessage {
+            node_id: NodeId::from("node".to_owned()),
+            output_id: DataId::from(index.to_string()),
+            metadata: Metadata::new(Timestamp::new(time, *HLC::default().get_id()), type_info),
+            data: Some(vec![0; data_len]),
+        }
+    }
+
+    #[test]
+    fn size_limit_deletes_oldest_chunks() {
+        let max_bytes = RING_BUFFER_CHUNKS * MIN_RING_BUFFER_CHUNK_SIZE;
+        let options = RecordingOptions {
+            max_bytes: Some(max_bytes),
+            ..Default::default()
+        };
+        let (recording, index) =
+            Recording::write("size", options, 500, Duration::from_millis(10), 10_000);
+
+        assert!(index.bytes() <= max_bytes, "{} bytes", index.bytes());
+        assert!(index.deleted_messages > 0);
+        assert_eq!(index.deleted_messages + index.messages(), 500);
+
+        // only the newest chunks remain
+        let files: Vec<_> = index.chunks.iter().map(|c| c.file.clone()).collect();
+        assert_eq!(recording.chunk_files(), files);
+        let first = index.next_chunk - files.len() as u64;
+        let expected: Vec<_> = (first..index.next_chunk)
+            .map(|n| format!("chunk_{n:05}.dorarec"))
+            .collect();
+        assert_eq!(files, expected);
+
+        let messages = recording.messages();
+        assert_eq!(messages.len() as u64, index.messages());
+        assert_eq!(messages.first(), Some(&index.deleted_messages));
+        assert_eq!(messages.last(), Some(&499));
+    }
+
+    #[test]
+    fn age_limit_deletes_old_chunks() {
+        let options = RecordingOptions {
+            keep_last: Some(Duration::from_secs(16)),
+            ..Default::default()
+        };
+        let (recording, index) = Recording::write("age", options, 100, Duration::from_secs(1), 10);
+
+        assert!(index.deleted_messages > 0);
+        assert_eq!(index.deleted_messages + index.messages(), 100);
+        let files: Vec<_> = index.chunks.iter().map(|c| c.file.clone()).collect();
+        assert_eq!(recording.chunk_files(), files);
+
+        // chunks span up to `keep_last / 16`, so a chunk is kept while its
+        // last message is within `keep_last` of the newest message
+        let messages = recording.messages();
+        let first = *messages.first().unwrap();
+        assert!((99 - 16 - 1..=99 - 16).contains(&first), "first: {first}");
+        assert_eq!(messages, (first..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn unlimited_recording_keeps_all_chunks() {
+        let (recording, index) = Recording::write(
+            "unlimited",
+            Default::default(),
+            100,
+            Duration::from_millis(10),
+            10,
+        );
+        assert_eq!(index.deleted_messages, 0);
+        assert_eq!(recording.messages(), (0..100).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,174 @@
+//! Conversion of recordings to [MCAP](https://mcap.dev), e.g. for viewing them
+//! in Foxglove Studio.
+//!
+//! Each output is written to a channel with topic `/<node_id>/<output_id>`.
+//! The messages are JSON-encoded objects with the following fields:
+//!
+//! - `timestamp`: send time of the message, in nanoseconds since the UNIX epoch
+//! - `parameters`: the metadata parameters of the message
+//! - `data`: the values of the Arrow array as JSON array
+//!
+//! The JSON schema of each channel is derived from the Arrow data type of the
+//! output. If the data type of an output changes during the recording, a new
+//! channel with the same topic is created.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use arrow::datatypes::DataType;
+use eyre::Context;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    json::{arrow_values, parameters_to_json},
+    merge, RecordingReader,
+};
+
+/// Statistics of an MCAP export.
+#[derive(Debug)]
+pub struct McapExport {
+    pub path: PathBuf,
+    pub messages: u64,
+    pub channels: usize,
+    /// Messages whose data couldn't be converted. They are exported without
+    /// data.
+    pub conversion_errors: u64,
+    pub first_conversion_error: Option<String>,
+}
+
+/// Converts the recording in the given directory to an MCAP file.
+///
+/// The directory is either the directory of the whole recording or of the
+/// part that was written by a single daemon, see
+/// [`RecordingReader::open_all`].
+pub fn export_mcap(recording: &Path, output: &Path) -> eyre::Result<McapExport> {
+    let parts = RecordingReader::open_all(recording)?;
+    let file = File::create(output)
+        .wrap_err_with(|| format!("failed to create `{}`", output.display()))?;
+    let mut writer =
+        mcap::Writer::new(BufWriter::new(file)).context("failed to start MCAP file")?;
+
+    let mut channels: BTreeMap<(String, String), u16> = BTreeMap::new();
+    let mut sequences: BTreeMap<u16, u32> = BTreeMap::new();
+    let mut messages = 0;
+    let mut conversion_errors = 0;
+    let mut first_conversion_error = None;
+    for message in merge(parts.iter().map(|part| part.messages())) {
+        let message = message?;
+        let topic = format!("/{}/{}", message.node_id, message.output_id);
+        let data_type = &message.metadata.type_info.data_type;
+
+        let channel_key = (topic.clone(), format!("{data_type:?}"));
+        let channel_id = match channels.get(&channel_key) {
+            Some(id) => *id,
+            None => {
+                let schema = json!({
+                    "type": "object",
+                    "properties": {
+                        "timestamp": { "type": "integer" },
+                        "parameters": { "type": "object" },
+                        "data": { "type": "array", "items": json_schema(data_type) },
+                    },
+                });
+                let schema = mcap::Schema {
+                    name: format!("dora{topic}"),
+                    encoding: "jsonschema".to_owned(),
+                    data: serde_json::to_vec(&schema)?.into(),
+                };
+                let metadata = BTreeMap::from([
+                    ("dora.node_id".to_owned(), message.node_id.to_string()),
+                    ("dora.output_id".to_owned(), message.output_id.to_string()),
+                    ("dora.data_type".to_owned(), format!("{data_type}")),
+                ]);
+                let channel = mcap::Channel {
+                    topic: topic.clone(),
+                    schema: Some(Arc::new(schema)),
+                    message_encoding: "json".to_owned(),
+                    metadata,
+                };
+                let id = writer
+                    .add_channel(&channel)
+                    .context("failed to write MCAP channel")?;
+                channels.insert(channel_key, id);
+                id
+            }
+        };
+
+        let data = match arrow_values(&message) {
+            Ok(values) => values,
+            Err(err) => {
+                first_conversion_error
+                    .get_or_insert_with(|| format!("failed to convert data of `{topic}`: {err:?}"));
+                conversion_errors += 1;
+                Vec::new()
+            }
+        };
+        let time = message
+            .timestamp()
+            .get_time()
+            .to_duration()
+            .as_nanos()
+            .try_into()
+            .unwrap_or(u64::MAX);
+        let encoded = serde_json::to_vec(&json!({
+            "timestamp": time,
+            "parameters": parameters_to_json(&message),
+            "data": data,
+        }))?;
+
+        let sequence = sequences.entry(channel_id).or_default();
+        *sequence += 1;
+        writer
+            .write_to_known_channel(
+                &mcap::records::MessageHeader {
+                    channel_id,
+                    sequence: *sequence,
+                    log_time: time,
+                    publish_time: time,
+                },
+                &encoded,
+            )
+            .context("failed to write MCAP message")?;
+        messages += 1;
+    }
+    writer.finish().context("failed to finish MCAP file")?;
+
+    Ok(McapExport {
+        path: output.to_owned(),
+        messages,
+        channels: channels.len(),
+        conversion_errors,
+        first_conversion_error,
+    })
+}
+
+/// JSON schema of the values of an Arrow array with the given type.
+fn json_schema(data_type: &DataType) -> Value {
+    match data_type {
+        DataType::Null => json!({ "type": "null" }),
+        DataType::Boolean => json!({ "type": "boolean" }),
+        t if t.is_integer() => json!({ "type": "integer" }),
+        t if t.is_floating() => json!({ "type": "number" }),
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => {
+            json!({ "type": "string" })
+        }
+        DataType::List(field) | DataType::LargeList(field) | DataType::FixedSizeList(field, _) => {
+            json!({ "type": "array", "items": json_schema(field.data_type()) })
+        }
+        DataType::Struct(fields) => {
+            let properties = fields
+                .iter()
+                .map(|field| (field.name().clone(), json_schema(field.data_type())))
+                .collect::<Map<_, _>>();
+            json!({ "type": "object", "properties": properties })
+        }
+        // other types are converted to strings or numbers, depending on the
+        // type
+        _ => json!({}),
+    }
+}
@@ -0,0 +1,150 @@
+use std::path::Path;
+
+use dora_node_api::dora_core::config::DataId;
+use eyre::{bail, Context};
+use rustdds::{policy, QosPolicies, QosPolicyBuilder};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub domain_id: u16,
+    #[serde(default)]
+    pub topics: Vec<Topic>,
+}
+
+impl Config {
+    pub fn read(path: &Path) -> eyre::Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to read `{}`", path.display()))?;
+        let config: Self = serde_yaml::from_str(&raw)
+            .wrap_err_with(|| format!("failed to parse `{}`", path.display()))?;
+        for topic in &config.topics {
+            if topic.input.is_some() == topic.output.is_some() {
+                bail!(
+                    "topic `{}` must have either an `input` or an `output` key",
+                    topic.topic
+                );
+            }
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Topic {
+    pub topic: String,
+    /// Input of the bridge that is written to the topic.
+    pub input: Option<DataId>,
+    /// Output of the bridge that the samples of the topic are sent to.
+    pub output: Option<DataId>,
+    #[serde(default)]
+    pub encoding: Encoding,
+    /// Overrides the DDS type name of the encoding, e.g. to match the type of
+    /// other participants with the same layout.
+    pub type_name: Option<String>,
+    #[serde(default)]
+    pub qos: Qos,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    /// The raw bytes of a `UInt8Array`, as `dora::Bytes`.
+    #[default]
+    Bytes,
+    /// A single string, as `dora::String`.
+    String,
+    /// Any Arrow array as Arrow IPC stream, as `dora::Bytes`.
+    Arrow,
+}
+
+impl Encoding {
+    pub fn type_name(self) -> &'static str {
+        match self {
+            Encoding::Bytes | Encoding::Arrow => "dora::Bytes",
+            Encoding::String => "dora::String",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Qos {
+    pub reliability: Reliability,
+    /// Maximum time that a reliable writer blocks if the history is full.
+    pub max_blocking_time_ms: u64,
+    pub durability: Durability,
+    /// Keep all samples instead of the last `keep_last` samples.
+    pub keep_all: bool,
+    pub keep_last: i32,
+    pub deadline_ms: Option<u64>,
+    pub lifespan_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reliability {
+    #[default]
+    BestEffort,
+    Reliable,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Durability {
+    #[default]
+    Volatile,
+    TransientLocal,
+    Transient,
+    Persistent,
+}
+
+impl Default for Qos {
+    fn default() -> Self {
+        Self {
+            reliability: Reliability::BestEffort,
+            max_blocking_time_ms: 0,
+            durability: Durability::Volatile,
+            keep_all: false,
+            keep_last: 1,
+            deadline_ms: None,
+            lifespan_ms: None,
+        }
+    }
+}
+
+impl From<&Qos> for QosPolicies {
+    fn from(qos: &Qos) -> Self {
+        let millis = |ms: u64| rustdds::Duration::from_frac_seconds(ms as f64 / 1000.0);
+        let mut builder = QosPolicyBuilder::new()
+            .reliability(match qos.reliability {
+                Reliability::BestEffort => policy::Reliability::BestEffort,
+                Reliability::Reliable => policy::Reliability::Reliable {
+                    max_blocking_time: millis(qos.max_blocking_time_ms),
+                },
+            })
+            .durability(match qos.durability {
+                Durability::Volatile => policy::Durability::Volatile,
+                Durability::TransientLocal => policy::Durability::TransientLocal,
+                Durability::Transient => policy::Durability::Transient,
+                Durability::Persistent => policy::Durability::Persistent,
+            })
+            .history(if qos.keep_all {
+                policy::History::KeepAll
+            } else {
+                policy::History::KeepLast {
+                    depth: qos.keep_last,
+                }
+            });
+        if let Some(deadline) = qos.deadline_ms {
+            builder = builder.deadline(policy::Deadline(millis(deadline)));
+        }
+        if let Some(lifespan) = qos.lifespan_ms {
+            builder = builder.lifespan(policy::Lifespan {
+                duration: millis(lifespan),
+            });
+        }
+        builder.build()
+    }
+}
@@ -0,0 +1,229 @@
+//! Bridge between DDS topics and the inputs and outputs of a dataflow.
+//!
+//! Inputs of this node are written to DDS topics and samples of subscribed
+//! topics are sent as outputs. The bridge uses a pure Rust DDS implementation
+//! that interoperates with other DDS implementations such as CycloneDDS and
+//! Fast DDS, so dataflows can co-exist with non-ROS DDS participants.
+
+use std::{collections::HashMap, io::Cursor, path::Path, sync::Arc, time::Duration};
+
+use config::{Config, Encoding};
+use dora_node_api::{
+    arrow::{
+        array::{ArrayRef, StringArray, UInt8Array},
+        ipc::{reader::StreamReader, writer::StreamWriter},
+        record_batch::RecordBatch,
+    },
+    dora_core::config::DataId,
+    ArrowData, DoraNode, Event, MetadataParameters,
+};
+use eyre::{eyre, Context, ContextCompat};
+use rustdds::{no_key, DomainParticipant, QosPolicies, TopicKind};
+use serde::{Deserialize, Serialize};
+
+mod config;
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Sample type of the `bytes` and `arrow` encodings, `struct Bytes {
+/// sequence<octet> data; }` in module `dora`.
+#[derive(Debug, Serialize, Deserialize)]
+struct BytesSample {
+    data: Vec<u8>,
+}
+
+/// Sample type of the `string` encoding, `struct String { string data; }` in
+/// module `dora`.
+#[derive(Debug, Serialize, Deserialize)]
+struct StringSample {
+    data: String,
+}
+
+enum Writer {
+    Bytes(no_key::DataWriterCdr<BytesSample>),
+    String(no_key::DataWriterCdr<StringSample>),
+}
+
+enum Reader {
+    Bytes(no_key::DataReaderCdr<BytesSample>),
+    String(no_key::DataReaderCdr<StringSample>),
+}
+
+fn main() -> eyre::Result<()> {
+    let (mut node, mut events) = DoraNode::init_from_env()?;
+
+    let config_path = std::env::var("CONFIG")
+        .wrap_err("no bridge configuration given, set the `CONFIG` env variable")?;
+    let config = Config::read(Path::new(&config_path))?;
+    let poll_interval = match std::env::var("POLL_INTERVAL_MS") {
+        Ok(ms) => Duration::from_millis(
+            ms.parse()
+                .wrap_err_with(|| format!("invalid POLL_INTERVAL_MS `{ms}`"))?,
+        ),
+        Err(_) => DEFAULT_POLL_INTERVAL,
+    };
+
+    let participant = DomainParticipant::new(config.domain_id)
+        .map_err(|err| eyre!("failed to create DDS participant: {err:?}"))?;
+    let default_qos = QosPolicies::qos_none();
+    let publisher = participant
+        .create_publisher(&default_qos)
+        .map_err(|err| eyre!("failed to create DDS publisher: {err:?}"))?;
+    let subscriber = participant
+        .create_subscriber(&default_qos)
+        .map_err(|err| eyre!("failed to create DDS subscriber: {err:?}"))?;
+
+    let mut writers: HashMap<DataId, (Writer, Encoding)> = HashMap::new();
+    let mut readers: Vec<(DataId, Reader, Encoding)> = Vec::new();
+    for topic_config in &config.topics {
+        let qos = QosPolicies::from(&topic_config.qos);
+        let type_name = topic_config
+            .type_name
+            .clone()
+            .unwrap_or_else(|| topic_config.encoding.type_name().to_owned());
+        let topic = participant
+            .create_topic(
+                topic_config.topic.clone(),
+                type_name,
+                &qos,
+                TopicKind::NoKey,
+            )
+            .map_err(|err| eyre!("failed to create topic `{}`: {err:?}", topic_config.topic))?;
+        let create_error = |err| {
+            eyre!(
+                "failed to create endpoint for `{}`: {err:?}",
+                topic_config.topic
+            )
+        };
+
+        let encoding = topic_config.encoding;
+        if let Some(input) = &topic_config.input {
+            let writer = match encoding {
+                Encoding::Bytes | Encoding::Arrow => Writer::Bytes(
+                    publisher
+                        .create_datawriter_no_key_cdr(&topic, Some(qos))
+                        .map_err(create_error)?,
+                ),
+                Encoding::String => Writer::String(
+                    publisher
+                        .create_datawriter_no_key_cdr(&topic, Some(qos))
+                        .map_err(create_error)?,
+                ),
+            };
+            writers.insert(input.clone(), (writer, encoding));
+        } else if let Some(output) = &topic_config.output {
+            let reader = match encoding {
+                Encoding::Bytes | Encoding::Arrow => Reader::Bytes(
+                    subscriber
+                        .create_datareader_no_key_cdr(&topic, Some(qos))
+                        .map_err(create_error)?,
+                ),
+                Encoding::String => Reader::String(
+                    subscriber
+                        .create_datareader_no_key_cdr(&topic, Some(qos))
+                        .map_err(create_error)?,
+                ),
+            };
+            readers.push((output.clone(), reader, encoding));
+        }
+    }
+
+    loop {
+        match events.recv_timeout(poll_interval) {
+            None | Some(Event::Stop) => break,
+            Some(Event::Input { id, data, .. }) => match writers.get(&id) {
+                Some((writer, encoding)) => {
+                    if let Err(err) = write(writer, *encoding, &data) {
+                        tracing::warn!("failed to write input `{id}` to DDS: {err:?}");
+                    }
+                }
+                None => tracing::warn!("ignoring input `{id}` that is not mapped to a topic"),
+            },
+            // timeouts are reported as error events
+            Some(_) => {}
+        }
+
+        for (output, reader, encoding) in &mut readers {
+            loop {
+                match take(reader, *encoding) {
+                    Ok(Some(data)) => {
+                        node.send_output(output.clone(), MetadataParameters::default(), data)?
+                    }
+                    Ok(None) => break,
+                    Err(err) => {
+                        tracing::warn!("failed to read sample for output `{output}`: {err:?}");
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn write(writer: &Writer, encoding: Encoding, data: &ArrowData) -> eyre::Result<()> {
+    match writer {
+        Writer::Bytes(writer) => {
+            let data = match encoding {
+                Encoding::Arrow => arrow_to_ipc(data)?,
+                _ => Vec::<u8>::try_from(data)?,
+            };
+            writer
+                .write(BytesSample { data }, None)
+                .map_err(|err| eyre!("{:?}", err.forget_data()))
+        }
+        Writer::String(writer) => {
+            let data = <&str>::try_from(data)?.to_owned();
+            writer
+                .write(StringSample { data }, None)
+                .map_err(|err| eyre!("{:?}", err.forget_data()))
+        }
+    }
+}
+
+/// Takes the next sample of the reader, if any.
+fn take(reader: &mut Reader, encoding: Encoding) -> eyre::Result<Option<ArrayRef>> {
+    let array: ArrayRef = match reader {
+        Reader::Bytes(reader) => {
+            let Some(sample) = reader.take_next_sample().map_err(|err| eyre!("{err:?}"))? else {
+                return Ok(None);
+            };
+            let data = sample.into_value().data;
+            match encoding {
+                Encoding::Arrow => ipc_to_arrow(&data)?,
+                _ => Arc::new(UInt8Array::from(data)),
+            }
+        }
+        Reader::String(reader) => {
+            let Some(sample) = reader.take_next_sample().map_err(|err| eyre!("{err:?}"))? else {
+                return Ok(None);
+            };
+            Arc::new(StringArray::from(vec![sample.into_value().data]))
+        }
+    };
+    Ok(Some(array))
+}
+
+fn arrow_to_ipc(data: &ArrayRef) -> eyre::Result<Vec<u8>> {
+    let batch = RecordBatch::try_from_iter([("value", data.clone())])
+        .wrap_err("failed to create record batch")?;
+    let mut writer = StreamWriter::try_new(Vec::new(), &batch.schema())?;
+    writer.write(&batch)?;
+    writer.finish()?;
+    Ok(writer.into_inner()?)
+}
+
+fn ipc_to_arrow(data: &[u8]) -> eyre::Result<ArrayRef> {
+    let mut reader = StreamReader::try_new(Cursor::new(data), None)
+        .wrap_err("failed to read Arrow IPC stream")?;
+    let batch = reader
+        .next()
+        .context("Arrow IPC stream is empty")?
+        .wrap_err("failed to read record batch")?;
+    let column = batch
+        .columns()
+        .first()
+        .context("record batch has no columns")?;
+    Ok(column.clone())
+}
@@ -0,0 +1,383 @@
+//! Source node that watches files and directories and sends an output for
+//! every change.
+//!
+//! Changes of the same file are debounced: the node waits until the file
+//! wasn't touched for `DEBOUNCE_MS` and then sends a single `event` that
+//! summarizes them, e.g. `created` for a file that was created and then
+//! written in several steps. If the node has a `contents` output, it also
+//! sends the contents of created and modified files up to `MAX_FILE_SIZE`.
+
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use dora_node_api::{
+    dora_core::config::DataId,
+    merged::{MergeExternal, MergedEvent},
+    DoraNode, Event, IntoArrow, MetadataParameters, Parameter,
+};
+use eyre::{bail, Context};
+use glob::{MatchOptions, Pattern};
+use notify::{
+    event::{ModifyKind, RenameMode},
+    Config, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+};
+
+const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(200);
+const DEFAULT_MAX_FILE_SIZE: u64 = 16 * 1024 * 1024;
+/// `*` and `?` must not match path separators, only `**` does.
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Created,
+    Modified,
+    Removed,
+}
+
+impl Change {
+    fn as_str(self) -> &'static str {
+        match self {
+            Change::Created => "created",
+            Change::Modified => "modified",
+            Change::Removed => "removed",
+        }
+    }
+
+    /// Combines two consecutive changes of the same file, returns `None` if
+    /// they cancel each other out.
+    fn merge(self, next: Change) -> Option<Change> {
+        match (self, next) {
+            // a file that was only there temporarily
+            (Change::Created, Change::Removed) => None,
+            (Change::Created, _) => Some(Change::Created),
+            (_, Change::Removed) => Some(Change::Removed),
+            // e.g. a file that was replaced atomically
+            (Change::Removed, _) | (Change::Modified, _) => Some(Change::Modified),
+        }
+    }
+}
+
+/// A watched path or glob pattern.
+struct Watch {
+    /// Existing directory that contains all matching files.
+    base: PathBuf,
+    pattern: Pattern,
+    recursive: bool,
+}
+
+impl Watch {
+    fn parse(spec: &str, working_dir: &Path) -> eyre::Result<Self> {
+        let path = working_dir.join(spec);
+        let is_glob = |c: &Component| c.as_os_str().to_string_lossy().contains(['*', '?', '[']);
+        let glob_start = path.components().position(|c| is_glob(&c));
+
+        let watch = match glob_start {
+            Some(index) => {
+                let base: PathBuf = path.components().take(index).collect();
+                let rest: Vec<_> = path
+                    .components()
+                    .skip(index)
+                    .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                    .collect();
+                let recursive = rest.len() > 1 || rest.iter().any(|c| c.contains("**"));
+                let pattern = format!("{}/{}", escape(&base), rest.join("/"));
+                Self {
+                    pattern: Pattern::new(&pattern)
+                        .wrap_err_with(|| format!("invalid glob pattern `{spec}`"))?,
+                    base,
+                    recursive,
+                }
+            }
+            None if path.is_dir() => Self {
+                pattern: Pattern::new(&format!("{}/**/*", escape(&path)))?,
+                base: path,
+                recursive: true,
+            },
+            // watch the parent, so that the file can be created and replaced
+            None => Self {
+                pattern: Pattern::new(&escape(&path))?,
+                base: path
+                    .parent()
+                    .map(Path::to_owned)
+                    .unwrap_or_else(|| working_dir.to_owned()),
+                recursive: false,
+            },
+        };
+        if !watch.base.is_dir() {
+            bail!(
+                "directory `{}` of `{spec}` does not exist",
+                watch.base.display()
+            );
+        }
+        Ok(watch)
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        self.pattern.matches_path_with(path, MATCH_OPTIONS)
+    }
+
+    /// Returns the matching files that exist already.
+    fn existing_files(&self) -> Vec<PathBuf> {
+        fn visit(watch: &Watch, dir: &Path, files: &mut Vec<PathBuf>) {
+            let Ok(entries) = std::fs::read_dir(dir) else {
+                return;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    if watch.recursive {
+                        visit(watch, &path, files);
+                    }
+                } else if watch.matches(&path) {
+                    files.push(path);
+                }
+            }
+        }
+
+        let mut files = Vec::new();
+        visit(self, &self.base, &mut files);
+        files.sort();
+        files
+    }
+}
+
+fn escape(path: &Path) -> String {
+    Pattern::escape(&path.to_string_lossy())
+}
+
+fn main() -> eyre::Result<()> {
+    let (mut node, events) = DoraNode::init_from_env()?;
+
+    let working_dir = std::env::current_dir().wrap_err("failed to get working directory")?;
+    let Ok(specs) = std::env::var("WATCH") else {
+        bail!("WATCH must be set to the comma-separated paths or glob patterns to watch");
+    };
+    let watches = specs
+        .split(',')
+        .map(str::trim)
+        .filter(|spec| !spec.is_empty())
+        .map(|spec| Watch::parse(spec, &working_dir))
+        .collect::<eyre::Result<Vec<_>>>()?;
+    let debounce = match std::env::var("DEBOUNCE_MS") {
+        Ok(value) => Duration::from_millis(parse_env("DEBOUNCE_MS", &value)?),
+        Err(_) => DEFAULT_DEBOUNCE,
+    };
+    let max_file_size = match std::env::var("MAX_FILE_SIZE") {
+        Ok(value) => parse_env("MAX_FILE_SIZE", &value)?,
+        Err(_) => DEFAULT_MAX_FILE_SIZE,
+    };
+    let emit_existing = match std::env::var("EMIT_EXISTING") {
+        Ok(value) => parse_env("EMIT_EXISTING", &value)?,
+        Err(_) => false,
+    };
+
+    let (raw_tx, raw_rx) = flume::unbounded();
+    let mut watcher = RecommendedWatcher::new(
+        move |event: notify::Result<notify::Event>| {
+            let _ = raw_tx.send(event);
+        },
+        Config::default(),
+    )
+    .wrap_err("failed to create file watcher")?;
+    for watch in &watches {
+        let mode = if watch.recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        watcher
+            .watch(&watch.base, mode)
+            .wrap_err_with(|| format!("failed to watch `{}`", watch.base.display()))?;
+    }
+
+    let (changes_tx, changes_rx) = flume::unbounded();
+    std::thread::spawn(move || {
+        debounce_changes(raw_rx, changes_tx, &watches, debounce, emit_existing)
+    });
+
+    let mut outputs = Outputs {
+        event: DataId::from("event".to_owned()),
+        contents: DataId::from("contents".to_owned()),
+        send_contents: node
+            .node_config()
+            .outputs
+            .contains(&DataId::from("contents".to_owned())),
+        max_file_size,
+    };
+    let events = futures::executor::block_on_stream(
+        events.merge_external(Box::pin(changes_rx.into_stream())),
+    );
+    for event in events {
+        match event {
+            MergedEvent::External((path, change)) => {
+                if let Err(err) = outputs.send(&mut node, &path, change) {
+                    tracing::warn!("failed to send change of `{}`: {err:?}", path.display());
+                }
+            }
+            MergedEvent::Dora(Event::Stop) => break,
+            MergedEvent::Dora(_) => {}
+        }
+    }
+
+    drop(watcher);
+    Ok(())
+}
+
+/// Collects the matching changes reported by the file watcher and forwards
+/// them once the file wasn't touched for the debounce duration.
+fn debounce_changes(
+    raw: flume::Receiver<notify::Result<notify::Event>>,
+    changes: flume::Sender<(PathBuf, Change)>,
+    watches: &[Watch],
+    debounce: Duration,
+    emit_existing: bool,
+) {
+    let mut pending: BTreeMap<PathBuf, (Change, Instant)> = BTreeMap::new();
+    if emit_existing {
+        for path in watches.iter().flat_map(Watch::existing_files) {
+            pending.insert(path, (Change::Created, Instant::now()));
+        }
+    }
+
+    loop {
+        let next_due = pending.values().map(|(_, time)| *time + debounce).min();
+        let received = match next_due {
+            Some(due) => raw.recv_deadline(due),
+            None => raw
+                .recv()
+                .map_err(|_| flume::RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(Ok(event)) => {
+                for (path, change) in changes_of(event) {
+                    if !watches.iter().any(|w| w.matches(&path)) {
+                        continue;
+                    }
+                    let now = Instant::now();
+                    match pending.remove(&path) {
+                        Some((previous, _)) => {
+                            if let Some(change) = previous.merge(change) {
+                                pending.insert(path, (change, now));
+                            }
+                        }
+                        None => {
+                            pending.insert(path, (change, now));
+                        }
+                    }
+                }
+            }
+            Ok(Err(err)) => tracing::warn!("file watcher error: {err}"),
+            Err(flume::RecvTimeoutError::Timeout) => {}
+            Err(flume::RecvTimeoutError::Disconnected) => break,
+        }
+
+        let now = Instant::now();
+        let due: Vec<_> = pending
+            .iter()
+            .filter(|(_, (_, time))| now >= *time + debounce)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in due {
+            if let Some((change, _)) = pending.remove(&path) {
+                if changes.send((path, change)).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Translates a file watcher event into changes of single files.
+fn changes_of(event: notify::Event) -> Vec<(PathBuf, Change)> {
+    let change = match event.kind {
+        EventKind::Create(_) => Change::Created,
+        EventKind::Remove(_) => Change::Removed,
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+            // a rename within the watched directories, from the first to the
+            // second path
+            let mut paths = event.paths.into_iter();
+            return paths
+                .next()
+                .map(|from| (from, Change::Removed))
+                .into_iter()
+                .chain(paths.next().map(|to| (to, Change::Created)))
+                .collect();
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => Change::Removed,
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => Change::Created,
+        // only permissions or timestamps changed
+        EventKind::Modify(ModifyKind::Metadata(_)) => return Vec::new(),
+        EventKind::Modify(_) => Change::Modified,
+        EventKind::Access(_) | EventKind::Any | EventKind::Other => return Vec::new(),
+    };
+    event.paths.into_iter().map(|path| (path, change)).collect()
+}
+
+struct Outputs {
+    event: DataId,
+    contents: DataId,
+    /// Whether the node has a `contents` output.
+    send_contents: bool,
+    max_file_size: u64,
+}
+
+impl Outputs {
+    fn send(&mut self, node: &mut DoraNode, path: &Path, change: Change) -> eyre::Result<()> {
+        let mut parameters = MetadataParameters::default();
+        parameters.insert(
+            "kind".to_owned(),
+            Parameter::String(change.as_str().to_owned()),
+        );
+        let size = match change {
+            Change::Removed => None,
+            Change::Created | Change::Modified => match std::fs::metadata(path) {
+                Ok(metadata) if metadata.is_dir() => return Ok(()),
+                Ok(metadata) => Some(metadata.len()),
+                // removed again in the meantime, which is reported separately
+                Err(_) => return Ok(()),
+            },
+        };
+        if let Some(size) = size {
+            parameters.insert("size".to_owned(), Parameter::Integer(size as i64));
+        }
+
+        let path_string = path.to_string_lossy().into_owned();
+        node.send_output(
+            self.event.clone(),
+            parameters.clone(),
+            path_string.as_str().into_arrow(),
+        )?;
+
+        let Some(size) = size.filter(|_| self.send_contents) else {
+            return Ok(());
+        };
+        if size > self.max_file_size {
+            tracing::warn!(
+                "not sending contents of `{path_string}`: \
+                its size of {size} bytes exceeds MAX_FILE_SIZE"
+            );
+            return Ok(());
+        }
+        let data =
+            std::fs::read(path).wrap_err_with(|| format!("failed to read `{path_string}`"))?;
+        parameters.insert("path".to_owned(), Parameter::String(path_string));
+        node.send_output_bytes(self.contents.clone(), parameters, data.len(), &data)
+    }
+}
+
+fn parse_env<T>(name: &str, value: &str) -> eyre::Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    value
+        .parse()
+        .wrap_err_with(|| format!("invalid value `{value}` for {name}"))
+}
@@ -0,0 +1,101 @@
+//! Arrow Flight endpoint for bulk access to dataflow data.
+//!
+//! The node buffers the latest messages of each of its inputs and serves them
+//! through a read-only Arrow Flight service, so that analytics tools such as
+//! pandas or DuckDB can pull columnar data from a running dataflow over the
+//! network. Alternatively or additionally, the node serves the outputs of a
+//! recording, as created by `dora record`.
+
+use std::{net::SocketAddr, sync::Arc};
+
+use arrow_flight::flight_service_server::FlightServiceServer;
+use dora_node_api::{arrow::array::make_array, DoraNode, Event};
+use eyre::Context;
+use store::{Row, Store};
+use tokio::sync::RwLock;
+
+mod service;
+mod store;
+
+const DEFAULT_PORT: u16 = 8815;
+/// Number of messages that are buffered per input.
+const DEFAULT_CAPACITY: usize = 10_000;
+/// Maximum number of messages per record batch.
+const BATCH_SIZE: usize = 1024;
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    let (_node, mut events) = DoraNode::init_from_env()?;
+
+    let port = env_parse("PORT", DEFAULT_PORT)?;
+    let capacity = env_parse("CAPACITY", DEFAULT_CAPACITY)?;
+
+    let store = Arc::new(RwLock::new(Store::default()));
+    if let Ok(dir) = std::env::var("RECORDING") {
+        load_recording(&dir, &mut *store.write().await)
+            .wrap_err_with(|| format!("failed to load recording `{dir}`"))?;
+    }
+
+    let service = FlightServiceServer::new(service::Service::new(store.clone(), BATCH_SIZE));
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let mut server = tokio::spawn(
+        tonic::transport::Server::builder()
+            .add_service(service)
+            .serve(addr),
+    );
+    tracing::info!("Arrow Flight service listening on port {port}");
+
+    loop {
+        let event = tokio::select! {
+            event = events.recv_async() => event,
+            result = &mut server => {
+                result
+                    .wrap_err("Flight service panicked")?
+                    .wrap_err("Flight service failed")?;
+                break;
+            }
+        };
+        match event {
+            None | Some(Event::Stop) => break,
+            Some(Event::Input { id, metadata, data }) => {
+                let timestamp_ns = metadata.timestamp().get_time().to_duration().as_nanos() as i64;
+                let row = Row::new(timestamp_ns, &metadata.parameters, data.0);
+                if let Err(err) = store.write().await.push(&id, row, Some(capacity)) {
+                    tracing::warn!("ignoring message of input `{id}`: {err:?}");
+                }
+            }
+            Some(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds the outputs of a recording as streams named `<node>/<output>`.
+fn load_recording(dir: &str, store: &mut Store) -> eyre::Result<()> {
+    let parts = dora_recording::RecordingReader::open_all(dir)?;
+    for message in dora_recording::merge(parts.iter().map(|part| part.messages())) {
+        let message = message?;
+        let name = format!("{}/{}", message.node_id, message.output_id);
+        let data = make_array(message.to_arrow()?);
+        let timestamp_ns = message.timestamp().get_time().to_duration().as_nanos() as i64;
+        let row = Row::new(timestamp_ns, &message.metadata.parameters, data);
+        if let Err(err) = store.push(&name, row, None) {
+            tracing::warn!("ignoring recorded message of `{name}`: {err:?}");
+        }
+    }
+    Ok(())
+}
+
+fn env_parse<T>(name: &str, default: T) -> eyre::Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .wrap_err_with(|| format!("invalid value `{value}` for {name}")),
+        Err(_) => Ok(default),
+    }
+}
@@ -0,0 +1,165 @@
+//! Read-only Arrow Flight service for the buffered streams.
+
+use std::sync::Arc;
+
+use arrow_flight::{
+    encode::FlightDataEncoderBuilder, error::FlightError, flight_service_server::FlightService,
+    Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightEndpoint, FlightInfo,
+    HandshakeRequest, HandshakeResponse, PollInfo, PutResult, SchemaAsIpc, SchemaResult, Ticket,
+};
+use dora_node_api::arrow::ipc::writer::IpcWriteOptions;
+use futures::{stream::BoxStream, StreamExt, TryStreamExt};
+use tokio::sync::RwLock;
+use tonic::{Request, Response, Status, Streaming};
+
+use crate::store::{Store, Stream};
+
+pub struct Service {
+    store: Arc<RwLock<Store>>,
+    batch_size: usize,
+}
+
+impl Service {
+    pub fn new(store: Arc<RwLock<Store>>, batch_size: usize) -> Self {
+        Self { store, batch_size }
+    }
+}
+
+#[tonic::async_trait]
+impl FlightService for Service {
+    type HandshakeStream = BoxStream<'static, Result<HandshakeResponse, Status>>;
+    type ListFlightsStream = BoxStream<'static, Result<FlightInfo, Status>>;
+    type DoGetStream = BoxStream<'static, Result<FlightData, Status>>;
+    type DoPutStream = BoxStream<'static, Result<PutResult, Status>>;
+    type DoActionStream = BoxStream<'static, Result<arrow_flight::Result, Status>>;
+    type ListActionsStream = BoxStream<'static, Result<ActionType, Status>>;
+    type DoExchangeStream = BoxStream<'static, Result<FlightData, Status>>;
+
+    async fn handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented("authentication is not supported"))
+    }
+
+    async fn list_flights(
+        &self,
+        _request: Request<Criteria>,
+    ) -> Result<Response<Self::ListFlightsStream>, Status> {
+        let store = self.store.read().await;
+        let infos = store
+            .iter()
+            .map(|(name, stream)| flight_info(name, stream))
+            .collect::<Vec<_>>();
+        Ok(Response::new(futures::stream::iter(infos).boxed()))
+    }
+
+    async fn get_flight_info(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        let name = stream_name(&request.into_inner())?;
+        let store = self.store.read().await;
+        let stream = store.get(&name).ok_or_else(|| not_found(&name))?;
+        flight_info(&name, stream).map(Response::new)
+    }
+
+    async fn poll_flight_info(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<PollInfo>, Status> {
+        Err(Status::unimplemented("use `GetFlightInfo` instead"))
+    }
+
+    async fn get_schema(
+        &self,
+        request: Request<FlightDescriptor>,
+    ) -> Result<Response<SchemaResult>, Status> {
+        let name = stream_name(&request.into_inner())?;
+        let store = self.store.read().await;
+        let stream = store.get(&name).ok_or_else(|| not_found(&name))?;
+        let schema = stream.schema();
+        SchemaResult::try_from(SchemaAsIpc::new(&schema, &IpcWriteOptions::default()))
+            .map(Response::new)
+            .map_err(|err| Status::internal(err.to_string()))
+    }
+
+    async fn do_get(
+        &self,
+        request: Request<Ticket>,
+    ) -> Result<Response<Self::DoGetStream>, Status> {
+        let name = String::from_utf8(request.into_inner().ticket.to_vec())
+            .map_err(|_| Status::invalid_argument("ticket is not valid UTF-8"))?;
+        // take a snapshot, so that the lock isn't held while the client reads
+        let (schema, batches) = {
+            let store = self.store.read().await;
+            let stream = store.get(&name).ok_or_else(|| not_found(&name))?;
+            let batches = stream
+                .to_batches(self.batch_size)
+                .map_err(|err| Status::internal(format!("{err:?}")))?;
+            (stream.schema(), batches)
+        };
+        let data = FlightDataEncoderBuilder::new()
+            .with_schema(schema)
+            .build(futures::stream::iter(
+                batches.into_iter().map(Ok::<_, FlightError>),
+            ))
+            .map_err(Status::from);
+        Ok(Response::new(data.boxed()))
+    }
+
+    async fn do_put(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoPutStream>, Status> {
+        Err(Status::unimplemented("the service is read-only"))
+    }
+
+    async fn do_action(
+        &self,
+        _request: Request<Action>,
+    ) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented("no actions are supported"))
+    }
+
+    async fn list_actions(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<Self::ListActionsStream>, Status> {
+        Ok(Response::new(futures::stream::empty().boxed()))
+    }
+
+    async fn do_exchange(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        Err(Status::unimplemented("the service is read-only"))
+    }
+}
+
+fn flight_info(name: &str, stream: &Stream) -> Result<FlightInfo, Status> {
+    let info = FlightInfo::new()
+        .try_with_schema(&stream.schema())
+        .map_err(|err| Status::internal(err.to_string()))?
+        .with_descriptor(FlightDescriptor::new_path(
+            name.split('/').map(str::to_owned).collect(),
+        ))
+        .with_endpoint(FlightEndpoint::new().with_ticket(Ticket::new(name.to_owned())))
+        .with_total_records(stream.num_rows() as i64);
+    Ok(info)
+}
+
+/// Name of the stream that the descriptor refers to, either as path, e.g.
+/// `["camera", "image"]`, or as command, e.g. `camera/image`.
+fn stream_name(descriptor: &FlightDescriptor) -> Result<String, Status> {
+    if !descriptor.path.is_empty() {
+        Ok(descriptor.path.join("/"))
+    } else {
+        String::from_utf8(descriptor.cmd.to_vec())
+            .map_err(|_| Status::invalid_argument("command is not valid UTF-8"))
+    }
+}
+
+fn not_found(name: &str) -> Status {
+    Status::not_found(format!("no stream named `{name}`"))
+}
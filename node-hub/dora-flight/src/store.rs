@@ -0,0 +1,158 @@
+//! Buffered messages of the streams that are served through Arrow Flight.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::Arc,
+};
+
+use dora_node_api::{
+    arrow::{
+        array::{Array, ArrayRef, ListArray, StringArray, TimestampNanosecondArray},
+        buffer::OffsetBuffer,
+        compute::concat,
+        datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit},
+        record_batch::RecordBatch,
+    },
+    MetadataParameters, Parameter,
+};
+use eyre::{bail, Context};
+use serde_json::{json, Map, Value};
+
+/// A single message of a stream.
+pub struct Row {
+    /// Time at which the message was sent, in nanoseconds since the Unix epoch.
+    pub timestamp_ns: i64,
+    /// The metadata parameters, encoded as JSON object.
+    pub parameters: String,
+    pub data: ArrayRef,
+}
+
+impl Row {
+    pub fn new(timestamp_ns: i64, parameters: &MetadataParameters, data: ArrayRef) -> Self {
+        Self {
+            timestamp_ns,
+            parameters: parameters_to_json(parameters),
+            data,
+        }
+    }
+}
+
+/// The messages of an input or recorded output.
+///
+/// All messages of a stream have the data type of the first message.
+pub struct Stream {
+    data_type: DataType,
+    rows: VecDeque<Row>,
+}
+
+impl Stream {
+    /// Schema of the record batches of this stream.
+    ///
+    /// Each row is a message, with the data of the message as list.
+    pub fn schema(&self) -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new(
+                "timestamp",
+                DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into())),
+                false,
+            ),
+            Field::new("parameters", DataType::Utf8, false),
+            Field::new("data", DataType::List(self.list_field()), false),
+        ]))
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Converts the buffered messages to record batches with at most
+    /// `batch_size` rows each.
+    pub fn to_batches(&self, batch_size: usize) -> eyre::Result<Vec<RecordBatch>> {
+        let rows: Vec<_> = self.rows.iter().collect();
+        rows.chunks(batch_size.max(1))
+            .map(|rows| self.to_batch(rows))
+            .collect()
+    }
+
+    fn to_batch(&self, rows: &[&Row]) -> eyre::Result<RecordBatch> {
+        let timestamps =
+            TimestampNanosecondArray::from_iter_values(rows.iter().map(|row| row.timestamp_ns))
+                .with_timezone("UTC");
+        let parameters =
+            StringArray::from_iter_values(rows.iter().map(|row| row.parameters.as_str()));
+        let values = concat(&rows.iter().map(|row| row.data.as_ref()).collect::<Vec<_>>())
+            .context("failed to concatenate message data")?;
+        let offsets = OffsetBuffer::from_lengths(rows.iter().map(|row| row.data.len()));
+        let data = ListArray::try_new(self.list_field(), offsets, values, None)
+            .context("failed to create list array")?;
+        RecordBatch::try_new(
+            self.schema(),
+            vec![Arc::new(timestamps), Arc::new(parameters), Arc::new(data)],
+        )
+        .context("failed to create record batch")
+    }
+
+    fn list_field(&self) -> Arc<Field> {
+        Arc::new(Field::new_list_field(self.data_type.clone(), true))
+    }
+}
+
+/// The streams by name.
+#[derive(Default)]
+pub struct Store {
+    streams: BTreeMap<String, Stream>,
+}
+
+impl Store {
+    /// Appends a message to the stream with the given name.
+    ///
+    /// If `capacity` is given, the oldest messages are dropped to keep at most
+    /// `capacity` messages.
+    pub fn push(&mut self, name: &str, row: Row, capacity: Option<usize>) -> eyre::Result<()> {
+        let stream = self
+            .streams
+            .entry(name.to_owned())
+            .or_insert_with(|| Stream {
+                data_type: row.data.data_type().clone(),
+                rows: VecDeque::new(),
+            });
+        if row.data.data_type() != &stream.data_type {
+            bail!(
+                "data type `{}` differs from type `{}` of the previous messages",
+                row.data.data_type(),
+                stream.data_type
+            );
+        }
+        if let Some(capacity) = capacity {
+            while stream.rows.len() >= capacity.max(1) {
+                stream.rows.pop_front();
+            }
+        }
+        stream.rows.push_back(row);
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Stream> {
+        self.streams.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Stream)> {
+        self.streams.iter()
+    }
+}
+
+fn parameters_to_json(parameters: &MetadataParameters) -> String {
+    let parameters = parameters
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Parameter::Bool(value) => json!(value),
+                Parameter::Integer(value) => json!(value),
+                Parameter::String(value) => json!(value),
+                Parameter::ListInt(value) => json!(value),
+            };
+            (key.clone(), value)
+        })
+        .collect::<Map<_, _>>();
+    Value::Object(parameters).to_string()
+}
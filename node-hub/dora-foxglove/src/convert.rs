@@ -0,0 +1,196 @@
+//! Conversion of inputs to JSON messages with Foxglove schemas.
+
+use base64::Engine;
+use dora_node_api::{
+    arrow::{
+        array::{Array, ArrayRef, AsArray},
+        compute::cast,
+        datatypes::{DataType, Float64Type},
+        json::ArrayWriter,
+        record_batch::RecordBatch,
+    },
+    ArrowData, Metadata, Parameter,
+};
+use eyre::{bail, Context};
+use serde_json::{json, Map, Value};
+
+/// The kind of an input, which determines the schema of its channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// Image data with `width`, `height` and `encoding` metadata parameters,
+    /// sent as `foxglove.RawImage`.
+    Image,
+    /// Seven floats `[x, y, z, qx, qy, qz, qw]` with `parent_frame_id` and
+    /// `child_frame_id` metadata parameters, sent as
+    /// `foxglove.FrameTransform`.
+    Transform,
+    /// Any other data, sent as `{ "data": [...] }`.
+    Values,
+}
+
+impl Kind {
+    pub fn of(metadata: &Metadata) -> Self {
+        let has = |key: &str| metadata.parameters.contains_key(key);
+        if has("width") && has("height") && has("encoding") {
+            Kind::Image
+        } else if has("parent_frame_id") && has("child_frame_id") {
+            Kind::Transform
+        } else {
+            Kind::Values
+        }
+    }
+
+    /// Name and JSON schema of channels of this kind.
+    pub fn schema(self, data_type: &DataType) -> (String, String) {
+        let timestamp = json!({
+            "type": "object",
+            "properties": { "sec": { "type": "integer" }, "nsec": { "type": "integer" } },
+        });
+        let vector = |keys: &[&str]| {
+            let properties: Map<_, _> = keys
+                .iter()
+                .map(|key| (key.to_string(), json!({ "type": "number" })))
+                .collect();
+            json!({ "type": "object", "properties": properties })
+        };
+        match self {
+            Kind::Image => {
+                let schema = json!({
+                    "type": "object",
+                    "properties": {
+                        "timestamp": timestamp,
+                        "frame_id": { "type": "string" },
+                        "width": { "type": "integer" },
+                        "height": { "type": "integer" },
+                        "encoding": { "type": "string" },
+                        "step": { "type": "integer" },
+                        "data": { "type": "string", "contentEncoding": "base64" },
+                    },
+                });
+                ("foxglove.RawImage".to_owned(), schema.to_string())
+            }
+            Kind::Transform => {
+                let schema = json!({
+                    "type": "object",
+                    "properties": {
+                        "timestamp": timestamp,
+                        "parent_frame_id": { "type": "string" },
+                        "child_frame_id": { "type": "string" },
+                        "translation": vector(&["x", "y", "z"]),
+                        "rotation": vector(&["x", "y", "z", "w"]),
+                    },
+                });
+                ("foxglove.FrameTransform".to_owned(), schema.to_string())
+            }
+            Kind::Values => {
+                let schema = json!({
+                    "type": "object",
+                    "properties": {
+                        "data": { "type": "array", "items": json_schema(data_type) },
+                    },
+                });
+                (format!("dora.{data_type}"), schema.to_string())
+            }
+        }
+    }
+
+    /// Converts an input to a JSON message that matches the schema.
+    pub fn to_json(self, metadata: &Metadata, data: &ArrowData) -> eyre::Result<Value> {
+        let time = metadata.timestamp().get_time().to_duration();
+        let timestamp = json!({ "sec": time.as_secs(), "nsec": time.subsec_nanos() });
+        let string = |key: &str| match metadata.parameters.get(key) {
+            Some(Parameter::String(value)) => value.clone(),
+            _ => String::new(),
+        };
+        let integer = |key: &str| match metadata.parameters.get(key) {
+            Some(Parameter::Integer(value)) => *value,
+            _ => 0,
+        };
+
+        let message = match self {
+            Kind::Image => {
+                let bytes = <&[u8]>::try_from(data).wrap_err("image data must be UInt8Array")?;
+                let (width, height) = (integer("width"), integer("height"));
+                let encoding = string("encoding");
+                let step = match encoding.as_str() {
+                    "mono8" | "8UC1" => width,
+                    "mono16" | "16UC1" => width * 2,
+                    "rgba8" | "bgra8" => width * 4,
+                    "rgb8" | "bgr8" => width * 3,
+                    _ if height > 0 => bytes.len() as i64 / height,
+                    _ => 0,
+                };
+                json!({
+                    "timestamp": timestamp,
+                    "frame_id": string("frame_id"),
+                    "width": width,
+                    "height": height,
+                    "encoding": encoding,
+                    "step": step,
+                    "data": base64::engine::general_purpose::STANDARD.encode(bytes),
+                })
+            }
+            Kind::Transform => {
+                let array: &ArrayRef = data;
+                let values = cast(array.as_ref(), &DataType::Float64)
+                    .wrap_err("transform must be numeric")?;
+                let values = values.as_primitive::<Float64Type>();
+                if values.len() != 7 || values.null_count() > 0 {
+                    bail!("transform must consist of seven values `[x, y, z, qx, qy, qz, qw]`");
+                }
+                let v = values.values();
+                json!({
+                    "timestamp": timestamp,
+                    "parent_frame_id": string("parent_frame_id"),
+                    "child_frame_id": string("child_frame_id"),
+                    "translation": { "x": v[0], "y": v[1], "z": v[2] },
+                    "rotation": { "x": v[3], "y": v[4], "z": v[5], "w": v[6] },
+                })
+            }
+            Kind::Values => json!({ "data": array_to_json(data)? }),
+        };
+        Ok(message)
+    }
+}
+
+fn array_to_json(data: &ArrayRef) -> eyre::Result<Value> {
+    let batch = RecordBatch::try_from_iter([("value", data.clone())])
+        .wrap_err("failed to create record batch")?;
+    let mut writer = ArrayWriter::new(Vec::new());
+    writer
+        .write(&batch)
+        .and_then(|()| writer.finish())
+        .wrap_err("failed to convert data to JSON")?;
+    let rows: Vec<Map<String, Value>> = serde_json::from_slice(&writer.into_inner())
+        .wrap_err("failed to parse JSON conversion of data")?;
+    // null values are omitted by the writer
+    let values = rows
+        .into_iter()
+        .map(|mut row| row.remove("value").unwrap_or(Value::Null))
+        .collect();
+    Ok(Value::Array(values))
+}
+
+/// JSON schema of the values of an Arrow array with the given type.
+fn json_schema(data_type: &DataType) -> Value {
+    match data_type {
+        DataType::Null => json!({ "type": "null" }),
+        DataType::Boolean => json!({ "type": "boolean" }),
+        t if t.is_integer() => json!({ "type": "integer" }),
+        t if t.is_floating() => json!({ "type": "number" }),
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => {
+            json!({ "type": "string" })
+        }
+        DataType::List(field) | DataType::LargeList(field) | DataType::FixedSizeList(field, _) => {
+            json!({ "type": "array", "items": json_schema(field.data_type()) })
+        }
+        DataType::Struct(fields) => {
+            let properties = fields
+                .iter()
+                .map(|field| (field.name().clone(), json_schema(field.data_type())))
+                .collect::<Map<_, _>>();
+            json!({ "type": "object", "properties": properties })
+        }
+        _ => json!({}),
+    }
+}
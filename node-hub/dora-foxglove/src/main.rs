@@ -0,0 +1,258 @@
+//! Live visualization of a dataflow in Foxglove Studio.
+//!
+//! This node implements the server side of the Foxglove WebSocket protocol
+//! (`foxglove.websocket.v1`). Every input of the node is advertised as a
+//! channel with a JSON schema when its first message arrives, so Foxglove
+//! Studio can connect directly to the node and subscribe to images,
+//! transforms, and plottable values of a running dataflow.
+
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+
+use convert::Kind;
+use dora_node_api::{arrow::array::Array, dora_core::config::DataId, DoraNode, Event};
+use eyre::Context;
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::{broadcast, Mutex},
+};
+use tokio_tungstenite::tungstenite::{
+    handshake::server::{ErrorResponse, Request, Response},
+    http::HeaderValue,
+    Message,
+};
+
+mod convert;
+
+const DEFAULT_PORT: u16 = 8765;
+const SUBPROTOCOL: &str = "foxglove.websocket.v1";
+/// Number of messages that are buffered for slow clients.
+///
+/// Clients that fall further behind skip the oldest messages.
+const CLIENT_CAPACITY: usize = 64;
+/// Opcode of binary `Message Data` frames.
+const MESSAGE_DATA_OPCODE: u8 = 0x01;
+
+#[derive(Debug, Clone)]
+enum ServerMessage {
+    Advertise(Value),
+    Message {
+        channel_id: u32,
+        timestamp_ns: u64,
+        payload: Arc<[u8]>,
+    },
+}
+
+struct State {
+    /// Advertisements of all channels, sent to newly connected clients.
+    channels: Mutex<Vec<Value>>,
+    sender: broadcast::Sender<ServerMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+enum ClientMessage {
+    Subscribe {
+        subscriptions: Vec<Subscription>,
+    },
+    Unsubscribe {
+        #[serde(rename = "subscriptionIds")]
+        subscription_ids: Vec<u32>,
+    },
+    #[serde(other)]
+    Unsupported,
+}
+
+#[derive(Debug, Deserialize)]
+struct Subscription {
+    id: u32,
+    #[serde(rename = "channelId")]
+    channel_id: u32,
+}
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    let (_node, mut events) = DoraNode::init_from_env()?;
+
+    let port = match std::env::var("PORT") {
+        Ok(port) => port
+            .parse()
+            .wrap_err_with(|| format!("invalid PORT `{port}`"))?,
+        Err(_) => DEFAULT_PORT,
+    };
+    let listener = TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], port)))
+        .await
+        .wrap_err_with(|| format!("failed to listen on port {port}"))?;
+    tracing::info!("Foxglove WebSocket server listening on port {port}");
+
+    let (sender, _) = broadcast::channel(CLIENT_CAPACITY);
+    let state = Arc::new(State {
+        channels: Mutex::new(Vec::new()),
+        sender,
+    });
+    tokio::spawn(accept_loop(listener, state.clone()));
+
+    let mut channels: HashMap<DataId, (u32, Kind)> = HashMap::new();
+    while let Some(event) = events.recv_async().await {
+        match event {
+            Event::Input { id, metadata, data } => {
+                let (channel_id, kind) = match channels.get(&id) {
+                    Some(channel) => *channel,
+                    None => {
+                        // the schema of a channel is determined by its first message
+                        let channel_id = channels.len() as u32;
+                        let kind = Kind::of(&metadata);
+                        let (schema_name, schema) = kind.schema(data.data_type());
+                        let advertisement = json!({
+                            "id": channel_id,
+                            "topic": format!("/{id}"),
+                            "encoding": "json",
+                            "schemaName": schema_name,
+                            "schema": schema,
+                        });
+                        let mut advertised = state.channels.lock().await;
+                        advertised.push(advertisement.clone());
+                        let _ = state.sender.send(ServerMessage::Advertise(advertisement));
+                        channels.insert(id.clone(), (channel_id, kind));
+                        (channel_id, kind)
+                    }
+                };
+
+                // skip the conversion if no client is connected
+                if state.sender.receiver_count() == 0 {
+                    continue;
+                }
+                let message = match kind.to_json(&metadata, &data) {
+                    Ok(message) => message,
+                    Err(err) => {
+                        tracing::warn!("failed to convert input `{id}`: {err:?}");
+                        continue;
+                    }
+                };
+                let timestamp_ns = metadata
+                    .timestamp()
+                    .get_time()
+                    .to_duration()
+                    .as_nanos()
+                    .try_into()
+                    .unwrap_or(u64::MAX);
+                let _ = state.sender.send(ServerMessage::Message {
+                    channel_id,
+                    timestamp_ns,
+                    payload: serde_json::to_vec(&message)?.into(),
+                });
+            }
+            Event::Stop => break,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+async fn accept_loop(listener: TcpListener, state: Arc<State>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, addr)) => {
+                let state = state.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = handle_client(stream, state).await {
+                        tracing::warn!("connection to {addr} failed: {err:?}");
+                    }
+                });
+            }
+            Err(err) => tracing::warn!("failed to accept connection: {err}"),
+        }
+    }
+}
+
+async fn handle_client(stream: TcpStream, state: Arc<State>) -> eyre::Result<()> {
+    let negotiate_subprotocol = |request: &Request, mut response: Response| {
+        let requested = request
+            .headers()
+            .get_all("Sec-WebSocket-Protocol")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|protocol| protocol.trim() == SUBPROTOCOL);
+        if requested {
+            response.headers_mut().insert(
+                "Sec-WebSocket-Protocol",
+                HeaderValue::from_static(SUBPROTOCOL),
+            );
+        }
+        Ok::<_, ErrorResponse>(response)
+    };
+    let websocket = tokio_tungstenite::accept_hdr_async(stream, negotiate_subprotocol)
+        .await
+        .wrap_err("WebSocket handshake failed")?;
+    let (mut sink, mut incoming) = websocket.split();
+
+    // subscribe under the lock, so that no channel is missed or advertised twice
+    let (advertised, mut messages) = {
+        let channels = state.channels.lock().await;
+        (channels.clone(), state.sender.subscribe())
+    };
+
+    let server_info = json!({
+        "op": "serverInfo",
+        "name": "dora",
+        "capabilities": [],
+        "supportedEncodings": [],
+        "metadata": {},
+    });
+    sink.send(Message::Text(server_info.to_string())).await?;
+    if !advertised.is_empty() {
+        let advertise = json!({ "op": "advertise", "channels": advertised });
+        sink.send(Message::Text(advertise.to_string())).await?;
+    }
+
+    // subscription IDs by channel ID
+    let mut subscriptions: HashMap<u32, u32> = HashMap::new();
+    loop {
+        tokio::select! {
+            message = incoming.next() => match message {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str(&text) {
+                    Ok(ClientMessage::Subscribe { subscriptions: new }) => {
+                        for subscription in new {
+                            subscriptions.insert(subscription.channel_id, subscription.id);
+                        }
+                    }
+                    Ok(ClientMessage::Unsubscribe { subscription_ids }) => {
+                        subscriptions.retain(|_, id| !subscription_ids.contains(id));
+                    }
+                    Ok(ClientMessage::Unsupported) => {}
+                    Err(err) => tracing::debug!("ignoring invalid client message: {err}"),
+                },
+                Some(Ok(Message::Close(_))) | None => break,
+                Some(Ok(_)) => {}
+                Some(Err(err)) => return Err(err.into()),
+            },
+            message = messages.recv() => match message {
+                Ok(ServerMessage::Advertise(channel)) => {
+                    let advertise = json!({ "op": "advertise", "channels": [channel] });
+                    sink.send(Message::Text(advertise.to_string())).await?;
+                }
+                Ok(ServerMessage::Message { channel_id, timestamp_ns, payload }) => {
+                    let Some(subscription_id) = subscriptions.get(&channel_id) else {
+                        continue;
+                    };
+                    let mut frame = Vec::with_capacity(13 + payload.len());
+                    frame.push(MESSAGE_DATA_OPCODE);
+                    frame.extend_from_slice(&subscription_id.to_le_bytes());
+                    frame.extend_from_slice(&timestamp_ns.to_le_bytes());
+                    frame.extend_from_slice(&payload);
+                    sink.send(Message::Binary(frame)).await?;
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::debug!("client fell behind, skipped {skipped} messages");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        }
+    }
+
+    Ok(())
+}
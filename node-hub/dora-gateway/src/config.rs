@@ -0,0 +1,79 @@
+use std::{net::SocketAddr, path::Path};
+
+use dora_node_api::dora_core::config::DataId;
+use eyre::{bail, Context};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct Config {
+    /// Address of the HTTP server.
+    #[serde(default = "default_listen")]
+    pub listen: SocketAddr,
+    /// Origin of web frontends that are allowed to access the gateway, e.g.
+    /// `https://app.example.com` or `*` for any origin.
+    ///
+    /// Without this setting, browsers only allow access from the origin of the
+    /// gateway itself.
+    #[serde(default)]
+    pub allow_origin: Option<String>,
+    /// Maximum size of the body of POST requests, in bytes.
+    #[serde(default = "default_max_body_len")]
+    pub max_body_len: usize,
+    /// Inputs of the gateway that are streamed to clients.
+    #[serde(default)]
+    pub streams: Vec<Stream>,
+    /// Outputs of the gateway that clients can send to through HTTP POST.
+    #[serde(default)]
+    pub endpoints: Vec<Endpoint>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Stream {
+    pub input: DataId,
+    #[serde(flatten)]
+    pub auth: Auth,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Endpoint {
+    pub output: DataId,
+    #[serde(flatten)]
+    pub auth: Auth,
+}
+
+/// Token that clients need to pass, either directly or through an env
+/// variable.
+#[derive(Debug, Default, Deserialize)]
+pub struct Auth {
+    token: Option<String>,
+    token_env: Option<String>,
+}
+
+impl Auth {
+    pub fn token(&self) -> eyre::Result<Option<String>> {
+        match (&self.token, &self.token_env) {
+            (Some(_), Some(_)) => bail!("only one of `token` and `token_env` can be set"),
+            (Some(token), None) => Ok(Some(token.clone())),
+            (None, Some(var)) => std::env::var(var)
+                .map(Some)
+                .wrap_err_with(|| format!("failed to read token from env variable `{var}`")),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+impl Config {
+    pub fn read(path: &Path) -> eyre::Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to read `{}`", path.display()))?;
+        serde_yaml::from_str(&raw).wrap_err_with(|| format!("failed to parse `{}`", path.display()))
+    }
+}
+
+fn default_listen() -> SocketAddr {
+    ([0, 0, 0, 0], 8080).into()
+}
+
+fn default_max_body_len() -> usize {
+    16 * 1024 * 1024
+}
@@ -0,0 +1,79 @@
+//! Conversion between Arrow arrays and JSON.
+
+use std::sync::Arc;
+
+use dora_node_api::{
+    arrow::{
+        array::{ArrayRef, BooleanArray, Float64Array, Int64Array, StringArray},
+        json::ArrayWriter,
+        record_batch::RecordBatch,
+    },
+    Metadata, Parameter,
+};
+use eyre::Context;
+use serde_json::{json, Map, Value};
+
+/// Serializes an input as JSON message for clients.
+pub fn input_to_json(id: &str, metadata: &Metadata, data: &ArrayRef) -> eyre::Result<String> {
+    let parameters: Map<_, _> = metadata
+        .parameters
+        .iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Parameter::Bool(value) => json!(value),
+                Parameter::Integer(value) => json!(value),
+                Parameter::String(value) => json!(value),
+                Parameter::ListInt(value) => json!(value),
+            };
+            (key.clone(), value)
+        })
+        .collect();
+    let message = json!({
+        "id": id,
+        "timestamp": metadata.timestamp().get_time().to_duration().as_nanos() as u64,
+        "parameters": parameters,
+        "data": array_to_json(data)?,
+    });
+    Ok(message.to_string())
+}
+
+fn array_to_json(data: &ArrayRef) -> eyre::Result<Value> {
+    let batch = RecordBatch::try_from_iter([("value", data.clone())])
+        .wrap_err("failed to create record batch")?;
+    let mut writer = ArrayWriter::new(Vec::new());
+    writer
+        .write(&batch)
+        .and_then(|()| writer.finish())
+        .wrap_err("failed to convert data to JSON")?;
+    let rows: Vec<Map<String, Value>> = serde_json::from_slice(&writer.into_inner())
+        .wrap_err("failed to parse JSON conversion of data")?;
+    // null values are omitted by the writer
+    let values = rows
+        .into_iter()
+        .map(|mut row| row.remove("value").unwrap_or(Value::Null))
+        .collect();
+    Ok(Value::Array(values))
+}
+
+/// Converts a JSON value or a list of JSON values of the same primitive type
+/// to an Arrow array.
+///
+/// Returns `None` for other values, e.g. objects.
+pub fn json_to_array(value: Value) -> Option<ArrayRef> {
+    let values = match value {
+        Value::Array(values) => values,
+        value => vec![value],
+    };
+    let array: ArrayRef = if values.iter().all(Value::is_boolean) {
+        Arc::new(values.iter().map(Value::as_bool).collect::<BooleanArray>())
+    } else if values.iter().all(Value::is_i64) {
+        Arc::new(values.iter().map(Value::as_i64).collect::<Int64Array>())
+    } else if values.iter().all(Value::is_number) {
+        Arc::new(values.iter().map(Value::as_f64).collect::<Float64Array>())
+    } else if values.iter().all(Value::is_string) {
+        Arc::new(values.iter().map(Value::as_str).collect::<StringArray>())
+    } else {
+        return None;
+    };
+    Some(array)
+}
@@ -0,0 +1,412 @@
+//! Gateway between a dataflow and web frontends.
+//!
+//! Inputs of this node are streamed to clients through WebSocket or
+//! server-sent events, and clients can send outputs of this node through HTTP
+//! POST requests. Each stream and endpoint can require a token, which is
+//! passed as `Authorization: Bearer <token>` header or as `token` query
+//! parameter, as browsers can't set headers for WebSockets and event sources.
+
+use std::{collections::HashMap, convert::Infallible, path::Path, sync::Arc};
+
+use dora_node_api::{
+    arrow::array::{ArrayRef, StringArray, UInt8Array},
+    dora_core::config::DataId,
+    merged::{MergeExternalSend, MergedEvent},
+    DoraNode, Event, MetadataParameters, Parameter,
+};
+use eyre::Context;
+use futures::{future, SinkExt, StreamExt};
+use hyper::{
+    body::HttpBody,
+    header::{
+        HeaderValue, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+        ACCESS_CONTROL_ALLOW_ORIGIN, AUTHORIZATION, CACHE_CONTROL, CONNECTION, CONTENT_LENGTH,
+        CONTENT_TYPE, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, UPGRADE,
+    },
+    server::conn::AddrStream,
+    service::{make_service_fn, service_fn},
+    upgrade::Upgraded,
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream, ReceiverStream};
+use tokio_tungstenite::{
+    tungstenite::{handshake::derive_accept_key, protocol::Role, Message},
+    WebSocketStream,
+};
+
+mod config;
+mod convert;
+
+/// Number of messages that are buffered per stream for slow clients.
+///
+/// Clients that fall further behind skip the oldest messages.
+const STREAM_CAPACITY: usize = 64;
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    let (mut node, events) = DoraNode::init_from_env()?;
+
+    let config_path = std::env::var("CONFIG")
+        .wrap_err("no gateway configuration given, set the `CONFIG` env variable")?;
+    let config = config::Config::read(Path::new(&config_path))?;
+
+    let mut streams = HashMap::new();
+    for stream in &config.streams {
+        let (sender, _) = broadcast::channel(STREAM_CAPACITY);
+        let route = StreamRoute {
+            token: stream.auth.token()?,
+            sender,
+        };
+        streams.insert(stream.input.clone(), route);
+    }
+    let mut endpoints = HashMap::new();
+    for endpoint in &config.endpoints {
+        endpoints.insert(endpoint.output.clone(), endpoint.auth.token()?);
+    }
+
+    let (server_events_tx, server_events_rx) = mpsc::channel(16);
+    let allow_origin = config
+        .allow_origin
+        .as_deref()
+        .map(HeaderValue::from_str)
+        .transpose()
+        .wrap_err("invalid `allow_origin`")?;
+    let state = Arc::new(State {
+        streams,
+        endpoints,
+        allow_origin,
+        max_body_len: config.max_body_len,
+        server_events: server_events_tx.clone(),
+    });
+
+    let service_state = state.clone();
+    let new_service = make_service_fn(move |_conn: &AddrStream| {
+        let state = service_state.clone();
+        async move {
+            let service = service_fn(move |req| handle_request(req, state.clone()));
+            Ok::<_, Infallible>(service)
+        }
+    });
+    let server = Server::try_bind(&config.listen)
+        .wrap_err_with(|| format!("failed to listen on {}", config.listen))?
+        .serve(new_service);
+    tracing::info!("gateway listening on {}", config.listen);
+    tokio::spawn(async move {
+        let result = server.await.context("server task failed");
+        if let Err(err) = server_events_tx.send(ServerEvent::Result(result)).await {
+            tracing::warn!("server result channel closed: {err}");
+        }
+    });
+
+    let mut events = events.merge_external_send(ReceiverStream::new(server_events_rx));
+    while let Some(event) = events.next().await {
+        match event {
+            MergedEvent::External(ServerEvent::Result(result)) => {
+                result.context("server failed")?;
+                break;
+            }
+            MergedEvent::External(ServerEvent::Post {
+                output_id,
+                parameters,
+                data,
+                reply,
+            }) => {
+                let result = node.send_output(output_id, parameters, data);
+                if reply.send(result).is_err() {
+                    tracing::debug!("client disconnected before output was sent");
+                }
+            }
+            MergedEvent::Dora(Event::Input { id, metadata, data }) => {
+                let Some(stream) = state.streams.get(&id) else {
+                    tracing::warn!("ignoring input `{id}` that is not streamed");
+                    continue;
+                };
+                if stream.sender.receiver_count() == 0 {
+                    // no connected clients
+                    continue;
+                }
+                match convert::input_to_json(&id, &metadata, &data) {
+                    Ok(message) => {
+                        let _ = stream.sender.send(message.into());
+                    }
+                    Err(err) => tracing::warn!("failed to convert input `{id}`: {err:?}"),
+                }
+            }
+            MergedEvent::Dora(Event::Stop) => break,
+            MergedEvent::Dora(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+struct State {
+    streams: HashMap<DataId, StreamRoute>,
+    /// Required token of each endpoint.
+    endpoints: HashMap<DataId, Option<String>>,
+    /// Value of the `Access-Control-Allow-Origin` header, if any.
+    allow_origin: Option<HeaderValue>,
+    max_body_len: usize,
+    server_events: mpsc::Sender<ServerEvent>,
+}
+
+struct StreamRoute {
+    token: Option<String>,
+    /// JSON messages of the input.
+    sender: broadcast::Sender<Arc<str>>,
+}
+
+enum ServerEvent {
+    Result(eyre::Result<()>),
+    Post {
+        output_id: DataId,
+        parameters: MetadataParameters,
+        data: ArrayRef,
+        reply: oneshot::Sender<eyre::Result<()>>,
+    },
+}
+
+async fn handle_request(
+    req: Request<Body>,
+    state: Arc<State>,
+) -> Result<Response<Body>, Infallible> {
+    let method = req.method().clone();
+    let path = req.uri().path().to_owned();
+    let mut response = match (&method, path.split_once('/').map(|(_, p)| p)) {
+        (&Method::OPTIONS, _) => preflight(),
+        (&Method::GET, Some(route)) if route.starts_with("streams/") => {
+            let id = &route["streams/".len()..];
+            match state.streams.get(id) {
+                Some(stream) if !authorized(&req, &stream.token) => {
+                    error(StatusCode::UNAUTHORIZED, "invalid or missing token")
+                }
+                Some(stream) if is_websocket_upgrade(&req) => {
+                    websocket(req, stream.sender.subscribe())
+                }
+                Some(stream) => event_stream(stream.sender.subscribe()),
+                None => error(StatusCode::NOT_FOUND, "unknown stream"),
+            }
+        }
+        (&Method::POST, Some(route)) if route.starts_with("endpoints/") => {
+            let id = &route["endpoints/".len()..];
+            match state.endpoints.get_key_value(id) {
+                Some((_, token)) if !authorized(&req, token) => {
+                    error(StatusCode::UNAUTHORIZED, "invalid or missing token")
+                }
+                Some((output_id, _)) => post(req, output_id.clone(), &state).await,
+                None => error(StatusCode::NOT_FOUND, "unknown endpoint"),
+            }
+        }
+        _ => error(StatusCode::NOT_FOUND, "not found"),
+    };
+    // allow access from web frontends that are served from another origin
+    if let Some(origin) = &state.allow_origin {
+        response
+            .headers_mut()
+            .insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+    }
+    Ok(response)
+}
+
+fn authorized(req: &Request<Body>, token: &Option<String>) -> bool {
+    let Some(token) = token else {
+        return true;
+    };
+    let header = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let query = req.uri().query().and_then(|query| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(key, _)| key == "token")
+            .map(|(_, value)| value.into_owned())
+    });
+    // check both to not leak through the timing which of them was passed
+    let header_matches = header.is_some_and(|value| tokens_match(value, token));
+    let query_matches = query.is_some_and(|value| tokens_match(&value, token));
+    header_matches | query_matches
+}
+
+/// Compares the tokens in constant time.
+fn tokens_match(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn is_websocket_upgrade(req: &Request<Body>) -> bool {
+    req.headers()
+        .get(UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"))
+}
+
+fn websocket(mut req: Request<Body>, receiver: broadcast::Receiver<Arc<str>>) -> Response<Body> {
+    let Some(key) = req.headers().get(SEC_WEBSOCKET_KEY) else {
+        return error(StatusCode::BAD_REQUEST, "missing Sec-WebSocket-Key header");
+    };
+    let accept = derive_accept_key(key.as_bytes());
+
+    tokio::spawn(async move {
+        match hyper::upgrade::on(&mut req).await {
+            Ok(upgraded) => {
+                let socket = WebSocketStream::from_raw_socket(upgraded, Role::Server, None).await;
+                if let Err(err) = forward_to_websocket(socket, receiver).await {
+                    tracing::debug!("websocket connection failed: {err}");
+                }
+            }
+            Err(err) => tracing::warn!("failed to upgrade to websocket: {err}"),
+        }
+    });
+
+    Response::builder()
+        .status(StatusCode::SWITCHING_PROTOCOLS)
+        .header(CONNECTION, "upgrade")
+        .header(UPGRADE, "websocket")
+        .header(SEC_WEBSOCKET_ACCEPT, accept)
+        .body(Body::empty())
+        .unwrap()
+}
+
+async fn forward_to_websocket(
+    mut socket: WebSocketStream<Upgraded>,
+    mut receiver: broadcast::Receiver<Arc<str>>,
+) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+    loop {
+        tokio::select! {
+            message = receiver.recv() => match message {
+                Ok(message) => socket.send(Message::Text(message.to_string())).await?,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::debug!("websocket client is too slow, skipped {skipped} messages");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            incoming = socket.next() => match incoming {
+                Some(Ok(Message::Close(_))) | None => return Ok(()),
+                // pings are answered by the websocket implementation
+                Some(Ok(_)) => {}
+                Some(Err(err)) => return Err(err),
+            },
+        }
+    }
+    socket.close(None).await
+}
+
+fn event_stream(receiver: broadcast::Receiver<Arc<str>>) -> Response<Body> {
+    let events = BroadcastStream::new(receiver).filter_map(|message| {
+        future::ready(match message {
+            Ok(message) => Some(Ok::<_, Infallible>(format!("data: {message}\n\n"))),
+            Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                tracing::debug!("event stream client is too slow, skipped {skipped} messages");
+                None
+            }
+        })
+    });
+    Response::builder()
+        .header(CONTENT_TYPE, "text/event-stream")
+        .header(CACHE_CONTROL, "no-cache")
+        .body(Body::wrap_stream(events))
+        .unwrap()
+}
+
+/// Sends the body of the request as output.
+///
+/// JSON bodies that consist of primitive values of the same type are converted
+/// to the corresponding Arrow array. Other JSON bodies are sent as string,
+/// and all other bodies as raw bytes.
+async fn post(req: Request<Body>, output_id: DataId, state: &State) -> Response<Body> {
+    let content_type = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(ToOwned::to_owned);
+    let content_length = req
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if content_length.is_some_and(|len| len > state.max_body_len) {
+        return error(StatusCode::PAYLOAD_TOO_LARGE, "body is too large");
+    }
+    let body = match read_body(req.into_body(), state.max_body_len).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+
+    let mut parameters = MetadataParameters::new();
+    let data: ArrayRef = match content_type.as_deref() {
+        Some(content_type) if content_type.starts_with("application/json") => {
+            let value = match serde_json::from_slice(&body) {
+                Ok(value) => value,
+                Err(err) => return error(StatusCode::BAD_REQUEST, &format!("invalid JSON: {err}")),
+            };
+            match convert::json_to_array(value) {
+                Some(array) => array,
+                None => {
+                    let text = String::from_utf8_lossy(&body);
+                    Arc::new(StringArray::from(vec![text.as_ref()]))
+                }
+            }
+        }
+        _ => Arc::new(UInt8Array::from(body)),
+    };
+    if let Some(content_type) = content_type {
+        parameters.insert("content_type".into(), Parameter::String(content_type));
+    }
+
+    let (reply, result) = oneshot::channel();
+    let event = ServerEvent::Post {
+        output_id,
+        parameters,
+        data,
+        reply,
+    };
+    if state.server_events.send(event).await.is_err() {
+        return error(StatusCode::SERVICE_UNAVAILABLE, "dataflow is stopping");
+    }
+    match result.await {
+        Ok(Ok(())) => Response::builder()
+            .status(StatusCode::ACCEPTED)
+            .body(Body::empty())
+            .unwrap(),
+        Ok(Err(err)) => error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &format!("failed to send output: {err}"),
+        ),
+        Err(_) => error(StatusCode::SERVICE_UNAVAILABLE, "dataflow is stopping"),
+    }
+}
+
+/// Reads the body, failing once it exceeds `max_len` bytes.
+///
+/// The `Content-Length` header is optional, e.g. for chunked bodies, so the
+/// limit is checked while reading too.
+async fn read_body(mut body: Body, max_len: usize) -> Result<Vec<u8>, Response<Body>> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk =
+            chunk.map_err(|err| error(StatusCode::BAD_REQUEST, &format!("invalid body: {err}")))?;
+        if bytes.len() + chunk.len() > max_len {
+            return Err(error(StatusCode::PAYLOAD_TOO_LARGE, "body is too large"));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+fn preflight() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .header(ACCESS_CONTROL_ALLOW_METHODS, "GET, POST")
+        .header(ACCESS_CONTROL_ALLOW_HEADERS, "authorization, content-type")
+        .body(Body::empty())
+        .unwrap()
+}
+
+fn error(status: StatusCode, message: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::from(message.to_owned()))
+        .unwrap()
+}
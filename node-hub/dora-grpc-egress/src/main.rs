@@ -0,0 +1,249 @@
+//! Streams the inputs of this node to a remote gRPC service, e.g. for shipping
+//! robot telemetry to a cloud ingestion service.
+//!
+//! The service implements `proto/egress.proto`. Inputs are buffered in a
+//! bounded queue, so that a slow or unreachable service doesn't block the
+//! dataflow: if the queue is full, new inputs are dropped. The connection is
+//! reestablished with exponential backoff when it fails.
+
+use std::{collections::VecDeque, time::Duration};
+
+use dora_node_api::{DoraNode, Event};
+use eyre::{bail, Context};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::{ClientTlsConfig, Endpoint};
+
+mod proto;
+
+const DEFAULT_QUEUE_SIZE: usize = 1000;
+const DEFAULT_MAX_IN_FLIGHT: usize = 100;
+const MIN_RECONNECT_DELAY: Duration = Duration::from_millis(100);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(10);
+/// Time to send the remaining queued messages after the dataflow stopped.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    /// Messages are sent through a client-streaming call, without
+    /// acknowledgements.
+    ClientStreaming,
+    /// Messages are acknowledged by the service through a bidirectional
+    /// call. Unacknowledged messages are sent again after a reconnect.
+    Bidirectional,
+}
+
+fn main() -> eyre::Result<()> {
+    let (_node, mut events) = DoraNode::init_from_env()?;
+
+    let endpoint = std::env::var("ENDPOINT")
+        .wrap_err("no gRPC endpoint given, set the `ENDPOINT` env variable")?;
+    let mode = match std::env::var("MODE").as_deref() {
+        Err(_) | Ok("client-streaming") => Mode::ClientStreaming,
+        Ok("bidirectional") => Mode::Bidirectional,
+        Ok(other) => {
+            bail!("unknown MODE `{other}`, expected `client-streaming` or `bidirectional`")
+        }
+    };
+    let queue_size = env_usize("QUEUE_SIZE", DEFAULT_QUEUE_SIZE)?;
+    let max_in_flight = env_usize("MAX_IN_FLIGHT", DEFAULT_MAX_IN_FLIGHT)?;
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .wrap_err("failed to create tokio runtime")?;
+    let (queue_tx, queue_rx) = mpsc::channel(queue_size);
+    let sender = runtime.spawn(send_loop(endpoint, mode, max_in_flight, queue_rx));
+
+    let mut sequence = 0;
+    let mut dropped = 0u64;
+    while let Some(event) = events.recv() {
+        match event {
+            Event::Input { id, metadata, data } => {
+                let message = match proto::Message::from_input(sequence, &id, &metadata, &data) {
+                    Ok(message) => message,
+                    Err(err) => {
+                        tracing::warn!("failed to convert input `{id}`: {err:?}");
+                        continue;
+                    }
+                };
+                sequence += 1;
+                match queue_tx.try_send(message) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(_)) => {
+                        dropped += 1;
+                        if dropped.is_power_of_two() {
+                            tracing::warn!(
+                                "gRPC service can't keep up, dropped {dropped} messages so far"
+                            );
+                        }
+                    }
+                    // the sender task failed, its error is reported below
+                    Err(TrySendError::Closed(_)) => break,
+                }
+            }
+            Event::Stop => break,
+            _ => {}
+        }
+    }
+
+    drop(queue_tx);
+    match runtime.block_on(tokio::time::timeout(FLUSH_TIMEOUT, sender)) {
+        Ok(result) => result.wrap_err("sender task panicked")?,
+        Err(_) => {
+            tracing::warn!("failed to send remaining messages within {FLUSH_TIMEOUT:?}");
+            Ok(())
+        }
+    }
+}
+
+async fn send_loop(
+    endpoint: String,
+    mode: Mode,
+    max_in_flight: usize,
+    mut queue: mpsc::Receiver<proto::Message>,
+) -> eyre::Result<()> {
+    let mut channel_endpoint = Endpoint::from_shared(endpoint.clone())
+        .wrap_err_with(|| format!("invalid endpoint `{endpoint}`"))?;
+    if endpoint.starts_with("https://") {
+        channel_endpoint = channel_endpoint
+            .tls_config(ClientTlsConfig::new().with_native_roots())
+            .wrap_err("failed to configure TLS")?;
+    }
+
+    let mut unacked = VecDeque::new();
+    let mut delay = MIN_RECONNECT_DELAY;
+    loop {
+        let mut delivered = 0;
+        let result = match channel_endpoint.connect().await {
+            Ok(channel) => {
+                let mut client = proto::EgressClient::new(channel);
+                match mode {
+                    Mode::ClientStreaming => publish(&mut client, &mut queue, &mut delivered).await,
+                    Mode::Bidirectional => {
+                        exchange(
+                            &mut client,
+                            &mut queue,
+                            &mut unacked,
+                            max_in_flight,
+                            &mut delivered,
+                        )
+                        .await
+                    }
+                }
+            }
+            Err(err) => Err(eyre::Report::new(err).wrap_err("failed to connect")),
+        };
+        match result {
+            Ok(()) => return Ok(()),
+            Err(err) => {
+                if delivered > 0 {
+                    delay = MIN_RECONNECT_DELAY;
+                }
+                tracing::warn!(
+                    "gRPC stream to `{endpoint}` failed, reconnecting in {delay:?}: {err:?}"
+                );
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+            }
+        }
+    }
+}
+
+/// Sends queued messages through a client-streaming call until the queue is
+/// closed.
+///
+/// Messages that are in transit when the call fails are lost.
+async fn publish(
+    client: &mut proto::EgressClient,
+    queue: &mut mpsc::Receiver<proto::Message>,
+    delivered: &mut u64,
+) -> eyre::Result<()> {
+    let (tx, rx) = mpsc::channel(1);
+    let call = client.publish(ReceiverStream::new(rx));
+    tokio::pin!(call);
+    loop {
+        tokio::select! {
+            result = &mut call => {
+                result?;
+                bail!("service closed the stream");
+            }
+            message = queue.recv() => match message {
+                Some(message) => {
+                    if tx.send(message).await.is_err() {
+                        call.await?;
+                        bail!("service closed the stream");
+                    }
+                    *delivered += 1;
+                }
+                None => {
+                    drop(tx);
+                    let summary = call.await?.into_inner();
+                    tracing::info!("gRPC service received {} messages", summary.received);
+                    return Ok(());
+                }
+            },
+        }
+    }
+}
+
+/// Sends queued messages through a bidirectional call, until the queue is
+/// closed and all messages are acknowledged.
+///
+/// At most `max_in_flight` messages are sent without acknowledgement, so a
+/// slow service fills up the queue.
+async fn exchange(
+    client: &mut proto::EgressClient,
+    queue: &mut mpsc::Receiver<proto::Message>,
+    unacked: &mut VecDeque<proto::Message>,
+    max_in_flight: usize,
+    delivered: &mut u64,
+) -> eyre::Result<()> {
+    let (tx, rx) = mpsc::channel(max_in_flight.max(unacked.len()).max(1));
+    // send the messages again that were not acknowledged before the
+    // connection failed
+    for message in unacked.iter() {
+        tx.try_send(message.clone())
+            .expect("channel has room for all unacknowledged messages");
+    }
+    let mut acks = client.exchange(ReceiverStream::new(rx)).await?.into_inner();
+
+    let mut queue_closed = false;
+    loop {
+        tokio::select! {
+            ack = acks.message() => match ack? {
+                Some(ack) => {
+                    while unacked.front().is_some_and(|m| m.sequence <= ack.sequence) {
+                        unacked.pop_front();
+                        *delivered += 1;
+                    }
+                    if queue_closed && unacked.is_empty() {
+                        return Ok(());
+                    }
+                }
+                None => bail!("service closed the stream"),
+            },
+            message = queue.recv(), if !queue_closed && unacked.len() < max_in_flight => {
+                match message {
+                    Some(message) => {
+                        unacked.push_back(message.clone());
+                        if tx.send(message).await.is_err() {
+                            bail!("service closed the stream");
+                        }
+                    }
+                    None if unacked.is_empty() => return Ok(()),
+                    None => queue_closed = true,
+                }
+            }
+        }
+    }
+}
+
+fn env_usize(name: &str, default: usize) -> eyre::Result<usize> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .wrap_err_with(|| format!("invalid value `{value}` for {name}")),
+        Err(_) => Ok(default),
+    }
+}
@@ -0,0 +1,128 @@
+//! Messages and client of the `dora.egress.v1.Egress` service, as defined in
+//! `proto/egress.proto`.
+//!
+//! The types are written by hand instead of generated in a build script, so
+//! that building the node doesn't require `protoc`.
+
+use std::collections::HashMap;
+
+use dora_node_api::{
+    arrow::{array::ArrayRef, ipc::writer::StreamWriter, record_batch::RecordBatch},
+    Metadata, Parameter,
+};
+use eyre::Context;
+use tokio_stream::Stream;
+use tonic::{
+    codec::{ProstCodec, Streaming},
+    codegen::http::uri::PathAndQuery,
+    transport::Channel,
+    Response, Status,
+};
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Message {
+    #[prost(uint64, tag = "1")]
+    pub sequence: u64,
+    #[prost(string, tag = "2")]
+    pub input_id: String,
+    #[prost(uint64, tag = "3")]
+    pub timestamp_ns: u64,
+    #[prost(map = "string, string", tag = "4")]
+    pub parameters: HashMap<String, String>,
+    #[prost(bytes = "vec", tag = "5")]
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PublishSummary {
+    #[prost(uint64, tag = "1")]
+    pub received: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Ack {
+    #[prost(uint64, tag = "1")]
+    pub sequence: u64,
+}
+
+impl Message {
+    pub fn from_input(
+        sequence: u64,
+        input_id: &str,
+        metadata: &Metadata,
+        data: &ArrayRef,
+    ) -> eyre::Result<Self> {
+        let parameters = metadata
+            .parameters
+            .iter()
+            .map(|(key, value)| {
+                let value = match value {
+                    Parameter::Bool(value) => value.to_string(),
+                    Parameter::Integer(value) => value.to_string(),
+                    Parameter::String(value) => value.clone(),
+                    Parameter::ListInt(value) => format!("{value:?}"),
+                };
+                (key.clone(), value)
+            })
+            .collect();
+
+        let batch = RecordBatch::try_from_iter([("value", data.clone())])
+            .wrap_err("failed to create record batch")?;
+        let mut writer = StreamWriter::try_new(Vec::new(), &batch.schema())
+            .wrap_err("failed to create Arrow IPC writer")?;
+        writer
+            .write(&batch)
+            .and_then(|()| writer.finish())
+            .wrap_err("failed to serialize data")?;
+        let data = writer.into_inner().wrap_err("failed to serialize data")?;
+
+        Ok(Self {
+            sequence,
+            input_id: input_id.to_owned(),
+            timestamp_ns: metadata.timestamp().get_time().to_duration().as_nanos() as u64,
+            parameters,
+            data,
+        })
+    }
+}
+
+pub struct EgressClient {
+    inner: tonic::client::Grpc<Channel>,
+}
+
+impl EgressClient {
+    pub fn new(channel: Channel) -> Self {
+        Self {
+            inner: tonic::client::Grpc::new(channel),
+        }
+    }
+
+    pub async fn publish(
+        &mut self,
+        messages: impl Stream<Item = Message> + Send + 'static,
+    ) -> Result<Response<PublishSummary>, Status> {
+        self.ready().await?;
+        let path = PathAndQuery::from_static("/dora.egress.v1.Egress/Publish");
+        self.inner
+            .client_streaming(tonic::Request::new(messages), path, ProstCodec::default())
+            .await
+    }
+
+    pub async fn exchange(
+        &mut self,
+        messages: impl Stream<Item = Message> + Send + 'static,
+    ) -> Result<Response<Streaming<Ack>>, Status> {
+        self.ready().await?;
+        let path = PathAndQuery::from_static("/dora.egress.v1.Egress/Exchange");
+        self.inner
+            .streaming(tonic::Request::new(messages), path, ProstCodec::default())
+            .await
+    }
+
+    async fn ready(&mut self) -> Result<(), Status> {
+        self.inner
+            .ready()
+            .await
+            .map_err(|err| Status::unknown(format!("service was not ready: {err}")))
+    }
+}
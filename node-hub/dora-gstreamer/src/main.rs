@@ -0,0 +1,242 @@
+//! Camera and video source that wraps a GStreamer pipeline.
+//!
+//! The node decodes a V4L2 camera, an RTSP stream, a video file, or any custom
+//! GStreamer source, converts the frames to the configured encoding, and sends
+//! them as `image` output. Frames are written directly into the shared memory
+//! of the output, so receivers can access them without copies. Frames that
+//! arrive while the previous frame is still being sent are dropped.
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use dora_node_api::{
+    dora_core::config::DataId,
+    merged::{MergeExternal, MergedEvent},
+    DoraNode, Event, MetadataParameters, MetadataParametersExt, Parameter,
+};
+use eyre::{bail, eyre, Context, ContextCompat};
+use gstreamer::{self as gst, prelude::*};
+use gstreamer_app as gst_app;
+use gstreamer_video as gst_video;
+
+const DEFAULT_SOURCE: &str = "/dev/video0";
+const DEFAULT_ENCODING: &str = "rgb8";
+
+enum PipelineEvent {
+    Frame(gst::Sample),
+    Eos,
+    Error(String),
+}
+
+fn main() -> eyre::Result<()> {
+    let (mut node, events) = DoraNode::init_from_env()?;
+    gst::init().wrap_err("failed to initialize GStreamer")?;
+
+    let encoding = std::env::var("ENCODING").unwrap_or_else(|_| DEFAULT_ENCODING.to_owned());
+    let (format, bytes_per_pixel) = video_format(&encoding)?;
+    let source = match std::env::var("PIPELINE") {
+        Ok(pipeline) => pipeline,
+        Err(_) => source_description(
+            &std::env::var("SOURCE").unwrap_or_else(|_| DEFAULT_SOURCE.to_owned()),
+        ),
+    };
+    let mut caps = format!("video/x-raw,format={format}");
+    if let Ok(width) = std::env::var("IMAGE_WIDTH") {
+        caps += &format!(",width={}", parse_env::<u32>("IMAGE_WIDTH", &width)?);
+    }
+    if let Ok(height) = std::env::var("IMAGE_HEIGHT") {
+        caps += &format!(",height={}", parse_env::<u32>("IMAGE_HEIGHT", &height)?);
+    }
+    let description = format!(
+        "{source} ! videoconvert ! videoscale ! {caps} ! appsink name=sink max-buffers=1 drop=true"
+    );
+
+    let mut parameters = MetadataParameters::default();
+    parameters.insert("encoding".to_owned(), Parameter::String(encoding));
+    if let Ok(frame_id) = std::env::var("FRAME_ID") {
+        parameters.insert("frame_id".to_owned(), Parameter::String(frame_id));
+    }
+    // camera intrinsics, in the format of the other camera nodes
+    for (name, key) in [("FOCAL", "focal"), ("RESOLUTION", "resolution")] {
+        if let Ok(value) = std::env::var(name) {
+            let values = value
+                .split(',')
+                .map(|v| parse_env::<i64>(name, v.trim()))
+                .collect::<eyre::Result<Vec<_>>>()?;
+            if values.len() != 2 {
+                bail!("{name} must consist of two comma-separated integers, got `{value}`");
+            }
+            parameters.insert(key.to_owned(), Parameter::ListInt(values));
+        }
+    }
+
+    let pipeline = gst::parse::launch(&description)
+        .wrap_err_with(|| format!("invalid pipeline `{description}`"))?
+        .downcast::<gst::Pipeline>()
+        .map_err(|_| eyre!("`{description}` is not a pipeline"))?;
+    // use the system time as pipeline clock, so that the buffer timestamps can
+    // be converted to acquisition times
+    let clock = gst::SystemClock::obtain();
+    clock.set_property("clock-type", gst::ClockType::Realtime);
+    pipeline.use_clock(Some(&clock));
+
+    let appsink = pipeline
+        .by_name("sink")
+        .context("pipeline has no `sink` element")?
+        .dynamic_cast::<gst_app::AppSink>()
+        .map_err(|_| eyre!("`sink` element is not an appsink"))?;
+    let (frames_tx, frames_rx) = flume::bounded(1);
+    appsink.set_callbacks(
+        gst_app::AppSinkCallbacks::builder()
+            .new_sample(move |sink| {
+                let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+                // drop the frame if the previous one is still being sent
+                let _ = frames_tx.try_send(PipelineEvent::Frame(sample));
+                Ok(gst::FlowSuccess::Ok)
+            })
+            .build(),
+    );
+
+    let bus = pipeline.bus().context("pipeline has no bus")?;
+    let (bus_tx, bus_rx) = flume::unbounded();
+    std::thread::spawn(move || {
+        for message in bus.iter_timed(gst::ClockTime::NONE) {
+            let event = match message.view() {
+                gst::MessageView::Eos(_) => PipelineEvent::Eos,
+                gst::MessageView::Error(err) => {
+                    PipelineEvent::Error(format!("{} ({:?})", err.error(), err.debug()))
+                }
+                gst::MessageView::Warning(warning) => {
+                    tracing::warn!("GStreamer warning: {}", warning.error());
+                    continue;
+                }
+                _ => continue,
+            };
+            if bus_tx.send(event).is_err() {
+                break;
+            }
+        }
+    });
+
+    pipeline
+        .set_state(gst::State::Playing)
+        .wrap_err("failed to start pipeline")?;
+
+    let output = DataId::from("image".to_owned());
+    let external = futures::stream::select(frames_rx.into_stream(), bus_rx.into_stream());
+    let mut events = futures::executor::block_on_stream(events.merge_external(Box::pin(external)));
+    let result = loop {
+        let Some(event) = events.next() else {
+            break Ok(());
+        };
+        match event {
+            MergedEvent::External(PipelineEvent::Frame(sample)) => {
+                let base_time = pipeline.base_time();
+                if let Err(err) = send_frame(
+                    &mut node,
+                    &output,
+                    &sample,
+                    parameters.clone(),
+                    bytes_per_pixel,
+                    base_time,
+                ) {
+                    tracing::warn!("failed to send frame: {err:?}");
+                }
+            }
+            MergedEvent::External(PipelineEvent::Eos) => {
+                tracing::info!("end of stream");
+                break Ok(());
+            }
+            MergedEvent::External(PipelineEvent::Error(err)) => {
+                break Err(eyre!("pipeline failed: {err}"));
+            }
+            MergedEvent::Dora(Event::Stop) => break Ok(()),
+            MergedEvent::Dora(_) => {}
+        }
+    };
+
+    pipeline
+        .set_state(gst::State::Null)
+        .wrap_err("failed to stop pipeline")?;
+    result
+}
+
+/// Sends a frame without row padding, e.g. `width * 3` bytes per row for
+/// `rgb8`.
+fn send_frame(
+    node: &mut DoraNode,
+    output: &DataId,
+    sample: &gst::Sample,
+    mut parameters: MetadataParameters,
+    bytes_per_pixel: usize,
+    base_time: Option<gst::ClockTime>,
+) -> eyre::Result<()> {
+    let buffer = sample.buffer().context("sample has no buffer")?;
+    let caps = sample.caps().context("sample has no caps")?;
+    let info = gst_video::VideoInfo::from_caps(caps).wrap_err("invalid video caps")?;
+    let frame = gst_video::VideoFrameRef::from_buffer_ref_readable(buffer, &info)
+        .map_err(|_| eyre!("failed to map video frame"))?;
+    let (width, height) = (frame.width() as usize, frame.height() as usize);
+    let stride = frame.plane_stride()[0] as usize;
+    let plane = frame
+        .plane_data(0)
+        .wrap_err("failed to access video frame")?;
+    let row_len = width * bytes_per_pixel;
+    if height > 0 && plane.len() < stride * (height - 1) + row_len {
+        bail!("video frame is smaller than expected");
+    }
+
+    parameters.insert("width".to_owned(), Parameter::Integer(width as i64));
+    parameters.insert("height".to_owned(), Parameter::Integer(height as i64));
+    // buffer timestamps are running times, i.e. relative to the base time
+    if let (Some(base_time), Some(pts)) = (base_time, buffer.pts()) {
+        let time = UNIX_EPOCH + Duration::from_nanos((base_time + pts).nseconds());
+        parameters.set_acquisition_time(time);
+    }
+
+    node.send_output_raw(output.clone(), parameters, row_len * height, |out| {
+        for (row, out_row) in out.chunks_exact_mut(row_len).enumerate() {
+            out_row.copy_from_slice(&plane[row * stride..][..row_len]);
+        }
+    })
+}
+
+/// Returns the GStreamer video format and the number of bytes per pixel of
+/// the given encoding.
+fn video_format(encoding: &str) -> eyre::Result<(&'static str, usize)> {
+    let format = match encoding {
+        "rgb8" => ("RGB", 3),
+        "bgr8" => ("BGR", 3),
+        "rgba8" => ("RGBA", 4),
+        "bgra8" => ("BGRA", 4),
+        "mono8" => ("GRAY8", 1),
+        "mono16" => ("GRAY16_LE", 2),
+        other => bail!(
+            "unsupported ENCODING `{other}`, expected one of \
+            `rgb8`, `bgr8`, `rgba8`, `bgra8`, `mono8`, `mono16`"
+        ),
+    };
+    Ok(format)
+}
+
+/// Returns the GStreamer source elements that decode the given device, URI, or
+/// file path.
+fn source_description(source: &str) -> String {
+    if source.starts_with("/dev/video") {
+        format!("v4l2src device=\"{source}\"")
+    } else if source.contains("://") {
+        // e.g. `rtsp://`, `http://`, or `file://`
+        format!("uridecodebin uri=\"{source}\"")
+    } else {
+        format!("filesrc location=\"{source}\" ! decodebin")
+    }
+}
+
+fn parse_env<T>(name: &str, value: &str) -> eyre::Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    value
+        .parse()
+        .wrap_err_with(|| format!("invalid value `{value}` for {name}"))
+}
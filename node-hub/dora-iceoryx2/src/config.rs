@@ -0,0 +1,66 @@
+use std::path::Path;
+
+use dora_node_api::dora_core::config::DataId;
+use eyre::{bail, Context};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub services: Vec<Service>,
+}
+
+impl Config {
+    pub fn read(path: &Path) -> eyre::Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to read `{}`", path.display()))?;
+        let config: Self = serde_yaml::from_str(&raw)
+            .wrap_err_with(|| format!("failed to parse `{}`", path.display()))?;
+        for service in &config.services {
+            if service.input.is_some() == service.output.is_some() {
+                bail!(
+                    "service `{}` must have either an `input` or an `output` key",
+                    service.service
+                );
+            }
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Service {
+    /// Name of the iceoryx2 publish-subscribe service, e.g. `camera/image`.
+    pub service: String,
+    /// Input of the bridge that is published on the service.
+    pub input: Option<DataId>,
+    /// Output of the bridge that the received samples are sent to.
+    pub output: Option<DataId>,
+    #[serde(default)]
+    pub encoding: Encoding,
+    /// Initial maximum size of published samples in bytes.
+    ///
+    /// Larger samples are supported, but require a reallocation of the
+    /// shared memory of the publisher.
+    #[serde(default = "default_max_slice_len")]
+    pub max_slice_len: usize,
+    /// Number of samples that late subscribers receive after connecting.
+    pub history_size: Option<usize>,
+    /// Number of samples that a subscriber buffers.
+    pub buffer_size: Option<usize>,
+}
+
+fn default_max_slice_len() -> usize {
+    4096
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    /// The raw bytes of a `UInt8Array`.
+    #[default]
+    Bytes,
+    /// Any Arrow array as Arrow IPC stream.
+    Arrow,
+}
@@ -0,0 +1,190 @@
+//! Bridge between iceoryx2 services and the inputs and outputs of a dataflow.
+//!
+//! Inputs of this node are published on iceoryx2 publish-subscribe services and
+//! samples of subscribed services are sent as outputs, so that non-dora
+//! processes on the same machine can exchange data with a dataflow through
+//! shared memory. Both sides are zero-copy, the bridge copies each message once
+//! between the shared memory of dora and of iceoryx2.
+
+use std::{collections::HashMap, io::Cursor, path::Path, time::Duration};
+
+use config::{Config, Encoding};
+use dora_node_api::{
+    arrow::{
+        array::ArrayRef,
+        ipc::{reader::StreamReader, writer::StreamWriter},
+        record_batch::RecordBatch,
+    },
+    dora_core::config::DataId,
+    ArrowData, DoraNode, Event, MetadataParameters,
+};
+use eyre::{eyre, Context, ContextCompat};
+use iceoryx2::{
+    port::{publisher::Publisher, subscriber::Subscriber},
+    prelude::*,
+};
+
+mod config;
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+fn main() -> eyre::Result<()> {
+    let (mut node, mut events) = DoraNode::init_from_env()?;
+
+    let config_path = std::env::var("CONFIG")
+        .wrap_err("no bridge configuration given, set the `CONFIG` env variable")?;
+    let config = Config::read(Path::new(&config_path))?;
+    let poll_interval = match std::env::var("POLL_INTERVAL_MS") {
+        Ok(ms) => Duration::from_millis(
+            ms.parse()
+                .wrap_err_with(|| format!("invalid POLL_INTERVAL_MS `{ms}`"))?,
+        ),
+        Err(_) => DEFAULT_POLL_INTERVAL,
+    };
+
+    let iceoryx = NodeBuilder::new()
+        .create::<ipc::Service>()
+        .map_err(|err| eyre!("failed to create iceoryx2 node: {err:?}"))?;
+
+    let mut publishers = HashMap::new();
+    let mut subscribers = Vec::new();
+    for service_config in &config.services {
+        let name = ServiceName::new(&service_config.service)
+            .map_err(|err| eyre!("invalid service name `{}`: {err:?}", service_config.service))?;
+        let mut builder = iceoryx.service_builder(&name).publish_subscribe::<[u8]>();
+        if let Some(history_size) = service_config.history_size {
+            builder = builder.history_size(history_size);
+        }
+        if let Some(buffer_size) = service_config.buffer_size {
+            builder = builder.subscriber_max_buffer_size(buffer_size);
+        }
+        let service = builder.open_or_create().map_err(|err| {
+            eyre!(
+                "failed to open service `{}`: {err:?}",
+                service_config.service
+            )
+        })?;
+
+        if let Some(input) = &service_config.input {
+            let publisher = service
+                .publisher_builder()
+                .initial_max_slice_len(service_config.max_slice_len)
+                .allocation_strategy(AllocationStrategy::PowerOfTwo)
+                .create()
+                .map_err(|err| {
+                    eyre!(
+                        "failed to create publisher for `{}`: {err:?}",
+                        service_config.service
+                    )
+                })?;
+            publishers.insert(input.clone(), (publisher, service_config.encoding));
+        } else if let Some(output) = &service_config.output {
+            let subscriber = service.subscriber_builder().create().map_err(|err| {
+                eyre!(
+                    "failed to create subscriber for `{}`: {err:?}",
+                    service_config.service
+                )
+            })?;
+            subscribers.push((output.clone(), subscriber, service_config.encoding));
+        }
+    }
+
+    loop {
+        match events.recv_timeout(poll_interval) {
+            None | Some(Event::Stop) => break,
+            Some(Event::Input { id, data, .. }) => match publishers.get(&id) {
+                Some((publisher, encoding)) => {
+                    if let Err(err) = publish(publisher, *encoding, &data) {
+                        tracing::warn!("failed to publish input `{id}`: {err:?}");
+                    }
+                }
+                None => tracing::warn!("ignoring input `{id}` that is not mapped to a service"),
+            },
+            // timeouts are reported as error events
+            Some(_) => {}
+        }
+
+        for (output, subscriber, encoding) in &subscribers {
+            if let Err(err) = forward(&mut node, output, subscriber, *encoding) {
+                tracing::warn!("failed to forward samples to output `{output}`: {err:?}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn publish(
+    publisher: &Publisher<ipc::Service, [u8], ()>,
+    encoding: Encoding,
+    data: &ArrowData,
+) -> eyre::Result<()> {
+    let ipc;
+    let bytes = match encoding {
+        Encoding::Bytes => <&[u8]>::try_from(data)?,
+        Encoding::Arrow => {
+            ipc = arrow_to_ipc(data)?;
+            ipc.as_slice()
+        }
+    };
+    let sample = publisher
+        .loan_slice_uninit(bytes.len())
+        .map_err(|err| eyre!("failed to loan sample: {err:?}"))?;
+    sample
+        .write_from_slice(bytes)
+        .send()
+        .map_err(|err| eyre!("failed to send sample: {err:?}"))?;
+    Ok(())
+}
+
+/// Sends all received samples of the subscriber as outputs.
+fn forward(
+    node: &mut DoraNode,
+    output: &DataId,
+    subscriber: &Subscriber<ipc::Service, [u8], ()>,
+    encoding: Encoding,
+) -> eyre::Result<()> {
+    while let Some(sample) = subscriber
+        .receive()
+        .map_err(|err| eyre!("failed to receive sample: {err:?}"))?
+    {
+        let payload = sample.payload();
+        match encoding {
+            Encoding::Bytes => node.send_output_raw(
+                output.clone(),
+                MetadataParameters::default(),
+                payload.len(),
+                |out| out.copy_from_slice(payload),
+            )?,
+            Encoding::Arrow => node.send_output(
+                output.clone(),
+                MetadataParameters::default(),
+                ipc_to_arrow(payload)?,
+            )?,
+        }
+    }
+    Ok(())
+}
+
+fn arrow_to_ipc(data: &ArrayRef) -> eyre::Result<Vec<u8>> {
+    let batch = RecordBatch::try_from_iter([("value", data.clone())])
+        .wrap_err("failed to create record batch")?;
+    let mut writer = StreamWriter::try_new(Vec::new(), &batch.schema())?;
+    writer.write(&batch)?;
+    writer.finish()?;
+    Ok(writer.into_inner()?)
+}
+
+fn ipc_to_arrow(data: &[u8]) -> eyre::Result<ArrayRef> {
+    let mut reader = StreamReader::try_new(Cursor::new(data), None)
+        .wrap_err("failed to read Arrow IPC stream")?;
+    let batch = reader
+        .next()
+        .context("Arrow IPC stream is empty")?
+        .wrap_err("failed to read record batch")?;
+    let column = batch
+        .columns()
+        .first()
+        .context("record batch has no columns")?;
+    Ok(column.clone())
+}
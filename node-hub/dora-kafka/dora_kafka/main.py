@@ -0,0 +1,184 @@
+"""Kafka source and sink for dora dataflows.
+
+Inputs of this node are produced to Kafka topics and records consumed from
+Kafka topics are sent as outputs. The topics are configured in a YAML file,
+see the README for its format.
+"""
+
+import argparse
+import json
+import os
+
+import pyarrow as pa
+import yaml
+from confluent_kafka import Consumer, KafkaException, Producer
+from dora import Node
+
+DEFAULT_POLL_INTERVAL = 0.01
+DEFAULT_BATCH_SIZE = 100
+FORMATS = ("raw", "json", "arrow")
+COMMIT_MODES = ("auto", "after_send")
+
+
+def load_config(path):
+    with open(path, encoding="utf-8") as f:
+        config = yaml.safe_load(f) or {}
+
+    for entry in config.get("topics") or []:
+        if "topic" not in entry:
+            raise ValueError(f"topic entry is missing the `topic` key: {entry}")
+        if ("input" in entry) == ("output" in entry):
+            raise ValueError(
+                f"topic `{entry['topic']}` must have either an `input` or an `output` key",
+            )
+        if entry.get("format", "raw") not in FORMATS:
+            raise ValueError(
+                f"topic `{entry['topic']}`: format must be one of {FORMATS}",
+            )
+    commit = (config.get("consumer") or {}).get("commit", "after_send")
+    if commit not in COMMIT_MODES:
+        raise ValueError(f"consumer commit mode must be one of {COMMIT_MODES}")
+    return config
+
+
+def serialize(value, serialization):
+    """Convert an input value to the value of a Kafka record."""
+    if serialization == "raw":
+        return value.to_numpy(zero_copy_only=False).tobytes()
+    if serialization == "json":
+        values = value.to_pylist()
+        return json.dumps(values[0] if len(values) == 1 else values).encode("utf-8")
+    batch = pa.record_batch([value], names=["value"])
+    sink = pa.BufferOutputStream()
+    with pa.ipc.new_stream(sink, batch.schema) as writer:
+        writer.write_batch(batch)
+    return sink.getvalue().to_pybytes()
+
+
+def deserialize(record_value, serialization):
+    """Convert the value of a Kafka record to an output value."""
+    if serialization == "raw":
+        return pa.array(memoryview(record_value), type=pa.uint8())
+    if serialization == "json":
+        value = json.loads(record_value)
+        return pa.array(value if isinstance(value, list) else [value])
+    table = pa.ipc.open_stream(record_value).read_all()
+    return table.column(0).combine_chunks()
+
+
+def main():
+    # Handle dynamic nodes, ask for the name of the node in the dataflow, and the same values as the ENV variables.
+    parser = argparse.ArgumentParser(description="Kafka source and sink")
+
+    parser.add_argument(
+        "--name",
+        type=str,
+        required=False,
+        help="The name of the node in the dataflow.",
+        default="kafka",
+    )
+    parser.add_argument(
+        "--config",
+        type=str,
+        required=False,
+        help="Path of the YAML file that maps Kafka topics to inputs and outputs.",
+        default=os.getenv("CONFIG"),
+    )
+    args = parser.parse_args()
+
+    node = Node(
+        args.name,
+    )  # provide the name to connect to the dataflow if dynamic node
+
+    if args.config is None:
+        raise ValueError("no connector configuration given, set the `CONFIG` env variable")
+    config = load_config(args.config)
+    poll_interval = float(os.getenv("POLL_INTERVAL", DEFAULT_POLL_INTERVAL))
+
+    # (topic, serialization, key) by dora input ID
+    sinks = {}
+    # (output ID, serialization) by Kafka topic
+    sources = {}
+    for entry in config.get("topics") or []:
+        serialization = entry.get("format", "raw")
+        if "input" in entry:
+            sinks[entry["input"]] = (entry["topic"], serialization, entry.get("key"))
+        else:
+            sources[entry["topic"]] = (entry["output"], serialization)
+
+    # properties of the Kafka clients, in librdkafka format
+    common = dict(config.get("kafka") or {})
+    common.setdefault("bootstrap.servers", "localhost:9092")
+
+    producer = None
+    if sinks:
+        producer = Producer({**common, **(config.get("producer") or {})})
+
+    consumer = None
+    if sources:
+        consumer_config = dict(config.get("consumer") or {})
+        batch_size = consumer_config.pop("batch_size", DEFAULT_BATCH_SIZE)
+        commit = consumer_config.pop("commit", "after_send")
+        consumer_config.setdefault("group.id", args.name)
+        consumer_config["enable.auto.commit"] = commit == "auto"
+        consumer = Consumer({**common, **consumer_config})
+        consumer.subscribe(list(sources))
+
+    try:
+        while True:
+            event = node.next(timeout=poll_interval)
+            if event is None:
+                break
+            if event["type"] == "INPUT":
+                if event["id"] in sinks:
+                    topic, serialization, key = sinks[event["id"]]
+                    producer.produce(
+                        topic,
+                        serialize(event["value"], serialization),
+                        key=key,
+                    )
+                    # serve delivery callbacks without blocking
+                    producer.poll(0)
+                else:
+                    print(f"ignoring input `{event['id']}` that is not mapped to a topic")
+            elif event["type"] == "STOP":
+                break
+
+            if consumer is None:
+                continue
+            records = consumer.consume(num_messages=batch_size, timeout=0)
+            for record in records:
+                if record.error() is not None:
+                    print(f"failed to consume from Kafka: {record.error()}")
+                    continue
+                output, serialization = sources[record.topic()]
+                try:
+                    value = deserialize(record.value(), serialization)
+                except (ValueError, pa.ArrowException) as err:
+                    print(f"failed to deserialize record of `{record.topic()}`: {err}")
+                    continue
+                node.send_output(
+                    output,
+                    value,
+                    {
+                        "topic": record.topic(),
+                        "partition": record.partition(),
+                        "offset": record.offset(),
+                    },
+                )
+            if records and commit == "after_send":
+                # only commit the offsets of records that were sent, so that
+                # they are consumed again after a crash
+                try:
+                    consumer.commit(asynchronous=True)
+                except KafkaException as err:
+                    print(f"failed to commit Kafka offsets: {err}")
+    finally:
+        if producer is not None:
+            producer.flush()
+        if consumer is not None:
+            consumer.close()
+
+
+if __name__ == "__main__":
+    main()
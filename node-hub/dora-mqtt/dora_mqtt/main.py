@@ -0,0 +1,173 @@
+"""Bridge between MQTT topics and the inputs and outputs of a dora dataflow.
+
+The broker and the topics are configured in a YAML file, see the README for
+its format.
+"""
+
+import argparse
+import json
+import os
+import queue
+
+import paho.mqtt.client as mqtt
+import pyarrow as pa
+import yaml
+from dora import Node
+
+DEFAULT_POLL_INTERVAL = 0.01
+PAYLOAD_FORMATS = ("raw", "string", "json")
+
+
+def load_config(path):
+    with open(path, encoding="utf-8") as f:
+        config = yaml.safe_load(f) or {}
+
+    for entry in config.get("topics") or []:
+        if "topic" not in entry:
+            raise ValueError(f"topic entry is missing the `topic` key: {entry}")
+        if ("input" in entry) == ("output" in entry):
+            raise ValueError(
+                f"topic `{entry['topic']}` must have either an `input` or an `output` key",
+            )
+        if entry.get("qos", 0) not in (0, 1, 2):
+            raise ValueError(f"topic `{entry['topic']}`: QoS must be 0, 1 or 2")
+        if entry.get("format", "raw") not in PAYLOAD_FORMATS:
+            raise ValueError(
+                f"topic `{entry['topic']}`: format must be one of {PAYLOAD_FORMATS}",
+            )
+    return config
+
+
+def encode(value, payload_format):
+    """Convert an input value to the payloads of MQTT messages."""
+    if payload_format == "raw":
+        return [value.to_numpy(zero_copy_only=False).tobytes()]
+    if payload_format == "string":
+        return [element.encode("utf-8") for element in value.to_pylist()]
+    values = value.to_pylist()
+    return [json.dumps(values[0] if len(values) == 1 else values).encode("utf-8")]
+
+
+def decode(payload, payload_format):
+    """Convert the payload of an MQTT message to an output value."""
+    if payload_format == "raw":
+        return pa.array(memoryview(payload), type=pa.uint8())
+    if payload_format == "string":
+        return pa.array([payload.decode("utf-8")])
+    value = json.loads(payload)
+    return pa.array(value if isinstance(value, list) else [value])
+
+
+def main():
+    # Handle dynamic nodes, ask for the name of the node in the dataflow, and the same values as the ENV variables.
+    parser = argparse.ArgumentParser(description="Bridge between MQTT and dora")
+
+    parser.add_argument(
+        "--name",
+        type=str,
+        required=False,
+        help="The name of the node in the dataflow.",
+        default="mqtt",
+    )
+    parser.add_argument(
+        "--config",
+        type=str,
+        required=False,
+        help="Path of the YAML file that maps MQTT topics to inputs and outputs.",
+        default=os.getenv("CONFIG"),
+    )
+    args = parser.parse_args()
+
+    node = Node(
+        args.name,
+    )  # provide the name to connect to the dataflow if dynamic node
+
+    if args.config is None:
+        raise ValueError("no bridge configuration given, set the `CONFIG` env variable")
+    config = load_config(args.config)
+    poll_interval = float(os.getenv("POLL_INTERVAL", DEFAULT_POLL_INTERVAL))
+    broker = config.get("broker") or {}
+
+    # (topic, QoS, format, retain) by dora input ID
+    publications = {}
+    # (output ID, QoS, format) by MQTT topic filter
+    subscriptions = {}
+    for entry in config.get("topics") or []:
+        qos = entry.get("qos", 0)
+        payload_format = entry.get("format", "raw")
+        if "input" in entry:
+            publications[entry["input"]] = (
+                entry["topic"],
+                qos,
+                payload_format,
+                entry.get("retain", False),
+            )
+        else:
+            subscriptions[entry["topic"]] = (entry["output"], qos, payload_format)
+
+    # messages are received on the network thread of the client
+    received = queue.Queue()
+
+    def on_connect(client, _userdata, _flags, reason_code, _properties):
+        if reason_code.is_failure:
+            print(f"failed to connect to MQTT broker: {reason_code}")
+            return
+        # subscribe again on reconnects, as the session might be lost
+        for topic, (_output, qos, _format) in subscriptions.items():
+            client.subscribe(topic, qos)
+
+    def on_message(_client, _userdata, message):
+        for topic, (output, _qos, payload_format) in subscriptions.items():
+            if mqtt.topic_matches_sub(topic, message.topic):
+                received.put((output, payload_format, message))
+
+    client = mqtt.Client(
+        mqtt.CallbackAPIVersion.VERSION2,
+        client_id=broker.get("client_id", ""),
+    )
+    if "username" in broker:
+        client.username_pw_set(broker["username"], broker.get("password"))
+    if broker.get("tls", False):
+        client.tls_set()
+    client.on_connect = on_connect
+    client.on_message = on_message
+    client.connect(
+        broker.get("host", "localhost"),
+        broker.get("port", 1883),
+        broker.get("keepalive", 60),
+    )
+    client.loop_start()
+
+    try:
+        while True:
+            event = node.next(timeout=poll_interval)
+            if event is None:
+                break
+            if event["type"] == "INPUT":
+                if event["id"] in publications:
+                    topic, qos, payload_format, retain = publications[event["id"]]
+                    for payload in encode(event["value"], payload_format):
+                        client.publish(topic, payload, qos=qos, retain=retain)
+                else:
+                    print(f"ignoring input `{event['id']}` that is not mapped to a topic")
+            elif event["type"] == "STOP":
+                break
+
+            while True:
+                try:
+                    output, payload_format, message = received.get_nowait()
+                except queue.Empty:
+                    break
+                try:
+                    value = decode(message.payload, payload_format)
+                except ValueError as err:
+                    print(f"failed to decode message on `{message.topic}`: {err}")
+                    continue
+                node.send_output(output, value, {"topic": message.topic})
+    finally:
+        client.loop_stop()
+        client.disconnect()
+
+
+if __name__ == "__main__":
+    main()
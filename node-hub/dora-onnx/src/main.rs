@@ -0,0 +1,332 @@
+//! Generic inference node for ONNX models.
+//!
+//! The node loads an ONNX model through ONNX Runtime and maps the inputs and
+//! outputs of the model to inputs and outputs of the node. Tensors are sent as
+//! flat Arrow arrays with a `shape` metadata parameter. The model runs as soon
+//! as each of its inputs received a new value since the previous run.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use dora_node_api::{
+    arrow::{
+        array::{Array, ArrayRef, AsArray, PrimitiveArray},
+        compute::cast,
+        datatypes::{
+            ArrowPrimitiveType, Float32Type, Float64Type, Int32Type, Int64Type, Int8Type, UInt8Type,
+        },
+    },
+    dora_core::config::DataId,
+    DoraNode, Event, MetadataParameters, NodeHealth, Parameter,
+};
+use eyre::{bail, Context, ContextCompat};
+use ort::{
+    session::Session,
+    tensor::{PrimitiveTensorElementType, TensorElementType},
+    value::{DynValue, Tensor, ValueType},
+};
+
+/// Interval in which the inference metrics are reported.
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Input or output of the model.
+struct Port {
+    name: String,
+    ty: TensorElementType,
+    dimensions: Vec<i64>,
+}
+
+impl Port {
+    fn new(name: &str, value_type: &ValueType) -> eyre::Result<Self> {
+        match value_type {
+            ValueType::Tensor { ty, dimensions, .. } => Ok(Self {
+                name: name.to_owned(),
+                ty: *ty,
+                dimensions: dimensions.clone(),
+            }),
+            other => bail!("`{name}` is not a tensor but {other:?}, which is not supported"),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Metrics {
+    inferences: u64,
+    failures: u64,
+    /// Inferences and their total duration since the last report.
+    interval_inferences: u32,
+    interval_duration: Duration,
+    last_duration: Duration,
+}
+
+impl Metrics {
+    fn health(&mut self) -> NodeHealth {
+        let mut health = NodeHealth::default();
+        health.counters.insert("inferences".into(), self.inferences);
+        health.counters.insert("failures".into(), self.failures);
+        health.gauges.insert(
+            "inference_time_ms".into(),
+            self.last_duration.as_secs_f64() * 1000.,
+        );
+        if self.interval_inferences > 0 {
+            health.gauges.insert(
+                "mean_inference_time_ms".into(),
+                (self.interval_duration / self.interval_inferences).as_secs_f64() * 1000.,
+            );
+        }
+        self.interval_inferences = 0;
+        self.interval_duration = Duration::ZERO;
+        health
+    }
+}
+
+fn main() -> eyre::Result<()> {
+    let (mut node, mut events) = DoraNode::init_from_env()?;
+
+    let model_path =
+        std::env::var("MODEL").wrap_err("no model given, set the `MODEL` env variable")?;
+    let session = build_session(&model_path)?;
+
+    let model_inputs = session
+        .inputs
+        .iter()
+        .map(|input| Port::new(&input.name, &input.input_type))
+        .collect::<eyre::Result<Vec<_>>>()?;
+    let model_outputs = session
+        .outputs
+        .iter()
+        .map(|output| Port::new(&output.name, &output.output_type))
+        .collect::<eyre::Result<Vec<_>>>()?;
+
+    // model input name by dora input ID
+    let input_map = parse_map("INPUTS")?;
+    // dora output ID by model output name
+    let output_map = parse_map("OUTPUTS")?;
+    let outputs = model_outputs
+        .iter()
+        .map(|port| {
+            let id = output_map.get(&port.name).unwrap_or(&port.name);
+            DataId::from(id.clone())
+        })
+        .collect::<Vec<_>>();
+
+    // latest values of the model inputs since the previous run
+    let mut pending: HashMap<usize, (ArrayRef, Vec<i64>)> = HashMap::new();
+    let mut metrics = Metrics::default();
+    let mut last_report = Instant::now();
+
+    while let Some(event) = events.recv() {
+        match event {
+            Event::Input { id, metadata, data } => {
+                let name = input_map
+                    .get(id.as_str())
+                    .map(String::as_str)
+                    .unwrap_or(id.as_str());
+                let Some(index) = model_inputs.iter().position(|port| port.name == name) else {
+                    tracing::warn!("ignoring input `{id}` that is not mapped to a model input");
+                    continue;
+                };
+                let port = &model_inputs[index];
+                match input_shape(port, &metadata.parameters, data.len()) {
+                    Ok(shape) => {
+                        pending.insert(index, (data.0, shape));
+                    }
+                    Err(err) => {
+                        tracing::warn!("ignoring input `{id}`: {err:?}");
+                        continue;
+                    }
+                }
+                if pending.len() < model_inputs.len() {
+                    continue;
+                }
+
+                let inputs = std::mem::take(&mut pending);
+                let start = Instant::now();
+                match run(&session, &model_inputs, &model_outputs, inputs) {
+                    Ok(results) => {
+                        let duration = start.elapsed();
+                        metrics.inferences += 1;
+                        metrics.interval_inferences += 1;
+                        metrics.interval_duration += duration;
+                        metrics.last_duration = duration;
+
+                        for (output, (array, shape)) in outputs.iter().zip(results) {
+                            let mut parameters = MetadataParameters::default();
+                            parameters.insert("shape".into(), Parameter::ListInt(shape));
+                            parameters.insert(
+                                "inference_time_us".into(),
+                                Parameter::Integer(duration.as_micros() as i64),
+                            );
+                            node.send_output(output.clone(), parameters, array)?;
+                        }
+                    }
+                    Err(err) => {
+                        metrics.failures += 1;
+                        tracing::warn!("inference failed: {err:?}");
+                    }
+                }
+
+                if last_report.elapsed() >= REPORT_INTERVAL {
+                    last_report = Instant::now();
+                    if let Err(err) = node.report_health(metrics.health()) {
+                        tracing::warn!("{err:?}");
+                    }
+                }
+            }
+            Event::Stop => break,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn build_session(model_path: &str) -> eyre::Result<Session> {
+    let mut builder = Session::builder().wrap_err("failed to create ONNX Runtime session")?;
+    match std::env::var("PROVIDER").as_deref() {
+        Err(_) | Ok("cpu") => {}
+        #[cfg(feature = "cuda")]
+        Ok("cuda") => {
+            builder = builder
+                .with_execution_providers([
+                    ort::execution_providers::CUDAExecutionProvider::default()
+                        .build()
+                        .error_on_failure(),
+                ])
+                .wrap_err("failed to register CUDA execution provider")?;
+        }
+        #[cfg(not(feature = "cuda"))]
+        Ok("cuda") => bail!("CUDA support is not enabled, build with the `cuda` feature"),
+        Ok(other) => bail!("unknown PROVIDER `{other}`, expected `cpu` or `cuda`"),
+    }
+    if let Ok(threads) = std::env::var("INTRA_THREADS") {
+        let threads = threads
+            .parse()
+            .wrap_err_with(|| format!("invalid INTRA_THREADS `{threads}`"))?;
+        builder = builder
+            .with_intra_threads(threads)
+            .wrap_err("failed to set number of intra-op threads")?;
+    }
+    builder
+        .commit_from_file(model_path)
+        .wrap_err_with(|| format!("failed to load model `{model_path}`"))
+}
+
+/// Parses a mapping in the form `from:to,from:to` from the given env variable.
+fn parse_map(name: &str) -> eyre::Result<BTreeMap<String, String>> {
+    let Ok(value) = std::env::var(name) else {
+        return Ok(BTreeMap::new());
+    };
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (from, to) = entry
+                .split_once(':')
+                .with_context(|| format!("invalid {name} entry `{entry}`, expected `from:to`"))?;
+            Ok((from.trim().to_owned(), to.trim().to_owned()))
+        })
+        .collect()
+}
+
+/// Shape of an input tensor, taken from the `shape` metadata parameter or
+/// from the model with a single dynamic dimension inferred from the length.
+fn input_shape(port: &Port, parameters: &MetadataParameters, len: usize) -> eyre::Result<Vec<i64>> {
+    let shape = match parameters.get("shape") {
+        Some(Parameter::ListInt(shape)) => shape.clone(),
+        Some(other) => bail!("`shape` parameter must be a list of integers, got {other:?}"),
+        None => {
+            let mut shape = port.dimensions.clone();
+            let known: i64 = shape.iter().filter(|dim| **dim >= 0).product();
+            let mut dynamic = shape.iter_mut().filter(|dim| **dim < 0);
+            match (dynamic.next(), dynamic.next()) {
+                (None, _) => {}
+                (Some(dim), None) if known > 0 => *dim = len as i64 / known,
+                _ => bail!(
+                    "model input `{}` has dynamic dimensions {:?}, set the `shape` parameter",
+                    port.name,
+                    port.dimensions
+                ),
+            }
+            shape
+        }
+    };
+    let expected: i64 = shape.iter().product();
+    if expected != len as i64 {
+        bail!("shape {shape:?} doesn't match the length {len}");
+    }
+    Ok(shape)
+}
+
+fn run(
+    session: &Session,
+    model_inputs: &[Port],
+    model_outputs: &[Port],
+    mut inputs: HashMap<usize, (ArrayRef, Vec<i64>)>,
+) -> eyre::Result<Vec<(ArrayRef, Vec<i64>)>> {
+    let mut values = Vec::with_capacity(model_inputs.len());
+    for (index, port) in model_inputs.iter().enumerate() {
+        let (array, shape) = inputs.remove(&index).context("missing input")?;
+        let value = to_tensor(&array, port.ty, shape)
+            .wrap_err_with(|| format!("failed to convert input `{}`", port.name))?;
+        values.push((port.name.as_str(), value));
+    }
+    let results = session.run(values).wrap_err("failed to run model")?;
+
+    model_outputs
+        .iter()
+        .map(|port| {
+            from_tensor(&results[port.name.as_str()], port.ty)
+                .wrap_err_with(|| format!("failed to convert output `{}`", port.name))
+        })
+        .collect()
+}
+
+fn to_tensor(array: &ArrayRef, ty: TensorElementType, shape: Vec<i64>) -> eyre::Result<DynValue> {
+    fn tensor<T>(array: &ArrayRef, shape: Vec<i64>) -> eyre::Result<DynValue>
+    where
+        T: ArrowPrimitiveType,
+        T::Native: PrimitiveTensorElementType + std::fmt::Debug,
+    {
+        let array = cast(array.as_ref(), &T::DATA_TYPE)
+            .wrap_err_with(|| format!("failed to cast to {}", T::DATA_TYPE))?;
+        let values = array.as_primitive::<T>().values().to_vec();
+        Ok(Tensor::from_array((shape, values))?.into_dyn())
+    }
+
+    match ty {
+        TensorElementType::Float32 => tensor::<Float32Type>(array, shape),
+        TensorElementType::Float64 => tensor::<Float64Type>(array, shape),
+        TensorElementType::Int64 => tensor::<Int64Type>(array, shape),
+        TensorElementType::Int32 => tensor::<Int32Type>(array, shape),
+        TensorElementType::Uint8 => tensor::<UInt8Type>(array, shape),
+        TensorElementType::Int8 => tensor::<Int8Type>(array, shape),
+        other => bail!("element type {other:?} is not supported"),
+    }
+}
+
+fn from_tensor(value: &DynValue, ty: TensorElementType) -> eyre::Result<(ArrayRef, Vec<i64>)> {
+    fn array<T>(value: &DynValue) -> eyre::Result<(ArrayRef, Vec<i64>)>
+    where
+        T: ArrowPrimitiveType,
+        T::Native: PrimitiveTensorElementType,
+    {
+        let (shape, data) = value.try_extract_raw_tensor::<T::Native>()?;
+        let array = PrimitiveArray::<T>::from_iter_values(data.iter().copied());
+        Ok((Arc::new(array), shape))
+    }
+
+    match ty {
+        TensorElementType::Float32 => array::<Float32Type>(value),
+        TensorElementType::Float64 => array::<Float64Type>(value),
+        TensorElementType::Int64 => array::<Int64Type>(value),
+        TensorElementType::Int32 => array::<Int32Type>(value),
+        TensorElementType::Uint8 => array::<UInt8Type>(value),
+        TensorElementType::Int8 => array::<Int8Type>(value),
+        other => bail!("element type {other:?} is not supported"),
+    }
+}
@@ -0,0 +1,166 @@
+"""Bridge between the variables of an OPC-UA server and a dora dataflow.
+
+Variables are read with a configurable sampling interval and sent as outputs,
+and inputs are written to variables. The server and the variables are
+configured in a YAML file, see the README for its format.
+"""
+
+import argparse
+import os
+import time
+
+import pyarrow as pa
+import yaml
+from asyncua import ua
+from asyncua.sync import Client
+from dora import Node
+
+DEFAULT_SAMPLING_INTERVAL_MS = 1000
+ON_CHANGE_MODES = ("always", "on_change")
+
+
+def load_config(path):
+    with open(path, encoding="utf-8") as f:
+        config = yaml.safe_load(f) or {}
+
+    if "endpoint" not in config:
+        raise ValueError("configuration is missing the `endpoint` key")
+    for entry in config.get("read") or []:
+        for key in ("node_id", "output"):
+            if key not in entry:
+                raise ValueError(f"read entry is missing the `{key}` key: {entry}")
+        if entry.get("send", "always") not in ON_CHANGE_MODES:
+            raise ValueError(f"`send` must be one of {ON_CHANGE_MODES}: {entry}")
+    for entry in config.get("write") or []:
+        for key in ("node_id", "input"):
+            if key not in entry:
+                raise ValueError(f"write entry is missing the `{key}` key: {entry}")
+        if "variant_type" in entry and not hasattr(ua.VariantType, entry["variant_type"]):
+            raise ValueError(f"unknown variant type `{entry['variant_type']}`")
+    return config
+
+
+def to_arrow(value):
+    if isinstance(value, (list, tuple)):
+        return pa.array(value)
+    return pa.array([value])
+
+
+def from_arrow(value):
+    values = value.to_pylist()
+    return values[0] if len(values) == 1 else values
+
+
+class Variable:
+    """A variable that is read periodically."""
+
+    def __init__(self, client, entry):
+        self.node = client.get_node(entry["node_id"])
+        self.output = entry["output"]
+        self.interval = (
+            entry.get("sampling_interval_ms", DEFAULT_SAMPLING_INTERVAL_MS) / 1000
+        )
+        self.on_change = entry.get("send", "always") == "on_change"
+        self.next_read = time.monotonic()
+        self.last_value = None
+
+    def read(self, node):
+        # skip ahead if reads fall behind, instead of reading repeatedly
+        self.next_read = max(self.next_read + self.interval, time.monotonic())
+        data_value = self.node.read_data_value()
+        value = data_value.Value.Value
+        if self.on_change and value == self.last_value:
+            return
+        self.last_value = value
+        node.send_output(
+            self.output,
+            to_arrow(value),
+            {"status": data_value.StatusCode.name},
+        )
+
+
+def main():
+    # Handle dynamic nodes, ask for the name of the node in the dataflow, and the same values as the ENV variables.
+    parser = argparse.ArgumentParser(description="Bridge between OPC-UA and dora")
+
+    parser.add_argument(
+        "--name",
+        type=str,
+        required=False,
+        help="The name of the node in the dataflow.",
+        default="opcua",
+    )
+    parser.add_argument(
+        "--config",
+        type=str,
+        required=False,
+        help="Path of the YAML file that maps OPC-UA variables to inputs and outputs.",
+        default=os.getenv("CONFIG"),
+    )
+    args = parser.parse_args()
+
+    node = Node(
+        args.name,
+    )  # provide the name to connect to the dataflow if dynamic node
+
+    if args.config is None:
+        raise ValueError("no bridge configuration given, set the `CONFIG` env variable")
+    config = load_config(args.config)
+
+    client = Client(config["endpoint"], timeout=config.get("timeout", 4))
+    if "username" in config:
+        client.set_user(config["username"])
+        client.set_password(config.get("password", ""))
+    if "security" in config:
+        # e.g. `Basic256Sha256,SignAndEncrypt,certificate.pem,private-key.pem`
+        client.set_security_string(config["security"])
+    client.connect()
+
+    try:
+        variables = [Variable(client, entry) for entry in config.get("read") or []]
+        # (OPC-UA node, variant type) by dora input ID
+        targets = {
+            entry["input"]: (
+                client.get_node(entry["node_id"]),
+                getattr(ua.VariantType, entry["variant_type"])
+                if "variant_type" in entry
+                else None,
+            )
+            for entry in config.get("write") or []
+        }
+
+        while True:
+            now = time.monotonic()
+            timeout = min(
+                (variable.next_read - now for variable in variables),
+                default=1.0,
+            )
+            event = node.next(timeout=max(timeout, 0.0))
+            if event is None:
+                break
+            if event["type"] == "INPUT":
+                if event["id"] in targets:
+                    target, variant_type = targets[event["id"]]
+                    value = from_arrow(event["value"])
+                    try:
+                        target.write_value(ua.Variant(value, variant_type))
+                    except ua.UaError as err:
+                        print(f"failed to write input `{event['id']}`: {err}")
+                else:
+                    print(f"ignoring input `{event['id']}` that is not mapped to a variable")
+            elif event["type"] == "STOP":
+                break
+
+            now = time.monotonic()
+            for variable in variables:
+                if variable.next_read <= now:
+                    try:
+                        variable.read(node)
+                    except ua.UaError as err:
+                        print(f"failed to read variable for `{variable.output}`: {err}")
+    finally:
+        client.disconnect()
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,9 @@
+import pytest
+
+
+def test_import_main():
+    from dora_opcua.main import main
+
+    # Check that everything is working, and catch dora Runtime Exception as we're not running in a dora dataflow.
+    with pytest.raises(RuntimeError):
+        main()
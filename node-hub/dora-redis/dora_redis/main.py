@@ -0,0 +1,255 @@
+"""Bridge between Redis and a dora dataflow.
+
+Inputs of this node are published on Redis pub/sub channels or appended to
+Redis Streams, and messages of subscribed channels and entries of read streams
+are sent as outputs. Streams can be read through consumer groups. The channels
+and streams are configured in a YAML file, see the README for its format.
+"""
+
+import argparse
+import functools
+import json
+import os
+
+import pyarrow as pa
+import redis
+import yaml
+from dora import Node
+
+DEFAULT_POLL_INTERVAL = 0.01
+DEFAULT_BATCH_SIZE = 100
+DEFAULT_FIELD = "data"
+FORMATS = ("raw", "string", "json", "arrow")
+
+
+def load_config(path):
+    with open(path, encoding="utf-8") as f:
+        config = yaml.safe_load(f) or {}
+
+    for kind in ("channel", "stream"):
+        for entry in config.get(f"{kind}s") or []:
+            if kind not in entry:
+                raise ValueError(f"{kind} entry is missing the `{kind}` key: {entry}")
+            if ("input" in entry) == ("output" in entry):
+                raise ValueError(
+                    f"{kind} `{entry[kind]}` must have either an `input` or an `output` key",
+                )
+            if entry.get("format", "raw") not in FORMATS:
+                raise ValueError(f"{kind} `{entry[kind]}`: format must be one of {FORMATS}")
+    return config
+
+
+def encode(value, value_format):
+    """Convert an input value to a Redis value."""
+    if value_format == "raw":
+        return value.to_numpy(zero_copy_only=False).tobytes()
+    if value_format == "string":
+        return "".join(value.to_pylist()).encode("utf-8")
+    if value_format == "json":
+        values = value.to_pylist()
+        return json.dumps(values[0] if len(values) == 1 else values).encode("utf-8")
+    batch = pa.record_batch([value], names=["value"])
+    sink = pa.BufferOutputStream()
+    with pa.ipc.new_stream(sink, batch.schema) as writer:
+        writer.write_batch(batch)
+    return sink.getvalue().to_pybytes()
+
+
+def decode(redis_value, value_format):
+    """Convert a Redis value to an output value."""
+    if value_format == "raw":
+        return pa.array(memoryview(redis_value), type=pa.uint8())
+    if value_format == "string":
+        return pa.array([redis_value.decode("utf-8")])
+    if value_format == "json":
+        value = json.loads(redis_value)
+        return pa.array(value if isinstance(value, list) else [value])
+    table = pa.ipc.open_stream(redis_value).read_all()
+    return table.column(0).combine_chunks()
+
+
+def publish(client, channel, value_format, value):
+    client.publish(channel, encode(value, value_format))
+
+
+def append(client, entry, value):
+    fields = {entry.get("field", DEFAULT_FIELD): encode(value, entry.get("format", "raw"))}
+    # trim the stream approximately, which is much more efficient
+    client.xadd(entry["stream"], fields, maxlen=entry.get("maxlen"), approximate=True)
+
+
+class StreamReader:
+    """Reads the entries of a Redis Stream, optionally through a consumer group."""
+
+    def __init__(self, client, entry, node_name):
+        self.client = client
+        self.stream = entry["stream"]
+        self.output = entry["output"]
+        self.format = entry.get("format", "raw")
+        self.field = entry.get("field", DEFAULT_FIELD).encode("utf-8")
+        self.group = entry.get("group")
+        self.consumer = entry.get("consumer", node_name)
+        start = str(entry.get("start", "$"))
+
+        if self.group is not None:
+            try:
+                client.xgroup_create(self.stream, self.group, id=start, mkstream=True)
+            except redis.ResponseError as err:
+                # the group exists already, continue where it left off
+                if "BUSYGROUP" not in str(err):
+                    raise
+            # first deliver the entries that this consumer read but didn't
+            # acknowledge before a restart, then new entries
+            self.last_id = "0"
+        elif start == "$":
+            # resolve `$` once, as non-blocking reads from `$` never return
+            # entries
+            last = client.xrevrange(self.stream, count=1)
+            self.last_id = last[0][0] if last else "0-0"
+        else:
+            self.last_id = start
+
+    def read(self, node, batch_size):
+        if self.group is not None:
+            response = self.client.xreadgroup(
+                self.group,
+                self.consumer,
+                {self.stream: self.last_id},
+                count=batch_size,
+            )
+        else:
+            response = self.client.xread({self.stream: self.last_id}, count=batch_size)
+        entries = response[0][1] if response else []
+
+        if self.group is not None and self.last_id == "0" and not entries:
+            # all pending entries are delivered
+            self.last_id = ">"
+
+        for entry_id, fields in entries:
+            if self.group is None:
+                self.last_id = entry_id
+            if self.field not in fields:
+                print(f"ignoring entry `{entry_id}` of `{self.stream}` without data field")
+            else:
+                try:
+                    value = decode(fields[self.field], self.format)
+                except (ValueError, pa.ArrowException) as err:
+                    print(f"failed to decode entry `{entry_id}` of `{self.stream}`: {err}")
+                else:
+                    node.send_output(
+                        self.output,
+                        value,
+                        {"stream": self.stream, "id": entry_id.decode("utf-8")},
+                    )
+            if self.group is not None:
+                # acknowledge after sending, so that unsent entries are
+                # delivered again after a restart
+                self.client.xack(self.stream, self.group, entry_id)
+
+
+def main():
+    # Handle dynamic nodes, ask for the name of the node in the dataflow, and the same values as the ENV variables.
+    parser = argparse.ArgumentParser(description="Bridge between Redis and dora")
+
+    parser.add_argument(
+        "--name",
+        type=str,
+        required=False,
+        help="The name of the node in the dataflow.",
+        default="redis",
+    )
+    parser.add_argument(
+        "--config",
+        type=str,
+        required=False,
+        help="Path of the YAML file that maps Redis channels and streams to inputs and outputs.",
+        default=os.getenv("CONFIG"),
+    )
+    args = parser.parse_args()
+
+    node = Node(
+        args.name,
+    )  # provide the name to connect to the dataflow if dynamic node
+
+    if args.config is None:
+        raise ValueError("no bridge configuration given, set the `CONFIG` env variable")
+    config = load_config(args.config)
+    poll_interval = float(os.getenv("POLL_INTERVAL", DEFAULT_POLL_INTERVAL))
+    batch_size = int(config.get("batch_size", DEFAULT_BATCH_SIZE))
+
+    client = redis.Redis.from_url(
+        (config.get("redis") or {}).get("url", "redis://localhost:6379/0"),
+    )
+
+    # publish or append function by dora input ID
+    sinks = {}
+    # (output ID, format) by channel or pattern
+    subscriptions = {}
+    pubsub = client.pubsub(ignore_subscribe_messages=True)
+    for entry in config.get("channels") or []:
+        channel = entry["channel"]
+        value_format = entry.get("format", "raw")
+        if "input" in entry:
+            sinks[entry["input"]] = functools.partial(publish, client, channel, value_format)
+        elif entry.get("pattern", False):
+            subscriptions[channel] = (entry["output"], value_format)
+            pubsub.psubscribe(channel)
+        else:
+            subscriptions[channel] = (entry["output"], value_format)
+            pubsub.subscribe(channel)
+
+    readers = []
+    for entry in config.get("streams") or []:
+        if "input" in entry:
+            sinks[entry["input"]] = functools.partial(append, client, entry)
+        else:
+            readers.append(StreamReader(client, entry, args.name))
+
+    try:
+        while True:
+            event = node.next(timeout=poll_interval)
+            if event is None:
+                break
+            if event["type"] == "INPUT":
+                if event["id"] in sinks:
+                    try:
+                        sinks[event["id"]](event["value"])
+                    except redis.RedisError as err:
+                        print(f"failed to write input `{event['id']}` to Redis: {err}")
+                else:
+                    print(f"ignoring input `{event['id']}` that is not mapped to Redis")
+            elif event["type"] == "STOP":
+                break
+
+            if subscriptions:
+                while True:
+                    message = pubsub.get_message(timeout=0)
+                    if message is None:
+                        break
+                    if message["type"] not in ("message", "pmessage"):
+                        continue
+                    key = message["pattern"] or message["channel"]
+                    output, value_format = subscriptions[key.decode("utf-8")]
+                    try:
+                        value = decode(message["data"], value_format)
+                    except (ValueError, pa.ArrowException) as err:
+                        print(f"failed to decode message of `{message['channel']}`: {err}")
+                        continue
+                    node.send_output(
+                        output,
+                        value,
+                        {"channel": message["channel"].decode("utf-8")},
+                    )
+
+            for reader in readers:
+                try:
+                    reader.read(node, batch_size)
+                except redis.RedisError as err:
+                    print(f"failed to read stream `{reader.stream}`: {err}")
+    finally:
+        pubsub.close()
+        client.close()
+
+
+if __name__ == "__main__":
+    main()
@@ -3,7 +3,6 @@ use dora_node_api::{
         array::AsArray,
         datatypes::{Float32Type, Float64Type, Int32Type, Int64Type},
     },
-    dora_core::config::DataId,
     ArrowData, Metadata, Parameter,
 };
 use eyre::{Context, ContextCompat, Result};
@@ -11,7 +10,7 @@ use rerun::{RecordingStream, Text};
 
 pub fn update_boxes2d(
     rec: &RecordingStream,
-    id: DataId,
+    entity_path: &str,
     data: ArrowData,
     metadata: Metadata,
 ) -> Result<()> {
@@ -78,7 +77,7 @@ pub fn update_boxes2d(
             });
         }
         rec.log(
-            id.as_str(),
+            entity_path,
             &rerun::Boxes2D::from_centers_and_sizes(centers, sizes).with_labels(labels),
         )
         .wrap_err("Could not log Boxes2D")?;
@@ -102,7 +101,7 @@ pub fn update_boxes2d(
             });
         }
         rec.log(
-            id.as_str(),
+            entity_path,
             &rerun::Boxes2D::from_centers_and_sizes(centers, sizes),
         )
         .wrap_err("Could not log Boxes2D")?;
@@ -126,7 +125,7 @@ pub fn update_boxes2d(
             });
         }
         rec.log(
-            id.as_str(),
+            entity_path,
             &rerun::Boxes2D::from_centers_and_sizes(centers, sizes),
         )
         .wrap_err("Could not log Boxes2D")?;
@@ -150,7 +149,7 @@ pub fn update_boxes2d(
             });
         }
         rec.log(
-            id.as_str(),
+            entity_path,
             &rerun::Boxes2D::from_centers_and_sizes(centers, sizes),
         )
         .wrap_err("Could not log Boxes2D")?;
@@ -174,7 +173,7 @@ pub fn update_boxes2d(
             });
         }
         rec.log(
-            id.as_str(),
+            entity_path,
             &rerun::Boxes2D::from_centers_and_sizes(centers, sizes),
         )
         .wrap_err("Could not log Boxes2D")?;
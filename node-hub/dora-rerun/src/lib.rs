@@ -4,6 +4,7 @@ use std::{collections::HashMap, env::VarError};
 
 use dora_node_api::{
     arrow::array::{Array, Float32Array, Float64Array, StringArray, UInt8Array},
+    dora_core::config::{DataId, InputMapping, UserInputMapping},
     DoraNode, Event, Parameter,
 };
 use eyre::{eyre, Context, ContextCompat, Result};
@@ -13,8 +14,10 @@ use rerun::{
 };
 pub mod boxes2d;
 pub mod series;
+pub mod spatial;
 pub mod urdf;
 use series::update_series;
+use spatial::{update_points3d, update_pose, update_tensor};
 use urdf::{init_urdf, update_visualization};
 
 pub fn lib_main() -> Result<()> {
@@ -48,8 +51,23 @@ pub fn lib_main() -> Result<()> {
 
     let chains = init_urdf(&rec).context("Could not load urdf")?;
 
-    let (_node, mut events) =
-        DoraNode::init_from_env().context("Could not initialize dora node")?;
+    let (node, mut events) = DoraNode::init_from_env().context("Could not initialize dora node")?;
+
+    // log inputs at the `<node>/<output>` path of their source
+    let entity_paths: HashMap<DataId, String> = node
+        .topology()
+        .inputs()
+        .iter()
+        .map(|(id, input)| {
+            let path = match &input.mapping {
+                InputMapping::User(UserInputMapping { source, output }) => {
+                    format!("{source}/{output}")
+                }
+                InputMapping::Timer { .. } | InputMapping::Status => id.to_string(),
+            };
+            (id.clone(), path)
+        })
+        .collect();
 
     match std::env::var("README") {
         Ok(readme) => {
@@ -67,6 +85,10 @@ pub fn lib_main() -> Result<()> {
 
     while let Some(event) = events.recv() {
         if let Event::Input { id, data, metadata } = event {
+            let entity_path = entity_paths
+                .get(&id)
+                .map(String::as_str)
+                .unwrap_or(id.as_str());
             if id.as_str().contains("image") {
                 let height =
                     if let Some(Parameter::Integer(height)) = metadata.parameters.get("height") {
@@ -105,7 +127,7 @@ pub fn lib_main() -> Result<()> {
                         image_buffer,
                         ImageFormat::rgb8([*width as u32, *height as u32]),
                     );
-                    rec.log(id.as_str(), &image)
+                    rec.log(entity_path, &image)
                         .context("could not log image")?;
                 } else if encoding == "rgb8" {
                     let buffer: &UInt8Array = data.as_any().downcast_ref().unwrap();
@@ -119,14 +141,14 @@ pub fn lib_main() -> Result<()> {
                         image_buffer,
                         ImageFormat::rgb8([*width as u32, *height as u32]),
                     );
-                    rec.log(id.as_str(), &image)
+                    rec.log(entity_path, &image)
                         .context("could not log image")?;
                 } else if ["jpeg", "png"].contains(&encoding) {
                     let buffer: &UInt8Array = data.as_any().downcast_ref().unwrap();
                     let buffer: &[u8] = buffer.values();
 
                     let image = rerun::EncodedImage::from_file_contents(buffer.to_vec());
-                    rec.log(id.as_str(), &image)
+                    rec.log(entity_path, &image)
                         .context("could not log image")?;
                 };
             } else if id.as_str().contains("depth") {
@@ -167,24 +189,25 @@ pub fn lib_main() -> Result<()> {
                         .chunks(3)
                         .map(|x| rerun::Color::from_rgb(x[0], x[1], x[2]))
                         .collect::<Vec<_>>();
-                    rec.log(id.as_str(), &points_3d.with_colors(colors))
+                    rec.log(entity_path, &points_3d.with_colors(colors))
                         .context("could not log points")?;
                 } else {
-                    rec.log(id.as_str(), &points_3d)
+                    rec.log(entity_path, &points_3d)
                         .context("could not log points")?;
                 }
             } else if id.as_str().contains("text") {
                 let buffer: StringArray = data.to_data().into();
                 buffer.iter().try_for_each(|string| -> Result<()> {
                     if let Some(str) = string {
-                        rec.log(id.as_str(), &rerun::TextLog::new(str))
+                        rec.log(entity_path, &rerun::TextLog::new(str))
                             .wrap_err("Could not log text")
                     } else {
                         Ok(())
                     }
                 })?;
             } else if id.as_str().contains("boxes2d") {
-                boxes2d::update_boxes2d(&rec, id, data, metadata).context("update boxes 2d")?;
+                boxes2d::update_boxes2d(&rec, entity_path, data, metadata)
+                    .context("update boxes 2d")?;
             } else if id.as_str().contains("jointstate") {
                 let buffer: &Float32Array = data
                     .as_any()
@@ -213,7 +236,14 @@ pub fn lib_main() -> Result<()> {
                     println!("Could not find chain for {}", id);
                 }
             } else if id.as_str().contains("series") {
-                update_series(&rec, id, data).context("could not plot series")?;
+                update_series(&rec, entity_path, data).context("could not plot series")?;
+            } else if id.as_str().contains("pose") {
+                update_pose(&rec, entity_path, &data).context("could not log pose")?;
+            } else if id.as_str().contains("points3d") {
+                update_points3d(&rec, entity_path, &data).context("could not log points")?;
+            } else if id.as_str().contains("tensor") {
+                update_tensor(&rec, entity_path, &data, &metadata)
+                    .context("could not log tensor")?;
             } else {
                 println!("Could not find handler for {}", id);
             }
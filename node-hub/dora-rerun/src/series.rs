@@ -1,12 +1,11 @@
 use dora_node_api::{
     arrow::array::{Float32Array, Float64Array, Int32Array, Int64Array},
-    dora_core::config::DataId,
     ArrowData,
 };
 use eyre::{Context, ContextCompat, Result};
 use rerun::RecordingStream;
 
-pub fn update_series(rec: &RecordingStream, id: DataId, data: ArrowData) -> Result<()> {
+pub fn update_series(rec: &RecordingStream, entity_path: &str, data: ArrowData) -> Result<()> {
     match data.data_type() {
         dora_node_api::arrow::datatypes::DataType::Float32 => {
             let buffer: &Float32Array = data
@@ -16,7 +15,7 @@ pub fn update_series(rec: &RecordingStream, id: DataId, data: ArrowData) -> Resu
             let series: Vec<_> = buffer.values().to_vec();
             for (i, value) in series.iter().enumerate() {
                 rec.log(
-                    format!("{}_{}", id.as_str(), i),
+                    format!("{entity_path}_{i}"),
                     &rerun::Scalar::new(*value as f64),
                 )
                 .wrap_err("could not log series")?;
@@ -30,7 +29,7 @@ pub fn update_series(rec: &RecordingStream, id: DataId, data: ArrowData) -> Resu
             let series: Vec<_> = buffer.values().to_vec();
             for (i, value) in series.iter().enumerate() {
                 rec.log(
-                    format!("{}_{}", id.as_str(), i),
+                    format!("{entity_path}_{i}"),
                     &rerun::Scalar::new(*value as f64),
                 )
                 .wrap_err("could not log series")?;
@@ -44,7 +43,7 @@ pub fn update_series(rec: &RecordingStream, id: DataId, data: ArrowData) -> Resu
             let series: Vec<_> = buffer.values().to_vec();
             for (i, value) in series.iter().enumerate() {
                 rec.log(
-                    format!("{}_{}", id.as_str(), i),
+                    format!("{entity_path}_{i}"),
                     &rerun::Scalar::new(*value as f64),
                 )
                 .wrap_err("could not log series")?;
@@ -58,7 +57,7 @@ pub fn update_series(rec: &RecordingStream, id: DataId, data: ArrowData) -> Resu
             let series: Vec<_> = buffer.values().to_vec();
             for (i, value) in series.iter().enumerate() {
                 rec.log(
-                    format!("{}_{}", id.as_str(), i),
+                    format!("{entity_path}_{i}"),
                     &rerun::Scalar::new(*value as f64),
                 )
                 .wrap_err("could not log series")?;
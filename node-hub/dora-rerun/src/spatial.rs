@@ -0,0 +1,62 @@
+use dora_node_api::{
+    arrow::{array::AsArray, compute::cast, datatypes::DataType, datatypes::Float32Type},
+    ArrowData, Metadata, Parameter,
+};
+use eyre::{bail, Context, Result};
+use rerun::RecordingStream;
+
+/// Casts numeric data to `f32` values.
+fn float_values(data: &ArrowData) -> Result<Vec<f32>> {
+    let values = cast(&data.0, &DataType::Float32).context("data is not numeric")?;
+    Ok(values.as_primitive::<Float32Type>().values().to_vec())
+}
+
+/// Logs a pose given as `[x, y, z]` or `[x, y, z, qx, qy, qz, qw]`.
+pub fn update_pose(rec: &RecordingStream, entity_path: &str, data: &ArrowData) -> Result<()> {
+    let values = float_values(data)?;
+    let transform = match values[..] {
+        [x, y, z] => rerun::Transform3D::from_translation([x, y, z]),
+        [x, y, z, qx, qy, qz, qw] => rerun::Transform3D::from_translation_rotation(
+            [x, y, z],
+            rerun::Quaternion::from_xyzw([qx, qy, qz, qw]),
+        ),
+        _ => bail!(
+            "pose must consist of `[x, y, z]` or `[x, y, z, qx, qy, qz, qw]`, got {} values",
+            values.len()
+        ),
+    };
+    rec.log(entity_path, &transform)
+        .wrap_err("could not log pose")
+}
+
+/// Logs a point cloud given as flat list of `[x, y, z]` coordinates.
+pub fn update_points3d(rec: &RecordingStream, entity_path: &str, data: &ArrowData) -> Result<()> {
+    let values = float_values(data)?;
+    if values.len() % 3 != 0 {
+        bail!("point cloud length {} is not a multiple of 3", values.len());
+    }
+    let points = values.chunks_exact(3).map(|p| [p[0], p[1], p[2]]);
+    rec.log(entity_path, &rerun::Points3D::new(points))
+        .wrap_err("could not log points")
+}
+
+/// Logs a tensor, with the dimensions given by the `shape` metadata parameter.
+///
+/// Tensors without `shape` are logged as one-dimensional tensors.
+pub fn update_tensor(
+    rec: &RecordingStream,
+    entity_path: &str,
+    data: &ArrowData,
+    metadata: &Metadata,
+) -> Result<()> {
+    let values = float_values(data)?;
+    let shape = match metadata.parameters.get("shape") {
+        Some(Parameter::ListInt(shape)) => shape.iter().map(|&dim| dim as usize).collect(),
+        _ => vec![values.len()],
+    };
+    let array = rerun::external::ndarray::ArrayD::from_shape_vec(shape, values)
+        .context("tensor shape doesn't match the data length")?;
+    let tensor = rerun::Tensor::try_from(array).context("could not convert tensor")?;
+    rec.log(entity_path, &tensor)
+        .wrap_err("could not log tensor")
+}
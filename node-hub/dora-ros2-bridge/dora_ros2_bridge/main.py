@@ -0,0 +1,149 @@
+"""Bridge between ROS2 topics and the inputs and outputs of a dora dataflow.
+
+The topics are configured in a YAML file, see the README for its format.
+"""
+
+import argparse
+import os
+
+import pyarrow as pa
+import yaml
+from dora import (
+    Node,
+    Ros2Context,
+    Ros2Durability,
+    Ros2NodeOptions,
+    Ros2QosPolicies,
+)
+
+DEFAULT_POLL_INTERVAL = 0.01
+
+DURABILITIES = {
+    "volatile": Ros2Durability.Volatile,
+    "transient_local": Ros2Durability.TransientLocal,
+    "transient": Ros2Durability.Transient,
+    "persistent": Ros2Durability.Persistent,
+}
+QOS_KEYS = {"reliable", "keep_all", "keep_last", "lease_duration", "max_blocking_time"}
+
+
+def load_config(path):
+    with open(path, encoding="utf-8") as f:
+        config = yaml.safe_load(f) or {}
+
+    topics = config.get("topics") or []
+    for topic in topics:
+        for key in ("topic", "type"):
+            if key not in topic:
+                raise ValueError(f"topic entry is missing the `{key}` key: {topic}")
+        if ("input" in topic) == ("output" in topic):
+            raise ValueError(
+                f"topic `{topic['topic']}` must have either an `input` or an `output` key",
+            )
+    return config
+
+
+def qos_policies(qos):
+    qos = dict(qos or {})
+    kwargs = {key: qos.pop(key) for key in QOS_KEYS if key in qos}
+    if "durability" in qos:
+        durability = qos.pop("durability")
+        if durability not in DURABILITIES:
+            raise ValueError(
+                f"unknown durability `{durability}`, expected one of {list(DURABILITIES)}",
+            )
+        kwargs["durability"] = DURABILITIES[durability]
+    if qos:
+        raise ValueError(f"unknown QoS options: {list(qos)}")
+    return Ros2QosPolicies(**kwargs)
+
+
+def rename_fields(value, fields):
+    """Rename the fields of a struct array according to the given mapping."""
+    if not fields or not isinstance(value.type, pa.StructType):
+        return value
+    names = [fields.get(field.name, field.name) for field in value.type]
+    arrays = [value.field(i) for i in range(value.type.num_fields)]
+    return pa.StructArray.from_arrays(arrays, names=names)
+
+
+def main():
+    # Handle dynamic nodes, ask for the name of the node in the dataflow, and the same values as the ENV variables.
+    parser = argparse.ArgumentParser(description="Bridge between ROS2 and dora")
+
+    parser.add_argument(
+        "--name",
+        type=str,
+        required=False,
+        help="The name of the node in the dataflow.",
+        default="ros2-bridge",
+    )
+    parser.add_argument(
+        "--config",
+        type=str,
+        required=False,
+        help="Path of the YAML file that maps ROS2 topics to inputs and outputs.",
+        default=os.getenv("CONFIG"),
+    )
+    args = parser.parse_args()
+
+    node = Node(
+        args.name,
+    )  # provide the name to connect to the dataflow if dynamic node
+
+    if args.config is None:
+        raise ValueError("no bridge configuration given, set the `CONFIG` env variable")
+    config = load_config(args.config)
+    poll_interval = float(os.getenv("POLL_INTERVAL", DEFAULT_POLL_INTERVAL))
+
+    ros2_config = config.get("ros2_node") or {}
+    ros2_context = Ros2Context()
+    ros2_node = ros2_context.new_node(
+        ros2_config.get("name", "dora_ros2_bridge"),
+        ros2_config.get("namespace", "/dora"),
+        Ros2NodeOptions(rosout=ros2_config.get("rosout", False)),
+    )
+
+    # ROS2 publishers by dora input ID
+    publishers = {}
+    # (dora output ID, ROS2 subscription, field mapping)
+    subscriptions = []
+    for entry in config.get("topics") or []:
+        topic = ros2_node.create_topic(
+            entry["topic"],
+            entry["type"],
+            qos_policies(entry.get("qos")),
+        )
+        fields = entry.get("fields") or {}
+        if "input" in entry:
+            publishers[entry["input"]] = (ros2_node.create_publisher(topic), fields)
+        else:
+            # the mapping is given from dora to ROS2 names
+            reversed_fields = {ros: dora for dora, ros in fields.items()}
+            subscriptions.append(
+                (entry["output"], ros2_node.create_subscription(topic), reversed_fields),
+            )
+
+    while True:
+        event = node.next(timeout=poll_interval)
+        if event is None:
+            break
+        if event["type"] == "INPUT":
+            if event["id"] in publishers:
+                publisher, fields = publishers[event["id"]]
+                publisher.publish(rename_fields(event["value"], fields))
+            else:
+                print(f"ignoring input `{event['id']}` that is not mapped to a topic")
+        elif event["type"] == "STOP":
+            break
+
+        for output, subscription, fields in subscriptions:
+            while True:
+                value = subscription.next()
+                if value is None:
+                    break
+                node.send_output(output, rename_fields(value, fields))
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,100 @@
+//! The `rust-dataflow` example, run through the `dora-test` harness.
+
+use dora_test::{OutputsClosed, TestDataflow};
+use eyre::{bail, Context};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+const NODES: &[&str] = &[
+    "rust-dataflow-example-node",
+    "rust-dataflow-example-status-node",
+    "rust-dataflow-example-sink",
+];
+
+#[test]
+fn rust_dataflow() -> eyre::Result<()> {
+    let target_dir = build_nodes()?;
+    let path = |package: &str| {
+        target_dir
+            .join(format!("{package}{}", std::env::consts::EXE_SUFFIX))
+            .display()
+            .to_string()
+    };
+    let yaml = format!(
+        r#"
+nodes:
+  - id: rust-node
+    path: '{node}'
+    inputs:
+      tick: dora/timer/millis/10
+    outputs:
+      - random
+  - id: rust-status-node
+    path: '{status_node}'
+    inputs:
+      tick: dora/timer/millis/100
+      random:
+        source: rust-node/random
+        queue_policy: lossless
+    outputs:
+      - status
+  - id: rust-sink
+    path: '{sink}'
+    inputs:
+      message: rust-status-node/status
+"#,
+        node = path(NODES[0]),
+        status_node = path(NODES[1]),
+        sink = path(NODES[2]),
+    );
+
+    let mut dataflow = TestDataflow::from_yaml(&yaml)?
+        .capture("rust-status-node/status")?
+        .start()?;
+
+    // the node sends 100 random values, which the status node receives
+    // without drops
+    let mut statuses = 0;
+    loop {
+        let output = match dataflow.recv_from("rust-status-node/status", Duration::from_secs(10)) {
+            Ok(output) => output,
+            Err(err) if err.is::<OutputsClosed>() => break,
+            Err(err) => return Err(err),
+        };
+        let status: &str = TryFrom::try_from(&output.data).context("expected string status")?;
+        assert!(
+            status.starts_with("operator received random value ") && status.ends_with(" ticks"),
+            "unexpected status `{status}`"
+        );
+        statuses += 1;
+    }
+    assert_eq!(statuses, 100);
+
+    let result = dataflow.wait(Duration::from_secs(30))?;
+    result.assert_success();
+    assert_eq!(result.node_results.len(), NODES.len());
+    Ok(())
+}
+
+/// Builds the nodes of the dataflow and returns the directory of their
+/// executables.
+fn build_nodes() -> eyre::Result<PathBuf> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let cargo = std::env::var("CARGO").wrap_err("`CARGO` is not set")?;
+    let mut cmd = std::process::Command::new(cargo);
+    cmd.arg("build").current_dir(root);
+    for package in NODES {
+        cmd.arg("--package").arg(package);
+    }
+    if !cmd.status()?.success() {
+        bail!("failed to build dataflow nodes");
+    }
+
+    let target_dir = match std::env::var_os("CARGO_TARGET_DIR") {
+        Some(dir) => root.join(dir),
+        None => root.join("target"),
+    };
+    Ok(target_dir.join("debug"))
+}